@@ -3,36 +3,297 @@
 //! A blockchain must identify the block chain (or equivalent), e.g. with the genesis hash, and the
 //! asset, e.g. for Etherum blockchain assets can be eth or dai.
 
+use std::convert::TryFrom;
 use std::error;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::io;
 use std::ops::Range;
 use std::str::FromStr;
+use std::time::Duration;
 
 use thiserror::Error;
 
-use crate::consensus::{self, deserialize, serialize, CanonicalBytes, Decodable, Encodable};
-use crate::crypto::{Keys, Signatures};
-use crate::transaction::{Buyable, Cancelable, Fundable, Lockable, Punishable, Refundable};
+use crate::consensus::{
+    self, deserialize, serialize, CanonicalBytes, Decodable, Encodable, UnknownCode,
+};
+use crate::crypto::{self, Keys, Signatures};
+use crate::transaction::{
+    Buyable, Cancelable, Fundable, Lockable, Punishable, Refundable, Sweepable, TxLabel,
+};
 
 /// Defines the type for a blockchain address, this type is used when manipulating transactions.
 pub trait Address {
     /// Defines the address format for the arbitrating blockchain.
-    type Address: Clone + Debug + CanonicalBytes;
+    type Address: Clone + Debug + CanonicalBytes + Send + Sync;
+}
+
+/// Classifies blockchain addresses by the type of script they lock funds to, this is used to
+/// validate addresses provided by a counter-party before sending funds to them.
+pub trait AddressScript: Address {
+    /// Defines the possible address types, e.g. P2PKH or P2WSH for Bitcoin.
+    type AddressType: Copy + Debug + PartialEq + Eq + Send + Sync;
+
+    /// Return the type of the address or `None` if the address does not lock funds to a known
+    /// script type.
+    fn address_type(address: &Self::Address) -> Option<Self::AddressType>;
+
+    /// Return the standard address types allowed when nothing else is negotiated.
+    fn standard_address_types() -> Vec<Self::AddressType>;
+
+    /// Return true if the address can be used on the network, e.g. a testnet address in a swap on
+    /// [`Network::Testnet`].
+    fn is_valid_for_network(address: &Self::Address, network: Network) -> bool;
+
+    /// Validate that the address can be used on the network.
+    fn validate_network(address: &Self::Address, network: Network) -> Result<(), AddressError> {
+        match Self::is_valid_for_network(address, network) {
+            true => Ok(()),
+            false => Err(AddressError::WrongNetwork(network)),
+        }
+    }
+}
+
+/// An allowlist of address types accepted for the destination and refund addresses revealed by a
+/// counter-party. The default allowlist contains the standard address types of the blockchain,
+/// other types can be added when negotiated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressAllowlist<T>
+where
+    T: AddressScript,
+{
+    allowed: Vec<T::AddressType>,
+}
+
+impl<T> AddressAllowlist<T>
+where
+    T: AddressScript,
+{
+    /// Create an allowlist accepting only the given address types.
+    pub fn new(allowed: Vec<T::AddressType>) -> Self {
+        Self { allowed }
+    }
+
+    /// Add an address type to the allowlist.
+    pub fn allow(mut self, address_type: T::AddressType) -> Self {
+        if !self.allowed.contains(&address_type) {
+            self.allowed.push(address_type);
+        }
+        self
+    }
+
+    /// Return the list of allowed address types.
+    pub fn allowed(&self) -> &[T::AddressType] {
+        &self.allowed
+    }
+
+    /// Validate that the address is of a known and allowed type.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn validate(&self, address: &T::Address) -> Result<(), AddressError> {
+        let address_type = T::address_type(address).ok_or(AddressError::UnknownAddressType)?;
+        if self.allowed.contains(&address_type) {
+            Ok(())
+        } else {
+            Err(AddressError::AddressTypeNotAllowed)
+        }
+    }
+}
+
+impl<T> Default for AddressAllowlist<T>
+where
+    T: AddressScript,
+{
+    fn default() -> Self {
+        Self::new(T::standard_address_types())
+    }
+}
+
+/// Define the type of errors an address validation can encounter.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AddressError {
+    /// The address does not lock funds to a known script type.
+    #[error("Unknown or non-standard address type")]
+    UnknownAddressType,
+    /// The address type is known but not part of the allowlist.
+    #[error("Address type not allowed")]
+    AddressTypeNotAllowed,
+    /// The address cannot be used on the network of the swap.
+    #[error("Address is not valid on network {0:?}")]
+    WrongNetwork(Network),
 }
 
 /// Defines the type for a blockchain timelock, this type is used when manipulating transactions
 /// and is carried in the [Offer](crate::negotiation::Offer) to fix the two timelocks.
 pub trait Timelock {
-    /// Defines the type of timelock used for the arbitrating transactions.
-    type Timelock: Copy + Debug + CanonicalBytes + PartialEq + Eq;
+    /// Defines the type of timelock used for the arbitrating transactions. Timelocks are ordered
+    /// by their expiry, timelocks of different kinds, e.g. block height and time based, are not
+    /// comparable.
+    type Timelock: Copy + Debug + CanonicalBytes + PartialEq + Eq + PartialOrd + Send + Sync;
+}
+
+/// Defines the expected time between two blocks of a blockchain, used to convert block heights into
+/// approximate wall-clock durations, e.g. for progress reporting and offer display.
+pub trait BlockTime {
+    /// The targeted average time between two blocks.
+    const BLOCK_TIME: Duration;
+
+    /// Return the expected duration for the given number of blocks to be mined.
+    fn blocks_to_duration(blocks: u32) -> Duration {
+        Self::BLOCK_TIME * blocks
+    }
+
+    /// Return the number of blocks expected to be mined during the given duration, rounded up.
+    fn duration_to_blocks(duration: Duration) -> u32 {
+        let block_time = Self::BLOCK_TIME.as_secs().max(1);
+        let blocks = duration.as_secs().div_ceil(block_time);
+        blocks.min(u32::MAX as u64) as u32
+    }
+}
+
+/// A duration displayed in a human readable and approximated format, e.g. `~5 hours`, intended to
+/// be shown to users in place of block counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApproxDuration(pub Duration);
+
+impl From<Duration> for ApproxDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl std::fmt::Display for ApproxDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0.as_secs();
+        let (value, unit) = match secs {
+            s if s >= 86400 => ((s + 43200) / 86400, "day"),
+            s if s >= 3600 => ((s + 1800) / 3600, "hour"),
+            s if s >= 60 => ((s + 30) / 60, "minute"),
+            s => (s, "second"),
+        };
+        let plural = if value == 1 { "" } else { "s" };
+        write!(f, "~{} {}{}", value, unit, plural)
+    }
+}
+
+/// Asset amounts counted in the smallest unit of the asset, e.g. satoshis or piconeros. Values are
+/// carried on 128 bits: amounts of the assets with 18 decimals, e.g. tokens, exceed 64 bits for a
+/// few units of the asset. Conversions and arithmetic are checked, amounts never wrap.
+pub trait AtomicAmount: Copy {
+    /// Return the value of the amount in the smallest unit of the asset.
+    fn to_atomic(&self) -> u128;
+
+    /// Create an amount from its value in the smallest unit of the asset, fails if the value does
+    /// not fit in the amount type.
+    fn from_atomic(value: u128) -> Result<Self, AmountError>;
+
+    /// Add two amounts, fails on overflow.
+    fn try_add(&self, other: &Self) -> Result<Self, AmountError> {
+        self.to_atomic()
+            .checked_add(other.to_atomic())
+            .ok_or(AmountError::Overflow)
+            .and_then(Self::from_atomic)
+    }
+
+    /// Subtract an amount, fails if the other amount is greater.
+    fn try_sub(&self, other: &Self) -> Result<Self, AmountError> {
+        self.to_atomic()
+            .checked_sub(other.to_atomic())
+            .ok_or(AmountError::Underflow)
+            .and_then(Self::from_atomic)
+    }
+
+    /// Sum amounts, fails on overflow. The sum of no amount is zero.
+    fn try_sum<I>(amounts: I) -> Result<Self, AmountError>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(Self::from_atomic(0)?, |total, amount| {
+                total.try_add(&amount)
+            })
+    }
+}
+
+/// Define the type of errors of checked operations on amounts.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AmountError {
+    /// The amount does not fit in the amount type.
+    #[error("Amount overflow")]
+    Overflow,
+    /// The amount is negative.
+    #[error("Amount underflow")]
+    Underflow,
+}
+
+impl AtomicAmount for u64 {
+    fn to_atomic(&self) -> u128 {
+        u128::from(*self)
+    }
+
+    fn from_atomic(value: u128) -> Result<Self, AmountError> {
+        u64::try_from(value).map_err(|_| AmountError::Overflow)
+    }
+}
+
+impl AtomicAmount for u128 {
+    fn to_atomic(&self) -> u128 {
+        *self
+    }
+
+    fn from_atomic(value: u128) -> Result<Self, AmountError> {
+        Ok(value)
+    }
+}
+
+/// Parse and display asset amounts with a unit suffix, e.g. `0.5 BTC`, `100000 sat`, `2.5 XMR`, or
+/// `2500000000000 pico`. Units are case insensitive and separated from the value by a whitespace.
+#[cfg(feature = "parse-amounts")]
+pub trait UnitAmount: Sized {
+    /// Parse an amount followed by its unit.
+    fn parse_with_unit(s: &str) -> Result<Self, AmountParseError>;
+
+    /// Format the amount in the main unit of the asset followed by the unit.
+    fn to_string_with_unit(&self) -> String;
+}
+
+/// Split an amount with a unit suffix into its value and lowercase unit.
+#[cfg(feature = "parse-amounts")]
+pub fn split_amount_unit(s: &str) -> Result<(&str, String), AmountParseError> {
+    let mut parts = s.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(value), Some(unit), None) => Ok((value, unit.to_lowercase())),
+        (Some(_), None, None) => Err(AmountParseError::MissingUnit),
+        _ => Err(AmountParseError::InvalidAmount(s.to_string())),
+    }
+}
+
+/// Define the type of errors when parsing an amount with a unit suffix.
+#[cfg(feature = "parse-amounts")]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AmountParseError {
+    /// The amount is not followed by a unit.
+    #[error("Missing amount unit")]
+    MissingUnit,
+    /// The unit is not a unit of the asset.
+    #[error("Unknown amount unit: {0}")]
+    UnknownUnit(String),
+    /// The value is not a valid amount in the given unit.
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
 }
 
 /// Defines the asset identifier for a blockchain and its associated asset unit type, it is carried
 /// in the [Offer](crate::negotiation::Offer) to fix exchanged amounts.
 pub trait Asset: Copy + Debug {
-    /// Type for the traded asset unit for a blockchain.
-    type AssetUnit: Copy + Eq + Debug + CanonicalBytes;
+    /// Type for the traded asset unit for a blockchain, amounts in offers are converted and
+    /// summed with the checked operations of [`AtomicAmount`].
+    type AssetUnit: Copy + Eq + PartialOrd + Debug + CanonicalBytes + AtomicAmount + Send + Sync;
 
     /// Parse an 32 bits identifier as defined in [SLIP
     /// 44](https://github.com/satoshilabs/slips/blob/master/slip-0044.md#slip-0044--registered-coin-types-for-bip-0044)
@@ -42,38 +303,163 @@ pub trait Asset: Copy + Debug {
     /// Return the 32 bits identifier for the blockchain as defined in [SLIP
     /// 44](https://github.com/satoshilabs/slips/blob/master/slip-0044.md#slip-0044--registered-coin-types-for-bip-0044).
     fn to_u32(&self) -> u32;
+
+    /// Return the asset identifier, by default the native asset of the blockchain identified by
+    /// [`Asset::to_u32`]. Token assets must return their composite identifier.
+    fn asset_id(&self) -> AssetId {
+        AssetId::native(self.to_u32())
+    }
+
+    /// Parse an asset identifier and return the asset if existant, by default only native assets
+    /// are parsed with [`Asset::from_u32`].
+    fn from_asset_id(id: &AssetId) -> Option<Self> {
+        match id.token {
+            Some(_) => None,
+            None => Self::from_u32(id.blockchain),
+        }
+    }
+}
+
+/// Marker encoded in place of the SLIP 44 identifier to signal a composite token identifier.
+pub const TOKEN_ASSET_MARKER: u32 = 0xffff_ffff;
+
+/// Identifies an asset, either the native asset of a blockchain or a token living on a
+/// blockchain, e.g. an ERC-20 token on Ethereum.
+///
+/// Native assets are encoded as their 32 bits SLIP 44 identifier. Tokens are encoded as the
+/// [`TOKEN_ASSET_MARKER`], followed by the SLIP 44 identifier of the blockchain and the 32 bytes
+/// hash of the token contract.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct AssetId {
+    /// The SLIP 44 identifier of the blockchain.
+    pub blockchain: u32,
+    /// The hash of the token contract, if the asset is a token.
+    pub token: Option<[u8; 32]>,
+}
+
+impl AssetId {
+    /// Create the identifier of a blockchain native asset.
+    pub fn native(blockchain: u32) -> Self {
+        Self {
+            blockchain,
+            token: None,
+        }
+    }
+
+    /// Create the identifier of a token on a blockchain.
+    pub fn token(blockchain: u32, contract_hash: [u8; 32]) -> Self {
+        Self {
+            blockchain,
+            token: Some(contract_hash),
+        }
+    }
+
+    /// Return true if the asset is a token and not the native asset of the blockchain.
+    pub fn is_token(&self) -> bool {
+        self.token.is_some()
+    }
 }
 
+impl Encodable for AssetId {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self.token {
+            None => self.blockchain.consensus_encode(s),
+            Some(contract_hash) => {
+                let len = TOKEN_ASSET_MARKER.consensus_encode(s)?;
+                Ok(len
+                    + self.blockchain.consensus_encode(s)?
+                    + contract_hash.consensus_encode(s)?)
+            }
+        }
+    }
+}
+
+impl Decodable for AssetId {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            TOKEN_ASSET_MARKER => Ok(Self::token(
+                Decodable::consensus_decode(d)?,
+                Decodable::consensus_decode(d)?,
+            )),
+            blockchain => Ok(Self::native(blockchain)),
+        }
+    }
+}
+
+impl_strict_encoding!(AssetId);
+
 /// Defines the types a blockchain needs to interact onchain, i.e. the transaction types.
 pub trait Onchain {
     /// Defines the transaction format used to transfer partial transaction between participant for
     /// the arbitrating blockchain
-    type PartialTransaction: Clone + Debug + CanonicalBytes;
+    type PartialTransaction: Clone + Debug + CanonicalBytes + Send + Sync;
 
     /// Defines the finalized transaction format for the arbitrating blockchain
-    type Transaction: Clone + Debug + CanonicalBytes;
+    type Transaction: Clone + Debug + CanonicalBytes + Send + Sync;
+}
+
+/// A finalized transaction serializable in the raw format expected by its blockchain network, e.g.
+/// the consensus serialization for Bitcoin or the transaction blob for Monero. Broadcast adapters
+/// use the raw bytes and the transaction identifier without knowing the transaction type.
+pub trait RawTransaction {
+    /// Serialize the transaction in the raw format expected by the network.
+    fn to_raw_bytes(&self) -> Vec<u8>;
+
+    /// Compute the identifier of the transaction as used on the network.
+    fn txid(&self) -> Vec<u8>;
+}
+
+/// Defines a proof that a transaction pays an address, verifiable by a counter-party without
+/// scanning the blockchain with its own wallet, e.g. the transaction private key for Monero.
+pub trait PaymentProof: Asset + Address {
+    /// The secret known by the sender of the transaction used to generate the proof.
+    type PaymentSecret: Send + Sync;
+
+    /// The proof format sent to the counter-party.
+    type PaymentProof: Clone + Debug + CanonicalBytes + Send + Sync;
+
+    /// The transaction format the proof is verified against.
+    type ProvedTransaction: RawTransaction;
+
+    /// Generate a proof that the transaction pays the address, fails if the secret does not
+    /// correspond to the transaction or if the transaction does not pay the address.
+    fn prove_payment(
+        tx: &Self::ProvedTransaction,
+        address: &Self::Address,
+        secret: &Self::PaymentSecret,
+    ) -> Result<Self::PaymentProof, crypto::Error>;
+
+    /// Verify the proof and return the total amount paid to the address by the transaction.
+    fn verify_payment(
+        tx: &Self::ProvedTransaction,
+        address: &Self::Address,
+        proof: &Self::PaymentProof,
+    ) -> Result<Self::AssetUnit, crypto::Error>;
 }
 
 /// Fix the types for all arbitrating transactions needed for the swap: [Fundable], [Lockable],
-/// [Buyable], [Cancelable], [Refundable], and [Punishable] transactions.
+/// [Buyable], [Cancelable], [Refundable], and [Punishable] transactions, and the [Sweepable]
+/// transaction used to recover a mismatching funding.
 pub trait Transactions: Timelock + Address + Fee + Keys + Signatures + Sized {
     /// The returned type of the consumable output and the `base_on` transaction method, used to
     /// reference the funds and chain other transactions on it. This must contain all necessary
     /// data to latter create a valid unlocking witness for the output and identify the funds.
-    type Metadata: Clone + Eq + Debug;
+    type Metadata: Clone + Eq + Debug + Send + Sync;
 
     /// Defines the type for the `funding (a)` transaction
-    type Funding: Fundable<Self, Self::Metadata>;
+    type Funding: Fundable<Self, Self::Metadata> + Send + Sync;
     /// Defines the type for the `lock (b)` transaction
-    type Lock: Lockable<Self, Self::Metadata>;
+    type Lock: Lockable<Self, Self::Metadata> + Send + Sync;
     /// Defines the type for the `buy (c)` transaction
-    type Buy: Buyable<Self, Self::Metadata>;
+    type Buy: Buyable<Self, Self::Metadata> + Send + Sync;
     /// Defines the type for the `cancel (d)` transaction
-    type Cancel: Cancelable<Self, Self::Metadata>;
+    type Cancel: Cancelable<Self, Self::Metadata> + Send + Sync;
     /// Defines the type for the `refund (e)` transaction
-    type Refund: Refundable<Self, Self::Metadata>;
+    type Refund: Refundable<Self, Self::Metadata> + Send + Sync;
     /// Defines the type for the `punish (f)` transaction
-    type Punish: Punishable<Self, Self::Metadata>;
+    type Punish: Punishable<Self, Self::Metadata> + Send + Sync;
+    /// Defines the type for the sweep transaction recovering a mismatching `funding (a)`
+    type Sweep: Sweepable<Self, Self::Metadata> + Send + Sync;
 }
 
 impl<T> FromStr for FeeStrategy<T>
@@ -96,7 +482,7 @@ where
 ///
 /// A fee strategy is included in an offer, so Alice and Bob can verify that transactions are valid
 /// upon reception by the other participant.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum FeeStrategy<T>
 where
     T: Clone + PartialOrd + PartialEq + CanonicalBytes,
@@ -105,6 +491,102 @@ where
     Fixed(T),
     /// A range with a minimum and maximum (inclusive) possible fees
     Range(Range<T>),
+    /// A fixed or range strategy with a hint on the urgency of the transactions, the target is
+    /// advisory and only used when estimating the fee, see [`FeeStrategy::estimate_with`]. A
+    /// targeted strategy cannot be nested.
+    Targeted(Box<FeeStrategy<T>>, ConfirmationTarget),
+}
+
+impl<T> FeeStrategy<T>
+where
+    T: Clone + PartialOrd + PartialEq + CanonicalBytes,
+{
+    /// Add a confirmation target hint to the strategy, replacing any previous target.
+    pub fn with_confirmation_target(self, target: ConfirmationTarget) -> Self {
+        FeeStrategy::Targeted(Box::new(self.base().clone()), target)
+    }
+
+    /// Return the confirmation target hint of the strategy, if any.
+    pub fn confirmation_target(&self) -> Option<ConfirmationTarget> {
+        match self {
+            FeeStrategy::Targeted(_, target) => Some(*target),
+            _ => None,
+        }
+    }
+
+    /// Return the fixed or range strategy, without the confirmation target hint.
+    pub fn base(&self) -> &FeeStrategy<T> {
+        match self {
+            FeeStrategy::Targeted(strategy, _) => strategy.base(),
+            strategy => strategy,
+        }
+    }
+
+    /// Return the fee to set on the transactions given the estimation for the confirmation target
+    /// of the strategy. A fixed strategy ignores the estimator and an estimation outside of a
+    /// range strategy is bounded to the closest allowed fee.
+    pub fn estimate_with(&self, estimator: &impl FeeEstimator<T>) -> Result<T, FeeStrategyError> {
+        match self.base() {
+            FeeStrategy::Fixed(fee) => Ok(fee.clone()),
+            FeeStrategy::Range(range) => {
+                let fee = estimator.estimate_fee(self.confirmation_target())?;
+                Ok(match fee {
+                    fee if fee < range.start => range.start.clone(),
+                    fee if fee > range.end => range.end.clone(),
+                    fee => fee,
+                })
+            }
+            FeeStrategy::Targeted(..) => unreachable!("base strategy is never targeted"),
+        }
+    }
+}
+
+/// The number of blocks within which a transaction is expected to be mined, e.g. within 6 blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConfirmationTarget(u16);
+
+impl ConfirmationTarget {
+    /// Create a target of the given number of blocks, `None` if null.
+    pub fn new(blocks: u16) -> Option<Self> {
+        match blocks {
+            0 => None,
+            blocks => Some(Self(blocks)),
+        }
+    }
+
+    /// Return the number of blocks of the target.
+    pub fn blocks(&self) -> u16 {
+        self.0
+    }
+}
+
+impl fmt::Display for ConfirmationTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "within {} blocks", self.0)
+    }
+}
+
+impl Encodable for ConfirmationTarget {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        self.0.consensus_encode(writer)
+    }
+}
+
+impl Decodable for ConfirmationTarget {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Self::new(Decodable::consensus_decode(d)?)
+            .ok_or(consensus::Error::ParseFailed("Null confirmation target"))
+    }
+}
+
+impl_strict_encoding!(ConfirmationTarget);
+
+/// Estimates the fee needed for a transaction to be mined within a confirmation target, e.g.
+/// backed by a node or a fee estimation service. Used to pick a fee within a range strategy.
+pub trait FeeEstimator<T> {
+    /// Estimate the fee for the given target, or for the default target of the estimator if
+    /// the strategy has no confirmation target.
+    fn estimate_fee(&self, target: Option<ConfirmationTarget>) -> Result<T, FeeStrategyError>;
 }
 
 impl<T> Encodable for FeeStrategy<T>
@@ -122,7 +604,30 @@ where
                 len += start.as_canonical_bytes().consensus_encode(writer)?;
                 Ok(len + end.as_canonical_bytes().consensus_encode(writer)?)
             }
+            FeeStrategy::Targeted(strategy, target) => {
+                let mut len = 0x03u8.consensus_encode(writer)?;
+                len += target.consensus_encode(writer)?;
+                Ok(len + strategy.base().consensus_encode(writer)?)
+            }
+        }
+    }
+}
+
+fn decode_base_fee_strategy<T, D>(tag: u8, d: &mut D) -> Result<FeeStrategy<T>, consensus::Error>
+where
+    T: Clone + PartialOrd + PartialEq + CanonicalBytes,
+    D: io::Read,
+{
+    match tag {
+        0x01u8 => Ok(FeeStrategy::Fixed(T::from_canonical_bytes(
+            unwrap_vec_ref!(d).as_ref(),
+        )?)),
+        0x02u8 => {
+            let start = T::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
+            let end = T::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
+            Ok(FeeStrategy::Range(Range { start, end }))
         }
+        _ => Err(consensus::Error::UnknownType),
     }
 }
 
@@ -132,15 +637,13 @@ where
 {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         match Decodable::consensus_decode(d)? {
-            0x01u8 => Ok(FeeStrategy::Fixed(T::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?)),
-            0x02u8 => {
-                let start = T::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
-                let end = T::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
-                Ok(FeeStrategy::Range(Range { start, end }))
+            0x03u8 => {
+                let target = Decodable::consensus_decode(d)?;
+                // Targeted strategies cannot be nested, the inner strategy is fixed or range
+                let strategy = decode_base_fee_strategy(Decodable::consensus_decode(d)?, d)?;
+                Ok(FeeStrategy::Targeted(Box::new(strategy), target))
             }
-            _ => Err(consensus::Error::UnknownType),
+            tag => decode_base_fee_strategy(tag, d),
         }
     }
 }
@@ -169,6 +672,7 @@ impl_strict_encoding!(
 /// Define the type of errors a fee strategy can encounter during calculation, application, and
 /// validation of fees on a partial transaction.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum FeeStrategyError {
     /// Missing metadata on inputs to retreive the amount of asset available.
     #[error("Missing metadata inputs to retreive available amount")]
@@ -182,6 +686,9 @@ pub enum FeeStrategyError {
     /// Not enough assets to cover the fees.
     #[error("Not enough assets to cover the fees")]
     NotEnoughAssets,
+    /// The fee politic is unknown to this implementation.
+    #[error("Unsupported fee politic {0}")]
+    UnsupportedPolitic(FeePolitic),
     /// Any fee strategy error not part of this list.
     #[error("Other: {0}")]
     Other(Box<dyn error::Error + Sync + Send>),
@@ -212,12 +719,57 @@ impl FeeStrategyError {
 }
 
 /// Defines how to set the fee when a strategy allows multiple possibilities.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum FeePolitic {
     /// Set the fee at the minimum allowed by the strategy
     Aggressive,
     /// Set the fee at the maximum allowed by the strategy
     Conservative,
+    /// A politic code unknown to this implementation, preserved when re-encoded.
+    Other(UnknownCode),
+}
+
+impl Encodable for FeePolitic {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            FeePolitic::Aggressive => 0x01u16.consensus_encode(writer),
+            FeePolitic::Conservative => 0x02u16.consensus_encode(writer),
+            FeePolitic::Other(code) => code.consensus_encode(writer),
+        }
+    }
+}
+
+impl Decodable for FeePolitic {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u16 => Ok(FeePolitic::Aggressive),
+            0x02u16 => Ok(FeePolitic::Conservative),
+            code => Ok(FeePolitic::Other(UnknownCode::new(code))),
+        }
+    }
+}
+
+impl_strict_encoding!(FeePolitic);
+
+impl fmt::Display for FeePolitic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeePolitic::Aggressive => write!(f, "Aggressive"),
+            FeePolitic::Conservative => write!(f, "Conservative"),
+            FeePolitic::Other(code) => write!(f, "Other({})", code),
+        }
+    }
+}
+
+/// The result of applying a fee strategy on a transaction.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AppliedFee<A, F> {
+    /// The amount of fee paid, in the fee currency.
+    pub fee: F,
+    /// The amount deducted from the traded asset, equal to the fee when fees are paid in the
+    /// traded asset and null when fees are paid in another currency.
+    pub deducted: A,
 }
 
 /// Enable fee management for an arbitrating blockchain. This trait require implementing the
@@ -227,19 +779,42 @@ pub enum FeePolitic {
 /// transactions.
 pub trait Fee: Onchain + Asset {
     /// Type for describing the fee of a blockchain
-    type FeeUnit: Clone + PartialOrd + PartialEq + Eq + Debug + CanonicalBytes;
+    type FeeUnit: Clone + PartialOrd + PartialEq + Eq + Debug + CanonicalBytes + Send + Sync;
+
+    /// Type for the amount of fee paid, fees can be paid in a currency different from the traded
+    /// asset, e.g. gas paid in ether when trading a token.
+    type FeeAssetUnit: Copy + Eq + PartialOrd + Debug + CanonicalBytes + Send + Sync;
+
+    /// Return the identifier of the asset fees are paid in, by default the traded asset.
+    fn fee_asset_id(&self) -> AssetId {
+        self.asset_id()
+    }
 
-    /// Calculates and sets the fee on the given transaction and return the amount of fee set in
-    /// the blockchain native amount format.
+    /// Calculates and sets the fee on the given transaction and return the amount of fee paid and
+    /// the amount deducted from the traded asset.
+    ///
+    /// The label identifies the transaction template and fixes the number of outputs expected,
+    /// transactions with any other number of outputs are rejected. The fee is deducted from a
+    /// single output chosen by the implementation, the other outputs are left untouched and their
+    /// values are not available to pay the fee. On Bitcoin the fee is always taken from the first
+    /// output, only the buy, with an anchor, and the sweep, with the funding excess, can carry a
+    /// second output after it.
     fn set_fee(
         tx: &mut Self::PartialTransaction,
+        label: TxLabel,
         strategy: &FeeStrategy<Self::FeeUnit>,
         politic: FeePolitic,
-    ) -> Result<Self::AssetUnit, FeeStrategyError>;
+    ) -> Result<AppliedFee<Self::AssetUnit, Self::FeeAssetUnit>, FeeStrategyError>;
+
+    /// Return the amount of fee paid by the given transaction as currently built, e.g. to account
+    /// for the fee of a transaction received from the counter-party.
+    fn fee_paid(tx: &Self::PartialTransaction) -> Result<Self::FeeAssetUnit, FeeStrategyError>;
 
-    /// Validates that the fee for the given transaction are set accordingly to the strategy.
+    /// Validates that the fee for the given transaction are set accordingly to the strategy. The
+    /// label fixes the number of outputs expected, as in [`Fee::set_fee`].
     fn validate_fee(
         tx: &Self::PartialTransaction,
+        label: TxLabel,
         strategy: &FeeStrategy<Self::FeeUnit>,
     ) -> Result<bool, FeeStrategyError>;
 }
@@ -259,7 +834,11 @@ impl FromStr for Network {
 
 /// Defines a blockchain network, identifies in which context the system interacts with the
 /// blockchain.
+///
+/// Contrary to other encoded enums, unknown networks are not preserved when decoding: data
+/// created for a network unknown to this implementation cannot be safely used and is rejected.
 #[derive(Copy, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Debug)]
+#[non_exhaustive]
 pub enum Network {
     /// Represents a real asset on his valuable network
     Mainnet,
@@ -291,3 +870,102 @@ impl Decodable for Network {
 }
 
 impl_strict_encoding!(Network);
+
+/// Constants identifying a network in serialized and human readable data, they are distinct for
+/// every network so data created for one network cannot be mistakenly used on another one.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct NetworkConstants {
+    /// First six magic bytes of a public offer.
+    pub offer_magic_bytes: [u8; 6],
+    /// First six magic bytes of a blinded public offer.
+    pub blinded_offer_magic_bytes: [u8; 6],
+    /// Human readable part used when encoding data in bech32.
+    pub bech32_hrp: &'static str,
+    /// Scheme used in URIs.
+    pub uri_scheme: &'static str,
+}
+
+/// Constants used on [`Network::Mainnet`].
+pub const MAINNET_CONSTANTS: NetworkConstants = NetworkConstants {
+    offer_magic_bytes: *b"FCSWAP",
+    blinded_offer_magic_bytes: *b"FCBLND",
+    bech32_hrp: "fc",
+    uri_scheme: "farcaster",
+};
+
+/// Constants used on [`Network::Testnet`].
+pub const TESTNET_CONSTANTS: NetworkConstants = NetworkConstants {
+    offer_magic_bytes: *b"FCSWPT",
+    blinded_offer_magic_bytes: *b"FCBLNT",
+    bech32_hrp: "tfc",
+    uri_scheme: "farcaster-testnet",
+};
+
+/// Constants used on [`Network::Local`].
+pub const LOCAL_CONSTANTS: NetworkConstants = NetworkConstants {
+    offer_magic_bytes: *b"FCSWPL",
+    blinded_offer_magic_bytes: *b"FCBLNL",
+    bech32_hrp: "lfc",
+    uri_scheme: "farcaster-local",
+};
+
+impl Network {
+    /// List of all the networks.
+    pub const ALL: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Local];
+
+    /// Returns the constants of the network.
+    pub fn constants(&self) -> &'static NetworkConstants {
+        match self {
+            Network::Mainnet => &MAINNET_CONSTANTS,
+            Network::Testnet => &TESTNET_CONSTANTS,
+            Network::Local => &LOCAL_CONSTANTS,
+        }
+    }
+
+    /// Returns the first six magic bytes of a public offer on this network.
+    pub fn offer_magic_bytes(&self) -> &'static [u8; 6] {
+        &self.constants().offer_magic_bytes
+    }
+
+    /// Returns the first six magic bytes of a blinded public offer on this network.
+    pub fn blinded_offer_magic_bytes(&self) -> &'static [u8; 6] {
+        &self.constants().blinded_offer_magic_bytes
+    }
+
+    /// Returns the human readable part used when encoding data in bech32 on this network.
+    pub fn bech32_hrp(&self) -> &'static str {
+        self.constants().bech32_hrp
+    }
+
+    /// Returns the scheme used in URIs on this network.
+    pub fn uri_scheme(&self) -> &'static str {
+        self.constants().uri_scheme
+    }
+
+    /// Returns the network of a public offer given its magic bytes, if any.
+    pub fn from_offer_magic_bytes(magic_bytes: &[u8]) -> Option<Network> {
+        Self::find(|c| magic_bytes == c.offer_magic_bytes)
+    }
+
+    /// Returns the network of a blinded public offer given its magic bytes, if any.
+    pub fn from_blinded_offer_magic_bytes(magic_bytes: &[u8]) -> Option<Network> {
+        Self::find(|c| magic_bytes == c.blinded_offer_magic_bytes)
+    }
+
+    /// Returns the network using the bech32 human readable part, if any.
+    pub fn from_bech32_hrp(hrp: &str) -> Option<Network> {
+        Self::find(|c| hrp == c.bech32_hrp)
+    }
+
+    /// Returns the network using the URI scheme, if any.
+    pub fn from_uri_scheme(scheme: &str) -> Option<Network> {
+        Self::find(|c| scheme == c.uri_scheme)
+    }
+
+    fn find(predicate: impl Fn(&NetworkConstants) -> bool) -> Option<Network> {
+        Self::ALL
+            .iter()
+            .find(|network| predicate(network.constants()))
+            .copied()
+    }
+}
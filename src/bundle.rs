@@ -6,9 +6,12 @@
 use std::io;
 
 use crate::blockchain::{Address, Fee, FeeStrategy, Onchain, Timelock};
-use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::consensus::{self, CanonicalBytes, Decodable, Deterministic, Encodable};
 use crate::crypto::{Keys, SharedKeyId, SharedPrivateKeys, Signatures, TaggedElement};
+use crate::describe::Kind;
 use crate::protocol_message;
+use crate::role::SwapRole;
+use crate::settlement::SwapPhase;
 use crate::swap::Swap;
 
 /// Provides the (counter-party) daemon with all the information required for the initialization
@@ -34,6 +37,35 @@ pub struct AliceParameters<Ctx: Swap> {
     pub fee_strategy: Option<FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>>,
 }
 
+impl<Ctx> AliceParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Return the serialized public keys of the parameters, arbitrating and accordant keys, e.g.
+    /// to track them in a [`KeyTracker`](crate::crypto::KeyTracker).
+    pub fn public_keys(&self) -> Vec<Vec<u8>> {
+        let mut keys = vec![
+            self.buy.as_canonical_bytes(),
+            self.cancel.as_canonical_bytes(),
+            self.refund.as_canonical_bytes(),
+            self.punish.as_canonical_bytes(),
+            self.adaptor.as_canonical_bytes(),
+            self.spend.as_canonical_bytes(),
+        ];
+        keys.extend(
+            self.extra_arbitrating_keys
+                .iter()
+                .map(|key| key.elem().as_canonical_bytes()),
+        );
+        keys.extend(
+            self.extra_accordant_keys
+                .iter()
+                .map(|key| key.elem().as_canonical_bytes()),
+        );
+        keys
+    }
+}
+
 impl<Ctx> Encodable for AliceParameters<Ctx>
 where
     Ctx: Swap,
@@ -96,6 +128,22 @@ where
 }
 
 impl_strict_encoding!(AliceParameters<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(AliceParameters<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    AliceParameters<Ctx>,
+    [Ctx: Swap],
+    Kind::Bundle,
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Setup),
+    {
+        buy, cancel, refund, punish, adaptor, extra_arbitrating_keys, arbitrating_shared_keys,
+        spend, extra_accordant_keys, accordant_shared_keys, destination_address, proof,
+        cancel_timelock, punish_timelock, fee_strategy
+    }
+);
+
+impl<Ctx> Deterministic for AliceParameters<Ctx> where Ctx: Swap {}
 
 impl<Ctx> From<protocol_message::RevealAliceParameters<Ctx>> for AliceParameters<Ctx>
 where
@@ -144,6 +192,34 @@ pub struct BobParameters<Ctx: Swap> {
     pub fee_strategy: Option<FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>>,
 }
 
+impl<Ctx> BobParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Return the serialized public keys of the parameters, arbitrating and accordant keys, e.g.
+    /// to track them in a [`KeyTracker`](crate::crypto::KeyTracker).
+    pub fn public_keys(&self) -> Vec<Vec<u8>> {
+        let mut keys = vec![
+            self.buy.as_canonical_bytes(),
+            self.cancel.as_canonical_bytes(),
+            self.refund.as_canonical_bytes(),
+            self.adaptor.as_canonical_bytes(),
+            self.spend.as_canonical_bytes(),
+        ];
+        keys.extend(
+            self.extra_arbitrating_keys
+                .iter()
+                .map(|key| key.elem().as_canonical_bytes()),
+        );
+        keys.extend(
+            self.extra_accordant_keys
+                .iter()
+                .map(|key| key.elem().as_canonical_bytes()),
+        );
+        keys
+    }
+}
+
 impl<Ctx> Encodable for BobParameters<Ctx>
 where
     Ctx: Swap,
@@ -155,6 +231,7 @@ where
         len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
         len += self.extra_arbitrating_keys.consensus_encode(s)?;
         len += self.arbitrating_shared_keys.consensus_encode(s)?;
+        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
         len += self.extra_accordant_keys.consensus_encode(s)?;
         len += self.accordant_shared_keys.consensus_encode(s)?;
         len += self
@@ -201,6 +278,22 @@ where
 }
 
 impl_strict_encoding!(BobParameters<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(BobParameters<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    BobParameters<Ctx>,
+    [Ctx: Swap],
+    Kind::Bundle,
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Setup),
+    {
+        buy, cancel, refund, adaptor, extra_arbitrating_keys, arbitrating_shared_keys, spend,
+        extra_accordant_keys, accordant_shared_keys, refund_address, proof, cancel_timelock,
+        punish_timelock, fee_strategy
+    }
+);
+
+impl<Ctx> Deterministic for BobParameters<Ctx> where Ctx: Swap {}
 
 impl<Ctx> From<protocol_message::RevealBobParameters<Ctx>> for BobParameters<Ctx>
 where
@@ -261,6 +354,18 @@ where
 }
 
 impl_strict_encoding!(CosignedArbitratingCancel<S>, S: Signatures);
+impl_eq_by_encoding!(CosignedArbitratingCancel<S>, S: Signatures);
+
+impl_describe!(
+    CosignedArbitratingCancel<S>,
+    [S: Signatures],
+    Kind::Bundle,
+    None,
+    Some(SwapPhase::Setup),
+    { cancel_sig }
+);
+
+impl<S> Deterministic for CosignedArbitratingCancel<S> where S: Signatures {}
 
 impl<Ctx> From<protocol_message::CoreArbitratingSetup<Ctx>> for CosignedArbitratingCancel<Ctx::Ar>
 where
@@ -315,6 +420,18 @@ where
 }
 
 impl_strict_encoding!(FundingTransaction<T>, T: Onchain);
+impl_eq_by_encoding!(FundingTransaction<T>, T: Onchain);
+
+impl_describe!(
+    FundingTransaction<T>,
+    [T: Onchain],
+    Kind::Bundle,
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Setup),
+    { funding }
+);
+
+impl<T> Deterministic for FundingTransaction<T> where T: Onchain {}
 
 /// Provides Bob's daemon or Alice's clients the core set of arbritrating transactions.
 #[derive(Debug, Clone)]
@@ -352,6 +469,18 @@ where
 }
 
 impl_strict_encoding!(CoreArbitratingTransactions<T>, T: Onchain);
+impl_eq_by_encoding!(CoreArbitratingTransactions<T>, T: Onchain);
+
+impl_describe!(
+    CoreArbitratingTransactions<T>,
+    [T: Onchain],
+    Kind::Bundle,
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Setup),
+    { lock, cancel, refund }
+);
+
+impl<T> Deterministic for CoreArbitratingTransactions<T> where T: Onchain {}
 
 impl<Ctx> From<protocol_message::CoreArbitratingSetup<Ctx>> for CoreArbitratingTransactions<Ctx::Ar>
 where
@@ -406,6 +535,18 @@ where
 }
 
 impl_strict_encoding!(SignedAdaptorBuy<T>, T: Signatures + Onchain);
+impl_eq_by_encoding!(SignedAdaptorBuy<T>, T: Signatures + Onchain);
+
+impl_describe!(
+    SignedAdaptorBuy<T>,
+    [T: Signatures + Onchain],
+    Kind::Bundle,
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Settlement),
+    { buy, buy_adaptor_sig }
+);
+
+impl<T> Deterministic for SignedAdaptorBuy<T> where T: Signatures + Onchain {}
 
 /// Provides Alice's daemon or Bob's clients with the two signatures on the unsigned buy (c)
 /// transaction.
@@ -445,6 +586,18 @@ where
 }
 
 impl_strict_encoding!(FullySignedBuy<S>, S: Signatures);
+impl_eq_by_encoding!(FullySignedBuy<S>, S: Signatures);
+
+impl_describe!(
+    FullySignedBuy<S>,
+    [S: Signatures],
+    Kind::Bundle,
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Settlement),
+    { buy_sig, buy_adapted_sig }
+);
+
+impl<S> Deterministic for FullySignedBuy<S> where S: Signatures {}
 
 /// Provides Alice's daemon or Bob's clients with a signature on the unsigned refund (e)
 /// transaction.
@@ -481,6 +634,18 @@ where
 }
 
 impl_strict_encoding!(SignedAdaptorRefund<S>, S: Signatures);
+impl_eq_by_encoding!(SignedAdaptorRefund<S>, S: Signatures);
+
+impl_describe!(
+    SignedAdaptorRefund<S>,
+    [S: Signatures],
+    Kind::Bundle,
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Setup),
+    { refund_adaptor_sig }
+);
+
+impl<S> Deterministic for SignedAdaptorRefund<S> where S: Signatures {}
 
 impl<Ctx> From<protocol_message::RefundProcedureSignatures<Ctx>> for SignedAdaptorRefund<Ctx::Ar>
 where
@@ -531,6 +696,18 @@ where
 }
 
 impl_strict_encoding!(FullySignedRefund<S>, S: Signatures);
+impl_eq_by_encoding!(FullySignedRefund<S>, S: Signatures);
+
+impl_describe!(
+    FullySignedRefund<S>,
+    [S: Signatures],
+    Kind::Bundle,
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Settlement),
+    { refund_sig, refund_adapted_sig }
+);
+
+impl<S> Deterministic for FullySignedRefund<S> where S: Signatures {}
 
 /// Provides Bob's daemon with the signature on the unsigned lock (b) transaction.
 #[derive(Debug, Clone)]
@@ -562,6 +739,18 @@ where
 }
 
 impl_strict_encoding!(SignedArbitratingLock<S>, S: Signatures);
+impl_eq_by_encoding!(SignedArbitratingLock<S>, S: Signatures);
+
+impl_describe!(
+    SignedArbitratingLock<S>,
+    [S: Signatures],
+    Kind::Bundle,
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Lock),
+    { lock_sig }
+);
+
+impl<S> Deterministic for SignedArbitratingLock<S> where S: Signatures {}
 
 /// Provides Alice's daemon with the signature on the unsigned punish (f) transaction.
 #[derive(Debug, Clone)]
@@ -596,3 +785,386 @@ where
 }
 
 impl_strict_encoding!(FullySignedPunish<T>, T: Signatures + Onchain);
+impl_eq_by_encoding!(FullySignedPunish<T>, T: Signatures + Onchain);
+
+impl_describe!(
+    FullySignedPunish<T>,
+    [T: Signatures + Onchain],
+    Kind::Bundle,
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Settlement),
+    { punish, punish_sig }
+);
+
+impl<T> Deterministic for FullySignedPunish<T> where T: Signatures + Onchain {}
+
+/// Provides a watchtower with everything needed to enforce the punish path on Alice's behalf.
+///
+/// The trigger is the `cancel` (d) transaction: once it is confirmed on-chain and the punish
+/// timelock is expired, the `punish` (f) transaction, fully signed and paying Alice's destination
+/// address, can be broadcast. The cancel transaction is provided unsigned, only to identify it
+/// on-chain, so the watchtower cannot trigger the cancel path nor do anything else than punishing
+/// Bob.
+#[derive(Debug, Clone)]
+pub struct PunishDelegation<T>
+where
+    T: Onchain + Timelock,
+{
+    pub cancel: T::PartialTransaction,
+    pub punish: T::Transaction,
+    pub punish_timelock: T::Timelock,
+}
+
+impl<T> Encodable for PunishDelegation<T>
+where
+    T: Onchain + Timelock,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.cancel.as_canonical_bytes().consensus_encode(s)?;
+        len += self.punish.as_canonical_bytes().consensus_encode(s)?;
+        Ok(len
+            + self
+                .punish_timelock
+                .as_canonical_bytes()
+                .consensus_encode(s)?)
+    }
+}
+
+impl<T> Decodable for PunishDelegation<T>
+where
+    T: Onchain + Timelock,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            cancel: T::PartialTransaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            punish: T::Transaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            punish_timelock: T::Timelock::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+impl_strict_encoding!(PunishDelegation<T>, T: Onchain + Timelock);
+impl_eq_by_encoding!(PunishDelegation<T>, T: Onchain + Timelock);
+
+impl_describe!(
+    PunishDelegation<T>,
+    [T: Onchain + Timelock],
+    Kind::Bundle,
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Lock),
+    { cancel, punish, punish_timelock }
+);
+
+impl<T> Deterministic for PunishDelegation<T> where T: Onchain + Timelock {}
+
+/// Provides Alice's daemon with the cooperative close transaction, spending the lock (b) output
+/// back to Bob's refund address without waiting for the cancel timelock, and Bob's signature.
+#[derive(Debug, Clone)]
+pub struct SignedCooperativeClose<T>
+where
+    T: Signatures + Onchain,
+{
+    pub close: T::PartialTransaction,
+    pub close_sig: T::Signature,
+}
+
+impl<T> Encodable for SignedCooperativeClose<T>
+where
+    T: Signatures + Onchain,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.close.as_canonical_bytes().consensus_encode(s)?;
+        Ok(len + self.close_sig.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<T> Decodable for SignedCooperativeClose<T>
+where
+    T: Signatures + Onchain,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            close: T::PartialTransaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            close_sig: T::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+impl_strict_encoding!(SignedCooperativeClose<T>, T: Signatures + Onchain);
+impl_eq_by_encoding!(SignedCooperativeClose<T>, T: Signatures + Onchain);
+
+impl_describe!(
+    SignedCooperativeClose<T>,
+    [T: Signatures + Onchain],
+    Kind::Bundle,
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Lock),
+    { close, close_sig }
+);
+
+impl<T> Deterministic for SignedCooperativeClose<T> where T: Signatures + Onchain {}
+
+impl<Ctx> From<protocol_message::CooperativeCloseRequest<Ctx>> for SignedCooperativeClose<Ctx::Ar>
+where
+    Ctx: Swap,
+{
+    fn from(msg: protocol_message::CooperativeCloseRequest<Ctx>) -> Self {
+        Self {
+            close: msg.close,
+            close_sig: msg.close_sig,
+        }
+    }
+}
+
+/// Provides Bob's daemon with Alice's signature on the cooperative close transaction.
+#[derive(Debug, Clone)]
+pub struct CosignedCooperativeClose<S>
+where
+    S: Signatures,
+{
+    pub close_sig: S::Signature,
+}
+
+impl<S> Encodable for CosignedCooperativeClose<S>
+where
+    S: Signatures,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.close_sig.as_canonical_bytes().consensus_encode(s)
+    }
+}
+
+impl<S> Decodable for CosignedCooperativeClose<S>
+where
+    S: Signatures,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            close_sig: S::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+impl_strict_encoding!(CosignedCooperativeClose<S>, S: Signatures);
+impl_eq_by_encoding!(CosignedCooperativeClose<S>, S: Signatures);
+
+impl_describe!(
+    CosignedCooperativeClose<S>,
+    [S: Signatures],
+    Kind::Bundle,
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Lock),
+    { close_sig }
+);
+
+impl<S> Deterministic for CosignedCooperativeClose<S> where S: Signatures {}
+
+impl<Ctx> From<protocol_message::CooperativeCloseSignature<Ctx>>
+    for CosignedCooperativeClose<Ctx::Ar>
+where
+    Ctx: Swap,
+{
+    fn from(msg: protocol_message::CooperativeCloseSignature<Ctx>) -> Self {
+        Self {
+            close_sig: msg.close_sig,
+        }
+    }
+}
+
+/// Provides a third-party auditor with a watch-only view of a swap. The bundle contains only public
+/// information: both participants' arbitrating public keys, accordant spend public keys and shared
+/// private view keys, addresses, and the arbitrating transaction templates. No secret allowing to
+/// move the funds is part of the bundle, it is thus safe to hand it over to an accounting system
+/// that wants to verify the execution of the swap.
+#[derive(Debug, Clone)]
+pub struct AuditBundle<Ctx: Swap> {
+    pub alice_buy: <Ctx::Ar as Keys>::PublicKey,
+    pub alice_cancel: <Ctx::Ar as Keys>::PublicKey,
+    pub alice_refund: <Ctx::Ar as Keys>::PublicKey,
+    pub alice_punish: <Ctx::Ar as Keys>::PublicKey,
+    pub alice_adaptor: <Ctx::Ar as Keys>::PublicKey,
+    pub alice_spend: <Ctx::Ac as Keys>::PublicKey,
+    pub alice_accordant_shared_keys:
+        Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey>>,
+    pub bob_buy: <Ctx::Ar as Keys>::PublicKey,
+    pub bob_cancel: <Ctx::Ar as Keys>::PublicKey,
+    pub bob_refund: <Ctx::Ar as Keys>::PublicKey,
+    pub bob_adaptor: <Ctx::Ar as Keys>::PublicKey,
+    pub bob_spend: <Ctx::Ac as Keys>::PublicKey,
+    pub bob_accordant_shared_keys:
+        Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey>>,
+    pub destination_address: <Ctx::Ar as Address>::Address,
+    pub refund_address: <Ctx::Ar as Address>::Address,
+    pub lock: <Ctx::Ar as Onchain>::PartialTransaction,
+    pub cancel: <Ctx::Ar as Onchain>::PartialTransaction,
+    pub refund: <Ctx::Ar as Onchain>::PartialTransaction,
+    pub buy: Option<<Ctx::Ar as Onchain>::PartialTransaction>,
+    pub punish: Option<<Ctx::Ar as Onchain>::PartialTransaction>,
+}
+
+impl<Ctx> AuditBundle<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create a watch-only audit bundle from both participants' parameters and the core
+    /// arbitrating transactions. The buy and punish templates are not known at this stage and
+    /// can be added later with [`AuditBundle::with_buy`] and [`AuditBundle::with_punish`].
+    pub fn new(
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        core: &CoreArbitratingTransactions<Ctx::Ar>,
+    ) -> Self {
+        Self {
+            alice_buy: alice_parameters.buy.clone(),
+            alice_cancel: alice_parameters.cancel.clone(),
+            alice_refund: alice_parameters.refund.clone(),
+            alice_punish: alice_parameters.punish.clone(),
+            alice_adaptor: alice_parameters.adaptor.clone(),
+            alice_spend: alice_parameters.spend.clone(),
+            alice_accordant_shared_keys: alice_parameters.accordant_shared_keys.clone(),
+            bob_buy: bob_parameters.buy.clone(),
+            bob_cancel: bob_parameters.cancel.clone(),
+            bob_refund: bob_parameters.refund.clone(),
+            bob_adaptor: bob_parameters.adaptor.clone(),
+            bob_spend: bob_parameters.spend.clone(),
+            bob_accordant_shared_keys: bob_parameters.accordant_shared_keys.clone(),
+            destination_address: alice_parameters.destination_address.clone(),
+            refund_address: bob_parameters.refund_address.clone(),
+            lock: core.lock.clone(),
+            cancel: core.cancel.clone(),
+            refund: core.refund.clone(),
+            buy: None,
+            punish: None,
+        }
+    }
+
+    /// Add the buy transaction template to the audit bundle.
+    pub fn with_buy(mut self, buy: <Ctx::Ar as Onchain>::PartialTransaction) -> Self {
+        self.buy = Some(buy);
+        self
+    }
+
+    /// Add the punish transaction template to the audit bundle.
+    pub fn with_punish(mut self, punish: <Ctx::Ar as Onchain>::PartialTransaction) -> Self {
+        self.punish = Some(punish);
+        self
+    }
+}
+
+impl<Ctx> Encodable for AuditBundle<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.alice_buy.as_canonical_bytes().consensus_encode(s)?;
+        len += self.alice_cancel.as_canonical_bytes().consensus_encode(s)?;
+        len += self.alice_refund.as_canonical_bytes().consensus_encode(s)?;
+        len += self.alice_punish.as_canonical_bytes().consensus_encode(s)?;
+        len += self
+            .alice_adaptor
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self.alice_spend.as_canonical_bytes().consensus_encode(s)?;
+        len += self.alice_accordant_shared_keys.consensus_encode(s)?;
+        len += self.bob_buy.as_canonical_bytes().consensus_encode(s)?;
+        len += self.bob_cancel.as_canonical_bytes().consensus_encode(s)?;
+        len += self.bob_refund.as_canonical_bytes().consensus_encode(s)?;
+        len += self.bob_adaptor.as_canonical_bytes().consensus_encode(s)?;
+        len += self.bob_spend.as_canonical_bytes().consensus_encode(s)?;
+        len += self.bob_accordant_shared_keys.consensus_encode(s)?;
+        len += self
+            .destination_address
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self
+            .refund_address
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self.lock.as_canonical_bytes().consensus_encode(s)?;
+        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
+        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
+        len += self.buy.consensus_encode(s)?;
+        Ok(len + self.punish.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for AuditBundle<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            alice_buy: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            alice_cancel: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            alice_refund: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            alice_punish: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            alice_adaptor: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            alice_spend: <Ctx::Ac as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            alice_accordant_shared_keys: Decodable::consensus_decode(d)?,
+            bob_buy: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            bob_cancel: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            bob_refund: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            bob_adaptor: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            bob_spend: <Ctx::Ac as Keys>::PublicKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            bob_accordant_shared_keys: Decodable::consensus_decode(d)?,
+            destination_address: <Ctx::Ar as Address>::Address::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            refund_address: <Ctx::Ar as Address>::Address::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            lock: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            cancel: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            refund: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            buy: Decodable::consensus_decode(d)?,
+            punish: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(AuditBundle<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(AuditBundle<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    AuditBundle<Ctx>,
+    [Ctx: Swap],
+    Kind::Bundle,
+    None,
+    None,
+    {
+        alice_buy, alice_cancel, alice_refund, alice_punish, alice_adaptor, alice_spend,
+        alice_accordant_shared_keys, bob_buy, bob_cancel, bob_refund, bob_adaptor, bob_spend,
+        bob_accordant_shared_keys, destination_address, refund_address, lock, cancel, refund, buy,
+        punish
+    }
+);
+
+impl<Ctx> Deterministic for AuditBundle<Ctx> where Ctx: Swap {}
@@ -1,8 +1,73 @@
+use crate::blockchain::{AddressScript, Network};
+use crate::chain::bitcoin::Bitcoin;
 use crate::consensus::{self, CanonicalBytes};
+use bitcoin::util::address::Payload;
 use bitcoin::Address;
+use bitcoin::Network as BtcNetwork;
 
 use std::str::{self, FromStr};
 
+/// The types of Bitcoin addresses, future witness versions are identified by their version
+/// number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressType {
+    /// Pay to public key hash
+    P2pkh,
+    /// Pay to script hash
+    P2sh,
+    /// Pay to witness public key hash
+    P2wpkh,
+    /// Pay to witness script hash
+    P2wsh,
+    /// Pay to a witness program of a future version (1 to 16)
+    WitnessProgram(u8),
+}
+
+impl AddressScript for Bitcoin {
+    type AddressType = AddressType;
+
+    fn address_type(address: &Address) -> Option<AddressType> {
+        match &address.payload {
+            Payload::PubkeyHash(_) => Some(AddressType::P2pkh),
+            Payload::ScriptHash(_) => Some(AddressType::P2sh),
+            Payload::WitnessProgram { version, program } => match version.to_u8() {
+                0 => match program.len() {
+                    20 => Some(AddressType::P2wpkh),
+                    32 => Some(AddressType::P2wsh),
+                    _ => None,
+                },
+                v @ 1..=16 => Some(AddressType::WitnessProgram(v)),
+                _ => None,
+            },
+        }
+    }
+
+    fn standard_address_types() -> Vec<AddressType> {
+        vec![
+            AddressType::P2pkh,
+            AddressType::P2sh,
+            AddressType::P2wpkh,
+            AddressType::P2wsh,
+        ]
+    }
+
+    fn is_valid_for_network(address: &Address, network: Network) -> bool {
+        match (network, address.network) {
+            (Network::Mainnet, BtcNetwork::Bitcoin) => true,
+            (Network::Testnet, BtcNetwork::Testnet) | (Network::Testnet, BtcNetwork::Signet) => {
+                true
+            }
+            (Network::Local, BtcNetwork::Regtest) => true,
+            // Base58 addresses use the same prefixes on test and regtest networks and are parsed
+            // as testnet addresses
+            (Network::Local, BtcNetwork::Testnet) => {
+                !matches!(address.payload, Payload::WitnessProgram { .. })
+            }
+            _ => false,
+        }
+    }
+}
+
 impl CanonicalBytes for Address {
     fn as_canonical_bytes(&self) -> Vec<u8> {
         self.to_string().into()
@@ -12,9 +77,7 @@ impl CanonicalBytes for Address {
     where
         Self: Sized,
     {
-        Ok(
-            Address::from_str(str::from_utf8(bytes).map_err(consensus::Error::new)?)
-                .map_err(consensus::Error::new)?,
-        )
+        Address::from_str(str::from_utf8(bytes).map_err(consensus::Error::new)?)
+            .map_err(consensus::Error::new)
     }
 }
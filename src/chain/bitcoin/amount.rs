@@ -1,5 +1,10 @@
+#[cfg(feature = "parse-amounts")]
+use crate::blockchain::{split_amount_unit, AmountParseError, UnitAmount};
+use crate::blockchain::{AmountError, AtomicAmount};
 use crate::consensus::{self, CanonicalBytes};
 use bitcoin::Amount;
+#[cfg(feature = "parse-amounts")]
+use bitcoin::Denomination;
 
 impl CanonicalBytes for Amount {
     fn as_canonical_bytes(&self) -> Vec<u8> {
@@ -15,3 +20,36 @@ impl CanonicalBytes for Amount {
         ))
     }
 }
+
+impl AtomicAmount for Amount {
+    /// The amount in satoshis
+    fn to_atomic(&self) -> u128 {
+        self.as_sat().to_atomic()
+    }
+
+    fn from_atomic(value: u128) -> Result<Self, AmountError> {
+        u64::from_atomic(value).map(Amount::from_sat)
+    }
+}
+
+#[cfg(feature = "parse-amounts")]
+impl UnitAmount for Amount {
+    /// Parse an amount in `btc`, `mbtc`, `ubtc`, `bits`, or `sat`.
+    fn parse_with_unit(s: &str) -> Result<Self, AmountParseError> {
+        let (value, unit) = split_amount_unit(s)?;
+        let denom = match unit.as_str() {
+            "btc" => Denomination::Bitcoin,
+            "mbtc" => Denomination::MilliBitcoin,
+            "ubtc" => Denomination::MicroBitcoin,
+            "bits" => Denomination::Bit,
+            "sat" | "sats" | "satoshi" | "satoshis" => Denomination::Satoshi,
+            _ => return Err(AmountParseError::UnknownUnit(unit)),
+        };
+        Amount::from_str_in(value, denom)
+            .map_err(|e| AmountParseError::InvalidAmount(e.to_string()))
+    }
+
+    fn to_string_with_unit(&self) -> String {
+        self.to_string_with_denomination(Denomination::Bitcoin)
+    }
+}
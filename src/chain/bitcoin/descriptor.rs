@@ -0,0 +1,540 @@
+//! Output descriptors of the swap outputs, following the descriptor language of Bitcoin Core
+//! (BIP380), used by external wallets and watchtowers to monitor the swap independently of the
+//! daemons.
+//!
+//! The funding output and the swap addresses are exported as `wpkh(KEY)` and `addr(ADDRESS)`
+//! descriptors. The lock and cancel scripts are expressed in miniscript and their outputs are
+//! exported as `wsh(MINISCRIPT)` descriptors, parsed back into their core script types, see
+//! [`SwapScript`]. The witness scripts, found in the partial transactions, are parsed back with
+//! [`lock_from_script`] and [`punish_lock_from_script`] and checked against their descriptors
+//! with [`Descriptor::verify_witness_script`].
+//!
+//! All descriptors are displayed with their checksum, when parsed the checksum is optional but
+//! must be valid when present.
+
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{self, Instruction, Script};
+use bitcoin::util::key::PublicKey;
+use bitcoin::Address;
+
+use thiserror::Error;
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bundle::{AliceParameters, BobParameters};
+use crate::chain::bitcoin::timelock::CSVTimelock;
+use crate::chain::bitcoin::transaction::cancel::punish_lock_script;
+use crate::chain::bitcoin::transaction::lock::lock_script;
+use crate::chain::bitcoin::Bitcoin;
+use crate::negotiation::PublicOffer;
+use crate::script::{DataLock, DataPunishableLock, DoubleKeys};
+use crate::swap::Swap;
+
+#[cfg(feature = "htlc")]
+use crate::chain::bitcoin::htlc::htlc_script;
+#[cfg(feature = "htlc")]
+use crate::script::{DataHashLock, HashedKey};
+
+const INPUT_CHARSET: &str = concat!(
+    "0123456789()[],'/*abcdefgh@:$%{}",
+    "IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~",
+    "ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ",
+);
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Errors when parsing or verifying descriptors.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The descriptor contains a character outside of the descriptor charset.
+    #[error("Invalid character in descriptor")]
+    InvalidCharacter,
+    /// The checksum does not match the descriptor.
+    #[error("Invalid descriptor checksum")]
+    InvalidChecksum,
+    /// The descriptor is malformed or not supported.
+    #[error("Malformed or unsupported descriptor")]
+    Malformed,
+    /// The key, address, or script of the descriptor cannot be parsed.
+    #[error("Invalid descriptor argument")]
+    InvalidArgument,
+    /// The script does not follow the swap script template.
+    #[error("Script does not follow the swap template")]
+    WrongTemplate,
+    /// The script does not match the descriptor.
+    #[error("Script does not match the descriptor")]
+    ScriptMismatch,
+}
+
+/// Compute the checksum of a descriptor, fails if the descriptor contains a character outside of
+/// the descriptor charset.
+pub fn checksum(desc: &str) -> Result<String, Error> {
+    fn poly_mod(mut c: u64, val: u64) -> u64 {
+        let c0 = c >> 35;
+        c = ((c & 0x7ffffffff) << 5) ^ val;
+        for (i, generator) in [
+            0xf5dee51989,
+            0xa9fdca3312,
+            0x1bab10e32d,
+            0x3706b1677a,
+            0x644d626ffd,
+        ]
+        .iter()
+        .enumerate()
+        {
+            if c0 & (1 << i) != 0 {
+                c ^= generator;
+            }
+        }
+        c
+    }
+
+    let mut c = 1;
+    let mut cls = 0;
+    let mut count = 0;
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET.find(ch).ok_or(Error::InvalidCharacter)? as u64;
+        c = poly_mod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        count += 1;
+        if count == 3 {
+            c = poly_mod(c, cls);
+            cls = 0;
+            count = 0;
+        }
+    }
+    if count > 0 {
+        c = poly_mod(c, cls);
+    }
+    for _ in 0..8 {
+        c = poly_mod(c, 0);
+    }
+    c ^= 1;
+
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+/// A descriptor of an output watched during the swap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Descriptor {
+    /// A pay-to-witness-public-key-hash output, `wpkh(KEY)`.
+    Wpkh(PublicKey),
+    /// An output paying to an address, `addr(ADDRESS)`.
+    Addr(Address),
+    /// An output with an arbitrary script pubkey, `raw(HEX)`.
+    Raw(Script),
+    /// A pay-to-witness-script-hash output of a swap script, `wsh(MINISCRIPT)`.
+    Wsh(SwapScript),
+}
+
+impl Descriptor {
+    /// Return the script pubkey of the described output, fails if the public key of a `wpkh`
+    /// descriptor is not compressed.
+    pub fn script_pubkey(&self) -> Result<Script, Error> {
+        match self {
+            Self::Wpkh(key) => Ok(Script::new_v0_wpkh(
+                &key.wpubkey_hash().ok_or(Error::InvalidArgument)?,
+            )),
+            Self::Addr(address) => Ok(address.script_pubkey()),
+            Self::Raw(script) => Ok(script.clone()),
+            Self::Wsh(swap_script) => Ok(swap_script.witness_script().to_v0_p2wsh()),
+        }
+    }
+
+    /// Verify that the witness script is the one committed in the described output.
+    pub fn verify_witness_script(&self, witness_script: &Script) -> Result<(), Error> {
+        match self.script_pubkey()? == witness_script.to_v0_p2wsh() {
+            true => Ok(()),
+            false => Err(Error::ScriptMismatch),
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            Self::Wpkh(key) => format!("wpkh({})", key),
+            Self::Addr(address) => format!("addr({})", address),
+            Self::Raw(script) => format!("raw({})", hex::encode(script.as_bytes())),
+            Self::Wsh(swap_script) => format!("wsh({})", swap_script.miniscript()),
+        }
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let body = self.body();
+        let checksum = checksum(&body).map_err(|_| fmt::Error)?;
+        write!(f, "{}#{}", body, checksum)
+    }
+}
+
+impl FromStr for Descriptor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let body = match s.split_once('#') {
+            Some((body, sum)) if checksum(body)? == sum => body,
+            Some(_) => return Err(Error::InvalidChecksum),
+            None => {
+                checksum(s)?;
+                s
+            }
+        };
+        let fragment = match Fragment::parse(body)? {
+            (fragment, "") => fragment,
+            _ => return Err(Error::Malformed),
+        };
+        let arg = fragment.args(fragment.name, 1)?;
+        match fragment.name {
+            "wpkh" => Ok(Self::Wpkh(key(&arg[0])?)),
+            "addr" => Ok(Self::Addr(
+                Address::from_str(arg[0].leaf()?).map_err(|_| Error::InvalidArgument)?,
+            )),
+            "raw" => Ok(Self::Raw(
+                hex::decode(arg[0].leaf()?)
+                    .map_err(|_| Error::InvalidArgument)?
+                    .into(),
+            )),
+            "wsh" => Ok(Self::Wsh(SwapScript::from_fragment(&arg[0])?)),
+            _ => Err(Error::Malformed),
+        }
+    }
+}
+
+/// The swap scripts committed in pay-to-witness-script-hash outputs. All the swap scripts are
+/// expressed in miniscript so external wallets can compute and satisfy them from their
+/// descriptors.
+#[derive(Debug, Clone)]
+pub enum SwapScript {
+    /// The script of the lock output with the buy and cancel paths.
+    Lock(DataLock<Bitcoin>),
+    /// The script of the cancel output with the refund and punish paths.
+    Cancel(DataPunishableLock<Bitcoin>),
+    /// The script of the hash time-locked lock output.
+    #[cfg(feature = "htlc")]
+    HashLock(DataHashLock<Bitcoin>),
+}
+
+impl SwapScript {
+    /// Return the witness script committed in the output.
+    pub fn witness_script(&self) -> Script {
+        match self {
+            Self::Lock(lock) => lock_script(lock),
+            Self::Cancel(punish_lock) => punish_lock_script(punish_lock),
+            #[cfg(feature = "htlc")]
+            Self::HashLock(lock) => htlc_script(lock),
+        }
+    }
+
+    fn miniscript(&self) -> String {
+        match self {
+            Self::Lock(lock) => format!(
+                "or_i({},and_v(v:older({}),{}))",
+                multi(&lock.success),
+                lock.timelock.as_u32(),
+                multi(&lock.failure),
+            ),
+            Self::Cancel(punish_lock) => format!(
+                "or_i({},and_v(v:older({}),pk({})))",
+                multi(&punish_lock.success),
+                punish_lock.timelock.as_u32(),
+                punish_lock.failure,
+            ),
+            #[cfg(feature = "htlc")]
+            Self::HashLock(lock) => format!(
+                "c:or_i({},and_v(v:older({}),{}))",
+                hashed_key(&lock.success),
+                lock.timelock.as_u32(),
+                hashed_key(&lock.failure),
+            ),
+        }
+    }
+
+    fn from_fragment(fragment: &Fragment) -> Result<Self, Error> {
+        match fragment.name {
+            "or_i" => {
+                let paths = fragment.args("or_i", 2)?;
+                let success = from_multi(&paths[0])?;
+                let failure = paths[1].args("and_v", 2)?;
+                let timelock = from_older(&failure[0])?;
+                match failure[1].name {
+                    "multi" => Ok(Self::Lock(DataLock {
+                        timelock,
+                        success,
+                        failure: from_multi(&failure[1])?,
+                    })),
+                    "pk" => Ok(Self::Cancel(DataPunishableLock {
+                        timelock,
+                        success,
+                        failure: key(&failure[1].args("pk", 1)?[0])?,
+                    })),
+                    _ => Err(Error::WrongTemplate),
+                }
+            }
+            #[cfg(feature = "htlc")]
+            "c:or_i" => {
+                let paths = fragment.args("c:or_i", 2)?;
+                let failure = paths[1].args("and_v", 2)?;
+                Ok(Self::HashLock(DataHashLock {
+                    timelock: from_older(&failure[0])?,
+                    success: from_hashed_key(&paths[0])?,
+                    failure: from_hashed_key(&failure[1])?,
+                }))
+            }
+            _ => Err(Error::WrongTemplate),
+        }
+    }
+}
+
+// Scripts are compared by their encoding, the swap script types are not comparable
+impl PartialEq for SwapScript {
+    fn eq(&self, other: &Self) -> bool {
+        self.witness_script() == other.witness_script()
+    }
+}
+
+impl Eq for SwapScript {}
+
+// A fragment of a descriptor, `NAME(ARG,...)` or a bare argument without arguments
+struct Fragment<'a> {
+    name: &'a str,
+    args: Vec<Fragment<'a>>,
+}
+
+impl<'a> Fragment<'a> {
+    // Parse the fragment at the start of the string, return the fragment and the rest
+    fn parse(s: &'a str) -> Result<(Self, &'a str), Error> {
+        let end = s.find(['(', ',', ')']).unwrap_or(s.len());
+        let (name, mut rest) = s.split_at(end);
+        if name.is_empty() {
+            return Err(Error::Malformed);
+        }
+        let mut args = vec![];
+        if let Some(mut inner) = rest.strip_prefix('(') {
+            loop {
+                let (arg, after) = Self::parse(inner)?;
+                args.push(arg);
+                match after.split_at(after.len().min(1)) {
+                    (",", after) => inner = after,
+                    (")", after) => {
+                        rest = after;
+                        break;
+                    }
+                    _ => return Err(Error::Malformed),
+                }
+            }
+        }
+        Ok((Self { name, args }, rest))
+    }
+
+    // Return the arguments of the fragment, fails if the fragment is not the expected one
+    fn args(&self, name: &str, count: usize) -> Result<&[Fragment<'a>], Error> {
+        match self.name == name && self.args.len() == count {
+            true => Ok(&self.args),
+            false => Err(Error::Malformed),
+        }
+    }
+
+    // Return the bare argument
+    fn leaf(&self) -> Result<&'a str, Error> {
+        match self.args.is_empty() {
+            true => Ok(self.name),
+            false => Err(Error::Malformed),
+        }
+    }
+}
+
+fn key(fragment: &Fragment) -> Result<PublicKey, Error> {
+    match PublicKey::from_str(fragment.leaf()?) {
+        Ok(key) if key.compressed => Ok(key),
+        _ => Err(Error::InvalidArgument),
+    }
+}
+
+fn multi(keys: &DoubleKeys<Bitcoin>) -> String {
+    format!("multi(2,{},{})", keys.alice, keys.bob)
+}
+
+fn from_multi(fragment: &Fragment) -> Result<DoubleKeys<Bitcoin>, Error> {
+    let args = fragment.args("multi", 3)?;
+    match args[0].leaf()? {
+        "2" => Ok(DoubleKeys::new(key(&args[1])?, key(&args[2])?)),
+        _ => Err(Error::WrongTemplate),
+    }
+}
+
+// Relative timelocks of miniscript are above zero and below the disable flag
+fn from_older(fragment: &Fragment) -> Result<CSVTimelock, Error> {
+    match u32::from_str(fragment.args("v:older", 1)?[0].leaf()?) {
+        Ok(timelock) if timelock > 0 && timelock < (1 << 31) => Ok(CSVTimelock::new(timelock)),
+        _ => Err(Error::InvalidArgument),
+    }
+}
+
+#[cfg(feature = "htlc")]
+fn hashed_key(hashed_key: &HashedKey<Bitcoin>) -> String {
+    format!(
+        "and_v(v:sha256({}),pk_k({}))",
+        hex::encode(hashed_key.hash),
+        hashed_key.key
+    )
+}
+
+#[cfg(feature = "htlc")]
+fn from_hashed_key(fragment: &Fragment) -> Result<HashedKey<Bitcoin>, Error> {
+    let args = fragment.args("and_v", 2)?;
+    let mut hash = [0u8; 32];
+    hex::decode_to_slice(args[0].args("v:sha256", 1)?[0].leaf()?, &mut hash)
+        .map_err(|_| Error::InvalidArgument)?;
+    Ok(HashedKey::new(key(&args[1].args("pk_k", 1)?[0])?, hash))
+}
+
+/// Return the descriptor of the lock output.
+pub fn lock_descriptor(lock: &DataLock<Bitcoin>) -> Descriptor {
+    Descriptor::Wsh(SwapScript::Lock(lock.clone()))
+}
+
+/// Return the descriptor of the cancel output.
+pub fn cancel_descriptor(punish_lock: &DataPunishableLock<Bitcoin>) -> Descriptor {
+    Descriptor::Wsh(SwapScript::Cancel(punish_lock.clone()))
+}
+
+/// Return the descriptor of the hash time-locked lock output.
+#[cfg(feature = "htlc")]
+pub fn htlc_descriptor(lock: &DataHashLock<Bitcoin>) -> Descriptor {
+    Descriptor::Wsh(SwapScript::HashLock(lock.clone()))
+}
+
+// Extract the keys and the timelock pushed in a swap script
+fn script_data(script: &Script) -> Result<(Vec<PublicKey>, CSVTimelock), Error> {
+    let instructions: Vec<Instruction> = script
+        .instructions()
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::WrongTemplate)?;
+    let keys = instructions
+        .iter()
+        .filter_map(|i| match i {
+            Instruction::PushBytes(bytes) if bytes.len() == 33 => {
+                Some(PublicKey::from_slice(bytes))
+            }
+            _ => None,
+        })
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::WrongTemplate)?;
+    let csv = instructions
+        .iter()
+        .position(|i| *i == Instruction::Op(opcodes::all::OP_CSV))
+        .ok_or(Error::WrongTemplate)?;
+    let timelock = match csv.checked_sub(1).map(|i| &instructions[i]) {
+        Some(Instruction::PushBytes(bytes)) => {
+            script::read_scriptint(bytes).map_err(|_| Error::WrongTemplate)?
+        }
+        Some(Instruction::Op(op))
+            if (opcodes::all::OP_PUSHNUM_1.into_u8()..=opcodes::all::OP_PUSHNUM_16.into_u8())
+                .contains(&op.into_u8()) =>
+        {
+            (op.into_u8() - opcodes::all::OP_PUSHNUM_1.into_u8() + 1).into()
+        }
+        _ => return Err(Error::WrongTemplate),
+    };
+    let timelock = u32::try_from(timelock).map_err(|_| Error::WrongTemplate)?;
+    Ok((keys, CSVTimelock::new(timelock)))
+}
+
+/// Parse the witness script of the lock output back into its [`DataLock`].
+pub fn lock_from_script(script: &Script) -> Result<DataLock<Bitcoin>, Error> {
+    let (keys, timelock) = script_data(script)?;
+    let lock = match keys.as_slice() {
+        [a_buy, b_buy, a_cancel, b_cancel] => DataLock {
+            timelock,
+            success: DoubleKeys::new(*a_buy, *b_buy),
+            failure: DoubleKeys::new(*a_cancel, *b_cancel),
+        },
+        _ => return Err(Error::WrongTemplate),
+    };
+    match &lock_script(&lock) == script {
+        true => Ok(lock),
+        false => Err(Error::WrongTemplate),
+    }
+}
+
+/// Parse the witness script of the cancel output back into its [`DataPunishableLock`].
+pub fn punish_lock_from_script(script: &Script) -> Result<DataPunishableLock<Bitcoin>, Error> {
+    let (keys, timelock) = script_data(script)?;
+    let punish_lock = match keys.as_slice() {
+        [a_refund, b_refund, a_punish] => DataPunishableLock {
+            timelock,
+            success: DoubleKeys::new(*a_refund, *b_refund),
+            failure: *a_punish,
+        },
+        _ => return Err(Error::WrongTemplate),
+    };
+    match &punish_lock_script(&punish_lock) == script {
+        true => Ok(punish_lock),
+        false => Err(Error::WrongTemplate),
+    }
+}
+
+/// The descriptors of all the outputs of a swap on the arbitrating blockchain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapDescriptors {
+    /// The funding output, controlled by Bob's funding key
+    pub funding: Descriptor,
+    /// The lock output
+    pub lock: Descriptor,
+    /// The cancel output
+    pub cancel: Descriptor,
+    /// Alice's destination address, paid by the buy and punish transactions
+    pub destination: Descriptor,
+    /// Bob's refund address, paid by the refund transaction
+    pub refund: Descriptor,
+}
+
+impl SwapDescriptors {
+    /// Create the descriptors of the swap from the parameters of both participants and the public
+    /// offer, the funding key is Bob's arbitrating funding key.
+    pub fn new<Ctx>(
+        funding: PublicKey,
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<Self, Error>
+    where
+        Ctx: Swap<Ar = Bitcoin>,
+    {
+        if !funding.compressed {
+            return Err(Error::InvalidArgument);
+        }
+        let lock = DataLock {
+            timelock: public_offer.offer.cancel_timelock,
+            success: DoubleKeys::new(alice_parameters.buy, bob_parameters.buy),
+            failure: DoubleKeys::new(alice_parameters.cancel, bob_parameters.cancel),
+        };
+        let punish_lock = DataPunishableLock {
+            timelock: public_offer.offer.punish_timelock,
+            success: DoubleKeys::new(alice_parameters.refund, bob_parameters.refund),
+            failure: alice_parameters.punish,
+        };
+        Ok(Self {
+            funding: Descriptor::Wpkh(funding),
+            lock: lock_descriptor(&lock),
+            cancel: cancel_descriptor(&punish_lock),
+            destination: Descriptor::Addr(alice_parameters.destination_address.clone()),
+            refund: Descriptor::Addr(bob_parameters.refund_address.clone()),
+        })
+    }
+
+    /// Return all the descriptors, in the order of the swap.
+    pub fn descriptors(&self) -> Vec<&Descriptor> {
+        vec![
+            &self.funding,
+            &self.lock,
+            &self.cancel,
+            &self.destination,
+            &self.refund,
+        ]
+    }
+}
@@ -0,0 +1,226 @@
+//! Economic deterrence of the punish path of a swap.
+//!
+//! Bob is only deterred from misbehaving if Alice actually punishes him, i.e. if what she takes
+//! with the punish transaction is worth the fee of broadcasting it. Once the cancel and the punish
+//! transactions paid their fees out of the locked amount, the punishable amount left to Alice must
+//! exceed a configurable multiple of the punish transaction fee, otherwise punishing is
+//! economically irrational and Bob's misbehavior goes unpunished in practice.
+//!
+//! A [`DeterrencePolicy`] validates the offers against this rule. The fees are computed with the
+//! offer [`FeeStrategy`] at its highest rate, on templates of the cancel and punish transactions
+//! built as by the swap, so the check holds whatever the fee politic of the participants. An
+//! offer not meeting the rule is surfaced as a [`DeterrenceWarning`] or rejected with an
+//! [`Error`] depending on the policy [`Severity`].
+
+use std::fmt;
+
+use bitcoin::blockdata::script::Builder;
+use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Amount, Script};
+
+use thiserror::Error;
+
+use crate::blockchain::{Fee, FeePolitic, FeeStrategy, FeeStrategyError};
+use crate::chain::bitcoin::fee::SatPerVByte;
+use crate::chain::bitcoin::Bitcoin;
+use crate::negotiation::Offer;
+use crate::swap::Swap;
+use crate::transaction::TxLabel;
+
+/// The default minimum ratio between the punishable amount and the punish transaction fee.
+pub const DEFAULT_MIN_DETERRENCE_MULTIPLE: u64 = 10;
+
+/// Errors when checking the punishment deterrence of an offer.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The punishable amount is lower than the minimum multiple of the punish transaction fee.
+    #[error("Punishable amount is below the deterrence threshold: {0}")]
+    InsufficientDeterrence(DeterrenceWarning),
+    /// The fees of the cancel and punish transactions cannot be paid by the locked amount.
+    #[error("Locked amount cannot pay the cancel and punish transaction fees")]
+    Unpunishable,
+    /// The fee strategy cannot be applied on the punish path.
+    #[error("Fee strategy error: {0}")]
+    FeeStrategy(#[from] FeeStrategyError),
+}
+
+/// Defines how an offer not meeting the deterrence rule is surfaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// Accept the offer and return a warning.
+    Warn,
+    /// Reject the offer with an error.
+    Reject,
+}
+
+/// Why an offer is not deterring enough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterrenceWarning {
+    /// The amount Alice receives with the punish transaction
+    pub punishable: Amount,
+    /// The fee of the punish transaction
+    pub punish_fee: Amount,
+    /// The minimum multiple of the punish fee required by the policy
+    pub min_multiple: u64,
+}
+
+impl fmt::Display for DeterrenceWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "punishable amount {} is less than {} times the punish fee {}",
+            self.punishable, self.min_multiple, self.punish_fee
+        )
+    }
+}
+
+/// The amounts of the punish path of an offer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PunishPath {
+    /// The fee of the cancel transaction
+    pub cancel_fee: Amount,
+    /// The fee of the punish transaction
+    pub punish_fee: Amount,
+    /// The amount Alice receives with the punish transaction
+    pub punishable: Amount,
+}
+
+impl PunishPath {
+    /// Compute the punish path of a locked amount with the highest fee allowed by the strategy.
+    pub fn new(
+        locked: Amount,
+        strategy: &FeeStrategy<SatPerVByte>,
+    ) -> Result<Self, FeeStrategyError> {
+        let mut cancel = spending_template(locked);
+        let cancel_fee = Bitcoin::set_fee(
+            &mut cancel,
+            TxLabel::Cancel,
+            strategy,
+            FeePolitic::Conservative,
+        )?
+        .fee;
+        let output = Amount::from_sat(cancel.global.unsigned_tx.output[0].value);
+        let mut punish = spending_template(output);
+        let punish_fee = Bitcoin::set_fee(
+            &mut punish,
+            TxLabel::Punish,
+            strategy,
+            FeePolitic::Conservative,
+        )?
+        .fee;
+        Ok(Self {
+            cancel_fee,
+            punish_fee,
+            punishable: Amount::from_sat(punish.global.unsigned_tx.output[0].value),
+        })
+    }
+
+    /// Return true if the punishable amount is at least `multiple` times the punish fee.
+    pub fn deters(&self, multiple: u64) -> bool {
+        match self.punish_fee.checked_mul(multiple) {
+            Some(threshold) => self.punishable >= threshold,
+            None => false,
+        }
+    }
+}
+
+// A transaction spending a P2WSH output into a P2WSH output, the heaviest output the cancel and
+// punish transactions can have, with the fee not set yet
+fn spending_template(amount: Amount) -> PartiallySignedTransaction {
+    let p2wsh = Builder::new()
+        .push_int(0)
+        .push_slice(&[0u8; 32])
+        .into_script();
+    let tx = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::default(),
+            script_sig: Script::default(),
+            sequence: 0xffffffff,
+            witness: vec![],
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: p2wsh.clone(),
+        }],
+    };
+    let mut psbt =
+        PartiallySignedTransaction::from_unsigned_tx(tx).expect("template has no signature");
+    psbt.inputs[0].witness_utxo = Some(TxOut {
+        value: amount.as_sat(),
+        script_pubkey: p2wsh,
+    });
+    psbt
+}
+
+/// Validates that the offers deter Bob from misbehaving, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeterrencePolicy {
+    min_multiple: u64,
+    severity: Severity,
+}
+
+impl Default for DeterrencePolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_DETERRENCE_MULTIPLE)
+    }
+}
+
+impl DeterrencePolicy {
+    /// Create a policy requiring the punishable amount to be at least `min_multiple` times the
+    /// punish transaction fee, offers not meeting the rule are rejected.
+    pub fn new(min_multiple: u64) -> Self {
+        Self {
+            min_multiple,
+            severity: Severity::Reject,
+        }
+    }
+
+    /// Set how the offers not meeting the rule are surfaced.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Return the minimum multiple of the punish fee of the policy.
+    pub fn min_multiple(&self) -> u64 {
+        self.min_multiple
+    }
+
+    /// Return the severity of the policy.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Check the punish path of the offer. Returns a warning if the offer does not meet the rule
+    /// and the policy only warns, an error if the policy rejects it. Offers whose locked amount
+    /// cannot pay the fees of the punish path are always rejected.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn check<Ctx>(&self, offer: &Offer<Ctx>) -> Result<Option<DeterrenceWarning>, Error>
+    where
+        Ctx: Swap<Ar = Bitcoin>,
+    {
+        let path = match PunishPath::new(offer.arbitrating_amount, &offer.fee_strategy) {
+            Err(FeeStrategyError::NotEnoughAssets) => return Err(Error::Unpunishable),
+            res => res?,
+        };
+        if path.deters(self.min_multiple) {
+            return Ok(None);
+        }
+        let warning = DeterrenceWarning {
+            punishable: path.punishable,
+            punish_fee: path.punish_fee,
+            min_multiple: self.min_multiple,
+        };
+        match self.severity {
+            Severity::Warn => Ok(Some(warning)),
+            Severity::Reject => Err(Error::InsufficientDeterrence(warning)),
+        }
+    }
+}
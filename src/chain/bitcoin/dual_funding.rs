@@ -0,0 +1,288 @@
+//! Experimental dual-funded construction of the arbitrating `lock (b)` transaction.
+//!
+//! In the dual-funded variant both participants contribute inputs to the lock transaction,
+//! Alice's share is agreed before the construction starts. Contributions are exchanged
+//! interactively, following the interactive transaction construction of dual-funded channels:
+//! each participant sends [`AddLockInput`] and [`AddLockOutput`] messages identified by a serial
+//! identifier, even for Bob initiating the construction and odd for Alice, until both
+//! participants send [`LockContributionComplete`] one after the other.
+//!
+//! Once complete, both participants build the same lock transaction with
+//! [`LockConstruction::to_lock`]: the lock output first, then the change outputs, inputs and change
+//! outputs in the deterministic order of [`ordering`], so the order does not reveal which
+//! participant contributed them. Only segwit outputs can be contributed so the transaction
+//! identifier cannot be malleated before the `cancel (d)` and `refund (e)` transactions are signed.
+//! The fee is the difference between the inputs and the outputs and is paid by Bob; each
+//! participant signs and finalizes its own inputs with its wallet.
+
+use std::collections::BTreeMap;
+
+use bitcoin::blockdata::transaction::{OutPoint, SigHashType, TxIn, TxOut};
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Amount, Script, Transaction};
+
+use thiserror::Error;
+
+use crate::protocol_message::{AddLockInput, AddLockOutput, LockContributionComplete};
+use crate::role::SwapRole;
+use crate::script::DataLock;
+use crate::transaction::{self, Transaction as _};
+
+use crate::chain::bitcoin::transaction::lock::lock_script;
+use crate::chain::bitcoin::transaction::ordering;
+use crate::chain::bitcoin::transaction::{Lock, Tx};
+use crate::chain::bitcoin::Bitcoin;
+
+/// The maximum number of inputs, and of change outputs, in a dual-funded lock transaction.
+pub const MAX_CONTRIBUTIONS: usize = 252;
+
+/// Errors when constructing a dual-funded lock transaction.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The serial identifier parity does not match the role of the contributor.
+    #[error("Serial identifier {0} has the wrong parity")]
+    WrongSerialIdParity(u64),
+    /// The serial identifier is already used by another contribution.
+    #[error("Serial identifier {0} is already used")]
+    DuplicateSerialId(u64),
+    /// The previous transaction cannot be parsed.
+    #[error("Invalid previous transaction")]
+    InvalidPrevTx,
+    /// The index does not point to an output of the previous transaction.
+    #[error("Invalid previous output index {0}")]
+    InvalidPrevVout(u32),
+    /// The output is already spent by another input.
+    #[error("The output is already contributed")]
+    DuplicateInput,
+    /// The contributed output is not a segwit output.
+    #[error("Only segwit outputs can be contributed")]
+    NonSegwitInput,
+    /// Too many inputs or change outputs are contributed.
+    #[error("Too many contributions")]
+    TooManyContributions,
+    /// The construction is not complete yet.
+    #[error("The construction is not complete")]
+    NotComplete,
+    /// The participant contributes less than its share.
+    #[error("{0} contributes less than its share")]
+    InsufficientContribution(SwapRole),
+    /// The change outputs are higher than the inputs.
+    #[error("Not enough assets to cover the outputs")]
+    NotEnoughAssets,
+}
+
+impl From<Error> for transaction::Error {
+    fn from(e: Error) -> transaction::Error {
+        transaction::Error::new(e)
+    }
+}
+
+/// The state of the interactive construction of a dual-funded lock transaction.
+#[derive(Debug, Clone)]
+pub struct LockConstruction {
+    target_amount: Amount,
+    alice_share: Amount,
+    inputs: BTreeMap<u64, (SwapRole, OutPoint, TxOut)>,
+    outputs: BTreeMap<u64, (SwapRole, TxOut)>,
+    complete: Option<SwapRole>,
+    done: bool,
+}
+
+impl LockConstruction {
+    /// Start the construction of a lock transaction locking `target_amount`, where Alice
+    /// contributes `alice_share` and Bob the rest with the fee.
+    pub fn new(target_amount: Amount, alice_share: Amount) -> Self {
+        Self {
+            target_amount,
+            alice_share,
+            inputs: BTreeMap::new(),
+            outputs: BTreeMap::new(),
+            complete: None,
+            done: false,
+        }
+    }
+
+    fn check_serial_id(&self, from: SwapRole, serial_id: u64) -> Result<(), Error> {
+        let expected = match from {
+            SwapRole::Bob => 0,
+            SwapRole::Alice => 1,
+        };
+        if serial_id % 2 != expected {
+            return Err(Error::WrongSerialIdParity(serial_id));
+        }
+        if self.inputs.contains_key(&serial_id) || self.outputs.contains_key(&serial_id) {
+            return Err(Error::DuplicateSerialId(serial_id));
+        }
+        Ok(())
+    }
+
+    // A new contribution restarts the completion of the construction
+    fn contributed(&mut self) {
+        self.complete = None;
+        self.done = false;
+    }
+
+    /// Add an input contributed by a participant.
+    pub fn add_input(&mut self, from: SwapRole, msg: &AddLockInput) -> Result<(), Error> {
+        self.check_serial_id(from, msg.serial_id)?;
+        if self.inputs.len() >= MAX_CONTRIBUTIONS {
+            return Err(Error::TooManyContributions);
+        }
+        let prev_tx: Transaction = deserialize(&msg.prev_tx).map_err(|_| Error::InvalidPrevTx)?;
+        let tx_out = prev_tx
+            .output
+            .get(msg.prev_vout as usize)
+            .cloned()
+            .ok_or(Error::InvalidPrevVout(msg.prev_vout))?;
+        if !tx_out.script_pubkey.is_witness_program() {
+            return Err(Error::NonSegwitInput);
+        }
+        let out_point = OutPoint::new(prev_tx.txid(), msg.prev_vout);
+        if self.inputs.values().any(|(_, op, _)| *op == out_point) {
+            return Err(Error::DuplicateInput);
+        }
+        self.inputs.insert(msg.serial_id, (from, out_point, tx_out));
+        self.contributed();
+        Ok(())
+    }
+
+    /// Add a change output contributed by a participant.
+    pub fn add_output(&mut self, from: SwapRole, msg: &AddLockOutput) -> Result<(), Error> {
+        self.check_serial_id(from, msg.serial_id)?;
+        if self.outputs.len() >= MAX_CONTRIBUTIONS {
+            return Err(Error::TooManyContributions);
+        }
+        let tx_out = TxOut {
+            value: msg.amount,
+            script_pubkey: Script::from(msg.script_pubkey.clone()),
+        };
+        self.outputs.insert(msg.serial_id, (from, tx_out));
+        self.contributed();
+        Ok(())
+    }
+
+    /// Register that a participant has nothing more to contribute.
+    pub fn complete(&mut self, from: SwapRole, _msg: &LockContributionComplete) {
+        match self.complete {
+            Some(role) if role == from.other() => self.done = true,
+            _ => self.complete = Some(from),
+        }
+    }
+
+    /// Return true if both participants completed the construction one after the other.
+    pub fn is_complete(&self) -> bool {
+        self.done
+    }
+
+    /// Return the amount contributed by a participant, its inputs minus its change outputs.
+    pub fn contribution(&self, role: SwapRole) -> Result<Amount, Error> {
+        // Input and output amounts are chosen by the counter-party and can overflow
+        let inputs = self
+            .inputs
+            .values()
+            .filter(|(from, _, _)| *from == role)
+            .try_fold(0u64, |sum, (_, _, tx_out)| sum.checked_add(tx_out.value));
+        let outputs = self
+            .outputs
+            .values()
+            .filter(|(from, _)| *from == role)
+            .try_fold(0u64, |sum, (_, tx_out)| sum.checked_add(tx_out.value));
+        inputs
+            .zip(outputs)
+            .and_then(|(inputs, outputs)| inputs.checked_sub(outputs))
+            .map(Amount::from_sat)
+            .ok_or(Error::NotEnoughAssets)
+    }
+
+    /// Validate that the construction is complete and that both participants contribute their
+    /// share, Bob's share covering the fee.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.is_complete() {
+            return Err(Error::NotComplete);
+        }
+        if self.contribution(SwapRole::Alice)? < self.alice_share {
+            return Err(Error::InsufficientContribution(SwapRole::Alice));
+        }
+        let bob_share = self
+            .target_amount
+            .checked_sub(self.alice_share)
+            .ok_or(Error::InsufficientContribution(SwapRole::Alice))?;
+        if self.contribution(SwapRole::Bob)? < bob_share || self.fee()? == Amount::ZERO {
+            return Err(Error::InsufficientContribution(SwapRole::Bob));
+        }
+        Ok(())
+    }
+
+    /// Return the fee paid by the lock transaction.
+    pub fn fee(&self) -> Result<Amount, Error> {
+        self.contribution(SwapRole::Alice)?
+            .checked_add(self.contribution(SwapRole::Bob)?)
+            .and_then(|contributed| contributed.checked_sub(self.target_amount))
+            .ok_or(Error::NotEnoughAssets)
+    }
+
+    /// Build the dual-funded lock transaction once the construction is validated.
+    pub fn to_lock(&self, lock: &DataLock<Bitcoin>) -> Result<Tx<Lock>, transaction::Error> {
+        self.validate()?;
+        let script = lock_script(lock);
+
+        let unsigned_tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: self
+                .inputs
+                .values()
+                .map(|(_, out_point, _)| TxIn {
+                    previous_output: *out_point,
+                    script_sig: Script::default(),
+                    sequence: (1 << 31) as u32, // activate disable flag on CSV
+                    witness: vec![],
+                })
+                .collect(),
+            output: std::iter::once(TxOut {
+                value: self.target_amount.as_sat(),
+                script_pubkey: script.to_v0_p2wsh(),
+            })
+            .chain(self.outputs.values().map(|(_, tx_out)| tx_out.clone()))
+            .collect(),
+        };
+
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned_tx)
+            .map_err(super::transaction::Error::from)?;
+
+        // Set the inputs witness data and sighash type
+        for (psbt_in, (_, _, tx_out)) in psbt.inputs.iter_mut().zip(self.inputs.values()) {
+            psbt_in.witness_utxo = Some(tx_out.clone());
+            psbt_in.sighash_type = Some(SigHashType::All);
+        }
+
+        // Set the script witness of the lock output
+        psbt.outputs[0].witness_script = Some(script);
+
+        // The lock output stays first, the contributions are ordered
+        ordering::sort_partial_transaction(&mut psbt, 0, 1);
+
+        Ok(Tx::from_partial(psbt))
+    }
+
+    /// Verify that the lock transaction received from the counter-party is the transaction
+    /// resulting from the construction.
+    pub fn verify_lock(
+        &self,
+        tx: &Tx<Lock>,
+        lock: &DataLock<Bitcoin>,
+    ) -> Result<(), transaction::Error> {
+        ordering::verify_order(&tx.as_partial().global.unsigned_tx, 0, 1)?;
+        let expected = self.to_lock(lock)?;
+        match tx.as_partial().global.unsigned_tx == expected.as_partial().global.unsigned_tx {
+            true => Ok(()),
+            false => Err(transaction::Error::WrongTemplate),
+        }
+    }
+}
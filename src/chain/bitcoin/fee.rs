@@ -2,15 +2,16 @@ use bitcoin::blockdata::transaction::TxOut;
 use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::Amount;
 
-use crate::blockchain::{Fee, FeePolitic, FeeStrategy, FeeStrategyError};
+use crate::blockchain::{AppliedFee, AtomicAmount, Fee, FeePolitic, FeeStrategy, FeeStrategyError};
 use crate::consensus::{self, CanonicalBytes};
+use crate::transaction::{self, TxLabel};
 
-use crate::chain::bitcoin::transaction;
+use crate::chain::bitcoin::policy::{self, MempoolPolicy};
 use crate::chain::bitcoin::Bitcoin;
 
 use std::str::FromStr;
 
-#[derive(Debug, Clone, PartialOrd, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialOrd, PartialEq, Eq, Hash)]
 pub struct SatPerVByte(Amount);
 
 impl SatPerVByte {
@@ -65,27 +66,57 @@ fn get_available_input_sat(tx: &PartiallySignedTransaction) -> Result<Amount, Fe
                 .ok_or(FeeStrategyError::MissingInputsMetadata)
         })
         .collect();
-    Ok(Amount::from_sat(
-        inputs?.iter().map(|txout| txout.value).sum(),
-    ))
+    sum_sat(inputs?.iter())
+}
+
+/// Returns the sum of the values of the outputs, fails on overflow as the values of the previous
+/// outputs are provided by the counter-party.
+fn sum_sat<'a>(txouts: impl Iterator<Item = &'a TxOut>) -> Result<Amount, FeeStrategyError> {
+    Amount::try_sum(txouts.map(|txout| Amount::from_sat(txout.value)))
+        .map_err(FeeStrategyError::new)
+}
+
+/// Returns the sum of all the outputs except the first one, on which the fee is applied.
+fn get_untouched_output_sat(tx: &PartiallySignedTransaction) -> Result<Amount, FeeStrategyError> {
+    sum_sat(tx.global.unsigned_tx.output.iter().skip(1))
+}
+
+/// Checks that the transaction has the number of outputs expected for its label. The first output
+/// pays the fee, only the buy, with an anchor, and the sweep, with the funding excess, can carry a
+/// second output.
+fn check_output_count(
+    tx: &PartiallySignedTransaction,
+    label: TxLabel,
+) -> Result<(), FeeStrategyError> {
+    let max_outputs = match label {
+        TxLabel::Lock | TxLabel::Cancel | TxLabel::Refund | TxLabel::Punish => 1,
+        TxLabel::Buy | TxLabel::Sweep => 2,
+        _ => return Err(FeeStrategyError::new(transaction::Error::WrongTemplate)),
+    };
+    if !(1..=max_outputs).contains(&tx.global.unsigned_tx.output.len()) {
+        return Err(FeeStrategyError::new(transaction::Error::WrongTemplate));
+    }
+    Ok(())
 }
 
 impl Fee for Bitcoin {
     type FeeUnit = SatPerVByte;
+    type FeeAssetUnit = Amount;
 
-    /// Calculates and sets the fees on the given transaction and return the fees set
+    /// Calculates and sets the fees on the given transaction and return the fees set, the fee is
+    /// taken from the first output only
     fn set_fee(
         tx: &mut PartiallySignedTransaction,
+        label: TxLabel,
         strategy: &FeeStrategy<SatPerVByte>,
         politic: FeePolitic,
-    ) -> Result<Amount, FeeStrategyError> {
-        if tx.global.unsigned_tx.output.len() != 1 {
-            return Err(FeeStrategyError::new(
-                transaction::Error::MultiUTXOUnsuported,
-            ));
-        }
+    ) -> Result<AppliedFee<Amount, Amount>, FeeStrategyError> {
+        check_output_count(tx, label)?;
 
-        let input_sum = get_available_input_sat(&tx)?;
+        // The fee is applied on the first output, the other outputs are left untouched
+        let input_sum = get_available_input_sat(tx)?
+            .checked_sub(get_untouched_output_sat(tx)?)
+            .ok_or(FeeStrategyError::NotEnoughAssets)?;
 
         // FIXME This does not account for witnesses
         // currently the fees are wrong
@@ -93,12 +124,14 @@ impl Fee for Bitcoin {
         let weight = tx.global.unsigned_tx.get_weight() as u64;
 
         // Compute the fee amount to set in total
-        let fee_amount = match strategy {
+        let fee_amount = match strategy.base() {
             FeeStrategy::Fixed(sat_per_vbyte) => sat_per_vbyte.as_native_unit().checked_mul(weight),
             FeeStrategy::Range(range) => match politic {
                 FeePolitic::Aggressive => range.start.as_native_unit().checked_mul(weight),
                 FeePolitic::Conservative => range.end.as_native_unit().checked_mul(weight),
+                politic => return Err(FeeStrategyError::UnsupportedPolitic(politic)),
             },
+            FeeStrategy::Targeted(..) => unreachable!("base strategy is never targeted"),
         }
         .ok_or_else(|| FeeStrategyError::AmountOfFeeTooHigh)?;
 
@@ -108,23 +141,34 @@ impl Fee for Bitcoin {
             .ok_or_else(|| FeeStrategyError::NotEnoughAssets)?
             .as_sat();
 
-        // Return the fee amount set in native blockchain asset unit
-        Ok(fee_amount)
+        // Fees are paid in bitcoin, the traded asset
+        Ok(AppliedFee {
+            fee: fee_amount,
+            deducted: fee_amount,
+        })
+    }
+
+    /// Returns the difference between the inputs and the outputs of the transaction
+    fn fee_paid(tx: &PartiallySignedTransaction) -> Result<Amount, FeeStrategyError> {
+        let output_sum = sum_sat(tx.global.unsigned_tx.output.iter())?;
+        get_available_input_sat(tx)?
+            .checked_sub(output_sum)
+            .ok_or(FeeStrategyError::AmountOfFeeTooHigh)
     }
 
     /// Validates that the fees for the given transaction are set accordingly to the strategy
+    ///
+    /// The mempool policy of the network is not checked, callers broadcasting the transaction
+    /// must use [`Bitcoin::validate_fee_with_policy`] to ensure it is relayed.
     fn validate_fee(
         tx: &PartiallySignedTransaction,
+        label: TxLabel,
         strategy: &FeeStrategy<SatPerVByte>,
     ) -> Result<bool, FeeStrategyError> {
-        if tx.global.unsigned_tx.output.len() != 1 {
-            return Err(FeeStrategyError::new(
-                transaction::Error::MultiUTXOUnsuported,
-            ));
-        }
+        check_output_count(tx, label)?;
 
-        let input_sum = get_available_input_sat(&tx)?.as_sat();
-        let output_sum = tx.global.unsigned_tx.output[0].value;
+        let input_sum = get_available_input_sat(tx)?.as_sat();
+        let output_sum = sum_sat(tx.global.unsigned_tx.output.iter())?.as_sat();
         let fee = input_sum
             .checked_sub(output_sum)
             .ok_or_else(|| FeeStrategyError::AmountOfFeeTooHigh)?;
@@ -136,9 +180,316 @@ impl Fee for Bitcoin {
                 .ok_or(FeeStrategyError::AmountOfFeeTooLow)?,
         );
 
-        Ok(match strategy {
+        // The confirmation target is advisory, only the base strategy is enforced
+        Ok(match strategy.base() {
             FeeStrategy::Fixed(fee_strat) => &effective_sat_per_vbyte == fee_strat,
             FeeStrategy::Range(range) => range.contains(&effective_sat_per_vbyte),
+            FeeStrategy::Targeted(..) => unreachable!("base strategy is never targeted"),
         })
     }
 }
+
+impl Bitcoin {
+    /// Validates that the fees for the given transaction are set accordingly to the strategy and
+    /// that the transaction is relayed by the network, see [`MempoolPolicy`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn validate_fee_with_policy(
+        tx: &PartiallySignedTransaction,
+        label: TxLabel,
+        strategy: &FeeStrategy<SatPerVByte>,
+        policy: &MempoolPolicy,
+    ) -> Result<bool, FeeStrategyError> {
+        match policy.check_fee(tx) {
+            Ok(()) => (),
+            Err(policy::Error::FeeBelowMinRelayFee) => {
+                return Err(FeeStrategyError::AmountOfFeeTooLow)
+            }
+            Err(policy::Error::MissingInputsMetadata) => {
+                return Err(FeeStrategyError::MissingInputsMetadata)
+            }
+            Err(e) => return Err(FeeStrategyError::new(e)),
+        }
+        policy
+            .check_standard(&tx.global.unsigned_tx)
+            .map_err(FeeStrategyError::new)?;
+        Self::validate_fee(tx, label, strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Asset, ConfirmationTarget, FeeEstimator};
+    use crate::consensus::{deserialize, serialize};
+    use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn};
+    use bitcoin::Script;
+
+    #[test]
+    fn fee_paid_in_traded_asset() {
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 0xffffffff,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: 0,
+                script_pubkey: Script::default(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 10_000,
+            script_pubkey: Script::default(),
+        });
+
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(2));
+        let applied =
+            Bitcoin::set_fee(&mut psbt, TxLabel::Lock, &strategy, FeePolitic::Aggressive).unwrap();
+        assert_eq!(applied.fee, applied.deducted);
+        assert_eq!(
+            psbt.global.unsigned_tx.output[0].value,
+            10_000 - applied.deducted.as_sat()
+        );
+        assert_eq!(Bitcoin::fee_paid(&psbt).unwrap(), applied.fee);
+        assert_eq!(Bitcoin.fee_asset_id(), Bitcoin.asset_id());
+
+        // Unknown politics are preserved but cannot be applied
+        assert_eq!(
+            deserialize::<FeePolitic>(&serialize(&FeePolitic::Conservative)).unwrap(),
+            FeePolitic::Conservative
+        );
+        let unknown: FeePolitic = deserialize(&[0x42, 0x00]).unwrap();
+        assert_eq!(serialize(&unknown), vec![0x42, 0x00]);
+        let range = FeeStrategy::Range(SatPerVByte::from_sat(1)..SatPerVByte::from_sat(2));
+        assert!(matches!(
+            Bitcoin::set_fee(&mut psbt, TxLabel::Lock, &range, unknown),
+            Err(FeeStrategyError::UnsupportedPolitic(_))
+        ));
+    }
+
+    #[test]
+    fn fee_taken_from_first_output() {
+        let output = TxOut {
+            value: 0,
+            script_pubkey: Script::default(),
+        };
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 0xffffffff,
+                witness: vec![],
+            }],
+            output: vec![output.clone()],
+        };
+        let mut single = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        single.inputs[0].witness_utxo = Some(TxOut {
+            value: 10_000,
+            script_pubkey: Script::default(),
+        });
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(2));
+
+        // The single output receives all the inputs minus the fee
+        let applied = Bitcoin::set_fee(
+            &mut single,
+            TxLabel::Lock,
+            &strategy,
+            FeePolitic::Aggressive,
+        )
+        .unwrap();
+        assert_eq!(
+            single.global.unsigned_tx.output[0].value,
+            10_000 - applied.fee.as_sat()
+        );
+        assert_eq!(Bitcoin::fee_paid(&single).unwrap(), applied.fee);
+
+        // Other outputs are left untouched and not used to pay the fee
+        let mut multi = single.clone();
+        multi.global.unsigned_tx.output.push(TxOut {
+            value: 330,
+            ..output.clone()
+        });
+        multi.outputs.push(Default::default());
+        let applied =
+            Bitcoin::set_fee(&mut multi, TxLabel::Buy, &strategy, FeePolitic::Aggressive).unwrap();
+        assert_eq!(multi.global.unsigned_tx.output[1].value, 330);
+        assert_eq!(
+            multi.global.unsigned_tx.output[0].value,
+            10_000 - 330 - applied.fee.as_sat()
+        );
+
+        assert!(Bitcoin::validate_fee(&multi, TxLabel::Sweep, &strategy).is_ok());
+
+        // MUST fail with more outputs than the template of the transaction
+        assert!(
+            Bitcoin::set_fee(&mut multi, TxLabel::Lock, &strategy, FeePolitic::Aggressive).is_err()
+        );
+        assert!(Bitcoin::validate_fee(&multi, TxLabel::Punish, &strategy).is_err());
+        let mut triple = multi;
+        triple.global.unsigned_tx.output.push(output);
+        triple.outputs.push(Default::default());
+        assert!(
+            Bitcoin::set_fee(&mut triple, TxLabel::Buy, &strategy, FeePolitic::Aggressive).is_err()
+        );
+        assert!(Bitcoin::validate_fee(&triple, TxLabel::Sweep, &strategy).is_err());
+
+        // MUST fail for labels without fee to set on the arbitrating blockchain
+        assert!(Bitcoin::validate_fee(&single, TxLabel::Funding, &strategy).is_err());
+        assert!(Bitcoin::validate_fee(&single, TxLabel::AccordantLock, &strategy).is_err());
+
+        // MUST fail without output to take the fee from
+        let mut empty = single;
+        empty.global.unsigned_tx.output.clear();
+        empty.outputs.clear();
+        assert!(
+            Bitcoin::set_fee(&mut empty, TxLabel::Lock, &strategy, FeePolitic::Aggressive).is_err()
+        );
+    }
+
+    struct StaticEstimator;
+
+    impl FeeEstimator<SatPerVByte> for StaticEstimator {
+        fn estimate_fee(
+            &self,
+            target: Option<ConfirmationTarget>,
+        ) -> Result<SatPerVByte, FeeStrategyError> {
+            // Faster confirmations cost more
+            let blocks = target.map(|t| t.blocks()).unwrap_or(6) as u64;
+            Ok(SatPerVByte::from_sat(60 / blocks))
+        }
+    }
+
+    #[test]
+    fn confirmation_target_is_advisory() {
+        let range = FeeStrategy::Range(SatPerVByte::from_sat(5)..SatPerVByte::from_sat(20));
+        let strategy = range
+            .clone()
+            .with_confirmation_target(ConfirmationTarget::new(2).unwrap());
+        assert_eq!(strategy.confirmation_target(), ConfirmationTarget::new(2));
+        assert_eq!(strategy.base(), &range);
+        assert_eq!(ConfirmationTarget::new(0), None);
+
+        // Encoding of strategies without target is unchanged
+        assert_eq!(serialize(&range)[0], 0x02);
+        let de: FeeStrategy<SatPerVByte> = deserialize(&serialize(&strategy)).unwrap();
+        assert_eq!(de, strategy);
+        // Targets cannot be nested nor null
+        let mut nested = vec![0x03, 0x02, 0x00];
+        nested.extend(serialize(&strategy));
+        assert!(deserialize::<FeeStrategy<SatPerVByte>>(&nested).is_err());
+        let mut null = serialize(&strategy);
+        null[1] = 0x00;
+        assert!(deserialize::<FeeStrategy<SatPerVByte>>(&null).is_err());
+
+        // Estimations are bounded by the range, fixed strategies ignore the estimator
+        assert_eq!(
+            strategy.estimate_with(&StaticEstimator).unwrap(),
+            SatPerVByte::from_sat(20)
+        );
+        assert_eq!(
+            range.estimate_with(&StaticEstimator).unwrap(),
+            SatPerVByte::from_sat(10)
+        );
+        let slow = range.with_confirmation_target(ConfirmationTarget::new(30).unwrap());
+        assert_eq!(
+            slow.estimate_with(&StaticEstimator).unwrap(),
+            SatPerVByte::from_sat(5)
+        );
+        let fixed = FeeStrategy::Fixed(SatPerVByte::from_sat(2))
+            .with_confirmation_target(ConfirmationTarget::new(1).unwrap());
+        assert_eq!(
+            fixed.estimate_with(&StaticEstimator).unwrap(),
+            SatPerVByte::from_sat(2)
+        );
+        assert_eq!(
+            format!("{}", ConfirmationTarget::new(6).unwrap()),
+            "within 6 blocks"
+        );
+    }
+
+    #[test]
+    fn mempool_policy() {
+        use crate::blockchain::Network;
+        use crate::chain::bitcoin::policy::{RbfPolicy, MAX_STANDARD_TX_WEIGHT};
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 0xffffffff,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: 10_000,
+                script_pubkey: Script::default(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx.clone()).unwrap();
+        let policy = MempoolPolicy::for_network(Network::Mainnet);
+        // MUST error without the previous outputs
+        assert_eq!(
+            policy.check_fee(&psbt),
+            Err(policy::Error::MissingInputsMetadata)
+        );
+
+        // No fee is paid
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 10_000,
+            script_pubkey: Script::default(),
+        });
+        assert_eq!(
+            policy.check_fee(&psbt),
+            Err(policy::Error::FeeBelowMinRelayFee)
+        );
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(1));
+        assert!(matches!(
+            Bitcoin::validate_fee_with_policy(&psbt, TxLabel::Lock, &strategy, &policy),
+            Err(FeeStrategyError::AmountOfFeeTooLow)
+        ));
+
+        // Pay exactly the minimum relay fee
+        let min_fee = policy.min_relay_fee_for(tx.get_weight() as u64);
+        psbt.global.unsigned_tx.output[0].value = 10_000 - min_fee.as_sat();
+        assert!(policy.check_fee(&psbt).is_ok());
+        assert!(policy.check_standard(&tx).is_ok());
+        assert!(policy.is_replaceable(&tx));
+
+        // Public networks policies cannot be overridden
+        assert_eq!(
+            policy.with_min_relay_fee(0),
+            Err(policy::Error::NotOverridable)
+        );
+        let local = MempoolPolicy::for_network(Network::Local)
+            .with_min_relay_fee(0)
+            .unwrap()
+            .with_rbf(RbfPolicy::OptIn)
+            .unwrap();
+        psbt.global.unsigned_tx.output[0].value = 10_000;
+        assert!(local.check_fee(&psbt).is_ok());
+        // MUST error if the previous outputs overflow
+        let mut overflowing = psbt.clone();
+        overflowing.inputs.push(overflowing.inputs[0].clone());
+        for input in overflowing.inputs.iter_mut() {
+            input.witness_utxo.as_mut().unwrap().value = u64::MAX;
+        }
+        assert_eq!(
+            local.check_fee(&overflowing),
+            Err(policy::Error::AmountOverflow)
+        );
+        // The transaction does not signal replaceability
+        assert!(!local.is_replaceable(&tx));
+        assert!(!local.require_standard());
+        assert_eq!(local.max_standard_weight(), MAX_STANDARD_TX_WEIGHT);
+    }
+}
@@ -0,0 +1,269 @@
+//! Experimental hash time-locked contracts used in place of the adaptor signature construction.
+//!
+//! The lock output is locked with a [`DataHashLock`] instead of a [`DataLock`]: the success path
+//! is spent with the signature of the success key and the secret hashed in the contract, the
+//! failure path is spent after the timelock with the signature of the failure key and its own
+//! secret. The secrets are the participants' shares of the accordant spend private key, the
+//! counter-party extracts them from the witness with [`extract_secret`] once the spending
+//! transaction is seen on-chain. Only 32 bytes secrets can be revealed.
+//!
+//! The contract is funded with a regular [`Tx<Lock>`], then spent by a [`Tx<HtlcClaim>`] or a
+//! [`Tx<HtlcRefund>`]. Both need a single signature, added with
+//! [`Witnessable::add_witness`], and the secret revealed, added with `add_secret`, before being
+//! finalized.
+//!
+//! # Security
+//!
+//! This mode is trusted and unsafe against a malicious counter-party. Unlike the adaptor
+//! signatures, nothing proves that the hash committed in
+//! [`CommitHashLock`](crate::protocol_message::CommitHashLock) is the hash of the share matching
+//! the sender's accordant public spend key: Alice can commit to the hash of a random secret,
+//! claim the arbitrating assets, and reveal a secret that cannot spend the accordant assets.
+//! The mode MUST only be used between participants trusting each other, e.g. for testing.
+//!
+//! [`DataLock`]: crate::script::DataLock
+//! [`Witnessable::add_witness`]: crate::transaction::Witnessable::add_witness
+
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Builder, Script};
+use bitcoin::blockdata::transaction::{SigHashType, TxIn, TxOut};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, Amount};
+
+use crate::script::{hash_secret, DataHashLock};
+use crate::transaction::{Error as FError, Fundable, Linkable, Transaction as _};
+
+use crate::chain::bitcoin::transaction::{Error, Lock, MetadataOutput, SubTransaction, Tx};
+use crate::chain::bitcoin::Bitcoin;
+
+/// Create the witness script of the hash time-locked output, the miniscript
+/// `c:or_i(and_v(v:sha256(H),pk_k(A)),and_v(v:older(N),and_v(v:sha256(H'),pk_k(B))))`.
+pub fn htlc_script(lock: &DataHashLock<Bitcoin>) -> Script {
+    Builder::new()
+        .push_opcode(opcodes::all::OP_IF)
+        .push_opcode(opcodes::all::OP_SIZE)
+        .push_int(32)
+        .push_opcode(opcodes::all::OP_EQUALVERIFY)
+        .push_opcode(opcodes::all::OP_SHA256)
+        .push_slice(&lock.success.hash)
+        .push_opcode(opcodes::all::OP_EQUALVERIFY)
+        .push_key(&lock.success.key)
+        .push_opcode(opcodes::all::OP_ELSE)
+        .push_int(lock.timelock.as_u32().into())
+        .push_opcode(opcodes::all::OP_CSV)
+        .push_opcode(opcodes::all::OP_VERIFY)
+        .push_opcode(opcodes::all::OP_SIZE)
+        .push_int(32)
+        .push_opcode(opcodes::all::OP_EQUALVERIFY)
+        .push_opcode(opcodes::all::OP_SHA256)
+        .push_slice(&lock.failure.hash)
+        .push_opcode(opcodes::all::OP_EQUALVERIFY)
+        .push_key(&lock.failure.key)
+        .push_opcode(opcodes::all::OP_ENDIF)
+        .push_opcode(opcodes::all::OP_CHECKSIG)
+        .into_script()
+}
+
+/// Create the arbitrating lock transaction paying the target amount to the hash time-locked
+/// contract.
+pub fn lock(
+    prev: &impl Fundable<Bitcoin, MetadataOutput>,
+    lock: &DataHashLock<Bitcoin>,
+    target_amount: Amount,
+) -> Result<Tx<Lock>, FError> {
+    let script = htlc_script(lock);
+    let output_metadata = prev.get_consumable_output()?;
+    if output_metadata.tx_out.value < target_amount.as_sat() {
+        return Err(FError::NotEnoughAssets);
+    }
+
+    let unsigned_tx = bitcoin::blockdata::transaction::Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: output_metadata.out_point,
+            script_sig: Script::default(),
+            sequence: (1 << 31) as u32, // activate disable flag on CSV
+            witness: vec![],
+        }],
+        output: vec![TxOut {
+            value: target_amount.as_sat(),
+            script_pubkey: script.to_v0_p2wsh(),
+        }],
+    };
+
+    let mut psbt =
+        PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).map_err(Error::from)?;
+    psbt.inputs[0].witness_utxo = Some(output_metadata.tx_out);
+    psbt.inputs[0].witness_script = output_metadata.script_pubkey;
+    psbt.inputs[0].sighash_type = Some(SigHashType::All);
+    psbt.outputs[0].witness_script = Some(script);
+    Ok(Tx::from_partial(psbt))
+}
+
+/// Verify that the lock transaction pays the target amount to the hash time-locked contract.
+pub fn verify_lock(
+    tx: &Tx<Lock>,
+    lock: &DataHashLock<Bitcoin>,
+    target_amount: Amount,
+) -> Result<(), FError> {
+    let unsigned_tx = &tx.as_partial().global.unsigned_tx;
+    match unsigned_tx.output.first() {
+        Some(txout)
+            if unsigned_tx.version == 2
+                && txout.value == target_amount.as_sat()
+                && txout.script_pubkey == htlc_script(lock).to_v0_p2wsh() =>
+        {
+            Ok(())
+        }
+        _ => Err(FError::WrongTemplate),
+    }
+}
+
+fn spend(
+    prev: &impl Linkable<MetadataOutput>,
+    destination: Address,
+    sequence: u32,
+) -> Result<PartiallySignedTransaction, FError> {
+    let output_metadata = prev.get_consumable_output()?;
+    match output_metadata.script_pubkey {
+        Some(ref script) if output_metadata.tx_out.script_pubkey == script.to_v0_p2wsh() => (),
+        _ => return Err(FError::WrongTemplate),
+    }
+
+    let unsigned_tx = bitcoin::blockdata::transaction::Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: output_metadata.out_point,
+            script_sig: Script::default(),
+            sequence,
+            witness: vec![],
+        }],
+        output: vec![TxOut {
+            value: output_metadata.tx_out.value,
+            script_pubkey: destination.script_pubkey(),
+        }],
+    };
+
+    let mut psbt =
+        PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).map_err(Error::from)?;
+    psbt.inputs[0].witness_utxo = Some(output_metadata.tx_out);
+    psbt.inputs[0].witness_script = output_metadata.script_pubkey;
+    psbt.inputs[0].sighash_type = Some(SigHashType::All);
+    Ok(psbt)
+}
+
+// Build the witness spending one path of the contract with its signature and secret
+fn finalize_path(psbt: &mut PartiallySignedTransaction, path: Vec<u8>) -> Result<(), FError> {
+    let script = psbt.inputs[0]
+        .witness_script
+        .clone()
+        .ok_or(FError::MissingWitness)?;
+    let (_, sig) = psbt.inputs[0]
+        .partial_sigs
+        .iter()
+        .next()
+        .ok_or(FError::MissingSignature)?;
+    let (_, secret) = psbt.inputs[0]
+        .sha256_preimages
+        .iter()
+        .next()
+        .ok_or(FError::MissingWitness)?;
+    psbt.inputs[0].final_script_witness =
+        Some(vec![sig.clone(), secret.clone(), path, script.into_bytes()]);
+    Ok(())
+}
+
+fn add_secret(psbt: &mut PartiallySignedTransaction, secret: Vec<u8>) {
+    psbt.inputs[0]
+        .sha256_preimages
+        .insert(sha256::Hash::hash(&secret), secret);
+}
+
+/// Spend the success path of the contract before or after the timelock.
+#[derive(Debug)]
+pub struct HtlcClaim;
+
+impl SubTransaction for HtlcClaim {
+    fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
+        finalize_path(psbt, vec![1]) // OP_TRUE
+    }
+}
+
+impl Tx<HtlcClaim> {
+    /// Create the transaction claiming the contract output to the destination address.
+    pub fn initialize(prev: &Tx<Lock>, destination: Address) -> Result<Self, FError> {
+        Ok(Tx::from_partial(spend(prev, destination, 0)?))
+    }
+
+    /// Add the secret of the success path, revealed on-chain when the transaction is broadcast.
+    pub fn add_secret(
+        &mut self,
+        lock: &DataHashLock<Bitcoin>,
+        secret: Vec<u8>,
+    ) -> Result<(), FError> {
+        match secret.len() == 32 && hash_secret(&secret) == lock.success.hash {
+            true => {
+                add_secret(self.as_partial_mut(), secret);
+                Ok(())
+            }
+            false => Err(FError::WrongTemplate),
+        }
+    }
+}
+
+/// Spend the failure path of the contract once the timelock is expired.
+#[derive(Debug)]
+pub struct HtlcRefund;
+
+impl SubTransaction for HtlcRefund {
+    fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
+        finalize_path(psbt, vec![]) // OP_FALSE
+    }
+}
+
+impl Tx<HtlcRefund> {
+    /// Create the transaction refunding the contract output to the destination address after
+    /// the timelock.
+    pub fn initialize(
+        prev: &Tx<Lock>,
+        lock: &DataHashLock<Bitcoin>,
+        destination: Address,
+    ) -> Result<Self, FError> {
+        Ok(Tx::from_partial(spend(
+            prev,
+            destination,
+            lock.timelock.as_u32(),
+        )?))
+    }
+
+    /// Add the secret of the failure path, revealed on-chain when the transaction is broadcast.
+    pub fn add_secret(
+        &mut self,
+        lock: &DataHashLock<Bitcoin>,
+        secret: Vec<u8>,
+    ) -> Result<(), FError> {
+        match secret.len() == 32 && hash_secret(&secret) == lock.failure.hash {
+            true => {
+                add_secret(self.as_partial_mut(), secret);
+                Ok(())
+            }
+            false => Err(FError::WrongTemplate),
+        }
+    }
+}
+
+/// Extract the secret matching the hash from the witness of a transaction spending a hash
+/// time-locked contract, if revealed.
+pub fn extract_secret(
+    tx: &bitcoin::blockdata::transaction::Transaction,
+    hash: &[u8; 32],
+) -> Option<Vec<u8>> {
+    tx.input
+        .iter()
+        .flat_map(|input| input.witness.iter())
+        .find(|item| &hash_secret(item) == hash)
+        .cloned()
+}
@@ -0,0 +1,126 @@
+//! Local Bitcoin networks used with [`Network::Local`], e.g. a regtest `bitcoind` driven by
+//! end-to-end tests.
+//!
+//! A local network can use a custom genesis block and message start (magic) bytes, and blocks are
+//! mined on demand so transactions are considered final after fewer confirmations than on public
+//! networks.
+//!
+//! [`Network::Local`]: crate::blockchain::Network::Local
+
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::BlockHash;
+
+use crate::syncer::{TaskId, WatchTransaction};
+use crate::transaction::TxLabel;
+
+/// Number of confirmations after which a transaction is considered final on public networks.
+pub const PUBLIC_NETWORK_FINALITY: u16 = 6;
+
+/// Number of confirmations after which a transaction is considered final on a regtest network.
+pub const REGTEST_FINALITY: u16 = 1;
+
+/// The parameters of a local Bitcoin network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalParams {
+    /// The message start bytes of the peer-to-peer protocol.
+    pub magic: u32,
+    /// The hash of the genesis block.
+    pub genesis_hash: BlockHash,
+    /// The number of confirmations after which a transaction is considered final.
+    pub finality: u16,
+}
+
+impl LocalParams {
+    /// Create the parameters of a default Bitcoin Core regtest network.
+    pub fn regtest() -> Self {
+        Self {
+            magic: bitcoin::Network::Regtest.magic(),
+            genesis_hash: genesis_block(bitcoin::Network::Regtest).block_hash(),
+            finality: REGTEST_FINALITY,
+        }
+    }
+
+    /// Set custom message start bytes.
+    pub fn with_magic(mut self, magic: u32) -> Self {
+        self.magic = magic;
+        self
+    }
+
+    /// Set a custom genesis block hash.
+    pub fn with_genesis_hash(mut self, genesis_hash: BlockHash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Set the number of confirmations after which a transaction is considered final.
+    pub fn with_finality(mut self, finality: u16) -> Self {
+        self.finality = finality;
+        self
+    }
+
+    /// Create a syncer task watching a transaction until it is final on the local network.
+    pub fn watch_transaction(
+        &self,
+        id: TaskId,
+        label: TxLabel,
+        lifetime: u64,
+        hash: Vec<u8>,
+    ) -> WatchTransaction {
+        WatchTransaction {
+            id,
+            label,
+            lifetime,
+            hash,
+            confirmation_bound: self.finality,
+        }
+    }
+}
+
+impl Default for LocalParams {
+    fn default() -> Self {
+        Self::regtest()
+    }
+}
+
+/// Create a deterministic transaction paying the amount to the address, as a faucet would on a
+/// local network. The transaction spends a null outpoint and can be fed to a mock syncer or used
+/// to update a funding transaction.
+#[cfg(feature = "test-utils")]
+pub fn faucet_transaction(
+    address: &bitcoin::Address,
+    amount: bitcoin::Amount,
+) -> bitcoin::Transaction {
+    bitcoin::Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![bitcoin::TxIn {
+            previous_output: bitcoin::OutPoint::default(),
+            script_sig: bitcoin::Script::default(),
+            sequence: 0xffffffff,
+            witness: vec![],
+        }],
+        output: vec![bitcoin::TxOut {
+            value: amount.as_sat(),
+            script_pubkey: address.script_pubkey(),
+        }],
+    }
+}
+
+/// Create a deterministic key pair with the byte repeated as the secret key, e.g. to build the
+/// scripts and the signatures of test transactions. Panics on the zero byte, not a valid secret
+/// key.
+#[cfg(any(test, feature = "test-utils"))]
+pub fn key_pair(byte: u8) -> (bitcoin::PublicKey, bitcoin::secp256k1::SecretKey) {
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let secret = bitcoin::secp256k1::SecretKey::from_slice(&[byte; 32]).expect("Valid secret key");
+    let pubkey = bitcoin::PublicKey::new(bitcoin::secp256k1::PublicKey::from_secret_key(
+        &secp, &secret,
+    ));
+    (pubkey, secret)
+}
+
+/// Create a deterministic public key, the public key of [`key_pair`].
+#[cfg(any(test, feature = "test-utils"))]
+pub fn key(byte: u8) -> bitcoin::PublicKey {
+    key_pair(byte).0
+}
@@ -1,25 +1,37 @@
 //! Defines and implements all the traits for Bitcoin
 
 use bitcoin::hashes::sha256d::Hash as Sha256dHash;
-use bitcoin::secp256k1::Signature;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{Message, Secp256k1, Signature};
 use bitcoin::util::key::{PrivateKey, PublicKey};
 use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::Address;
 use bitcoin::Amount;
 
-use crate::blockchain::{self, Asset, Onchain, Timelock, Transactions};
+use crate::blockchain::{self, Asset, BlockTime, Onchain, RawTransaction, Timelock, Transactions};
 use crate::consensus::{self, CanonicalBytes};
-use crate::crypto::{Keys, SharedKeyId, SharedPrivateKeys, Signatures};
-use crate::role::Arbitrating;
+use crate::crypto::{
+    self, EcdsaCapable, Keys, SharedKeyId, SharedPrivateKeys, SigHashPreimage, Signatures,
+};
 
-use transaction::{Buy, Cancel, Funding, Lock, Punish, Refund, Tx};
+use transaction::{Buy, Cancel, Funding, Lock, Punish, Refund, Sweep, Tx};
 
 use std::fmt::Debug;
 use std::str::FromStr;
+use std::time::Duration;
 
 pub mod address;
 pub mod amount;
+pub mod descriptor;
+pub mod deterrence;
+#[cfg(feature = "dual-funding")]
+pub mod dual_funding;
 pub mod fee;
+#[cfg(feature = "htlc")]
+pub mod htlc;
+pub mod local;
+pub mod params;
+pub mod policy;
 pub mod tasks;
 pub mod timelock;
 pub mod transaction;
@@ -27,7 +39,37 @@ pub mod transaction;
 #[derive(Clone, Debug, Copy, Eq, PartialEq)]
 pub struct Bitcoin;
 
-impl Arbitrating for Bitcoin {}
+impl Bitcoin {
+    /// Return the default chain parameters used on the network, see [`params::ChainParams`].
+    pub fn chain_params(network: blockchain::Network) -> params::ChainParams {
+        match network {
+            blockchain::Network::Mainnet => params::ChainParams::mainnet(),
+            blockchain::Network::Testnet => params::ChainParams::testnet(),
+            blockchain::Network::Local => params::ChainParams::regtest(),
+        }
+    }
+
+    /// Return the Bitcoin network used by default on the network. Test networks default to
+    /// testnet3, signet is used with [`params::ChainParams::signet`].
+    pub fn network(network: blockchain::Network) -> bitcoin::Network {
+        match network {
+            blockchain::Network::Mainnet => bitcoin::Network::Bitcoin,
+            blockchain::Network::Testnet => bitcoin::Network::Testnet,
+            blockchain::Network::Local => bitcoin::Network::Regtest,
+        }
+    }
+}
+
+/// Testnet3 and signet are both test networks, regtest is a local network.
+impl From<bitcoin::Network> for blockchain::Network {
+    fn from(network: bitcoin::Network) -> Self {
+        match network {
+            bitcoin::Network::Bitcoin => blockchain::Network::Mainnet,
+            bitcoin::Network::Testnet | bitcoin::Network::Signet => blockchain::Network::Testnet,
+            bitcoin::Network::Regtest => blockchain::Network::Local,
+        }
+    }
+}
 
 impl FromStr for Bitcoin {
     type Err = consensus::Error;
@@ -66,6 +108,11 @@ impl Timelock for Bitcoin {
     type Timelock = timelock::CSVTimelock;
 }
 
+impl BlockTime for Bitcoin {
+    /// Bitcoin targets ten minutes between two blocks
+    const BLOCK_TIME: Duration = Duration::from_secs(600);
+}
+
 impl Onchain for Bitcoin {
     /// Defines the transaction format used to transfer partial transaction between participant for
     /// the arbitrating blockchain
@@ -75,6 +122,17 @@ impl Onchain for Bitcoin {
     type Transaction = bitcoin::Transaction;
 }
 
+impl RawTransaction for bitcoin::Transaction {
+    fn to_raw_bytes(&self) -> Vec<u8> {
+        bitcoin::consensus::encode::serialize(self)
+    }
+
+    /// The txid in internal byte order, i.e. not reversed as displayed by block explorers.
+    fn txid(&self) -> Vec<u8> {
+        bitcoin::Transaction::txid(self).into_inner().to_vec()
+    }
+}
+
 impl Transactions for Bitcoin {
     type Metadata = transaction::MetadataOutput;
 
@@ -84,6 +142,7 @@ impl Transactions for Bitcoin {
     type Cancel = Tx<Cancel>;
     type Refund = Tx<Refund>;
     type Punish = Tx<Punish>;
+    type Sweep = Tx<Sweep>;
 }
 
 impl Keys for Bitcoin {
@@ -140,10 +199,50 @@ impl CanonicalBytes for PublicKey {
     }
 }
 
+impl EcdsaCapable for Bitcoin {}
+
 impl Signatures for Bitcoin {
+    type Engine = crypto::Ecdsa;
     type Message = Sha256dHash;
     type Signature = Signature;
     type AdaptorSignature = Signature;
+
+    fn verify_adaptor(
+        sig: &Signature,
+        msg: Sha256dHash,
+        signing_key: &PublicKey,
+        adaptor_point: &PublicKey,
+    ) -> Result<(), crypto::Error> {
+        // FIXME adaptor signatures are plain ECDSA signatures, the binding to the adaptor point
+        // cannot be verified until ECDSA adaptor signatures are implemented
+        if adaptor_point == signing_key {
+            return Err(crypto::Error::InvalidAdaptorSignature);
+        }
+        let secp = Secp256k1::verification_only();
+        let message = Message::from_slice(&msg).expect("Hash is always ok");
+        secp.verify(&message, sig, &signing_key.key)
+            .map_err(crypto::Error::new)
+    }
+}
+
+impl SigHashPreimage for Bitcoin {
+    /// The message is the double SHA-256 of the BIP-143 signing data
+    fn message_from_preimage(preimage: &[u8]) -> Sha256dHash {
+        Sha256dHash::hash(preimage)
+    }
+}
+
+impl CanonicalBytes for Sha256dHash {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        self.into_inner().into()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        Sha256dHash::from_slice(bytes).map_err(consensus::Error::new)
+    }
 }
 
 impl CanonicalBytes for Signature {
@@ -0,0 +1,207 @@
+//! Parameters of the Bitcoin chain, or of a chain derived from Bitcoin, a swap runs on.
+//!
+//! The [`ChainParams`] of the public networks are provided, parameters for signet variants, custom
+//! regtests, and forks sharing the Bitcoin consensus rules are created from them by changing the
+//! message start bytes, the genesis block, or the address format. Addresses are encoded and
+//! parsed with the human readable part and prefixes of the chain instead of the ones hardcoded in
+//! [`bitcoin::Address`].
+
+use bitcoin::bech32::{self, FromBase32, ToBase32};
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::hashes::Hash;
+use bitcoin::util::address::{Error, Payload};
+use bitcoin::util::base58;
+use bitcoin::{Address, BlockHash, PubkeyHash, ScriptHash};
+
+use crate::blockchain::Network;
+use crate::chain::bitcoin::local::{LocalParams, PUBLIC_NETWORK_FINALITY};
+use crate::syncer::{TaskId, WatchTransaction};
+use crate::transaction::TxLabel;
+
+/// The parameters identifying a Bitcoin chain and its address format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainParams {
+    /// The network the chain derives its consensus rules from, set in parsed addresses.
+    pub base: bitcoin::Network,
+    /// The message start bytes of the peer-to-peer protocol.
+    pub magic: u32,
+    /// The hash of the genesis block.
+    pub genesis_hash: BlockHash,
+    /// The human readable part of segwit addresses.
+    pub bech32_hrp: String,
+    /// The prefix of base58 pay to public key hash addresses.
+    pub p2pkh_prefix: u8,
+    /// The prefix of base58 pay to script hash addresses.
+    pub p2sh_prefix: u8,
+    /// The number of confirmations after which a transaction is considered final.
+    pub finality: u16,
+}
+
+impl ChainParams {
+    fn public(base: bitcoin::Network, hrp: &str, p2pkh_prefix: u8, p2sh_prefix: u8) -> Self {
+        Self {
+            base,
+            magic: base.magic(),
+            genesis_hash: genesis_block(base).block_hash(),
+            bech32_hrp: hrp.into(),
+            p2pkh_prefix,
+            p2sh_prefix,
+            finality: PUBLIC_NETWORK_FINALITY,
+        }
+    }
+
+    /// Create the parameters of Bitcoin mainnet.
+    pub fn mainnet() -> Self {
+        Self::public(bitcoin::Network::Bitcoin, "bc", 0x00, 0x05)
+    }
+
+    /// Create the parameters of Bitcoin testnet3.
+    pub fn testnet() -> Self {
+        Self::public(bitcoin::Network::Testnet, "tb", 0x6f, 0xc4)
+    }
+
+    /// Create the parameters of the default Bitcoin signet.
+    pub fn signet() -> Self {
+        Self::public(bitcoin::Network::Signet, "tb", 0x6f, 0xc4)
+    }
+
+    /// Create the parameters of a default Bitcoin Core regtest network.
+    pub fn regtest() -> Self {
+        Self::from(LocalParams::regtest())
+    }
+
+    /// Return the network of the swap the chain is used on, e.g. [`Network::Testnet`] for
+    /// signet chains.
+    pub fn network(&self) -> Network {
+        self.base.into()
+    }
+
+    /// Set custom message start bytes.
+    pub fn with_magic(mut self, magic: u32) -> Self {
+        self.magic = magic;
+        self
+    }
+
+    /// Set a custom genesis block hash.
+    pub fn with_genesis_hash(mut self, genesis_hash: BlockHash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Set the human readable part of segwit addresses.
+    pub fn with_bech32_hrp(mut self, hrp: impl Into<String>) -> Self {
+        self.bech32_hrp = hrp.into();
+        self
+    }
+
+    /// Set the prefixes of base58 pay to public key hash and pay to script hash addresses.
+    pub fn with_address_prefixes(mut self, p2pkh_prefix: u8, p2sh_prefix: u8) -> Self {
+        self.p2pkh_prefix = p2pkh_prefix;
+        self.p2sh_prefix = p2sh_prefix;
+        self
+    }
+
+    /// Set the number of confirmations after which a transaction is considered final.
+    pub fn with_finality(mut self, finality: u16) -> Self {
+        self.finality = finality;
+        self
+    }
+
+    /// Encode the address with the address format of the chain. Fails if the human readable part
+    /// of the chain is not valid.
+    pub fn encode_address(&self, address: &Address) -> Result<String, Error> {
+        let (prefix, hash) = match &address.payload {
+            Payload::PubkeyHash(hash) => (self.p2pkh_prefix, hash.into_inner()),
+            Payload::ScriptHash(hash) => (self.p2sh_prefix, hash.into_inner()),
+            Payload::WitnessProgram { version, program } => {
+                let mut data = vec![*version];
+                data.extend(program.to_base32());
+                return Ok(bech32::encode(&self.bech32_hrp, data)?);
+            }
+        };
+        let mut prefixed = vec![prefix];
+        prefixed.extend_from_slice(&hash);
+        Ok(base58::check_encode_slice(&prefixed))
+    }
+
+    /// Parse an address encoded with the address format of the chain, the network of the parsed
+    /// address is the base network of the chain.
+    pub fn parse_address(&self, s: &str) -> Result<Address, Error> {
+        let payload = match s.rfind('1').map(|sep| s.split_at(sep).0) {
+            Some(hrp) if hrp.eq_ignore_ascii_case(&self.bech32_hrp) => {
+                let (_, data) = bech32::decode(s)?;
+                let (version, program) = data.split_first().ok_or(Error::EmptyBech32Payload)?;
+                let program = Vec::<u8>::from_base32(program)?;
+                if version.to_u8() > 16 {
+                    return Err(Error::InvalidWitnessVersion(version.to_u8()));
+                }
+                if program.len() < 2 || program.len() > 40 {
+                    return Err(Error::InvalidWitnessProgramLength(program.len()));
+                }
+                if version.to_u8() == 0 && program.len() != 20 && program.len() != 32 {
+                    return Err(Error::InvalidSegwitV0ProgramLength(program.len()));
+                }
+                Payload::WitnessProgram {
+                    version: *version,
+                    program,
+                }
+            }
+            _ => {
+                let data = base58::from_check(s)?;
+                if data.len() != 21 {
+                    return Err(Error::Base58(base58::Error::InvalidLength(data.len())));
+                }
+                match data[0] {
+                    p if p == self.p2pkh_prefix => {
+                        Payload::PubkeyHash(PubkeyHash::from_slice(&data[1..]).expect("20 bytes"))
+                    }
+                    p if p == self.p2sh_prefix => {
+                        Payload::ScriptHash(ScriptHash::from_slice(&data[1..]).expect("20 bytes"))
+                    }
+                    p => return Err(Error::Base58(base58::Error::InvalidVersion(vec![p]))),
+                }
+            }
+        };
+        Ok(Address {
+            network: self.base,
+            payload,
+        })
+    }
+
+    /// Create a syncer task watching a transaction until it is final on the chain.
+    pub fn watch_transaction(
+        &self,
+        id: TaskId,
+        label: TxLabel,
+        lifetime: u64,
+        hash: Vec<u8>,
+    ) -> WatchTransaction {
+        WatchTransaction {
+            id,
+            label,
+            lifetime,
+            hash,
+            confirmation_bound: self.finality,
+        }
+    }
+}
+
+impl From<LocalParams> for ChainParams {
+    fn from(params: LocalParams) -> Self {
+        Self {
+            base: bitcoin::Network::Regtest,
+            magic: params.magic,
+            genesis_hash: params.genesis_hash,
+            bech32_hrp: "bcrt".into(),
+            p2pkh_prefix: 0x6f,
+            p2sh_prefix: 0xc4,
+            finality: params.finality,
+        }
+    }
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
@@ -0,0 +1,229 @@
+//! Mempool policy of the Bitcoin networks, transactions not following the policy are not relayed
+//! nor mined by most nodes even if valid.
+//!
+//! Each network has a default [`MempoolPolicy`] following the Bitcoin Core defaults, consulted
+//! when validating fees and the standardness of the swap transactions. Local networks (regtest)
+//! are often run with custom settings, their policy can be overridden to match the node.
+//!
+//! Signet is a test network too, [`MempoolPolicy::for_chain`] returns the policy of the exact
+//! Bitcoin network described by some [`ChainParams`].
+
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::Amount;
+
+use thiserror::Error;
+
+use crate::blockchain::Network;
+use crate::chain::bitcoin::params::ChainParams;
+
+/// The minimum relay fee, in satoshi per thousand virtual bytes, for all the networks.
+pub const DEFAULT_MIN_RELAY_FEE: u64 = 1_000;
+
+/// The maximum weight of a standard transaction.
+pub const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+
+/// The mempool policy of the main network.
+pub const MAINNET_POLICY: MempoolPolicy = MempoolPolicy {
+    network: Network::Mainnet,
+    min_relay_fee: DEFAULT_MIN_RELAY_FEE,
+    max_standard_weight: MAX_STANDARD_TX_WEIGHT,
+    require_standard: true,
+    rbf: RbfPolicy::Full,
+};
+
+/// The mempool policy of the test networks (testnet3).
+pub const TESTNET_POLICY: MempoolPolicy = MempoolPolicy {
+    network: Network::Testnet,
+    min_relay_fee: DEFAULT_MIN_RELAY_FEE,
+    max_standard_weight: MAX_STANDARD_TX_WEIGHT,
+    require_standard: true,
+    rbf: RbfPolicy::Full,
+};
+
+/// The mempool policy of signet, signet nodes follow the main network relay rules.
+pub const SIGNET_POLICY: MempoolPolicy = MempoolPolicy {
+    network: Network::Testnet,
+    min_relay_fee: DEFAULT_MIN_RELAY_FEE,
+    max_standard_weight: MAX_STANDARD_TX_WEIGHT,
+    require_standard: true,
+    rbf: RbfPolicy::Full,
+};
+
+/// The default mempool policy of local networks, non-standard transactions are accepted.
+pub const LOCAL_POLICY: MempoolPolicy = MempoolPolicy {
+    network: Network::Local,
+    min_relay_fee: DEFAULT_MIN_RELAY_FEE,
+    max_standard_weight: MAX_STANDARD_TX_WEIGHT,
+    require_standard: false,
+    rbf: RbfPolicy::Full,
+};
+
+/// Errors when checking a transaction against a mempool policy.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The fee rate of the transaction is below the minimum relay fee.
+    #[error("Fee rate is below the minimum relay fee")]
+    FeeBelowMinRelayFee,
+    /// The transaction is heavier than the maximum standard weight.
+    #[error("Transaction weight is above the maximum standard weight")]
+    NonStandardWeight,
+    /// Missing metadata on inputs to retreive the amount of asset available.
+    #[error("Missing metadata inputs to retreive available amount")]
+    MissingInputsMetadata,
+    /// The sum of the input or output amounts overflows.
+    #[error("Sum of the transaction amounts overflows")]
+    AmountOverflow,
+    /// The policy of a public network cannot be overridden.
+    #[error("Mempool policy can only be overridden on local networks")]
+    NotOverridable,
+}
+
+/// Defines how unconfirmed transactions can be replaced by transactions paying more fees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RbfPolicy {
+    /// Only transactions signaling replaceability (BIP 125) can be replaced.
+    OptIn,
+    /// All transactions can be replaced.
+    Full,
+}
+
+/// The relay policy applied by the nodes of a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MempoolPolicy {
+    network: Network,
+    min_relay_fee: u64,
+    max_standard_weight: u64,
+    require_standard: bool,
+    rbf: RbfPolicy,
+}
+
+impl MempoolPolicy {
+    /// Return the default policy of the network.
+    pub fn for_network(network: Network) -> Self {
+        match network {
+            Network::Mainnet => MAINNET_POLICY,
+            Network::Testnet => TESTNET_POLICY,
+            Network::Local => LOCAL_POLICY,
+        }
+    }
+
+    /// Return the default policy of the Bitcoin network the chain derives from, i.e.
+    /// [`SIGNET_POLICY`] for signet chains and [`LOCAL_POLICY`] for regtest chains.
+    pub fn for_chain(params: &ChainParams) -> Self {
+        match params.base {
+            bitcoin::Network::Bitcoin => MAINNET_POLICY,
+            bitcoin::Network::Testnet => TESTNET_POLICY,
+            bitcoin::Network::Signet => SIGNET_POLICY,
+            bitcoin::Network::Regtest => LOCAL_POLICY,
+        }
+    }
+
+    /// Return the network of the policy.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Return the minimum relay fee in satoshi per thousand virtual bytes.
+    pub fn min_relay_fee(&self) -> u64 {
+        self.min_relay_fee
+    }
+
+    /// Return the maximum weight of a standard transaction.
+    pub fn max_standard_weight(&self) -> u64 {
+        self.max_standard_weight
+    }
+
+    /// Return true if non-standard transactions are rejected.
+    pub fn require_standard(&self) -> bool {
+        self.require_standard
+    }
+
+    /// Return the replacement policy.
+    pub fn rbf(&self) -> RbfPolicy {
+        self.rbf
+    }
+
+    /// Override the minimum relay fee, in satoshi per thousand virtual bytes, e.g. to match
+    /// `-minrelaytxfee`. Only allowed on local networks.
+    pub fn with_min_relay_fee(mut self, min_relay_fee: u64) -> Result<Self, Error> {
+        self.overridable()?;
+        self.min_relay_fee = min_relay_fee;
+        Ok(self)
+    }
+
+    /// Override the standardness requirement, e.g. to match `-acceptnonstdtxn`. Only allowed on
+    /// local networks.
+    pub fn with_require_standard(mut self, require_standard: bool) -> Result<Self, Error> {
+        self.overridable()?;
+        self.require_standard = require_standard;
+        Ok(self)
+    }
+
+    /// Override the replacement policy, e.g. to match `-mempoolfullrbf`. Only allowed on local
+    /// networks.
+    pub fn with_rbf(mut self, rbf: RbfPolicy) -> Result<Self, Error> {
+        self.overridable()?;
+        self.rbf = rbf;
+        Ok(self)
+    }
+
+    fn overridable(&self) -> Result<(), Error> {
+        match self.network {
+            Network::Local => Ok(()),
+            _ => Err(Error::NotOverridable),
+        }
+    }
+
+    /// Return the minimum fee to relay a transaction of the given weight.
+    pub fn min_relay_fee_for(&self, weight: u64) -> Amount {
+        let vsize = weight.div_ceil(4);
+        Amount::from_sat(self.min_relay_fee * vsize / 1_000)
+    }
+
+    /// Check that the fee paid by the transaction is above the minimum relay fee. Inputs must
+    /// contain the previous outputs.
+    pub fn check_fee(&self, tx: &PartiallySignedTransaction) -> Result<(), Error> {
+        let mut input_sum = 0u64;
+        for input in tx.inputs.iter() {
+            let value = input
+                .witness_utxo
+                .as_ref()
+                .ok_or(Error::MissingInputsMetadata)?
+                .value;
+            // Previous outputs are provided by the counter-party and can overflow
+            input_sum = input_sum.checked_add(value).ok_or(Error::AmountOverflow)?;
+        }
+        let output_sum = tx
+            .global
+            .unsigned_tx
+            .output
+            .iter()
+            .try_fold(0u64, |sum, output| sum.checked_add(output.value))
+            .ok_or(Error::AmountOverflow)?;
+        let fee = Amount::from_sat(input_sum.saturating_sub(output_sum));
+        // FIXME This does not account for witnesses, see the fee strategy
+        let weight = tx.global.unsigned_tx.get_weight() as u64;
+        match fee >= self.min_relay_fee_for(weight) {
+            true => Ok(()),
+            false => Err(Error::FeeBelowMinRelayFee),
+        }
+    }
+
+    /// Check that the transaction is standard, if required by the policy.
+    pub fn check_standard(&self, tx: &bitcoin::Transaction) -> Result<(), Error> {
+        if self.require_standard && tx.get_weight() as u64 > self.max_standard_weight {
+            return Err(Error::NonStandardWeight);
+        }
+        Ok(())
+    }
+
+    /// Return true if the transaction, once broadcasted, can be replaced by a transaction paying
+    /// more fees.
+    pub fn is_replaceable(&self, tx: &bitcoin::Transaction) -> bool {
+        match self.rbf {
+            RbfPolicy::Full => true,
+            RbfPolicy::OptIn => tx.input.iter().any(|txin| txin.sequence < 0xfffffffe),
+        }
+    }
+}
@@ -1,7 +1,21 @@
+use crate::blockchain::BlockTime;
+use crate::chain::bitcoin::Bitcoin;
 use crate::consensus::{self, CanonicalBytes};
 
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::str::FromStr;
+use std::time::Duration;
+
+/// Flag set in a relative timelock when the value is expressed in units of 512 seconds instead of
+/// blocks, as defined in BIP 68.
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+
+/// Mask applied on a relative timelock to extract its value, as defined in BIP 68.
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
+
+/// Granularity of time-based relative timelocks, as defined in BIP 68.
+pub const SEQUENCE_LOCKTIME_GRANULARITY: u64 = 512;
 
 impl FromStr for CSVTimelock {
     type Err = consensus::Error;
@@ -14,9 +28,21 @@ impl FromStr for CSVTimelock {
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Clone, Debug, Copy)]
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
 pub struct CSVTimelock(u32);
 
+// Block-based and time-based timelocks are not comparable
+impl PartialOrd for CSVTimelock {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.is_time_based() == other.is_time_based() {
+            true => {
+                (self.0 & SEQUENCE_LOCKTIME_MASK).partial_cmp(&(other.0 & SEQUENCE_LOCKTIME_MASK))
+            }
+            false => None,
+        }
+    }
+}
+
 impl CSVTimelock {
     pub fn new(timelock: u32) -> Self {
         Self(timelock)
@@ -25,6 +51,27 @@ impl CSVTimelock {
     pub fn as_u32(&self) -> u32 {
         self.0
     }
+
+    /// Create a block-based timelock expected to expire after the given duration, rounded up to
+    /// the next block.
+    pub fn from_duration(duration: Duration) -> Self {
+        Self(Bitcoin::duration_to_blocks(duration).min(SEQUENCE_LOCKTIME_MASK))
+    }
+
+    /// Return `true` if the timelock is expressed in units of 512 seconds instead of blocks.
+    pub fn is_time_based(&self) -> bool {
+        self.0 & SEQUENCE_LOCKTIME_TYPE_FLAG != 0
+    }
+
+    /// Return the approximate wall-clock duration before the timelock expires. Block-based
+    /// timelocks are converted with the expected Bitcoin block time.
+    pub fn approx_duration(&self) -> Duration {
+        let value = self.0 & SEQUENCE_LOCKTIME_MASK;
+        match self.is_time_based() {
+            true => Duration::from_secs(value as u64 * SEQUENCE_LOCKTIME_GRANULARITY),
+            false => Bitcoin::blocks_to_duration(value),
+        }
+    }
 }
 
 impl CanonicalBytes for CSVTimelock {
@@ -41,3 +88,46 @@ impl CanonicalBytes for CSVTimelock {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::ApproxDuration;
+
+    #[test]
+    fn timelock_durations() {
+        let timelock = CSVTimelock::new(30);
+        assert!(!timelock.is_time_based());
+        assert_eq!(timelock.approx_duration(), Duration::from_secs(30 * 600));
+        assert_eq!(
+            ApproxDuration(timelock.approx_duration()).to_string(),
+            "~5 hours"
+        );
+        assert_eq!(
+            CSVTimelock::from_duration(Duration::from_secs(18000)),
+            timelock
+        );
+        assert_eq!(
+            CSVTimelock::from_duration(Duration::from_secs(17999)),
+            timelock
+        );
+
+        let timelock = CSVTimelock::new(SEQUENCE_LOCKTIME_TYPE_FLAG | 10);
+        assert!(timelock.is_time_based());
+        assert!(CSVTimelock::new(10) < CSVTimelock::new(20));
+        assert_eq!(timelock.partial_cmp(&CSVTimelock::new(20)), None);
+        assert_eq!(timelock.approx_duration(), Duration::from_secs(5120));
+        assert_eq!(
+            ApproxDuration(timelock.approx_duration()).to_string(),
+            "~1 hour"
+        );
+        assert_eq!(
+            ApproxDuration(Duration::from_secs(1)).to_string(),
+            "~1 second"
+        );
+        assert_eq!(
+            ApproxDuration(Duration::from_secs(172800)).to_string(),
+            "~2 days"
+        );
+    }
+}
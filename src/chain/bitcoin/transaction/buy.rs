@@ -1,17 +1,58 @@
 use std::marker::PhantomData;
 
-use bitcoin::blockdata::script::Instruction;
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Builder, Instruction, Script};
 use bitcoin::blockdata::transaction::{SigHashType, TxIn, TxOut};
 use bitcoin::util::key::PublicKey;
 use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::Address;
 
+use crate::negotiation::{Version, FEATURE_ANCHOR};
 use crate::script;
 use crate::transaction::{Buyable, Error as FError, Lockable};
 
-use crate::chain::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
+use crate::chain::bitcoin::transaction::lock::lock_script;
+use crate::chain::bitcoin::transaction::{
+    malleability, ordering, Error, MetadataOutput, SubTransaction, Tx,
+};
 use crate::chain::bitcoin::Bitcoin;
 
+/// The value of an anchor output, the dust limit of a P2WSH output.
+pub const ANCHOR_VALUE: u64 = 330;
+
+/// An anchor output attached to the buy transaction so its fee can be bumped with CPFP after
+/// broadcast. Anchors are optional and negotiated in the public offer with [`FEATURE_ANCHOR`], a
+/// keyed anchor must be spendable by one of the buy keys of the lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    /// Anyone can spend the anchor, the witness script is `OP_TRUE`.
+    OpTrue,
+    /// Only the owner of the key can spend the anchor, the witness script is `<key>
+    /// OP_CHECKSIG`.
+    Keyed(PublicKey),
+}
+
+impl Anchor {
+    /// Return the witness script spending the anchor.
+    pub fn witness_script(&self) -> Script {
+        match self {
+            Anchor::OpTrue => Builder::new().push_opcode(opcodes::OP_TRUE).into_script(),
+            Anchor::Keyed(key) => Builder::new()
+                .push_key(key)
+                .push_opcode(opcodes::all::OP_CHECKSIG)
+                .into_script(),
+        }
+    }
+
+    /// Return the anchor output.
+    pub fn tx_out(&self) -> TxOut {
+        TxOut {
+            value: ANCHOR_VALUE,
+            script_pubkey: self.witness_script().to_v0_p2wsh(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Buy;
 
@@ -105,10 +146,180 @@ impl Buyable<Bitcoin, MetadataOutput> for Tx<Buy> {
 
     fn verify_template(
         &self,
-        _lock: script::DataLock<Bitcoin>,
-        _destination_target: Address,
+        lock: script::DataLock<Bitcoin>,
+        destination_target: Address,
+    ) -> Result<(), FError> {
+        self.verify_template_with_version(lock, destination_target, &Version::new_v1())
+    }
+}
+
+impl Tx<Buy> {
+    /// Attach an anchor output to the buy transaction, the anchor value is deducted from the
+    /// destination output. The anchor must be attached before setting the fee.
+    pub fn with_anchor(mut self, anchor: &Anchor) -> Result<Self, FError> {
+        let tx = &mut self.psbt.global.unsigned_tx;
+        if tx.output.len() != 1 {
+            return Err(FError::WrongTemplate);
+        }
+        tx.output[0].value = tx.output[0]
+            .value
+            .checked_sub(ANCHOR_VALUE)
+            .ok_or(FError::NotEnoughAssets)?;
+        tx.output.push(anchor.tx_out());
+        self.psbt.outputs.push(Default::default());
+        // The destination output pays the fee and is anchored first
+        ordering::sort_partial_transaction(&mut self.psbt, 1, 1);
+        Ok(self)
+    }
+
+    /// Return the anchor output of the transaction, if any.
+    pub fn anchor_output(&self) -> Option<&TxOut> {
+        self.psbt.global.unsigned_tx.output.get(1)
+    }
+
+    /// Verifies the template of the buy transaction against the version of the public offer,
+    /// the extra anchor output is required if and only if the offer activates [`FEATURE_ANCHOR`].
+    /// The anchor must be an [`Anchor::OpTrue`] or an [`Anchor::Keyed`] with one of the buy keys
+    /// of the lock, and the input must spend the lock output.
+    pub fn verify_template_with_version(
+        &self,
+        lock: script::DataLock<Bitcoin>,
+        destination_target: Address,
+        version: &Version,
     ) -> Result<(), FError> {
-        // FIXME
+        let tx = &self.psbt.global.unsigned_tx;
+        let anchored = version.has_feature(FEATURE_ANCHOR);
+        let expected_outputs = if anchored { 2 } else { 1 };
+        if tx.version != 2
+            || tx.lock_time != 0
+            || tx.input.len() != 1
+            || tx.output.len() != expected_outputs
+            || tx.output[0].script_pubkey != destination_target.script_pubkey()
+        {
+            return Err(FError::WrongTemplate);
+        }
+        malleability::verify_segwit_inputs(&self.psbt)?;
+        ordering::verify_order(tx, 1, 1)?;
+
+        let script = lock_script(&lock);
+        let input = &self.psbt.inputs[0];
+        match &input.witness_utxo {
+            Some(txout) if txout.script_pubkey == script.to_v0_p2wsh() => (),
+            _ => return Err(FError::WrongTemplate),
+        }
+        if input.witness_script.as_ref() != Some(&script) {
+            return Err(FError::WrongTemplate);
+        }
+
+        if anchored {
+            let anchors = [
+                Anchor::OpTrue,
+                Anchor::Keyed(lock.success.alice),
+                Anchor::Keyed(lock.success.bob),
+            ];
+            if !anchors.iter().any(|anchor| tx.output[1] == anchor.tx_out()) {
+                return Err(FError::WrongTemplate);
+            }
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::bitcoin::local::key;
+    use crate::chain::bitcoin::timelock::CSVTimelock;
+    use crate::script::{DataLock, DoubleKeys};
+    use crate::transaction::Transaction;
+    use bitcoin::blockdata::transaction::OutPoint;
+    use bitcoin::Network;
+
+    fn buy(lock: &DataLock<Bitcoin>, destination: &Address) -> Tx<Buy> {
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 0,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: 10_000,
+                script_pubkey: destination.script_pubkey(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        // The buy spends the segwit lock output
+        let script = lock_script(lock);
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 11_000,
+            script_pubkey: script.to_v0_p2wsh(),
+        });
+        psbt.inputs[0].witness_script = Some(script);
+        Tx::from_partial(psbt)
+    }
+
+    #[test]
+    fn accept_anchor_only_if_negotiated() {
+        let lock = DataLock {
+            timelock: CSVTimelock::new(10),
+            success: DoubleKeys::new(key(1), key(2)),
+            failure: DoubleKeys::new(key(3), key(4)),
+        };
+        let destination = Address::p2wpkh(&key(5), Network::Bitcoin).unwrap();
+        let negotiated = Version::new_v1().with_feature(FEATURE_ANCHOR);
+
+        let plain = buy(&lock, &destination);
+        assert!(plain
+            .verify_template(lock.clone(), destination.clone())
+            .is_ok());
+        assert!(plain.anchor_output().is_none());
+        // MUST error if the negotiated anchor is missing
+        assert!(matches!(
+            plain.verify_template_with_version(lock.clone(), destination.clone(), &negotiated),
+            Err(FError::WrongTemplate)
+        ));
+
+        let anchor = Anchor::Keyed(key(2));
+        let anchored = buy(&lock, &destination).with_anchor(&anchor).unwrap();
+        assert_eq!(anchored.anchor_output(), Some(&anchor.tx_out()));
+        assert_eq!(
+            anchored.as_partial().global.unsigned_tx.output[0].value,
+            10_000 - ANCHOR_VALUE
+        );
+        assert!(anchored
+            .verify_template_with_version(lock.clone(), destination.clone(), &negotiated)
+            .is_ok());
+        let anyone = buy(&lock, &destination)
+            .with_anchor(&Anchor::OpTrue)
+            .unwrap();
+        assert!(anyone
+            .verify_template_with_version(lock.clone(), destination.clone(), &negotiated)
+            .is_ok());
+
+        // MUST error if the anchor has not been negotiated or is not keyed with a buy key
+        assert!(matches!(
+            anchored.verify_template(lock.clone(), destination.clone()),
+            Err(FError::WrongTemplate)
+        ));
+        let foreign = buy(&lock, &destination)
+            .with_anchor(&Anchor::Keyed(key(6)))
+            .unwrap();
+        assert!(matches!(
+            foreign.verify_template_with_version(lock.clone(), destination.clone(), &negotiated),
+            Err(FError::WrongTemplate)
+        ));
+
+        // MUST error if the input does not spend the lock output
+        let other = DataLock {
+            timelock: CSVTimelock::new(20),
+            ..lock.clone()
+        };
+        assert!(matches!(
+            plain.verify_template(other, destination),
+            Err(FError::WrongTemplate)
+        ));
+    }
+}
@@ -1,8 +1,8 @@
 use std::marker::PhantomData;
 
 use bitcoin::blockdata::opcodes;
-use bitcoin::blockdata::script::Builder;
 use bitcoin::blockdata::script::Instruction;
+use bitcoin::blockdata::script::{Builder, Script};
 use bitcoin::blockdata::transaction::{SigHashType, TxIn, TxOut};
 use bitcoin::util::key::PublicKey;
 use bitcoin::util::psbt::PartiallySignedTransaction;
@@ -10,12 +10,32 @@ use bitcoin::util::psbt::PartiallySignedTransaction;
 use crate::script;
 use crate::transaction::{Cancelable, Error as FError, Lockable};
 
-use crate::chain::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
+use crate::chain::bitcoin::transaction::{malleability, Error, MetadataOutput, SubTransaction, Tx};
 use crate::chain::bitcoin::Bitcoin;
 
 #[derive(Debug)]
 pub struct Cancel;
 
+/// Create the witness script of the cancel output with the refund and punish paths, the
+/// miniscript `or_i(multi(2,A,B),and_v(v:older(N),pk(C)))`.
+pub(crate) fn punish_lock_script(lock: &script::DataPunishableLock<Bitcoin>) -> Script {
+    Builder::new()
+        .push_opcode(opcodes::all::OP_IF)
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_key(&lock.success.alice)
+        .push_key(&lock.success.bob)
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+        .push_opcode(opcodes::all::OP_ELSE)
+        .push_int(lock.timelock.as_u32().into())
+        .push_opcode(opcodes::all::OP_CSV)
+        .push_opcode(opcodes::all::OP_VERIFY)
+        .push_key(&lock.failure)
+        .push_opcode(opcodes::all::OP_CHECKSIG)
+        .push_opcode(opcodes::all::OP_ENDIF)
+        .into_script()
+}
+
 impl SubTransaction for Cancel {
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
         let script = psbt.inputs[0]
@@ -73,21 +93,7 @@ impl Cancelable<Bitcoin, MetadataOutput> for Tx<Cancel> {
         lock: script::DataLock<Bitcoin>,
         punish_lock: script::DataPunishableLock<Bitcoin>,
     ) -> Result<Self, FError> {
-        let script = Builder::new()
-            .push_opcode(opcodes::all::OP_IF)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_key(&punish_lock.success.alice)
-            .push_key(&punish_lock.success.bob)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
-            .push_opcode(opcodes::all::OP_ELSE)
-            .push_int(punish_lock.timelock.as_u32().into())
-            .push_opcode(opcodes::all::OP_CSV)
-            .push_opcode(opcodes::all::OP_DROP)
-            .push_key(&punish_lock.failure)
-            .push_opcode(opcodes::all::OP_CHECKSIG)
-            .push_opcode(opcodes::all::OP_ENDIF)
-            .into_script();
+        let script = punish_lock_script(&punish_lock);
 
         let output_metadata = prev.get_consumable_output()?;
 
@@ -128,7 +134,8 @@ impl Cancelable<Bitcoin, MetadataOutput> for Tx<Cancel> {
         _lock: script::DataLock<Bitcoin>,
         _punish_lock: script::DataPunishableLock<Bitcoin>,
     ) -> Result<(), FError> {
-        // FIXME
+        // FIXME verify the outputs of the template
+        malleability::verify_segwit_inputs(&self.psbt)?;
         Ok(())
     }
 }
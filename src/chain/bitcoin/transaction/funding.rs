@@ -1,7 +1,6 @@
 use bitcoin::blockdata::transaction::{OutPoint, Transaction};
-use bitcoin::network::constants::Network as BtcNetwork;
 use bitcoin::util::key::PublicKey;
-use bitcoin::Address;
+use bitcoin::{Address, Amount};
 
 use crate::blockchain::Network;
 use crate::transaction::{Error as FError, Fundable, Linkable};
@@ -16,37 +15,49 @@ pub struct Funding {
     seen_tx: Option<Transaction>,
 }
 
+impl Funding {
+    /// Detect the output paying to the funding address in the transaction seen on-chain, fails if
+    /// no output pays to it. If the funding address is unknown, i.e. the structure was created
+    /// with [`Fundable::raw`], only single output transactions, or coinbase transactions with two
+    /// outputs, are supported and the first output is used.
+    fn find_vout(&self, tx: &Transaction) -> Result<u32, FError> {
+        if self.pubkey.is_some() && self.network.is_some() {
+            let script_pubkey = self.get_address()?.script_pubkey();
+            return tx
+                .output
+                .iter()
+                .position(|txout| txout.script_pubkey == script_pubkey)
+                .map(|vout| vout as u32)
+                .ok_or_else(|| FError::new(Error::MissingFundingOutput));
+        }
+
+        // More than one UTXO is not supported
+        match tx.output.len() {
+            1 => Ok(0),
+            // Check if coinbase transaction
+            2 if tx.is_coin_base() => Ok(0),
+            _ => Err(FError::new(Error::MultiUTXOUnsuported)),
+        }
+    }
+}
+
 impl Linkable<MetadataOutput> for Funding {
     fn get_consumable_output(&self) -> Result<MetadataOutput, FError> {
         match &self.seen_tx {
             Some(t) => {
-                // More than one UTXO is not supported
-                match t.output.len() {
-                    1 => (),
-                    2 =>
-                    // Check if coinbase transaction
-                    {
-                        if !t.is_coin_base() {
-                            return Err(FError::new(Error::MultiUTXOUnsuported));
-                        }
-                    }
-                    _ => return Err(FError::new(Error::MultiUTXOUnsuported)),
-                }
+                let vout = self.find_vout(t)?;
 
                 let pubkey = match self.pubkey {
                     Some(pubkey) => Ok(pubkey),
                     None => Err(FError::MissingPublicKey),
                 }?;
 
-                // vout is always 0 because output len is 1
                 Ok(MetadataOutput {
-                    out_point: OutPoint::new(t.txid(), 0),
-                    tx_out: t.output[0].clone(),
+                    out_point: OutPoint::new(t.txid(), vout),
+                    tx_out: t.output[vout as usize].clone(),
                     script_pubkey: Some(
                         match self.network {
-                            Some(Network::Mainnet) => Address::p2pkh(&pubkey, BtcNetwork::Bitcoin),
-                            Some(Network::Testnet) => Address::p2pkh(&pubkey, BtcNetwork::Testnet),
-                            Some(Network::Local) => Address::p2pkh(&pubkey, BtcNetwork::Regtest),
+                            Some(network) => Address::p2pkh(&pubkey, Bitcoin::network(network)),
                             None => Err(FError::MissingNetwork)?,
                         }
                         .script_pubkey(),
@@ -75,14 +86,8 @@ impl Fundable<Bitcoin, MetadataOutput> for Funding {
         }?;
 
         match self.network {
-            Some(Network::Mainnet) => {
-                Ok(Address::p2wpkh(&pubkey, BtcNetwork::Bitcoin).map_err(Error::from)?)
-            }
-            Some(Network::Testnet) => {
-                Ok(Address::p2wpkh(&pubkey, BtcNetwork::Testnet).map_err(Error::from)?)
-            }
-            Some(Network::Local) => {
-                Ok(Address::p2wpkh(&pubkey, BtcNetwork::Regtest).map_err(Error::from)?)
+            Some(network) => {
+                Ok(Address::p2wpkh(&pubkey, Bitcoin::network(network)).map_err(Error::from)?)
             }
             None => Err(FError::MissingNetwork),
         }
@@ -100,4 +105,14 @@ impl Fundable<Bitcoin, MetadataOutput> for Funding {
             seen_tx: Some(tx),
         })
     }
+
+    fn get_amount(&self) -> Result<Amount, FError> {
+        match &self.seen_tx {
+            Some(t) => {
+                let vout = self.find_vout(t)?;
+                Ok(Amount::from_sat(t.output[vout as usize].value))
+            }
+            None => Err(FError::MissingOnchainTransaction),
+        }
+    }
 }
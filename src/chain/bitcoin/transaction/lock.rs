@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use bitcoin::blockdata::opcodes;
-use bitcoin::blockdata::script::Builder;
+use bitcoin::blockdata::script::{Builder, Script};
 use bitcoin::blockdata::transaction::{SigHashType, TxIn, TxOut};
 use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::Amount;
@@ -9,12 +9,35 @@ use bitcoin::Amount;
 use crate::script;
 use crate::transaction::{Error as FError, Fundable, Lockable};
 
-use crate::chain::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
+use crate::chain::bitcoin::transaction::{malleability, Error, MetadataOutput, SubTransaction, Tx};
 use crate::chain::bitcoin::Bitcoin;
 
 #[derive(Debug)]
 pub struct Lock;
 
+/// Create the witness script of the lock output with the success and failure paths, the
+/// miniscript `or_i(multi(2,A,B),and_v(v:older(N),multi(2,C,D)))`.
+pub(crate) fn lock_script(lock: &script::DataLock<Bitcoin>) -> Script {
+    Builder::new()
+        .push_opcode(opcodes::all::OP_IF)
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_key(&lock.success.alice)
+        .push_key(&lock.success.bob)
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+        .push_opcode(opcodes::all::OP_ELSE)
+        .push_int(lock.timelock.as_u32().into())
+        .push_opcode(opcodes::all::OP_CSV)
+        .push_opcode(opcodes::all::OP_VERIFY)
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_key(&lock.failure.alice)
+        .push_key(&lock.failure.bob)
+        .push_opcode(opcodes::all::OP_PUSHNUM_2)
+        .push_opcode(opcodes::all::OP_CHECKMULTISIG)
+        .push_opcode(opcodes::all::OP_ENDIF)
+        .into_script()
+}
+
 impl SubTransaction for Lock {
     fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
         let (pubkey, full_sig) = psbt.inputs[0]
@@ -33,30 +56,12 @@ impl Lockable<Bitcoin, MetadataOutput> for Tx<Lock> {
         lock: script::DataLock<Bitcoin>,
         target_amount: Amount,
     ) -> Result<Self, FError> {
-        let script = Builder::new()
-            .push_opcode(opcodes::all::OP_IF)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_key(&lock.success.alice)
-            .push_key(&lock.success.bob)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
-            .push_opcode(opcodes::all::OP_ELSE)
-            .push_int(lock.timelock.as_u32().into())
-            .push_opcode(opcodes::all::OP_CSV)
-            .push_opcode(opcodes::all::OP_DROP)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_key(&lock.failure.alice)
-            .push_key(&lock.failure.bob)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
-            .push_opcode(opcodes::all::OP_ENDIF)
-            .into_script();
+        let script = lock_script(&lock);
 
         let output_metadata = prev.get_consumable_output()?;
 
-        match output_metadata.tx_out.value < target_amount.as_sat() {
-            true => Err(FError::NotEnoughAssets)?,
-            false => (),
+        if output_metadata.tx_out.value < target_amount.as_sat() {
+            return Err(FError::NotEnoughAssets);
         }
 
         let unsigned_tx = bitcoin::blockdata::transaction::Transaction {
@@ -82,6 +87,10 @@ impl Lockable<Bitcoin, MetadataOutput> for Tx<Lock> {
         psbt.inputs[0].witness_script = output_metadata.script_pubkey;
         psbt.inputs[0].sighash_type = Some(SigHashType::All);
 
+        // The funding output MUST be a segwit output, the cancel and refund transactions are
+        // signed on top of the lock transaction identifier
+        malleability::verify_segwit_inputs(&psbt)?;
+
         // Set the script witness of the output
         psbt.outputs[0].witness_script = Some(script);
 
@@ -97,46 +106,30 @@ impl Lockable<Bitcoin, MetadataOutput> for Tx<Lock> {
 
     fn verify_template(&self, lock: script::DataLock<Bitcoin>) -> Result<(), FError> {
         (self.psbt.global.unsigned_tx.version == 2)
-            .then(|| 0)
+            .then_some(0)
             .ok_or_else(|| FError::WrongTemplate)?;
         (self.psbt.global.unsigned_tx.lock_time == 0)
-            .then(|| 0)
+            .then_some(0)
             .ok_or_else(|| FError::WrongTemplate)?;
         (self.psbt.global.unsigned_tx.input.len() == 1)
-            .then(|| 0)
+            .then_some(0)
             .ok_or_else(|| FError::WrongTemplate)?;
         (self.psbt.global.unsigned_tx.output.len() == 1)
-            .then(|| 0)
+            .then_some(0)
             .ok_or_else(|| FError::WrongTemplate)?;
 
         let txin = &self.psbt.global.unsigned_tx.input[0];
         (txin.sequence == (1 << 31) as u32)
-            .then(|| 0)
+            .then_some(0)
             .ok_or_else(|| FError::WrongTemplate)?;
 
         let txout = &self.psbt.global.unsigned_tx.output[0];
-        let script = Builder::new()
-            .push_opcode(opcodes::all::OP_IF)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_key(&lock.success.alice)
-            .push_key(&lock.success.bob)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
-            .push_opcode(opcodes::all::OP_ELSE)
-            .push_int(lock.timelock.as_u32().into())
-            .push_opcode(opcodes::all::OP_CSV)
-            .push_opcode(opcodes::all::OP_DROP)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_key(&lock.failure.alice)
-            .push_key(&lock.failure.bob)
-            .push_opcode(opcodes::all::OP_PUSHNUM_2)
-            .push_opcode(opcodes::all::OP_CHECKMULTISIG)
-            .push_opcode(opcodes::all::OP_ENDIF)
-            .into_script();
+        let script = lock_script(&lock);
         (txout.script_pubkey == script.to_v0_p2wsh())
-            .then(|| 0)
+            .then_some(0)
             .ok_or_else(|| FError::WrongTemplate)?;
 
+        malleability::verify_segwit_inputs(&self.psbt)?;
         Ok(())
     }
 }
@@ -0,0 +1,123 @@
+//! Protection of the arbitrating transactions against malleability.
+//!
+//! The cancel, refund, and punish transactions are signed before the lock is broadcast and spend
+//! outputs identified by the transaction identifier of their parent. The identifier commits to the
+//! signature scripts of the inputs: anyone relaying a transaction spending a legacy output can
+//! change its signature script, e.g. by re-encoding a signature, and with it the identifier, so
+//! the pre-signed children no longer spend the confirmed parent. Witnesses are not committed in
+//! the identifier, a transaction spending only segwit outputs, natively or nested in P2SH, cannot
+//! be malleated by a third-party.
+//!
+//! Templates are built and verified with segwit inputs only, and the outputs of a transaction are
+//! only consumed by a dependent transaction if its identifier is not malleable, see
+//! [`non_malleable_txid`].
+
+use bitcoin::util::psbt::{self, PartiallySignedTransaction};
+use bitcoin::Txid;
+
+use crate::chain::bitcoin::transaction::Error;
+
+/// Return true if the input spends a segwit output, natively or nested in P2SH. The previous
+/// output MUST be set in the input witness UTXO, legacy inputs only carrying the full previous
+/// transaction are not segwit inputs.
+pub fn spends_segwit(input: &psbt::Input) -> bool {
+    let prevout = match &input.witness_utxo {
+        Some(prevout) => prevout,
+        None => return false,
+    };
+    match &input.redeem_script {
+        _ if prevout.script_pubkey.is_witness_program() => true,
+        Some(redeem) => redeem.is_witness_program() && redeem.to_p2sh() == prevout.script_pubkey,
+        None => false,
+    }
+}
+
+/// Verify that all the inputs of the partial transaction spend segwit outputs, see
+/// [`spends_segwit`].
+pub fn verify_segwit_inputs(psbt: &PartiallySignedTransaction) -> Result<(), Error> {
+    match psbt.inputs.iter().all(spends_segwit) {
+        true => Ok(()),
+        false => Err(Error::LegacyInput),
+    }
+}
+
+/// Return the transaction identifier of the partial transaction, fails if the identifier can be
+/// malleated, i.e. if an input does not spend a segwit output.
+pub fn non_malleable_txid(psbt: &PartiallySignedTransaction) -> Result<Txid, Error> {
+    verify_segwit_inputs(psbt)?;
+    Ok(psbt.global.unsigned_tx.txid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::blockdata::opcodes;
+    use bitcoin::blockdata::script::{Builder, Script};
+    use bitcoin::blockdata::transaction::{OutPoint, TxIn, TxOut};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{PubkeyHash, Transaction, WPubkeyHash};
+
+    fn psbt(script_pubkey: Script, redeem_script: Option<Script>) -> PartiallySignedTransaction {
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 0xffffffff,
+                witness: vec![],
+            }],
+            output: vec![],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 1_000,
+            script_pubkey,
+        });
+        psbt.inputs[0].redeem_script = redeem_script;
+        psbt
+    }
+
+    #[test]
+    fn reject_malleable_inputs() {
+        let p2wpkh = Script::new_v0_wpkh(&WPubkeyHash::hash(&[0x02; 33]));
+        let multisig = Builder::new()
+            .push_opcode(opcodes::all::OP_PUSHNUM_1)
+            .into_script();
+
+        // Native and nested segwit inputs are not malleable
+        let native = psbt(p2wpkh.clone(), None);
+        assert!(verify_segwit_inputs(&native).is_ok());
+        assert_eq!(
+            non_malleable_txid(&native).unwrap(),
+            native.global.unsigned_tx.txid()
+        );
+        let p2wsh = psbt(multisig.to_v0_p2wsh(), None);
+        assert!(verify_segwit_inputs(&p2wsh).is_ok());
+        let nested = psbt(p2wpkh.to_p2sh(), Some(p2wpkh.clone()));
+        assert!(verify_segwit_inputs(&nested).is_ok());
+
+        // Legacy inputs are malleable
+        let p2pkh = psbt(Script::new_p2pkh(&PubkeyHash::hash(&[0x02; 33])), None);
+        assert!(matches!(
+            non_malleable_txid(&p2pkh),
+            Err(Error::LegacyInput)
+        ));
+        let p2sh = psbt(multisig.to_p2sh(), Some(multisig));
+        assert!(matches!(
+            verify_segwit_inputs(&p2sh),
+            Err(Error::LegacyInput)
+        ));
+        // The redeem script MUST be the one committed in the previous output
+        let wrong_redeem = psbt(
+            p2sh.inputs[0].witness_utxo.clone().unwrap().script_pubkey,
+            Some(p2wpkh),
+        );
+        assert!(!spends_segwit(&wrong_redeem.inputs[0]));
+        // Inputs only carrying the previous transaction are legacy inputs
+        let mut non_witness = native;
+        non_witness.inputs[0].witness_utxo = None;
+        non_witness.inputs[0].non_witness_utxo = Some(non_witness.global.unsigned_tx.clone());
+        assert!(!spends_segwit(&non_witness.inputs[0]));
+    }
+}
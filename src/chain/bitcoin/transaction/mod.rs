@@ -25,17 +25,22 @@ pub mod buy;
 pub mod cancel;
 pub mod funding;
 pub mod lock;
+pub mod malleability;
+pub mod ordering;
 pub mod punish;
 pub mod refund;
+pub mod sweep;
 
-pub use buy::Buy;
+pub use buy::{Anchor, Buy};
 pub use cancel::Cancel;
 pub use funding::Funding;
 pub use lock::Lock;
 pub use punish::Punish;
 pub use refund::Refund;
+pub use sweep::Sweep;
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Multi-input transaction is not supported
     #[error("Multi-input transaction is not supported")]
@@ -55,6 +60,27 @@ pub enum Error {
     /// Bitcoin script error
     #[error("Bitcoin script error: `{0}`")]
     BitcoinScript(#[from] bitcoin::blockdata::script::Error),
+    /// The partially signed transaction does not match the expected transaction
+    #[error("Partially signed transaction does not match the expected transaction")]
+    UnexpectedTransaction,
+    /// The previous output spent by an input is missing
+    #[error("Previous output of an input is missing")]
+    MissingPreviousOutput,
+    /// A script does not match the output it is attached to
+    #[error("Script does not match its output")]
+    UnexpectedScript,
+    /// The SigHash type is not supported
+    #[error("SigHash type is not supported")]
+    UnsupportedSigHashType,
+    /// The inputs or outputs are not in the deterministic order
+    #[error("Inputs or outputs are not in the deterministic order")]
+    UnorderedTransaction,
+    /// An input does not spend a segwit output, the transaction identifier is malleable
+    #[error("Input does not spend a segwit output, the transaction identifier is malleable")]
+    LegacyInput,
+    /// No output of the funding transaction pays to the funding address
+    #[error("No output pays to the funding address")]
+    MissingFundingOutput,
 }
 
 impl From<Error> for FError {
@@ -105,9 +131,7 @@ where
 
     fn based_on(&self) -> MetadataOutput {
         MetadataOutput {
-            out_point: self.psbt.global.unsigned_tx.input[0]
-                .previous_output
-                .clone(),
+            out_point: self.psbt.global.unsigned_tx.input[0].previous_output,
             tx_out: self.psbt.inputs[0].witness_utxo.clone().unwrap(), // FIXME
             script_pubkey: self.psbt.inputs[0].witness_script.clone(),
         }
@@ -143,47 +167,62 @@ where
     fn get_consumable_output(&self) -> Result<MetadataOutput, FError> {
         match self.psbt.global.unsigned_tx.output.len() {
             1 => (),
-            2 => {
-                if !self.psbt.global.unsigned_tx.is_coin_base() {
-                    return Err(FError::new(Error::MultiUTXOUnsuported));
-                }
-            }
+            2 if self.psbt.global.unsigned_tx.is_coin_base() => (),
+            // The first output is a protocol script output followed by change outputs, e.g. a
+            // dual-funded lock
+            _ if self.psbt.outputs[0].witness_script.is_some() => (),
             _ => return Err(FError::new(Error::MultiUTXOUnsuported)),
         }
 
         Ok(MetadataOutput {
-            out_point: OutPoint::new(self.psbt.global.unsigned_tx.txid(), 0),
+            out_point: OutPoint::new(malleability::non_malleable_txid(&self.psbt)?, 0),
             tx_out: self.psbt.global.unsigned_tx.output[0].clone(),
             script_pubkey: self.psbt.outputs[0].witness_script.clone(),
         })
     }
 }
 
+impl<T> Tx<T>
+where
+    T: SubTransaction,
+{
+    // Return the script, value, and SigHash type of the first input
+    fn signing_data(&self) -> Result<(Script, u64, SigHashType), FError> {
+        let input = &self.psbt.inputs[0];
+        let witness_utxo = input.witness_utxo.as_ref().ok_or(FError::MissingWitness)?;
+        let script = input.witness_script.clone().ok_or(FError::MissingWitness)?;
+        let sighash_type = input
+            .sighash_type
+            .ok_or(FError::new(Error::MissingSigHashType))?;
+        Ok((script, witness_utxo.value, sighash_type))
+    }
+}
+
 impl<T> Witnessable<Bitcoin> for Tx<T>
 where
     T: SubTransaction,
 {
     // FIXME this assume only one input
     fn generate_witness_message(&self, _path: ScriptPath) -> Result<Hash, FError> {
-        let unsigned_tx = self.psbt.global.unsigned_tx.clone();
-        let txin = TxInRef::new(&unsigned_tx, 0);
-
-        let witness_utxo = self.psbt.inputs[0]
-            .witness_utxo
-            .clone()
-            .ok_or(FError::MissingWitness)?;
-
-        let script = self.psbt.inputs[0]
-            .witness_script
-            .clone()
-            .ok_or(FError::MissingWitness)?;
-        let value = witness_utxo.value;
-
-        let sighash_type = self.psbt.inputs[0]
-            .sighash_type
-            .ok_or(FError::new(Error::MissingSigHashType))?;
+        let unsigned_tx = &self.psbt.global.unsigned_tx;
+        let (script, value, sighash_type) = self.signing_data()?;
+        Ok(signature_hash(
+            TxInRef::new(unsigned_tx, 0),
+            &script,
+            value,
+            sighash_type,
+        ))
+    }
 
-        Ok(signature_hash(txin, &script, value, sighash_type))
+    fn generate_witness_preimage(&self, _path: ScriptPath) -> Result<Vec<u8>, FError> {
+        let unsigned_tx = &self.psbt.global.unsigned_tx;
+        let (script, value, sighash_type) = self.signing_data()?;
+        Ok(signature_hash_preimage(
+            TxInRef::new(unsigned_tx, 0),
+            &script,
+            value,
+            sighash_type,
+        ))
     }
 
     fn add_witness(&mut self, pubkey: PublicKey, sig: Signature) -> Result<(), FError> {
@@ -262,6 +301,108 @@ impl CanonicalBytes for PartiallySignedTransaction {
     }
 }
 
+/// Sanitize a partially signed transaction received from a counter-party before signing any of
+/// its inputs, returns a new partially signed transaction containing only the fields needed to
+/// sign and finalize the expected transaction.
+///
+/// The unsigned transaction MUST be the expected one, rebuilt from the negotiated parameters, so
+/// no input, output, or script can be added by the counter-party. Inputs MUST contain their
+/// previous output, spend a segwit output, see [`malleability::spends_segwit`], and use
+/// `SIGHASH_ALL` if a SigHash type is set, witness and redeem scripts
+/// MUST match the output they are attached to. Signatures are kept, all other fields, e.g. key
+/// derivations, hash preimages, final scripts, or proprietary and unknown fields, are stripped as
+/// they can be used to trick the signer into signing more than expected or to exfiltrate data.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, err)
+)]
+pub fn sanitize_partial_transaction(
+    psbt: &PartiallySignedTransaction,
+    expected: &bitcoin::Transaction,
+) -> Result<PartiallySignedTransaction, Error> {
+    let unsigned_tx = &psbt.global.unsigned_tx;
+    if unsigned_tx != expected
+        || psbt.inputs.len() != unsigned_tx.input.len()
+        || psbt.outputs.len() != unsigned_tx.output.len()
+    {
+        return Err(Error::UnexpectedTransaction);
+    }
+    let mut sanitized = PartiallySignedTransaction::from_unsigned_tx(expected.clone())?;
+
+    for ((input, txin), sanitized) in psbt
+        .inputs
+        .iter()
+        .zip(expected.input.iter())
+        .zip(sanitized.inputs.iter_mut())
+    {
+        let prevout = input
+            .witness_utxo
+            .as_ref()
+            .ok_or(Error::MissingPreviousOutput)?;
+        if let Some(prev_tx) = &input.non_witness_utxo {
+            if prev_tx.txid() != txin.previous_output.txid
+                || prev_tx.output.get(txin.previous_output.vout as usize) != Some(prevout)
+            {
+                return Err(Error::UnexpectedTransaction);
+            }
+        }
+        if !malleability::spends_segwit(input) {
+            return Err(Error::LegacyInput);
+        }
+        match input.sighash_type {
+            None | Some(SigHashType::All) => (),
+            Some(_) => return Err(Error::UnsupportedSigHashType),
+        }
+        check_scripts(
+            &prevout.script_pubkey,
+            &input.redeem_script,
+            &input.witness_script,
+        )?;
+        sanitized.non_witness_utxo = input.non_witness_utxo.clone();
+        sanitized.witness_utxo = Some(prevout.clone());
+        sanitized.partial_sigs = input.partial_sigs.clone();
+        sanitized.sighash_type = input.sighash_type;
+        sanitized.redeem_script = input.redeem_script.clone();
+        sanitized.witness_script = input.witness_script.clone();
+    }
+
+    for ((output, txout), sanitized) in psbt
+        .outputs
+        .iter()
+        .zip(expected.output.iter())
+        .zip(sanitized.outputs.iter_mut())
+    {
+        check_scripts(
+            &txout.script_pubkey,
+            &output.redeem_script,
+            &output.witness_script,
+        )?;
+        sanitized.redeem_script = output.redeem_script.clone();
+        sanitized.witness_script = output.witness_script.clone();
+    }
+
+    Ok(sanitized)
+}
+
+// Check that the redeem and witness scripts, if any, hash to the script pubkey
+fn check_scripts(
+    script_pubkey: &Script,
+    redeem_script: &Option<Script>,
+    witness_script: &Option<Script>,
+) -> Result<(), Error> {
+    let witness_program = witness_script.as_ref().map(Script::to_v0_p2wsh);
+    let valid = match (redeem_script, &witness_program) {
+        (None, None) => true,
+        (None, Some(program)) => program == script_pubkey,
+        (Some(redeem), None) => &redeem.to_p2sh() == script_pubkey,
+        (Some(redeem), Some(program)) => redeem == program && &redeem.to_p2sh() == script_pubkey,
+    };
+    match valid {
+        true => Ok(()),
+        false => Err(Error::UnexpectedScript),
+    }
+}
+
 /// Computes the [`BIP-143`][bip-143] compliant sighash for a [`SIGHASH_ALL`][sighash_all]
 /// signature for the given input.
 ///
@@ -278,6 +419,23 @@ pub fn signature_hash<'a>(
         .as_hash()
 }
 
+/// Serializes the [`BIP-143`][bip-143] signing data of the given input, i.e. the preimage of
+/// [`signature_hash`], for signers verifying what they sign.
+///
+/// [bip-143]: https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki
+pub fn signature_hash_preimage<'a>(
+    txin: TxInRef<'a>,
+    script: &Script,
+    value: u64,
+    sighash_type: SigHashType,
+) -> Vec<u8> {
+    let mut preimage = vec![];
+    SigHashCache::new(txin.transaction)
+        .encode_signing_data_to(&mut preimage, txin.index, script, value, sighash_type)
+        .expect("writing to a vector never fails");
+    preimage
+}
+
 /// Computes the [`BIP-143`][bip-143] compliant signature for the given input.
 /// [Read more...][signature-hash]
 ///
@@ -316,3 +474,144 @@ pub fn sign_hash(
     sig.normalize_s();
     Ok(sig)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::bitcoin::local::key;
+    use bitcoin::blockdata::opcodes;
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::util::bip32::{DerivationPath, Fingerprint};
+    use bitcoin::util::psbt::raw;
+    use std::str::FromStr;
+
+    fn script(byte: u8) -> Script {
+        Builder::new()
+            .push_key(&key(byte))
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script()
+    }
+
+    fn psbt() -> PartiallySignedTransaction {
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 0,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: 10_000,
+                script_pubkey: script(2).to_v0_p2wsh(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: 11_000,
+            script_pubkey: script(1).to_v0_p2wsh(),
+        });
+        psbt.inputs[0].witness_script = Some(script(1));
+        psbt.inputs[0].sighash_type = Some(SigHashType::All);
+        psbt.outputs[0].witness_script = Some(script(2));
+        psbt
+    }
+
+    #[test]
+    fn sanitize_untrusted_psbt() {
+        let expected = psbt();
+        let tx = expected.global.unsigned_tx.clone();
+        assert_eq!(
+            sanitize_partial_transaction(&expected, &tx).unwrap(),
+            expected
+        );
+
+        // Signatures are kept, unknown fields and derivations are stripped
+        let mut received = expected.clone();
+        received.inputs[0].partial_sigs.insert(key(3), vec![0x30]);
+        let mut signed = expected.clone();
+        signed.inputs[0].partial_sigs.insert(key(3), vec![0x30]);
+        let proprietary = raw::ProprietaryKey {
+            prefix: b"leak".to_vec(),
+            subtype: 0x00,
+            key: vec![0x01],
+        };
+        received.global.unknown.insert(
+            raw::Key {
+                type_value: 0xf0,
+                key: vec![],
+            },
+            vec![0x42; 32],
+        );
+        received.inputs[0]
+            .proprietary
+            .insert(proprietary.clone(), vec![0x42; 32]);
+        received.inputs[0].bip32_derivation.insert(
+            key(4),
+            (
+                Fingerprint::default(),
+                DerivationPath::from_str("m/0'").unwrap(),
+            ),
+        );
+        received.inputs[0].final_script_witness = Some(vec![vec![0x42; 32]]);
+        received.outputs[0]
+            .proprietary
+            .insert(proprietary, vec![0x42]);
+        let sanitized = sanitize_partial_transaction(&received, &tx).unwrap();
+        assert_eq!(sanitized, signed);
+        assert!(sanitized.global.unknown.is_empty());
+        assert!(sanitized.inputs[0].bip32_derivation.is_empty());
+
+        // MUST error if the transaction does not match the expected one
+        let mut received = expected.clone();
+        received.global.unsigned_tx.output[0].value = 9_000;
+        assert!(matches!(
+            sanitize_partial_transaction(&received, &tx),
+            Err(Error::UnexpectedTransaction)
+        ));
+        let mut received = expected.clone();
+        received.outputs.push(Default::default());
+        assert!(matches!(
+            sanitize_partial_transaction(&received, &tx),
+            Err(Error::UnexpectedTransaction)
+        ));
+
+        // MUST error if the inputs or scripts are not the expected ones
+        let mut received = expected.clone();
+        received.inputs[0].witness_utxo = None;
+        assert!(matches!(
+            sanitize_partial_transaction(&received, &tx),
+            Err(Error::MissingPreviousOutput)
+        ));
+        let mut received = expected.clone();
+        received.inputs[0].witness_utxo = Some(TxOut {
+            value: 11_000,
+            script_pubkey: script(1).to_p2sh(),
+        });
+        received.inputs[0].redeem_script = Some(script(1));
+        received.inputs[0].witness_script = None;
+        assert!(matches!(
+            sanitize_partial_transaction(&received, &tx),
+            Err(Error::LegacyInput)
+        ));
+        let mut received = expected.clone();
+        received.inputs[0].sighash_type = Some(SigHashType::None);
+        assert!(matches!(
+            sanitize_partial_transaction(&received, &tx),
+            Err(Error::UnsupportedSigHashType)
+        ));
+        let mut received = expected.clone();
+        received.inputs[0].witness_script = Some(script(3));
+        assert!(matches!(
+            sanitize_partial_transaction(&received, &tx),
+            Err(Error::UnexpectedScript)
+        ));
+        let mut received = expected;
+        received.outputs[0].redeem_script = Some(script(2));
+        assert!(matches!(
+            sanitize_partial_transaction(&received, &tx),
+            Err(Error::UnexpectedScript)
+        ));
+    }
+}
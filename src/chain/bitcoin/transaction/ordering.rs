@@ -0,0 +1,185 @@
+//! Deterministic ordering of the inputs and outputs of the arbitrating transactions.
+//!
+//! The order in which the inputs and outputs of a transaction are listed is free, if left to the
+//! implementation it reveals which participant or which wallet built the transaction, e.g. the
+//! serial identifiers of a dual-funded lock leak who contributed each input, and two
+//! implementations build different transactions from the same template. Inputs and outputs are
+//! ordered following [BIP 69][bip-69]: inputs by previous transaction identifier, in the
+//! reversed byte order, then previous output index; outputs by amount, then script pubkey bytes.
+//!
+//! The protocol identifies some inputs and outputs by their position, e.g. the contract output is
+//! always the first output and the fee is set on the first output. These protocol inputs and
+//! outputs are anchored at the beginning of the transaction and keep their position, only the
+//! inputs and outputs following them are ordered.
+//!
+//! Every template that can carry more than one input or output is ordered when built and
+//! verified when received: the dual-funded lock, the buy with an anchor output, and the sweep
+//! refunding the funding excess.
+//!
+//! [bip-69]: https://github.com/bitcoin/bips/blob/master/bip-0069.mediawiki
+
+use std::cmp::Ordering;
+
+use bitcoin::blockdata::transaction::{TxIn, TxOut};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+
+use crate::chain::bitcoin::transaction::Error;
+
+/// Compare two inputs following BIP 69.
+pub fn cmp_inputs(a: &TxIn, b: &TxIn) -> Ordering {
+    let (a, b) = (&a.previous_output, &b.previous_output);
+    a.txid[..]
+        .iter()
+        .rev()
+        .cmp(b.txid[..].iter().rev())
+        .then(a.vout.cmp(&b.vout))
+}
+
+/// Compare two outputs following BIP 69.
+pub fn cmp_outputs(a: &TxOut, b: &TxOut) -> Ordering {
+    a.value
+        .cmp(&b.value)
+        .then_with(|| a.script_pubkey[..].cmp(&b.script_pubkey[..]))
+}
+
+/// Order the inputs and outputs of the partial transaction following the first
+/// `anchored_inputs` inputs and `anchored_outputs` outputs, the partial transaction inputs and
+/// outputs metadata are moved along the unsigned transaction ones.
+pub fn sort_partial_transaction(
+    psbt: &mut PartiallySignedTransaction,
+    anchored_inputs: usize,
+    anchored_outputs: usize,
+) {
+    let tx = &mut psbt.global.unsigned_tx;
+    if anchored_inputs < tx.input.len() {
+        let mut inputs: Vec<_> = tx
+            .input
+            .drain(anchored_inputs..)
+            .zip(psbt.inputs.drain(anchored_inputs..))
+            .collect();
+        inputs.sort_by(|(a, _), (b, _)| cmp_inputs(a, b));
+        for (txin, input) in inputs {
+            tx.input.push(txin);
+            psbt.inputs.push(input);
+        }
+    }
+    if anchored_outputs < tx.output.len() {
+        let mut outputs: Vec<_> = tx
+            .output
+            .drain(anchored_outputs..)
+            .zip(psbt.outputs.drain(anchored_outputs..))
+            .collect();
+        outputs.sort_by(|(a, _), (b, _)| cmp_outputs(a, b));
+        for (txout, output) in outputs {
+            tx.output.push(txout);
+            psbt.outputs.push(output);
+        }
+    }
+}
+
+/// Return true if the inputs and outputs following the anchored ones are ordered.
+pub fn is_sorted(
+    tx: &bitcoin::Transaction,
+    anchored_inputs: usize,
+    anchored_outputs: usize,
+) -> bool {
+    let inputs = tx.input.get(anchored_inputs..).unwrap_or_default();
+    let outputs = tx.output.get(anchored_outputs..).unwrap_or_default();
+    inputs
+        .windows(2)
+        .all(|w| cmp_inputs(&w[0], &w[1]) != Ordering::Greater)
+        && outputs
+            .windows(2)
+            .all(|w| cmp_outputs(&w[0], &w[1]) != Ordering::Greater)
+}
+
+/// Verify that a transaction received from the counter-party is ordered, see [`is_sorted`].
+pub fn verify_order(
+    tx: &bitcoin::Transaction,
+    anchored_inputs: usize,
+    anchored_outputs: usize,
+) -> Result<(), Error> {
+    match is_sorted(tx, anchored_inputs, anchored_outputs) {
+        true => Ok(()),
+        false => Err(Error::UnorderedTransaction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::blockdata::transaction::OutPoint;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Script, Transaction, Txid};
+
+    fn input(txid: [u8; 32], vout: u32) -> TxIn {
+        TxIn {
+            previous_output: OutPoint::new(Txid::from_inner(txid), vout),
+            script_sig: Script::default(),
+            sequence: 0xffffffff,
+            witness: vec![],
+        }
+    }
+
+    fn output(value: u64, script: &[u8]) -> TxOut {
+        TxOut {
+            value,
+            script_pubkey: Script::from(script.to_vec()),
+        }
+    }
+
+    #[test]
+    fn order_following_bip69() {
+        // Transaction identifiers are compared in the reversed byte order
+        let mut low = [0u8; 32];
+        low[0] = 0xff;
+        let mut high = [0u8; 32];
+        high[31] = 0x01;
+        assert_eq!(cmp_inputs(&input(low, 0), &input(high, 0)), Ordering::Less);
+        assert_eq!(
+            cmp_inputs(&input(high, 1), &input(high, 0)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            cmp_outputs(&output(1, &[0xff]), &output(2, &[0x00])),
+            Ordering::Less
+        );
+        assert_eq!(
+            cmp_outputs(&output(1, &[0x01, 0x00]), &output(1, &[0x01])),
+            Ordering::Greater
+        );
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![input(high, 0), input(high, 1), input(low, 0)],
+            output: vec![output(9, &[0x09]), output(3, &[0x02]), output(3, &[0x01])],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx.clone()).unwrap();
+        for (i, input) in psbt.inputs.iter_mut().enumerate() {
+            input.witness_utxo = Some(output(i as u64, &[]));
+        }
+        assert!(!is_sorted(&tx, 1, 1));
+
+        // The anchored input and output keep their position
+        sort_partial_transaction(&mut psbt, 1, 1);
+        let sorted = &psbt.global.unsigned_tx;
+        assert_eq!(
+            sorted.input,
+            vec![input(high, 0), input(low, 0), input(high, 1)]
+        );
+        assert_eq!(
+            sorted.output,
+            vec![output(9, &[0x09]), output(3, &[0x01]), output(3, &[0x02])]
+        );
+        // The inputs metadata follow their input
+        assert_eq!(psbt.inputs[1].witness_utxo, Some(output(2, &[])));
+        assert!(verify_order(sorted, 1, 1).is_ok());
+        assert!(matches!(
+            verify_order(sorted, 0, 0),
+            Err(Error::UnorderedTransaction)
+        ));
+        // Anchoring all the inputs and outputs is always ordered
+        assert!(is_sorted(&tx, 3, 5));
+    }
+}
@@ -71,4 +71,98 @@ impl Punishable<Bitcoin, MetadataOutput> for Tx<Punish> {
             _t: PhantomData,
         })
     }
+
+    fn validate_fee_source(&self) -> Result<(), Error> {
+        let tx = &self.psbt.global.unsigned_tx;
+        // Only the cancel output is consumed, no input is added to pay the fee
+        if tx.input.len() != 1 || tx.output.len() != 1 {
+            return Err(Error::WrongTemplate);
+        }
+        let claimed = self.psbt.inputs[0]
+            .witness_utxo
+            .as_ref()
+            .ok_or(Error::MissingWitness)?
+            .value;
+        match tx.output[0].value {
+            0 => Err(Error::FeeExceedsPunishedOutput),
+            value if value > claimed => Err(Error::WrongTemplate),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::{Fee, FeePolitic, FeeStrategy, FeeStrategyError};
+    use crate::chain::bitcoin::fee::SatPerVByte;
+    use crate::transaction::{Transaction, TxLabel};
+    use bitcoin::blockdata::transaction::OutPoint;
+    use bitcoin::Script;
+
+    fn punish(claimed: u64) -> Tx<Punish> {
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 10,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: claimed,
+                script_pubkey: Script::default(),
+            }],
+        };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: claimed,
+            script_pubkey: Script::default(),
+        });
+        Tx::from_partial(psbt)
+    }
+
+    #[test]
+    fn fee_paid_from_punished_output() {
+        let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(1));
+        let mut tx = punish(10_000);
+        Bitcoin::set_fee(
+            tx.as_partial_mut(),
+            TxLabel::Punish,
+            &strategy,
+            FeePolitic::Aggressive,
+        )
+        .unwrap();
+        assert!(tx.validate_fee_source().is_ok());
+
+        // MUST error if the fee consumes the whole output
+        let mut tx = punish(10_000);
+        tx.as_partial_mut().global.unsigned_tx.output[0].value = 0;
+        assert!(matches!(
+            tx.validate_fee_source(),
+            Err(Error::FeeExceedsPunishedOutput)
+        ));
+
+        // MUST error if an input is added to pay the fee
+        let mut tx = punish(10_000);
+        let input = tx.as_partial().global.unsigned_tx.input[0].clone();
+        tx.as_partial_mut().global.unsigned_tx.input.push(input);
+        assert!(matches!(
+            tx.validate_fee_source(),
+            Err(Error::WrongTemplate)
+        ));
+
+        // The strategy cannot be applied on a too small output
+        let mut tx = punish(100);
+        assert!(matches!(
+            Bitcoin::set_fee(
+                tx.as_partial_mut(),
+                TxLabel::Punish,
+                &strategy,
+                FeePolitic::Aggressive
+            ),
+            Err(FeeStrategyError::NotEnoughAssets)
+        ));
+    }
 }
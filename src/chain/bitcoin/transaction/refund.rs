@@ -9,7 +9,7 @@ use bitcoin::Address;
 use crate::script;
 use crate::transaction::{Cancelable, Error as FError, Refundable};
 
-use crate::chain::bitcoin::transaction::{Error, MetadataOutput, SubTransaction, Tx};
+use crate::chain::bitcoin::transaction::{malleability, Error, MetadataOutput, SubTransaction, Tx};
 use crate::chain::bitcoin::Bitcoin;
 
 #[derive(Debug)]
@@ -108,7 +108,8 @@ impl Refundable<Bitcoin, MetadataOutput> for Tx<Refund> {
         _punish_lock: script::DataPunishableLock<Bitcoin>,
         _refund_target: Address,
     ) -> Result<(), FError> {
-        // FIXME
+        // FIXME verify the outputs of the template
+        malleability::verify_segwit_inputs(&self.psbt)?;
         Ok(())
     }
 }
@@ -0,0 +1,109 @@
+use std::marker::PhantomData;
+
+use bitcoin::blockdata::transaction::{SigHashType, TxIn, TxOut};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, Amount};
+
+use crate::transaction::{Error as FError, Fundable, Sweepable};
+
+use crate::chain::bitcoin::transaction::{ordering, Error, MetadataOutput, SubTransaction, Tx};
+use crate::chain::bitcoin::Bitcoin;
+
+#[derive(Debug)]
+pub struct Sweep;
+
+impl SubTransaction for Sweep {
+    fn finalize(psbt: &mut PartiallySignedTransaction) -> Result<(), FError> {
+        let (pubkey, full_sig) = psbt.inputs[0]
+            .partial_sigs
+            .iter()
+            .next()
+            .ok_or(FError::MissingSignature)?;
+        psbt.inputs[0].final_script_witness = Some(vec![full_sig.clone(), pubkey.to_bytes()]);
+        Ok(())
+    }
+}
+
+impl Tx<Sweep> {
+    fn new(output_metadata: MetadataOutput, output: Vec<TxOut>) -> Result<Self, FError> {
+        let unsigned_tx = bitcoin::blockdata::transaction::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: output_metadata.out_point,
+                script_sig: bitcoin::blockdata::script::Script::default(),
+                sequence: 0xffffffff,
+                witness: vec![],
+            }],
+            output,
+        };
+
+        let mut psbt =
+            PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).map_err(Error::from)?;
+
+        // Set the input witness data and sighash type
+        psbt.inputs[0].witness_utxo = Some(output_metadata.tx_out);
+        psbt.inputs[0].witness_script = output_metadata.script_pubkey;
+        psbt.inputs[0].sighash_type = Some(SigHashType::All);
+        // The first output pays the fee and is anchored first
+        ordering::sort_partial_transaction(&mut psbt, 1, 1);
+
+        Ok(Tx {
+            psbt,
+            _t: PhantomData,
+        })
+    }
+}
+
+impl Sweepable<Bitcoin, MetadataOutput> for Tx<Sweep> {
+    fn initialize(
+        prev: &impl Fundable<Bitcoin, MetadataOutput>,
+        sweep_target: Address,
+    ) -> Result<Self, FError> {
+        let output_metadata = prev.get_consumable_output()?;
+        let value = output_metadata.tx_out.value;
+
+        Self::new(
+            output_metadata,
+            vec![TxOut {
+                value,
+                script_pubkey: sweep_target.script_pubkey(),
+            }],
+        )
+    }
+
+    fn initialize_refund_excess(
+        prev: &impl Fundable<Bitcoin, MetadataOutput>,
+        refund_target: Address,
+        target_amount: Amount,
+        lock_fee: Amount,
+    ) -> Result<Self, FError> {
+        let output_metadata = prev.get_consumable_output()?;
+
+        let refunding_amount = target_amount
+            .checked_add(lock_fee)
+            .ok_or(FError::InvalidTargetAmount)?;
+        let excess = output_metadata
+            .tx_out
+            .value
+            .checked_sub(refunding_amount.as_sat())
+            .ok_or(FError::NotEnoughAssets)?;
+
+        // The funding address is the output consumed by the sweep
+        let funding_script = output_metadata.tx_out.script_pubkey.clone();
+
+        Self::new(
+            output_metadata,
+            vec![
+                TxOut {
+                    value: excess,
+                    script_pubkey: refund_target.script_pubkey(),
+                },
+                TxOut {
+                    value: refunding_amount.as_sat(),
+                    script_pubkey: funding_script,
+                },
+            ],
+        )
+    }
+}
@@ -0,0 +1,376 @@
+//! Key images of the shared account used to monitor the spends of the locked funds.
+//!
+//! CryptoNote transactions do not reference the outputs they spend, an input references a ring of
+//! outputs and reveals the key image `I = x * Hp(P)` of the one-time key `P = x * G` of the real
+//! output, so the same output cannot be spent twice. Once both shares of the accordant spend key
+//! are known, the key images of the outputs received by the shared account are computed with
+//! [`lock_key_images`]; a transaction spending the locked funds is then recognized with
+//! [`spent_key_images`], e.g. to detect a spend that is not the expected sweep of the funds.
+//!
+//! The hash to point function `Hp` matches `hash_to_ec` of the CryptoNote reference
+//! implementation: the Keccak-256 hash of the key is mapped on the curve with
+//! `ge_fromfe_frombytes_vartime`, then multiplied by the cofactor.
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use monero::blockdata::transaction::{KeyImage, TxIn};
+use monero::cryptonote::hash::{keccak_256, Hash};
+use monero::cryptonote::onetime_key::{KeyGenerator, KeyRecoverer};
+use monero::cryptonote::subaddress::Index;
+use monero::util::key::{KeyPair, PrivateKey, PublicKey, ViewPair};
+
+/// Map the public key on the curve, as `hash_to_ec` of the CryptoNote reference implementation.
+pub fn hash_to_point(key: &PublicKey) -> EdwardsPoint {
+    let (x, y) = field::map_to_curve(&keccak_256(key.as_bytes()));
+    let mut bytes = y.to_bytes();
+    bytes[31] ^= (x.is_negative() as u8) << 7;
+    CompressedEdwardsY(bytes)
+        .decompress()
+        .expect("the mapping always returns a point on the curve")
+        .mul_by_cofactor()
+}
+
+/// Compute the key image of the one-time private key of an output.
+pub fn key_image(one_time_key: &PrivateKey) -> KeyImage {
+    let public = PublicKey::from_private_key(one_time_key);
+    let image = one_time_key.scalar * hash_to_point(&public);
+    KeyImage {
+        image: Hash(image.compress().to_bytes()),
+    }
+}
+
+/// Compute the key images of the outputs of the transaction received by the shared account of the
+/// swap, given both shares of the spend private key and the aggregated private view key. Returns
+/// the index of each output received with its key image, outputs sent to sub-addresses are not
+/// recognized.
+pub fn lock_key_images(
+    tx: &monero::Transaction,
+    spend_alice: &PrivateKey,
+    spend_bob: &PrivateKey,
+    view: &PrivateKey,
+) -> Vec<(usize, KeyImage)> {
+    let tx_pubkey = match tx.tx_pubkey() {
+        Some(key) => key,
+        None => return vec![],
+    };
+    let keys = KeyPair {
+        view: *view,
+        spend: spend_alice + spend_bob,
+    };
+    let pair = ViewPair::from(&keys);
+    let generator = KeyGenerator::from_key(&pair, tx_pubkey);
+    let recoverer = KeyRecoverer::new(&keys, tx_pubkey);
+    tx.prefix
+        .outputs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, output)| match output.target.as_one_time_key() {
+            Some(key) if generator.check(index, *key) => {
+                let one_time_key = recoverer.recover(index, Index { major: 0, minor: 0 });
+                Some((index, key_image(&one_time_key)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Return the key images revealed by the inputs of the transaction among the given key images,
+/// i.e. the watched outputs spent by the transaction.
+pub fn spent_key_images<'a>(
+    tx: &monero::Transaction,
+    key_images: &'a [KeyImage],
+) -> Vec<&'a KeyImage> {
+    key_images
+        .iter()
+        .filter(|watched| {
+            tx.prefix.inputs.iter().any(|input| match input {
+                TxIn::ToKey { k_image, .. } => k_image.image == watched.image,
+                _ => false,
+            })
+        })
+        .collect()
+}
+
+// Arithmetic in the field of the curve needed by the mapping, the field elements of the curve
+// library are not public
+mod field {
+    use std::ops::{Add, Mul, Neg, Sub};
+
+    const MASK: u64 = (1 << 51) - 1;
+
+    // -A with A = 486662 the Montgomery curve parameter
+    const MA: [u8; 32] = [
+        0xe7, 0x92, 0xf8, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ];
+    // -A^2
+    const MA2: [u8; 32] = [
+        0xc9, 0xe3, 0x3d, 0xdb, 0xc8, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ];
+    // sqrt(-1)
+    const SQRTM1: [u8; 32] = [
+        0xb0, 0xa0, 0x0e, 0x4a, 0x27, 0x1b, 0xee, 0xc4, 0x78, 0xe4, 0x2f, 0xad, 0x06, 0x18, 0x43,
+        0x2f, 0xa7, 0xd7, 0xfb, 0x3d, 0x99, 0x00, 0x4d, 0x2b, 0x0b, 0xdf, 0xc1, 0x4f, 0x80, 0x24,
+        0x83, 0x2b,
+    ];
+    // sqrt(-2 * A * (A + 2))
+    const FFFB1: [u8; 32] = [
+        0xff, 0xbd, 0xe3, 0xcd, 0x8a, 0x96, 0x58, 0xdd, 0x72, 0x8c, 0xd5, 0x46, 0x57, 0xfb, 0x6b,
+        0x2e, 0x1c, 0xe6, 0x04, 0xbe, 0xc8, 0x3a, 0x56, 0xdf, 0xe8, 0xe4, 0x29, 0x25, 0x10, 0x04,
+        0x8e, 0x01,
+    ];
+    // sqrt(2 * A * (A + 2))
+    const FFFB2: [u8; 32] = [
+        0x0d, 0x65, 0x83, 0x9f, 0x7c, 0x9b, 0x21, 0x2d, 0x20, 0x08, 0xa9, 0xfb, 0xb9, 0xfc, 0x21,
+        0xae, 0x41, 0xa0, 0xe9, 0x3f, 0x48, 0xae, 0x2b, 0x6e, 0x09, 0xd3, 0xa5, 0xfb, 0xf5, 0xe1,
+        0xf9, 0x32,
+    ];
+    // sqrt(-sqrt(-1) * A * (A + 2))
+    const FFFB3: [u8; 32] = [
+        0x66, 0x2c, 0x30, 0x17, 0x87, 0x7d, 0x1b, 0x58, 0x29, 0x42, 0x96, 0xa5, 0x4e, 0xff, 0x24,
+        0x40, 0xed, 0xa2, 0x0d, 0x3f, 0x40, 0x46, 0x95, 0xb8, 0xef, 0x08, 0xc2, 0x14, 0x0d, 0x11,
+        0x4a, 0x67,
+    ];
+    // sqrt(sqrt(-1) * A * (A + 2))
+    const FFFB4: [u8; 32] = [
+        0x67, 0x6e, 0x4c, 0x49, 0xfc, 0xe6, 0xc2, 0x7a, 0xb6, 0xb5, 0xc0, 0x5e, 0xf7, 0x03, 0xb9,
+        0x11, 0xd1, 0xbc, 0x08, 0x81, 0x77, 0x0b, 0x3f, 0xd9, 0x06, 0x24, 0x98, 0xef, 0xfc, 0x0c,
+        0xbc, 0x65,
+    ];
+
+    /// An element of the field of integers modulo 2^255 - 19 in radix 2^51.
+    #[derive(Debug, Clone, Copy)]
+    pub(super) struct FieldElement([u64; 5]);
+
+    impl FieldElement {
+        const ZERO: FieldElement = FieldElement([0, 0, 0, 0, 0]);
+        const ONE: FieldElement = FieldElement([1, 0, 0, 0, 0]);
+
+        /// Load the 256 bits little-endian integer reduced modulo p, the highest bit included.
+        pub(super) fn from_bytes(bytes: &[u8; 32]) -> Self {
+            let mut words = [0u64; 4];
+            for (i, word) in words.iter_mut().enumerate() {
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(&bytes[i * 8..i * 8 + 8]);
+                *word = u64::from_le_bytes(buf);
+            }
+            Self([
+                words[0] & MASK,
+                ((words[0] >> 51) | (words[1] << 13)) & MASK,
+                ((words[1] >> 38) | (words[2] << 26)) & MASK,
+                ((words[2] >> 25) | (words[3] << 39)) & MASK,
+                words[3] >> 12,
+            ])
+            .reduce()
+        }
+
+        /// Return the canonical little-endian encoding of the element.
+        pub(super) fn to_bytes(self) -> [u8; 32] {
+            let mut limbs = self.reduce().0;
+            // Compute the carry of limbs + 19, i.e. 1 iff the element is greater or equal to p
+            let mut q = (limbs[0] + 19) >> 51;
+            for limb in limbs.iter().skip(1) {
+                q = (limb + q) >> 51;
+            }
+            limbs[0] += 19 * q;
+            for i in 0..4 {
+                limbs[i + 1] += limbs[i] >> 51;
+                limbs[i] &= MASK;
+            }
+            limbs[4] &= MASK;
+            let mut bytes = [0u8; 32];
+            let mut acc: u128 = 0;
+            let mut bits = 0;
+            let mut pos = 0;
+            for limb in limbs.iter() {
+                acc |= (*limb as u128) << bits;
+                bits += 51;
+                while bits >= 8 && pos < 32 {
+                    bytes[pos] = acc as u8;
+                    acc >>= 8;
+                    bits -= 8;
+                    pos += 1;
+                }
+            }
+            if pos < 32 {
+                bytes[pos] = acc as u8;
+            }
+            bytes
+        }
+
+        // Carry the limbs so they fit in 51 bits, plus a small excess on the first one
+        fn reduce(self) -> Self {
+            let mut l = self.0;
+            let c0 = l[0] >> 51;
+            let c1 = l[1] >> 51;
+            let c2 = l[2] >> 51;
+            let c3 = l[3] >> 51;
+            let c4 = l[4] >> 51;
+            l[0] &= MASK;
+            l[1] &= MASK;
+            l[2] &= MASK;
+            l[3] &= MASK;
+            l[4] &= MASK;
+            l[0] += c4 * 19;
+            l[1] += c0;
+            l[2] += c1;
+            l[3] += c2;
+            l[4] += c3;
+            Self(l)
+        }
+
+        pub(super) fn square(&self) -> Self {
+            *self * *self
+        }
+
+        fn pow(&self, exponent: &[u8; 32]) -> Self {
+            let mut result = Self::ONE;
+            for i in (0..256).rev() {
+                result = result.square();
+                if (exponent[i / 8] >> (i % 8)) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+            result
+        }
+
+        // Raise the element to the power (p - 5) / 8
+        fn pow_p58(&self) -> Self {
+            let mut exponent = [0xffu8; 32];
+            exponent[0] = 0xfd;
+            exponent[31] = 0x0f;
+            self.pow(&exponent)
+        }
+
+        pub(super) fn is_zero(&self) -> bool {
+            self.to_bytes() == [0u8; 32]
+        }
+
+        pub(super) fn is_negative(&self) -> bool {
+            self.to_bytes()[0] & 1 == 1
+        }
+
+        pub(super) fn invert(&self) -> Self {
+            let mut exponent = [0xffu8; 32];
+            exponent[0] = 0xeb;
+            exponent[31] = 0x7f;
+            self.pow(&exponent)
+        }
+    }
+
+    impl Add for FieldElement {
+        type Output = Self;
+
+        fn add(self, other: Self) -> Self {
+            let mut l = self.0;
+            for (limb, other) in l.iter_mut().zip(other.0.iter()) {
+                *limb += other;
+            }
+            Self(l).reduce()
+        }
+    }
+
+    impl Sub for FieldElement {
+        type Output = Self;
+
+        fn sub(self, other: Self) -> Self {
+            // Add 16 * p to avoid underflows, the limbs of reduced elements are below 2^52
+            let reduced = other.reduce().0;
+            Self([
+                (self.0[0] + 36028797018963664) - reduced[0],
+                (self.0[1] + 36028797018963952) - reduced[1],
+                (self.0[2] + 36028797018963952) - reduced[2],
+                (self.0[3] + 36028797018963952) - reduced[3],
+                (self.0[4] + 36028797018963952) - reduced[4],
+            ])
+            .reduce()
+        }
+    }
+
+    impl Neg for FieldElement {
+        type Output = Self;
+
+        fn neg(self) -> Self {
+            Self::ZERO - self
+        }
+    }
+
+    impl Mul for FieldElement {
+        type Output = Self;
+
+        fn mul(self, other: Self) -> Self {
+            let a = self.0;
+            let b = other.0;
+            let m = |x: u64, y: u64| (x as u128) * (y as u128);
+            let b1 = b[1] * 19;
+            let b2 = b[2] * 19;
+            let b3 = b[3] * 19;
+            let b4 = b[4] * 19;
+            let c0 = m(a[0], b[0]) + m(a[4], b1) + m(a[3], b2) + m(a[2], b3) + m(a[1], b4);
+            let mut c1 = m(a[1], b[0]) + m(a[0], b[1]) + m(a[4], b2) + m(a[3], b3) + m(a[2], b4);
+            let mut c2 = m(a[2], b[0]) + m(a[1], b[1]) + m(a[0], b[2]) + m(a[4], b3) + m(a[3], b4);
+            let mut c3 =
+                m(a[3], b[0]) + m(a[2], b[1]) + m(a[1], b[2]) + m(a[0], b[3]) + m(a[4], b4);
+            let mut c4 =
+                m(a[4], b[0]) + m(a[3], b[1]) + m(a[2], b[2]) + m(a[1], b[3]) + m(a[0], b[4]);
+            let mut out = [0u64; 5];
+            c1 += c0 >> 51;
+            out[0] = (c0 as u64) & MASK;
+            c2 += c1 >> 51;
+            out[1] = (c1 as u64) & MASK;
+            c3 += c2 >> 51;
+            out[2] = (c2 as u64) & MASK;
+            c4 += c3 >> 51;
+            out[3] = (c3 as u64) & MASK;
+            let carry = (c4 >> 51) as u64;
+            out[4] = (c4 as u64) & MASK;
+            out[0] += carry * 19;
+            out[1] += out[0] >> 51;
+            out[0] &= MASK;
+            Self(out)
+        }
+    }
+
+    /// Map the 32 bytes on the curve as `ge_fromfe_frombytes_vartime` of the Monero reference
+    /// implementation, and return the affine coordinates of the point.
+    pub(super) fn map_to_curve(bytes: &[u8; 32]) -> (FieldElement, FieldElement) {
+        let u = FieldElement::from_bytes(bytes);
+        let v = (u.square()) + (u.square()); // 2 * u^2
+        let w = v + FieldElement::ONE; // 2 * u^2 + 1
+        let mut x = w.square() + FieldElement::from_bytes(&MA2) * v; // w^2 - 2 * A^2 * u^2
+                                                                     // (w / x)^((p + 3) / 8) computed as w * x^3 * (w * x^7)^((p - 5) / 8)
+        let x3 = x.square() * x;
+        let mut rx = (x3.square() * x * w).pow_p58() * x3 * w;
+        x = rx.square() * x;
+        let mut z = FieldElement::from_bytes(&MA);
+        let sign = if !(w - x).is_zero() {
+            if !(w + x).is_zero() {
+                x = x * FieldElement::from_bytes(&SQRTM1);
+                if !(w - x).is_zero() {
+                    rx = rx * FieldElement::from_bytes(&FFFB3);
+                } else {
+                    rx = rx * FieldElement::from_bytes(&FFFB4);
+                }
+                true
+            } else {
+                rx = rx * FieldElement::from_bytes(&FFFB1) * u;
+                z = z * v;
+                false
+            }
+        } else {
+            rx = rx * FieldElement::from_bytes(&FFFB2) * u;
+            z = z * v;
+            false
+        };
+        if rx.is_negative() != sign {
+            rx = -rx;
+        }
+        let rz = z + w;
+        let ry = z - w;
+        let rx = rx * rz;
+        let inv = rz.invert();
+        (rx * inv, ry * inv)
+    }
+}
@@ -0,0 +1,355 @@
+//! Generic implementation of the accordant traits for the CryptoNote blockchains.
+//!
+//! CryptoNote blockchains, e.g. Monero or its forks, share the same keys, addresses, and
+//! transactions, they only differ by their parameters: the asset identifier, the block time, the
+//! address prefixes and network identifiers of each [`Network`], and the domain of the hashes to
+//! scalar. A chain implementing [`CryptoNote`] with its parameters implements all the traits
+//! required to be used as an accordant blockchain, see [`Monero`] for an example. Only the
+//! [`Display`] and [`FromStr`] of the chain and the units of its amounts are left to implement.
+//!
+//! The keys, addresses, amounts and transactions of all the CryptoNote chains are the types of
+//! the [`monero`] crate, addresses of another chain are encoded and parsed with
+//! [`ChainParams::encode_address`] and [`ChainParams::parse_address`].
+//!
+//! [`Network`]: blockchain::Network
+//! [`Monero`]: crate::chain::monero::Monero
+//! [`Display`]: std::fmt::Display
+//! [`FromStr`]: std::str::FromStr
+
+use crate::blockchain::{
+    self, AddressScript, AmountError, Asset, AtomicAmount, BlockTime, PaymentProof, RawTransaction,
+};
+use crate::chain::monero::params::ChainParams;
+use crate::consensus::{self, CanonicalBytes};
+use crate::crypto::{self, hash, KeyShares, Keys, SharedKeyId, SharedPrivateKeys};
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::scalar::Scalar;
+use monero::cryptonote::hash::Hashable;
+use monero::cryptonote::onetime_key::KeyGenerator;
+use monero::util::address::AddressType;
+use monero::util::key::{PrivateKey, PublicKey, ViewPair};
+use monero::Address;
+use monero::Amount;
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+pub mod key_image;
+
+/// The identifier of the shared private view key of the swap account.
+pub const SHARED_VIEW_KEY_ID: u16 = 0x01;
+
+/// The parameters of a CryptoNote blockchain, see the [module documentation](self).
+pub trait CryptoNote: Copy + Clone + Debug + Default + Eq {
+    /// The asset identifier of the chain, see [`Asset::to_u32`].
+    const ASSET_ID: u32;
+
+    /// The targeted average time between two blocks.
+    const BLOCK_TIME: Duration;
+
+    /// The domain prefixed to the tags of the hashes to scalar of the chain, see
+    /// [`hash_to_scalar`]. Empty for Monero, chains sharing keys derived from the same seed must
+    /// use distinct domains.
+    const HASH_DOMAIN: &'static str;
+
+    /// Return the parameters of the chain used on the network.
+    fn chain_params(network: blockchain::Network) -> ChainParams;
+
+    /// Return true if the address can be used on the network, by default if it has the address
+    /// format of the network. Regtest nodes use the mainnet address format, so are mainnet
+    /// addresses on local networks.
+    fn is_valid_for_network(address: &Address, network: blockchain::Network) -> bool {
+        match (network, address.network) {
+            (blockchain::Network::Local, monero::Network::Mainnet) => true,
+            (network, address_network) => Self::chain_params(network).network == address_network,
+        }
+    }
+
+    /// Return the address of the shared account of the swap on the network, given the
+    /// aggregated public spend key and the aggregated private view key.
+    fn lock_address(
+        network: blockchain::Network,
+        public_spend: PublicKey,
+        private_view: &PrivateKey,
+    ) -> Address {
+        Address::standard(
+            Self::chain_params(network).network,
+            public_spend,
+            PublicKey::from_private_key(private_view),
+        )
+    }
+}
+
+/// Hash the data into a scalar of the chain, the tag is prefixed with the chain
+/// [`CryptoNote::HASH_DOMAIN`], see [`hash::hash_to_ed25519_scalar`].
+pub fn hash_to_scalar<C: CryptoNote>(tag: &str, data: &[u8]) -> PrivateKey {
+    hash::hash_to_ed25519_scalar(&format!("{}{}", C::HASH_DOMAIN, tag), data)
+}
+
+impl<C> BlockTime for C
+where
+    C: CryptoNote,
+{
+    const BLOCK_TIME: Duration = <C as CryptoNote>::BLOCK_TIME;
+}
+
+impl<C> Asset for C
+where
+    C: CryptoNote,
+{
+    /// Type for the traded asset unit
+    type AssetUnit = Amount;
+
+    fn from_u32(bytes: u32) -> Option<Self> {
+        match bytes {
+            id if id == C::ASSET_ID => Some(Self::default()),
+            _ => None,
+        }
+    }
+
+    fn to_u32(&self) -> u32 {
+        C::ASSET_ID
+    }
+}
+
+impl CanonicalBytes for Amount {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        monero::consensus::encode::serialize(&self.as_pico())
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        Ok(Amount::from_pico(
+            monero::consensus::encode::deserialize(bytes).map_err(consensus::Error::new)?,
+        ))
+    }
+}
+
+impl AtomicAmount for Amount {
+    /// The amount in piconeros
+    fn to_atomic(&self) -> u128 {
+        self.as_pico().to_atomic()
+    }
+
+    fn from_atomic(value: u128) -> Result<Self, AmountError> {
+        u64::from_atomic(value).map(Amount::from_pico)
+    }
+}
+
+impl RawTransaction for monero::Transaction {
+    fn to_raw_bytes(&self) -> Vec<u8> {
+        monero::consensus::encode::serialize(self)
+    }
+
+    fn txid(&self) -> Vec<u8> {
+        self.hash().to_bytes().to_vec()
+    }
+}
+
+impl<C> PaymentProof for C
+where
+    C: CryptoNote,
+{
+    /// The transaction private key `r`
+    type PaymentSecret = PrivateKey;
+
+    /// The transaction private key `r`, the proof reveals the key
+    type PaymentProof = PrivateKey;
+
+    type ProvedTransaction = monero::Transaction;
+
+    fn prove_payment(
+        tx: &monero::Transaction,
+        address: &Address,
+        secret: &PrivateKey,
+    ) -> Result<PrivateKey, crypto::Error> {
+        Self::verify_payment(tx, address, secret)?;
+        Ok(*secret)
+    }
+
+    fn verify_payment(
+        tx: &monero::Transaction,
+        address: &Address,
+        tx_key: &PrivateKey,
+    ) -> Result<Amount, crypto::Error> {
+        // The transaction key must be the one used to derive the outputs of the transaction
+        if tx.tx_pubkey() != Some(PublicKey::from_private_key(tx_key)) {
+            return Err(crypto::Error::InvalidProof);
+        }
+        let rct = tx
+            .rct_signatures
+            .sig
+            .as_ref()
+            .ok_or(crypto::Error::InvalidProof)?;
+        let generator =
+            KeyGenerator::from_random(address.public_view, address.public_spend, *tx_key);
+        // The shared secret r*8*V is equal to v*8*R, the amounts are opened with the transaction
+        // key in place of the private view key
+        let pair = ViewPair {
+            view: *tx_key,
+            spend: address.public_spend,
+        };
+        let mut amount = None;
+        for (index, output) in tx.prefix.outputs.iter().enumerate() {
+            match output.target.as_one_time_key() {
+                Some(key) if generator.check(index, *key) => (),
+                _ => continue,
+            }
+            let commitment = rct
+                .out_pk
+                .get(index)
+                .and_then(|out| CompressedEdwardsY(out.mask.key).decompress())
+                .ok_or(crypto::Error::InvalidProof)?;
+            let opening = rct
+                .ecdh_info
+                .get(index)
+                .and_then(|ecdh| {
+                    ecdh.open_commitment(&pair, &address.public_view, index, &commitment)
+                })
+                .ok_or(crypto::Error::InvalidProof)?;
+            amount = Some(
+                amount
+                    .unwrap_or(0u64)
+                    .checked_add(opening.amount)
+                    .ok_or(crypto::Error::InvalidProof)?,
+            );
+        }
+        amount
+            .map(Amount::from_pico)
+            .ok_or(crypto::Error::InvalidProof)
+    }
+}
+
+impl<C> blockchain::Address for C
+where
+    C: CryptoNote,
+{
+    type Address = Address;
+}
+
+impl<C> AddressScript for C
+where
+    C: CryptoNote,
+{
+    type AddressType = AddressType;
+
+    fn address_type(address: &Address) -> Option<AddressType> {
+        Some(address.addr_type)
+    }
+
+    fn standard_address_types() -> Vec<AddressType> {
+        vec![AddressType::Standard, AddressType::SubAddress]
+    }
+
+    fn is_valid_for_network(address: &Address, network: blockchain::Network) -> bool {
+        <C as CryptoNote>::is_valid_for_network(address, network)
+    }
+}
+
+impl CanonicalBytes for Address {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        self.as_bytes()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        Address::from_bytes(bytes).map_err(consensus::Error::new)
+    }
+}
+
+impl<C> Keys for C
+where
+    C: CryptoNote,
+{
+    /// Private key type for the blockchain
+    type PrivateKey = PrivateKey;
+
+    /// Public key type for the blockchain
+    type PublicKey = PublicKey;
+
+    fn extra_keys() -> Vec<u16> {
+        // No extra key
+        vec![]
+    }
+}
+
+impl CanonicalBytes for PrivateKey {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        self.to_bytes().into()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        PrivateKey::from_slice(bytes).map_err(consensus::Error::new)
+    }
+}
+
+impl CanonicalBytes for PublicKey {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        self.as_bytes().into()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        PublicKey::from_slice(bytes).map_err(consensus::Error::new)
+    }
+}
+
+impl<C> KeyShares for C
+where
+    C: CryptoNote,
+{
+    fn split_key(
+        key: &PrivateKey,
+        randomness: [u8; 32],
+    ) -> Result<(PrivateKey, PrivateKey), crypto::Error> {
+        // The first share is kept strictly below the highest bit of the key, so the second share
+        // `key - first` is computed without modular reduction
+        let bytes = key.scalar.to_bytes();
+        let top = (0..256)
+            .rev()
+            .find(|i| bytes[i / 8] >> (i % 8) & 1 == 1)
+            .ok_or(crypto::Error::UnsupportedKey)?;
+        let mut first = randomness;
+        for i in top..256 {
+            first[i / 8] &= !(1 << (i % 8));
+        }
+        let first = Scalar::from_bits(first);
+        if first == Scalar::zero() {
+            return Err(crypto::Error::UnsupportedKey);
+        }
+        Ok((
+            PrivateKey::from_scalar(first),
+            PrivateKey::from_scalar(key.scalar - first),
+        ))
+    }
+
+    fn aggregate_public_shares(first: &PublicKey, second: &PublicKey) -> PublicKey {
+        first + second
+    }
+
+    fn aggregate_private_shares(first: &PrivateKey, second: &PrivateKey) -> PrivateKey {
+        first + second
+    }
+}
+
+impl<C> SharedPrivateKeys for C
+where
+    C: CryptoNote,
+{
+    type SharedPrivateKey = PrivateKey;
+
+    fn shared_keys() -> Vec<SharedKeyId> {
+        // Share one key: the private view key
+        vec![SharedKeyId::new(SHARED_VIEW_KEY_ID)]
+    }
+}
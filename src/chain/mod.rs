@@ -1,3 +1,8 @@
+#[cfg(feature = "bitcoin")]
 pub mod bitcoin;
+#[cfg(feature = "monero")]
+pub mod cryptonote;
+#[cfg(feature = "monero")]
 pub mod monero;
+#[cfg(all(feature = "bitcoin", feature = "monero"))]
 pub mod pairs;
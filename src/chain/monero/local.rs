@@ -0,0 +1,116 @@
+//! Local Monero networks used with [`Network::Local`], e.g. a `monerod --regtest` driven by
+//! end-to-end tests.
+//!
+//! A regtest `monerod` runs a fake chain with the mainnet address format, blocks are mined on
+//! demand so transactions are considered final after fewer confirmations than on public networks.
+//! Outputs can still only be spent after the consensus unlock time of 10 blocks.
+//!
+//! [`Network::Local`]: crate::blockchain::Network::Local
+
+use monero::cryptonote::hash::Hash;
+use monero::util::key::{PrivateKey, PublicKey};
+use monero::Address;
+
+use crate::syncer::{TaskId, WatchTransaction};
+use crate::transaction::TxLabel;
+
+/// Number of confirmations after which a transaction is considered final on public networks.
+pub const PUBLIC_NETWORK_FINALITY: u16 = 10;
+
+/// Number of confirmations after which a transaction is considered final on a regtest network.
+pub const REGTEST_FINALITY: u16 = 1;
+
+/// The network identifier of the peer-to-peer protocol used by mainnet and regtest nodes.
+pub const MAINNET_NETWORK_ID: [u8; 16] = [
+    0x12, 0x30, 0xf1, 0x71, 0x61, 0x04, 0x41, 0x61, 0x17, 0x31, 0x00, 0x82, 0x16, 0xa1, 0xa1, 0x10,
+];
+
+/// The hash of the mainnet genesis block, also used by regtest nodes.
+pub const MAINNET_GENESIS_HASH: [u8; 32] = [
+    0x41, 0x80, 0x15, 0xbb, 0x9a, 0xe9, 0x82, 0xa1, 0x97, 0x5d, 0xa7, 0xd7, 0x92, 0x77, 0xc2, 0x70,
+    0x57, 0x27, 0xa5, 0x68, 0x94, 0xba, 0x0f, 0xb2, 0x46, 0xad, 0xaa, 0xbb, 0x1f, 0x46, 0x32, 0xe3,
+];
+
+/// The parameters of a local Monero network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalParams {
+    /// The address format used by the network.
+    pub network: monero::Network,
+    /// The network identifier of the peer-to-peer protocol.
+    pub network_id: [u8; 16],
+    /// The hash of the genesis block.
+    pub genesis_hash: Hash,
+    /// The number of confirmations after which a transaction is considered final.
+    pub finality: u16,
+}
+
+impl LocalParams {
+    /// Create the parameters of a default `monerod --regtest` network.
+    pub fn regtest() -> Self {
+        Self {
+            network: monero::Network::Mainnet,
+            network_id: MAINNET_NETWORK_ID,
+            genesis_hash: Hash(MAINNET_GENESIS_HASH),
+            finality: REGTEST_FINALITY,
+        }
+    }
+
+    /// Set the address format used by the network.
+    pub fn with_network(mut self, network: monero::Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Set a custom network identifier.
+    pub fn with_network_id(mut self, network_id: [u8; 16]) -> Self {
+        self.network_id = network_id;
+        self
+    }
+
+    /// Set a custom genesis block hash.
+    pub fn with_genesis_hash(mut self, genesis_hash: Hash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Set the number of confirmations after which a transaction is considered final.
+    pub fn with_finality(mut self, finality: u16) -> Self {
+        self.finality = finality;
+        self
+    }
+
+    /// Return the address of the shared Monero account of the swap in the address format of the
+    /// local network, see [`Monero::lock_address`].
+    ///
+    /// [`Monero::lock_address`]: crate::chain::monero::Monero::lock_address
+    pub fn lock_address(&self, public_spend: PublicKey, private_view: &PrivateKey) -> Address {
+        Address::standard(
+            self.network,
+            public_spend,
+            PublicKey::from_private_key(private_view),
+        )
+    }
+
+    /// Create a syncer task watching a transaction until it is final on the local network.
+    pub fn watch_transaction(
+        &self,
+        id: TaskId,
+        label: TxLabel,
+        lifetime: u64,
+        hash: Vec<u8>,
+    ) -> WatchTransaction {
+        WatchTransaction {
+            id,
+            label,
+            lifetime,
+            hash,
+            confirmation_bound: self.finality,
+        }
+    }
+}
+
+impl Default for LocalParams {
+    fn default() -> Self {
+        Self::regtest()
+    }
+}
@@ -1,135 +1,113 @@
 //! Defines and implements all the traits for Monero
 
-use crate::blockchain::{self, Asset};
-use crate::consensus::{self, CanonicalBytes};
-use crate::crypto::{Keys, SharedKeyId, SharedPrivateKeys};
-use crate::role::Accordant;
+use crate::blockchain;
+#[cfg(feature = "parse-amounts")]
+use crate::blockchain::{split_amount_unit, AmountParseError, UnitAmount};
+use crate::chain::cryptonote::CryptoNote;
+use crate::chain::monero::params::ChainParams;
 
 use monero::util::key::{PrivateKey, PublicKey};
 use monero::Address;
+#[cfg(feature = "parse-amounts")]
 use monero::Amount;
+#[cfg(feature = "parse-amounts")]
+use monero::Denomination;
 
-use std::fmt::{self, Debug, Display, Formatter};
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
 
-pub mod tasks;
+pub use crate::chain::cryptonote::{key_image, SHARED_VIEW_KEY_ID};
 
-pub const SHARED_VIEW_KEY_ID: u16 = 0x01;
+pub mod local;
+pub mod params;
+pub mod tasks;
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq)]
 pub struct Monero;
 
-impl Accordant for Monero {}
-
-impl std::str::FromStr for Monero {
-    type Err = crate::consensus::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "Monero" => Ok(Monero),
-            _ => Err(crate::consensus::Error::UnknownType),
-        }
+impl Monero {
+    /// Return the address format used by default on the network: mainnet addresses on
+    /// [`blockchain::Network::Mainnet`], stagenet addresses on [`blockchain::Network::Testnet`],
+    /// and testnet addresses on [`blockchain::Network::Local`]. Local networks using another
+    /// format, e.g. a `monerod --regtest`, are configured with [`local::LocalParams`].
+    pub fn network(network: blockchain::Network) -> monero::Network {
+        Self::chain_params(network).network
     }
-}
 
-impl Display for Monero {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> fmt::Result {
-        println!("xmr");
-        Ok(())
+    /// Return the address of the shared Monero account of the swap on the network, given the
+    /// aggregated public spend key and the aggregated private view key.
+    pub fn lock_address(
+        network: blockchain::Network,
+        public_spend: PublicKey,
+        private_view: &PrivateKey,
+    ) -> Address {
+        <Self as CryptoNote>::lock_address(network, public_spend, private_view)
     }
 }
 
-impl Asset for Monero {
-    /// Type for the traded asset unit
-    type AssetUnit = Amount;
-
-    fn from_u32(bytes: u32) -> Option<Self> {
-        match bytes {
-            0x80000080 => Some(Self),
-            _ => None,
-        }
-    }
+impl CryptoNote for Monero {
+    const ASSET_ID: u32 = 0x80000080;
 
-    fn to_u32(&self) -> u32 {
-        0x80000080
-    }
-}
+    /// Monero targets two minutes between two blocks
+    const BLOCK_TIME: Duration = Duration::from_secs(120);
 
-impl CanonicalBytes for Amount {
-    fn as_canonical_bytes(&self) -> Vec<u8> {
-        monero::consensus::encode::serialize(&self.as_pico())
-    }
+    const HASH_DOMAIN: &'static str = "";
 
-    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
-    where
-        Self: Sized,
-    {
-        Ok(Amount::from_pico(
-            monero::consensus::encode::deserialize(bytes).map_err(consensus::Error::new)?,
-        ))
+    fn chain_params(network: blockchain::Network) -> ChainParams {
+        match network {
+            blockchain::Network::Mainnet => ChainParams::mainnet(),
+            blockchain::Network::Testnet => ChainParams::stagenet(),
+            blockchain::Network::Local => ChainParams::testnet(),
+        }
     }
 }
 
-impl blockchain::Address for Monero {
-    type Address = Address;
-}
-
-impl CanonicalBytes for Address {
-    fn as_canonical_bytes(&self) -> Vec<u8> {
-        self.as_bytes()
-    }
-
-    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
-    where
-        Self: Sized,
-    {
-        Address::from_bytes(bytes).map_err(consensus::Error::new)
+/// The inverse of [`Monero::network`].
+impl From<monero::Network> for blockchain::Network {
+    fn from(network: monero::Network) -> Self {
+        match network {
+            monero::Network::Mainnet => blockchain::Network::Mainnet,
+            monero::Network::Stagenet => blockchain::Network::Testnet,
+            monero::Network::Testnet => blockchain::Network::Local,
+        }
     }
 }
 
-impl Keys for Monero {
-    /// Private key type for the blockchain
-    type PrivateKey = PrivateKey;
-
-    /// Public key type for the blockchain
-    type PublicKey = PublicKey;
+impl std::str::FromStr for Monero {
+    type Err = crate::consensus::Error;
 
-    fn extra_keys() -> Vec<u16> {
-        // No extra key
-        vec![]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Monero" => Ok(Monero),
+            _ => Err(crate::consensus::Error::UnknownType),
+        }
     }
 }
 
-impl CanonicalBytes for PrivateKey {
-    fn as_canonical_bytes(&self) -> Vec<u8> {
-        self.to_bytes().into()
-    }
-
-    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
-    where
-        Self: Sized,
-    {
-        PrivateKey::from_slice(bytes).map_err(consensus::Error::new)
+impl Display for Monero {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "xmr")
     }
 }
 
-impl CanonicalBytes for PublicKey {
-    fn as_canonical_bytes(&self) -> Vec<u8> {
-        self.as_bytes().into()
-    }
-
-    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
-    where
-        Self: Sized,
-    {
-        PublicKey::from_slice(bytes).map_err(consensus::Error::new)
+#[cfg(feature = "parse-amounts")]
+impl UnitAmount for Amount {
+    /// Parse an amount in `xmr`, `millinero`, `micronero`, `nanonero`, or `pico`.
+    fn parse_with_unit(s: &str) -> Result<Self, AmountParseError> {
+        let (value, unit) = split_amount_unit(s)?;
+        let denom = match unit.as_str() {
+            "xmr" => Denomination::Monero,
+            "millinero" => Denomination::Millinero,
+            "micronero" => Denomination::Micronero,
+            "nanonero" => Denomination::Nanonero,
+            "pico" | "piconero" => Denomination::Piconero,
+            _ => return Err(AmountParseError::UnknownUnit(unit)),
+        };
+        Amount::from_str_in(value, denom)
+            .map_err(|e| AmountParseError::InvalidAmount(e.to_string()))
     }
-}
-
-impl SharedPrivateKeys for Monero {
-    type SharedPrivateKey = PrivateKey;
 
-    fn shared_keys() -> Vec<SharedKeyId> {
-        // Share one key: the private view key
-        vec![SharedKeyId::new(SHARED_VIEW_KEY_ID)]
+    fn to_string_with_unit(&self) -> String {
+        self.to_string_with_denomination(Denomination::Monero)
     }
 }
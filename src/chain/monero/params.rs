@@ -0,0 +1,232 @@
+//! Parameters of the Monero chain, or of a chain derived from Monero, a swap runs on.
+//!
+//! The [`ChainParams`] of the public networks are provided, parameters for custom regtests and
+//! forks sharing the Monero consensus rules are created from them by changing the network
+//! identifier, the genesis block, or the address prefixes. Addresses are encoded and parsed with
+//! the prefixes of the chain instead of the ones of [`monero::Network`], only single byte
+//! prefixes are supported.
+
+use base58_monero::base58;
+use monero::cryptonote::hash::{keccak_256, Hash};
+use monero::util::address::{Address, AddressType, Error};
+
+use crate::chain::monero::local::{
+    LocalParams, MAINNET_GENESIS_HASH, MAINNET_NETWORK_ID, PUBLIC_NETWORK_FINALITY,
+};
+use crate::syncer::{TaskId, WatchTransaction};
+use crate::transaction::TxLabel;
+
+/// The network identifier of the peer-to-peer protocol used by testnet nodes.
+pub const TESTNET_NETWORK_ID: [u8; 16] = [
+    0x12, 0x30, 0xf1, 0x71, 0x61, 0x04, 0x41, 0x61, 0x17, 0x31, 0x00, 0x82, 0x16, 0xa1, 0xa1, 0x11,
+];
+
+/// The network identifier of the peer-to-peer protocol used by stagenet nodes.
+pub const STAGENET_NETWORK_ID: [u8; 16] = [
+    0x12, 0x30, 0xf1, 0x71, 0x61, 0x04, 0x41, 0x61, 0x17, 0x31, 0x00, 0x82, 0x16, 0xa1, 0xa1, 0x12,
+];
+
+/// The hash of the testnet genesis block.
+pub const TESTNET_GENESIS_HASH: [u8; 32] = [
+    0x48, 0xca, 0x7c, 0xd3, 0xc8, 0xde, 0x5b, 0x6a, 0x4d, 0x53, 0xd2, 0x86, 0x1f, 0xbd, 0xae, 0xdc,
+    0xa1, 0x41, 0x55, 0x35, 0x59, 0xf9, 0xbe, 0x95, 0x20, 0x06, 0x80, 0x53, 0xcd, 0xa8, 0x43, 0x0b,
+];
+
+/// The hash of the stagenet genesis block.
+pub const STAGENET_GENESIS_HASH: [u8; 32] = [
+    0x76, 0xee, 0x3c, 0xc9, 0x86, 0x46, 0x29, 0x22, 0x06, 0xcd, 0x3e, 0x86, 0xf7, 0x4d, 0x88, 0xb4,
+    0xdc, 0xc1, 0xd9, 0x37, 0x08, 0x86, 0x45, 0xe9, 0xb0, 0xcb, 0xca, 0x84, 0xb7, 0xce, 0x74, 0xeb,
+];
+
+/// The prefixes identifying the type of an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressPrefixes {
+    /// The prefix of standard addresses.
+    pub standard: u8,
+    /// The prefix of integrated addresses.
+    pub integrated: u8,
+    /// The prefix of subaddresses.
+    pub subaddress: u8,
+}
+
+impl AddressPrefixes {
+    /// Return the address prefixes used on a Monero network.
+    pub fn of(network: monero::Network) -> Self {
+        Self {
+            standard: network.as_u8(&AddressType::Standard),
+            integrated: network.as_u8(&AddressType::Integrated(Default::default())),
+            subaddress: network.as_u8(&AddressType::SubAddress),
+        }
+    }
+
+    fn prefix(&self, address_type: &AddressType) -> u8 {
+        match address_type {
+            AddressType::Standard => self.standard,
+            AddressType::Integrated(_) => self.integrated,
+            AddressType::SubAddress => self.subaddress,
+        }
+    }
+
+    // Return the prefix of the same address type in other prefixes and the address length
+    fn translate(&self, prefix: u8, other: &AddressPrefixes) -> Option<(u8, usize)> {
+        match prefix {
+            p if p == self.standard => Some((other.standard, 69)),
+            p if p == self.integrated => Some((other.integrated, 77)),
+            p if p == self.subaddress => Some((other.subaddress, 69)),
+            _ => None,
+        }
+    }
+}
+
+/// The parameters identifying a Monero chain and its address format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainParams {
+    /// The network the chain derives its consensus rules from, set in parsed addresses.
+    pub network: monero::Network,
+    /// The network identifier of the peer-to-peer protocol.
+    pub network_id: [u8; 16],
+    /// The hash of the genesis block.
+    pub genesis_hash: Hash,
+    /// The prefixes of the addresses.
+    pub prefixes: AddressPrefixes,
+    /// The number of confirmations after which a transaction is considered final.
+    pub finality: u16,
+}
+
+impl ChainParams {
+    fn public(network: monero::Network, network_id: [u8; 16], genesis_hash: [u8; 32]) -> Self {
+        Self {
+            network,
+            network_id,
+            genesis_hash: Hash(genesis_hash),
+            prefixes: AddressPrefixes::of(network),
+            finality: PUBLIC_NETWORK_FINALITY,
+        }
+    }
+
+    /// Create the parameters of Monero mainnet.
+    pub fn mainnet() -> Self {
+        Self::public(
+            monero::Network::Mainnet,
+            MAINNET_NETWORK_ID,
+            MAINNET_GENESIS_HASH,
+        )
+    }
+
+    /// Create the parameters of Monero testnet.
+    pub fn testnet() -> Self {
+        Self::public(
+            monero::Network::Testnet,
+            TESTNET_NETWORK_ID,
+            TESTNET_GENESIS_HASH,
+        )
+    }
+
+    /// Create the parameters of Monero stagenet.
+    pub fn stagenet() -> Self {
+        Self::public(
+            monero::Network::Stagenet,
+            STAGENET_NETWORK_ID,
+            STAGENET_GENESIS_HASH,
+        )
+    }
+
+    /// Create the parameters of a default `monerod --regtest` network.
+    pub fn regtest() -> Self {
+        Self::from(LocalParams::regtest())
+    }
+
+    /// Set a custom network identifier.
+    pub fn with_network_id(mut self, network_id: [u8; 16]) -> Self {
+        self.network_id = network_id;
+        self
+    }
+
+    /// Set a custom genesis block hash.
+    pub fn with_genesis_hash(mut self, genesis_hash: Hash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Set custom address prefixes.
+    pub fn with_prefixes(mut self, prefixes: AddressPrefixes) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// Set the number of confirmations after which a transaction is considered final.
+    pub fn with_finality(mut self, finality: u16) -> Self {
+        self.finality = finality;
+        self
+    }
+
+    /// Encode the address with the address prefixes of the chain.
+    pub fn encode_address(&self, address: &Address) -> Result<String, Error> {
+        let mut bytes = address.as_bytes();
+        bytes[0] = self.prefixes.prefix(&address.addr_type);
+        let checksum_at = bytes.len() - 4;
+        let checksum = keccak_256(&bytes[..checksum_at]);
+        bytes[checksum_at..].copy_from_slice(&checksum[..4]);
+        Ok(base58::encode(&bytes)?)
+    }
+
+    /// Parse an address encoded with the address prefixes of the chain, the network of the
+    /// parsed address is the base network of the chain.
+    pub fn parse_address(&self, s: &str) -> Result<Address, Error> {
+        let mut bytes = base58::decode(s)?;
+        let (prefix, len) = bytes
+            .first()
+            .and_then(|p| {
+                self.prefixes
+                    .translate(*p, &AddressPrefixes::of(self.network))
+            })
+            .ok_or(Error::InvalidMagicByte)?;
+        if bytes.len() != len {
+            return Err(Error::InvalidFormat);
+        }
+        let checksum = keccak_256(&bytes[..len - 4]);
+        if checksum[..4] != bytes[len - 4..] {
+            return Err(Error::InvalidChecksum);
+        }
+        // Re-encode the address in the format of the base network
+        bytes[0] = prefix;
+        let checksum = keccak_256(&bytes[..len - 4]);
+        bytes[len - 4..].copy_from_slice(&checksum[..4]);
+        Address::from_bytes(&bytes)
+    }
+
+    /// Create a syncer task watching a transaction until it is final on the chain.
+    pub fn watch_transaction(
+        &self,
+        id: TaskId,
+        label: TxLabel,
+        lifetime: u64,
+        hash: Vec<u8>,
+    ) -> WatchTransaction {
+        WatchTransaction {
+            id,
+            label,
+            lifetime,
+            hash,
+            confirmation_bound: self.finality,
+        }
+    }
+}
+
+impl From<LocalParams> for ChainParams {
+    fn from(params: LocalParams) -> Self {
+        Self {
+            network: params.network,
+            network_id: params.network_id,
+            genesis_hash: params.genesis_hash,
+            prefixes: AddressPrefixes::of(params.network),
+            finality: params.finality,
+        }
+    }
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
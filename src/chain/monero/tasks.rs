@@ -18,26 +18,17 @@ impl XmrAddressAddendum {
 
     pub fn deserialize(data_vec: Vec<u8>) -> Result<XmrAddressAddendum, io::Error> {
         if data_vec.len() != 32 + 32 + 8 {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Not a serialized pair of keys and u64",
-            ))?
+            Err(io::Error::other("Not a serialized pair of keys and u64"))?
         }
 
         let mut spend_key = [0; 32];
-        for i in 0..32 {
-            spend_key[i] = data_vec[i];
-        }
+        spend_key.copy_from_slice(&data_vec[..32]);
 
         let mut view_key = [0; 32];
-        for i in 0..32 {
-            view_key[i] = data_vec[32 + i];
-        }
+        view_key.copy_from_slice(&data_vec[32..64]);
 
         let mut height = [0; 8];
-        for i in 0..8 {
-            height[i] = data_vec[58 + i];
-        }
+        height.copy_from_slice(&data_vec[58..66]);
 
         Ok(XmrAddressAddendum {
             spend_key,
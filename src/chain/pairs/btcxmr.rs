@@ -1,7 +1,7 @@
 use crate::consensus::{self, CanonicalBytes};
 use crate::crypto::{
-    self, AccordantKeyId, ArbitratingKeyId, Commit, Commitment, GenerateKey, GenerateSharedKey,
-    ProveCrossGroupDleq, SharedKeyId, Sign,
+    self, AccordantKeyId, ArbitratingKeyId, Commit, Commitment, GenerateKey, GenerateKeyShare,
+    GenerateSharedKey, KeyShares, ProveCrossGroupDleq, SharedKeyId, Sign, Signatures,
 };
 use crate::swap::Swap;
 
@@ -9,6 +9,7 @@ use crate::chain::bitcoin::transaction::sign_hash;
 use crate::chain::bitcoin::Bitcoin;
 use crate::chain::monero::{self as xmr, Monero};
 
+use curve25519_dalek::scalar::Scalar;
 use monero::cryptonote::hash::Hash;
 
 use bitcoin::hashes::sha256d::Hash as Sha256dHash;
@@ -90,7 +91,7 @@ impl Wallet {
         let secp = Secp256k1::new();
         if let Some(seed) = self.seed {
             let master_key = ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, &seed)
-                .map_err(|e| crypto::Error::new(e))?;
+                .map_err(crypto::Error::new)?;
             let key =
                 match key_id {
                     ArbitratingKeyId::Fund => master_key
@@ -105,7 +106,7 @@ impl Wallet {
                         .derive_priv(&secp, &DerivationPath::from_str("m/0/1'/5").unwrap()),
                     ArbitratingKeyId::Extra(_) => Err(crypto::Error::UnsupportedKey)?,
                 };
-            Ok(key.map_err(|e| crypto::Error::new(e))?.private_key)
+            Ok(key.map_err(crypto::Error::new)?.private_key)
         } else {
             Err(crypto::Error::UnsupportedKey)
         }
@@ -133,17 +134,157 @@ impl Wallet {
 
     pub fn private_spend_from_seed(&self) -> Result<monero::PrivateKey, crypto::Error> {
         if let Some(seed) = self.seed {
+            // The derivation is not domain separated with a tagged hash, existing seeds MUST
+            // derive the same keys
             let mut bytes = Vec::from(b"farcaster_priv_spend".as_ref());
             bytes.extend_from_slice(&seed);
 
             let mut key = Hash::hash(&bytes).to_fixed_bytes();
             key[31] &= 0b0000_1111; // Chop off bits that might be greater than the curve modulus
 
-            monero::PrivateKey::from_slice(&key).map_err(|e| crypto::Error::new(e))
+            monero::PrivateKey::from_slice(&key).map_err(crypto::Error::new)
         } else {
             Err(crypto::Error::UnsupportedKey)
         }
     }
+
+    /// Split the accordant spend key across two devices, the shares are derived from the given
+    /// randomness which MUST be generated from a secure source and never reused.
+    pub fn split_spend_key(
+        &self,
+        randomness: [u8; 32],
+    ) -> Result<(SpendShareDevice, SpendShareDevice), crypto::Error> {
+        let (first, second) = Monero::split_key(&self.private_spend_from_seed()?, randomness)?;
+        Ok((SpendShareDevice::new(first), SpendShareDevice::new(second)))
+    }
+}
+
+#[cfg(feature = "test-utils")]
+impl Wallet {
+    /// Create a deterministic wallet from a label, e.g. `"alice"`, to use in tests.
+    pub fn from_label(label: &str) -> Self {
+        Self::new(crypto::hash::tagged_sha256("wallet:test", label.as_bytes()))
+    }
+
+    /// Return the address of the funding key on a local network, the address used by the
+    /// funding transaction of a swap on [`Network::Local`].
+    ///
+    /// [`Network::Local`]: crate::blockchain::Network::Local
+    pub fn local_funding_address(&self) -> Result<bitcoin::Address, crypto::Error> {
+        let pubkey: bitcoin::PublicKey = self.get_pubkey(ArbitratingKeyId::Fund)?;
+        bitcoin::Address::p2wpkh(&pubkey, bitcoin::Network::Regtest).map_err(crypto::Error::new)
+    }
+}
+
+/// Create a deterministic wallet from a label and a faucet transaction paying the amount to its
+/// funding address on a local network, see [`faucet_transaction`].
+///
+/// [`faucet_transaction`]: crate::chain::bitcoin::local::faucet_transaction
+#[cfg(feature = "test-utils")]
+pub fn funded_wallet(
+    label: &str,
+    amount: bitcoin::Amount,
+) -> Result<(Wallet, bitcoin::Transaction), crypto::Error> {
+    let wallet = Wallet::from_label(label);
+    let address = wallet.local_funding_address()?;
+    let tx = crate::chain::bitcoin::local::faucet_transaction(&address, amount);
+    Ok((wallet, tx))
+}
+
+/// Map an accordant spend private key to the adaptor secret on the arbitrating curve with the
+/// cross-group construction: the 32 bytes of the ed25519 scalar are used as the bytes of the
+/// secp256k1 scalar, as in previous releases. The mapping is injective but not additive, the
+/// adaptor secret of an aggregated key is not the sum of the adaptor secrets of its shares. Fails
+/// if the bytes are not a valid secp256k1 scalar, e.g. on the zero key.
+pub fn adaptor_secret(spend: &monero::PrivateKey) -> Result<SecretKey, crypto::Error> {
+    let bytes = spend.to_bytes(); // FIXME warn this copy the priv key
+    SecretKey::from_slice(&bytes).map_err(crypto::Error::new)
+}
+
+/// Map an adaptor secret back to the accordant spend private key, the inverse of
+/// [`adaptor_secret`], e.g. once the adaptor secret is recovered from an adapted signature. Fails
+/// if the secret is not the image of an ed25519 scalar.
+pub fn accordant_spend(adaptor: &SecretKey) -> Result<monero::PrivateKey, crypto::Error> {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&adaptor[..]);
+    match Scalar::from_canonical_bytes(bytes) {
+        Some(scalar) => Ok(monero::PrivateKey::from_scalar(scalar)),
+        None => Err(crypto::Error::UnsupportedKey),
+    }
+}
+
+/// Derive the adaptor point of an accordant spend private key, i.e. the public key of its
+/// [`adaptor_secret`].
+pub fn adaptor_point(spend: &monero::PrivateKey) -> Result<bitcoin::PublicKey, crypto::Error> {
+    let secp = Secp256k1::signing_only();
+    Ok(bitcoin::PublicKey {
+        compressed: true,
+        key: bitcoin::secp256k1::PublicKey::from_secret_key(&secp, &adaptor_secret(spend)?),
+    })
+}
+
+/// Validate a revealed accordant spend private key against the accordant spend public key and
+/// the adaptor point of the counter-party. Fails with [`crypto::Error::InvalidProof`] if the key
+/// does not match one of them.
+pub fn verify_revealed_spend(
+    spend: &monero::PrivateKey,
+    public_spend: &monero::PublicKey,
+    adaptor: &bitcoin::PublicKey,
+) -> Result<(), crypto::Error> {
+    match monero::PublicKey::from_private_key(spend) == *public_spend
+        && adaptor_point(spend)? == *adaptor
+    {
+        true => Ok(()),
+        false => Err(crypto::Error::InvalidProof),
+    }
+}
+
+/// A device holding one of the two additive shares of a participant's accordant spend key, e.g. a
+/// second-factor device. The shares of both devices are aggregated with [`aggregate_spend_shares`]
+/// into the accordant spend public key and the adaptor public key shown to the counter-party.
+#[derive(Clone, Debug)]
+pub struct SpendShareDevice {
+    share: monero::PrivateKey,
+}
+
+impl SpendShareDevice {
+    pub fn new(share: monero::PrivateKey) -> Self {
+        Self { share }
+    }
+
+    /// Release the private share, e.g. to aggregate the private spend key with
+    /// [`KeyShares::aggregate_private_shares`] when the swap requires it.
+    pub fn private_share(&self) -> monero::PrivateKey {
+        self.share
+    }
+}
+
+impl GenerateKeyShare<monero::PublicKey, AccordantKeyId> for SpendShareDevice {
+    fn get_pubkey_share(&self, key_id: AccordantKeyId) -> Result<monero::PublicKey, crypto::Error> {
+        match key_id {
+            AccordantKeyId::Spend => Ok(monero::PublicKey::from_private_key(&self.share)),
+            AccordantKeyId::Extra(_) => Err(crypto::Error::UnsupportedKey),
+        }
+    }
+}
+
+/// Aggregate the shares of two devices into the accordant spend public key and the adaptor public
+/// key. The accordant spend public key is the sum of the public shares, but the cross-group
+/// mapping is not additive: the adaptor public key is derived from the aggregated private key,
+/// see [`adaptor_secret`].
+pub fn aggregate_spend_shares(
+    first: &SpendShareDevice,
+    second: &SpendShareDevice,
+) -> Result<(monero::PublicKey, bitcoin::PublicKey), crypto::Error> {
+    let spend = Monero::aggregate_public_shares(
+        &first.get_pubkey_share(AccordantKeyId::Spend)?,
+        &second.get_pubkey_share(AccordantKeyId::Spend)?,
+    );
+    let adaptor = adaptor_point(&Monero::aggregate_private_shares(
+        &first.private_share(),
+        &second.private_share(),
+    ))?;
+    Ok((spend, adaptor))
 }
 
 impl GenerateKey<monero::PublicKey, AccordantKeyId> for Wallet {
@@ -194,7 +335,7 @@ impl Sign<bitcoin::PublicKey, bitcoin::PrivateKey, Sha256dHash, Signature, Signa
         key: &bitcoin::PublicKey,
         msg: Sha256dHash,
     ) -> Result<Signature, crypto::Error> {
-        sign_hash(msg, &self.get_btc_privkey_by_pub(key)?.key).map_err(|e| crypto::Error::new(e))
+        sign_hash(msg, &self.get_btc_privkey_by_pub(key)?.key).map_err(crypto::Error::new)
     }
 
     fn verify_signature(
@@ -205,8 +346,8 @@ impl Sign<bitcoin::PublicKey, bitcoin::PrivateKey, Sha256dHash, Signature, Signa
     ) -> Result<(), crypto::Error> {
         let secp = Secp256k1::new();
         let message = Message::from_slice(&msg).expect("Hash is always ok");
-        secp.verify(&message, &sig, &key.key)
-            .map_err(|e| crypto::Error::new(e))
+        secp.verify(&message, sig, &key.key)
+            .map_err(crypto::Error::new)
     }
 
     fn adaptor_sign_with_key(
@@ -216,21 +357,17 @@ impl Sign<bitcoin::PublicKey, bitcoin::PrivateKey, Sha256dHash, Signature, Signa
         msg: Sha256dHash,
     ) -> Result<Signature, crypto::Error> {
         // FIXME this ignore the adaptor
-        sign_hash(msg, &self.get_btc_privkey_by_pub(key)?.key).map_err(|e| crypto::Error::new(e))
+        sign_hash(msg, &self.get_btc_privkey_by_pub(key)?.key).map_err(crypto::Error::new)
     }
 
     fn verify_adaptor_signature(
         &self,
         key: &bitcoin::PublicKey,
-        _adaptor: &bitcoin::PublicKey,
+        adaptor: &bitcoin::PublicKey,
         msg: Sha256dHash,
         sig: &Signature,
     ) -> Result<(), crypto::Error> {
-        // FIXME this ignore the adaptor
-        let secp = Secp256k1::new();
-        let message = Message::from_slice(&msg).expect("Hash is always ok");
-        secp.verify(&message, &sig, &key.key)
-            .map_err(|e| crypto::Error::new(e))
+        Bitcoin::verify_adaptor(sig, msg, key, adaptor)
     }
 
     fn adapt_signature(
@@ -248,6 +385,7 @@ impl Sign<bitcoin::PublicKey, bitcoin::PrivateKey, Sha256dHash, Signature, Signa
 
 impl Commit<Hash> for Wallet {
     fn commit_to<T: AsRef<[u8]>>(&self, value: T) -> Hash {
+        // Commitments are verified by peers running previous releases
         Hash::hash(value.as_ref())
     }
 }
@@ -272,17 +410,7 @@ impl ProveCrossGroupDleq<bitcoin::PublicKey, monero::PublicKey, RingProof> for W
     /// Project the accordant sepnd secret key over the arbitrating curve to get the public key
     /// used as the adaptor public key.
     fn project_over(&self) -> Result<bitcoin::PublicKey, crypto::Error> {
-        let secp = Secp256k1::new();
-        let spend = self.private_spend_from_seed()?;
-        let bytes = spend.to_bytes(); // FIXME warn this copy the priv key
-        let adaptor = SecretKey::from_slice(&bytes).map_err(|e| crypto::Error::new(e))?;
-
-        Ok(bitcoin::PrivateKey {
-            compressed: true,
-            network: bitcoin::Network::Bitcoin,
-            key: adaptor,
-        }
-        .public_key(&secp))
+        adaptor_point(&self.private_spend_from_seed()?)
     }
 
     /// Verify the proof given the two public keys: the accordant spend public key and the
@@ -0,0 +1,250 @@
+//! Checkpoints are snapshots of a running swap persisted by the daemon, they allow to resume the
+//! swap after a restart.
+//!
+//! The serialized format of a checkpoint starts with the [`CHECKPOINT_MAGIC_BYTES`], so a
+//! checkpoint is recognized among other farcaster blobs, followed by the version of the format on
+//! two bytes checked on decoding. Checkpoints persisted with a previous version of the
+//! format are migrated to the current one when decoded, the fields added since are left empty, so
+//! upgrading the daemon in the middle of a swap does not strand it. Checkpoints are always
+//! encoded with [`CHECKPOINT_VERSION`], unknown versions are rejected with
+//! [`consensus::Error::UnsupportedVersion`]. [`Checkpoint::version`] returns the version of a
+//! serialized checkpoint without decoding it.
+//!
+//! Persisted checkpoints can optionally be authenticated with a secret known only by the daemon, an
+//! HMAC-SHA256 is appended to the serialization and verified before decoding, so a tampered or
+//! corrupted state is detected before the swap resumes. See
+//! [`Checkpoint::to_authenticated_bytes`] and [`Checkpoint::from_authenticated_bytes`].
+//!
+//! The intermediate secrets needed to complete the swap after a crash, see [`SecretEscrow`], are
+//! persisted in the checkpoint sealed with a secret known only by the daemon.
+//!
+//! # Versions
+//!
+//!  * `1`: the swap identifier, the swap role, the public offer, and both participants'
+//!    parameters
+//!  * `2`: adds the core arbitrating transactions
+//!  * `3`: adds the sealed secret escrow
+//!  * `4`: adds the magic bytes before the version, previous versions start with the version
+
+use std::io;
+
+use crate::bundle::{AliceParameters, BobParameters, CoreArbitratingTransactions};
+use crate::consensus::{self, decode_option, encode_option, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::{hash, Keys};
+use crate::escrow::{SealedEscrow, SecretEscrow};
+use crate::negotiation::PublicOffer;
+use crate::role::SwapRole;
+use crate::swap::{Swap, SwapId};
+
+/// The current version of the checkpoint format.
+pub const CHECKPOINT_VERSION: u16 = 4;
+
+/// The magic bytes prefixing serialized checkpoints since version 4 of the format.
+pub const CHECKPOINT_MAGIC_BYTES: [u8; 6] = *b"FCCHKP";
+
+// The first version of the format prefixed with the magic bytes
+const MAGIC_BYTES_VERSION: u16 = 4;
+
+/// The length in bytes of the message authentication code appended to authenticated checkpoints.
+pub const CHECKPOINT_MAC_LEN: usize = 32;
+
+/// A snapshot of a running swap from the point of view of one participant. Parameters and
+/// transactions are added as the swap progresses.
+#[derive(Debug, Clone)]
+pub struct Checkpoint<Ctx: Swap> {
+    pub swap_id: SwapId,
+    pub swap_role: SwapRole,
+    pub public_offer: PublicOffer<Ctx>,
+    pub alice_parameters: Option<AliceParameters<Ctx>>,
+    pub bob_parameters: Option<BobParameters<Ctx>>,
+    pub core_arbitrating_transactions: Option<CoreArbitratingTransactions<Ctx::Ar>>,
+    pub secret_escrow: Option<SealedEscrow>,
+}
+
+impl<Ctx> Checkpoint<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create a new checkpoint for a swap, without parameters nor transactions.
+    pub fn new(swap_id: SwapId, swap_role: SwapRole, public_offer: PublicOffer<Ctx>) -> Self {
+        Self {
+            swap_id,
+            swap_role,
+            public_offer,
+            alice_parameters: None,
+            bob_parameters: None,
+            core_arbitrating_transactions: None,
+            secret_escrow: None,
+        }
+    }
+
+    /// Add Alice's parameters to the checkpoint.
+    pub fn with_alice_parameters(mut self, alice_parameters: AliceParameters<Ctx>) -> Self {
+        self.alice_parameters = Some(alice_parameters);
+        self
+    }
+
+    /// Add Bob's parameters to the checkpoint.
+    pub fn with_bob_parameters(mut self, bob_parameters: BobParameters<Ctx>) -> Self {
+        self.bob_parameters = Some(bob_parameters);
+        self
+    }
+
+    /// Add the core arbitrating transactions to the checkpoint.
+    pub fn with_core_arbitrating_transactions(
+        mut self,
+        core: CoreArbitratingTransactions<Ctx::Ar>,
+    ) -> Self {
+        self.core_arbitrating_transactions = Some(core);
+        self
+    }
+
+    /// Add the sealed secret escrow to the checkpoint, replacing the previous one if any.
+    pub fn with_secret_escrow(mut self, escrow: SealedEscrow) -> Self {
+        self.secret_escrow = Some(escrow);
+        self
+    }
+
+    /// Seal the secret escrow with the daemon `secret` and add it to the checkpoint. Fails if the
+    /// escrow is not for the swap of the checkpoint or if `sequence` is not greater than the
+    /// sequence of the escrow already in the checkpoint.
+    pub fn escrow_secrets(
+        &mut self,
+        escrow: &SecretEscrow<Ctx>,
+        secret: &[u8],
+        sequence: u64,
+    ) -> Result<(), consensus::Error>
+    where
+        <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+    {
+        if escrow.swap_id != self.swap_id {
+            return Err(consensus::Error::ParseFailed("escrow swap id mismatch"));
+        }
+        if matches!(&self.secret_escrow, Some(sealed) if sealed.sequence() >= sequence) {
+            return Err(consensus::Error::ParseFailed(
+                "escrow sequence not increasing",
+            ));
+        }
+        self.secret_escrow = Some(escrow.seal(secret, sequence)?);
+        Ok(())
+    }
+
+    /// Open the secret escrow of the checkpoint with the daemon `secret`, if any. Fails with
+    /// [`consensus::Error::InvalidMac`] if the secret is wrong or the escrow has been tampered
+    /// with.
+    pub fn open_secret_escrow(
+        &self,
+        secret: &[u8],
+    ) -> Result<Option<SecretEscrow<Ctx>>, consensus::Error>
+    where
+        <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+    {
+        match &self.secret_escrow {
+            Some(sealed) if sealed.swap_id() != self.swap_id => {
+                Err(consensus::Error::ParseFailed("escrow swap id mismatch"))
+            }
+            Some(sealed) => Ok(Some(sealed.open(secret)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the version of a serialized checkpoint without decoding it.
+    pub fn version(bytes: &[u8]) -> Result<u16, consensus::Error> {
+        let bytes = match bytes.starts_with(&CHECKPOINT_MAGIC_BYTES) {
+            true => &bytes[CHECKPOINT_MAGIC_BYTES.len()..],
+            false => bytes,
+        };
+        Ok(consensus::deserialize_partial::<u16>(bytes)?.0)
+    }
+
+    /// Serialize the checkpoint and append a message authentication code computed with the
+    /// daemon `secret`.
+    pub fn to_authenticated_bytes(&self, secret: &[u8]) -> Vec<u8> {
+        let mut bytes = consensus::serialize(self);
+        let mac = compute_mac(&bytes, secret);
+        bytes.extend_from_slice(&mac);
+        bytes
+    }
+
+    /// Verify the message authentication code of an authenticated checkpoint with the daemon
+    /// `secret` and decode it. Fails with [`consensus::Error::InvalidMac`] if the persisted data
+    /// has been tampered with or corrupted.
+    pub fn from_authenticated_bytes(bytes: &[u8], secret: &[u8]) -> Result<Self, consensus::Error> {
+        if bytes.len() < CHECKPOINT_MAC_LEN {
+            return Err(consensus::Error::InvalidMac);
+        }
+        let (data, mac) = bytes.split_at(bytes.len() - CHECKPOINT_MAC_LEN);
+        let expected = compute_mac(data, secret);
+        // Compare in constant time
+        let diff = expected
+            .iter()
+            .zip(mac.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return Err(consensus::Error::InvalidMac);
+        }
+        consensus::deserialize(data)
+    }
+}
+
+fn compute_mac(data: &[u8], secret: &[u8]) -> [u8; CHECKPOINT_MAC_LEN] {
+    hash::keyed_hash("checkpoint", secret, data)
+}
+
+impl<Ctx> Encodable for Checkpoint<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        s.write_all(&CHECKPOINT_MAGIC_BYTES)?;
+        let mut len = CHECKPOINT_MAGIC_BYTES.len();
+        len += CHECKPOINT_VERSION.consensus_encode(s)?;
+        len += self.swap_id.consensus_encode(s)?;
+        len += self.swap_role.consensus_encode(s)?;
+        len += self.public_offer.consensus_encode(s)?;
+        len += encode_option(&self.alice_parameters, s)?;
+        len += encode_option(&self.bob_parameters, s)?;
+        len += encode_option(&self.core_arbitrating_transactions, s)?;
+        Ok(len + encode_option(&self.secret_escrow, s)?)
+    }
+}
+
+impl<Ctx> Decodable for Checkpoint<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        // Versions before the magic bytes start directly with the version
+        let mut prefix = [0u8; 6];
+        d.read_exact(&mut prefix)?;
+        let (mut d, versions) = match prefix == CHECKPOINT_MAGIC_BYTES {
+            true => (
+                io::Read::chain(&prefix[6..], d),
+                MAGIC_BYTES_VERSION..=CHECKPOINT_VERSION,
+            ),
+            false => (io::Read::chain(&prefix[..], d), 1..=MAGIC_BYTES_VERSION - 1),
+        };
+        // Previous versions of the format are migrated, the missing fields are left empty
+        let version = u16::consensus_decode(&mut d)?;
+        if !versions.contains(&version) {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(&mut d)?,
+            swap_role: Decodable::consensus_decode(&mut d)?,
+            public_offer: Decodable::consensus_decode(&mut d)?,
+            alice_parameters: decode_option(&mut d)?,
+            bob_parameters: decode_option(&mut d)?,
+            core_arbitrating_transactions: match version {
+                1 => None,
+                _ => decode_option(&mut d)?,
+            },
+            secret_escrow: match version {
+                1 | 2 => None,
+                _ => decode_option(&mut d)?,
+            },
+        })
+    }
+}
+
+impl_strict_encoding!(Checkpoint<Ctx>, Ctx: Swap);
@@ -0,0 +1,104 @@
+//! Time sources for the deadline logic of the library.
+//!
+//! Deadlines, such as the stall timeouts of the setup phases or the expiry of the envelopes in a
+//! mailbox, are computed against a [`Clock`] returning the current time as the duration elapsed
+//! since an arbitrary fixed point, usually the UNIX epoch. The library never reads the system time
+//! directly: daemons use the [`SystemClock`], tests fast-forward a [`ManualClock`]
+//! deterministically, and targets without a system time, e.g. embedded or WASM, implement their
+//! own time source.
+//!
+//! A [`Duration`] is also a clock frozen at that time, so deadlines can still be checked against
+//! an explicit timestamp.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of time for deadline computations.
+pub trait Clock {
+    /// Return the current time as the duration elapsed since the fixed point of the clock.
+    fn now(&self) -> Duration;
+}
+
+impl Clock for Duration {
+    fn now(&self) -> Duration {
+        *self
+    }
+}
+
+impl<C> Clock for &C
+where
+    C: Clock + ?Sized,
+{
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+impl<C> Clock for Box<C>
+where
+    C: Clock + ?Sized,
+{
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+impl<C> Clock for Arc<C>
+where
+    C: Clock + ?Sized,
+{
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+/// The system time as the duration elapsed since the UNIX epoch. Not available on
+/// `wasm32-unknown-unknown` where reading the system time panics.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        // A system time before the epoch is a misconfigured host, treated as the epoch
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+/// A clock moving only when told to. Clones share the same time, so a test can keep a handle to
+/// fast-forward the clock given to the code under test.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    /// Create a clock stopped at the given time.
+    pub fn new(now: Duration) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Move the clock forward by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().expect("clock poisoned");
+        *now = now.saturating_add(duration);
+    }
+
+    /// Set the time of the clock, possibly in the past.
+    pub fn set(&self, time: Duration) {
+        *self.now.lock().expect("clock poisoned") = time;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().expect("clock poisoned")
+    }
+}
@@ -4,17 +4,51 @@
 //! Implementation on blockchain foreign types must follow the strict consensus encoding from the
 //! blockchain itself, Farcaster core will then wrap the serialization and treat it as a lenght
 //! prefixed vector of bytes when needed.
+//!
+//! # Determinism
+//!
+//! Offer identifiers, commitments, and signatures are computed over serialized data, serialization
+//! must then be byte-deterministic across platforms and crate versions. The consensus encoding
+//! guarantees that:
+//!
+//!  * integers are always encoded in little-endian with a fixed size, independently of the
+//!    platform
+//!  * fields are encoded in the order of declaration, enums with a fixed `u16` tag
+//!  * vectors are prefixed with their `u16` length and keep the order of their elements
+//!  * foreign types are encoded with the consensus encoding of their own blockchain
+//!  * durations are encoded as `u64` seconds and `u32` nanoseconds, unix timestamps as `u64`
+//!    seconds, both with range checks, see [`MAX_DURATION`] and [`MAX_TIMESTAMP`]
+//!  * amounts carried as native `u64` or `u128` integers are variable-width: their canonical
+//!    bytes are little-endian without trailing zero bytes, the length prefix carries the width
+//!
+//! Types relied upon for such computations implement [`Deterministic`] and expose their
+//! serialization with [`Deterministic::canonical_bytes`]. Any change in their serialization is a
+//! breaking change of the protocol.
 
 use hex::encode as hex_encode;
 use thiserror::Error;
 
+use std::convert::TryFrom;
 use std::error;
 use std::io;
 use std::str;
+use std::time::Duration;
+
+use crate::blockchain::Network;
+use crate::checkpoint::{Checkpoint, CHECKPOINT_MAGIC_BYTES};
+use crate::negotiation::{BlindedPublicOffer, PublicOffer};
+use crate::protocol_message::ProtocolMessage;
+use crate::swap::Swap;
+
+#[macro_use]
+pub mod annotate;
+
+pub use annotate::{annotate, Annotate, AnnotatedDump, Annotator, Failure, Span};
 
 /// Encoding and decoding errors and data transformation errors (when converting data from protocol
 /// messages into datum messages).
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// The type is not defined in the consensus.
     #[error("Unknown consensus type")]
@@ -25,12 +59,32 @@ pub enum Error {
     /// The magic bytes expected does not match.
     #[error("Incorrect magic bytes")]
     IncorrectMagicBytes,
+    /// The version of the serialized format is not supported.
+    #[error("Unsupported format version")]
+    UnsupportedVersion,
+    /// The message authentication code does not match the data.
+    #[error("Invalid message authentication code")]
+    InvalidMac,
+    /// The compression algorithm used is not supported by the receiver.
+    #[error("Unsupported compression algorithm")]
+    UnsupportedCompression,
     /// And I/O error.
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
     /// A generic parsing error.
     #[error("Parsing error: {0}")]
     ParseFailed(&'static str),
+    /// A protocol message failed to decode, with the message type and the offset in bytes from
+    /// the start of the message where the decoding failed.
+    #[error("Failed to decode message type {msg_type:#06x} at byte {offset}: {source}")]
+    Message {
+        /// The type of the message.
+        msg_type: u16,
+        /// The offset where the decoding failed.
+        offset: u64,
+        /// The decoding error.
+        source: Box<Error>,
+    },
     /// Any Consensus error not part of this list.
     #[error("Consensus error: {0}")]
     Other(Box<dyn error::Error + Send + Sync>),
@@ -58,6 +112,79 @@ impl Error {
             _ => None,
         }
     }
+
+    /// Returns the origin of the error: data received from a peer that is malformed, or a failure
+    /// of the local node when reading or writing the data.
+    pub fn fault(&self) -> Fault {
+        match self {
+            Self::Io(e) => match e.kind() {
+                io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData => Fault::Peer,
+                _ => Fault::Local,
+            },
+            Self::Message { source, .. } => source.fault(),
+            _ => Fault::Peer,
+        }
+    }
+}
+
+/// The origin of an encoding or decoding error, see [`Error::fault`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// The data received are malformed or not supported, the peer is misbehaving or incompatible.
+    Peer,
+    /// The local node failed, e.g. an I/O error of the underlying stream.
+    Local,
+}
+
+#[cfg(feature = "lightning")]
+impl From<lightning_encoding::Error> for Error {
+    fn from(e: lightning_encoding::Error) -> Self {
+        match e {
+            lightning_encoding::Error::Io(e) => Self::Io(e.into()),
+            lightning_encoding::Error::BigSizeNotCanonical => {
+                Self::ParseFailed("BigSize is not canonical")
+            }
+            lightning_encoding::Error::BigSizeEof => Self::Io(io::ErrorKind::UnexpectedEof.into()),
+            lightning_encoding::Error::DataNotEntirelyConsumed => {
+                Self::ParseFailed("data not consumed entirely when explicitly deserializing")
+            }
+            lightning_encoding::Error::DataIntegrityError(e) => Self::new(e),
+        }
+    }
+}
+
+#[cfg(feature = "lightning")]
+impl From<Error> for lightning_encoding::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => Self::Io(e.into()),
+            e => Self::DataIntegrityError(e.to_string()),
+        }
+    }
+}
+
+// Reader counting the bytes consumed, used to report where the decoding of a message failed
+pub(crate) struct CountingReader<'a, R: io::Read> {
+    inner: &'a mut R,
+    count: u64,
+}
+
+impl<'a, R: io::Read> CountingReader<'a, R> {
+    pub(crate) fn new(inner: &'a mut R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<'a, R: io::Read> io::Read for CountingReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count += len as u64;
+        Ok(len)
+    }
 }
 
 /// Data that can be represented in a canonical bytes format.
@@ -74,6 +201,26 @@ pub trait CanonicalBytes {
         Self: Sized;
 }
 
+/// Marker for types with a byte-deterministic serialization, two equal values always produce the
+/// same bytes. See the [module level documentation](self) for the guarantees.
+pub trait Deterministic: Encodable {
+    /// Returns the canonical serialization of the element, used to compute identifiers,
+    /// commitments, and signatures.
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = Vec::new();
+        self.consensus_encode(&mut encoder)
+            .expect("Encoding into a vector does not fail");
+        encoder
+    }
+}
+
+/// Marker for types converting from and into their consensus encoding with the standard
+/// conversion traits, e.g. `PublicOffer::try_from(&bytes[..])` and `Vec::<u8>::from(&offer)`.
+/// Decoding fails if the bytes are not consumed entirely, as with [`deserialize`].
+///
+/// The conversions are implemented for all the types strictly encoded in Farcaster core.
+pub trait BytesConvertible: Encodable + Decodable {}
+
 /// Encode an object into a vector
 pub fn serialize<T: Encodable + std::fmt::Debug + ?Sized>(data: &T) -> Vec<u8> {
     let mut encoder = Vec::new();
@@ -82,6 +229,14 @@ pub fn serialize<T: Encodable + std::fmt::Debug + ?Sized>(data: &T) -> Vec<u8> {
     encoder
 }
 
+// Encode an object into a vector of bytes, used by types not implementing `Debug`
+pub(crate) fn encoded<T: Encodable + ?Sized>(data: &T) -> Vec<u8> {
+    let mut encoder = Vec::new();
+    data.consensus_encode(&mut encoder)
+        .expect("in-memory writers don't error");
+    encoder
+}
+
 /// Encode an object into a hex-encoded string
 pub fn serialize_hex<T: Encodable + std::fmt::Debug + ?Sized>(data: &T) -> String {
     hex_encode(serialize(data))
@@ -112,6 +267,254 @@ pub fn deserialize_partial<T: Decodable>(data: &[u8]) -> Result<(T, usize), Erro
     Ok((rv, consumed))
 }
 
+/// Padding buckets applied on encoded messages before being sent over the encrypted transport.
+///
+/// A padded message is composed of the original message length as a 4 bytes little endian
+/// integer, the message itself, and a sequence of zeros filling the message up to the smallest
+/// bucket size able to contain it. If the message is larger than the largest bucket, the message
+/// is padded to the next multiple of the largest bucket. Padding is deterministic, two messages of
+/// the same length always produce padded messages of the same length.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Padding {
+    buckets: Vec<u32>,
+}
+
+impl Padding {
+    /// Create a new padding scheme with the list of bucket sizes, in bytes. Buckets are sorted and
+    /// deduplicated, null sizes are removed.
+    pub fn new(mut buckets: Vec<u32>) -> Self {
+        buckets.retain(|b| *b != 0);
+        buckets.sort_unstable();
+        buckets.dedup();
+        Self { buckets }
+    }
+
+    /// Return the list of bucket sizes in ascending order.
+    pub fn buckets(&self) -> &[u32] {
+        &self.buckets
+    }
+
+    /// Return the total size of a padded message containing `len` bytes of data, the size includes
+    /// the 4 bytes length prefix.
+    pub fn padded_len(&self, len: usize) -> usize {
+        let len = len + 4;
+        match self.buckets.iter().find(|b| **b as usize >= len) {
+            Some(bucket) => *bucket as usize,
+            None => match self.buckets.last() {
+                Some(largest) => {
+                    let largest = *largest as usize;
+                    len.div_ceil(largest) * largest
+                }
+                None => len,
+            },
+        }
+    }
+
+    /// Pad the data to the bucket size matching its length.
+    pub fn pad(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        if data.len() > u32::MAX as usize - 4 {
+            return Err(Error::ParseFailed("data too long to be padded"));
+        }
+        let mut padded = Vec::with_capacity(self.padded_len(data.len()));
+        padded.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        padded.extend_from_slice(data);
+        padded.resize(self.padded_len(data.len()), 0u8);
+        Ok(padded)
+    }
+}
+
+impl Default for Padding {
+    fn default() -> Self {
+        Self::new(vec![256, 1024, 4096, 16384, 65536])
+    }
+}
+
+impl Encodable for Padding {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.buckets.consensus_encode(s)
+    }
+}
+
+impl Decodable for Padding {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
+        Ok(Self::new(Decodable::consensus_decode(d)?))
+    }
+}
+
+/// Remove the padding added with [`Padding::pad`] and return the original data. The padding must
+/// be filled with zeros, any other value is rejected.
+pub fn unpad(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < 4 {
+        return Err(Error::ParseFailed("padded data too short"));
+    }
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&data[..4]);
+    let len = u32::from_le_bytes(len) as usize;
+    if len > data.len() - 4 {
+        return Err(Error::ParseFailed("padded data length prefix out of range"));
+    }
+    if data[4 + len..].iter().any(|b| *b != 0) {
+        return Err(Error::ParseFailed("padding must be filled with zeros"));
+    }
+    Ok(data[4..4 + len].to_vec())
+}
+
+/// Encode an object and pad the result with the given padding scheme.
+pub fn serialize_padded<T: Encodable + std::fmt::Debug + ?Sized>(
+    data: &T,
+    padding: &Padding,
+) -> Result<Vec<u8>, Error> {
+    padding.pad(&serialize(data))
+}
+
+/// Remove the padding and deserialize an object, will error if said deserialization doesn't
+/// consume the entire unpadded data.
+pub fn deserialize_padded<T: Decodable>(data: &[u8]) -> Result<T, Error> {
+    deserialize(&unpad(data)?)
+}
+
+/// Messages smaller than this size in bytes are never compressed.
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Maximum size in bytes of a decompressed message, larger messages are rejected to protect
+/// against decompression bombs.
+pub const MAX_DECOMPRESSED_LEN: usize = 1 << 20;
+
+/// Compression algorithms applied on large encoded messages, e.g. messages embedding multiple
+/// partial transactions, before being sent over bandwidth constrained links.
+///
+/// A compressed frame is composed of the algorithm identifier as one byte followed by the,
+/// possibly compressed, encoded message. Compression is used only if both participants signal
+/// the feature, see [`FEATURE_COMPRESSION`](crate::negotiation::FEATURE_COMPRESSION).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// The message is not compressed.
+    None,
+    /// The message is compressed with deflate (RFC 1951).
+    Deflate,
+    /// The message is compressed with zstd (RFC 8878).
+    Zstd,
+}
+
+impl Encodable for Compression {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            Compression::None => 0x00u8.consensus_encode(s),
+            Compression::Deflate => 0x01u8.consensus_encode(s),
+            Compression::Zstd => 0x02u8.consensus_encode(s),
+        }
+    }
+}
+
+impl Decodable for Compression {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
+        match Decodable::consensus_decode(d)? {
+            0x00u8 => Ok(Compression::None),
+            0x01u8 => Ok(Compression::Deflate),
+            0x02u8 => Ok(Compression::Zstd),
+            _ => Err(Error::UnsupportedCompression),
+        }
+    }
+}
+
+/// A compression codec provided by the daemon, e.g. backed by a zstd or deflate library, used to
+/// compress and decompress frames with [`serialize_compressed`] and [`deserialize_compressed`].
+pub trait Compressor {
+    /// The algorithm implemented by the codec.
+    fn algorithm(&self) -> Compression;
+
+    /// Compress the data.
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Decompress the data, must fail if the decompressed data is larger than `max_len`.
+    fn decompress(&self, data: &[u8], max_len: usize) -> Result<Vec<u8>, Error>;
+}
+
+/// Encode an object in a compressed frame. The encoded object is compressed only if a
+/// compressor is given, the encoded object is larger than [`COMPRESSION_THRESHOLD`], and the
+/// compression reduces its size.
+pub fn serialize_compressed<T: Encodable + std::fmt::Debug + ?Sized>(
+    data: &T,
+    compressor: Option<&dyn Compressor>,
+) -> Result<Vec<u8>, Error> {
+    let encoded = serialize(data);
+    if let Some(compressor) = compressor {
+        if encoded.len() >= COMPRESSION_THRESHOLD && compressor.algorithm() != Compression::None {
+            let compressed = compressor.compress(&encoded)?;
+            if compressed.len() < encoded.len() {
+                let mut frame = serialize(&compressor.algorithm());
+                frame.extend(compressed);
+                return Ok(frame);
+            }
+        }
+    }
+    let mut frame = serialize(&Compression::None);
+    frame.extend(encoded);
+    Ok(frame)
+}
+
+/// Decode an object from a compressed frame, transparently decompressing it if needed. Fails with
+/// [`Error::UnsupportedCompression`] if the frame is compressed with an algorithm different from
+/// the compressor one.
+pub fn deserialize_compressed<T: Decodable>(
+    data: &[u8],
+    compressor: Option<&dyn Compressor>,
+) -> Result<T, Error> {
+    let (algorithm, consumed) = deserialize_partial::<Compression>(data)?;
+    let payload = &data[consumed..];
+    match (algorithm, compressor) {
+        (Compression::None, _) => deserialize(payload),
+        (algorithm, Some(compressor)) if compressor.algorithm() == algorithm => {
+            deserialize(&compressor.decompress(payload, MAX_DECOMPRESSED_LEN)?)
+        }
+        _ => Err(Error::UnsupportedCompression),
+    }
+}
+
+/// An entity recognized by [`try_decode_any`] from an arbitrary blob of bytes.
+#[derive(Debug, Clone)]
+pub enum DecodedEntity<Ctx: Swap> {
+    /// A public offer, recognized by its magic bytes.
+    PublicOffer(PublicOffer<Ctx>),
+    /// A blinded public offer, recognized by its magic bytes.
+    BlindedPublicOffer(BlindedPublicOffer<Ctx>),
+    /// A protocol message, recognized by its message type prefix.
+    ProtocolMessage(ProtocolMessage<Ctx>),
+    /// A swap checkpoint, recognized by its magic bytes.
+    Checkpoint(Box<Checkpoint<Ctx>>),
+}
+
+/// Try to recognize and decode an arbitrary farcaster blob, e.g. received from logs, pastes or QR
+/// codes. Public offers, blinded public offers and checkpoints are recognized with their magic
+/// bytes, other blobs are decoded as protocol messages with their type prefix. The blob is
+/// expected to be consumed entirely.
+///
+/// Checkpoints persisted before version 4 of the format have no magic bytes and are not
+/// recognized, they must be decoded directly as a [`Checkpoint`].
+///
+/// The blob is decoded as is, use [`try_decode_any_str`] to decode the hex representation of an
+/// entity.
+pub fn try_decode_any<Ctx: Swap>(data: &[u8]) -> Result<DecodedEntity<Ctx>, Error> {
+    let magic_bytes = &data[..data.len().min(6)];
+    if Network::from_offer_magic_bytes(magic_bytes).is_some() {
+        return Ok(DecodedEntity::PublicOffer(deserialize(data)?));
+    }
+    if Network::from_blinded_offer_magic_bytes(magic_bytes).is_some() {
+        return Ok(DecodedEntity::BlindedPublicOffer(deserialize(data)?));
+    }
+    if data.starts_with(&CHECKPOINT_MAGIC_BYTES) {
+        return Ok(DecodedEntity::Checkpoint(Box::new(deserialize(data)?)));
+    }
+    Ok(DecodedEntity::ProtocolMessage(deserialize(data)?))
+}
+
+/// Try to recognize and decode the hex representation of a farcaster blob, surrounding
+/// whitespaces are ignored, see [`try_decode_any`].
+pub fn try_decode_any_str<Ctx: Swap>(data: &str) -> Result<DecodedEntity<Ctx>, Error> {
+    let bytes = hex::decode(data.trim()).map_err(|_| Error::ParseFailed("invalid hex string"))?;
+    try_decode_any(&bytes)
+}
+
 /// Data which can be encoded in a consensus-consistent way.
 pub trait Encodable {
     /// Encode an object with a well-defined format, should only ever error if
@@ -134,7 +537,7 @@ where
     #[inline]
     fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
         if self.len() > u16::MAX as usize {
-            return Err(io::Error::new(io::ErrorKind::Other, "Value is too long"));
+            return Err(io::Error::other("Value is too long"));
         }
         let mut len = (self.len() as u16).consensus_encode(s)?;
         for t in self {
@@ -176,6 +579,40 @@ impl Decodable for [u8; 6] {
     }
 }
 
+impl Encodable for [u8; 16] {
+    #[inline]
+    fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
+        s.write_all(&self[..])?;
+        Ok(16)
+    }
+}
+
+impl Decodable for [u8; 16] {
+    #[inline]
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
+        let mut buffer = [0u8; 16];
+        d.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+impl Encodable for [u8; 32] {
+    #[inline]
+    fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
+        s.write_all(&self[..])?;
+        Ok(32)
+    }
+}
+
+impl Decodable for [u8; 32] {
+    #[inline]
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
+        let mut buffer = [0u8; 32];
+        d.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
 macro_rules! unwrap_vec_ref {
     ($reader: ident) => {{
         let v: Vec<u8> = $crate::consensus::Decodable::consensus_decode($reader)?;
@@ -285,6 +722,176 @@ impl Decodable for u64 {
     }
 }
 
+impl Encodable for u128 {
+    #[inline]
+    fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
+        s.write_all(&self.to_le_bytes())?;
+        Ok(16)
+    }
+}
+
+impl Decodable for u128 {
+    #[inline]
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
+        let mut buffer = [0u8; 16];
+        d.read_exact(&mut buffer)?;
+        Ok(u128::from_le_bytes(buffer))
+    }
+}
+
+/// The longest duration accepted in encoded data, a hundred years. Longer durations are absurd
+/// and rejected, e.g. when decoding corrupted data.
+pub const MAX_DURATION: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+/// The latest unix timestamp accepted in encoded data, the last second of the year 9999. Later
+/// timestamps are absurd and rejected, e.g. when decoding corrupted data.
+pub const MAX_TIMESTAMP: u64 = 253_402_300_799;
+
+impl Encodable for Duration {
+    #[inline]
+    fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
+        if *self > MAX_DURATION {
+            return Err(io::Error::other("Duration is too long"));
+        }
+        let len = self.as_secs().consensus_encode(s)?;
+        Ok(len + self.subsec_nanos().consensus_encode(s)?)
+    }
+}
+
+impl Decodable for Duration {
+    #[inline]
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
+        let secs = u64::consensus_decode(d)?;
+        let nanos = u32::consensus_decode(d)?;
+        if nanos >= 1_000_000_000 {
+            return Err(Error::ParseFailed("invalid duration nanoseconds"));
+        }
+        let duration = Duration::new(secs, nanos);
+        match duration <= MAX_DURATION {
+            true => Ok(duration),
+            false => Err(Error::ParseFailed("duration is too long")),
+        }
+    }
+}
+
+/// A unix timestamp with a precision of one second, e.g. the expiry time of an offer in a book.
+/// Timestamps are given as durations since the unix epoch, see [`Clock`](crate::clock::Clock),
+/// and are at most [`MAX_TIMESTAMP`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    /// Create a timestamp from the number of seconds since the unix epoch, fails if later than
+    /// [`MAX_TIMESTAMP`].
+    pub fn from_secs(secs: u64) -> Result<Self, Error> {
+        match secs <= MAX_TIMESTAMP {
+            true => Ok(Self(secs)),
+            false => Err(Error::ParseFailed("timestamp is too far in the future")),
+        }
+    }
+
+    /// Return the number of seconds since the unix epoch.
+    pub fn as_secs(&self) -> u64 {
+        self.0
+    }
+
+    /// Return the timestamp as a duration since the unix epoch.
+    pub fn as_duration(&self) -> Duration {
+        Duration::from_secs(self.0)
+    }
+}
+
+impl TryFrom<Duration> for Timestamp {
+    type Error = Error;
+
+    /// Convert a duration since the unix epoch, the sub-second part is truncated.
+    fn try_from(duration: Duration) -> Result<Self, Error> {
+        Self::from_secs(duration.as_secs())
+    }
+}
+
+impl From<Timestamp> for Duration {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.as_duration()
+    }
+}
+
+impl Encodable for Timestamp {
+    #[inline]
+    fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
+        self.0.consensus_encode(s)
+    }
+}
+
+impl Decodable for Timestamp {
+    #[inline]
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
+        Self::from_secs(u64::consensus_decode(d)?)
+    }
+}
+
+/// A type code unknown to this implementation, e.g. a [`TxLabel`](crate::transaction::TxLabel)
+/// added by a later version, preserved when re-encoded. Unknown codes are only created by the
+/// decoders: a known code always decodes to its own variant, so encoding round-trips.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnknownCode(u16);
+
+impl UnknownCode {
+    pub(crate) fn new(code: u16) -> Self {
+        Self(code)
+    }
+
+    /// Return the code as sent on the wire.
+    pub fn code(&self) -> u16 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for UnknownCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#06x}", self.0)
+    }
+}
+
+impl Encodable for UnknownCode {
+    #[inline]
+    fn consensus_encode<S: io::Write>(&self, s: &mut S) -> Result<usize, io::Error> {
+        self.0.consensus_encode(s)
+    }
+}
+
+// Amounts of the assets without foreign amount type, see the module documentation. Encodings
+// with trailing zero bytes are rejected, so each amount has a unique encoding.
+macro_rules! impl_var_amount {
+    ($ty:ty) => {
+        impl CanonicalBytes for $ty {
+            fn as_canonical_bytes(&self) -> Vec<u8> {
+                let bytes = self.to_le_bytes();
+                let width = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+                bytes[..width].to_vec()
+            }
+
+            fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error>
+            where
+                Self: Sized,
+            {
+                let mut buffer = [0u8; std::mem::size_of::<$ty>()];
+                if bytes.len() > buffer.len() {
+                    return Err(Error::ParseFailed("amount overflow"));
+                }
+                if bytes.last() == Some(&0) {
+                    return Err(Error::ParseFailed("non-minimal amount encoding"));
+                }
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                Ok(<$ty>::from_le_bytes(buffer))
+            }
+        }
+    };
+}
+
+impl_var_amount!(u64);
+impl_var_amount!(u128);
+
 impl<T> Encodable for Option<T>
 where
     T: CanonicalBytes,
@@ -316,6 +923,28 @@ where
     }
 }
 
+/// Encode an optional value as a presence flag followed by the consensus encoding of the value.
+/// Optional values implementing [`CanonicalBytes`] are encoded with the [`Option`] consensus
+/// implementation instead, the canonical bytes are then prefixed by their length.
+pub fn encode_option<T: Encodable, W: io::Write>(
+    value: &Option<T>,
+    s: &mut W,
+) -> Result<usize, io::Error> {
+    match value {
+        Some(t) => Ok(1u8.consensus_encode(s)? + t.consensus_encode(s)?),
+        None => 0u8.consensus_encode(s),
+    }
+}
+
+/// Decode an optional value encoded with [`encode_option`].
+pub fn decode_option<T: Decodable, D: io::Read>(d: &mut D) -> Result<Option<T>, Error> {
+    match u8::consensus_decode(d)? {
+        1u8 => Ok(Some(Decodable::consensus_decode(d)?)),
+        0u8 => Ok(None),
+        _ => Err(Error::UnknownType),
+    }
+}
+
 impl CanonicalBytes for String {
     fn as_canonical_bytes(&self) -> Vec<u8> {
         self.as_bytes().into()
@@ -345,8 +974,54 @@ impl Decodable for String {
     }
 }
 
+macro_rules! impl_bytes_conversions {
+    ($thing:ty, $($args:tt)*) => {
+        impl<$($args)*> $crate::consensus::BytesConvertible for $thing {}
+
+        impl<'a, $($args)*> ::std::convert::TryFrom<&'a [u8]> for $thing {
+            type Error = $crate::consensus::Error;
+
+            fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+                $crate::consensus::deserialize(bytes)
+            }
+        }
+
+        impl<'a, $($args)*> From<&'a $thing> for Vec<u8> {
+            fn from(thing: &'a $thing) -> Self {
+                let mut encoder = Vec::new();
+                $crate::consensus::Encodable::consensus_encode(thing, &mut encoder)
+                    .expect("Encoding into a vector does not fail");
+                encoder
+            }
+        }
+    };
+    ($thing:ty) => {
+        impl $crate::consensus::BytesConvertible for $thing {}
+
+        impl<'a> ::std::convert::TryFrom<&'a [u8]> for $thing {
+            type Error = $crate::consensus::Error;
+
+            fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+                $crate::consensus::deserialize(bytes)
+            }
+        }
+
+        impl<'a> From<&'a $thing> for Vec<u8> {
+            fn from(thing: &'a $thing) -> Self {
+                let mut encoder = Vec::new();
+                $crate::consensus::Encodable::consensus_encode(thing, &mut encoder)
+                    .expect("Encoding into a vector does not fail");
+                encoder
+            }
+        }
+    };
+}
+
 macro_rules! impl_strict_encoding {
     ($thing:ty, $($args:tt)*) => {
+        impl_bytes_conversions!($thing, $($args)*);
+
+        #[cfg(feature = "strict")]
         impl<$($args)*> ::strict_encoding::StrictEncode for $thing {
             fn strict_encode<E: ::std::io::Write>(
                 &self,
@@ -357,6 +1032,7 @@ macro_rules! impl_strict_encoding {
             }
         }
 
+        #[cfg(feature = "strict")]
         impl<$($args)*> ::strict_encoding::StrictDecode for $thing {
             fn strict_decode<D: ::std::io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
                 $crate::consensus::Decodable::consensus_decode(&mut d)
@@ -365,6 +1041,9 @@ macro_rules! impl_strict_encoding {
         }
     };
     ($thing:ty) => {
+        impl_bytes_conversions!($thing);
+
+        #[cfg(feature = "strict")]
         impl strict_encoding::StrictEncode for $thing {
             fn strict_encode<E: ::std::io::Write>(
                 &self,
@@ -375,6 +1054,7 @@ macro_rules! impl_strict_encoding {
             }
         }
 
+        #[cfg(feature = "strict")]
         impl strict_encoding::StrictDecode for $thing {
             fn strict_decode<D: ::std::io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
                 $crate::consensus::Decodable::consensus_decode(&mut d)
@@ -384,6 +1064,26 @@ macro_rules! impl_strict_encoding {
     };
 }
 
+// Implement equality and hashing over the consensus encoding, for generic types whose parameters,
+// e.g. the swap context, do not implement the traits themselves.
+macro_rules! impl_eq_by_encoding {
+    ($thing:ty, $($args:tt)*) => {
+        impl<$($args)*> PartialEq for $thing {
+            fn eq(&self, other: &Self) -> bool {
+                $crate::consensus::encoded(self) == $crate::consensus::encoded(other)
+            }
+        }
+
+        impl<$($args)*> Eq for $thing {}
+
+        impl<$($args)*> ::std::hash::Hash for $thing {
+            fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                state.write(&$crate::consensus::encoded(self));
+            }
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -408,4 +1108,104 @@ mod tests {
         let vec = vec![0x41; u16::MAX.into()];
         assert_eq!(deserialize::<Vec<u8>>(&serialize(&vec)[..]).unwrap(), vec);
     }
+
+    #[test]
+    fn pad_to_buckets() {
+        let padding = Padding::new(vec![64, 16, 32]);
+        assert_eq!(padding.buckets(), &[16, 32, 64]);
+        for len in [0usize, 1, 12, 13, 60, 61, 200].iter() {
+            let data = vec![0x41; *len];
+            let padded = padding.pad(&data).unwrap();
+            assert_eq!(padded.len() % 16, 0);
+            assert_eq!(padded.len(), padding.padded_len(*len));
+            assert_eq!(unpad(&padded).unwrap(), data);
+        }
+        assert_eq!(padding.padded_len(12), 16);
+        assert_eq!(padding.padded_len(13), 32);
+        assert_eq!(padding.padded_len(200), 256);
+    }
+
+    #[test]
+    fn padded_message() {
+        let vec: Vec<u8> = vec![0xde, 0xad, 0xbe, 0xef];
+        let padding = Padding::default();
+        let padded = serialize_padded(&vec, &padding).unwrap();
+        assert_eq!(padded.len(), 256);
+        assert_eq!(deserialize_padded::<Vec<u8>>(&padded).unwrap(), vec);
+        assert_eq!(
+            deserialize::<Padding>(&serialize(&padding)).unwrap(),
+            padding
+        );
+    }
+
+    // Run-length encoding of the bytes, standing as a compression library for the tests
+    struct RunLength;
+
+    impl Compressor for RunLength {
+        fn algorithm(&self) -> Compression {
+            Compression::Deflate
+        }
+
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+            let mut out = vec![];
+            for chunk in data.chunk_by(|a, b| a == b) {
+                for run in chunk.chunks(255) {
+                    out.extend_from_slice(&[run.len() as u8, run[0]]);
+                }
+            }
+            Ok(out)
+        }
+
+        fn decompress(&self, data: &[u8], max_len: usize) -> Result<Vec<u8>, Error> {
+            let mut out = vec![];
+            for pair in data.chunks(2) {
+                out.extend(std::iter::repeat_n(pair[1], pair[0] as usize));
+                if out.len() > max_len {
+                    return Err(Error::ParseFailed("decompressed data too large"));
+                }
+            }
+            Ok(out)
+        }
+    }
+
+    #[test]
+    fn compressed_message() {
+        let vec: Vec<u8> = vec![0x41; 4000];
+        let compressed = serialize_compressed(&vec, Some(&RunLength)).unwrap();
+        assert_eq!(compressed[0], 0x01);
+        assert!(compressed.len() < 100);
+        assert_eq!(
+            deserialize_compressed::<Vec<u8>>(&compressed, Some(&RunLength)).unwrap(),
+            vec
+        );
+        // The receiver does not support the algorithm
+        assert!(matches!(
+            deserialize_compressed::<Vec<u8>>(&compressed, None),
+            Err(Error::UnsupportedCompression)
+        ));
+
+        // Small messages and messages not benefiting from compression are sent uncompressed
+        let small: Vec<u8> = vec![0x41; 10];
+        let frame = serialize_compressed(&small, Some(&RunLength)).unwrap();
+        assert_eq!(frame[0], 0x00);
+        assert_eq!(
+            deserialize_compressed::<Vec<u8>>(&frame, None).unwrap(),
+            small
+        );
+        let random: Vec<u8> = (0..2000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let frame = serialize_compressed(&random, Some(&RunLength)).unwrap();
+        assert_eq!(frame[0], 0x00);
+        assert!(deserialize_compressed::<Vec<u8>>(&[0x03, 0x00], None).is_err());
+    }
+
+    #[test]
+    fn invalid_padding() {
+        assert!(unpad(&[0x01, 0x00, 0x00]).is_err());
+        assert!(unpad(&[0x05, 0x00, 0x00, 0x00, 0x41]).is_err());
+        assert!(unpad(&[0x01, 0x00, 0x00, 0x00, 0x41, 0x01]).is_err());
+        assert_eq!(
+            unpad(&[0x01, 0x00, 0x00, 0x00, 0x41, 0x00]).unwrap(),
+            vec![0x41]
+        );
+    }
 }
@@ -0,0 +1,348 @@
+//! Annotated hex dumps of serialized structures.
+//!
+//! [`annotate`] decodes serialized data field by field and labels each byte range with the field
+//! it encodes, e.g. to inspect an offer or a message in a debugging CLI or to report where the
+//! decoding failed: "decode failed at bytes 34..42 (offer.cancel_timelock)". Types annotated
+//! implement [`Annotate`], listing their fields in encoding order, usually with
+//! `impl_annotate!`.
+//!
+//! Nested structures are labeled with the path of their fields, separated by dots, and both the
+//! nested structure and its fields get a [`Span`].
+
+use std::fmt;
+use std::io;
+use std::ops::Range;
+
+use crate::consensus::{CanonicalBytes, Decodable, Error};
+
+/// A byte range of the serialized data and the field it encodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// The byte range of the field
+    pub range: Range<usize>,
+    /// The path of the field, e.g. `offer.cancel_timelock`
+    pub field: String,
+    /// The nesting depth of the field, zero for the fields of the top-level structure
+    pub depth: usize,
+}
+
+/// The field whose decoding failed, with the bytes read before failing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// The byte range read when decoding the field
+    pub range: Range<usize>,
+    /// The path of the field
+    pub field: String,
+    /// The decoding error
+    pub error: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "decode failed at bytes {}..{} ({}): {}",
+            self.range.start, self.range.end, self.field, self.error
+        )
+    }
+}
+
+/// Serialized data with the byte ranges of the fields it encodes, see [`annotate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedDump {
+    bytes: Vec<u8>,
+    spans: Vec<Span>,
+    failure: Option<Failure>,
+}
+
+impl AnnotatedDump {
+    /// Return the annotated bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Return the spans of the fields decoded, ordered by position and depth.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Return the field whose decoding failed, `None` if the data decoded entirely.
+    pub fn failure(&self) -> Option<&Failure> {
+        self.failure.as_ref()
+    }
+
+    /// Return true if the data decoded entirely without trailing bytes.
+    pub fn is_complete(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// Return the innermost field encoded at the byte offset, if any.
+    pub fn field_at(&self, offset: usize) -> Option<&Span> {
+        self.spans
+            .iter()
+            .filter(|span| span.range.contains(&offset))
+            .max_by_key(|span| span.depth)
+    }
+
+    /// Return the bytes of a span.
+    pub fn bytes_of(&self, span: &Span) -> &[u8] {
+        &self.bytes[span.range.clone()]
+    }
+}
+
+impl fmt::Display for AnnotatedDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for span in self.spans.iter() {
+            let range = format!("{}..{}", span.range.start, span.range.end);
+            writeln!(
+                f,
+                "{:>11}  {}{:<w$}  {}",
+                range,
+                "  ".repeat(span.depth),
+                span.field,
+                hex::encode(self.bytes_of(span)),
+                w = 32usize.saturating_sub(2 * span.depth),
+            )?;
+        }
+        if let Some(failure) = &self.failure {
+            writeln!(f, "{}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// Types whose serialization can be annotated, see [`annotate`].
+pub trait Annotate {
+    /// Decode the fields of the type in encoding order with the annotator.
+    fn annotate(annotator: &mut Annotator) -> Result<(), Error>;
+}
+
+/// Decodes serialized data field by field and records the byte range of each field.
+#[derive(Debug)]
+pub struct Annotator<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    path: Vec<&'static str>,
+    spans: Vec<Span>,
+    failure: Option<Failure>,
+}
+
+impl<'a> Annotator<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            path: vec![],
+            spans: vec![],
+            failure: None,
+        }
+    }
+
+    /// Return the position of the next byte to decode.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path_to(&self, name: &str) -> String {
+        let mut path = self.path.join(".");
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(name);
+        path
+    }
+
+    /// Decode a field with a custom decoder, e.g. a field validated while decoding or encoded
+    /// with another encoding.
+    pub fn decode_with<T>(
+        &mut self,
+        name: &'static str,
+        decoder: impl FnOnce(&mut io::Cursor<&[u8]>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let start = self.position;
+        let mut cursor = io::Cursor::new(&self.bytes[start..]);
+        let res = decoder(&mut cursor);
+        let end = start + cursor.position() as usize;
+        self.position = end;
+        match res {
+            Ok(value) => {
+                self.spans.push(Span {
+                    range: start..end,
+                    field: self.path_to(name),
+                    depth: self.path.len(),
+                });
+                Ok(value)
+            }
+            Err(error) => {
+                self.fail(start..end, self.path_to(name), &error);
+                Err(error)
+            }
+        }
+    }
+
+    fn fail(&mut self, range: Range<usize>, field: String, error: &Error) {
+        // Only the innermost failing field is reported
+        if self.failure.is_none() {
+            self.failure = Some(Failure {
+                range,
+                field,
+                error: error.to_string(),
+            });
+        }
+    }
+
+    /// Decode a field encoded with its consensus encoding.
+    pub fn decode<T: Decodable>(&mut self, name: &'static str) -> Result<T, Error> {
+        self.decode_with(name, |d| T::consensus_decode(d))
+    }
+
+    /// Decode a field encoded with its canonical bytes prefixed with their length.
+    pub fn canonical<T: CanonicalBytes>(&mut self, name: &'static str) -> Result<T, Error> {
+        self.decode_with(name, |d| {
+            T::from_canonical_bytes(Vec::<u8>::consensus_decode(d)?.as_ref())
+        })
+    }
+
+    /// Decode an optional field encoded with a presence flag followed by its consensus
+    /// encoding.
+    pub fn optional<T: Decodable>(&mut self, name: &'static str) -> Result<Option<T>, Error> {
+        self.decode_with(name, |d| match u8::consensus_decode(d)? {
+            1u8 => Ok(Some(T::consensus_decode(d)?)),
+            0u8 => Ok(None),
+            _ => Err(Error::UnknownType),
+        })
+    }
+
+    /// Annotate a nested structure, its fields are labeled with the name of the structure.
+    pub fn nested<T: Annotate>(&mut self, name: &'static str) -> Result<(), Error> {
+        self.nested_with(name, T::annotate)
+    }
+
+    /// Annotate a nested structure with a custom annotation, e.g. a structure whose layout
+    /// depends on a previously decoded field.
+    pub fn nested_with(
+        &mut self,
+        name: &'static str,
+        annotate: impl FnOnce(&mut Annotator) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let start = self.position;
+        let index = self.spans.len();
+        self.spans.push(Span {
+            range: start..start,
+            field: self.path_to(name),
+            depth: self.path.len(),
+        });
+        self.path.push(name);
+        let res = annotate(self);
+        self.path.pop();
+        let end = self.position;
+        match res {
+            Ok(()) => {
+                self.spans[index].range = start..end;
+                Ok(())
+            }
+            Err(error) => {
+                let span = self.spans.remove(index);
+                self.fail(start..end, span.field, &error);
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Annotate the serialized data of a type, e.g. a public offer or a protocol message. The
+/// annotation stops at the first field failing to decode, the bytes left after a complete
+/// decoding are reported as a failure too.
+pub fn annotate<T: Annotate>(bytes: &[u8]) -> AnnotatedDump {
+    let mut annotator = Annotator::new(bytes);
+    match T::annotate(&mut annotator) {
+        Ok(()) if annotator.position < bytes.len() => {
+            annotator.failure = Some(Failure {
+                range: annotator.position..bytes.len(),
+                field: "trailing bytes".to_string(),
+                error: "data not consumed entirely".to_string(),
+            });
+        }
+        // Failures outside of a field, e.g. a validation across fields
+        Err(error) => {
+            let position = annotator.position;
+            annotator.fail(position..position, "unknown".to_string(), &error);
+        }
+        Ok(()) => (),
+    }
+    AnnotatedDump {
+        bytes: bytes.to_vec(),
+        spans: annotator.spans,
+        failure: annotator.failure,
+    }
+}
+
+// Hidden helpers inferring the type of a field from an accessor, used by `impl_annotate!`
+#[doc(hidden)]
+pub fn decode_field<S, T: Decodable>(
+    annotator: &mut Annotator,
+    name: &'static str,
+    _: fn(&S) -> &T,
+) -> Result<(), Error> {
+    annotator.decode::<T>(name).map(|_| ())
+}
+
+#[doc(hidden)]
+pub fn canonical_field<S, T: CanonicalBytes>(
+    annotator: &mut Annotator,
+    name: &'static str,
+    _: fn(&S) -> &T,
+) -> Result<(), Error> {
+    annotator.canonical::<T>(name).map(|_| ())
+}
+
+#[doc(hidden)]
+pub fn optional_field<S, T: Decodable>(
+    annotator: &mut Annotator,
+    name: &'static str,
+    _: fn(&S) -> &Option<T>,
+) -> Result<(), Error> {
+    annotator.optional::<T>(name).map(|_| ())
+}
+
+// Implement `Annotate` for a struct given the exhaustive list of its fields in encoding order,
+// each field prefixed by its encoding: `decode` for the consensus encoding, `canonical` for the
+// length prefixed canonical bytes, and `optional` for a presence flag followed by the consensus
+// encoding. Listing a field that does not exist or omitting one fails to compile.
+macro_rules! impl_annotate {
+    ($thing:ty, [$($args:tt)*], { $($kind:ident $field:ident),* $(,)? }) => {
+        impl<$($args)*> $crate::consensus::Annotate for $thing {
+            #[allow(unused_variables)]
+            fn annotate(
+                annotator: &mut $crate::consensus::Annotator,
+            ) -> Result<(), $crate::consensus::Error> {
+                let _exhaustive = |value: &Self| {
+                    let Self { $($field: _),* } = value;
+                };
+                $(impl_annotate!(@field annotator, $kind, $field);)*
+                Ok(())
+            }
+        }
+    };
+    (@field $annotator:ident, decode, $field:ident) => {
+        $crate::consensus::annotate::decode_field(
+            $annotator,
+            stringify!($field),
+            |value: &Self| &value.$field,
+        )?
+    };
+    (@field $annotator:ident, canonical, $field:ident) => {
+        $crate::consensus::annotate::canonical_field(
+            $annotator,
+            stringify!($field),
+            |value: &Self| &value.$field,
+        )?
+    };
+    (@field $annotator:ident, optional, $field:ident) => {
+        $crate::consensus::annotate::optional_field(
+            $annotator,
+            stringify!($field),
+            |value: &Self| &value.$field,
+        )?
+    };
+}
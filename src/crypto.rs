@@ -1,5 +1,6 @@
 //! Cryptographic types and primitives supported in Farcaster
 
+use std::collections::HashMap;
 use std::error;
 use std::fmt::Debug;
 use std::io;
@@ -7,10 +8,16 @@ use std::io;
 use thiserror::Error;
 
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::swap::SwapId;
+
+pub mod hash;
+pub mod merkle;
+pub mod pedersen;
 
 /// List of cryptographic errors that can be encountered when processing cryptographic operation
 /// such as signatures, proofs, key derivation, or commitments.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// The key identifier is not supported and the key cannot be derived.
     #[error("The key identifier is not supported and the key cannot be derived")]
@@ -27,6 +34,9 @@ pub enum Error {
     /// The commitment does not match the given value.
     #[error("The commitment does not match the given value")]
     InvalidCommitment,
+    /// The public key is already used in another swap.
+    #[error("The public key is already used in swap {0}")]
+    KeyReused(SwapId),
     /// Any cryptographic error not part of this list.
     #[error("Cryptographic error: {0}")]
     Other(Box<dyn error::Error + Send + Sync>),
@@ -107,7 +117,7 @@ where
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArbitratingKeyId {
     Fund,
     Buy,
@@ -117,6 +127,36 @@ pub enum ArbitratingKeyId {
     Extra(u16),
 }
 
+impl Encodable for ArbitratingKeyId {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            ArbitratingKeyId::Fund => 0x01u16.consensus_encode(writer),
+            ArbitratingKeyId::Buy => 0x02u16.consensus_encode(writer),
+            ArbitratingKeyId::Cancel => 0x03u16.consensus_encode(writer),
+            ArbitratingKeyId::Refund => 0x04u16.consensus_encode(writer),
+            ArbitratingKeyId::Punish => 0x05u16.consensus_encode(writer),
+            ArbitratingKeyId::Extra(tag) => {
+                let len = 0x06u16.consensus_encode(writer)?;
+                Ok(len + tag.consensus_encode(writer)?)
+            }
+        }
+    }
+}
+
+impl Decodable for ArbitratingKeyId {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u16 => Ok(ArbitratingKeyId::Fund),
+            0x02u16 => Ok(ArbitratingKeyId::Buy),
+            0x03u16 => Ok(ArbitratingKeyId::Cancel),
+            0x04u16 => Ok(ArbitratingKeyId::Refund),
+            0x05u16 => Ok(ArbitratingKeyId::Punish),
+            0x06u16 => Ok(ArbitratingKeyId::Extra(Decodable::consensus_decode(d)?)),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum AccordantKeyId {
     Spend,
@@ -154,10 +194,10 @@ impl Decodable for SharedKeyId {
 /// key associated type is shared across the network.
 pub trait Keys {
     /// Private key type given the blockchain and the crypto engine.
-    type PrivateKey;
+    type PrivateKey: Send + Sync;
 
     /// Public key type given the blockchain and the crypto engine.
-    type PublicKey: Clone + PartialEq + Debug + CanonicalBytes;
+    type PublicKey: Clone + PartialEq + Debug + CanonicalBytes + Send + Sync;
 
     fn extra_keys() -> Vec<u16>;
 }
@@ -166,32 +206,133 @@ pub trait Keys {
 /// the network.
 pub trait SharedPrivateKeys {
     /// A shareable private key type used to parse non-transparent blockchain
-    type SharedPrivateKey: Clone + PartialEq + Debug + CanonicalBytes;
+    type SharedPrivateKey: Clone + PartialEq + Debug + CanonicalBytes + Send + Sync;
 
     fn shared_keys() -> Vec<SharedKeyId>;
 }
 
+/// This trait is required for accordant blockchains supporting the split of a participant's key
+/// across two devices as 2-of-2 additive shares, e.g. to require a second-factor device for the
+/// spend key. The counter-party only sees the aggregated keys, the split is invisible at the
+/// protocol level.
+pub trait KeyShares: Keys {
+    /// Split the private key in two additive shares derived from the randomness. The shares add
+    /// up to the key without modular reduction.
+    fn split_key(
+        key: &Self::PrivateKey,
+        randomness: [u8; 32],
+    ) -> Result<(Self::PrivateKey, Self::PrivateKey), Error>;
+
+    /// Aggregate the public keys of two shares into the public key.
+    fn aggregate_public_shares(
+        first: &Self::PublicKey,
+        second: &Self::PublicKey,
+    ) -> Self::PublicKey;
+
+    /// Aggregate two private shares into the private key.
+    fn aggregate_private_shares(
+        first: &Self::PrivateKey,
+        second: &Self::PrivateKey,
+    ) -> Self::PrivateKey;
+}
+
 /// This trait is required for blockchains for fixing the commitment types of the keys and
 /// parameters that must go through the commit/reveal scheme at the beginning of the protocol.
 pub trait Commitment {
     /// Commitment type used in the commit/reveal scheme during swap parameters setup.
-    type Commitment: Clone + PartialEq + Eq + Debug + CanonicalBytes;
+    type Commitment: Clone + PartialEq + Eq + Debug + CanonicalBytes + Send + Sync;
+}
+
+mod sealed {
+    pub trait Sealed {}
 }
 
+/// Marks arbitrating blockchains able to verify ECDSA signatures over secp256k1 on-chain.
+pub trait EcdsaCapable {}
+
+/// Marks arbitrating blockchains able to verify BIP 340 Schnorr signatures in taproot outputs.
+pub trait TaprootCapable {}
+
+/// The crypto engine used to produce signatures and adaptor signatures on an arbitrating
+/// blockchain. Engines are sealed and only implemented for blockchains with the required
+/// capability, so pairing an engine with an incompatible blockchain fails to compile.
+///
+#[cfg_attr(feature = "bitcoin", doc = "```")]
+#[cfg_attr(not(feature = "bitcoin"), doc = "```ignore")]
+/// use farcaster_core::chain::bitcoin::Bitcoin;
+/// use farcaster_core::crypto::{CryptoEngine, Ecdsa};
+///
+/// fn pair<C, E: CryptoEngine<C>>() {}
+/// pair::<Bitcoin, Ecdsa>();
+/// ```
+///
+#[cfg_attr(feature = "bitcoin", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "bitcoin"), doc = "```ignore")]
+/// use farcaster_core::chain::bitcoin::Bitcoin;
+/// use farcaster_core::crypto::{CryptoEngine, TrSchnorr};
+///
+/// fn pair<C, E: CryptoEngine<C>>() {}
+/// pair::<Bitcoin, TrSchnorr>();
+/// ```
+pub trait CryptoEngine<C: ?Sized>: sealed::Sealed {}
+
+/// ECDSA signatures with ECDSA one-time verifiably encrypted signatures as adaptor signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ecdsa;
+
+/// BIP 340 Schnorr signatures with Schnorr adaptor signatures spent through taproot outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrSchnorr;
+
+impl sealed::Sealed for Ecdsa {}
+impl sealed::Sealed for TrSchnorr {}
+
+impl<C: ?Sized + EcdsaCapable> CryptoEngine<C> for Ecdsa {}
+impl<C: ?Sized + TaprootCapable> CryptoEngine<C> for TrSchnorr {}
+
 /// This trait is required for arbitrating blockchains for defining the types of messages,
 /// signatures and adaptor signatures used in the cryptographic operation such as signing/verifying
 /// signatures and adaptor signatures.
-pub trait Signatures {
+pub trait Signatures: Keys {
+    /// The crypto engine producing the signatures, it must be compatible with the blockchain.
+    type Engine: CryptoEngine<Self>;
+
     /// Type of the message passed to sign or adaptor sign methods, transactions will produce
     /// messages that will be passed to these methods.
-    type Message: Clone + Debug;
+    type Message: Clone + Debug + CanonicalBytes + Send + Sync;
 
     /// Defines the signature format for the arbitrating blockchain.
-    type Signature: Clone + Debug + CanonicalBytes;
+    type Signature: Clone + Debug + CanonicalBytes + Send + Sync;
 
     /// Defines the adaptor signature format for the arbitrating blockchain. Adaptor signature may
     /// have a different format from the signature depending on the cryptographic primitives used.
-    type AdaptorSignature: Clone + Debug + CanonicalBytes;
+    type AdaptorSignature: Clone + Debug + CanonicalBytes + Send + Sync;
+
+    /// Verify that an adaptor signature is valid for the message and the signing public key and is
+    /// bound to the adaptor point. Only public data is required, participants can then check the
+    /// counter-party adaptor signatures without access to any secret.
+    ///
+    /// # Limitations
+    ///
+    /// Implementations whose adaptor signatures are not yet bound to the adaptor point, e.g.
+    /// Bitcoin until ECDSA adaptor signatures are implemented, only verify the signature itself
+    /// and cannot detect an adaptor signature encrypted with another adaptor point. They MUST
+    /// still return an error for adaptor points that cannot be used, such as the signing key.
+    fn verify_adaptor(
+        sig: &Self::AdaptorSignature,
+        msg: Self::Message,
+        signing_key: &Self::PublicKey,
+        adaptor_point: &Self::PublicKey,
+    ) -> Result<(), Error>;
+}
+
+/// Implemented by arbitrating blockchains where the messages to sign are digests of a serialized
+/// preimage, e.g. the BIP-143 signing data of a Bitcoin transaction input. External signers can
+/// recompute the message from the preimage and verify what they sign instead of trusting an
+/// opaque digest.
+pub trait SigHashPreimage: Signatures {
+    /// Compute the message to sign from its preimage.
+    fn message_from_preimage(preimage: &[u8]) -> Self::Message;
 }
 
 pub trait Wallet<ArPublicKey, AcPublicKey, ArSharedKey, AcSharedKey, Proof>:
@@ -224,6 +365,13 @@ pub trait GenerateKey<PublicKey, KeyId> {
     }
 }
 
+/// Implemented by devices holding a share of a participant's key, see [`KeyShares`].
+pub trait GenerateKeyShare<PublicKey, KeyId> {
+    /// Retreive the public key of the share held by the device for a key id. If the device does
+    /// not hold a share for the key the implementation must return an [`Error::UnsupportedKey`]
+    fn get_pubkey_share(&self, key_id: KeyId) -> Result<PublicKey, Error>;
+}
+
 pub trait GenerateSharedKey<SharedKey> {
     /// Retreive a specific shared private key by its key id. If the key cannot be derived the
     /// implementation must return an [`Error::UnsupportedKey`]
@@ -267,6 +415,68 @@ pub trait Sign<PublicKey, PrivateKey, Message, Signature, AdaptorSignature> {
     fn recover_key(&self, sig: Signature, adapted_sig: AdaptorSignature) -> PrivateKey;
 }
 
+/// Records the public keys used in active swaps to detect keys re-used across sessions. A
+/// counter-party presenting keys already seen in another swap indicates either a broken wallet or
+/// an attempt to correlate or grief swaps.
+///
+/// Keys are tracked by their canonical bytes, keys from different blockchains can then be tracked
+/// in the same tracker.
+#[derive(Debug, Clone, Default)]
+pub struct KeyTracker {
+    keys: HashMap<Vec<u8>, SwapId>,
+}
+
+impl KeyTracker {
+    /// Create a new empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a list of serialized public keys for a swap. Fails with [`Error::KeyReused`] if one
+    /// of the keys is already tracked for another swap, in that case none of the keys are
+    /// tracked. Tracking keys again for the same swap is allowed.
+    pub fn track<I>(&mut self, swap_id: SwapId, keys: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+    {
+        let keys: Vec<Vec<u8>> = keys.into_iter().collect();
+        if let Some(other) = keys
+            .iter()
+            .filter_map(|key| self.keys.get(key))
+            .find(|&&other| other != swap_id)
+        {
+            return Err(Error::KeyReused(*other));
+        }
+        self.keys.extend(keys.into_iter().map(|key| (key, swap_id)));
+        Ok(())
+    }
+
+    /// Track a public key for a swap, see [`KeyTracker::track`].
+    pub fn track_key(&mut self, swap_id: SwapId, key: &impl CanonicalBytes) -> Result<(), Error> {
+        self.track(swap_id, vec![key.as_canonical_bytes()])
+    }
+
+    /// Return the swap using the public key, if any.
+    pub fn swap_of(&self, key: &impl CanonicalBytes) -> Option<SwapId> {
+        self.keys.get(&key.as_canonical_bytes()).copied()
+    }
+
+    /// Stop tracking the keys of a swap, e.g. when the swap is over.
+    pub fn release(&mut self, swap_id: &SwapId) {
+        self.keys.retain(|_, id| id != swap_id);
+    }
+
+    /// Return the number of tracked keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Return true if no key is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
 pub trait Commit<Commitment: Eq> {
     /// Provides a generic method to commit to any value referencable as stream of bytes.
     fn commit_to<T: AsRef<[u8]>>(&self, value: T) -> Commitment;
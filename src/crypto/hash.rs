@@ -0,0 +1,302 @@
+//! Tagged and versioned hash helpers used for commitments, key derivation, and any other hash
+//! computed by Farcaster core.
+//!
+//! Every hash is domain separated with a tag prefixed by [`HASH_DOMAIN`] and the [`HASH_VERSION`],
+//! e.g. `farcaster/v1/pedersen:value`. Two hashes computed with different tags can never collide
+//! and changing how a value is hashed is done by bumping the version.
+//!
+//! Keyed hashes are computed with BLAKE2b-256 (RFC 7693) in keyed mode.
+//!
+//! The wallet key derivation and the parameter commitments of the Bitcoin-Monero pair predate
+//! these helpers and are kept unchanged: existing seeds must derive the same keys and peers
+//! running previous releases must verify the same commitments.
+
+use bitcoin_hashes::hmac::{Hmac, HmacEngine};
+use bitcoin_hashes::{sha256, Hash, HashEngine};
+use secp256k1::SecretKey;
+
+/// Prefix of all the hash tags.
+pub const HASH_DOMAIN: &str = "farcaster";
+
+/// Version of the hash tags.
+pub const HASH_VERSION: u8 = 1;
+
+/// Return the full domain separation tag for the given tag, e.g. `farcaster/v1/tag`.
+pub fn domain(tag: &str) -> Vec<u8> {
+    format!("{}/v{}/{}", HASH_DOMAIN, HASH_VERSION, tag).into_bytes()
+}
+
+/// Compute a tagged SHA256 of the data as `SHA256(SHA256(domain) || SHA256(domain) || data)`,
+/// similarly to BIP 340 tagged hashes.
+pub fn tagged_sha256(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(&domain(tag));
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash[..]);
+    engine.input(&tag_hash[..]);
+    engine.input(data);
+    sha256::Hash::from_engine(engine).into_inner()
+}
+
+/// Compute a tagged Keccak256 of the data as `Keccak256(len(domain) || domain || data)`, used by
+/// ed25519 based blockchains.
+#[cfg(feature = "monero")]
+pub fn tagged_keccak256(tag: &str, data: &[u8]) -> [u8; 32] {
+    let domain = domain(tag);
+    let mut bytes = vec![domain.len() as u8];
+    bytes.extend_from_slice(&domain);
+    bytes.extend_from_slice(data);
+    monero::cryptonote::hash::keccak_256(&bytes)
+}
+
+/// Compute a keyed BLAKE2b-256 of the data as `BLAKE2b(key, len(domain) || domain || data)`.
+/// Keys longer than the 64 bytes allowed by BLAKE2b are first hashed with BLAKE2b-512.
+pub fn keyed_hash(tag: &str, key: &[u8], data: &[u8]) -> [u8; 32] {
+    let domain = domain(tag);
+    let mut bytes = vec![domain.len() as u8];
+    bytes.extend_from_slice(&domain);
+    bytes.extend_from_slice(data);
+    let mut hash = [0u8; 32];
+    match key.len() > BLAKE2B_MAX_KEY_LEN {
+        true => blake2b(&blake2b_512(&[], key), &bytes, &mut hash),
+        false => blake2b(key, &bytes, &mut hash),
+    }
+    hash
+}
+
+const BLAKE2B_MAX_KEY_LEN: usize = 64;
+
+const BLAKE2B_BLOCK_LEN: usize = 128;
+
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+fn blake2b_512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut hash = [0u8; 64];
+    blake2b(key, data, &mut hash);
+    hash
+}
+
+// Compute the BLAKE2b hash of the data with an output length of `out.len()` bytes, at most 64,
+// keyed if the key is not empty
+fn blake2b(key: &[u8], data: &[u8], out: &mut [u8]) {
+    debug_assert!(key.len() <= BLAKE2B_MAX_KEY_LEN && !out.is_empty() && out.len() <= 64);
+    let mut h = BLAKE2B_IV;
+    h[0] ^= 0x0101_0000 ^ ((key.len() as u64) << 8) ^ out.len() as u64;
+
+    // The key is padded to a full block processed before the data
+    let mut input = vec![];
+    if !key.is_empty() {
+        input.extend_from_slice(key);
+        input.resize(BLAKE2B_BLOCK_LEN, 0);
+    }
+    input.extend_from_slice(data);
+
+    let blocks = match input.len() {
+        0 => 1,
+        len => len.div_ceil(BLAKE2B_BLOCK_LEN),
+    };
+    for i in 0..blocks {
+        let start = i * BLAKE2B_BLOCK_LEN;
+        let end = input.len().min(start + BLAKE2B_BLOCK_LEN);
+        let mut block = [0u8; BLAKE2B_BLOCK_LEN];
+        block[..end - start].copy_from_slice(&input[start..end]);
+        blake2b_compress(&mut h, &block, end as u128, i == blocks - 1);
+    }
+
+    let mut bytes = [0u8; 64];
+    for (chunk, word) in bytes.chunks_mut(8).zip(h.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    let len = out.len();
+    out.copy_from_slice(&bytes[..len]);
+}
+
+fn blake2b_compress(h: &mut [u64; 8], block: &[u8; BLAKE2B_BLOCK_LEN], t: u128, last: bool) {
+    let mut m = [0u64; 16];
+    for (word, chunk) in m.iter_mut().zip(block.chunks(8)) {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(chunk);
+        *word = u64::from_le_bytes(bytes);
+    }
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+    v[12] ^= t as u64;
+    v[13] ^= (t >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+    for round in 0..12 {
+        let s = &BLAKE2B_SIGMA[round % 10];
+        blake2b_mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        blake2b_mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        blake2b_mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        blake2b_mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        blake2b_mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        blake2b_mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        blake2b_mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        blake2b_mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+#[allow(clippy::many_single_char_names)]
+fn blake2b_mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// Derive a 32 bytes key from a password with PBKDF2-HMAC-SHA256 (RFC 8018), used to encrypt
+/// secrets exported by the user.
+pub fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(password);
+    engine.input(salt);
+    engine.input(&1u32.to_be_bytes());
+    let mut block = Hmac::<sha256::Hash>::from_engine(engine).into_inner();
+    let mut key = block;
+    for _ in 1..iterations {
+        let mut engine = HmacEngine::<sha256::Hash>::new(password);
+        engine.input(&block);
+        block = Hmac::<sha256::Hash>::from_engine(engine).into_inner();
+        key.iter_mut().zip(block.iter()).for_each(|(k, b)| *k ^= b);
+    }
+    key
+}
+
+/// Hash the data into a secp256k1 scalar. The tagged hash is computed with an incremented counter
+/// until the result is a valid non-zero scalar.
+pub fn hash_to_secp256k1_scalar(tag: &str, data: &[u8]) -> SecretKey {
+    let mut counter = 0u32;
+    loop {
+        let mut bytes = data.to_vec();
+        bytes.extend_from_slice(&counter.to_le_bytes());
+        if let Ok(scalar) = SecretKey::from_slice(&tagged_sha256(tag, &bytes)) {
+            return scalar;
+        }
+        counter += 1;
+    }
+}
+
+/// Hash the data into an ed25519 scalar, the tagged Keccak256 is reduced modulo the group order.
+#[cfg(feature = "monero")]
+pub fn hash_to_ed25519_scalar(tag: &str, data: &[u8]) -> monero::PrivateKey {
+    monero::cryptonote::hash::Hash::from(tagged_keccak256(tag, data)).as_scalar()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_separation() {
+        assert_eq!(domain("test"), b"farcaster/v1/test".to_vec());
+        assert_ne!(tagged_sha256("a", b"data"), tagged_sha256("b", b"data"));
+        assert_ne!(
+            keyed_hash("a", b"key", b"data"),
+            keyed_hash("a", b"other key", b"data")
+        );
+        assert_ne!(
+            hash_to_secp256k1_scalar("a", b"data"),
+            hash_to_secp256k1_scalar("b", b"data")
+        );
+        // Hashes are deterministic
+        assert_eq!(tagged_sha256("a", b"data"), tagged_sha256("a", b"data"));
+    }
+
+    #[test]
+    #[cfg(feature = "monero")]
+    fn ed25519_domain_separation() {
+        assert_ne!(
+            tagged_keccak256("a", b"data"),
+            tagged_keccak256("b", b"data")
+        );
+        assert_ne!(
+            hash_to_ed25519_scalar("a", b"data"),
+            hash_to_ed25519_scalar("b", b"data")
+        );
+    }
+
+    #[test]
+    fn blake2b_test_vectors() {
+        // RFC 7693 appendix A and the first keyed vectors of the reference implementation
+        assert_eq!(
+            hex::encode(&blake2b_512(&[], b"abc")[..]),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc2\
+             52d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+        let key: Vec<u8> = (0u8..64).collect();
+        assert_eq!(
+            hex::encode(&blake2b_512(&key, &[])[..]),
+            "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786b5e996e8f0f4eb981fc2\
+             14b005f42d2ff4233499391653df7aefcbc13fc51568"
+        );
+        let data: Vec<u8> = (0u8..=255).collect();
+        assert_eq!(
+            hex::encode(&blake2b_512(&key, &data)[..]),
+            "b72071e096277edebb8ee5134dd3714996307ba3a55aa4733d412abbe28e909e10e57e6fbfb4ef53b3b9\
+             60518294ff889a90829254412e2a60b85add07a3674f"
+        );
+        let mut hash = [0u8; 32];
+        blake2b(&key, &data, &mut hash);
+        assert_eq!(
+            hex::encode(hash),
+            "1ee3b6312b4e0f0b9663b812b8c129e6d45c410b1c9c5a1667bfc6dd951db79f"
+        );
+
+        // Keyed hashes are domain separated, long keys are hashed first
+        assert_eq!(
+            hex::encode(keyed_hash("test", b"key", b"data")),
+            "ae399308b0e73fa242fbd0ecfa0e6f5576727ba407c62779e69ed6acfca405c4"
+        );
+        let long_key: Vec<u8> = (0u8..100).collect();
+        assert_eq!(
+            hex::encode(keyed_hash("test", &long_key, b"data")),
+            "7d92065dfad6b587d380c7270283c1eb8ba3b059edc6b87e4c42ae18e7b523df"
+        );
+    }
+
+    #[test]
+    fn pbkdf2_test_vectors() {
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 2)),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Merkle trees over arbitrary elements used to commit to a set while revealing only one of its
+//! members, e.g. Bob's set of refund addresses.
+//!
+//! Leaves and nodes are hashed with domain separated tagged hashes, see [`hash::tagged_sha256`], so
+//! a leaf can never be interpreted as a node. When a level has an odd number of nodes the last
+//! node is paired with itself.
+
+use std::io;
+
+use crate::consensus::{self, Decodable, Encodable};
+use crate::crypto::hash;
+
+fn hash_leaf(data: &[u8]) -> [u8; 32] {
+    hash::tagged_sha256("merkle:leaf", data)
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = left.to_vec();
+    data.extend_from_slice(right);
+    hash::tagged_sha256("merkle:node", &data)
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| hash_node(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// Compute the root of the Merkle tree over the leaves, `None` if there is no leaf.
+pub fn merkle_root<T: AsRef<[u8]>>(leaves: &[T]) -> Option<[u8; 32]> {
+    let mut level: Vec<[u8; 32]> = leaves.iter().map(|l| hash_leaf(l.as_ref())).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.pop()
+}
+
+/// A proof of inclusion of a leaf in a Merkle tree, the position of the leaf and the sibling
+/// hashes from the leaf to the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub index: u32,
+    pub path: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    /// Build the proof of inclusion of the leaf at `index`, `None` if the index is out of bounds.
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T], index: usize) -> Option<Self> {
+        if index >= leaves.len() {
+            return None;
+        }
+        let mut level: Vec<[u8; 32]> = leaves.iter().map(|l| hash_leaf(l.as_ref())).collect();
+        let mut path = vec![];
+        let mut position = index;
+        while level.len() > 1 {
+            let sibling = position ^ 1;
+            path.push(*level.get(sibling).unwrap_or(&level[position]));
+            level = next_level(&level);
+            position /= 2;
+        }
+        Some(Self {
+            index: index as u32,
+            path,
+        })
+    }
+
+    /// Verify that the leaf is part of the tree with the given root.
+    pub fn verify<T: AsRef<[u8]>>(&self, root: &[u8; 32], leaf: T) -> bool {
+        let mut position = self.index;
+        let mut node = hash_leaf(leaf.as_ref());
+        for sibling in self.path.iter() {
+            node = match position & 1 {
+                0 => hash_node(&node, sibling),
+                _ => hash_node(sibling, &node),
+            };
+            position >>= 1;
+        }
+        // The index must not point outside the tree
+        position == 0 && &node == root
+    }
+}
+
+impl Encodable for MerkleProof {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.index.consensus_encode(s)?;
+        len += (self.path.len() as u8).consensus_encode(s)?;
+        for node in self.path.iter() {
+            len += node.consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl Decodable for MerkleProof {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let index = Decodable::consensus_decode(d)?;
+        let depth = u8::consensus_decode(d)?;
+        let path = (0..depth)
+            .map(|_| Decodable::consensus_decode(d))
+            .collect::<Result<Vec<[u8; 32]>, consensus::Error>>()?;
+        Ok(Self { index, path })
+    }
+}
+
+impl_strict_encoding!(MerkleProof);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prove_inclusion() {
+        let leaves: Vec<Vec<u8>> = (0u8..5).map(|i| vec![i; 4]).collect();
+        let root = merkle_root(&leaves).unwrap();
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = MerkleProof::new(&leaves, index).unwrap();
+            assert!(proof.verify(&root, leaf));
+            assert!(!proof.verify(&root, vec![0xff; 4]));
+            let de: MerkleProof = consensus::deserialize(&consensus::serialize(&proof)).unwrap();
+            assert_eq!(de, proof);
+        }
+        assert!(MerkleProof::new(&leaves, 5).is_none());
+        // A leaf is never valid at another position
+        let mut proof = MerkleProof::new(&leaves, 1).unwrap();
+        proof.index = 0;
+        assert!(!proof.verify(&root, &leaves[1]));
+        // A single leaf is its own proof
+        let root = merkle_root(&leaves[..1]).unwrap();
+        assert!(MerkleProof::new(&leaves[..1], 0)
+            .unwrap()
+            .verify(&root, &leaves[0]));
+        assert_eq!(merkle_root::<Vec<u8>>(&[]), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Pedersen commitments over the secp256k1 curve used to hide values, e.g. amounts in blinded
+//! offers, while binding the committer to them.
+//!
+//! A commitment to a value `v` with a blinding factor `r` is computed as `C = v*G + r*H` where `G`
+//! is the curve generator and `H` a second generator with unknown discrete logarithm relative to
+//! `G`. The value is first hashed into a scalar, allowing to commit to any canonical bytes.
+
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+use std::io;
+
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::{hash, Error};
+
+/// Domain separation tag used to derive the second generator `H`.
+pub const GENERATOR_H_TAG: &str = "pedersen:H";
+
+/// Domain separation tag used to hash values into scalars.
+pub const VALUE_TAG: &str = "pedersen:value";
+
+/// A Pedersen commitment to an hidden value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PedersenCommitment(PublicKey);
+
+impl PedersenCommitment {
+    /// Commit to the value with the given blinding factor.
+    pub fn commit(value: &[u8], blinding: &[u8; 32]) -> Result<Self, Error> {
+        let secp = Secp256k1::new();
+        let value = hash::hash_to_secp256k1_scalar(VALUE_TAG, value);
+        let blinding = SecretKey::from_slice(blinding).map_err(Error::new)?;
+        let value_point = PublicKey::from_secret_key(&secp, &value);
+        let mut blinding_point = generator_h();
+        blinding_point
+            .mul_assign(&secp, &blinding[..])
+            .map_err(Error::new)?;
+        Ok(Self(
+            value_point.combine(&blinding_point).map_err(Error::new)?,
+        ))
+    }
+
+    /// Verify that the commitment opens to the given value and blinding factor.
+    pub fn verify(&self, value: &[u8], blinding: &[u8; 32]) -> Result<(), Error> {
+        match Self::commit(value, blinding)? == *self {
+            true => Ok(()),
+            false => Err(Error::InvalidCommitment),
+        }
+    }
+}
+
+impl CanonicalBytes for PedersenCommitment {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        self.0.serialize().to_vec()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        Ok(Self(
+            PublicKey::from_slice(bytes).map_err(consensus::Error::new)?,
+        ))
+    }
+}
+
+impl Encodable for PedersenCommitment {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.as_canonical_bytes().consensus_encode(s)
+    }
+}
+
+impl Decodable for PedersenCommitment {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Self::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())
+    }
+}
+
+impl_strict_encoding!(PedersenCommitment);
+
+/// Return the second generator `H`, derived by hashing [`GENERATOR_H_TAG`] with an incremented
+/// counter until a valid curve point is found. Nobody knows the discrete logarithm of `H` relative
+/// to `G`.
+pub fn generator_h() -> PublicKey {
+    let mut counter = 0u32;
+    loop {
+        let hash = hash::tagged_sha256(GENERATOR_H_TAG, &counter.to_le_bytes());
+        let mut point = [0x02u8; 33];
+        point[1..].copy_from_slice(&hash[..]);
+        if let Ok(h) = PublicKey::from_slice(&point) {
+            return h;
+        }
+        counter += 1;
+    }
+}
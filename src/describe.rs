@@ -0,0 +1,117 @@
+//! Introspection of the protocol messages and bundles.
+//!
+//! Protocol messages and bundles implement [`Describe`], returning a [`Description`] of the
+//! type: the names and the types of its fields, the role producing it, and the phase of the swap
+//! it belongs to. RPC explorers and documentation of the protocol are generated from the
+//! descriptions, e.g. listing all the messages with [`ProtocolMessage::descriptions`].
+//!
+//! Descriptions are implemented next to their types and the list of fields is checked at
+//! compile time: a field added to or removed from a type without updating its description fails
+//! the build, so the descriptions cannot drift from the code. Types names are given by
+//! [`std::any::type_name`] on the concrete swap context, they are meant to be read, not parsed.
+//!
+//! [`ProtocolMessage::descriptions`]: crate::protocol_message::ProtocolMessage::descriptions
+
+use std::fmt;
+
+use crate::role::SwapRole;
+use crate::settlement::SwapPhase;
+
+/// The kind of a described type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// A protocol message exchanged between the participants, with its message type.
+    Message(u16),
+    /// A bundle produced and consumed locally by the participant's daemon.
+    Bundle,
+}
+
+/// The description of a field of a message or a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldDescription {
+    /// The name of the field
+    pub name: &'static str,
+    /// The type of the field
+    pub type_name: &'static str,
+}
+
+/// The structured description of a message or a bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Description {
+    /// The name of the type
+    pub name: &'static str,
+    /// The kind of the type, message or bundle
+    pub kind: Kind,
+    /// The role sending the message or producing the bundle, `None` if both roles do
+    pub role: Option<SwapRole>,
+    /// The phase of the swap the message or the bundle belongs to, `None` if it can happen in
+    /// any phase
+    pub phase: Option<SwapPhase>,
+    /// The fields of the type in declaration order
+    pub fields: Vec<FieldDescription>,
+}
+
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Kind::Message(msg_type) => write!(f, "message {} ({:#04x})", self.name, msg_type)?,
+            Kind::Bundle => write!(f, "bundle {}", self.name)?,
+        }
+        match self.role {
+            Some(role) => write!(f, ", from {}", role)?,
+            None => write!(f, ", from both roles")?,
+        }
+        match self.phase {
+            Some(phase) => write!(f, ", during {:?}", phase)?,
+            None => write!(f, ", during any phase")?,
+        }
+        for field in self.fields.iter() {
+            write!(f, "\n  {}: {}", field.name, field.type_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Types with a structured description, implemented by the protocol messages and bundles.
+pub trait Describe {
+    /// Return the description of the type.
+    fn describe() -> Description;
+}
+
+// Return the name of the type without its generic parameters, used by `impl_describe!`
+#[doc(hidden)]
+pub fn base_name(name: &'static str) -> &'static str {
+    name.split('<').next().unwrap_or(name).trim()
+}
+
+// Return the name of the type of a field given an accessor, used by `impl_describe!`
+#[doc(hidden)]
+pub fn field_type<T, F>(_: fn(&T) -> &F) -> &'static str {
+    std::any::type_name::<F>()
+}
+
+// Implement `Describe` for a struct given its kind, role, phase, and the exhaustive list of its
+// fields. Listing a field that does not exist or omitting one fails to compile.
+macro_rules! impl_describe {
+    ($thing:ty, [$($args:tt)*], $kind:expr, $role:expr, $phase:expr, { $($field:ident),* $(,)? }) => {
+        impl<$($args)*> $crate::describe::Describe for $thing {
+            fn describe() -> $crate::describe::Description {
+                let _exhaustive = |value: &Self| {
+                    let Self { $($field: _),* } = value;
+                };
+                $crate::describe::Description {
+                    name: $crate::describe::base_name(stringify!($thing)),
+                    kind: $kind,
+                    role: $role,
+                    phase: $phase,
+                    fields: vec![$(
+                        $crate::describe::FieldDescription {
+                            name: stringify!($field),
+                            type_name: $crate::describe::field_type(|value: &Self| &value.$field),
+                        }
+                    ),*],
+                }
+            }
+        }
+    };
+}
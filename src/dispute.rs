@@ -0,0 +1,370 @@
+//! Selective disclosure of a swap to a third-party arbiter.
+//!
+//! The protocol is trustless, but OTC desks may want to rely on a human arbiter to settle
+//! disputes, e.g. when a participant claims the counter-party never answered. Each participant
+//! records the protocol messages exchanged in a [`Transcript`], its [`TranscriptCommitment`] is a
+//! Merkle root over the encoded messages that both participants can sign with their arbitrating
+//! keys.
+//!
+//! A [`DisputePackage`] contains the signed commitment, a selection of the messages with their
+//! proofs of inclusion in the transcript, and the arbitrating transactions relevant to the
+//! dispute. The arbiter verifies the package with [`DisputePackage::verify`] and learns nothing
+//! about the messages not disclosed. Protocol messages never contain the arbitrating or accordant
+//! spend private keys, but the reveal messages contain the shared private keys, e.g. the Monero
+//! view key shares, and should be disclosed only if needed.
+
+use std::io;
+
+use thiserror::Error;
+
+use crate::blockchain::Onchain;
+use crate::consensus::{self, serialize, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::merkle::{self, MerkleProof};
+use crate::crypto::{self, hash, Keys, SigHashPreimage, Sign, Signatures};
+use crate::protocol_message::ProtocolMessage;
+use crate::role::SwapRole;
+use crate::swap::{Swap, SwapId};
+use crate::transaction::TxLabel;
+
+/// Errors when verifying a dispute package.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The disclosed message at the index is not part of the committed transcript.
+    #[error("Disclosed message {0} is not part of the transcript")]
+    InvalidProof(u32),
+    /// A signature of the transcript commitment is not valid.
+    #[error("Invalid signature of the transcript commitment by {0}")]
+    InvalidSignature(SwapRole),
+    /// The transcript commitment is signed by none of the participants.
+    #[error("Transcript commitment not signed")]
+    MissingSignature,
+    /// A cryptographic error when signing or verifying.
+    #[error("Cryptographic error: {0}")]
+    Crypto(#[from] crypto::Error),
+    /// A consensus error when decoding a disclosed message.
+    #[error("Consensus error: {0}")]
+    Consensus(#[from] consensus::Error),
+}
+
+/// The encoded protocol messages of a swap, in the order they are sent or received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    swap_id: SwapId,
+    messages: Vec<Vec<u8>>,
+}
+
+impl Transcript {
+    /// Create an empty transcript for a swap.
+    pub fn new(swap_id: SwapId) -> Self {
+        Self {
+            swap_id,
+            messages: vec![],
+        }
+    }
+
+    /// Record a message sent or received, return its index in the transcript.
+    pub fn push<Ctx: Swap>(&mut self, message: &ProtocolMessage<Ctx>) -> u32 {
+        self.messages.push(serialize(message));
+        self.messages.len() as u32 - 1
+    }
+
+    /// Return the number of recorded messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Return true if no message is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Return the commitment to the recorded messages, `None` if no message is recorded.
+    pub fn commitment(&self) -> Option<TranscriptCommitment> {
+        self.commitment_at(self.messages.len() as u32)
+    }
+
+    /// Return the commitment to the first `len` recorded messages, `None` if `len` is zero or
+    /// more messages than recorded.
+    pub fn commitment_at(&self, len: u32) -> Option<TranscriptCommitment> {
+        let messages = self.messages.get(..len as usize)?;
+        merkle::merkle_root(messages).map(|root| TranscriptCommitment {
+            swap_id: self.swap_id,
+            len,
+            root,
+        })
+    }
+
+    /// Disclose the message at `index` with its proof of inclusion, `None` if the index is out of
+    /// bounds.
+    pub fn disclose(&self, index: u32) -> Option<DisclosedMessage> {
+        let proof = MerkleProof::new(&self.messages, index as usize)?;
+        Some(DisclosedMessage {
+            message: self.messages[index as usize].clone(),
+            proof,
+        })
+    }
+}
+
+/// The commitment to the transcript of a swap, signed by the participants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TranscriptCommitment {
+    pub swap_id: SwapId,
+    /// The number of messages in the transcript
+    pub len: u32,
+    /// The Merkle root over the encoded messages
+    pub root: [u8; 32],
+}
+
+impl TranscriptCommitment {
+    /// Return the message signed by the participants, derived from the serialized commitment.
+    pub fn message<Ar: SigHashPreimage>(&self) -> Ar::Message {
+        Ar::message_from_preimage(&hash::tagged_sha256("transcript", &serialize(self)))
+    }
+}
+
+impl Encodable for TranscriptCommitment {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.len.consensus_encode(s)?;
+        Ok(len + self.root.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for TranscriptCommitment {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            len: Decodable::consensus_decode(d)?,
+            root: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(TranscriptCommitment);
+
+/// An encoded protocol message with its proof of inclusion in a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisclosedMessage {
+    pub message: Vec<u8>,
+    pub proof: MerkleProof,
+}
+
+impl DisclosedMessage {
+    /// Return the index of the message in the transcript.
+    pub fn index(&self) -> u32 {
+        self.proof.index
+    }
+
+    /// Return true if the message is part of the committed transcript.
+    pub fn verify(&self, commitment: &TranscriptCommitment) -> bool {
+        self.proof.index < commitment.len && self.proof.verify(&commitment.root, &self.message)
+    }
+}
+
+impl Encodable for DisclosedMessage {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.message.consensus_encode(s)?;
+        Ok(len + self.proof.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for DisclosedMessage {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            message: Decodable::consensus_decode(d)?,
+            proof: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(DisclosedMessage);
+
+/// The signature of a transcript commitment by a participant.
+#[derive(Debug, Clone)]
+pub struct CommitmentSignature<Ctx: Swap> {
+    pub swap_role: SwapRole,
+    pub key: <Ctx::Ar as Keys>::PublicKey,
+    pub signature: <Ctx::Ar as Signatures>::Signature,
+}
+
+/// The swap details disclosed to an arbiter.
+#[derive(Debug, Clone)]
+pub struct DisputePackage<Ctx: Swap> {
+    pub commitment: TranscriptCommitment,
+    pub signatures: Vec<CommitmentSignature<Ctx>>,
+    pub messages: Vec<DisclosedMessage>,
+    pub transactions: Vec<(TxLabel, <Ctx::Ar as Onchain>::Transaction)>,
+}
+
+impl<Ctx> DisputePackage<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create a package for the transcript commitment, without signatures, messages, nor
+    /// transactions.
+    pub fn new(commitment: TranscriptCommitment) -> Self {
+        Self {
+            commitment,
+            signatures: vec![],
+            messages: vec![],
+            transactions: vec![],
+        }
+    }
+
+    /// Add the signature of the commitment by a participant to the package.
+    pub fn with_signature(
+        mut self,
+        swap_role: SwapRole,
+        key: <Ctx::Ar as Keys>::PublicKey,
+        signature: <Ctx::Ar as Signatures>::Signature,
+    ) -> Self {
+        self.signatures.push(CommitmentSignature {
+            swap_role,
+            key,
+            signature,
+        });
+        self
+    }
+
+    /// Add a disclosed message to the package.
+    pub fn with_message(mut self, message: DisclosedMessage) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Add an arbitrating transaction to the package.
+    pub fn with_transaction(
+        mut self,
+        label: TxLabel,
+        tx: <Ctx::Ar as Onchain>::Transaction,
+    ) -> Self {
+        self.transactions.push((label, tx));
+        self
+    }
+
+    /// Return the disclosed protocol messages decoded with their index in the transcript. The
+    /// messages must be verified first, see [`DisputePackage::verify`].
+    pub fn protocol_messages(&self) -> Result<Vec<(u32, ProtocolMessage<Ctx>)>, consensus::Error> {
+        self.messages
+            .iter()
+            .map(|disclosed| {
+                Ok((
+                    disclosed.index(),
+                    consensus::deserialize(&disclosed.message)?,
+                ))
+            })
+            .collect()
+    }
+}
+
+impl<Ctx> DisputePackage<Ctx>
+where
+    Ctx: Swap,
+    Ctx::Ar: SigHashPreimage,
+{
+    /// Sign the commitment with the participant arbitrating key and add the signature to the
+    /// package.
+    pub fn sign(
+        self,
+        swap_role: SwapRole,
+        key: <Ctx::Ar as Keys>::PublicKey,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+    ) -> Result<Self, Error> {
+        let signature = wallet.sign_with_key(&key, self.commitment.message::<Ctx::Ar>())?;
+        Ok(self.with_signature(swap_role, key, signature))
+    }
+
+    /// Verify the signatures of the commitment and that every disclosed message is part of the
+    /// committed transcript and is a valid protocol message. The arbiter must check that the
+    /// signing keys are the ones of the participants, e.g. from the disclosed reveal messages.
+    pub fn verify(
+        &self,
+        verifier: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+    ) -> Result<(), Error> {
+        if self.signatures.is_empty() {
+            return Err(Error::MissingSignature);
+        }
+        for sig in self.signatures.iter() {
+            verifier
+                .verify_signature(
+                    &sig.key,
+                    self.commitment.message::<Ctx::Ar>(),
+                    &sig.signature,
+                )
+                .map_err(|_| Error::InvalidSignature(sig.swap_role))?;
+        }
+        for disclosed in self.messages.iter() {
+            if !disclosed.verify(&self.commitment) {
+                return Err(Error::InvalidProof(disclosed.index()));
+            }
+        }
+        self.protocol_messages()?;
+        Ok(())
+    }
+}
+
+impl<Ctx> Encodable for DisputePackage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.commitment.consensus_encode(s)?;
+        len += (self.signatures.len() as u8).consensus_encode(s)?;
+        for sig in self.signatures.iter() {
+            len += sig.swap_role.consensus_encode(s)?;
+            len += sig.key.as_canonical_bytes().consensus_encode(s)?;
+            len += sig.signature.as_canonical_bytes().consensus_encode(s)?;
+        }
+        len += self.messages.consensus_encode(s)?;
+        len += (self.transactions.len() as u8).consensus_encode(s)?;
+        for (label, tx) in self.transactions.iter() {
+            len += label.consensus_encode(s)?;
+            len += tx.as_canonical_bytes().consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl<Ctx> Decodable for DisputePackage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let mut package = Self::new(Decodable::consensus_decode(d)?);
+        for _ in 0..u8::consensus_decode(d)? {
+            package.signatures.push(CommitmentSignature {
+                swap_role: Decodable::consensus_decode(d)?,
+                key: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
+                    unwrap_vec_ref!(d).as_ref(),
+                )?,
+                signature: <Ctx::Ar as Signatures>::Signature::from_canonical_bytes(
+                    unwrap_vec_ref!(d).as_ref(),
+                )?,
+            });
+        }
+        package.messages = Decodable::consensus_decode(d)?;
+        for _ in 0..u8::consensus_decode(d)? {
+            let label = Decodable::consensus_decode(d)?;
+            let tx = <Ctx::Ar as Onchain>::Transaction::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?;
+            package.transactions.push((label, tx));
+        }
+        Ok(package)
+    }
+}
+
+impl_strict_encoding!(DisputePackage<Ctx>, Ctx: Swap);
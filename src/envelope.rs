@@ -0,0 +1,353 @@
+//! Encryption of protocol messages independent of the transport.
+//!
+//! Swap daemons usually talk over an authenticated and encrypted transport, but messages can also
+//! be relayed through untrusted brokers or store-and-forward services. An [`Envelope`] encrypts an
+//! encoded message to the session key of the counter-party so the relay learns nothing but the
+//! size of the message.
+//!
+//! Every envelope is encrypted with a fresh ephemeral key, as in the Noise `K` pattern: two X25519
+//! shared secrets are computed, between the ephemeral key and the recipient session key and
+//! between the sender and the recipient session keys, the encryption key is derived from both
+//! with a tagged hash, and the message is encrypted with ChaCha20-Poly1305. Only the holder of the
+//! sender session key can seal an envelope the recipient opens with the sender public session
+//! key, a relay knowing the public session keys cannot forge messages. The header, containing the
+//! format version, the ephemeral public key, and the sequence number of the message, is
+//! authenticated as associated data so a relay cannot change the sequence number undetected;
+//! replayed and reordered envelopes are rejected by [`Envelope::open_in_sequence`].
+//!
+//! Envelopes for a participant rarely online can be packed in a [`Mailbox`] with an expiry time
+//! per message, e.g. to be posted on a nostr or email-like relay and fetched later. Expiry times
+//! are durations elapsed since the UNIX epoch, checked against a [`Clock`] given by the caller.
+//!
+//! # Format
+//!
+//! ```text
+//! version (u16) | ephemeral public key (32 bytes) | sequence (u64) | ciphertext
+//! ```
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use curve25519_dalek::constants::X25519_BASEPOINT;
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use std::convert::TryFrom;
+use std::io;
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::consensus::{self, Decodable, Encodable, Timestamp};
+use crate::crypto::hash;
+
+/// The current version of the envelope format.
+pub const ENVELOPE_VERSION: u16 = 1;
+
+/// The secret session key of a participant, used to open the envelopes sent to it. The key MUST
+/// be generated from a secure source of randomness.
+#[derive(Clone)]
+pub struct SessionKey([u8; 32]);
+
+impl SessionKey {
+    /// Create a session key from 32 random bytes, the bytes are clamped as specified by X25519.
+    pub fn from_bytes(mut bytes: [u8; 32]) -> Self {
+        bytes[0] &= 248;
+        bytes[31] &= 127;
+        bytes[31] |= 64;
+        Self(bytes)
+    }
+
+    /// Return the public session key to share with the counter-party.
+    pub fn public_key(&self) -> SessionPublicKey {
+        SessionPublicKey((X25519_BASEPOINT * Scalar::from_bits(self.0)).to_bytes())
+    }
+
+    // Compute the X25519 shared secret, fails if the public key has a small order
+    fn diffie_hellman(&self, public: &SessionPublicKey) -> Result<[u8; 32], consensus::Error> {
+        let shared = (MontgomeryPoint(public.0) * Scalar::from_bits(self.0)).to_bytes();
+        match shared == [0u8; 32] {
+            true => Err(consensus::Error::ParseFailed("invalid session public key")),
+            false => Ok(shared),
+        }
+    }
+}
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SessionKey(..)")
+    }
+}
+
+/// The public session key of a participant, envelopes are encrypted to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionPublicKey(pub [u8; 32]);
+
+impl Encodable for SessionPublicKey {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.0.consensus_encode(s)
+    }
+}
+
+impl Decodable for SessionPublicKey {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self(Decodable::consensus_decode(d)?))
+    }
+}
+
+impl_strict_encoding!(SessionPublicKey);
+
+/// An encrypted message with its authenticated header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    ephemeral: SessionPublicKey,
+    sequence: u64,
+    ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+    // Serialize the header authenticated as associated data
+    fn header(ephemeral: &SessionPublicKey, sequence: u64) -> Vec<u8> {
+        let mut header = consensus::serialize(&ENVELOPE_VERSION);
+        header.extend_from_slice(&ephemeral.0);
+        header.extend(consensus::serialize(&sequence));
+        header
+    }
+
+    fn cipher(
+        ephemeral_shared: [u8; 32],
+        static_shared: [u8; 32],
+        ephemeral: &SessionPublicKey,
+        sender: &SessionPublicKey,
+        recipient: &SessionPublicKey,
+    ) -> ChaCha20Poly1305 {
+        let mut data = ephemeral_shared.to_vec();
+        data.extend_from_slice(&static_shared);
+        data.extend_from_slice(&ephemeral.0);
+        data.extend_from_slice(&sender.0);
+        data.extend_from_slice(&recipient.0);
+        ChaCha20Poly1305::new(&Key::from(hash::tagged_sha256("envelope:key", &data)))
+    }
+
+    // The key is unique per envelope, the nonce is derived from the sequence number
+    fn nonce(sequence: u64) -> Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&sequence.to_le_bytes());
+        Nonce::from(nonce)
+    }
+
+    /// Encrypt the encoded message from the sender session key to the recipient session key with
+    /// the given ephemeral key and sequence number. The ephemeral key MUST be freshly generated
+    /// for every envelope and the sequence number incremented for every message sent in the swap.
+    pub fn seal<T: Encodable>(
+        message: &T,
+        sender: &SessionKey,
+        recipient: &SessionPublicKey,
+        ephemeral_key: &SessionKey,
+        sequence: u64,
+    ) -> Result<Self, consensus::Error> {
+        let ephemeral = ephemeral_key.public_key();
+        let ephemeral_shared = ephemeral_key.diffie_hellman(recipient)?;
+        let static_shared = sender.diffie_hellman(recipient)?;
+        let mut plaintext = vec![];
+        message.consensus_encode(&mut plaintext)?;
+        let ciphertext = Self::cipher(
+            ephemeral_shared,
+            static_shared,
+            &ephemeral,
+            &sender.public_key(),
+            recipient,
+        )
+        .encrypt(
+            &Self::nonce(sequence),
+            Payload {
+                msg: &plaintext,
+                aad: &Self::header(&ephemeral, sequence),
+            },
+        )
+        .map_err(|_| consensus::Error::ParseFailed("envelope encryption failed"))?;
+        Ok(Self {
+            ephemeral,
+            sequence,
+            ciphertext,
+        })
+    }
+
+    /// Decrypt and decode the message with the recipient session key, authenticating the sender
+    /// with its public session key. Fails with [`consensus::Error::InvalidMac`] if a key is wrong
+    /// or the envelope has been tampered with.
+    pub fn open<T: Decodable>(
+        &self,
+        key: &SessionKey,
+        sender: &SessionPublicKey,
+    ) -> Result<T, consensus::Error> {
+        let ephemeral_shared = key.diffie_hellman(&self.ephemeral)?;
+        let static_shared = key.diffie_hellman(sender)?;
+        let plaintext = Self::cipher(
+            ephemeral_shared,
+            static_shared,
+            &self.ephemeral,
+            sender,
+            &key.public_key(),
+        )
+        .decrypt(
+            &Self::nonce(self.sequence),
+            Payload {
+                msg: &self.ciphertext,
+                aad: &Self::header(&self.ephemeral, self.sequence),
+            },
+        )
+        .map_err(|_| consensus::Error::InvalidMac)?;
+        consensus::deserialize(&plaintext)
+    }
+
+    /// Same as [`Envelope::open`] but fails if the sequence number is not the expected one, e.g.
+    /// when a relay replays or reorders the messages.
+    pub fn open_in_sequence<T: Decodable>(
+        &self,
+        key: &SessionKey,
+        sender: &SessionPublicKey,
+        expected: u64,
+    ) -> Result<T, consensus::Error> {
+        if self.sequence != expected {
+            return Err(consensus::Error::ParseFailed("unexpected sequence number"));
+        }
+        self.open(key, sender)
+    }
+
+    /// Return the sequence number of the envelope, authenticated when opened.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+impl Encodable for Envelope {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = ENVELOPE_VERSION.consensus_encode(s)?;
+        len += self.ephemeral.consensus_encode(s)?;
+        len += self.sequence.consensus_encode(s)?;
+        Ok(len + self.ciphertext.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for Envelope {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        if u16::consensus_decode(d)? != ENVELOPE_VERSION {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
+        Ok(Self {
+            ephemeral: Decodable::consensus_decode(d)?,
+            sequence: Decodable::consensus_decode(d)?,
+            ciphertext: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(Envelope);
+
+/// A bundle of envelopes for the same recipient with their expiry times, for asynchronous
+/// delivery through store-and-forward relays. Expiry times are serialized with a precision of one
+/// second.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mailbox {
+    messages: Vec<(Duration, Envelope)>,
+}
+
+impl Mailbox {
+    /// Create an empty mailbox.
+    pub fn new() -> Self {
+        Self { messages: vec![] }
+    }
+
+    /// Add an envelope expiring at the given time.
+    pub fn with_message(mut self, envelope: Envelope, expiry: Duration) -> Self {
+        self.push(envelope, expiry);
+        self
+    }
+
+    /// Add an envelope expiring at the given time.
+    pub fn push(&mut self, envelope: Envelope, expiry: Duration) {
+        let expiry = Duration::from_secs(expiry.as_secs());
+        self.messages.push((expiry, envelope));
+    }
+
+    /// Drop the envelopes expired at `now` and return the number of envelopes dropped.
+    pub fn expire(&mut self, now: impl Clock) -> usize {
+        let len = self.messages.len();
+        let now = now.now();
+        self.messages.retain(|(expiry, _)| *expiry > now);
+        len - self.messages.len()
+    }
+
+    /// Return the envelopes not expired at `now` ordered by sequence number.
+    pub fn deliverable(&self, now: impl Clock) -> Vec<&Envelope> {
+        let now = now.now();
+        let mut envelopes: Vec<&Envelope> = self
+            .messages
+            .iter()
+            .filter(|(expiry, _)| *expiry > now)
+            .map(|(_, envelope)| envelope)
+            .collect();
+        envelopes.sort_by_key(|envelope| envelope.sequence());
+        envelopes
+    }
+
+    /// Open all the envelopes not expired at `now`, in order of sequence number starting at
+    /// `next_sequence`. Fails if a message is missing from the sequence or cannot be opened.
+    pub fn open_all<T: Decodable>(
+        &self,
+        key: &SessionKey,
+        sender: &SessionPublicKey,
+        next_sequence: u64,
+        now: impl Clock,
+    ) -> Result<Vec<T>, consensus::Error> {
+        self.deliverable(now)
+            .iter()
+            .zip(next_sequence..)
+            .map(|(envelope, sequence)| envelope.open_in_sequence(key, sender, sequence))
+            .collect()
+    }
+
+    /// Return the number of envelopes in the mailbox, expired envelopes included.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Return true if the mailbox contains no envelope.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl Encodable for Mailbox {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        if self.messages.len() > u16::MAX as usize {
+            return Err(io::Error::other("Too many messages"));
+        }
+        let mut len = ENVELOPE_VERSION.consensus_encode(s)?;
+        len += (self.messages.len() as u16).consensus_encode(s)?;
+        for (expiry, envelope) in self.messages.iter() {
+            len += Timestamp::try_from(*expiry)
+                .map_err(io::Error::other)?
+                .consensus_encode(s)?;
+            len += envelope.consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl Decodable for Mailbox {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        if u16::consensus_decode(d)? != ENVELOPE_VERSION {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
+        let count = u16::consensus_decode(d)?;
+        let messages = (0..count)
+            .map(|_| {
+                let expiry = Timestamp::consensus_decode(d)?.into();
+                Ok((expiry, Decodable::consensus_decode(d)?))
+            })
+            .collect::<Result<_, consensus::Error>>()?;
+        Ok(Self { messages })
+    }
+}
+
+impl_strict_encoding!(Mailbox);
@@ -0,0 +1,228 @@
+//! Escrow of the intermediate secrets needed to complete a swap after a crash.
+//!
+//! Once the counter-party transaction revealing the adaptor secret is seen on-chain, e.g. Bob's
+//! buy transaction for Alice, the participant must recover the secret and broadcast its own sweep.
+//! If the daemon crashes between the two, the adaptor signature and the decrypted signature only
+//! exist in memory and the funds are lost. A [`SecretEscrow`] holds these secrets and is persisted
+//! with the [`Checkpoint`](crate::checkpoint::Checkpoint) of the swap as soon as they are known,
+//! so the daemon can complete the swap when it restarts.
+//!
+//! Escrows are persisted encrypted as a [`SealedEscrow`] with a secret known only by the daemon.
+//! The encryption key is derived from the secret, the swap identifier, and the sequence number of
+//! the escrow with a keyed hash, and the escrow is encrypted with ChaCha20-Poly1305. The header is
+//! authenticated as associated data so a sealed escrow cannot be moved to another swap or
+//! replaced by an older one undetected.
+//!
+//! # Format
+//!
+//! ```text
+//! version (u16) | swap id (32 bytes) | sequence (u64) | ciphertext
+//! ```
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use std::io;
+
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::{hash, Keys, Signatures};
+use crate::swap::{Swap, SwapId};
+
+/// The current version of the sealed escrow format.
+pub const SECRET_ESCROW_VERSION: u16 = 1;
+
+/// The intermediate secrets needed to complete the swap of a participant, added as the swap
+/// progresses.
+pub struct SecretEscrow<Ctx: Swap> {
+    pub swap_id: SwapId,
+    /// The adaptor signature of the counter-party transaction, encrypted with the secret
+    pub adaptor_signature: Option<<Ctx::Ar as Signatures>::AdaptorSignature>,
+    /// The decrypted signature extracted from the counter-party transaction seen on-chain
+    pub signature: Option<<Ctx::Ar as Signatures>::Signature>,
+    /// The counter-party share of the accordant spend private key, once recovered
+    pub recovered_secret: Option<<Ctx::Ac as Keys>::PrivateKey>,
+}
+
+impl<Ctx> SecretEscrow<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create an empty escrow for a swap.
+    pub fn new(swap_id: SwapId) -> Self {
+        Self {
+            swap_id,
+            adaptor_signature: None,
+            signature: None,
+            recovered_secret: None,
+        }
+    }
+
+    /// Add the adaptor signature of the counter-party transaction to the escrow.
+    pub fn with_adaptor_signature(
+        mut self,
+        sig: <Ctx::Ar as Signatures>::AdaptorSignature,
+    ) -> Self {
+        self.adaptor_signature = Some(sig);
+        self
+    }
+
+    /// Add the decrypted signature seen on-chain to the escrow.
+    pub fn with_signature(mut self, sig: <Ctx::Ar as Signatures>::Signature) -> Self {
+        self.signature = Some(sig);
+        self
+    }
+
+    /// Add the recovered counter-party secret to the escrow.
+    pub fn with_recovered_secret(mut self, key: <Ctx::Ac as Keys>::PrivateKey) -> Self {
+        self.recovered_secret = Some(key);
+        self
+    }
+
+    /// Return true if the adaptor signature and the decrypted signature are escrowed but the
+    /// secret is not yet recovered, i.e. the daemon must recover it before sweeping.
+    pub fn is_pending_recovery(&self) -> bool {
+        self.adaptor_signature.is_some()
+            && self.signature.is_some()
+            && self.recovered_secret.is_none()
+    }
+}
+
+impl<Ctx> SecretEscrow<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+{
+    /// Encrypt the escrow with the daemon `secret`. The `sequence` MUST be incremented every time
+    /// the escrow of the swap is sealed, the encryption key is unique per sequence number.
+    pub fn seal(&self, secret: &[u8], sequence: u64) -> Result<SealedEscrow, consensus::Error> {
+        let mut plaintext = vec![];
+        self.consensus_encode(&mut plaintext)?;
+        let ciphertext = SealedEscrow::cipher(secret, &self.swap_id, sequence)
+            .encrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: &plaintext,
+                    aad: &SealedEscrow::header(&self.swap_id, sequence),
+                },
+            )
+            .map_err(|_| consensus::Error::ParseFailed("escrow encryption failed"))?;
+        Ok(SealedEscrow {
+            swap_id: self.swap_id,
+            sequence,
+            ciphertext,
+        })
+    }
+}
+
+/// An encrypted [`SecretEscrow`] safe to persist along the checkpoint of the swap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedEscrow {
+    swap_id: SwapId,
+    sequence: u64,
+    ciphertext: Vec<u8>,
+}
+
+impl SealedEscrow {
+    fn header(swap_id: &SwapId, sequence: u64) -> Vec<u8> {
+        let mut header = consensus::serialize(&SECRET_ESCROW_VERSION);
+        header.extend_from_slice(&swap_id.0);
+        header.extend(consensus::serialize(&sequence));
+        header
+    }
+
+    // The key is unique per sequence number, the nonce is always zero
+    fn cipher(secret: &[u8], swap_id: &SwapId, sequence: u64) -> ChaCha20Poly1305 {
+        let mut data = swap_id.0.to_vec();
+        data.extend_from_slice(&sequence.to_le_bytes());
+        ChaCha20Poly1305::new(&Key::from(hash::keyed_hash(
+            "secret_escrow:key",
+            secret,
+            &data,
+        )))
+    }
+
+    /// Return the swap of the escrow, authenticated when opened.
+    pub fn swap_id(&self) -> SwapId {
+        self.swap_id
+    }
+
+    /// Return the sequence number of the escrow, authenticated when opened.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Decrypt the escrow with the daemon `secret`. Fails with [`consensus::Error::InvalidMac`]
+    /// if the secret is wrong or the escrow has been tampered with.
+    pub fn open<Ctx>(&self, secret: &[u8]) -> Result<SecretEscrow<Ctx>, consensus::Error>
+    where
+        Ctx: Swap,
+        <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+    {
+        let plaintext = Self::cipher(secret, &self.swap_id, self.sequence)
+            .decrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &Self::header(&self.swap_id, self.sequence),
+                },
+            )
+            .map_err(|_| consensus::Error::InvalidMac)?;
+        let escrow: SecretEscrow<Ctx> = consensus::deserialize(&plaintext)?;
+        match escrow.swap_id == self.swap_id {
+            true => Ok(escrow),
+            false => Err(consensus::Error::ParseFailed("escrow swap id mismatch")),
+        }
+    }
+}
+
+impl Encodable for SealedEscrow {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = SECRET_ESCROW_VERSION.consensus_encode(s)?;
+        len += self.swap_id.consensus_encode(s)?;
+        len += self.sequence.consensus_encode(s)?;
+        Ok(len + self.ciphertext.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for SealedEscrow {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        if u16::consensus_decode(d)? != SECRET_ESCROW_VERSION {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            sequence: Decodable::consensus_decode(d)?,
+            ciphertext: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(SealedEscrow);
+
+impl<Ctx> Encodable for SecretEscrow<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.adaptor_signature.consensus_encode(s)?;
+        len += self.signature.consensus_encode(s)?;
+        Ok(len + self.recovered_secret.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for SecretEscrow<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            adaptor_signature: Decodable::consensus_decode(d)?,
+            signature: Decodable::consensus_decode(d)?,
+            recovered_secret: Decodable::consensus_decode(d)?,
+        })
+    }
+}
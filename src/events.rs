@@ -40,6 +40,7 @@ impl EventCore for HeightChanged {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Event {
     HeightChanged,
 }
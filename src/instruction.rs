@@ -1,5 +1,29 @@
 //! Farcaster instructions sent between client and daemon to instruct what to do next in the swap
 //! process.
+//!
+//! Signatures can be fully externalized to the client: every digest needing a signature is sent
+//! to the client in a [`SignRequest`] and answered with a [`SignResponse`], the
+//! [`RemoteSigner`] allows to run the role implementations without touching private keys. For
+//! air-gapped clients the [`WatchOnlySigner`] records the requests and lets the daemon resume the
+//! swap once the responses are imported.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt::Debug;
+use std::io;
+use std::sync::{Mutex, PoisonError};
+
+use secp256k1::PublicKey;
+
+use thiserror::Error;
+
+use crate::blockchain::{Asset, Network};
+use crate::consensus::{self, decode_option, encode_option, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::{self, ArbitratingKeyId, GenerateKey, Keys, SigHashPreimage, Sign, Signatures};
+use crate::negotiation::Offer;
+use crate::role::Arbitrating;
+use crate::swap::{Swap, SwapId};
+use crate::transaction::TxLabel;
 
 pub trait Instruction {}
 
@@ -21,3 +45,998 @@ pub struct Next {
 }
 
 impl Instruction for Next {}
+
+/// List of errors that can be encountered when exchanging sign requests and responses with the
+/// client.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The response does not correspond to the request.
+    #[error("The sign response does not match the sign request")]
+    InvalidResponse,
+    /// The client refused to honor the sign request.
+    #[error("The client refused the sign request")]
+    Refused,
+    /// The swap amount exceeds the maximum allowed by the policy.
+    #[error("The swap amount is not allowed by the policy")]
+    AmountNotAllowed,
+    /// The swap network is not allowed by the policy.
+    #[error("The swap network is not allowed by the policy")]
+    NetworkNotAllowed,
+    /// The counter-party node is not allowed by the policy.
+    #[error("The counter-party node is not allowed by the policy")]
+    CounterpartyNotAllowed,
+    /// Too many requests are sent, the request is refused by the policy.
+    #[error("The request is rate limited by the policy")]
+    RateLimited,
+    /// The signature is deferred to the client, the request is pending until answered.
+    #[error("The signature is deferred to the client")]
+    Deferred,
+    /// The sign request does not contain the preimage of the digest.
+    #[error("The sign request does not contain the preimage of the digest")]
+    MissingPreimage,
+    /// The digest of the sign request is not computed from its preimage.
+    #[error("The digest is not computed from the preimage")]
+    InvalidPreimage,
+    /// Any instruction error not part of this list.
+    #[error("Instruction error: {0}")]
+    Other(Box<dyn error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// Creates a new instruction error of type other with an arbitrary payload.
+    pub fn new<E>(error: E) -> Self
+    where
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+    {
+        Self::Other(error.into())
+    }
+
+    /// Consumes the `Error`, returning its inner error (if any).
+    ///
+    /// If this [`enum@Error`] was constructed via [`new`] then this function will return [`Some`],
+    /// otherwise it will return [`None`].
+    ///
+    /// [`new`]: Error::new
+    ///
+    pub fn into_inner(self) -> Option<Box<dyn error::Error + Send + Sync>> {
+        match self {
+            Self::Other(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for crypto::Error {
+    fn from(e: Error) -> crypto::Error {
+        crypto::Error::new(e)
+    }
+}
+
+/// Sent by the daemon to the client holding the private keys for every digest needing a
+/// signature. If an adaptor public key is set the client must produce an adaptor signature.
+#[derive(Debug, Clone)]
+pub struct SignRequest<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    /// The swap identifier the signature is requested for.
+    pub swap_id: SwapId,
+    /// The transaction the digest is computed from.
+    pub tx_label: TxLabel,
+    /// The digest to sign.
+    pub digest: Ar::Message,
+    /// The key to sign the digest with.
+    pub key_id: ArbitratingKeyId,
+    /// OPTIONAL: The adaptor public key used to encrypt the signature.
+    pub adaptor: Option<Ar::PublicKey>,
+    /// OPTIONAL: The exact data the digest is computed from, e.g. the BIP-143 signing data, for
+    /// the client to verify what it signs.
+    pub preimage: Option<Vec<u8>>,
+}
+
+impl<Ar> SignRequest<Ar>
+where
+    Ar: SigHashPreimage,
+{
+    /// Verify that the digest to sign is computed from the preimage of the request. Clients
+    /// SHOULD verify the preimage and decode it to check the transaction before signing.
+    pub fn verify_preimage(&self) -> Result<(), Error> {
+        let preimage = self.preimage.as_ref().ok_or(Error::MissingPreimage)?;
+        let digest = Ar::message_from_preimage(preimage);
+        match digest.as_canonical_bytes() == self.digest.as_canonical_bytes() {
+            true => Ok(()),
+            false => Err(Error::InvalidPreimage),
+        }
+    }
+}
+
+impl<Ar> Instruction for SignRequest<Ar> where Ar: Keys + Signatures {}
+
+impl<Ar> Encodable for SignRequest<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.tx_label.consensus_encode(s)?;
+        len += self.digest.as_canonical_bytes().consensus_encode(s)?;
+        len += self.key_id.consensus_encode(s)?;
+        len += self.adaptor.consensus_encode(s)?;
+        Ok(len + encode_option(&self.preimage, s)?)
+    }
+}
+
+impl<Ar> Decodable for SignRequest<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            tx_label: Decodable::consensus_decode(d)?,
+            digest: Ar::Message::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            key_id: Decodable::consensus_decode(d)?,
+            adaptor: Decodable::consensus_decode(d)?,
+            preimage: decode_option(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(SignRequest<Ar>, Ar: Keys + Signatures);
+
+/// Sent by the daemon to the client holding the private keys to adapt, i.e. decrypt, an adaptor
+/// signature with the private key corresponding to the adaptor.
+#[derive(Debug, Clone)]
+pub struct AdaptRequest<Ar>
+where
+    Ar: Signatures,
+{
+    /// The swap identifier the adapted signature is requested for.
+    pub swap_id: SwapId,
+    /// The transaction the adaptor signature is valid for.
+    pub tx_label: TxLabel,
+    /// The adaptor signature to adapt.
+    pub adaptor_signature: Ar::AdaptorSignature,
+    /// The key to adapt the signature with.
+    pub key_id: ArbitratingKeyId,
+}
+
+impl<Ar> Instruction for AdaptRequest<Ar> where Ar: Signatures {}
+
+impl<Ar> Encodable for AdaptRequest<Ar>
+where
+    Ar: Signatures,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.tx_label.consensus_encode(s)?;
+        len += self
+            .adaptor_signature
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        Ok(len + self.key_id.consensus_encode(s)?)
+    }
+}
+
+impl<Ar> Decodable for AdaptRequest<Ar>
+where
+    Ar: Signatures,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            tx_label: Decodable::consensus_decode(d)?,
+            adaptor_signature: Ar::AdaptorSignature::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            key_id: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(AdaptRequest<Ar>, Ar: Signatures);
+
+/// The signature produced by the client, a regular signature or an adaptor signature.
+#[derive(Debug, Clone)]
+pub enum SignedDigest<Ar>
+where
+    Ar: Signatures,
+{
+    /// A regular signature.
+    Signature(Ar::Signature),
+    /// An adaptor signature, i.e. an encrypted signature.
+    AdaptorSignature(Ar::AdaptorSignature),
+}
+
+impl<Ar> Encodable for SignedDigest<Ar>
+where
+    Ar: Signatures,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            SignedDigest::Signature(sig) => {
+                let len = 0x01u16.consensus_encode(s)?;
+                Ok(len + sig.as_canonical_bytes().consensus_encode(s)?)
+            }
+            SignedDigest::AdaptorSignature(sig) => {
+                let len = 0x02u16.consensus_encode(s)?;
+                Ok(len + sig.as_canonical_bytes().consensus_encode(s)?)
+            }
+        }
+    }
+}
+
+impl<Ar> Decodable for SignedDigest<Ar>
+where
+    Ar: Signatures,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u16 => Ok(SignedDigest::Signature(
+                Ar::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            )),
+            0x02u16 => Ok(SignedDigest::AdaptorSignature(
+                Ar::AdaptorSignature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            )),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+/// Sent by the client to the daemon in response to a [`SignRequest`] or an [`AdaptRequest`].
+#[derive(Debug, Clone)]
+pub struct SignResponse<Ar>
+where
+    Ar: Signatures,
+{
+    /// The swap identifier of the request.
+    pub swap_id: SwapId,
+    /// The transaction label of the request.
+    pub tx_label: TxLabel,
+    /// The signature produced by the client.
+    pub signature: SignedDigest<Ar>,
+}
+
+impl<Ar> Instruction for SignResponse<Ar> where Ar: Signatures {}
+
+impl<Ar> Encodable for SignResponse<Ar>
+where
+    Ar: Signatures,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.tx_label.consensus_encode(s)?;
+        Ok(len + self.signature.consensus_encode(s)?)
+    }
+}
+
+impl<Ar> Decodable for SignResponse<Ar>
+where
+    Ar: Signatures,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            tx_label: Decodable::consensus_decode(d)?,
+            signature: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(SignResponse<Ar>, Ar: Signatures);
+
+/// Implemented by the client holding the private keys, answers the sign requests sent by the
+/// daemon. The transport between the daemon and the client is left to the implementation.
+pub trait SignClient<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    /// Produce a signature, or an adaptor signature, for the request.
+    fn sign(&self, request: SignRequest<Ar>) -> Result<SignResponse<Ar>, Error>;
+
+    /// Adapt the adaptor signature of the request.
+    fn adapt(&self, request: AdaptRequest<Ar>) -> Result<SignResponse<Ar>, Error>;
+}
+
+// Retreive the public keys of the participant bound to the swap transactions
+fn arbitrating_keys<PublicKey>(
+    keys: &impl GenerateKey<PublicKey, ArbitratingKeyId>,
+) -> Vec<(ArbitratingKeyId, PublicKey)> {
+    [
+        ArbitratingKeyId::Fund,
+        ArbitratingKeyId::Buy,
+        ArbitratingKeyId::Cancel,
+        ArbitratingKeyId::Refund,
+        ArbitratingKeyId::Punish,
+    ]
+    .iter()
+    .filter_map(|id| keys.get_pubkey(*id).ok().map(|key| (*id, key)))
+    .collect()
+}
+
+fn find_key_id<PublicKey: PartialEq>(
+    keys: &[(ArbitratingKeyId, PublicKey)],
+    key: &PublicKey,
+) -> Result<ArbitratingKeyId, crypto::Error> {
+    keys.iter()
+        .find(|(_, k)| k == key)
+        .map(|(id, _)| *id)
+        .ok_or(crypto::Error::UnsupportedKey)
+}
+
+fn infer_tx_label(
+    forced: Option<TxLabel>,
+    key_id: ArbitratingKeyId,
+) -> Result<TxLabel, crypto::Error> {
+    match (forced, key_id) {
+        (Some(label), _) => Ok(label),
+        // The funding key signs both the lock and the sweep, the label must be forced
+        (None, ArbitratingKeyId::Fund) => Err(crypto::Error::UnsupportedKey),
+        (None, ArbitratingKeyId::Buy) => Ok(TxLabel::Buy),
+        (None, ArbitratingKeyId::Cancel) => Ok(TxLabel::Cancel),
+        (None, ArbitratingKeyId::Refund) => Ok(TxLabel::Refund),
+        (None, ArbitratingKeyId::Punish) => Ok(TxLabel::Punish),
+        // Extra keys are not bound to a transaction, the label must be forced
+        (None, ArbitratingKeyId::Extra(_)) => Err(crypto::Error::UnsupportedKey),
+    }
+}
+
+// Register the preimage with the digest computed from it
+fn add_preimage<Ar: SigHashPreimage>(preimages: &mut Vec<(Vec<u8>, Vec<u8>)>, preimage: Vec<u8>) {
+    let digest = Ar::message_from_preimage(&preimage).as_canonical_bytes();
+    if !preimages.iter().any(|(d, _)| d == &digest) {
+        preimages.push((digest, preimage));
+    }
+}
+
+fn find_preimage(
+    preimages: &[(Vec<u8>, Vec<u8>)],
+    digest: &impl CanonicalBytes,
+) -> Option<Vec<u8>> {
+    let digest = digest.as_canonical_bytes();
+    preimages
+        .iter()
+        .find(|(d, _)| d == &digest)
+        .map(|(_, preimage)| preimage.clone())
+}
+
+/// A signer that never touches private keys: every signing operation is turned into a
+/// [`SignRequest`] or an [`AdaptRequest`] and forwarded to the [`SignClient`]. Verifications and
+/// key recovery are done with the public `verifier`.
+///
+/// The signer implements [`Sign`] and can be passed to the role implementations in place of a
+/// wallet holding the private keys. The transaction label of each request is inferred from the
+/// key used to sign, unless one is forced with [`RemoteSigner::with_tx_label`]. The funding key
+/// signs both the [`TxLabel::Lock`] and the [`TxLabel::Sweep`] transactions, its label must always
+/// be forced. Preimages added
+/// with [`RemoteSigner::add_preimage`] are attached to the requests of their digest.
+///
+/// Signatures and adaptor signatures returned by the client are verified against the requested
+/// key and digest before being returned. Adapted signatures cannot be verified without the
+/// digest and are returned as is.
+pub struct RemoteSigner<'a, Ar, C, V>
+where
+    Ar: Keys + Signatures,
+{
+    swap_id: SwapId,
+    tx_label: Option<TxLabel>,
+    keys: Vec<(ArbitratingKeyId, Ar::PublicKey)>,
+    preimages: Vec<(Vec<u8>, Vec<u8>)>,
+    client: &'a C,
+    verifier: &'a V,
+}
+
+impl<'a, Ar, C, V> RemoteSigner<'a, Ar, C, V>
+where
+    Ar: Keys + Signatures,
+    Ar::PublicKey: PartialEq,
+    C: SignClient<Ar>,
+    V: Sign<Ar::PublicKey, Ar::PrivateKey, Ar::Message, Ar::Signature, Ar::AdaptorSignature>,
+{
+    /// Create a new remote signer for the swap. The public `keys` are used to identify the key to
+    /// sign with in requests.
+    pub fn new(
+        swap_id: SwapId,
+        keys: &impl GenerateKey<Ar::PublicKey, ArbitratingKeyId>,
+        client: &'a C,
+        verifier: &'a V,
+    ) -> Self {
+        Self {
+            swap_id,
+            tx_label: None,
+            keys: arbitrating_keys(keys),
+            preimages: vec![],
+            client,
+            verifier,
+        }
+    }
+
+    /// Force the transaction label set in the requests instead of inferring it from the key.
+    pub fn with_tx_label(mut self, tx_label: TxLabel) -> Self {
+        self.tx_label = Some(tx_label);
+        self
+    }
+
+    /// Add the preimage of a digest to sign, e.g. computed with
+    /// [`Witnessable::generate_witness_preimage`], the preimage is sent in the requests of the
+    /// digest.
+    ///
+    /// [`Witnessable::generate_witness_preimage`]: crate::transaction::Witnessable::generate_witness_preimage
+    pub fn add_preimage(&mut self, preimage: Vec<u8>)
+    where
+        Ar: SigHashPreimage,
+    {
+        add_preimage::<Ar>(&mut self.preimages, preimage);
+    }
+
+    fn key_id(&self, key: &Ar::PublicKey) -> Result<ArbitratingKeyId, crypto::Error> {
+        find_key_id(&self.keys, key)
+    }
+
+    fn tx_label(&self, key_id: ArbitratingKeyId) -> Result<TxLabel, crypto::Error> {
+        infer_tx_label(self.tx_label, key_id)
+    }
+
+    fn request(
+        &self,
+        key: &Ar::PublicKey,
+        adaptor: Option<Ar::PublicKey>,
+        msg: Ar::Message,
+    ) -> Result<SignedDigest<Ar>, crypto::Error> {
+        let key_id = self.key_id(key)?;
+        let tx_label = self.tx_label(key_id)?;
+        let response = self.client.sign(SignRequest {
+            swap_id: self.swap_id,
+            tx_label,
+            preimage: find_preimage(&self.preimages, &msg),
+            digest: msg.clone(),
+            key_id,
+            adaptor: adaptor.clone(),
+        })?;
+        if response.swap_id != self.swap_id || response.tx_label != tx_label {
+            return Err(Error::InvalidResponse.into());
+        }
+        // The client is not trusted, the signature must be valid for the requested key and digest
+        match (&response.signature, adaptor) {
+            (SignedDigest::Signature(sig), None) => {
+                self.verifier.verify_signature(key, msg, sig)?
+            }
+            (SignedDigest::AdaptorSignature(sig), Some(adaptor)) => self
+                .verifier
+                .verify_adaptor_signature(key, &adaptor, msg, sig)?,
+            _ => return Err(Error::InvalidResponse.into()),
+        }
+        Ok(response.signature)
+    }
+}
+
+impl<'a, Ar, C, V>
+    Sign<Ar::PublicKey, Ar::PrivateKey, Ar::Message, Ar::Signature, Ar::AdaptorSignature>
+    for RemoteSigner<'a, Ar, C, V>
+where
+    Ar: Keys + Signatures,
+    Ar::PublicKey: PartialEq,
+    C: SignClient<Ar>,
+    V: Sign<Ar::PublicKey, Ar::PrivateKey, Ar::Message, Ar::Signature, Ar::AdaptorSignature>,
+{
+    fn sign_with_key(
+        &self,
+        key: &Ar::PublicKey,
+        msg: Ar::Message,
+    ) -> Result<Ar::Signature, crypto::Error> {
+        match self.request(key, None, msg)? {
+            SignedDigest::Signature(sig) => Ok(sig),
+            SignedDigest::AdaptorSignature(_) => Err(Error::InvalidResponse.into()),
+        }
+    }
+
+    fn verify_signature(
+        &self,
+        key: &Ar::PublicKey,
+        msg: Ar::Message,
+        sig: &Ar::Signature,
+    ) -> Result<(), crypto::Error> {
+        self.verifier.verify_signature(key, msg, sig)
+    }
+
+    fn adaptor_sign_with_key(
+        &self,
+        key: &Ar::PublicKey,
+        adaptor: &Ar::PublicKey,
+        msg: Ar::Message,
+    ) -> Result<Ar::AdaptorSignature, crypto::Error> {
+        match self.request(key, Some(adaptor.clone()), msg)? {
+            SignedDigest::AdaptorSignature(sig) => Ok(sig),
+            SignedDigest::Signature(_) => Err(Error::InvalidResponse.into()),
+        }
+    }
+
+    fn verify_adaptor_signature(
+        &self,
+        key: &Ar::PublicKey,
+        adaptor: &Ar::PublicKey,
+        msg: Ar::Message,
+        sig: &Ar::AdaptorSignature,
+    ) -> Result<(), crypto::Error> {
+        self.verifier
+            .verify_adaptor_signature(key, adaptor, msg, sig)
+    }
+
+    fn adapt_signature(
+        &self,
+        key: &Ar::PublicKey,
+        sig: Ar::AdaptorSignature,
+    ) -> Result<Ar::Signature, crypto::Error> {
+        let key_id = self.key_id(key)?;
+        let tx_label = self.tx_label(key_id)?;
+        let response = self.client.adapt(AdaptRequest {
+            swap_id: self.swap_id,
+            tx_label,
+            adaptor_signature: sig,
+            key_id,
+        })?;
+        match (
+            response.swap_id == self.swap_id && response.tx_label == tx_label,
+            response.signature,
+        ) {
+            (true, SignedDigest::Signature(sig)) => Ok(sig),
+            _ => Err(Error::InvalidResponse.into()),
+        }
+    }
+
+    fn recover_key(&self, sig: Ar::Signature, adapted_sig: Ar::AdaptorSignature) -> Ar::PrivateKey {
+        self.verifier.recover_key(sig, adapted_sig)
+    }
+}
+
+/// A request waiting for the answer of an offline client, see [`WatchOnlySigner`].
+#[derive(Debug, Clone)]
+pub enum PendingRequest<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    /// A signature or adaptor signature request.
+    Sign(SignRequest<Ar>),
+    /// An adapt request.
+    Adapt(AdaptRequest<Ar>),
+}
+
+impl<Ar> PendingRequest<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    fn accepts(&self, response: &SignResponse<Ar>) -> bool {
+        let (swap_id, tx_label) = match self {
+            PendingRequest::Sign(request) => (request.swap_id, request.tx_label),
+            PendingRequest::Adapt(request) => (request.swap_id, request.tx_label),
+        };
+        let kind = matches!(
+            (self, &response.signature),
+            (
+                PendingRequest::Sign(SignRequest { adaptor: None, .. }),
+                SignedDigest::Signature(_)
+            ) | (
+                PendingRequest::Sign(SignRequest {
+                    adaptor: Some(_),
+                    ..
+                }),
+                SignedDigest::AdaptorSignature(_)
+            ) | (PendingRequest::Adapt(_), SignedDigest::Signature(_))
+        );
+        kind && swap_id == response.swap_id && tx_label == response.tx_label
+    }
+}
+
+impl<Ar> Encodable for PendingRequest<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            PendingRequest::Sign(request) => {
+                let len = 0x01u16.consensus_encode(s)?;
+                Ok(len + request.consensus_encode(s)?)
+            }
+            PendingRequest::Adapt(request) => {
+                let len = 0x02u16.consensus_encode(s)?;
+                Ok(len + request.consensus_encode(s)?)
+            }
+        }
+    }
+}
+
+impl<Ar> Decodable for PendingRequest<Ar>
+where
+    Ar: Keys + Signatures,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u16 => Ok(PendingRequest::Sign(Decodable::consensus_decode(d)?)),
+            0x02u16 => Ok(PendingRequest::Adapt(Decodable::consensus_decode(d)?)),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl_strict_encoding!(PendingRequest<Ar>, Ar: Keys + Signatures);
+
+/// A watch-only signer for air-gapped signing workflows. Like the [`RemoteSigner`] it never
+/// touches private keys, but it does not wait for the client: every signing operation without an
+/// answer yet is recorded as a [`PendingRequest`] and fails with [`Error::Deferred`].
+///
+/// The daemon exports the pending requests to the offline client, e.g. with a QR code, and
+/// imports the responses with [`WatchOnlySigner::answer`]. Signatures and adaptor signatures are
+/// verified with the public `verifier` when answered, the role method that failed can then be
+/// called again and completes with the answered signatures.
+pub struct WatchOnlySigner<'a, Ar, V>
+where
+    Ar: Keys + Signatures,
+{
+    swap_id: SwapId,
+    tx_label: Option<TxLabel>,
+    keys: Vec<(ArbitratingKeyId, Ar::PublicKey)>,
+    preimages: Vec<(Vec<u8>, Vec<u8>)>,
+    verifier: &'a V,
+    pending: Mutex<Vec<PendingRequest<Ar>>>,
+    answered: Vec<(Vec<u8>, SignedDigest<Ar>)>,
+}
+
+impl<'a, Ar, V> WatchOnlySigner<'a, Ar, V>
+where
+    Ar: Keys + Signatures + Clone + Debug,
+    Ar::PublicKey: PartialEq,
+    V: Sign<Ar::PublicKey, Ar::PrivateKey, Ar::Message, Ar::Signature, Ar::AdaptorSignature>,
+{
+    /// Create a new watch-only signer for the swap. The public `keys` are used to identify the
+    /// key to sign with in requests.
+    pub fn new(
+        swap_id: SwapId,
+        keys: &impl GenerateKey<Ar::PublicKey, ArbitratingKeyId>,
+        verifier: &'a V,
+    ) -> Self {
+        Self {
+            swap_id,
+            tx_label: None,
+            keys: arbitrating_keys(keys),
+            preimages: vec![],
+            verifier,
+            pending: Mutex::new(vec![]),
+            answered: vec![],
+        }
+    }
+
+    /// Force the transaction label set in the requests instead of inferring it from the key.
+    pub fn with_tx_label(mut self, tx_label: TxLabel) -> Self {
+        self.tx_label = Some(tx_label);
+        self
+    }
+
+    /// Add the preimage of a digest to sign, the preimage is exported in the pending requests of
+    /// the digest, see [`RemoteSigner::add_preimage`].
+    pub fn add_preimage(&mut self, preimage: Vec<u8>)
+    where
+        Ar: SigHashPreimage,
+    {
+        add_preimage::<Ar>(&mut self.preimages, preimage);
+    }
+
+    /// Return the requests waiting for an answer, in the order they were issued.
+    pub fn pending(&self) -> Vec<PendingRequest<Ar>> {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Return true if at least one request is waiting for an answer.
+    pub fn has_pending(&self) -> bool {
+        !self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
+    }
+
+    /// Import the response of the client to the first matching pending request. Fails with
+    /// [`Error::InvalidResponse`] if no request matches or if the signature is not valid for the
+    /// request. Adapted signatures cannot be verified without the message and are accepted as is.
+    pub fn answer(&mut self, response: SignResponse<Ar>) -> Result<(), Error> {
+        let (keys, verifier) = (&self.keys, self.verifier);
+        let pending = self
+            .pending
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        let index = pending
+            .iter()
+            .position(|request| request.accepts(&response))
+            .ok_or(Error::InvalidResponse)?;
+        let valid = match (&pending[index], &response.signature) {
+            (PendingRequest::Sign(request), SignedDigest::Signature(sig)) => {
+                find_key(keys, request.key_id).and_then(|key| {
+                    verifier
+                        .verify_signature(key, request.digest.clone(), sig)
+                        .ok()
+                })
+            }
+            (PendingRequest::Sign(request), SignedDigest::AdaptorSignature(sig)) => {
+                match (find_key(keys, request.key_id), &request.adaptor) {
+                    (Some(key), Some(adaptor)) => verifier
+                        .verify_adaptor_signature(key, adaptor, request.digest.clone(), sig)
+                        .ok(),
+                    _ => None,
+                }
+            }
+            (PendingRequest::Adapt(_), _) => Some(()),
+        };
+        valid.ok_or(Error::InvalidResponse)?;
+        let request = pending.remove(index);
+        self.answered
+            .push((consensus::serialize(&request), response.signature));
+        Ok(())
+    }
+
+    // Return the answered signature of the request or record the request as pending
+    fn request(&self, request: PendingRequest<Ar>) -> Result<SignedDigest<Ar>, crypto::Error> {
+        let id = consensus::serialize(&request);
+        if let Some((_, signature)) = self.answered.iter().find(|(r, _)| r == &id) {
+            return Ok(signature.clone());
+        }
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        if !pending.iter().any(|r| consensus::serialize(r) == id) {
+            pending.push(request);
+        }
+        Err(Error::Deferred.into())
+    }
+
+    fn sign_request(
+        &self,
+        key: &Ar::PublicKey,
+        adaptor: Option<Ar::PublicKey>,
+        msg: Ar::Message,
+    ) -> Result<SignedDigest<Ar>, crypto::Error> {
+        let key_id = find_key_id(&self.keys, key)?;
+        self.request(PendingRequest::Sign(SignRequest {
+            swap_id: self.swap_id,
+            tx_label: infer_tx_label(self.tx_label, key_id)?,
+            preimage: find_preimage(&self.preimages, &msg),
+            digest: msg,
+            key_id,
+            adaptor,
+        }))
+    }
+}
+
+fn find_key<PublicKey>(
+    keys: &[(ArbitratingKeyId, PublicKey)],
+    key_id: ArbitratingKeyId,
+) -> Option<&PublicKey> {
+    keys.iter()
+        .find(|(id, _)| *id == key_id)
+        .map(|(_, key)| key)
+}
+
+impl<'a, Ar, V>
+    Sign<Ar::PublicKey, Ar::PrivateKey, Ar::Message, Ar::Signature, Ar::AdaptorSignature>
+    for WatchOnlySigner<'a, Ar, V>
+where
+    Ar: Keys + Signatures + Clone + Debug,
+    Ar::PublicKey: PartialEq,
+    V: Sign<Ar::PublicKey, Ar::PrivateKey, Ar::Message, Ar::Signature, Ar::AdaptorSignature>,
+{
+    fn sign_with_key(
+        &self,
+        key: &Ar::PublicKey,
+        msg: Ar::Message,
+    ) -> Result<Ar::Signature, crypto::Error> {
+        match self.sign_request(key, None, msg)? {
+            SignedDigest::Signature(sig) => Ok(sig),
+            SignedDigest::AdaptorSignature(_) => Err(Error::InvalidResponse.into()),
+        }
+    }
+
+    fn verify_signature(
+        &self,
+        key: &Ar::PublicKey,
+        msg: Ar::Message,
+        sig: &Ar::Signature,
+    ) -> Result<(), crypto::Error> {
+        self.verifier.verify_signature(key, msg, sig)
+    }
+
+    fn adaptor_sign_with_key(
+        &self,
+        key: &Ar::PublicKey,
+        adaptor: &Ar::PublicKey,
+        msg: Ar::Message,
+    ) -> Result<Ar::AdaptorSignature, crypto::Error> {
+        match self.sign_request(key, Some(adaptor.clone()), msg)? {
+            SignedDigest::AdaptorSignature(sig) => Ok(sig),
+            SignedDigest::Signature(_) => Err(Error::InvalidResponse.into()),
+        }
+    }
+
+    fn verify_adaptor_signature(
+        &self,
+        key: &Ar::PublicKey,
+        adaptor: &Ar::PublicKey,
+        msg: Ar::Message,
+        sig: &Ar::AdaptorSignature,
+    ) -> Result<(), crypto::Error> {
+        self.verifier
+            .verify_adaptor_signature(key, adaptor, msg, sig)
+    }
+
+    fn adapt_signature(
+        &self,
+        key: &Ar::PublicKey,
+        sig: Ar::AdaptorSignature,
+    ) -> Result<Ar::Signature, crypto::Error> {
+        let key_id = find_key_id(&self.keys, key)?;
+        let request = PendingRequest::Adapt(AdaptRequest {
+            swap_id: self.swap_id,
+            tx_label: infer_tx_label(self.tx_label, key_id)?,
+            adaptor_signature: sig,
+            key_id,
+        });
+        match self.request(request)? {
+            SignedDigest::Signature(sig) => Ok(sig),
+            SignedDigest::AdaptorSignature(_) => Err(Error::InvalidResponse.into()),
+        }
+    }
+
+    fn recover_key(&self, sig: Ar::Signature, adapted_sig: Ar::AdaptorSignature) -> Ar::PrivateKey {
+        self.verifier.recover_key(sig, adapted_sig)
+    }
+}
+
+/// The details of a swap the client needs to apply its [`Policy`] on the sign requests.
+#[derive(Debug, Clone)]
+pub struct SwapContext<Ar>
+where
+    Ar: Asset,
+{
+    /// The network the swap runs on.
+    pub network: Network,
+    /// The amount of arbitrating assets exchanged in the swap.
+    pub arbitrating_amount: Ar::AssetUnit,
+    /// OPTIONAL: The node identifier of the counter-party daemon.
+    pub counterparty: Option<PublicKey>,
+}
+
+impl<Ar> SwapContext<Ar>
+where
+    Ar: Arbitrating,
+{
+    /// Create the swap context from the offer agreed upon during the negotiation phase and the
+    /// counter-party node, if known.
+    pub fn new<Ctx>(offer: &Offer<Ctx>, counterparty: Option<PublicKey>) -> Self
+    where
+        Ctx: Swap<Ar = Ar>,
+    {
+        Self {
+            network: offer.network,
+            arbitrating_amount: offer.arbitrating_amount,
+            counterparty,
+        }
+    }
+}
+
+/// Veto policy implemented by the client wallet and consulted before honoring any sign request,
+/// turning the client into an enforcement point instead of trusting the daemon. All the checks
+/// allow everything by default.
+pub trait Policy<Ar>
+where
+    Ar: Asset,
+{
+    /// The maximum amount of arbitrating assets allowed per swap, `None` if unlimited.
+    fn max_amount(&self) -> Option<Ar::AssetUnit> {
+        None
+    }
+
+    /// The list of networks allowed, `None` if all networks are allowed.
+    fn allowed_networks(&self) -> Option<Vec<Network>> {
+        None
+    }
+
+    /// The list of counter-party node identifiers allowed, `None` if all nodes are allowed. If a
+    /// list is set swaps without known counter-party are refused.
+    fn allowed_counterparties(&self) -> Option<Vec<PublicKey>> {
+        None
+    }
+
+    /// Called for every request passing the other checks, implementations can keep track of the
+    /// requests to enforce rate limits. Returns `false` if the request must be refused.
+    fn allow_request(&self, _swap_id: &SwapId, _tx_label: TxLabel) -> bool {
+        true
+    }
+
+    /// Check the request against the policy, returns an error if it must not be honored.
+    fn check(
+        &self,
+        swap_id: &SwapId,
+        tx_label: TxLabel,
+        context: &SwapContext<Ar>,
+    ) -> Result<(), Error> {
+        if let Some(max) = self.max_amount() {
+            if context.arbitrating_amount > max {
+                return Err(Error::AmountNotAllowed);
+            }
+        }
+        if let Some(networks) = self.allowed_networks() {
+            if !networks.contains(&context.network) {
+                return Err(Error::NetworkNotAllowed);
+            }
+        }
+        if let Some(nodes) = self.allowed_counterparties() {
+            match context.counterparty {
+                Some(node) if nodes.contains(&node) => (),
+                _ => return Err(Error::CounterpartyNotAllowed),
+            }
+        }
+        match self.allow_request(swap_id, tx_label) {
+            true => Ok(()),
+            false => Err(Error::RateLimited),
+        }
+    }
+}
+
+/// A [`SignClient`] enforcing a [`Policy`] before forwarding the requests to the inner client.
+/// Requests for swaps not registered with [`PolicyClient::register_swap`] are refused.
+pub struct PolicyClient<'a, Ar, C, P>
+where
+    Ar: Asset,
+{
+    client: &'a C,
+    policy: &'a P,
+    swaps: HashMap<SwapId, SwapContext<Ar>>,
+}
+
+impl<'a, Ar, C, P> PolicyClient<'a, Ar, C, P>
+where
+    Ar: Keys + Signatures + Asset,
+    C: SignClient<Ar>,
+    P: Policy<Ar>,
+{
+    /// Create a new client enforcing the policy, without any registered swap.
+    pub fn new(client: &'a C, policy: &'a P) -> Self {
+        Self {
+            client,
+            policy,
+            swaps: HashMap::new(),
+        }
+    }
+
+    /// Register a swap and its context, the requests for this swap are then checked against the
+    /// policy.
+    pub fn register_swap(&mut self, swap_id: SwapId, context: SwapContext<Ar>) {
+        self.swaps.insert(swap_id, context);
+    }
+
+    /// Remove a swap, the requests for this swap are then refused.
+    pub fn remove_swap(&mut self, swap_id: &SwapId) -> Option<SwapContext<Ar>> {
+        self.swaps.remove(swap_id)
+    }
+
+    fn check(&self, swap_id: &SwapId, tx_label: TxLabel) -> Result<(), Error> {
+        let context = self.swaps.get(swap_id).ok_or(Error::Refused)?;
+        self.policy.check(swap_id, tx_label, context)
+    }
+}
+
+impl<'a, Ar, C, P> SignClient<Ar> for PolicyClient<'a, Ar, C, P>
+where
+    Ar: Keys + Signatures + Asset,
+    C: SignClient<Ar>,
+    P: Policy<Ar>,
+{
+    fn sign(&self, request: SignRequest<Ar>) -> Result<SignResponse<Ar>, Error> {
+        self.check(&request.swap_id, request.tx_label)?;
+        self.client.sign(request)
+    }
+
+    fn adapt(&self, request: AdaptRequest<Ar>) -> Result<SignResponse<Ar>, Error> {
+        self.check(&request.swap_id, request.tx_label)?;
+        self.client.adapt(request)
+    }
+}
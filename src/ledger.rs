@@ -0,0 +1,220 @@
+//! Accounting of the fees set on the transactions of a swap.
+//!
+//! A [`FeeLedger`] records the fee paid by every arbitrating transaction built during a swap, as
+//! applied by [`Fee::set_fee`] or read back from a transaction received from the counter-party.
+//! The ledger is attached to the [`Session`] of the swap, aggregated in the [`Progress`] of the
+//! sessions, and added to the [`SettlementReport`] once the swap is over, so the total on-chain
+//! cost of the swap can be shown to the user before consenting to each signing step with
+//! [`FeeLedger::total_with`].
+//!
+//! Transactions are recorded per [`TxLabel`], recording a transaction again, e.g. when it is
+//! rebuilt after a re-funding, replaces its previous fee.
+//!
+//! [`Session`]: crate::protocol::session::Session
+//! [`Progress`]: crate::protocol::session::Progress
+//! [`SettlementReport`]: crate::settlement::SettlementReport
+
+use std::io;
+use std::ops::Add;
+
+use crate::blockchain::{
+    AmountError, AppliedFee, AtomicAmount, Fee, FeePolitic, FeeStrategy, FeeStrategyError, Onchain,
+};
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::transaction::TxLabel;
+
+/// The fee paid by a transaction of the swap, in the fee asset of the blockchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEntry<F> {
+    /// The transaction paying the fee
+    pub label: TxLabel,
+    /// The amount of fee paid
+    pub fee: F,
+}
+
+impl<F> Encodable for FeeEntry<F>
+where
+    F: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.label.consensus_encode(s)?;
+        Ok(len + self.fee.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<F> Decodable for FeeEntry<F>
+where
+    F: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            label: Decodable::consensus_decode(d)?,
+            fee: F::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+/// The fees of all the transactions built during a swap, in the order they were first recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeLedger<F> {
+    entries: Vec<FeeEntry<F>>,
+}
+
+impl<F> Default for FeeLedger<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> FeeLedger<F> {
+    /// Create an empty ledger.
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Return the recorded fees in the order the transactions were first recorded.
+    pub fn entries(&self) -> &[FeeEntry<F>] {
+        &self.entries
+    }
+
+    /// Return the number of transactions recorded.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return true if no transaction is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record the fee paid by the transaction and return the fee previously recorded for it, if
+    /// any.
+    pub fn record(&mut self, label: TxLabel, fee: F) -> Option<F> {
+        match self.entries.iter_mut().find(|entry| entry.label == label) {
+            Some(entry) => Some(std::mem::replace(&mut entry.fee, fee)),
+            None => {
+                self.entries.push(FeeEntry { label, fee });
+                None
+            }
+        }
+    }
+
+    /// Remove the transaction from the ledger, e.g. when it is replaced by another transaction,
+    /// and return its fee.
+    pub fn remove(&mut self, label: TxLabel) -> Option<F> {
+        let index = self.entries.iter().position(|entry| entry.label == label)?;
+        Some(self.entries.remove(index).fee)
+    }
+}
+
+impl<F> FeeLedger<F>
+where
+    F: Copy,
+{
+    /// Return the fee recorded for the transaction, if any.
+    pub fn fee(&self, label: TxLabel) -> Option<F> {
+        self.entries
+            .iter()
+            .find(|entry| entry.label == label)
+            .map(|entry| entry.fee)
+    }
+
+    /// Set the fee on the transaction with [`Fee::set_fee`] and record the fee paid.
+    pub fn apply<Ar>(
+        &mut self,
+        label: TxLabel,
+        tx: &mut <Ar as Onchain>::PartialTransaction,
+        strategy: &FeeStrategy<Ar::FeeUnit>,
+        politic: FeePolitic,
+    ) -> Result<AppliedFee<Ar::AssetUnit, F>, FeeStrategyError>
+    where
+        Ar: Fee<FeeAssetUnit = F>,
+    {
+        let applied = Ar::set_fee(tx, label, strategy, politic)?;
+        self.record(label, applied.fee);
+        Ok(applied)
+    }
+
+    /// Record the fee paid by a transaction already built, e.g. received from the counter-party,
+    /// see [`Fee::fee_paid`], and return it.
+    pub fn record_transaction<Ar>(
+        &mut self,
+        label: TxLabel,
+        tx: &<Ar as Onchain>::PartialTransaction,
+    ) -> Result<F, FeeStrategyError>
+    where
+        Ar: Fee<FeeAssetUnit = F>,
+    {
+        let fee = Ar::fee_paid(tx)?;
+        self.record(label, fee);
+        Ok(fee)
+    }
+}
+
+impl<F> FeeLedger<F>
+where
+    F: Copy + Default + Add<Output = F>,
+{
+    /// Return the sum of the fees of all the recorded transactions.
+    pub fn total(&self) -> F {
+        self.entries
+            .iter()
+            .fold(F::default(), |total, entry| total + entry.fee)
+    }
+
+    /// Return the total the swap would cost if the transaction is signed with the given fee,
+    /// replacing its recorded fee if any. Meant to be shown before consenting to sign it.
+    pub fn total_with(&self, label: TxLabel, fee: F) -> F {
+        self.entries
+            .iter()
+            .filter(|entry| entry.label != label)
+            .fold(fee, |total, entry| total + entry.fee)
+    }
+}
+
+impl<F> FeeLedger<F>
+where
+    F: AtomicAmount,
+{
+    /// Return the sum of the fees of all the recorded transactions, fails on overflow, e.g. for
+    /// fees recorded from transactions built by the counter-party.
+    pub fn checked_total(&self) -> Result<F, AmountError> {
+        F::try_sum(self.entries.iter().map(|entry| entry.fee))
+    }
+
+    /// Checked version of [`FeeLedger::total_with`], fails on overflow.
+    pub fn checked_total_with(&self, label: TxLabel, fee: F) -> Result<F, AmountError> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.label != label)
+            .try_fold(fee, |total, entry| total.try_add(&entry.fee))
+    }
+}
+
+impl<F> Encodable for FeeLedger<F>
+where
+    F: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.entries.consensus_encode(s)
+    }
+}
+
+impl<F> Decodable for FeeLedger<F>
+where
+    F: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let entries: Vec<FeeEntry<F>> = Decodable::consensus_decode(d)?;
+        for (i, entry) in entries.iter().enumerate() {
+            if entries[..i].iter().any(|other| other.label == entry.label) {
+                return Err(consensus::Error::ParseFailed(
+                    "duplicated transaction in fee ledger",
+                ));
+            }
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl_strict_encoding!(FeeLedger<F>, F: CanonicalBytes);
@@ -1,28 +1,49 @@
 //! Farcaster Core library
+//!
+//! The blockchain implementations live under [`chain`] and the messages exchanged between swap
+//! participants in [`protocol_message`].
 
 use thiserror::Error;
 
 #[macro_use]
 pub mod consensus;
+#[macro_use]
+pub mod describe;
 
 pub mod blockchain;
 pub mod bundle;
 pub mod chain;
+pub mod checkpoint;
+pub mod clock;
 pub mod crypto;
+pub mod dispute;
+#[cfg(feature = "envelope")]
+pub mod envelope;
+pub mod escrow;
 //pub mod datum;
 pub mod events;
 pub mod instruction;
+pub mod ledger;
 pub mod negotiation;
+pub mod observer;
+pub mod protocol;
 pub mod protocol_message;
+pub mod recovery;
 pub mod role;
 pub mod script;
+pub mod settlement;
+#[cfg(feature = "test-utils")]
+pub mod strategies;
 pub mod swap;
 pub mod syncer;
+pub mod timeouts;
 pub mod transaction;
+pub mod vectors;
 
 /// A list of possible errors when performing a cross-chain atomic swap with the **Farcaster**
 /// software stack. Each error can have multiple level down to the blockchain implementation.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// A consensus error during encoding/decoding operation or data type missmatch.
     #[error("Consensus error: {0}")]
@@ -35,6 +56,9 @@ pub enum Error {
     /// transactions.
     #[error("Fee Strategy error: {0}")]
     FeeStrategy(#[from] blockchain::FeeStrategyError),
+    /// An address validation error, e.g. when a counter-party reveals an address not allowed.
+    #[error("Address error: {0}")]
+    Address(#[from] blockchain::AddressError),
     /// An arbitrating transaction error.
     #[error("Transaction error: {0}")]
     Transaction(#[from] transaction::Error),
@@ -1,21 +1,61 @@
 //! Negotiation phase utilities
 
+use bech32::{FromBase32, ToBase32};
 use internet2::RemoteNodeAddr;
 use thiserror::Error;
 
+use std::cmp::Ordering;
+use std::fmt;
 use std::hash::Hasher;
 use std::io;
 
+#[cfg(feature = "parse-amounts")]
+use crate::blockchain::{AmountParseError, UnitAmount};
 use crate::blockchain::{Asset, Fee, FeeStrategy, Network, Timelock};
-use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::consensus::{
+    self, Annotate, Annotator, CanonicalBytes, Decodable, Deterministic, Encodable,
+};
+use crate::crypto::hash;
+use crate::crypto::pedersen::PedersenCommitment;
 use crate::role::{SwapRole, TradeRole};
 use crate::swap::Swap;
+use crate::timeouts::{StallPhase, StallTimeouts};
 
-/// First six magic bytes of a public offer
-pub const OFFER_MAGIC_BYTES: &[u8; 6] = b"FCSWAP";
+pub mod book;
+pub mod gossip;
+pub mod signing;
+
+/// Feature bit signaling the support of compressed protocol messages, see
+/// [`consensus::Compression`].
+pub const FEATURE_COMPRESSION: u16 = 0x0100;
+
+/// Feature bit signaling the hash time-locked fallback mode in place of adaptor signatures, see
+/// [`CommitHashLock`](crate::protocol_message::CommitHashLock). The mode is trusted: the hashes
+/// are not bound to the accordant spend keys, a malicious counter-party can claim the arbitrating
+/// assets without revealing a usable secret, see the [`htlc`](crate::chain::bitcoin::htlc)
+/// module.
+#[cfg(feature = "htlc")]
+pub const FEATURE_HTLC: u16 = 0x0200;
+
+/// Feature bit signaling the support of the signed checkpoints of the protocol position, see
+/// [`StateCheckpoint`](crate::protocol_message::StateCheckpoint).
+pub const FEATURE_CHECKPOINTS: u16 = 0x0400;
+
+/// Feature bit signaling the anchor output attached to the buy transaction to bump its fee, see
+/// [`Anchor`](crate::chain::bitcoin::transaction::Anchor).
+#[cfg(feature = "bitcoin")]
+pub const FEATURE_ANCHOR: u16 = 0x0800;
+
+/// The human readable part of the bech32 encoding of public offers, see
+/// [`PublicOffer::to_bech32`].
+pub const PUBLIC_OFFER_HRP: &str = "fcswap";
+
+/// The latest public offer version, version 2 adds the stall timeouts to the offer. Peers
+/// running an older version cannot interpret the fields added after their version.
+pub const LATEST_VERSION: u16 = 2;
 
 /// A public offer version containing the version and the activated features if
-/// any.
+/// any. The version is stored in the lower byte and the features in the upper byte.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Version(u16);
 
@@ -34,6 +74,32 @@ impl Version {
     pub fn to_u16(&self) -> u16 {
         self.0
     }
+
+    /// Version without the features
+    pub fn version(&self) -> u16 {
+        self.0 & 0x00ff
+    }
+
+    /// Return the same features with another version
+    pub fn with_version(self, version: u16) -> Self {
+        Version((self.0 & 0xff00) | (version & 0x00ff))
+    }
+
+    /// Activate a feature, e.g. [`FEATURE_COMPRESSION`]
+    pub fn with_feature(self, feature: u16) -> Self {
+        Version(self.0 | (feature & 0xff00))
+    }
+
+    /// Returns true if the feature is activated
+    pub fn has_feature(&self, feature: u16) -> bool {
+        feature & 0xff00 != 0 && self.0 & feature == feature
+    }
+
+    /// Returns true if compressed messages can be used with a peer advertising the `other`
+    /// version, i.e. both versions activate [`FEATURE_COMPRESSION`].
+    pub fn supports_compression_with(&self, other: &Version) -> bool {
+        self.has_feature(FEATURE_COMPRESSION) && other.has_feature(FEATURE_COMPRESSION)
+    }
 }
 
 impl Encodable for Version {
@@ -50,13 +116,57 @@ impl Decodable for Version {
 
 /// Negotiation errors used when manipulating offers, public offers and its version.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// The public offer version is not supported.
     #[error("Unsupported version")]
     UnsupportedVersion,
+    /// The offer cannot be represented in the requested version without losing content.
+    #[error("Offer cannot be converted to version {0} without loss")]
+    LossyConversion(u16),
     /// The public offer signature does not pass the validation tests.
     #[error("Invalid signature")]
     InvalidSignature,
+    /// The signing key is not the node key of the public offer daemon.
+    #[error("Signing key does not match the offer node")]
+    NodeKeyMismatch,
+    /// The opening does not match the blinded offer commitments.
+    #[error("Invalid blinded offer opening")]
+    InvalidOpening,
+    /// The amounts cannot be committed to.
+    #[error("Blinding failed: {0}")]
+    Blinding(#[from] crate::crypto::Error),
+    /// The quoted offer does not match the taker intent.
+    #[error("Quote does not match the intent")]
+    InvalidQuote,
+    /// The re-quote does not refresh the expired quote.
+    #[error("Re-quote does not refresh the expired quote")]
+    InvalidReQuote,
+    /// Re-quotes are not allowed once the swap reached a funding phase.
+    #[error("Re-quotes are not allowed in phase {0}")]
+    ReQuoteNotAllowed(StallPhase),
+}
+
+/// Errors when building an offer with the [`Buy`] and [`Sell`] helpers, meant to be shown to the
+/// maker.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OfferBuilderError {
+    /// Fields required in an offer are not set, e.g. the network or the fee strategy.
+    #[error(
+        "Missing offer fields: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    MissingFields(Vec<OfferField>),
+    /// The minimum of the fee strategy range is above its maximum.
+    #[error("Fee strategy range is empty")]
+    EmptyFeeRange,
+    /// The cancel timelock is not lower than the punish timelock.
+    #[error("Cancel timelock must be lower than the punish timelock")]
+    InconsistentTimelocks,
+    /// The stall timeouts are out of bounds.
+    #[error("Invalid stall timeouts: {0}")]
+    InvalidStallTimeouts(#[from] crate::timeouts::Error),
 }
 
 /// An offer is created by a Maker before the start of his daemon, it references all the data
@@ -83,13 +193,26 @@ pub struct Offer<Ctx: Swap> {
     pub fee_strategy: FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>,
     /// The future maker swap role
     pub maker_role: SwapRole,
+    /// The maximum response times agreed for the setup phases, if any
+    pub stall_timeouts: Option<StallTimeouts>,
 }
 
 impl<Ctx: Swap> Eq for Offer<Ctx> {}
 
 impl<Ctx: Swap> PartialEq for Offer<Ctx> {
     fn eq(&self, other: &Self) -> bool {
-        consensus::serialize_hex(self) == consensus::serialize_hex(other)
+        self.canonical_bytes() == other.canonical_bytes()
+    }
+}
+
+impl<Ctx: Swap> Deterministic for Offer<Ctx> {
+    /// The canonical bytes of an offer are encoded with the layout of [`Offer::min_version`],
+    /// the bytes of an offer without stall timeouts are the ones of version 1.
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = Vec::new();
+        self.consensus_encode_versioned(self.min_version(), &mut encoder)
+            .expect("Encoding into a vector does not fail");
+        encoder
     }
 }
 
@@ -98,20 +221,189 @@ impl<Ctx: Swap> std::hash::Hash for Offer<Ctx> {
     where
         H: Hasher,
     {
-        hasher.write(&consensus::serialize(self)[..]);
+        hasher.write(&self.canonical_bytes()[..]);
+    }
+}
+
+/// The fields of a public offer compared by [`Offer::diff`] and [`PublicOffer::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OfferField {
+    /// The version of the public offer
+    Version,
+    /// The daemon service of the public offer
+    DaemonService,
+    /// The network of the offer
+    Network,
+    /// The arbitrating blockchain
+    ArbitratingBlockchain,
+    /// The accordant blockchain
+    AccordantBlockchain,
+    /// The amount of arbitrating assets
+    ArbitratingAmount,
+    /// The amount of accordant assets
+    AccordantAmount,
+    /// The cancel timelock
+    CancelTimelock,
+    /// The punish timelock
+    PunishTimelock,
+    /// The fee strategy
+    FeeStrategy,
+    /// The maker swap role
+    MakerRole,
+    /// The stall timeouts
+    StallTimeouts,
+}
+
+impl fmt::Display for OfferField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            OfferField::Version => "version",
+            OfferField::DaemonService => "daemon service",
+            OfferField::Network => "network",
+            OfferField::ArbitratingBlockchain => "arbitrating blockchain",
+            OfferField::AccordantBlockchain => "accordant blockchain",
+            OfferField::ArbitratingAmount => "arbitrating amount",
+            OfferField::AccordantAmount => "accordant amount",
+            OfferField::CancelTimelock => "cancel timelock",
+            OfferField::PunishTimelock => "punish timelock",
+            OfferField::FeeStrategy => "fee strategy",
+            OfferField::MakerRole => "maker role",
+            OfferField::StallTimeouts => "stall timeouts",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A field differing between two offers, with the values of the field in the reference offer and
+/// in the compared offer, formatted for display.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldDiff {
+    /// The differing field
+    pub field: OfferField,
+    /// The value in the reference offer
+    pub expected: String,
+    /// The value in the compared offer
+    pub found: String,
+}
+
+impl FieldDiff {
+    fn compare<T>(diffs: &mut Vec<FieldDiff>, field: OfferField, expected: &T, found: &T)
+    where
+        T: PartialEq + fmt::Debug,
+    {
+        if expected != found {
+            diffs.push(FieldDiff {
+                field,
+                expected: format!("{:?}", expected),
+                found: format!("{:?}", found),
+            });
+        }
+    }
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, found {}",
+            self.field, self.expected, self.found
+        )
     }
 }
 
 impl<Ctx: Swap> Offer<Ctx> {
-    /// Transform the offer in a public offer of [Version] 1
-    pub fn to_public_v1(self, daemon_service: RemoteNodeAddr) -> PublicOffer<Ctx> {
+    /// Return the fields of the other offer differing from this offer, in the order of the
+    /// offer fields. Returns an empty list if the offers are equal.
+    pub fn diff(&self, other: &Self) -> Vec<FieldDiff> {
+        let mut diffs = vec![];
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::Network,
+            &self.network,
+            &other.network,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::ArbitratingBlockchain,
+            &self.arbitrating_blockchain,
+            &other.arbitrating_blockchain,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::AccordantBlockchain,
+            &self.accordant_blockchain,
+            &other.accordant_blockchain,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::ArbitratingAmount,
+            &self.arbitrating_amount,
+            &other.arbitrating_amount,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::AccordantAmount,
+            &self.accordant_amount,
+            &other.accordant_amount,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::CancelTimelock,
+            &self.cancel_timelock,
+            &other.cancel_timelock,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::PunishTimelock,
+            &self.punish_timelock,
+            &other.punish_timelock,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::FeeStrategy,
+            &self.fee_strategy,
+            &other.fee_strategy,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::MakerRole,
+            &self.maker_role,
+            &other.maker_role,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::StallTimeouts,
+            &self.stall_timeouts,
+            &other.stall_timeouts,
+        );
+        diffs
+    }
+
+    /// Transform the offer in a public offer of the lowest [Version] able to carry its content,
+    /// see [`Offer::min_version`].
+    pub fn to_public(self, daemon_service: RemoteNodeAddr) -> PublicOffer<Ctx> {
         PublicOffer {
-            version: Version::new_v1(),
+            version: Version::new(self.min_version()),
             offer: self,
             daemon_service,
         }
     }
 
+    /// Transform the offer in a public offer of [Version] 1. Offers with stall timeouts cannot be
+    /// carried by version 1 and are transformed in a public offer of version 2, as with
+    /// [`Offer::to_public`].
+    pub fn to_public_v1(self, daemon_service: RemoteNodeAddr) -> PublicOffer<Ctx> {
+        self.to_public(daemon_service)
+    }
+
+    /// Return the lowest public offer version able to carry the content of the offer.
+    pub fn min_version(&self) -> u16 {
+        match self.stall_timeouts {
+            Some(_) => 2,
+            None => 1,
+        }
+    }
+
     /// Return the future swap role for the given negotiation role.
     pub fn swap_role(&self, nego_role: &TradeRole) -> SwapRole {
         match nego_role {
@@ -119,16 +411,71 @@ impl<Ctx: Swap> Offer<Ctx> {
             TradeRole::Taker => self.maker_role.other(),
         }
     }
+
+    /// Hide the amounts of the offer behind Pedersen commitments with the given blinding
+    /// factors. Returns the blinded offer, safe to publish, and the opening to reveal only to
+    /// a taker initiating contact. Blinding factors MUST be generated from a secure source of
+    /// randomness and never reused.
+    pub fn blind(
+        &self,
+        arbitrating_blinding: [u8; 32],
+        accordant_blinding: [u8; 32],
+    ) -> Result<(BlindedOffer<Ctx>, OfferOpening<Ctx>), Error> {
+        let blinded = BlindedOffer {
+            network: self.network,
+            arbitrating_blockchain: self.arbitrating_blockchain,
+            accordant_blockchain: self.accordant_blockchain,
+            arbitrating_amount: PedersenCommitment::commit(
+                &self.arbitrating_amount.as_canonical_bytes(),
+                &arbitrating_blinding,
+            )?,
+            accordant_amount: PedersenCommitment::commit(
+                &self.accordant_amount.as_canonical_bytes(),
+                &accordant_blinding,
+            )?,
+            cancel_timelock: self.cancel_timelock,
+            punish_timelock: self.punish_timelock,
+            fee_strategy: self.fee_strategy.clone(),
+            maker_role: self.maker_role,
+            stall_timeouts: self.stall_timeouts,
+        };
+        let opening = OfferOpening {
+            arbitrating_amount: self.arbitrating_amount,
+            accordant_amount: self.accordant_amount,
+            arbitrating_blinding,
+            accordant_blinding,
+        };
+        Ok((blinded, opening))
+    }
 }
 
-impl<Ctx> Encodable for Offer<Ctx>
+impl<Ctx> Offer<Ctx>
 where
     Ctx: Swap,
 {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+    /// Encode the offer with the layout of the given public offer version, the fields added
+    /// after version 1 are encoded only by the versions defining them. Fails if the version is
+    /// unknown or if the offer sets fields unknown to the version, see [`Offer::min_version`].
+    pub fn consensus_encode_versioned<W: io::Write>(
+        &self,
+        version: u16,
+        s: &mut W,
+    ) -> Result<usize, io::Error> {
+        if version == 0 || version > LATEST_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unsupported offer version",
+            ));
+        }
+        if version < self.min_version() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The offer sets fields unknown to the version",
+            ));
+        }
         let mut len = self.network.consensus_encode(s)?;
-        len += self.arbitrating_blockchain.to_u32().consensus_encode(s)?;
-        len += self.accordant_blockchain.to_u32().consensus_encode(s)?;
+        len += self.arbitrating_blockchain.asset_id().consensus_encode(s)?;
+        len += self.accordant_blockchain.asset_id().consensus_encode(s)?;
         len += self
             .arbitrating_amount
             .as_canonical_bytes()
@@ -146,20 +493,28 @@ where
             .as_canonical_bytes()
             .consensus_encode(s)?;
         len += self.fee_strategy.consensus_encode(s)?;
-        Ok(len + self.maker_role.consensus_encode(s)?)
+        len += self.maker_role.consensus_encode(s)?;
+        if version >= 2 {
+            len += self.stall_timeouts.consensus_encode(s)?;
+        }
+        Ok(len)
     }
-}
 
-impl<Ctx> Decodable for Offer<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+    /// Decode an offer encoded with the layout of the given public offer version, see
+    /// [`Offer::consensus_encode_versioned`]. Fails with
+    /// [`UnsupportedVersion`](consensus::Error::UnsupportedVersion) if the version is unknown.
+    pub fn consensus_decode_versioned<D: io::Read>(
+        version: u16,
+        d: &mut D,
+    ) -> Result<Self, consensus::Error> {
+        if version == 0 || version > LATEST_VERSION {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
         Ok(Offer {
             network: Decodable::consensus_decode(d)?,
-            arbitrating_blockchain: Ctx::Ar::from_u32(Decodable::consensus_decode(d)?)
+            arbitrating_blockchain: Ctx::Ar::from_asset_id(&Decodable::consensus_decode(d)?)
                 .ok_or(consensus::Error::UnknownType)?,
-            accordant_blockchain: Ctx::Ac::from_u32(Decodable::consensus_decode(d)?)
+            accordant_blockchain: Ctx::Ac::from_asset_id(&Decodable::consensus_decode(d)?)
                 .ok_or(consensus::Error::UnknownType)?,
             arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit::from_canonical_bytes(
                 unwrap_vec_ref!(d).as_ref(),
@@ -175,12 +530,71 @@ where
             )?,
             fee_strategy: Decodable::consensus_decode(d)?,
             maker_role: Decodable::consensus_decode(d)?,
+            stall_timeouts: match version {
+                1 => None,
+                _ => Decodable::consensus_decode(d)?,
+            },
         })
     }
+
+    /// Annotate an offer encoded with the layout of the given public offer version.
+    pub fn annotate_versioned(version: u16, a: &mut Annotator) -> Result<(), consensus::Error> {
+        if version == 0 || version > LATEST_VERSION {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
+        a.decode::<Network>("network")?;
+        a.decode_with("arbitrating_blockchain", |d| {
+            Ctx::Ar::from_asset_id(&Decodable::consensus_decode(d)?)
+                .ok_or(consensus::Error::UnknownType)
+        })?;
+        a.decode_with("accordant_blockchain", |d| {
+            Ctx::Ac::from_asset_id(&Decodable::consensus_decode(d)?)
+                .ok_or(consensus::Error::UnknownType)
+        })?;
+        a.canonical::<<Ctx::Ar as Asset>::AssetUnit>("arbitrating_amount")?;
+        a.canonical::<<Ctx::Ac as Asset>::AssetUnit>("accordant_amount")?;
+        a.canonical::<<Ctx::Ar as Timelock>::Timelock>("cancel_timelock")?;
+        a.canonical::<<Ctx::Ar as Timelock>::Timelock>("punish_timelock")?;
+        a.decode::<FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>>("fee_strategy")?;
+        a.decode::<SwapRole>("maker_role")?;
+        if version >= 2 {
+            a.decode::<Option<StallTimeouts>>("stall_timeouts")?;
+        }
+        Ok(())
+    }
+}
+
+// An offer is encoded alone with the layout of its minimal version, offers setting fields added
+// by later versions must be decoded within a public offer of the matching version.
+impl<Ctx> Encodable for Offer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.consensus_encode_versioned(self.min_version(), s)
+    }
+}
+
+impl<Ctx> Decodable for Offer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Self::consensus_decode_versioned(1, d)
+    }
 }
 
 impl_strict_encoding!(Offer<Ctx>, Ctx: Swap);
 
+impl<Ctx> Annotate for Offer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn annotate(a: &mut Annotator) -> Result<(), consensus::Error> {
+        Self::annotate_versioned(1, a)
+    }
+}
+
 /// Helper to create an offer from an arbitrating asset buyer perspective.
 ///
 /// **This helper works only for buying Arbitrating assets with some Accordant
@@ -234,24 +648,38 @@ where
         self
     }
 
-    /// Transform the internal state into an offer if all parameters have been
-    /// set properly, otherwise return `None`.
+    /// Sets the maximum response times of the setup phases for the proposed offer
+    pub fn with_stall_timeouts(mut self, timeouts: StallTimeouts) -> Self {
+        self.0.stall_timeouts = Some(timeouts);
+        self
+    }
+
+    /// Transform the internal state into an offer if all parameters have been set properly,
+    /// otherwise return the missing or inconsistent parameters, see [`OfferBuilderError`].
     ///
     /// This function automatically sets the maker swap role as **Alice** to
     /// comply with the buy contract.
-    pub fn to_offer(mut self) -> Option<Offer<Ctx>> {
+    pub fn to_offer(mut self) -> Result<Offer<Ctx>, OfferBuilderError> {
         self.0.maker_role = Some(SwapRole::Alice);
-        Some(Offer {
-            network: self.0.network?,
-            arbitrating_blockchain: self.0.arbitrating_blockchain?,
-            accordant_blockchain: self.0.accordant_blockchain?,
-            arbitrating_amount: self.0.arbitrating_amount?,
-            accordant_amount: self.0.accordant_amount?,
-            cancel_timelock: self.0.cancel_timelock?,
-            punish_timelock: self.0.punish_timelock?,
-            fee_strategy: self.0.fee_strategy?,
-            maker_role: self.0.maker_role?,
-        })
+        self.0.build()
+    }
+}
+
+#[cfg(feature = "parse-amounts")]
+impl<Ctx> Buy<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ar as Asset>::AssetUnit: UnitAmount,
+    <Ctx::Ac as Asset>::AssetUnit: UnitAmount,
+{
+    /// Same as [`Buy::some`] with an amount parsed with its unit, e.g. `0.5 BTC`.
+    pub fn some_parsed(asset: Ctx::Ar, amount: &str) -> Result<Self, AmountParseError> {
+        Ok(Self::some(asset, UnitAmount::parse_with_unit(amount)?))
+    }
+
+    /// Same as [`Buy::with`] with an amount parsed with its unit, e.g. `2.5 XMR`.
+    pub fn with_parsed(self, asset: Ctx::Ac, amount: &str) -> Result<Self, AmountParseError> {
+        Ok(self.with(asset, UnitAmount::parse_with_unit(amount)?))
     }
 }
 
@@ -308,24 +736,38 @@ where
         self
     }
 
-    /// Transform the internal state into an offer if all parameters have been
-    /// set properly, otherwise return `None`.
+    /// Sets the maximum response times of the setup phases for the proposed offer
+    pub fn with_stall_timeouts(mut self, timeouts: StallTimeouts) -> Self {
+        self.0.stall_timeouts = Some(timeouts);
+        self
+    }
+
+    /// Transform the internal state into an offer if all parameters have been set properly,
+    /// otherwise return the missing or inconsistent parameters, see [`OfferBuilderError`].
     ///
     /// This function automatically sets the maker swap role as **Bob** to
     /// comply with the buy contract.
-    pub fn to_offer(mut self) -> Option<Offer<Ctx>> {
+    pub fn to_offer(mut self) -> Result<Offer<Ctx>, OfferBuilderError> {
         self.0.maker_role = Some(SwapRole::Bob);
-        Some(Offer {
-            network: self.0.network?,
-            arbitrating_blockchain: self.0.arbitrating_blockchain?,
-            accordant_blockchain: self.0.accordant_blockchain?,
-            arbitrating_amount: self.0.arbitrating_amount?,
-            accordant_amount: self.0.accordant_amount?,
-            cancel_timelock: self.0.cancel_timelock?,
-            punish_timelock: self.0.punish_timelock?,
-            fee_strategy: self.0.fee_strategy?,
-            maker_role: self.0.maker_role?,
-        })
+        self.0.build()
+    }
+}
+
+#[cfg(feature = "parse-amounts")]
+impl<Ctx> Sell<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ar as Asset>::AssetUnit: UnitAmount,
+    <Ctx::Ac as Asset>::AssetUnit: UnitAmount,
+{
+    /// Same as [`Sell::some`] with an amount parsed with its unit, e.g. `100000 sat`.
+    pub fn some_parsed(asset: Ctx::Ar, amount: &str) -> Result<Self, AmountParseError> {
+        Ok(Self::some(asset, UnitAmount::parse_with_unit(amount)?))
+    }
+
+    /// Same as [`Sell::for_some`] with an amount parsed with its unit, e.g. `2500000000000 pico`.
+    pub fn for_some_parsed(self, asset: Ctx::Ac, amount: &str) -> Result<Self, AmountParseError> {
+        Ok(self.for_some(asset, UnitAmount::parse_with_unit(amount)?))
     }
 }
 
@@ -340,6 +782,7 @@ struct BuilderState<Ctx: Swap> {
     punish_timelock: Option<<Ctx::Ar as Timelock>::Timelock>,
     fee_strategy: Option<FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>>,
     maker_role: Option<SwapRole>,
+    stall_timeouts: Option<StallTimeouts>,
 }
 
 impl<Ctx> Default for BuilderState<Ctx>
@@ -357,6 +800,94 @@ where
             punish_timelock: None,
             fee_strategy: None,
             maker_role: None,
+            stall_timeouts: None,
+        }
+    }
+}
+
+impl<Ctx> BuilderState<Ctx>
+where
+    Ctx: Swap,
+{
+    // Return the fields required in an offer and not set
+    fn missing_fields(&self) -> Vec<OfferField> {
+        let fields = [
+            (OfferField::Network, self.network.is_some()),
+            (
+                OfferField::ArbitratingBlockchain,
+                self.arbitrating_blockchain.is_some(),
+            ),
+            (
+                OfferField::AccordantBlockchain,
+                self.accordant_blockchain.is_some(),
+            ),
+            (
+                OfferField::ArbitratingAmount,
+                self.arbitrating_amount.is_some(),
+            ),
+            (OfferField::AccordantAmount, self.accordant_amount.is_some()),
+            (OfferField::CancelTimelock, self.cancel_timelock.is_some()),
+            (OfferField::PunishTimelock, self.punish_timelock.is_some()),
+            (OfferField::FeeStrategy, self.fee_strategy.is_some()),
+            (OfferField::MakerRole, self.maker_role.is_some()),
+        ];
+        fields
+            .iter()
+            .filter(|(_, is_set)| !is_set)
+            .map(|(field, _)| *field)
+            .collect()
+    }
+
+    // Build the offer, all the missing fields are reported at once
+    fn build(self) -> Result<Offer<Ctx>, OfferBuilderError> {
+        let missing = self.missing_fields();
+        match (
+            self.network,
+            self.arbitrating_blockchain,
+            self.accordant_blockchain,
+            self.arbitrating_amount,
+            self.accordant_amount,
+            self.cancel_timelock,
+            self.punish_timelock,
+            self.fee_strategy,
+            self.maker_role,
+        ) {
+            (
+                Some(network),
+                Some(arbitrating_blockchain),
+                Some(accordant_blockchain),
+                Some(arbitrating_amount),
+                Some(accordant_amount),
+                Some(cancel_timelock),
+                Some(punish_timelock),
+                Some(fee_strategy),
+                Some(maker_role),
+            ) => {
+                if let FeeStrategy::Range(range) = fee_strategy.base() {
+                    if range.start > range.end {
+                        return Err(OfferBuilderError::EmptyFeeRange);
+                    }
+                }
+                if cancel_timelock.partial_cmp(&punish_timelock) != Some(Ordering::Less) {
+                    return Err(OfferBuilderError::InconsistentTimelocks);
+                }
+                if let Some(timeouts) = self.stall_timeouts {
+                    timeouts.validate()?;
+                }
+                Ok(Offer {
+                    network,
+                    arbitrating_blockchain,
+                    accordant_blockchain,
+                    arbitrating_amount,
+                    accordant_amount,
+                    cancel_timelock,
+                    punish_timelock,
+                    fee_strategy,
+                    maker_role,
+                    stall_timeouts: self.stall_timeouts,
+                })
+            }
+            _ => Err(OfferBuilderError::MissingFields(missing)),
         }
     }
 }
@@ -375,12 +906,14 @@ pub struct PublicOffer<Ctx: Swap> {
     pub daemon_service: RemoteNodeAddr,
 }
 
+impl<Ctx: Swap> Deterministic for PublicOffer<Ctx> {}
+
 impl<Ctx: Swap> std::hash::Hash for PublicOffer<Ctx> {
     fn hash<H>(&self, hasher: &mut H)
     where
         H: Hasher,
     {
-        hasher.write(&consensus::serialize(self)[..]);
+        hasher.write(&self.canonical_bytes()[..]);
     }
 }
 
@@ -389,6 +922,89 @@ impl<Ctx: Swap> PublicOffer<Ctx> {
     pub fn swap_role(&self, nego_role: &TradeRole) -> SwapRole {
         self.offer.swap_role(nego_role)
     }
+
+    /// Return the fields of the other public offer differing from this public offer, e.g. to
+    /// explain how an offer presented by a taker differs from the advertised one. The version and
+    /// the daemon service are compared first, then the offer fields, see [`Offer::diff`].
+    pub fn diff(&self, other: &Self) -> Vec<FieldDiff> {
+        let mut diffs = vec![];
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::Version,
+            &self.version,
+            &other.version,
+        );
+        FieldDiff::compare(
+            &mut diffs,
+            OfferField::DaemonService,
+            &self.daemon_service,
+            &other.daemon_service,
+        );
+        diffs.extend(self.offer.diff(&other.offer));
+        diffs
+    }
+
+    /// Encode the public offer in bech32 with the [`PUBLIC_OFFER_HRP`] prefix. Unlike the hex
+    /// encoding, the checksum detects offers corrupted when copy-pasted or shared on forums.
+    pub fn to_bech32(&self) -> String {
+        bech32::encode(PUBLIC_OFFER_HRP, consensus::serialize(self).to_base32())
+            .expect("the human readable part is valid")
+    }
+
+    /// Decode a public offer encoded with [`PublicOffer::to_bech32`], in lowercase or uppercase.
+    /// Fails if the checksum is invalid or if the string is not prefixed with
+    /// [`PUBLIC_OFFER_HRP`].
+    pub fn from_bech32(s: &str) -> Result<Self, consensus::Error> {
+        let (hrp, data) = bech32::decode(s).map_err(consensus::Error::new)?;
+        if hrp != PUBLIC_OFFER_HRP {
+            return Err(consensus::Error::ParseFailed("not a bech32 public offer"));
+        }
+        let bytes = Vec::<u8>::from_base32(&data).map_err(consensus::Error::new)?;
+        consensus::deserialize(&bytes)
+    }
+
+    /// Return the compact identifier of the public offer advertised by gossip layers, see
+    /// [`gossip::ShortOfferId`].
+    pub fn short_id(&self) -> gossip::ShortOfferId {
+        gossip::ShortOfferId::new(&self.canonical_bytes())
+    }
+
+    /// Return the human-typeable short code of the public offer, see [`gossip::ShortCode`].
+    pub fn short_code(&self) -> gossip::ShortCode {
+        self.short_id().short_code()
+    }
+
+    /// Convert the public offer to a newer version, up to [`LATEST_VERSION`]. The activated
+    /// features are preserved. Upgrading is always lossless, the public offer is then encoded
+    /// with the layout of the new version.
+    pub fn upgrade(&self, to_version: u16) -> Result<Self, Error> {
+        if to_version < self.version.version() || to_version > LATEST_VERSION {
+            return Err(Error::UnsupportedVersion);
+        }
+        Ok(self.with_version(to_version))
+    }
+
+    /// Convert the public offer to an older version for peers not supporting the current one.
+    /// The activated features are preserved. Fails with [`Error::LossyConversion`] if the offer
+    /// sets fields unknown to the older version, e.g. stall timeouts in a version 1 offer. The
+    /// downgraded public offer is encoded with the layout of the older version.
+    pub fn downgrade(&self, to_version: u16) -> Result<Self, Error> {
+        if to_version == 0 || to_version > self.version.version() {
+            return Err(Error::UnsupportedVersion);
+        }
+        if to_version < self.offer.min_version() {
+            return Err(Error::LossyConversion(to_version));
+        }
+        Ok(self.with_version(to_version))
+    }
+
+    fn with_version(&self, version: u16) -> Self {
+        Self {
+            version: self.version.clone().with_version(version),
+            offer: self.offer.clone(),
+            daemon_service: self.daemon_service.clone(),
+        }
+    }
 }
 
 impl<Ctx> std::fmt::Display for PublicOffer<Ctx>
@@ -406,7 +1022,12 @@ where
 {
     type Err = consensus::Error;
 
+    /// Parse a hex encoded public offer, or a bech32 encoded public offer if prefixed with
+    /// [`PUBLIC_OFFER_HRP`].
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.to_lowercase().starts_with(PUBLIC_OFFER_HRP) {
+            return Self::from_bech32(s);
+        }
         let decoded = hex::decode(s).map_err(consensus::Error::new)?;
         let mut res = std::io::Cursor::new(decoded);
         Decodable::consensus_decode(&mut res)
@@ -418,9 +1039,11 @@ where
     Ctx: Swap,
 {
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = OFFER_MAGIC_BYTES.consensus_encode(s)?;
+        let mut len = self.offer.network.offer_magic_bytes().consensus_encode(s)?;
         len += self.version.consensus_encode(s)?;
-        len += self.offer.consensus_encode(s)?;
+        len += self
+            .offer
+            .consensus_encode_versioned(self.version.version(), s)?;
         len += strict_encoding::StrictEncode::strict_encode(&self.daemon_service, s).map_err(
             |_| {
                 io::Error::new(
@@ -439,16 +1062,834 @@ where
 {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         let magic_bytes: [u8; 6] = Decodable::consensus_decode(d)?;
-        if magic_bytes != *OFFER_MAGIC_BYTES {
+        let network = Network::from_offer_magic_bytes(&magic_bytes)
+            .ok_or(consensus::Error::IncorrectMagicBytes)?;
+        let version: Version = Decodable::consensus_decode(d)?;
+        let public_offer = PublicOffer {
+            offer: Offer::consensus_decode_versioned(version.version(), d)?,
+            version,
+            daemon_service: strict_encoding::StrictDecode::strict_decode(d)
+                .map_err(consensus::Error::new)?,
+        };
+        // The magic bytes must be the ones of the offer network
+        if public_offer.offer.network != network {
             return Err(consensus::Error::IncorrectMagicBytes);
         }
+        Ok(public_offer)
+    }
+}
+
+impl_strict_encoding!(PublicOffer<Ctx>, Ctx: Swap);
+
+impl<Ctx> Annotate for PublicOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn annotate(a: &mut Annotator) -> Result<(), consensus::Error> {
+        a.decode_with("magic_bytes", |d| {
+            let magic_bytes: [u8; 6] = Decodable::consensus_decode(d)?;
+            Network::from_offer_magic_bytes(&magic_bytes)
+                .ok_or(consensus::Error::IncorrectMagicBytes)
+        })?;
+        let version = a.decode::<Version>("version")?;
+        a.nested_with("offer", |a| {
+            Offer::<Ctx>::annotate_versioned(version.version(), a)
+        })?;
+        a.decode_with("daemon_service", |d| {
+            <RemoteNodeAddr as strict_encoding::StrictDecode>::strict_decode(d)
+                .map_err(consensus::Error::new)
+        })?;
+        Ok(())
+    }
+}
+
+/// A public offer found in a byte stream by [`scan`], with the position of its first byte in
+/// the stream.
+#[derive(Debug, Clone)]
+pub struct ScannedOffer<Ctx: Swap> {
+    /// Position of the offer magic bytes in the stream
+    pub position: usize,
+    /// The decoded public offer
+    pub public_offer: PublicOffer<Ctx>,
+}
+
+/// Iterator over the public offers found in a byte stream, see [`scan`].
+pub struct OfferScanner<R, Ctx> {
+    reader: Option<R>,
+    buffer: Vec<u8>,
+    position: usize,
+    _ctx: std::marker::PhantomData<Ctx>,
+}
+
+/// Scan an arbitrary byte stream, e.g. log files or gossip dumps, for public offers of any
+/// network. The stream is searched for offer magic bytes and every match is decoded, yielding
+/// the offer with its position or the decoding error if the data following the magic bytes is
+/// not a valid offer. The stream is read entirely on the first call to `next`.
+pub fn scan<Ctx, R>(reader: R) -> OfferScanner<R, Ctx>
+where
+    Ctx: Swap,
+    R: io::Read,
+{
+    OfferScanner {
+        reader: Some(reader),
+        buffer: vec![],
+        position: 0,
+        _ctx: std::marker::PhantomData,
+    }
+}
+
+impl<R, Ctx> OfferScanner<R, Ctx> {
+    // Return the position of the next offer magic bytes, if any
+    fn next_magic_bytes(&self) -> Option<usize> {
+        let magics: Vec<&[u8; 6]> = Network::ALL
+            .iter()
+            .map(|network| network.offer_magic_bytes())
+            .collect();
+        self.buffer
+            .get(self.position..)?
+            .windows(6)
+            .position(|window| magics.iter().any(|magic| window == &magic[..]))
+            .map(|offset| self.position + offset)
+    }
+}
+
+impl<R, Ctx> Iterator for OfferScanner<R, Ctx>
+where
+    Ctx: Swap,
+    R: io::Read,
+{
+    type Item = Result<ScannedOffer<Ctx>, consensus::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(mut reader) = self.reader.take() {
+            if let Err(e) = reader.read_to_end(&mut self.buffer) {
+                self.position = self.buffer.len();
+                return Some(Err(e.into()));
+            }
+        }
+        let position = self.next_magic_bytes()?;
+        match consensus::deserialize_partial::<PublicOffer<Ctx>>(&self.buffer[position..]) {
+            Ok((public_offer, consumed)) => {
+                self.position = position + consumed;
+                Some(Ok(ScannedOffer {
+                    position,
+                    public_offer,
+                }))
+            }
+            Err(e) => {
+                // Resume the scan after the magic bytes
+                self.position = position + 1;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A blinded offer is an offer where the exchanged amounts are hidden behind Pedersen commitments,
+/// allowing a maker to advertise a trade without revealing its exact size. The amounts are
+/// revealed with an [`OfferOpening`] to takers initiating contact.
+#[derive(Debug, Clone)]
+pub struct BlindedOffer<Ctx: Swap> {
+    /// Type of offer and network to use
+    pub network: Network,
+    /// The chosen arbitrating blockchain
+    pub arbitrating_blockchain: Ctx::Ar,
+    /// The chosen accordant blockchain
+    pub accordant_blockchain: Ctx::Ac,
+    /// Commitment to the amount of arbitrating assets to exchanged
+    pub arbitrating_amount: PedersenCommitment,
+    /// Commitment to the amount of accordant assets to exchanged
+    pub accordant_amount: PedersenCommitment,
+    /// The cancel timelock parameter of the arbitrating blockchain
+    pub cancel_timelock: <Ctx::Ar as Timelock>::Timelock,
+    /// The punish timelock parameter of the arbitrating blockchain
+    pub punish_timelock: <Ctx::Ar as Timelock>::Timelock,
+    /// The chosen fee strategy for the arbitrating transactions
+    pub fee_strategy: FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>,
+    /// The future maker swap role
+    pub maker_role: SwapRole,
+    /// The maximum response times agreed for the setup phases, if any
+    pub stall_timeouts: Option<StallTimeouts>,
+}
+
+impl<Ctx: Swap> BlindedOffer<Ctx> {
+    /// Return the lowest public offer version able to carry the content of the blinded offer.
+    pub fn min_version(&self) -> u16 {
+        match self.stall_timeouts {
+            Some(_) => 2,
+            None => 1,
+        }
+    }
+
+    /// Transform the blinded offer in a blinded public offer of the lowest [Version] able to carry
+    /// its content, see [`BlindedOffer::min_version`].
+    pub fn to_public(self, daemon_service: RemoteNodeAddr) -> BlindedPublicOffer<Ctx> {
+        BlindedPublicOffer {
+            version: Version::new(self.min_version()),
+            offer: self,
+            daemon_service,
+        }
+    }
+
+    /// Transform the blinded offer in a blinded public offer of [Version] 1, or of version 2 if
+    /// the blinded offer sets stall timeouts, as with [`BlindedOffer::to_public`].
+    pub fn to_public_v1(self, daemon_service: RemoteNodeAddr) -> BlindedPublicOffer<Ctx> {
+        self.to_public(daemon_service)
+    }
+
+    /// Verify the opening against the amount commitments and return the unblinded offer.
+    pub fn open(&self, opening: &OfferOpening<Ctx>) -> Result<Offer<Ctx>, Error> {
+        self.arbitrating_amount
+            .verify(
+                &opening.arbitrating_amount.as_canonical_bytes(),
+                &opening.arbitrating_blinding,
+            )
+            .map_err(|_| Error::InvalidOpening)?;
+        self.accordant_amount
+            .verify(
+                &opening.accordant_amount.as_canonical_bytes(),
+                &opening.accordant_blinding,
+            )
+            .map_err(|_| Error::InvalidOpening)?;
+        Ok(Offer {
+            network: self.network,
+            arbitrating_blockchain: self.arbitrating_blockchain,
+            accordant_blockchain: self.accordant_blockchain,
+            arbitrating_amount: opening.arbitrating_amount,
+            accordant_amount: opening.accordant_amount,
+            cancel_timelock: self.cancel_timelock,
+            punish_timelock: self.punish_timelock,
+            fee_strategy: self.fee_strategy.clone(),
+            maker_role: self.maker_role,
+            stall_timeouts: self.stall_timeouts,
+        })
+    }
+}
+
+impl<Ctx> BlindedOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Encode the blinded offer with the layout of the given public offer version, see
+    /// [`Offer::consensus_encode_versioned`].
+    pub fn consensus_encode_versioned<W: io::Write>(
+        &self,
+        version: u16,
+        s: &mut W,
+    ) -> Result<usize, io::Error> {
+        if version == 0 || version > LATEST_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unsupported offer version",
+            ));
+        }
+        if version < self.min_version() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The offer sets fields unknown to the version",
+            ));
+        }
+        let mut len = self.network.consensus_encode(s)?;
+        len += self.arbitrating_blockchain.asset_id().consensus_encode(s)?;
+        len += self.accordant_blockchain.asset_id().consensus_encode(s)?;
+        len += self.arbitrating_amount.consensus_encode(s)?;
+        len += self.accordant_amount.consensus_encode(s)?;
+        len += self
+            .cancel_timelock
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self
+            .punish_timelock
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self.fee_strategy.consensus_encode(s)?;
+        len += self.maker_role.consensus_encode(s)?;
+        if version >= 2 {
+            len += self.stall_timeouts.consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+
+    /// Decode a blinded offer encoded with the layout of the given public offer version.
+    pub fn consensus_decode_versioned<D: io::Read>(
+        version: u16,
+        d: &mut D,
+    ) -> Result<Self, consensus::Error> {
+        if version == 0 || version > LATEST_VERSION {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
+        Ok(BlindedOffer {
+            network: Decodable::consensus_decode(d)?,
+            arbitrating_blockchain: Ctx::Ar::from_asset_id(&Decodable::consensus_decode(d)?)
+                .ok_or(consensus::Error::UnknownType)?,
+            accordant_blockchain: Ctx::Ac::from_asset_id(&Decodable::consensus_decode(d)?)
+                .ok_or(consensus::Error::UnknownType)?,
+            arbitrating_amount: Decodable::consensus_decode(d)?,
+            accordant_amount: Decodable::consensus_decode(d)?,
+            cancel_timelock: <Ctx::Ar as Timelock>::Timelock::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            punish_timelock: <Ctx::Ar as Timelock>::Timelock::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            fee_strategy: Decodable::consensus_decode(d)?,
+            maker_role: Decodable::consensus_decode(d)?,
+            stall_timeouts: match version {
+                1 => None,
+                _ => Decodable::consensus_decode(d)?,
+            },
+        })
+    }
+}
+
+// A blinded offer is encoded alone with the layout of its minimal version, as an offer.
+impl<Ctx> Encodable for BlindedOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.consensus_encode_versioned(self.min_version(), s)
+    }
+}
+
+impl<Ctx> Decodable for BlindedOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Self::consensus_decode_versioned(1, d)
+    }
+}
+
+impl_strict_encoding!(BlindedOffer<Ctx>, Ctx: Swap);
+
+impl<Ctx: Swap> Eq for BlindedOffer<Ctx> {}
+
+impl<Ctx: Swap> PartialEq for BlindedOffer<Ctx> {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical_bytes() == other.canonical_bytes()
+    }
+}
+
+impl<Ctx: Swap> std::hash::Hash for BlindedOffer<Ctx> {
+    fn hash<H>(&self, hasher: &mut H)
+    where
+        H: Hasher,
+    {
+        hasher.write(&self.canonical_bytes()[..]);
+    }
+}
+
+impl<Ctx> Deterministic for BlindedOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    /// The canonical bytes are encoded with the layout of [`BlindedOffer::min_version`].
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = Vec::new();
+        self.consensus_encode_versioned(self.min_version(), &mut encoder)
+            .expect("Encoding into a vector does not fail");
+        encoder
+    }
+}
+
+/// The opening of a [`BlindedOffer`], containing the hidden amounts and their blinding factors.
+/// The opening is sent by the maker to a taker initiating contact in its reveal message, see
+/// [`RevealedOffer`](crate::protocol_message::RevealedOffer), and MUST be verified by the taker
+/// with [`BlindedOffer::open`].
+#[derive(Debug, Clone)]
+pub struct OfferOpening<Ctx: Swap> {
+    /// Amount of arbitrating assets to exchanged
+    pub arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit,
+    /// Amount of accordant assets to exchanged
+    pub accordant_amount: <Ctx::Ac as Asset>::AssetUnit,
+    /// Blinding factor of the arbitrating amount commitment
+    pub arbitrating_blinding: [u8; 32],
+    /// Blinding factor of the accordant amount commitment
+    pub accordant_blinding: [u8; 32],
+}
+
+impl<Ctx> Encodable for OfferOpening<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self
+            .arbitrating_amount
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self
+            .accordant_amount
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self.arbitrating_blinding.to_vec().consensus_encode(s)?;
+        Ok(len + self.accordant_blinding.to_vec().consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for OfferOpening<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let arbitrating_amount =
+            <Ctx::Ar as Asset>::AssetUnit::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
+        let accordant_amount =
+            <Ctx::Ac as Asset>::AssetUnit::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
+        let mut arbitrating_blinding = [0u8; 32];
+        let mut accordant_blinding = [0u8; 32];
+        for blinding in [&mut arbitrating_blinding, &mut accordant_blinding].iter_mut() {
+            let bytes = unwrap_vec_ref!(d);
+            if bytes.len() != 32 {
+                return Err(consensus::Error::ParseFailed(
+                    "Blinding factor must be 32 bytes",
+                ));
+            }
+            blinding.copy_from_slice(&bytes);
+        }
+        Ok(OfferOpening {
+            arbitrating_amount,
+            accordant_amount,
+            arbitrating_blinding,
+            accordant_blinding,
+        })
+    }
+}
+
+impl_strict_encoding!(OfferOpening<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(OfferOpening<Ctx>, Ctx: Swap);
+
+impl<Ctx> Deterministic for OfferOpening<Ctx> where Ctx: Swap {}
+
+/// A blinded public offer is the public version of a [`BlindedOffer`], shared across maker's
+/// prefered network in place of a [`PublicOffer`] when the traded amounts must stay hidden.
+#[derive(Clone, Debug)]
+pub struct BlindedPublicOffer<Ctx: Swap> {
+    /// The public offer version
+    pub version: Version,
+    /// The content of the blinded offer
+    pub offer: BlindedOffer<Ctx>,
+    /// Address of the listening daemon's peer
+    pub daemon_service: RemoteNodeAddr,
+}
+
+impl<Ctx: Swap> BlindedPublicOffer<Ctx> {
+    /// Verify the opening against the blinded offer and return the unblinded public offer.
+    pub fn open(&self, opening: &OfferOpening<Ctx>) -> Result<PublicOffer<Ctx>, Error> {
         Ok(PublicOffer {
-            version: Decodable::consensus_decode(d)?,
-            offer: Decodable::consensus_decode(d)?,
+            version: self.version.clone(),
+            offer: self.offer.open(opening)?,
+            daemon_service: self.daemon_service.clone(),
+        })
+    }
+}
+
+impl<Ctx> Encodable for BlindedPublicOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self
+            .offer
+            .network
+            .blinded_offer_magic_bytes()
+            .consensus_encode(s)?;
+        len += self.version.consensus_encode(s)?;
+        len += self
+            .offer
+            .consensus_encode_versioned(self.version.version(), s)?;
+        len += strict_encoding::StrictEncode::strict_encode(&self.daemon_service, s).map_err(
+            |_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Failed to encode RemoteNodeAddr",
+                )
+            },
+        )?;
+        Ok(len)
+    }
+}
+
+impl<Ctx> Decodable for BlindedPublicOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let magic_bytes: [u8; 6] = Decodable::consensus_decode(d)?;
+        let network = Network::from_blinded_offer_magic_bytes(&magic_bytes)
+            .ok_or(consensus::Error::IncorrectMagicBytes)?;
+        let version: Version = Decodable::consensus_decode(d)?;
+        let public_offer = BlindedPublicOffer {
+            offer: BlindedOffer::consensus_decode_versioned(version.version(), d)?,
+            version,
             daemon_service: strict_encoding::StrictDecode::strict_decode(d)
                 .map_err(consensus::Error::new)?,
+        };
+        // The magic bytes must be the ones of the offer network
+        if public_offer.offer.network != network {
+            return Err(consensus::Error::IncorrectMagicBytes);
+        }
+        Ok(public_offer)
+    }
+}
+
+impl_strict_encoding!(BlindedPublicOffer<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(BlindedPublicOffer<Ctx>, Ctx: Swap);
+
+impl<Ctx> Deterministic for BlindedPublicOffer<Ctx> where Ctx: Swap {}
+
+/// The amount of assets a taker wants to exchange, expressed in one of the two assets of the
+/// swap. The maker prices the other side when quoting.
+#[derive(Debug)]
+pub enum IntentAmount<Ctx: Swap> {
+    /// Amount of arbitrating assets to exchange
+    Arbitrating(<Ctx::Ar as Asset>::AssetUnit),
+    /// Amount of accordant assets to exchange
+    Accordant(<Ctx::Ac as Asset>::AssetUnit),
+}
+
+impl<Ctx: Swap> Copy for IntentAmount<Ctx> {}
+
+impl<Ctx: Swap> Clone for IntentAmount<Ctx> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Ctx: Swap> Eq for IntentAmount<Ctx> {}
+
+impl<Ctx: Swap> PartialEq for IntentAmount<Ctx> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (IntentAmount::Arbitrating(a), IntentAmount::Arbitrating(b)) => a == b,
+            (IntentAmount::Accordant(a), IntentAmount::Accordant(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<Ctx> Encodable for IntentAmount<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            IntentAmount::Arbitrating(amount) => {
+                let len = 0x01u8.consensus_encode(s)?;
+                Ok(len + amount.as_canonical_bytes().consensus_encode(s)?)
+            }
+            IntentAmount::Accordant(amount) => {
+                let len = 0x02u8.consensus_encode(s)?;
+                Ok(len + amount.as_canonical_bytes().consensus_encode(s)?)
+            }
+        }
+    }
+}
+
+impl<Ctx> Decodable for IntentAmount<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u8 => Ok(IntentAmount::Arbitrating(
+                <Ctx::Ar as Asset>::AssetUnit::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            )),
+            0x02u8 => Ok(IntentAmount::Accordant(
+                <Ctx::Ac as Asset>::AssetUnit::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            )),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl_strict_encoding!(IntentAmount<Ctx>, Ctx: Swap);
+
+/// An intent sent by a taker to market makers, describing the trade the taker wants to do.
+/// Makers implementing a [`MakerPolicy`] answer with a [`Quote`] generated on demand instead of
+/// pre-publishing static offers.
+#[derive(Debug, Clone)]
+pub struct TakerIntent<Ctx: Swap> {
+    /// Network to use
+    pub network: Network,
+    /// The chosen arbitrating blockchain
+    pub arbitrating_blockchain: Ctx::Ar,
+    /// The chosen accordant blockchain
+    pub accordant_blockchain: Ctx::Ac,
+    /// The amount to exchange
+    pub amount: IntentAmount<Ctx>,
+    /// The future taker swap role
+    pub taker_role: SwapRole,
+}
+
+impl<Ctx: Swap> TakerIntent<Ctx> {
+    /// Return the identifier of the intent, referenced by the quotes answering it.
+    pub fn id(&self) -> [u8; 32] {
+        hash::tagged_sha256("negotiation:intent", &self.canonical_bytes())
+    }
+
+    /// Return true if the offer satisfies the intent: same network and blockchains, the amount
+    /// requested by the taker, and the complementary swap role for the maker.
+    pub fn is_satisfied_by(&self, offer: &Offer<Ctx>) -> bool {
+        let amount = match self.amount {
+            IntentAmount::Arbitrating(amount) => offer.arbitrating_amount == amount,
+            IntentAmount::Accordant(amount) => offer.accordant_amount == amount,
+        };
+        amount
+            && offer.network == self.network
+            && offer.arbitrating_blockchain.asset_id() == self.arbitrating_blockchain.asset_id()
+            && offer.accordant_blockchain.asset_id() == self.accordant_blockchain.asset_id()
+            && offer.maker_role == self.taker_role.other()
+    }
+}
+
+impl<Ctx> Encodable for TakerIntent<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.network.consensus_encode(s)?;
+        len += self.arbitrating_blockchain.asset_id().consensus_encode(s)?;
+        len += self.accordant_blockchain.asset_id().consensus_encode(s)?;
+        len += self.amount.consensus_encode(s)?;
+        Ok(len + self.taker_role.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for TakerIntent<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(TakerIntent {
+            network: Decodable::consensus_decode(d)?,
+            arbitrating_blockchain: Ctx::Ar::from_asset_id(&Decodable::consensus_decode(d)?)
+                .ok_or(consensus::Error::UnknownType)?,
+            accordant_blockchain: Ctx::Ac::from_asset_id(&Decodable::consensus_decode(d)?)
+                .ok_or(consensus::Error::UnknownType)?,
+            amount: Decodable::consensus_decode(d)?,
+            taker_role: Decodable::consensus_decode(d)?,
         })
     }
 }
 
-impl_strict_encoding!(PublicOffer<Ctx>, Ctx: Swap);
+impl_strict_encoding!(TakerIntent<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(TakerIntent<Ctx>, Ctx: Swap);
+
+impl<Ctx> Deterministic for TakerIntent<Ctx> where Ctx: Swap {}
+
+/// The answer of a maker to a [`TakerIntent`], a public offer generated for the intent.
+#[derive(Debug, Clone)]
+pub struct Quote<Ctx: Swap> {
+    /// The identifier of the intent answered, see [`TakerIntent::id`]
+    pub intent_id: [u8; 32],
+    /// The offer generated for the intent
+    pub public_offer: PublicOffer<Ctx>,
+}
+
+impl<Ctx: Swap> Quote<Ctx> {
+    /// Return the identifier of the quote, referenced when the quote expires.
+    pub fn id(&self) -> [u8; 32] {
+        hash::tagged_sha256("negotiation:quote", &self.canonical_bytes())
+    }
+
+    /// Verify that the quote answers the intent and that the quoted offer satisfies it.
+    pub fn verify(&self, intent: &TakerIntent<Ctx>) -> Result<(), Error> {
+        match self.intent_id == intent.id() && intent.is_satisfied_by(&self.public_offer.offer) {
+            true => Ok(()),
+            false => Err(Error::InvalidQuote),
+        }
+    }
+}
+
+impl<Ctx> Encodable for Quote<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.intent_id.consensus_encode(s)?;
+        Ok(len + self.public_offer.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for Quote<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Quote {
+            intent_id: Decodable::consensus_decode(d)?,
+            public_offer: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(Quote<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(Quote<Ctx>, Ctx: Swap);
+
+impl<Ctx> Deterministic for Quote<Ctx> where Ctx: Swap {}
+
+/// Sent by a maker when the taker did not accept a quote in time, the quoted pricing is not
+/// valid anymore. The maker can follow with a [`ReQuote`] instead of aborting the handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuoteExpired {
+    /// The identifier of the intent answered by the expired quote
+    pub intent_id: [u8; 32],
+    /// The identifier of the expired quote, see [`Quote::id`]
+    pub quote_id: [u8; 32],
+}
+
+impl QuoteExpired {
+    /// Create the message signaling the expiry of the quote.
+    pub fn new<Ctx: Swap>(quote: &Quote<Ctx>) -> Self {
+        Self {
+            intent_id: quote.intent_id,
+            quote_id: quote.id(),
+        }
+    }
+}
+
+impl Encodable for QuoteExpired {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.intent_id.consensus_encode(s)?;
+        Ok(len + self.quote_id.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for QuoteExpired {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(QuoteExpired {
+            intent_id: Decodable::consensus_decode(d)?,
+            quote_id: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(QuoteExpired);
+
+/// A fresh quote replacing an expired one. Only the amounts of the offer can change, and only
+/// before the swap reaches a funding phase, see [`StallPhase::is_pre_funding`].
+#[derive(Debug, Clone)]
+pub struct ReQuote<Ctx: Swap> {
+    /// The identifier of the expired quote, see [`Quote::id`]
+    pub expired_quote_id: [u8; 32],
+    /// The quote with the refreshed pricing
+    pub quote: Quote<Ctx>,
+}
+
+// The phase is `None` before the commitments are exchanged
+fn check_requote_phase(phase: Option<StallPhase>) -> Result<(), Error> {
+    match phase {
+        Some(phase) if !phase.is_pre_funding() => Err(Error::ReQuoteNotAllowed(phase)),
+        _ => Ok(()),
+    }
+}
+
+impl<Ctx: Swap> ReQuote<Ctx> {
+    /// Refresh the pricing of the expired quote with the new amounts, `phase` is the current
+    /// phase of the swap, if any. Fails if re-quotes are not allowed in the phase.
+    pub fn new(
+        expired: &Quote<Ctx>,
+        arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit,
+        accordant_amount: <Ctx::Ac as Asset>::AssetUnit,
+        phase: Option<StallPhase>,
+    ) -> Result<Self, Error> {
+        check_requote_phase(phase)?;
+        let mut quote = expired.clone();
+        quote.public_offer.offer.arbitrating_amount = arbitrating_amount;
+        quote.public_offer.offer.accordant_amount = accordant_amount;
+        Ok(Self {
+            expired_quote_id: expired.id(),
+            quote,
+        })
+    }
+
+    /// Verify that the re-quote is allowed in the current phase, refreshes the expired quote
+    /// without changing anything but the amounts, and still satisfies the intent.
+    pub fn verify(
+        &self,
+        intent: &TakerIntent<Ctx>,
+        expired: &Quote<Ctx>,
+        phase: Option<StallPhase>,
+    ) -> Result<(), Error> {
+        check_requote_phase(phase)?;
+        self.quote.verify(intent)?;
+        let mut unpriced = self.quote.public_offer.clone();
+        unpriced.offer.arbitrating_amount = expired.public_offer.offer.arbitrating_amount;
+        unpriced.offer.accordant_amount = expired.public_offer.offer.accordant_amount;
+        match self.expired_quote_id == expired.id()
+            && self.quote.intent_id == expired.intent_id
+            && unpriced.canonical_bytes() == expired.public_offer.canonical_bytes()
+        {
+            true => Ok(()),
+            false => Err(Error::InvalidReQuote),
+        }
+    }
+}
+
+impl<Ctx> Encodable for ReQuote<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.expired_quote_id.consensus_encode(s)?;
+        Ok(len + self.quote.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for ReQuote<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(ReQuote {
+            expired_quote_id: Decodable::consensus_decode(d)?,
+            quote: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(ReQuote<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(ReQuote<Ctx>, Ctx: Swap);
+
+/// A maker pricing policy, called by market maker bots for every intent received to generate an
+/// offer on demand. Returns `None` if the maker does not want to trade.
+pub trait MakerPolicy<Ctx: Swap> {
+    /// Generate an offer for the intent, if any.
+    fn quote(&self, intent: &TakerIntent<Ctx>) -> Option<Offer<Ctx>>;
+}
+
+impl<Ctx, F> MakerPolicy<Ctx> for F
+where
+    Ctx: Swap,
+    F: Fn(&TakerIntent<Ctx>) -> Option<Offer<Ctx>>,
+{
+    fn quote(&self, intent: &TakerIntent<Ctx>) -> Option<Offer<Ctx>> {
+        self(intent)
+    }
+}
+
+/// Answer an intent with the maker policy, returns the quote to send to the taker if the policy
+/// generated an offer. Fails with [`Error::InvalidQuote`] if the generated offer does not satisfy
+/// the intent.
+pub fn quote<Ctx, P>(
+    policy: &P,
+    intent: &TakerIntent<Ctx>,
+    daemon_service: RemoteNodeAddr,
+) -> Result<Option<Quote<Ctx>>, Error>
+where
+    Ctx: Swap,
+    P: MakerPolicy<Ctx>,
+{
+    let offer = match policy.quote(intent) {
+        Some(offer) => offer,
+        None => return Ok(None),
+    };
+    if !intent.is_satisfied_by(&offer) {
+        return Err(Error::InvalidQuote);
+    }
+    Ok(Some(Quote {
+        intent_id: intent.id(),
+        public_offer: offer.to_public(daemon_service),
+    }))
+}
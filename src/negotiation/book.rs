@@ -0,0 +1,373 @@
+//! Memory-bounded books of public offers for long-running aggregators.
+//!
+//! An [`OfferBook`] holds at most a fixed number of public offers, indexed by their
+//! [`ShortOfferId`], each with an expiry time checked against a [`Clock`] given by the caller.
+//! Expired offers are never returned and are dropped before any other offer when room is needed.
+//! When the book is full the [`EvictionPolicy`] selects the offer to evict: the least recently
+//! used one, or the one expiring first.
+//!
+//! Offers edited by their maker are replaced with [`OfferBook::update`], which returns how the new
+//! offer differs from the one in the book, see [`PublicOffer::diff`]. Offers dictated by users
+//! are found by their [`ShortCode`] with [`OfferBook::resolve`].
+//!
+//! The book tracks the entries changed since the last [`OfferBook::flush`]: only those entries
+//! are serialized as [`BookRecord`]s and written to the [`OfferStore`] given by the caller, so
+//! the book can be restored after a restart with [`OfferBook::restore`] without rewriting the
+//! whole book on every change.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::clock::Clock;
+use crate::consensus::{self, deserialize, serialize, Decodable, Encodable, Timestamp};
+use crate::negotiation::gossip::{ShortCode, ShortOfferId};
+use crate::negotiation::{FieldDiff, PublicOffer};
+use crate::swap::Swap;
+
+/// The policy selecting the offer evicted when a full book receives a new offer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EvictionPolicy {
+    /// Evict the offer inserted, updated, or accessed the longest time ago.
+    #[default]
+    LeastRecentlyUsed,
+    /// Evict the offer expiring first.
+    EarliestExpiry,
+}
+
+/// Errors when resolving a short code to an offer of the book.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResolveError {
+    /// No offer of the book matches the short code.
+    #[error("No offer matches the short code")]
+    NotFound,
+    /// Multiple offers of the book match the short code, the full offer must be shared.
+    #[error("{0} offers match the short code")]
+    Ambiguous(usize),
+}
+
+/// The persisted form of an offer of the book with its expiry time. Expiry times are serialized
+/// with a precision of one second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookRecord<Ctx: Swap> {
+    /// The public offer
+    pub public_offer: PublicOffer<Ctx>,
+    /// The expiry time of the offer
+    pub expiry: Duration,
+}
+
+impl<Ctx> Encodable for BookRecord<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.public_offer.consensus_encode(s)?;
+        let expiry = Timestamp::try_from(self.expiry).map_err(io::Error::other)?;
+        Ok(len + expiry.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for BookRecord<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            public_offer: Decodable::consensus_decode(d)?,
+            expiry: Timestamp::consensus_decode(d)?.into(),
+        })
+    }
+}
+
+impl_strict_encoding!(BookRecord<Ctx>, Ctx: Swap);
+
+/// A persistent storage of the records of an offer book, implemented by the aggregator.
+pub trait OfferStore {
+    /// The error returned by the storage.
+    type Error;
+
+    /// Write the serialized [`BookRecord`] of the offer, replacing any previous record.
+    fn save(&mut self, id: &ShortOfferId, record: &[u8]) -> Result<(), Self::Error>;
+
+    /// Delete the record of the offer, if any.
+    fn delete(&mut self, id: &ShortOfferId) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug, Clone)]
+struct Entry<Ctx: Swap> {
+    public_offer: PublicOffer<Ctx>,
+    expiry: Duration,
+    last_used: u64,
+    dirty: bool,
+}
+
+/// A book of public offers bounded in size, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct OfferBook<Ctx: Swap> {
+    capacity: usize,
+    policy: EvictionPolicy,
+    entries: HashMap<ShortOfferId, Entry<Ctx>>,
+    removed: HashSet<ShortOfferId>,
+    tick: u64,
+}
+
+impl<Ctx> OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create an empty book holding at most `capacity` offers, at least one, evicting the least
+    /// recently used offer when full.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            policy: EvictionPolicy::default(),
+            entries: HashMap::new(),
+            removed: HashSet::new(),
+            tick: 0,
+        }
+    }
+
+    /// Set the eviction policy of the book.
+    pub fn with_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Return the maximum number of offers in the book.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Return the eviction policy of the book.
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Return the number of offers in the book, expired offers not yet dropped included.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return true if the book contains no offer.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Add an offer expiring at the given time, or refresh its expiry time if the offer is
+    /// already in the book. Returns the offers dropped to make room for it: the offers expired
+    /// at `now` if any, otherwise the offer selected by the eviction policy.
+    pub fn insert(
+        &mut self,
+        public_offer: PublicOffer<Ctx>,
+        expiry: Duration,
+        now: impl Clock,
+    ) -> Vec<PublicOffer<Ctx>> {
+        let id = public_offer.short_id();
+        let mut dropped = vec![];
+        if !self.entries.contains_key(&id) && self.entries.len() >= self.capacity {
+            dropped = self.drain_expired(now.now());
+            if dropped.is_empty() {
+                dropped.extend(self.evict());
+            }
+        }
+        let last_used = self.touch();
+        self.removed.remove(&id);
+        self.entries.insert(
+            id,
+            Entry {
+                public_offer,
+                expiry: Duration::from_secs(expiry.as_secs()),
+                last_used,
+                dirty: true,
+            },
+        );
+        dropped
+    }
+
+    /// Replace the offer with the given identifier by its edited version expiring at the given
+    /// time. Returns how the edited offer differs from the replaced one, or `None` if the book
+    /// does not contain the offer, in which case the book is left untouched.
+    pub fn update(
+        &mut self,
+        id: &ShortOfferId,
+        public_offer: PublicOffer<Ctx>,
+        expiry: Duration,
+    ) -> Option<Vec<FieldDiff>> {
+        let previous = self.entries.remove(id)?;
+        let diffs = previous.public_offer.diff(&public_offer);
+        let new_id = public_offer.short_id();
+        if new_id != *id {
+            self.removed.insert(*id);
+        }
+        let last_used = self.touch();
+        self.removed.remove(&new_id);
+        self.entries.insert(
+            new_id,
+            Entry {
+                public_offer,
+                expiry: Duration::from_secs(expiry.as_secs()),
+                last_used,
+                dirty: true,
+            },
+        );
+        Some(diffs)
+    }
+
+    /// Return the offer if in the book and not expired at `now`, marking it as recently used.
+    pub fn get(&mut self, id: &ShortOfferId, now: impl Clock) -> Option<&PublicOffer<Ctx>> {
+        let now = now.now();
+        let last_used = self.tick + 1;
+        match self.entries.get_mut(id) {
+            Some(entry) if entry.expiry > now => {
+                entry.last_used = last_used;
+                self.tick = last_used;
+                Some(&entry.public_offer)
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the offer if in the book and not expired at `now`, without marking it as used.
+    pub fn peek(&self, id: &ShortOfferId, now: impl Clock) -> Option<&PublicOffer<Ctx>> {
+        let now = now.now();
+        self.entries
+            .get(id)
+            .filter(|entry| entry.expiry > now)
+            .map(|entry| &entry.public_offer)
+    }
+
+    /// Return the offer with the given short code if in the book and not expired at `now`,
+    /// without marking it as used. Fails if no offer or more than one offer matches the code.
+    pub fn resolve(
+        &self,
+        code: &ShortCode,
+        now: impl Clock,
+    ) -> Result<&PublicOffer<Ctx>, ResolveError> {
+        let now = now.now();
+        let mut matching = self
+            .entries
+            .iter()
+            .filter(|(id, entry)| code.matches(id) && entry.expiry > now)
+            .map(|(_, entry)| &entry.public_offer);
+        match (matching.next(), matching.count()) {
+            (Some(public_offer), 0) => Ok(public_offer),
+            (Some(_), others) => Err(ResolveError::Ambiguous(others + 1)),
+            (None, _) => Err(ResolveError::NotFound),
+        }
+    }
+
+    /// Remove the offer from the book and return it, expired or not.
+    pub fn remove(&mut self, id: &ShortOfferId) -> Option<PublicOffer<Ctx>> {
+        let entry = self.entries.remove(id)?;
+        self.removed.insert(*id);
+        Some(entry.public_offer)
+    }
+
+    /// Return the offers not expired at `now`, ordered by identifier.
+    pub fn offers(&self, now: impl Clock) -> Vec<&PublicOffer<Ctx>> {
+        let now = now.now();
+        let mut offers: Vec<(&ShortOfferId, &Entry<Ctx>)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expiry > now)
+            .collect();
+        offers.sort_by_key(|(id, _)| **id);
+        offers
+            .into_iter()
+            .map(|(_, entry)| &entry.public_offer)
+            .collect()
+    }
+
+    /// Drop the offers expired at `now` and return the number of offers dropped.
+    pub fn expire(&mut self, now: impl Clock) -> usize {
+        self.drain_expired(now.now()).len()
+    }
+
+    fn drain_expired(&mut self, now: Duration) -> Vec<PublicOffer<Ctx>> {
+        let expired: Vec<ShortOfferId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expiry <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        expired.iter().filter_map(|id| self.remove(id)).collect()
+    }
+
+    fn evict(&mut self) -> Option<PublicOffer<Ctx>> {
+        let id = match self.policy {
+            EvictionPolicy::LeastRecentlyUsed => self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| *id),
+            EvictionPolicy::EarliestExpiry => self
+                .entries
+                .iter()
+                .min_by_key(|(id, entry)| (entry.expiry, **id))
+                .map(|(id, _)| *id),
+        }?;
+        self.remove(&id)
+    }
+
+    /// Return true if entries were inserted, updated, or removed since the last flush.
+    pub fn is_dirty(&self) -> bool {
+        !self.removed.is_empty() || self.entries.values().any(|entry| entry.dirty)
+    }
+
+    /// Write the records of the offers changed since the last flush to the store, and delete the
+    /// records of the offers removed from the book. Returns the number of records written or
+    /// deleted. On error the entries not yet persisted are kept dirty and flushed again on the
+    /// next call.
+    pub fn flush<S: OfferStore>(&mut self, store: &mut S) -> Result<usize, S::Error> {
+        let mut count = 0;
+        let removed: Vec<ShortOfferId> = self.removed.iter().copied().collect();
+        for id in removed {
+            store.delete(&id)?;
+            self.removed.remove(&id);
+            count += 1;
+        }
+        for (id, entry) in self.entries.iter_mut().filter(|(_, entry)| entry.dirty) {
+            let record = BookRecord {
+                public_offer: entry.public_offer.clone(),
+                expiry: entry.expiry,
+            };
+            store.save(id, &serialize(&record))?;
+            entry.dirty = false;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Restore the serialized records of a store in the book, dropping the offers expired at
+    /// `now` and evicting offers if the records exceed the capacity. Restored offers are not
+    /// dirty, the records of the dropped offers are deleted on the next flush. Returns the number
+    /// of offers in the book.
+    pub fn restore<'a, I>(&mut self, records: I, now: impl Clock) -> Result<usize, consensus::Error>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let now = now.now();
+        for bytes in records {
+            let record: BookRecord<Ctx> = deserialize(bytes)?;
+            if record.expiry <= now {
+                self.removed.insert(record.public_offer.short_id());
+                continue;
+            }
+            let id = record.public_offer.short_id();
+            self.insert(record.public_offer, record.expiry, now);
+            if let Some(entry) = self.entries.get_mut(&id) {
+                entry.dirty = false;
+            }
+        }
+        Ok(self.entries.len())
+    }
+}
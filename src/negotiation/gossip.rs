@@ -0,0 +1,261 @@
+//! Compact offer identifiers and filters for gossip protocols.
+//!
+//! Public offers are large compared to what a gossip layer needs to know whether a peer already
+//! has them. A [`ShortOfferId`] is a 16 bytes digest of the canonical bytes of a public offer
+//! that peers advertise in their inventories instead of the full offers, similarly to the short
+//! transaction identifiers of compact block relay.
+//!
+//! Peers reconcile their offer books by sending an [`OfferFilter`] containing the identifiers of
+//! all the offers they know, the receiving peer then transfers only the offers not matching the
+//! filter. Bloom filters have false positives but no false negatives: an offer may not be
+//! transferred even if the peer does not know it, changing the tweak between reconciliation
+//! rounds makes sure it is not missed twice.
+//!
+//! Users dictating an offer over voice or paper use its [`ShortCode`] instead: the first 60 bits
+//! of the short identifier written with 12 characters of the bech32 alphabet. A short code is
+//! resolved back to the full offer within an offer book, see
+//! [`OfferBook::resolve`](crate::negotiation::book::OfferBook::resolve).
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::consensus::{self, Decodable, Encodable};
+use crate::crypto::hash;
+use crate::negotiation::PublicOffer;
+use crate::swap::Swap;
+
+/// The maximum size of an offer filter in bytes.
+pub const MAX_FILTER_SIZE: usize = 36_000;
+
+/// The maximum number of hash functions used by an offer filter.
+pub const MAX_HASH_FUNCS: u8 = 50;
+
+/// A 16 bytes digest identifying a public offer in gossip inventories, computed as the truncated
+/// tagged hash of the canonical bytes of the public offer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShortOfferId(pub [u8; 16]);
+
+impl ShortOfferId {
+    pub(crate) fn new(canonical_bytes: &[u8]) -> Self {
+        let mut id = [0u8; 16];
+        id.copy_from_slice(&hash::tagged_sha256("gossip:offer", canonical_bytes)[..16]);
+        Self(id)
+    }
+}
+
+impl ShortOfferId {
+    /// Return the human-typeable short code of the offer.
+    pub fn short_code(&self) -> ShortCode {
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&self.0[..8]);
+        ShortCode(u64::from_be_bytes(prefix) >> 4)
+    }
+}
+
+impl fmt::Display for ShortOfferId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl Encodable for ShortOfferId {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        self.0.consensus_encode(writer)
+    }
+}
+
+impl Decodable for ShortOfferId {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self(Decodable::consensus_decode(d)?))
+    }
+}
+
+impl_strict_encoding!(ShortOfferId);
+
+/// The characters of the short codes, the bech32 alphabet excludes characters easily confused
+/// such as `1`, `b`, `i`, and `o`.
+const CODE_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The number of characters of a short code.
+pub const SHORT_CODE_LEN: usize = 12;
+
+/// A human-typeable code of 12 bech32 characters identifying a public offer, derived from the
+/// first 60 bits of its [`ShortOfferId`]. Short codes are displayed in three groups of four
+/// characters, e.g. `qpzr-y9x8-gf2t`, and parsed case insensitively with or without the dashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShortCode(u64);
+
+impl ShortCode {
+    /// Return true if the short code is the one of the offer identifier.
+    pub fn matches(&self, id: &ShortOfferId) -> bool {
+        id.short_code() == *self
+    }
+}
+
+impl fmt::Display for ShortCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for i in 0..SHORT_CODE_LEN {
+            if i > 0 && i % 4 == 0 {
+                write!(f, "-")?;
+            }
+            let index = (self.0 >> (5 * (SHORT_CODE_LEN - 1 - i))) & 0x1f;
+            write!(f, "{}", CODE_CHARSET[index as usize] as char)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ShortCode {
+    type Err = consensus::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut value = 0u64;
+        let mut len = 0;
+        for c in s.chars().filter(|c| *c != '-' && !c.is_whitespace()) {
+            let index = CODE_CHARSET
+                .iter()
+                .position(|x| *x as char == c.to_ascii_lowercase())
+                .ok_or(consensus::Error::ParseFailed(
+                    "invalid short code character",
+                ))?;
+            value = (value << 5) | index as u64;
+            len += 1;
+            if len > SHORT_CODE_LEN {
+                return Err(consensus::Error::ParseFailed("invalid short code length"));
+            }
+        }
+        match len {
+            SHORT_CODE_LEN => Ok(Self(value)),
+            _ => Err(consensus::Error::ParseFailed("invalid short code length")),
+        }
+    }
+}
+
+/// Return the sorted and deduplicated identifiers of the offers to advertise to peers.
+pub fn inventory<'a, Ctx, I>(offers: I) -> Vec<ShortOfferId>
+where
+    Ctx: Swap + 'a,
+    I: IntoIterator<Item = &'a PublicOffer<Ctx>>,
+{
+    let mut ids: Vec<ShortOfferId> = offers.into_iter().map(PublicOffer::short_id).collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// A bloom filter over short offer identifiers used to reconcile offer books between peers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfferFilter {
+    bits: Vec<u8>,
+    hash_count: u8,
+    tweak: u32,
+}
+
+impl OfferFilter {
+    /// Create an empty filter of `size` bytes using `hash_count` hash functions, the values are
+    /// bounded by [`MAX_FILTER_SIZE`] and [`MAX_HASH_FUNCS`]. The tweak randomizes the hash
+    /// functions and should be changed on every reconciliation.
+    pub fn new(size: usize, hash_count: u8, tweak: u32) -> Self {
+        Self {
+            bits: vec![0u8; size.clamp(1, MAX_FILTER_SIZE)],
+            hash_count: hash_count.clamp(1, MAX_HASH_FUNCS),
+            tweak,
+        }
+    }
+
+    /// Create an empty filter sized to hold `capacity` identifiers with the given false positive
+    /// rate, within the bounds of [`OfferFilter::new`].
+    pub fn with_capacity(capacity: usize, false_positive_rate: f64, tweak: u32) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let capacity = capacity.max(1) as f64;
+        let bits = -capacity * false_positive_rate.ln() / (ln2 * ln2);
+        let size = (bits / 8.0).ceil().min(MAX_FILTER_SIZE as f64) as usize;
+        let hash_count = (size as f64 * 8.0 / capacity * ln2).round();
+        Self::new(size, hash_count.min(MAX_HASH_FUNCS as f64) as u8, tweak)
+    }
+
+    /// Create a filter containing all the offers of an offer book.
+    pub fn from_offers<Ctx: Swap>(
+        offers: &[PublicOffer<Ctx>],
+        false_positive_rate: f64,
+        tweak: u32,
+    ) -> Self {
+        let mut filter = Self::with_capacity(offers.len(), false_positive_rate, tweak);
+        for offer in offers.iter() {
+            filter.insert(&offer.short_id());
+        }
+        filter
+    }
+
+    // Double hashing over a tweaked digest of the identifier
+    fn indexes(&self, id: &ShortOfferId) -> impl Iterator<Item = usize> {
+        let mut data = self.tweak.to_le_bytes().to_vec();
+        data.extend_from_slice(&id.0);
+        let digest = hash::tagged_sha256("gossip:filter", &data);
+        let mut h1 = [0u8; 8];
+        let mut h2 = [0u8; 8];
+        h1.copy_from_slice(&digest[..8]);
+        h2.copy_from_slice(&digest[8..16]);
+        let (h1, h2) = (u64::from_le_bytes(h1), u64::from_le_bytes(h2));
+        let len = self.bits.len() as u64 * 8;
+        (0..self.hash_count as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    /// Add an identifier to the filter.
+    pub fn insert(&mut self, id: &ShortOfferId) {
+        let indexes: Vec<usize> = self.indexes(id).collect();
+        for index in indexes {
+            self.bits[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    /// Return true if the identifier may be in the filter, false if it is not.
+    pub fn contains(&self, id: &ShortOfferId) -> bool {
+        self.indexes(id)
+            .all(|index| self.bits[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// Return the offers not matching the filter, i.e. the offers to transfer to the peer.
+    pub fn missing<'a, Ctx, I>(&self, offers: I) -> Vec<&'a PublicOffer<Ctx>>
+    where
+        Ctx: Swap + 'a,
+        I: IntoIterator<Item = &'a PublicOffer<Ctx>>,
+    {
+        offers
+            .into_iter()
+            .filter(|offer| !self.contains(&offer.short_id()))
+            .collect()
+    }
+}
+
+impl Encodable for OfferFilter {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.bits.consensus_encode(s)?;
+        len += self.hash_count.consensus_encode(s)?;
+        Ok(len + self.tweak.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for OfferFilter {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let bits: Vec<u8> = Decodable::consensus_decode(d)?;
+        if bits.is_empty() || bits.len() > MAX_FILTER_SIZE {
+            return Err(consensus::Error::ParseFailed("invalid offer filter size"));
+        }
+        let hash_count = u8::consensus_decode(d)?;
+        if hash_count == 0 || hash_count > MAX_HASH_FUNCS {
+            return Err(consensus::Error::ParseFailed(
+                "invalid number of offer filter hash functions",
+            ));
+        }
+        Ok(Self {
+            bits,
+            hash_count,
+            tweak: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(OfferFilter);
@@ -0,0 +1,193 @@
+//! Batch signing and verification of public offers.
+//!
+//! Market makers publish many public offers from the same daemon and aggregators ingest offer
+//! books containing thousands of them. Instead of signing every offer, a maker signs a batch of
+//! offers at once with the key of its daemon node: the canonical bytes of the offers are the
+//! leaves of a Merkle tree, see [`merkle`], and a single BIP340 Schnorr signature commits to its
+//! root. Every [`SignedOffer`] carries the batch root, the signature, and the proof of inclusion
+//! of the offer in the batch, so it can be relayed and verified on its own.
+//!
+//! When verifying a book with [`verify_offers`] or [`valid_offers`], each batch signature is
+//! verified only once and the remaining offers of the batch only require their cheap Merkle
+//! proofs to be checked.
+
+use secp256k1::schnorrsig::{KeyPair, PublicKey, Signature};
+use secp256k1::{All, Message, Secp256k1, SecretKey};
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::consensus::{self, Decodable, Deterministic, Encodable};
+use crate::crypto::hash;
+use crate::crypto::merkle::{self, MerkleProof};
+use crate::negotiation::{Error, PublicOffer};
+use crate::swap::Swap;
+
+/// Return the message signed by the node key for the batch with the given Merkle root.
+pub fn batch_message(root: &[u8; 32]) -> [u8; 32] {
+    hash::tagged_sha256("offer_batch:sig", root)
+}
+
+/// A public offer signed by the node key of its daemon as part of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedOffer<Ctx: Swap> {
+    /// The signed public offer
+    pub public_offer: PublicOffer<Ctx>,
+    /// The Merkle root of the batch of offers
+    pub root: [u8; 32],
+    /// The proof of inclusion of the offer in the batch
+    pub proof: MerkleProof,
+    /// The Schnorr signature of the batch root by the node key
+    pub signature: Signature,
+}
+
+impl<Ctx> SignedOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Return the x-only node key expected to sign the offer.
+    pub fn node_key(&self) -> PublicKey {
+        PublicKey::from(self.public_offer.daemon_service.node_id)
+    }
+
+    /// Verify the inclusion of the offer in the batch and the batch signature.
+    pub fn verify(&self) -> Result<(), Error> {
+        self.verify_proof()?;
+        verify_batch(
+            &Secp256k1::new(),
+            &self.root,
+            &self.signature,
+            &self.node_key(),
+        )
+    }
+
+    fn verify_proof(&self) -> Result<(), Error> {
+        match self
+            .proof
+            .verify(&self.root, self.public_offer.canonical_bytes())
+        {
+            true => Ok(()),
+            false => Err(Error::InvalidSignature),
+        }
+    }
+
+    fn batch_key(&self) -> ([u8; 32], [u8; 32], [u8; 64]) {
+        let mut sig = [0u8; 64];
+        sig.copy_from_slice(&self.signature[..]);
+        (self.root, self.node_key().serialize(), sig)
+    }
+}
+
+fn verify_batch(
+    secp: &Secp256k1<All>,
+    root: &[u8; 32],
+    signature: &Signature,
+    key: &PublicKey,
+) -> Result<(), Error> {
+    let msg = Message::from_slice(&batch_message(root)).expect("32 bytes digest");
+    secp.schnorrsig_verify(signature, &msg, key)
+        .map_err(|_| Error::InvalidSignature)
+}
+
+/// Sign a batch of public offers with the node key of their daemon, producing one Schnorr
+/// signature for the whole batch. The auxiliary randomness is mixed in the signature nonce as
+/// specified in BIP340, it SHOULD be freshly generated for each batch.
+///
+/// Fails with [`Error::NodeKeyMismatch`] if the node of an offer is not the one of the key.
+pub fn sign_offers<Ctx>(
+    offers: Vec<PublicOffer<Ctx>>,
+    key: &SecretKey,
+    aux_rand: &[u8; 32],
+) -> Result<Vec<SignedOffer<Ctx>>, Error>
+where
+    Ctx: Swap,
+{
+    let secp = Secp256k1::signing_only();
+    let node_id = secp256k1::PublicKey::from_secret_key(&secp, key);
+    if offers.iter().any(|o| o.daemon_service.node_id != node_id) {
+        return Err(Error::NodeKeyMismatch);
+    }
+    let leaves: Vec<Vec<u8>> = offers.iter().map(|o| o.canonical_bytes()).collect();
+    let root = match merkle::merkle_root(&leaves) {
+        Some(root) => root,
+        None => return Ok(vec![]),
+    };
+    let msg = Message::from_slice(&batch_message(&root)).expect("32 bytes digest");
+    let keypair = KeyPair::from_secret_key(&secp, *key);
+    let signature = secp.schnorrsig_sign_with_aux_rand(&msg, &keypair, aux_rand);
+    Ok(offers
+        .into_iter()
+        .enumerate()
+        .map(|(i, public_offer)| SignedOffer {
+            public_offer,
+            root,
+            proof: MerkleProof::new(&leaves, i).expect("index in bounds"),
+            signature,
+        })
+        .collect())
+}
+
+/// Verify a book of signed offers, verifying each batch signature only once. Fails with
+/// [`Error::InvalidSignature`] if any offer is invalid.
+pub fn verify_offers<Ctx>(offers: &[SignedOffer<Ctx>]) -> Result<(), Error>
+where
+    Ctx: Swap,
+{
+    match valid_offers(offers).len() == offers.len() {
+        true => Ok(()),
+        false => Err(Error::InvalidSignature),
+    }
+}
+
+/// Return the valid offers of a book of signed offers, verifying each batch signature only once.
+pub fn valid_offers<Ctx>(offers: &[SignedOffer<Ctx>]) -> Vec<&SignedOffer<Ctx>>
+where
+    Ctx: Swap,
+{
+    let secp = Secp256k1::new();
+    let mut batches = HashMap::new();
+    offers
+        .iter()
+        .filter(|offer| {
+            offer.verify_proof().is_ok()
+                && *batches.entry(offer.batch_key()).or_insert_with(|| {
+                    verify_batch(&secp, &offer.root, &offer.signature, &offer.node_key()).is_ok()
+                })
+        })
+        .collect()
+}
+
+impl<Ctx> Encodable for SignedOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.public_offer.consensus_encode(s)?;
+        len += self.root.consensus_encode(s)?;
+        len += self.proof.consensus_encode(s)?;
+        s.write_all(&self.signature[..])?;
+        Ok(len + 64)
+    }
+}
+
+impl<Ctx> Decodable for SignedOffer<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let public_offer = Decodable::consensus_decode(d)?;
+        let root = Decodable::consensus_decode(d)?;
+        let proof = Decodable::consensus_decode(d)?;
+        let mut sig = [0u8; 64];
+        d.read_exact(&mut sig)?;
+        Ok(Self {
+            public_offer,
+            root,
+            proof,
+            signature: Signature::from_slice(&sig)
+                .map_err(|_| consensus::Error::ParseFailed("invalid schnorr signature"))?,
+        })
+    }
+}
+
+impl_strict_encoding!(SignedOffer<Ctx>, Ctx: Swap);
@@ -0,0 +1,249 @@
+//! Instrumentation hooks for exporting metrics without depending on a metrics library.
+//!
+//! A daemon implements [`Observer`] to count or time what the core library does, e.g. to export
+//! Prometheus metrics or to open tracing spans, and installs it once at startup with
+//! [`set_observer`]. All the callbacks have an empty default implementation so an observer only
+//! implements the ones it is interested in. When no observer is installed the callbacks are
+//! dispatched to [`NoopObserver`].
+//!
+//! The transitions of the [`Session`](crate::protocol::session::Session)s and the encoding and
+//! decoding of [`ProtocolMessage`](crate::protocol_message::ProtocolMessage)s are reported
+//! directly. Signature operations and syncer task registrations are reported by wrapping the
+//! wallet or the syncer of the daemon in [`Observed`].
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::crypto::{self, Sign};
+use crate::swap::SwapId;
+use crate::syncer::{
+    self, Abort, BroadcastTransaction, Event, Syncer, TaskId, WatchAddress, WatchHeight,
+    WatchTransaction,
+};
+
+static OBSERVER: OnceLock<Box<dyn Observer>> = OnceLock::new();
+
+/// Errors when installing an observer.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An observer is already installed.
+    #[error("An observer is already set")]
+    AlreadySet,
+}
+
+/// A transition executed by a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransitionEvent {
+    /// The swap of the session.
+    pub swap_id: SwapId,
+    /// Whether the state machine accepted the input.
+    pub accepted: bool,
+    /// The number of outputs produced by the transition.
+    pub outputs: usize,
+    /// Whether the session reached a terminal state.
+    pub terminal: bool,
+    /// The time spent executing the transition.
+    pub elapsed: Duration,
+}
+
+/// The signature operations of a wallet, see [`Sign`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureOp {
+    /// A message is signed.
+    Sign,
+    /// A signature is verified.
+    Verify,
+    /// A message is signed and encrypted with an adaptor key.
+    AdaptorSign,
+    /// An adaptor signature is verified.
+    VerifyAdaptor,
+    /// An adaptor signature is decrypted.
+    Adapt,
+    /// An adaptor key is recovered from a signature and its adaptor signature.
+    RecoverKey,
+}
+
+/// The tasks registered in a syncer, see [`Syncer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskKind {
+    Abort,
+    WatchHeight,
+    WatchAddress,
+    WatchTransaction,
+    BroadcastTransaction,
+}
+
+/// Callbacks invoked by the core library, implementations must be cheap and must not panic.
+pub trait Observer: Send + Sync {
+    /// Called after a session executed an input, accepted or not.
+    fn on_transition(&self, _event: &TransitionEvent) {}
+
+    /// Called after a protocol message of the given type is encoded in `len` bytes.
+    fn on_message_encoded(&self, _message_type: u16, _len: usize) {}
+
+    /// Called after a protocol message of the given type is decoded, `success` is false if the
+    /// message is not valid.
+    fn on_message_decoded(&self, _message_type: u16, _success: bool) {}
+
+    /// Called after a signature operation of a wallet.
+    fn on_signature(&self, _op: SignatureOp, _success: bool, _elapsed: Duration) {}
+
+    /// Called after a task is registered in a syncer.
+    fn on_task_registered(&self, _kind: TaskKind, _id: TaskId, _success: bool) {}
+}
+
+/// The observer ignoring all the callbacks, used when no observer is installed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl Observer for NoopObserver {}
+
+/// Install the observer of the process. Fails if an observer is already installed.
+pub fn set_observer(observer: impl Observer + 'static) -> Result<(), Error> {
+    OBSERVER
+        .set(Box::new(observer))
+        .map_err(|_| Error::AlreadySet)
+}
+
+/// Return the installed observer, or [`NoopObserver`] if none is installed.
+pub fn observer() -> &'static dyn Observer {
+    match OBSERVER.get() {
+        Some(observer) => observer.as_ref(),
+        None => &NoopObserver,
+    }
+}
+
+fn signature<T, E>(op: SignatureOp, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    let start = Instant::now();
+    let res = f();
+    observer().on_signature(op, res.is_ok(), start.elapsed());
+    res
+}
+
+fn registered<T>(
+    kind: TaskKind,
+    id: TaskId,
+    res: Result<T, syncer::Error>,
+) -> Result<T, syncer::Error> {
+    observer().on_task_registered(kind, id, res.is_ok());
+    res
+}
+
+/// Wraps a wallet or a syncer to report its operations to the installed observer.
+#[derive(Debug, Clone, Default)]
+pub struct Observed<T>(pub T);
+
+impl<T> Observed<T> {
+    /// Return the wrapped wallet or syncer.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, PublicKey, PrivateKey, Message, Signature, AdaptorSignature>
+    Sign<PublicKey, PrivateKey, Message, Signature, AdaptorSignature> for Observed<T>
+where
+    T: Sign<PublicKey, PrivateKey, Message, Signature, AdaptorSignature>,
+{
+    fn sign_with_key(&self, key: &PublicKey, msg: Message) -> Result<Signature, crypto::Error> {
+        signature(SignatureOp::Sign, || self.0.sign_with_key(key, msg))
+    }
+
+    fn verify_signature(
+        &self,
+        key: &PublicKey,
+        msg: Message,
+        sig: &Signature,
+    ) -> Result<(), crypto::Error> {
+        signature(SignatureOp::Verify, || {
+            self.0.verify_signature(key, msg, sig)
+        })
+    }
+
+    fn adaptor_sign_with_key(
+        &self,
+        key: &PublicKey,
+        adaptor: &PublicKey,
+        msg: Message,
+    ) -> Result<AdaptorSignature, crypto::Error> {
+        signature(SignatureOp::AdaptorSign, || {
+            self.0.adaptor_sign_with_key(key, adaptor, msg)
+        })
+    }
+
+    fn verify_adaptor_signature(
+        &self,
+        key: &PublicKey,
+        adaptor: &PublicKey,
+        msg: Message,
+        sig: &AdaptorSignature,
+    ) -> Result<(), crypto::Error> {
+        signature(SignatureOp::VerifyAdaptor, || {
+            self.0.verify_adaptor_signature(key, adaptor, msg, sig)
+        })
+    }
+
+    fn adapt_signature(
+        &self,
+        key: &PublicKey,
+        sig: AdaptorSignature,
+    ) -> Result<Signature, crypto::Error> {
+        signature(SignatureOp::Adapt, || self.0.adapt_signature(key, sig))
+    }
+
+    fn recover_key(&self, sig: Signature, adapted_sig: AdaptorSignature) -> PrivateKey {
+        let start = Instant::now();
+        let key = self.0.recover_key(sig, adapted_sig);
+        observer().on_signature(SignatureOp::RecoverKey, true, start.elapsed());
+        key
+    }
+}
+
+impl<T> Syncer for Observed<T>
+where
+    T: Syncer,
+{
+    fn abort(&mut self, task: Abort) -> Result<(), syncer::Error> {
+        let id = task.id;
+        registered(TaskKind::Abort, id, self.0.abort(task))
+    }
+
+    fn watch_height(&mut self, task: WatchHeight) -> Result<(), syncer::Error> {
+        let id = task.id;
+        registered(TaskKind::WatchHeight, id, self.0.watch_height(task))
+    }
+
+    fn watch_address(&mut self, task: WatchAddress) -> Result<(), syncer::Error> {
+        let id = task.id;
+        registered(TaskKind::WatchAddress, id, self.0.watch_address(task))
+    }
+
+    fn watch_transaction(&mut self, task: WatchTransaction) -> Result<(), syncer::Error> {
+        let id = task.id;
+        registered(
+            TaskKind::WatchTransaction,
+            id,
+            self.0.watch_transaction(task),
+        )
+    }
+
+    fn broadcast_transaction(&mut self, task: BroadcastTransaction) -> Result<(), syncer::Error> {
+        let id = task.id;
+        registered(
+            TaskKind::BroadcastTransaction,
+            id,
+            self.0.broadcast_transaction(task),
+        )
+    }
+
+    fn poll(&mut self) -> Result<Vec<Event>, syncer::Error> {
+        self.0.poll()
+    }
+
+    fn active_tasks(&self) -> Result<Vec<TaskId>, syncer::Error> {
+        self.0.active_tasks()
+    }
+}
@@ -0,0 +1,234 @@
+//! Swap state machines and the append-only log of their transitions.
+//!
+//! A state machine driving a swap, e.g. in the daemon, implements [`StateMachine`]: given the
+//! current state and an input, such as a protocol message or a syncer event, it returns the next
+//! state and the outputs to execute. Transitions are pure, the same state and input always produce
+//! the same next state and outputs.
+//!
+//! Every transition executed through an [`EventLog`] is recorded as a [`TransitionRecord`] along
+//! with its input. The log is encodable so it can be persisted with the swap and used later to
+//! replay the swap deterministically for debugging and dispute analysis, see [`replay`].
+//!
+//! Daemons running multiple swaps in parallel route their inputs to the right state machine with
+//! a [`session::SessionManager`], and release the outputs by priority through a
+//! [`queue::OutputQueue`]. Both roles of a self-swap can run in the same process as a
+//! [`loopback::Loopback`]. The randomness internal to a swap is derived from the
+//! [`beacon::EntropyBeacon`] of its session, and participants detect a disagreement about their
+//! position in the protocol with the signed checkpoints of [`position::PositionTracker`]. With
+//! the `test-utils` feature, a [`simulation::Simulation`] runs both roles against a mock chain
+//! with injected failures to test every failure path deterministically.
+
+use std::fmt::Debug;
+use std::io;
+
+use crate::consensus::{self, serialize, Decodable, Encodable};
+use crate::crypto::hash;
+
+pub mod beacon;
+pub mod loopback;
+pub mod position;
+pub mod queue;
+pub mod replay;
+pub mod session;
+#[cfg(feature = "test-utils")]
+pub mod simulation;
+
+/// The next state and the outputs produced by a transition of the state machine.
+pub type Transition<M> = (<M as StateMachine>::State, Vec<<M as StateMachine>::Output>);
+
+/// A deterministic state machine driving a swap.
+pub trait StateMachine: Debug + Clone + PartialEq + Send + Sync {
+    /// The states of the machine.
+    type State: Clone + PartialEq + Debug + Encodable + Decodable + Send + Sync;
+    /// The inputs triggering transitions, e.g. protocol messages or syncer events.
+    type Input: Clone + PartialEq + Debug + Encodable + Decodable + Send + Sync;
+    /// The outputs produced by a transition, e.g. messages to send or transactions to broadcast.
+    type Output: Clone + PartialEq + Debug + Encodable + Decodable + Send + Sync;
+    /// The error returned when an input is not valid in a state.
+    type Error: Debug + Send + Sync;
+
+    /// Compute the next state and the outputs given the current state and an input.
+    fn transition(
+        state: &Self::State,
+        input: &Self::Input,
+    ) -> Result<Transition<Self>, Self::Error>;
+
+    /// Return true if the state is terminal, i.e. the swap is over and no input is expected. By
+    /// default no state is terminal.
+    fn is_terminal(_state: &Self::State) -> bool {
+        false
+    }
+}
+
+/// Return the digest of a transition input as recorded in the log.
+pub fn input_digest<I: Encodable + Debug>(input: &I) -> [u8; 32] {
+    hash::tagged_sha256("event_log:input", &serialize(input))
+}
+
+/// A transition executed by a state machine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionRecord<M: StateMachine> {
+    /// The state before the transition.
+    pub from: M::State,
+    /// The digest of the input triggering the transition, see [`input_digest`].
+    pub input_digest: [u8; 32],
+    /// The state after the transition.
+    pub to: M::State,
+    /// The outputs produced by the transition.
+    pub outputs: Vec<M::Output>,
+}
+
+impl<M> Encodable for TransitionRecord<M>
+where
+    M: StateMachine,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.from.consensus_encode(s)?;
+        len += self.input_digest.consensus_encode(s)?;
+        len += self.to.consensus_encode(s)?;
+        Ok(len + self.outputs.consensus_encode(s)?)
+    }
+}
+
+impl<M> Decodable for TransitionRecord<M>
+where
+    M: StateMachine,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            from: Decodable::consensus_decode(d)?,
+            input_digest: Decodable::consensus_decode(d)?,
+            to: Decodable::consensus_decode(d)?,
+            outputs: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(TransitionRecord<M>, M: StateMachine);
+
+/// A recorded transition with the input that triggered it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry<M: StateMachine> {
+    pub input: M::Input,
+    pub record: TransitionRecord<M>,
+}
+
+impl<M> Encodable for LogEntry<M>
+where
+    M: StateMachine,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.input.consensus_encode(s)?;
+        Ok(len + self.record.consensus_encode(s)?)
+    }
+}
+
+impl<M> Decodable for LogEntry<M>
+where
+    M: StateMachine,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            input: Decodable::consensus_decode(d)?,
+            record: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(LogEntry<M>, M: StateMachine);
+
+/// The append-only log of the transitions of a swap, in execution order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventLog<M: StateMachine> {
+    entries: Vec<LogEntry<M>>,
+}
+
+impl<M> Default for EventLog<M>
+where
+    M: StateMachine,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> EventLog<M>
+where
+    M: StateMachine,
+{
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Execute the transition triggered by the input on the state, record it, and return the
+    /// next state with the outputs. Nothing is recorded if the transition fails.
+    pub fn execute(
+        &mut self,
+        state: &M::State,
+        input: M::Input,
+    ) -> Result<Transition<M>, M::Error> {
+        let (to, outputs) = M::transition(state, &input)?;
+        let record = TransitionRecord {
+            from: state.clone(),
+            input_digest: input_digest(&input),
+            to: to.clone(),
+            outputs: outputs.clone(),
+        };
+        self.entries.push(LogEntry { input, record });
+        Ok((to, outputs))
+    }
+
+    /// Return the recorded transitions with their inputs.
+    pub fn entries(&self) -> &[LogEntry<M>] {
+        &self.entries
+    }
+
+    /// Return an iterator over the recorded transitions.
+    pub fn records(&self) -> impl Iterator<Item = &TransitionRecord<M>> {
+        self.entries.iter().map(|entry| &entry.record)
+    }
+
+    /// Return the state after the last recorded transition, if any.
+    pub fn last_state(&self) -> Option<&M::State> {
+        self.entries.last().map(|entry| &entry.record.to)
+    }
+
+    /// Return the number of recorded transitions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return true if no transition is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<M> Encodable for EventLog<M>
+where
+    M: StateMachine,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.entries.consensus_encode(s)
+    }
+}
+
+impl<M> Decodable for EventLog<M>
+where
+    M: StateMachine,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let entries: Vec<LogEntry<M>> = Decodable::consensus_decode(d)?;
+        // Recorded inputs must match their digests
+        if entries
+            .iter()
+            .any(|entry| input_digest(&entry.input) != entry.record.input_digest)
+        {
+            return Err(consensus::Error::ParseFailed("invalid input digest in log"));
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl_strict_encoding!(EventLog<M>, M: StateMachine);
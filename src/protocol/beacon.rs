@@ -0,0 +1,92 @@
+//! Per-swap entropy beacon from which the randomness internal to a swap is derived.
+//!
+//! Both participants commit to their parameters before revealing them, so once both reveals are
+//! received and checked against their commitments neither participant could have chosen the
+//! content of the other's reveal. An [`EntropyBeacon`] hashes the two reveals together with the
+//! swap identifier: the beacon is unpredictable to either participant alone, and since the reveals
+//! are persisted along the swap the beacon, and every value derived from it, can be recomputed
+//! from a checkpoint.
+//!
+//! Values are derived per label with [`EntropyBeacon::entropy`], e.g. the padding of the
+//! messages, and are known by both participants. Values that must stay secret, e.g. blinding
+//! factors or key tweaks, are derived with [`EntropyBeacon::keyed_entropy`] which mixes a secret
+//! known only by the participant, e.g. derived from its wallet seed.
+
+use std::io;
+
+use crate::consensus::{self, serialize, Decodable, Encodable};
+use crate::crypto::hash;
+use crate::protocol_message::{RevealAliceParameters, RevealBobParameters};
+use crate::swap::{Swap, SwapId};
+
+/// The source of the randomness of a swap, computed from both participants' reveals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntropyBeacon {
+    swap_id: SwapId,
+    seed: [u8; 32],
+}
+
+impl EntropyBeacon {
+    /// Compute the beacon of the swap from the reveals of both participants. The reveals MUST be
+    /// verified against their commitments first, otherwise the last participant to reveal can
+    /// choose the beacon.
+    pub fn from_reveals<Ctx>(
+        swap_id: SwapId,
+        alice: &RevealAliceParameters<Ctx>,
+        bob: &RevealBobParameters<Ctx>,
+    ) -> Self
+    where
+        Ctx: Swap,
+    {
+        Self::from_contributions(swap_id, &serialize(alice), &serialize(bob))
+    }
+
+    /// Compute the beacon of the swap from the raw contributions of Alice and Bob, both revealed
+    /// after being committed to.
+    pub fn from_contributions(swap_id: SwapId, alice: &[u8], bob: &[u8]) -> Self {
+        let mut data = swap_id.0.to_vec();
+        // Length prefixes so contributions cannot be shifted from one participant to the other
+        data.extend(serialize(&alice.to_vec()));
+        data.extend(serialize(&bob.to_vec()));
+        Self {
+            swap_id,
+            seed: hash::tagged_sha256("beacon:seed", &data),
+        }
+    }
+
+    /// Return the swap of the beacon.
+    pub fn swap_id(&self) -> SwapId {
+        self.swap_id
+    }
+
+    /// Derive the public randomness for the label, known by both participants.
+    pub fn entropy(&self, label: &str) -> [u8; 32] {
+        hash::keyed_hash("beacon:entropy", &self.seed, label.as_bytes())
+    }
+
+    /// Derive the private randomness for the label, unpredictable to the counter-party without
+    /// the secret of the participant.
+    pub fn keyed_entropy(&self, secret: &[u8], label: &str) -> [u8; 32] {
+        let mut data = self.seed.to_vec();
+        data.extend_from_slice(label.as_bytes());
+        hash::keyed_hash("beacon:keyed_entropy", secret, &data)
+    }
+}
+
+impl Encodable for EntropyBeacon {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.swap_id.consensus_encode(s)?;
+        Ok(len + self.seed.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for EntropyBeacon {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            seed: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(EntropyBeacon);
@@ -0,0 +1,105 @@
+//! Both roles of a swap running in the same process.
+//!
+//! A loopback swap runs Alice and Bob side by side against the real chains, e.g. to move funds
+//! to new keys with a self-swap or to test a full swap in integration tests. The two sessions
+//! share the same swap identifier and the protocol messages produced by one role are delivered
+//! in memory as inputs to the other role instead of being sent over the network.
+
+use std::collections::VecDeque;
+
+use crate::protocol::session::{Error, Session};
+use crate::protocol::StateMachine;
+use crate::role::SwapRole;
+use crate::swap::SwapId;
+
+/// The outputs to execute by the daemon after a loopback transition, with the role producing
+/// them.
+pub type LoopbackOutputs<M> = Vec<(SwapRole, <M as StateMachine>::Output)>;
+
+/// State machines able to run in loopback mode.
+pub trait LoopbackMachine: StateMachine {
+    /// Return the input to deliver to the counter-party if the output is a protocol message
+    /// addressed to it, `None` if the output must be executed by the daemon.
+    fn deliver(output: &Self::Output) -> Option<Self::Input>;
+}
+
+/// The sessions of Alice and Bob for the same swap.
+#[derive(Debug, Clone)]
+pub struct Loopback<M: StateMachine, P, F = u64> {
+    alice: Session<M, P, F>,
+    bob: Session<M, P, F>,
+}
+
+impl<M, P, F> Loopback<M, P, F>
+where
+    M: StateMachine,
+    P: Clone,
+{
+    /// Create the sessions of both roles in their initial state.
+    pub fn new(swap_id: SwapId, peer: P, alice: M::State, bob: M::State) -> Self {
+        Self {
+            alice: Session::new(swap_id, peer.clone(), alice),
+            bob: Session::new(swap_id, peer, bob),
+        }
+    }
+}
+
+impl<M, P, F> Loopback<M, P, F>
+where
+    M: StateMachine,
+{
+    /// Return the identifier of the swap shared by both sessions.
+    pub fn swap_id(&self) -> SwapId {
+        self.alice.swap_id()
+    }
+
+    /// Return the session of the role.
+    pub fn session(&self, role: SwapRole) -> &Session<M, P, F> {
+        match role {
+            SwapRole::Alice => &self.alice,
+            SwapRole::Bob => &self.bob,
+        }
+    }
+
+    pub(crate) fn session_mut(&mut self, role: SwapRole) -> &mut Session<M, P, F> {
+        match role {
+            SwapRole::Alice => &mut self.alice,
+            SwapRole::Bob => &mut self.bob,
+        }
+    }
+
+    /// Return both sessions, Alice first.
+    pub fn sessions(&self) -> impl Iterator<Item = &Session<M, P, F>> {
+        vec![&self.alice, &self.bob].into_iter()
+    }
+}
+
+impl<M, P, F> Loopback<M, P, F>
+where
+    M: LoopbackMachine,
+{
+    /// Execute the input on the session of the role and deliver the resulting messages to the
+    /// other role until no message is left. Return the outputs to execute by the daemon with the
+    /// role producing them, in order.
+    ///
+    /// If a delivered message is rejected the error is returned and the messages not yet
+    /// delivered are dropped, the transitions already executed are kept.
+    pub fn handle(
+        &mut self,
+        role: SwapRole,
+        input: M::Input,
+    ) -> Result<LoopbackOutputs<M>, Error<M::Error>> {
+        let mut mailbox = VecDeque::new();
+        mailbox.push_back((role, input));
+        let mut outputs = LoopbackOutputs::<M>::new();
+        while let Some((role, input)) = mailbox.pop_front() {
+            for output in self.session_mut(role).handle(input)? {
+                match M::deliver(&output) {
+                    Some(message) => mailbox.push_back((role.other(), message)),
+                    None => outputs.push((role, output)),
+                }
+            }
+        }
+        Ok(outputs)
+    }
+}
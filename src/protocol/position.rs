@@ -0,0 +1,340 @@
+//! Signed checkpoints of the protocol position exchanged between the participants.
+//!
+//! A participant disagreeing with its counter-party about the position in the protocol, e.g.
+//! after a lost message or a restart from an outdated checkpoint, usually only notices it later
+//! as a confusing validation failure. When both participants activate
+//! [`FEATURE_CHECKPOINTS`], they periodically exchange a [`StateCheckpoint`]: a [`StateDigest`] of
+//! their current phase and of the messages sent by each role, signed with their arbitrating key.
+//! A [`PositionTracker`] records the messages of the swap and verifies the checkpoints received,
+//! so a disagreement is detected as soon as the next checkpoint is received.
+//!
+//! The messages sent by Alice and by Bob are digested separately: each role sends its own
+//! messages in order, but messages crossing in flight are not received in the same order by both
+//! participants. A checkpoint is compared with the local messages digested up to the number of
+//! messages seen by the counter-party, so checkpoints sent while messages are still in flight are
+//! accepted, see [`CheckpointStatus::InFlight`]. The checkpoints themselves are not recorded.
+//!
+//! [`FEATURE_CHECKPOINTS`]: crate::negotiation::FEATURE_CHECKPOINTS
+
+use std::fmt;
+use std::io;
+
+use thiserror::Error;
+
+use crate::consensus::{self, Decodable, Encodable};
+use crate::crypto::{self, Keys, SigHashPreimage, Sign, Signatures};
+use crate::dispute::Transcript;
+use crate::protocol_message::{ProtocolMessage, StateCheckpoint};
+use crate::role::SwapRole;
+use crate::settlement::SwapPhase;
+use crate::swap::{Swap, SwapId};
+
+/// Errors when verifying a checkpoint received from the counter-party.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The checkpoint is not about the swap tracked.
+    #[error("Checkpoint of another swap")]
+    SwapMismatch,
+    /// The signature of the checkpoint is not valid.
+    #[error("Invalid signature of the checkpoint")]
+    InvalidSignature,
+    /// The counter-party disagrees about the position in the protocol.
+    #[error("Protocol position diverged: {local} locally, {remote} for the counter-party")]
+    Diverged {
+        /// The local digest of the swap
+        local: Box<StateDigest>,
+        /// The digest of the swap signed by the counter-party
+        remote: Box<StateDigest>,
+    },
+    /// A cryptographic error when signing the checkpoint.
+    #[error("Cryptographic error: {0}")]
+    Crypto(#[from] crypto::Error),
+}
+
+/// The number of messages sent by a role and the Merkle root over them, the root is zero if
+/// no message is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessagesDigest {
+    /// The number of messages sent
+    pub len: u32,
+    /// The Merkle root over the encoded messages
+    pub root: [u8; 32],
+}
+
+impl MessagesDigest {
+    // Digest the first `len` messages of the transcript, `None` if fewer messages are recorded
+    fn of(transcript: &Transcript, len: u32) -> Option<Self> {
+        match len {
+            0 => Some(Self {
+                len,
+                root: [0u8; 32],
+            }),
+            len => transcript.commitment_at(len).map(|commitment| Self {
+                len,
+                root: commitment.root,
+            }),
+        }
+    }
+}
+
+impl Encodable for MessagesDigest {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.len.consensus_encode(s)?;
+        Ok(len + self.root.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for MessagesDigest {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            len: Decodable::consensus_decode(d)?,
+            root: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+/// The digest of the state of a swap from the point of view of a participant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateDigest {
+    /// The swap identifier
+    pub swap_id: SwapId,
+    /// The current phase of the swap
+    pub phase: SwapPhase,
+    /// The messages sent by Alice
+    pub alice: MessagesDigest,
+    /// The messages sent by Bob
+    pub bob: MessagesDigest,
+}
+
+impl StateDigest {
+    /// Return the digest of the messages sent by the role.
+    pub fn messages(&self, swap_role: SwapRole) -> &MessagesDigest {
+        match swap_role {
+            SwapRole::Alice => &self.alice,
+            SwapRole::Bob => &self.bob,
+        }
+    }
+
+    /// Return the message signed by the participants, derived from the serialized digest.
+    pub fn message<Ar: SigHashPreimage>(&self) -> Ar::Message {
+        let mut preimage = b"farcaster:checkpoint".to_vec();
+        preimage.extend(consensus::serialize(self));
+        Ar::message_from_preimage(&preimage)
+    }
+}
+
+impl fmt::Display for StateDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} phase after {} Alice and {} Bob messages",
+            self.phase, self.alice.len, self.bob.len
+        )
+    }
+}
+
+impl Encodable for StateDigest {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.phase.consensus_encode(s)?;
+        len += self.alice.consensus_encode(s)?;
+        Ok(len + self.bob.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for StateDigest {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            phase: Decodable::consensus_decode(d)?,
+            alice: Decodable::consensus_decode(d)?,
+            bob: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(StateDigest);
+
+/// The result of a successful verification of a checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointStatus {
+    /// Both participants are at the same position.
+    Agreed,
+    /// Both participants agree on the messages seen by both of them, but messages are still in
+    /// flight. The phases are not compared, the next checkpoint is expected to agree.
+    InFlight {
+        /// The number of messages sent but not yet received by the counter-party
+        to_peer: u32,
+        /// The number of messages sent by the counter-party but not yet received
+        from_peer: u32,
+    },
+}
+
+/// Records the messages of a swap and digests the position of the participant in the protocol,
+/// see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionTracker {
+    swap_id: SwapId,
+    swap_role: SwapRole,
+    phase: SwapPhase,
+    alice: Transcript,
+    bob: Transcript,
+}
+
+impl PositionTracker {
+    /// Create a tracker for the participant playing the swap role, starting in the setup phase.
+    pub fn new(swap_id: SwapId, swap_role: SwapRole) -> Self {
+        Self {
+            swap_id,
+            swap_role,
+            phase: SwapPhase::Setup,
+            alice: Transcript::new(swap_id),
+            bob: Transcript::new(swap_id),
+        }
+    }
+
+    /// Return the swap identifier.
+    pub fn swap_id(&self) -> SwapId {
+        self.swap_id
+    }
+
+    /// Return the swap role of the participant.
+    pub fn swap_role(&self) -> SwapRole {
+        self.swap_role
+    }
+
+    /// Return the current phase of the swap.
+    pub fn phase(&self) -> SwapPhase {
+        self.phase
+    }
+
+    /// Set the current phase of the swap.
+    pub fn set_phase(&mut self, phase: SwapPhase) {
+        self.phase = phase;
+    }
+
+    /// Record a message sent to the counter-party, checkpoints are ignored.
+    pub fn record_sent<Ctx: Swap>(&mut self, message: &ProtocolMessage<Ctx>) {
+        self.record(self.swap_role, message);
+    }
+
+    /// Record a message received from the counter-party, checkpoints are ignored.
+    pub fn record_received<Ctx: Swap>(&mut self, message: &ProtocolMessage<Ctx>) {
+        self.record(self.swap_role.other(), message);
+    }
+
+    fn record<Ctx: Swap>(&mut self, sender: SwapRole, message: &ProtocolMessage<Ctx>) {
+        if let ProtocolMessage::StateCheckpoint(_) = message {
+            return;
+        }
+        match sender {
+            SwapRole::Alice => self.alice.push(message),
+            SwapRole::Bob => self.bob.push(message),
+        };
+    }
+
+    fn transcript(&self, swap_role: SwapRole) -> &Transcript {
+        match swap_role {
+            SwapRole::Alice => &self.alice,
+            SwapRole::Bob => &self.bob,
+        }
+    }
+
+    /// Return the digest of the current position of the participant.
+    pub fn digest(&self) -> StateDigest {
+        let digest = |transcript: &Transcript| {
+            MessagesDigest::of(transcript, transcript.len() as u32)
+                .expect("all the recorded messages are digested")
+        };
+        StateDigest {
+            swap_id: self.swap_id,
+            phase: self.phase,
+            alice: digest(&self.alice),
+            bob: digest(&self.bob),
+        }
+    }
+
+    /// Sign the digest of the current position with the participant arbitrating key, the
+    /// checkpoint is sent to the counter-party.
+    pub fn checkpoint<Ctx>(
+        &self,
+        key: &<Ctx::Ar as Keys>::PublicKey,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+    ) -> Result<StateCheckpoint<Ctx>, Error>
+    where
+        Ctx: Swap,
+        Ctx::Ar: SigHashPreimage,
+    {
+        let digest = self.digest();
+        let signature = wallet.sign_with_key(key, digest.message::<Ctx::Ar>())?;
+        Ok(StateCheckpoint { digest, signature })
+    }
+
+    /// Verify a checkpoint received from the counter-party, signed with its arbitrating `key`,
+    /// against the local position. Fails with [`Error::Diverged`] if the counter-party saw other
+    /// messages than the ones recorded, or is in another phase once all the messages are
+    /// received.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn verify<Ctx>(
+        &self,
+        checkpoint: &StateCheckpoint<Ctx>,
+        key: &<Ctx::Ar as Keys>::PublicKey,
+        verifier: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+    ) -> Result<CheckpointStatus, Error>
+    where
+        Ctx: Swap,
+        Ctx::Ar: SigHashPreimage,
+    {
+        let remote = checkpoint.digest;
+        if remote.swap_id != self.swap_id {
+            return Err(Error::SwapMismatch);
+        }
+        verifier
+            .verify_signature(key, remote.message::<Ctx::Ar>(), &checkpoint.signature)
+            .map_err(|_| Error::InvalidSignature)?;
+        let diverged = || Error::Diverged {
+            local: Box::new(self.digest()),
+            remote: Box::new(remote),
+        };
+        // Compare the messages seen by both participants
+        let mut in_flight = [0u32; 2];
+        for (i, swap_role) in [self.swap_role, self.swap_role.other()].iter().enumerate() {
+            let transcript = self.transcript(*swap_role);
+            let seen = remote.messages(*swap_role);
+            let local_len = transcript.len() as u32;
+            // The counter-party cannot see more of our messages than sent, nor fewer of its own
+            // messages than received
+            let is_own = *swap_role == self.swap_role;
+            if (is_own && seen.len > local_len) || (!is_own && seen.len < local_len) {
+                return Err(diverged());
+            }
+            in_flight[i] = local_len.max(seen.len) - local_len.min(seen.len);
+            if seen.len <= local_len
+                && MessagesDigest::of(transcript, seen.len).as_ref() != Some(seen)
+            {
+                return Err(diverged());
+            }
+        }
+        match in_flight {
+            [0, 0] if remote.phase == self.phase => Ok(CheckpointStatus::Agreed),
+            [0, 0] => Err(diverged()),
+            [to_peer, from_peer] => Ok(CheckpointStatus::InFlight { to_peer, from_peer }),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Priority queue of the outputs produced by the state machines, with acknowledgments.
+//!
+//! Outputs are not equally urgent: broadcasting a cancel or punish transaction in time protects
+//! the funds, while a progress report can wait. An [`OutputQueue`] releases the outputs by
+//! [`Priority`], in order within the same priority, so a congested transport never delays a
+//! safety-critical broadcast behind chatty messages.
+//!
+//! Released outputs stay in flight until the daemon acknowledges them with
+//! [`OutputQueue::ack`]; an output not delivered is put back in front of its priority level with
+//! [`OutputQueue::nack`].
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::swap::SwapId;
+
+/// The priority of an output, from the most to the least urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Broadcasts protecting the funds, e.g. cancel, refund, or punish transactions.
+    Safety,
+    /// Protocol messages sent to the counter-party.
+    Protocol,
+    /// Progress reports sent to the client.
+    Progress,
+}
+
+impl Priority {
+    /// All the priorities from the most to the least urgent.
+    pub const ALL: [Priority; 3] = [Priority::Safety, Priority::Protocol, Priority::Progress];
+
+    fn level(&self) -> usize {
+        match self {
+            Priority::Safety => 0,
+            Priority::Protocol => 1,
+            Priority::Progress => 2,
+        }
+    }
+}
+
+/// Outputs with a priority.
+pub trait Prioritized {
+    /// Return the priority of the output.
+    fn priority(&self) -> Priority;
+}
+
+/// Outputs tagged with the swap they belong to keep their priority.
+impl<O> Prioritized for (SwapId, O)
+where
+    O: Prioritized,
+{
+    fn priority(&self) -> Priority {
+        self.1.priority()
+    }
+}
+
+/// Identifies an output released by the queue and waiting for acknowledgment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OutputId(u64);
+
+/// Queue releasing the outputs by priority and tracking the outputs in flight.
+#[derive(Debug, Clone)]
+pub struct OutputQueue<O> {
+    levels: [VecDeque<(OutputId, O)>; 3],
+    in_flight: BTreeMap<OutputId, O>,
+    next_id: u64,
+}
+
+impl<O> Default for OutputQueue<O>
+where
+    O: Prioritized + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> OutputQueue<O>
+where
+    O: Prioritized + Clone,
+{
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        Self {
+            levels: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            in_flight: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Queue an output after the outputs of the same priority.
+    pub fn push(&mut self, output: O) -> OutputId {
+        let id = OutputId(self.next_id);
+        self.next_id += 1;
+        self.levels[output.priority().level()].push_back((id, output));
+        id
+    }
+
+    /// Queue all the outputs of a transition in order.
+    pub fn extend<I: IntoIterator<Item = O>>(&mut self, outputs: I) {
+        for output in outputs {
+            self.push(output);
+        }
+    }
+
+    /// Release the most urgent output, it stays in flight until acknowledged.
+    pub fn pop(&mut self) -> Option<(OutputId, O)> {
+        let (id, output) = self.levels.iter_mut().find_map(|level| level.pop_front())?;
+        self.in_flight.insert(id, output.clone());
+        Some((id, output))
+    }
+
+    /// Return the most urgent output without releasing it.
+    pub fn peek(&self) -> Option<&O> {
+        self.levels
+            .iter()
+            .find_map(|level| level.front())
+            .map(|(_, output)| output)
+    }
+
+    /// Acknowledge the delivery of an output in flight and return it, `None` if the output is
+    /// not in flight.
+    pub fn ack(&mut self, id: OutputId) -> Option<O> {
+        self.in_flight.remove(&id)
+    }
+
+    /// Put an output in flight back in front of its priority level, e.g. when the transport
+    /// failed to deliver it. Return false if the output is not in flight.
+    pub fn nack(&mut self, id: OutputId) -> bool {
+        match self.in_flight.remove(&id) {
+            Some(output) => {
+                let level = &mut self.levels[output.priority().level()];
+                // Keep the outputs of the level ordered by queuing order
+                let position = level
+                    .iter()
+                    .position(|(queued, _)| *queued > id)
+                    .unwrap_or(level.len());
+                level.insert(position, (id, output));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the number of queued outputs with the priority, in flight outputs excluded.
+    pub fn pending(&self, priority: Priority) -> usize {
+        self.levels[priority.level()].len()
+    }
+
+    /// Return the number of outputs released and not acknowledged.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Return the number of queued outputs, in flight outputs excluded.
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(VecDeque::len).sum()
+    }
+
+    /// Return true if no output is queued, in flight outputs excluded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
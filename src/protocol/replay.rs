@@ -0,0 +1,86 @@
+//! Deterministic replay of recorded swaps.
+//!
+//! [`replay`] re-executes the inputs recorded in an [`EventLog`] against the current
+//! implementation of the state machine and compares every transition with the recorded one. Used
+//! to validate that changes in a state machine do not alter its behavior on historical swaps.
+
+use crate::protocol::{EventLog, StateMachine};
+
+/// The first difference found between a recorded transition and its replay. The index is the
+/// position of the transition in the log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence<M: StateMachine> {
+    /// The state before the transition is not the one reached by the replay, e.g. the log does not
+    /// start from the given initial state.
+    From {
+        index: usize,
+        recorded: M::State,
+        replayed: M::State,
+    },
+    /// The input is rejected by the state machine.
+    Rejected { index: usize, error: M::Error },
+    /// The input leads to another state.
+    To {
+        index: usize,
+        recorded: M::State,
+        replayed: M::State,
+    },
+    /// The input produces other outputs.
+    Outputs {
+        index: usize,
+        recorded: Vec<M::Output>,
+        replayed: Vec<M::Output>,
+    },
+}
+
+impl<M> Divergence<M>
+where
+    M: StateMachine,
+{
+    /// Return the index in the log of the diverging transition.
+    pub fn index(&self) -> usize {
+        match self {
+            Divergence::From { index, .. }
+            | Divergence::Rejected { index, .. }
+            | Divergence::To { index, .. }
+            | Divergence::Outputs { index, .. } => *index,
+        }
+    }
+}
+
+/// Re-execute the transitions recorded in the log from the initial state and return the final
+/// state, or the first divergence between the log and the replay.
+pub fn replay<M>(log: &EventLog<M>, initial_state: M::State) -> Result<M::State, Divergence<M>>
+where
+    M: StateMachine,
+{
+    let mut state = initial_state;
+    for (index, entry) in log.entries().iter().enumerate() {
+        let record = &entry.record;
+        if record.from != state {
+            return Err(Divergence::From {
+                index,
+                recorded: record.from.clone(),
+                replayed: state,
+            });
+        }
+        let (to, outputs) = M::transition(&state, &entry.input)
+            .map_err(|error| Divergence::Rejected { index, error })?;
+        if record.to != to {
+            return Err(Divergence::To {
+                index,
+                recorded: record.to.clone(),
+                replayed: to,
+            });
+        }
+        if record.outputs != outputs {
+            return Err(Divergence::Outputs {
+                index,
+                recorded: record.outputs.clone(),
+                replayed: outputs,
+            });
+        }
+        state = to;
+    }
+    Ok(state)
+}
@@ -0,0 +1,435 @@
+//! Multiplexing of the swaps running in parallel.
+//!
+//! A [`Session`] is a live swap: the current state of its state machine and the log of its
+//! transitions. A [`SessionManager`] owns all the sessions of a daemon keyed by [`SwapId`],
+//! routes the decoded messages and events to the right session, and limits the number of
+//! sessions running with the same peer. A manager also runs the two sessions of a loopback swap
+//! under the same swap identifier, see [`Loopback`].
+//!
+//! Once both participants revealed their parameters, the session holds the [`EntropyBeacon`] of
+//! the swap from which all the randomness internal to the swap is derived, see
+//! [`Session::entropy`].
+//!
+//! Each session holds the [`FeeLedger`] of the fees set on the transactions of its swap, the
+//! fees of all the sessions are summed up in the [`Progress`] of the manager. Fees are accounted
+//! in the unit `F`, the fee asset unit of the arbitrating blockchain, see
+//! [`Fee::FeeAssetUnit`](crate::blockchain::Fee::FeeAssetUnit).
+//!
+//! The messages of a session are optionally padded with a [`Padding`] scheme before being sent
+//! over the encrypted transport, see [`Session::encode_message`]. The padding is configured per
+//! session or for all the sessions opened by a manager with [`SessionManager::with_padding`].
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::Add;
+use std::time::Instant;
+
+use thiserror::Error;
+
+use crate::consensus::{self, deserialize, deserialize_padded, serialize, Decodable, Encodable};
+use crate::consensus::{serialize_padded, Padding};
+use crate::ledger::FeeLedger;
+use crate::observer::{observer, TransitionEvent};
+use crate::protocol::beacon::EntropyBeacon;
+use crate::protocol::loopback::{Loopback, LoopbackMachine, LoopbackOutputs};
+use crate::protocol::queue::{OutputQueue, Prioritized};
+use crate::protocol::{EventLog, StateMachine};
+use crate::role::SwapRole;
+use crate::swap::SwapId;
+use crate::transaction::TxLabel;
+
+/// Errors when managing sessions.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error<E: Debug> {
+    /// No session is running for the swap.
+    #[error("Unknown session {0}")]
+    UnknownSession(SwapId),
+    /// A session is already running for the swap.
+    #[error("Session {0} already exists")]
+    DuplicateSession(SwapId),
+    /// The peer reached its maximum number of running sessions.
+    #[error("Too many sessions with the peer")]
+    PeerLimitReached,
+    /// The session is over and does not accept any input.
+    #[error("Session {0} is terminated")]
+    SessionTerminated(SwapId),
+    /// The state machine rejected the input.
+    #[error("Transition failed: {0:?}")]
+    Transition(E),
+    /// The entropy beacon is computed for another swap or differs from the one already set.
+    #[error("Invalid entropy beacon for session {0}")]
+    InvalidBeacon(SwapId),
+}
+
+/// A live swap with a peer.
+#[derive(Debug, Clone)]
+pub struct Session<M: StateMachine, P, F = u64> {
+    swap_id: SwapId,
+    peer: P,
+    state: M::State,
+    log: EventLog<M>,
+    beacon: Option<EntropyBeacon>,
+    fees: FeeLedger<F>,
+    padding: Option<Padding>,
+}
+
+impl<M, P, F> Session<M, P, F>
+where
+    M: StateMachine,
+{
+    /// Create a new session in the initial state.
+    pub fn new(swap_id: SwapId, peer: P, initial_state: M::State) -> Self {
+        Self {
+            swap_id,
+            peer,
+            state: initial_state,
+            log: EventLog::new(),
+            beacon: None,
+            fees: FeeLedger::new(),
+            padding: None,
+        }
+    }
+
+    /// Pad the messages of the session with the padding scheme, see [`Session::encode_message`].
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Return the padding scheme applied on the messages of the session, if any.
+    pub fn padding(&self) -> Option<&Padding> {
+        self.padding.as_ref()
+    }
+
+    /// Encode a message to send to the counter-party, the encoded message is padded if the
+    /// session has a padding scheme.
+    pub fn encode_message<T>(&self, msg: &T) -> Result<Vec<u8>, consensus::Error>
+    where
+        T: Encodable + Debug,
+    {
+        match &self.padding {
+            Some(padding) => serialize_padded(msg, padding),
+            None => Ok(serialize(msg)),
+        }
+    }
+
+    /// Decode a message received from the counter-party, the message must be padded if the
+    /// session has a padding scheme.
+    pub fn decode_message<T: Decodable>(&self, data: &[u8]) -> Result<T, consensus::Error> {
+        match self.padding {
+            Some(_) => deserialize_padded(data),
+            None => deserialize(data),
+        }
+    }
+
+    /// Return the identifier of the swap.
+    pub fn swap_id(&self) -> SwapId {
+        self.swap_id
+    }
+
+    /// Return the peer the swap runs with.
+    pub fn peer(&self) -> &P {
+        &self.peer
+    }
+
+    /// Return the current state of the swap.
+    pub fn state(&self) -> &M::State {
+        &self.state
+    }
+
+    /// Return the log of the transitions executed by the session.
+    pub fn log(&self) -> &EventLog<M> {
+        &self.log
+    }
+
+    /// Set the entropy beacon of the swap once both reveals are verified, e.g. when receiving the
+    /// counter-party reveal or when restoring the session from a checkpoint. Setting the same
+    /// beacon again is a no-op.
+    pub fn set_beacon(&mut self, beacon: EntropyBeacon) -> Result<(), Error<M::Error>> {
+        match self.beacon {
+            _ if beacon.swap_id() != self.swap_id => Err(Error::InvalidBeacon(self.swap_id)),
+            Some(current) if current != beacon => Err(Error::InvalidBeacon(self.swap_id)),
+            _ => {
+                self.beacon = Some(beacon);
+                Ok(())
+            }
+        }
+    }
+
+    /// Return the entropy beacon of the swap, if both participants revealed their parameters.
+    pub fn beacon(&self) -> Option<&EntropyBeacon> {
+        self.beacon.as_ref()
+    }
+
+    /// Derive the randomness of the swap for the label, known by both participants, see
+    /// [`EntropyBeacon::entropy`]. Returns `None` until the beacon is set.
+    pub fn entropy(&self, label: &str) -> Option<[u8; 32]> {
+        self.beacon.map(|beacon| beacon.entropy(label))
+    }
+
+    /// Derive the private randomness of the swap for the label with the participant secret, see
+    /// [`EntropyBeacon::keyed_entropy`]. Returns `None` until the beacon is set.
+    pub fn keyed_entropy(&self, secret: &[u8], label: &str) -> Option<[u8; 32]> {
+        self.beacon
+            .map(|beacon| beacon.keyed_entropy(secret, label))
+    }
+
+    /// Return the fees set on the transactions of the swap.
+    pub fn fees(&self) -> &FeeLedger<F> {
+        &self.fees
+    }
+
+    /// Return the fees set on the transactions of the swap to record new transactions, e.g. with
+    /// [`FeeLedger::apply`] when building them.
+    pub fn fees_mut(&mut self) -> &mut FeeLedger<F> {
+        &mut self.fees
+    }
+
+    /// Return true if the session reached a terminal state.
+    pub fn is_terminal(&self) -> bool {
+        M::is_terminal(&self.state)
+    }
+
+    /// Execute the transition triggered by the input and return the outputs, the state is left
+    /// unchanged if the transition fails.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(swap_id = %self.swap_id))
+    )]
+    pub fn handle(&mut self, input: M::Input) -> Result<Vec<M::Output>, Error<M::Error>> {
+        if self.is_terminal() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("input received by a terminated session");
+            return Err(Error::SessionTerminated(self.swap_id));
+        }
+        let start = Instant::now();
+        match self.log.execute(&self.state, input) {
+            Ok((state, outputs)) => {
+                self.state = state;
+                self.observe(start, true, outputs.len());
+                Ok(outputs)
+            }
+            Err(error) => {
+                self.observe(start, false, 0);
+                Err(Error::Transition(error))
+            }
+        }
+    }
+
+    fn observe(&self, start: Instant, accepted: bool, outputs: usize) {
+        #[cfg(feature = "tracing")]
+        match accepted {
+            true => tracing::debug!(
+                outputs,
+                terminal = self.is_terminal(),
+                "transition executed"
+            ),
+            false => tracing::warn!(state = ?self.state, "transition rejected"),
+        }
+        observer().on_transition(&TransitionEvent {
+            swap_id: self.swap_id,
+            accepted,
+            outputs,
+            terminal: self.is_terminal(),
+            elapsed: start.elapsed(),
+        });
+    }
+}
+
+/// The aggregate progress of the sessions of a manager.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress<F = u64> {
+    /// The number of sessions still running.
+    pub running: usize,
+    /// The number of sessions in a terminal state.
+    pub terminated: usize,
+    /// The number of transitions executed by all the sessions.
+    pub transitions: usize,
+    /// The fees set on the transactions of all the sessions, see [`FeeLedger::total`].
+    pub fees: F,
+}
+
+/// Owns the sessions of a daemon and routes the inputs to them.
+#[derive(Debug, Clone)]
+pub struct SessionManager<M: StateMachine, P, F = u64> {
+    sessions: HashMap<SwapId, Session<M, P, F>>,
+    loopbacks: HashMap<SwapId, Loopback<M, P, F>>,
+    max_sessions_per_peer: usize,
+    padding: Option<Padding>,
+}
+
+impl<M, P, F> SessionManager<M, P, F>
+where
+    M: StateMachine,
+    P: Clone + Eq + Hash,
+{
+    /// Create a manager allowing at most `max_sessions_per_peer` running sessions with the same
+    /// peer, terminated sessions are not counted.
+    pub fn new(max_sessions_per_peer: usize) -> Self {
+        Self {
+            sessions: HashMap::new(),
+            loopbacks: HashMap::new(),
+            max_sessions_per_peer,
+            padding: None,
+        }
+    }
+
+    /// Pad the messages of the sessions opened afterwards with the padding scheme, loopback swaps
+    /// do not use the transport and are never padded.
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Start a new session with a peer.
+    pub fn open(
+        &mut self,
+        swap_id: SwapId,
+        peer: P,
+        initial_state: M::State,
+    ) -> Result<&mut Session<M, P, F>, Error<M::Error>> {
+        self.check_open(swap_id, &peer, 1)?;
+        let mut session = Session::new(swap_id, peer, initial_state);
+        session.padding = self.padding.clone();
+        Ok(self.sessions.entry(swap_id).or_insert(session))
+    }
+
+    /// Start the sessions of both roles of a loopback swap, the two sessions count toward the
+    /// limit of the peer.
+    pub fn open_loopback(
+        &mut self,
+        swap_id: SwapId,
+        peer: P,
+        alice: M::State,
+        bob: M::State,
+    ) -> Result<&mut Loopback<M, P, F>, Error<M::Error>> {
+        self.check_open(swap_id, &peer, 2)?;
+        Ok(self
+            .loopbacks
+            .entry(swap_id)
+            .or_insert_with(|| Loopback::new(swap_id, peer, alice, bob)))
+    }
+
+    fn check_open(&self, swap_id: SwapId, peer: &P, count: usize) -> Result<(), Error<M::Error>> {
+        if self.sessions.contains_key(&swap_id) || self.loopbacks.contains_key(&swap_id) {
+            return Err(Error::DuplicateSession(swap_id));
+        }
+        if self.running_with(peer) + count > self.max_sessions_per_peer {
+            return Err(Error::PeerLimitReached);
+        }
+        Ok(())
+    }
+
+    /// Route an input to the session of the swap and return the outputs of the transition.
+    pub fn route(
+        &mut self,
+        swap_id: SwapId,
+        input: M::Input,
+    ) -> Result<Vec<M::Output>, Error<M::Error>> {
+        self.sessions
+            .get_mut(&swap_id)
+            .ok_or(Error::UnknownSession(swap_id))?
+            .handle(input)
+    }
+
+    /// Route an input to the session of the role in a loopback swap, see [`Loopback::handle`].
+    pub fn route_loopback(
+        &mut self,
+        swap_id: SwapId,
+        role: SwapRole,
+        input: M::Input,
+    ) -> Result<LoopbackOutputs<M>, Error<M::Error>>
+    where
+        M: LoopbackMachine,
+    {
+        self.loopbacks
+            .get_mut(&swap_id)
+            .ok_or(Error::UnknownSession(swap_id))?
+            .handle(role, input)
+    }
+
+    /// Route an input to the session of the swap and queue the outputs of the transition, tagged
+    /// with the swap identifier, to be released by priority.
+    pub fn route_queued(
+        &mut self,
+        swap_id: SwapId,
+        input: M::Input,
+        queue: &mut OutputQueue<(SwapId, M::Output)>,
+    ) -> Result<(), Error<M::Error>>
+    where
+        M::Output: Prioritized,
+    {
+        let outputs = self.route(swap_id, input)?;
+        queue.extend(outputs.into_iter().map(|output| (swap_id, output)));
+        Ok(())
+    }
+
+    /// Return the session of the swap, if any.
+    pub fn session(&self, swap_id: &SwapId) -> Option<&Session<M, P, F>> {
+        self.sessions.get(swap_id)
+    }
+
+    /// Return the loopback swap, if any.
+    pub fn loopback(&self, swap_id: &SwapId) -> Option<&Loopback<M, P, F>> {
+        self.loopbacks.get(swap_id)
+    }
+
+    /// Record the fee of a transaction in the ledger of the session of the swap and return the fee
+    /// previously recorded for the transaction, if any, see [`FeeLedger::record`].
+    pub fn record_fee(
+        &mut self,
+        swap_id: SwapId,
+        label: TxLabel,
+        fee: F,
+    ) -> Result<Option<F>, Error<M::Error>> {
+        Ok(self
+            .sessions
+            .get_mut(&swap_id)
+            .ok_or(Error::UnknownSession(swap_id))?
+            .fees_mut()
+            .record(label, fee))
+    }
+
+    /// Remove the session of the swap and return it, e.g. once terminated and persisted.
+    pub fn close(&mut self, swap_id: &SwapId) -> Option<Session<M, P, F>> {
+        self.sessions.remove(swap_id)
+    }
+
+    /// Remove the loopback swap and return it.
+    pub fn close_loopback(&mut self, swap_id: &SwapId) -> Option<Loopback<M, P, F>> {
+        self.loopbacks.remove(swap_id)
+    }
+
+    /// Return an iterator over all the sessions, the sessions of loopback swaps included.
+    pub fn sessions(&self) -> impl Iterator<Item = &Session<M, P, F>> {
+        self.sessions
+            .values()
+            .chain(self.loopbacks.values().flat_map(Loopback::sessions))
+    }
+
+    /// Return the number of sessions still running with the peer.
+    pub fn running_with(&self, peer: &P) -> usize {
+        self.sessions()
+            .filter(|session| session.peer() == peer && !session.is_terminal())
+            .count()
+    }
+
+    /// Return the aggregate progress of all the sessions.
+    pub fn progress(&self) -> Progress<F>
+    where
+        F: Copy + Default + Add<Output = F>,
+    {
+        self.sessions()
+            .fold(Progress::default(), |mut progress, session| {
+                match session.is_terminal() {
+                    true => progress.terminated += 1,
+                    false => progress.running += 1,
+                }
+                progress.transitions += session.log().len();
+                progress.fees = progress.fees + session.fees().total();
+                progress
+            })
+    }
+}
@@ -0,0 +1,354 @@
+//! Deterministic simulation of a swap with injected failures.
+//!
+//! A [`Simulation`] runs both roles of a swap in [`Loopback`] against a [`MockSyncer`] chain. The
+//! protocol messages are delivered in memory, the transactions broadcasted by a role are added to
+//! the mempool and watched by both roles, and every block mined by the test is reported to the
+//! roles as [`ChainEvent`]s.
+//!
+//! [`Fault`]s are injected on the way: a message is dropped or its signature corrupted before
+//! delivery, a transaction is held back from the mempool so its confirmation arrives past a
+//! deadline, or the chain is reorged. Each fault is triggered once, in the order of the messages,
+//! broadcasts, and blocks of the simulation, so a failure path of the state machine is reproduced
+//! identically on every run and the tests assert on the resulting terminal states.
+//!
+//! Inputs rejected by a role are recorded instead of stopping the simulation, as a daemon ignores
+//! an invalid message, see [`Simulation::rejected`].
+
+use std::collections::VecDeque;
+
+use crate::protocol::loopback::{Loopback, LoopbackMachine, LoopbackOutputs};
+use crate::protocol::session::{Error, Session};
+use crate::role::SwapRole;
+use crate::swap::SwapId;
+use crate::syncer::mock::MockSyncer;
+use crate::syncer::{
+    BroadcastTransaction, Event, HeightChanged, Syncer, TaskId, TaskIdAllocator,
+    TransactionConfirmations, WatchHeight, WatchTransaction,
+};
+use crate::transaction::TxLabel;
+
+/// A change of the chain reported to the roles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainEvent {
+    /// The chain reached a new height, lower than the previous one after a reorg.
+    Height(u64),
+    /// The number of confirmations of a broadcasted transaction changed, `0` when back in the
+    /// mempool after a reorg.
+    Confirmations {
+        label: TxLabel,
+        tx: Vec<u8>,
+        confirmations: i32,
+    },
+}
+
+/// A failure injected in the simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Drop the n-th protocol message exchanged by the roles, counted from zero in delivery order.
+    DropMessage(usize),
+    /// Corrupt the signature of the n-th protocol message exchanged by the roles, see
+    /// [`SimulatedMachine::corrupt`].
+    CorruptSignature(usize),
+    /// Hold back the n-th broadcasted transaction, counted from zero, from the mempool during
+    /// `blocks` blocks.
+    DelayConfirmation { broadcast: usize, blocks: u64 },
+    /// Remove the last `depth` blocks once the chain reaches `height`.
+    Reorg { height: u64, depth: u64 },
+}
+
+/// State machines able to run in a simulation against a mock chain.
+pub trait SimulatedMachine: LoopbackMachine {
+    /// Return the label and the transaction to broadcast if the output is a broadcast, the
+    /// transaction is also its identifier on the mock chain.
+    fn broadcast(output: &Self::Output) -> Option<(TxLabel, Vec<u8>)>;
+
+    /// Return the input notifying a role of a change of the chain, `None` if the role ignores it.
+    fn on_chain(event: &ChainEvent) -> Option<Self::Input>;
+
+    /// Return the message with an invalid signature, `None` if the message carries no signature,
+    /// in which case it is delivered unchanged.
+    fn corrupt(message: &Self::Input) -> Option<Self::Input>;
+}
+
+/// Both roles of a swap running against a mock chain with injected faults.
+#[derive(Debug)]
+pub struct Simulation<M: SimulatedMachine> {
+    loopback: Loopback<M, ()>,
+    syncer: MockSyncer,
+    tasks: TaskIdAllocator,
+    heights: Vec<(TaskId, SwapRole)>,
+    watches: Vec<(TaskId, SwapRole, Vec<u8>)>,
+    faults: Vec<Fault>,
+    delayed: Vec<(u64, TxLabel, Vec<u8>)>,
+    messages: usize,
+    broadcasts: usize,
+    rejected: Vec<(SwapRole, Error<M::Error>)>,
+}
+
+impl<M> Simulation<M>
+where
+    M: SimulatedMachine,
+{
+    /// Create a simulation with both roles in their initial state on an empty chain.
+    pub fn new(swap_id: SwapId, alice: M::State, bob: M::State) -> Self {
+        let mut simulation = Self {
+            loopback: Loopback::new(swap_id, (), alice, bob),
+            syncer: MockSyncer::new(|tx| tx.to_vec()),
+            tasks: TaskIdAllocator::new(),
+            heights: vec![],
+            watches: vec![],
+            faults: vec![],
+            delayed: vec![],
+            messages: 0,
+            broadcasts: 0,
+            rejected: vec![],
+        };
+        for role in [SwapRole::Alice, SwapRole::Bob].iter() {
+            let id = simulation
+                .tasks
+                .allocate()
+                .expect("task identifiers are not exhausted");
+            simulation
+                .syncer
+                .watch_height(WatchHeight {
+                    id,
+                    lifetime: u64::MAX,
+                    addendum: vec![],
+                })
+                .expect("mock syncer does not fail");
+            simulation.heights.push((id, *role));
+        }
+        simulation
+    }
+
+    /// Inject a fault in the simulation.
+    pub fn with_fault(mut self, fault: Fault) -> Self {
+        self.faults.push(fault);
+        self
+    }
+
+    /// Execute the input on the session of the role and run the simulation until no message or
+    /// chain event is left. Return the outputs not handled by the simulation with the role
+    /// producing them, broadcasts included, in order.
+    pub fn handle(&mut self, role: SwapRole, input: M::Input) -> LoopbackOutputs<M> {
+        let mut inputs = VecDeque::new();
+        inputs.push_back((role, input));
+        self.run(inputs)
+    }
+
+    /// Mine `count` blocks one by one, triggering the chain faults and running the simulation
+    /// after each block. Return the outputs produced, see [`Simulation::handle`].
+    pub fn mine_blocks(&mut self, count: u64) -> LoopbackOutputs<M> {
+        let mut outputs = LoopbackOutputs::<M>::new();
+        for _ in 0..count {
+            self.syncer.mine_block();
+            outputs.extend(self.run(VecDeque::new()));
+            let height = self.syncer.height();
+            if let Some(depth) = self.take_fault(|fault| match fault {
+                Fault::Reorg { height: h, depth } if *h == height => Some(*depth),
+                _ => None,
+            }) {
+                self.syncer.reorg(depth);
+                outputs.extend(self.run(VecDeque::new()));
+            }
+            let height = self.syncer.height();
+            let (released, delayed) = self
+                .delayed
+                .drain(..)
+                .partition(|(release, _, _)| *release <= height);
+            self.delayed = delayed;
+            for (_, label, tx) in released {
+                self.submit(label, tx);
+            }
+        }
+        outputs
+    }
+
+    /// Return the session of the role.
+    pub fn session(&self, role: SwapRole) -> &Session<M, ()> {
+        self.loopback.session(role)
+    }
+
+    /// Return the mock chain of the simulation.
+    pub fn syncer(&self) -> &MockSyncer {
+        &self.syncer
+    }
+
+    /// Return the inputs rejected by the roles with the error, in order.
+    pub fn rejected(&self) -> &[(SwapRole, Error<M::Error>)] {
+        &self.rejected
+    }
+
+    /// Return the faults not triggered yet.
+    pub fn pending_faults(&self) -> &[Fault] {
+        &self.faults
+    }
+
+    /// Panic if the session of the role is not terminated in the expected state.
+    pub fn assert_terminal(&self, role: SwapRole, expected: &M::State) {
+        let session = self.session(role);
+        assert!(
+            session.is_terminal(),
+            "{} is not in a terminal state: {:?}",
+            role,
+            session.state()
+        );
+        assert_eq!(
+            session.state(),
+            expected,
+            "{} terminated in an unexpected state",
+            role
+        );
+    }
+
+    fn run(&mut self, mut inputs: VecDeque<(SwapRole, M::Input)>) -> LoopbackOutputs<M> {
+        let mut outputs = LoopbackOutputs::<M>::new();
+        let mut events = VecDeque::new();
+        loop {
+            while let Some((role, input)) = inputs.pop_front() {
+                let produced = match self.loopback.session_mut(role).handle(input) {
+                    Ok(produced) => produced,
+                    Err(error) => {
+                        self.rejected.push((role, error));
+                        continue;
+                    }
+                };
+                for output in produced {
+                    if let Some(message) = M::deliver(&output) {
+                        if let Some(message) = self.inject(message) {
+                            inputs.push_back((role.other(), message));
+                        }
+                        continue;
+                    }
+                    if let Some((label, tx)) = M::broadcast(&output) {
+                        self.broadcast(label, tx);
+                    }
+                    outputs.push((role, output));
+                }
+            }
+            if events.is_empty() {
+                events.extend(self.chain_events());
+            }
+            // Chain events are delivered one by one, to the roles still running
+            match events.pop_front() {
+                Some((role, event)) => {
+                    if !self.loopback.session(role).is_terminal() {
+                        inputs.extend(M::on_chain(&event).map(|input| (role, input)));
+                    }
+                }
+                None => return outputs,
+            }
+        }
+    }
+
+    // Apply the message faults to the next delivered message
+    fn inject(&mut self, message: M::Input) -> Option<M::Input> {
+        let index = self.messages;
+        self.messages += 1;
+        if self
+            .take_fault(|fault| match fault {
+                Fault::DropMessage(n) if *n == index => Some(()),
+                _ => None,
+            })
+            .is_some()
+        {
+            return None;
+        }
+        match self.take_fault(|fault| match fault {
+            Fault::CorruptSignature(n) if *n == index => Some(()),
+            _ => None,
+        }) {
+            Some(()) => Some(M::corrupt(&message).unwrap_or(message)),
+            None => Some(message),
+        }
+    }
+
+    // Watch the transaction for both roles and add it to the mempool, unless held back
+    fn broadcast(&mut self, label: TxLabel, tx: Vec<u8>) {
+        let index = self.broadcasts;
+        self.broadcasts += 1;
+        if !self.watches.iter().any(|(_, _, watched)| *watched == tx) {
+            for role in [SwapRole::Alice, SwapRole::Bob].iter() {
+                let id = self
+                    .tasks
+                    .allocate()
+                    .expect("task identifiers are not exhausted");
+                self.syncer
+                    .watch_transaction(WatchTransaction {
+                        id,
+                        label,
+                        lifetime: u64::MAX,
+                        hash: tx.clone(),
+                        confirmation_bound: u16::MAX,
+                    })
+                    .expect("mock syncer does not fail");
+                self.watches.push((id, *role, tx.clone()));
+            }
+        }
+        match self.take_fault(|fault| match fault {
+            Fault::DelayConfirmation { broadcast, blocks } if *broadcast == index => Some(*blocks),
+            _ => None,
+        }) {
+            Some(blocks) => self
+                .delayed
+                .push((self.syncer.height() + blocks, label, tx)),
+            None => self.submit(label, tx),
+        }
+    }
+
+    fn submit(&mut self, label: TxLabel, tx: Vec<u8>) {
+        let id = self
+            .tasks
+            .allocate()
+            .expect("task identifiers are not exhausted");
+        self.syncer
+            .broadcast_transaction(BroadcastTransaction { id, label, tx })
+            .expect("mock syncer does not fail");
+    }
+
+    // Translate the syncer events into chain events for the roles watching them
+    fn chain_events(&mut self) -> Vec<(SwapRole, ChainEvent)> {
+        let events = self.syncer.poll().expect("mock syncer does not fail");
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::HeightChanged(HeightChanged { id, height, .. }) => self
+                    .heights
+                    .iter()
+                    .find(|(task, _)| *task == id)
+                    .map(|(_, role)| (*role, ChainEvent::Height(height))),
+                Event::TransactionConfirmations(TransactionConfirmations {
+                    id,
+                    label,
+                    confirmations,
+                    ..
+                }) => self
+                    .watches
+                    .iter()
+                    .find(|(task, _, _)| *task == id)
+                    .map(|(_, role, tx)| {
+                        (
+                            *role,
+                            ChainEvent::Confirmations {
+                                label,
+                                tx: tx.clone(),
+                                confirmations,
+                            },
+                        )
+                    }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Remove and return the first pending fault matching
+    fn take_fault<T>(&mut self, matching: impl Fn(&Fault) -> Option<T>) -> Option<T> {
+        let (index, value) = self
+            .faults
+            .iter()
+            .enumerate()
+            .find_map(|(index, fault)| matching(fault).map(|value| (index, value)))?;
+        self.faults.remove(index);
+        Some(value)
+    }
+}
@@ -2,20 +2,35 @@
 
 use std::io;
 
-use crate::blockchain::{Address, Onchain};
+#[cfg(feature = "lightning")]
+use lightning_encoding::{LightningDecode, LightningEncode};
+
+use crate::blockchain::{
+    Address, AddressAllowlist, AddressScript, Network, Onchain, PaymentProof, RawTransaction,
+};
 use crate::bundle;
-use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::consensus::{
+    self, decode_option, encode_option, Annotate, Annotator, CanonicalBytes, CountingReader,
+    Decodable, Encodable, UnknownCode,
+};
+use crate::crypto::merkle::{self, MerkleProof};
 use crate::crypto::{
     self, Commit, Keys, SharedKeyId, SharedPrivateKeys, Signatures, TaggedElement,
 };
+use crate::describe::{Describe, Description, Kind};
+use crate::negotiation::{self, BlindedPublicOffer, OfferOpening, PublicOffer};
+use crate::observer::observer;
+use crate::protocol::position::StateDigest;
+use crate::role::SwapRole;
+use crate::settlement::SwapPhase;
 use crate::swap::Swap;
 use crate::Error;
 
 fn commit_to_vec<T: Clone + Eq, K: CanonicalBytes, C: Clone + Eq>(
     wallet: &impl Commit<C>,
-    keys: &Vec<TaggedElement<T, K>>,
+    keys: &[TaggedElement<T, K>],
 ) -> Vec<TaggedElement<T, C>> {
-    keys.into_iter()
+    keys.iter()
         .map(|tagged_key| {
             TaggedElement::new(
                 tagged_key.tag().clone(),
@@ -28,9 +43,9 @@ fn commit_to_vec<T: Clone + Eq, K: CanonicalBytes, C: Clone + Eq>(
 fn verify_vec_of_commitments<T: Eq, K: CanonicalBytes, C: Clone + Eq>(
     wallet: &impl Commit<C>,
     keys: Vec<TaggedElement<T, K>>,
-    commitments: &Vec<TaggedElement<T, C>>,
+    commitments: &[TaggedElement<T, C>],
 ) -> Result<(), Error> {
-    keys.into_iter()
+    keys.iter()
         .map(|tagged_key| {
             commitments
                 .iter()
@@ -41,7 +56,7 @@ fn verify_vec_of_commitments<T: Eq, K: CanonicalBytes, C: Clone + Eq>(
                             tagged_key.elem().as_canonical_bytes(),
                             tagged_commitment.elem().clone(),
                         )
-                        .map_err(|e| Error::Crypto(e))
+                        .map_err(Error::Crypto)
                 })
                 .ok_or(Error::Crypto(crypto::Error::InvalidCommitment))
         })
@@ -49,6 +64,62 @@ fn verify_vec_of_commitments<T: Eq, K: CanonicalBytes, C: Clone + Eq>(
         .map(|_| ())
 }
 
+/// A protocol message revealing an arbitrating address of its sender, Alice's destination address
+/// or Bob's refund address. The address MUST be validated when the message is received, before
+/// any transaction paying to it is built.
+pub trait RevealedAddress<Ctx: Swap> {
+    /// Return the address revealed in the message.
+    fn revealed_address(&self) -> &<Ctx::Ar as Address>::Address;
+
+    /// Verify that the revealed address is of a known script type allowed by the allowlist, funds
+    /// should never be sent to unspendable or non-standard scripts.
+    fn verify_address(&self, allowlist: &AddressAllowlist<Ctx::Ar>) -> Result<(), Error> {
+        Ok(allowlist.validate(self.revealed_address())?)
+    }
+
+    /// Validate the revealed address when processing the message: the address must be valid on
+    /// the network of the swap and allowed by the allowlist. Return the abort message to send to
+    /// the counter-party otherwise, so the swap is rejected before any transaction is built.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn validate_address(
+        &self,
+        network: Network,
+        allowlist: &AddressAllowlist<Ctx::Ar>,
+    ) -> Result<(), Abort> {
+        <Ctx::Ar as AddressScript>::validate_network(self.revealed_address(), network)
+            .and_then(|_| allowlist.validate(self.revealed_address()))
+            .map_err(|e| Abort::new(AbortReason::ValidationFailure).with_body(e.to_string()))
+    }
+}
+
+/// A protocol message revealing the opening of the blinded offer the swap was taken from, sent
+/// by the maker to the taker. The opening MUST be verified against the blinded offer when the
+/// message is received, before the amounts are used.
+pub trait RevealedOffer<Ctx: Swap> {
+    /// Return the opening revealed in the message, if any.
+    fn offer_opening(&self) -> Option<&OfferOpening<Ctx>>;
+
+    /// Open the blinded public offer with the revealed opening and return the unblinded public
+    /// offer. Fails if the opening is missing or does not match the blinded offer commitments.
+    fn open_offer(&self, offer: &BlindedPublicOffer<Ctx>) -> Result<PublicOffer<Ctx>, Error> {
+        let opening = self
+            .offer_opening()
+            .ok_or(negotiation::Error::InvalidOpening)?;
+        Ok(offer.open(opening)?)
+    }
+
+    /// Verify the revealed opening when processing the message. Return the abort message to send
+    /// to the counter-party otherwise, so the swap is rejected before the amounts are used.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn validate_offer_opening(
+        &self,
+        offer: &BlindedPublicOffer<Ctx>,
+    ) -> Result<PublicOffer<Ctx>, Abort> {
+        self.open_offer(offer)
+            .map_err(|e| Abort::new(AbortReason::ValidationFailure).with_body(e.to_string()))
+    }
+}
+
 /// `commit_alice_session_params` forces Alice to commit to the result of her cryptographic setup
 /// before receiving Bob's setup. This is done to remove adaptive behavior.
 #[derive(Clone, Debug)]
@@ -97,6 +168,10 @@ where
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
     pub fn verify_with_reveal(
         &self,
         wallet: &impl Commit<Ctx::Commitment>,
@@ -170,6 +245,29 @@ where
 }
 
 impl_strict_encoding!(CommitAliceParameters<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(CommitAliceParameters<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    CommitAliceParameters<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x01),
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Setup),
+    {
+        buy, cancel, refund, punish, adaptor, extra_arbitrating_keys, arbitrating_shared_keys,
+        spend, extra_accordant_keys, accordant_shared_keys
+    }
+);
+
+impl_annotate!(
+    CommitAliceParameters<Ctx>,
+    [Ctx: Swap],
+    {
+        canonical buy, canonical cancel, canonical refund, canonical punish, canonical adaptor,
+        decode extra_arbitrating_keys, decode arbitrating_shared_keys, canonical spend,
+        decode extra_accordant_keys, decode accordant_shared_keys
+    }
+);
 
 /// `commit_bob_session_params` forces Bob to commit to the result of his cryptographic setup
 /// before receiving Alice's setup. This is done to remove adaptive behavior.
@@ -193,6 +291,8 @@ pub struct CommitBobParameters<Ctx: Swap> {
     pub extra_accordant_keys: Vec<TaggedElement<u16, Ctx::Commitment>>,
     /// Commitments to the accordant shared keys
     pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, Ctx::Commitment>>,
+    /// Merkle root of the refund addresses Bob may reveal, if pre-committed
+    pub refund_addresses_root: Option<[u8; 32]>,
 }
 
 impl<Ctx> CommitBobParameters<Ctx>
@@ -213,14 +313,35 @@ where
             spend: wallet.commit_to(bundle.spend.as_canonical_bytes()),
             extra_accordant_keys: commit_to_vec(wallet, &bundle.extra_accordant_keys),
             accordant_shared_keys: commit_to_vec(wallet, &bundle.accordant_shared_keys),
+            refund_addresses_root: None,
         }
     }
 
+    /// Pre-commit to a set of refund addresses, only the one used is revealed with a proof of
+    /// inclusion, see [`RevealBobParameters::with_refund_address_proof`]. Rotating refund
+    /// addresses across retried swaps with the same counter-party avoids linking them.
+    pub fn with_refund_addresses(mut self, addresses: &[<Ctx::Ar as Address>::Address]) -> Self {
+        let leaves: Vec<Vec<u8>> = addresses.iter().map(|a| a.as_canonical_bytes()).collect();
+        self.refund_addresses_root = merkle::merkle_root(&leaves);
+        self
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
     pub fn verify_with_reveal(
         &self,
         wallet: &impl Commit<Ctx::Commitment>,
         reveal: RevealBobParameters<Ctx>,
     ) -> Result<(), Error> {
+        // The revealed refund address must be part of the pre-committed set, if any
+        match (&self.refund_addresses_root, &reveal.refund_address_proof) {
+            (Some(root), Some(proof))
+                if proof.verify(root, reveal.address.as_canonical_bytes()) => {}
+            (None, None) => (),
+            _ => return Err(Error::Crypto(crypto::Error::InvalidCommitment)),
+        }
         wallet.validate(reveal.buy.as_canonical_bytes(), self.buy.clone())?;
         wallet.validate(reveal.cancel.as_canonical_bytes(), self.cancel.clone())?;
         wallet.validate(reveal.refund.as_canonical_bytes(), self.refund.clone())?;
@@ -262,7 +383,8 @@ where
         len += self.arbitrating_shared_keys.consensus_encode(s)?;
         len += self.spend.as_canonical_bytes().consensus_encode(s)?;
         len += self.extra_accordant_keys.consensus_encode(s)?;
-        Ok(len + self.accordant_shared_keys.consensus_encode(s)?)
+        len += self.accordant_shared_keys.consensus_encode(s)?;
+        Ok(len + encode_option(&self.refund_addresses_root, s)?)
     }
 }
 
@@ -281,11 +403,35 @@ where
             spend: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
             extra_accordant_keys: Decodable::consensus_decode(d)?,
             accordant_shared_keys: Decodable::consensus_decode(d)?,
+            refund_addresses_root: decode_option(d)?,
         })
     }
 }
 
 impl_strict_encoding!(CommitBobParameters<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(CommitBobParameters<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    CommitBobParameters<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x02),
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Setup),
+    {
+        buy, cancel, refund, adaptor, extra_arbitrating_keys, arbitrating_shared_keys, spend,
+        extra_accordant_keys, accordant_shared_keys, refund_addresses_root
+    }
+);
+
+impl_annotate!(
+    CommitBobParameters<Ctx>,
+    [Ctx: Swap],
+    {
+        canonical buy, canonical cancel, canonical refund, canonical adaptor,
+        decode extra_arbitrating_keys, decode arbitrating_shared_keys, canonical spend,
+        decode extra_accordant_keys, decode accordant_shared_keys, optional refund_addresses_root
+    }
+);
 
 // TODO: Add more common data to reveal, e.g. help to ensure that both node uses the same value for
 // fee
@@ -320,6 +466,53 @@ pub struct RevealAliceParameters<Ctx: Swap> {
     pub address: <Ctx::Ar as Address>::Address,
     /// Reveal the cross-group discrete logarithm zero-knowledge proof
     pub proof: Ctx::Proof,
+    /// Merkle root of the destination addresses Alice may rotate to with [`UpdateBuyAddress`],
+    /// if pre-committed
+    pub destination_addresses_root: Option<[u8; 32]>,
+    /// Opening of the blinded offer if Alice is the maker of a blinded offer
+    pub offer_opening: Option<OfferOpening<Ctx>>,
+}
+
+impl<Ctx> RevealAliceParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Pre-commit to a set of destination addresses Alice may rotate to before the buy procedure
+    /// signature is produced, see [`UpdateBuyAddress`]. Without a pre-committed set the
+    /// destination address cannot be updated.
+    pub fn with_destination_addresses(
+        mut self,
+        addresses: &[<Ctx::Ar as Address>::Address],
+    ) -> Self {
+        let leaves: Vec<Vec<u8>> = addresses.iter().map(|a| a.as_canonical_bytes()).collect();
+        self.destination_addresses_root = merkle::merkle_root(&leaves);
+        self
+    }
+
+    /// Reveal the opening of the blinded offer to the taker when Alice is the maker, see
+    /// [`RevealedOffer`].
+    pub fn with_offer_opening(mut self, opening: OfferOpening<Ctx>) -> Self {
+        self.offer_opening = Some(opening);
+        self
+    }
+}
+
+impl<Ctx> RevealedAddress<Ctx> for RevealAliceParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    fn revealed_address(&self) -> &<Ctx::Ar as Address>::Address {
+        &self.address
+    }
+}
+
+impl<Ctx> RevealedOffer<Ctx> for RevealAliceParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    fn offer_opening(&self) -> Option<&OfferOpening<Ctx>> {
+        self.offer_opening.as_ref()
+    }
 }
 
 impl<Ctx> Encodable for RevealAliceParameters<Ctx>
@@ -338,7 +531,9 @@ where
         len += self.extra_accordant_keys.consensus_encode(s)?;
         len += self.accordant_shared_keys.consensus_encode(s)?;
         len += self.address.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len + self.proof.as_canonical_bytes().consensus_encode(s)?)
+        len += self.proof.as_canonical_bytes().consensus_encode(s)?;
+        len += encode_option(&self.destination_addresses_root, s)?;
+        Ok(len + encode_option(&self.offer_opening, s)?)
     }
 }
 
@@ -370,11 +565,38 @@ where
                 unwrap_vec_ref!(d).as_ref(),
             )?,
             proof: Ctx::Proof::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            destination_addresses_root: decode_option(d)?,
+            offer_opening: decode_option(d)?,
         })
     }
 }
 
 impl_strict_encoding!(RevealAliceParameters<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(RevealAliceParameters<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    RevealAliceParameters<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x03),
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Setup),
+    {
+        buy, cancel, refund, punish, adaptor, extra_arbitrating_keys, arbitrating_shared_keys,
+        spend, extra_accordant_keys, accordant_shared_keys, address, proof,
+        destination_addresses_root, offer_opening
+    }
+);
+
+impl_annotate!(
+    RevealAliceParameters<Ctx>,
+    [Ctx: Swap],
+    {
+        canonical buy, canonical cancel, canonical refund, canonical punish, canonical adaptor,
+        decode extra_arbitrating_keys, decode arbitrating_shared_keys, canonical spend,
+        decode extra_accordant_keys, decode accordant_shared_keys, canonical address,
+        canonical proof, optional destination_addresses_root, optional offer_opening
+    }
+);
 
 impl<Ctx> From<bundle::AliceParameters<Ctx>> for RevealAliceParameters<Ctx>
 where
@@ -394,6 +616,8 @@ where
             accordant_shared_keys: bundle.accordant_shared_keys,
             address: bundle.destination_address,
             proof: bundle.proof,
+            destination_addresses_root: None,
+            offer_opening: None,
         }
     }
 }
@@ -426,6 +650,53 @@ pub struct RevealBobParameters<Ctx: Swap> {
     pub address: <Ctx::Ar as Address>::Address,
     /// The cross-group discrete logarithm zero-knowledge proof
     pub proof: Ctx::Proof,
+    /// Proof of inclusion of the refund address in the pre-committed set, if any
+    pub refund_address_proof: Option<MerkleProof>,
+    /// Opening of the blinded offer if Bob is the maker of a blinded offer
+    pub offer_opening: Option<OfferOpening<Ctx>>,
+}
+
+impl<Ctx> RevealBobParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Add the proof of inclusion of the revealed refund address in the set pre-committed with
+    /// [`CommitBobParameters::with_refund_addresses`], `None` if the address is not in the set.
+    pub fn with_refund_address_proof(
+        mut self,
+        addresses: &[<Ctx::Ar as Address>::Address],
+    ) -> Option<Self> {
+        let leaves: Vec<Vec<u8>> = addresses.iter().map(|a| a.as_canonical_bytes()).collect();
+        let address = self.address.as_canonical_bytes();
+        let index = leaves.iter().position(|leaf| *leaf == address)?;
+        self.refund_address_proof = Some(MerkleProof::new(&leaves, index)?);
+        Some(self)
+    }
+
+    /// Reveal the opening of the blinded offer to the taker when Bob is the maker, see
+    /// [`RevealedOffer`].
+    pub fn with_offer_opening(mut self, opening: OfferOpening<Ctx>) -> Self {
+        self.offer_opening = Some(opening);
+        self
+    }
+}
+
+impl<Ctx> RevealedAddress<Ctx> for RevealBobParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    fn revealed_address(&self) -> &<Ctx::Ar as Address>::Address {
+        &self.address
+    }
+}
+
+impl<Ctx> RevealedOffer<Ctx> for RevealBobParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    fn offer_opening(&self) -> Option<&OfferOpening<Ctx>> {
+        self.offer_opening.as_ref()
+    }
 }
 
 impl<Ctx> Encodable for RevealBobParameters<Ctx>
@@ -443,7 +714,9 @@ where
         len += self.extra_accordant_keys.consensus_encode(s)?;
         len += self.accordant_shared_keys.consensus_encode(s)?;
         len += self.address.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len + self.proof.as_canonical_bytes().consensus_encode(s)?)
+        len += self.proof.as_canonical_bytes().consensus_encode(s)?;
+        len += encode_option(&self.refund_address_proof, s)?;
+        Ok(len + encode_option(&self.offer_opening, s)?)
     }
 }
 
@@ -472,11 +745,38 @@ where
                 unwrap_vec_ref!(d).as_ref(),
             )?,
             proof: Ctx::Proof::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            refund_address_proof: decode_option(d)?,
+            offer_opening: decode_option(d)?,
         })
     }
 }
 
 impl_strict_encoding!(RevealBobParameters<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(RevealBobParameters<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    RevealBobParameters<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x04),
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Setup),
+    {
+        buy, cancel, refund, adaptor, extra_arbitrating_keys, arbitrating_shared_keys, spend,
+        extra_accordant_keys, accordant_shared_keys, address, proof, refund_address_proof,
+        offer_opening
+    }
+);
+
+impl_annotate!(
+    RevealBobParameters<Ctx>,
+    [Ctx: Swap],
+    {
+        canonical buy, canonical cancel, canonical refund, canonical adaptor,
+        decode extra_arbitrating_keys, decode arbitrating_shared_keys, canonical spend,
+        decode extra_accordant_keys, decode accordant_shared_keys, canonical address,
+        canonical proof, optional refund_address_proof, optional offer_opening
+    }
+);
 
 impl<Ctx> From<bundle::BobParameters<Ctx>> for RevealBobParameters<Ctx>
 where
@@ -495,6 +795,8 @@ where
             accordant_shared_keys: bundle.accordant_shared_keys,
             address: bundle.refund_address,
             proof: bundle.proof,
+            refund_address_proof: None,
+            offer_opening: None,
         }
     }
 }
@@ -548,6 +850,22 @@ where
 }
 
 impl_strict_encoding!(CoreArbitratingSetup<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(CoreArbitratingSetup<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    CoreArbitratingSetup<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x05),
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Setup),
+    { lock, cancel, refund, cancel_sig }
+);
+
+impl_annotate!(
+    CoreArbitratingSetup<Ctx>,
+    [Ctx: Swap],
+    { canonical lock, canonical cancel, canonical refund, canonical cancel_sig }
+);
 
 impl<Ctx>
     From<(
@@ -614,6 +932,22 @@ where
 }
 
 impl_strict_encoding!(RefundProcedureSignatures<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(RefundProcedureSignatures<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    RefundProcedureSignatures<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x06),
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Setup),
+    { cancel_sig, refund_adaptor_sig }
+);
+
+impl_annotate!(
+    RefundProcedureSignatures<Ctx>,
+    [Ctx: Swap],
+    { canonical cancel_sig, canonical refund_adaptor_sig }
+);
 
 impl<Ctx>
     From<(
@@ -678,6 +1012,22 @@ where
 }
 
 impl_strict_encoding!(BuyProcedureSignature<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(BuyProcedureSignature<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    BuyProcedureSignature<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x07),
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Settlement),
+    { buy, buy_adaptor_sig }
+);
+
+impl_annotate!(
+    BuyProcedureSignature<Ctx>,
+    [Ctx: Swap],
+    { canonical buy, canonical buy_adaptor_sig }
+);
 
 impl<Ctx> From<bundle::SignedAdaptorBuy<Ctx::Ar>> for BuyProcedureSignature<Ctx>
 where
@@ -691,26 +1041,1201 @@ where
     }
 }
 
-/// `abort` is an `OPTIONAL` courtesy message from either swap partner to inform the counterparty
-/// that they have aborted the swap with an `OPTIONAL` message body to provide the reason.
+/// `cooperative_close_request` is sent by Bob to terminate the swap early, before the accordant
+/// lock, with a transaction spending the arbitrating `lock (b)` output back to his refund address
+/// through the success path, i.e. without waiting for the cancel timelock, and his signature.
+/// Alice MUST NOT co-sign it once she has locked her accordant assets.
+#[derive(Clone, Debug)]
+pub struct CooperativeCloseRequest<Ctx: Swap> {
+    /// The arbitrating cooperative close transaction
+    pub close: <Ctx::Ar as Onchain>::PartialTransaction,
+    /// The `Bb` cooperative close signature
+    pub close_sig: <Ctx::Ar as Signatures>::Signature,
+}
+
+impl<Ctx> Encodable for CooperativeCloseRequest<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.close.as_canonical_bytes().consensus_encode(s)?;
+        Ok(len + self.close_sig.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for CooperativeCloseRequest<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            close: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            close_sig: <Ctx::Ar as Signatures>::Signature::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+        })
+    }
+}
+
+impl_strict_encoding!(CooperativeCloseRequest<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(CooperativeCloseRequest<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    CooperativeCloseRequest<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x10),
+    Some(SwapRole::Bob),
+    Some(SwapPhase::Lock),
+    { close, close_sig }
+);
+
+impl_annotate!(
+    CooperativeCloseRequest<Ctx>,
+    [Ctx: Swap],
+    { canonical close, canonical close_sig }
+);
+
+impl<Ctx> From<bundle::SignedCooperativeClose<Ctx::Ar>> for CooperativeCloseRequest<Ctx>
+where
+    Ctx: Swap,
+{
+    fn from(bundle: bundle::SignedCooperativeClose<Ctx::Ar>) -> Self {
+        Self {
+            close: bundle.close,
+            close_sig: bundle.close_sig,
+        }
+    }
+}
+
+/// `cooperative_close_signature` is sent by Alice in response to a
+/// [`CooperativeCloseRequest`] with her signature on the cooperative close transaction. Upon
+/// reception Bob can finalize and broadcast the transaction.
 #[derive(Clone, Debug)]
+pub struct CooperativeCloseSignature<Ctx: Swap> {
+    /// The `Ab` cooperative close signature
+    pub close_sig: <Ctx::Ar as Signatures>::Signature,
+}
+
+impl<Ctx> Encodable for CooperativeCloseSignature<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.close_sig.as_canonical_bytes().consensus_encode(s)
+    }
+}
+
+impl<Ctx> Decodable for CooperativeCloseSignature<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            close_sig: <Ctx::Ar as Signatures>::Signature::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+        })
+    }
+}
+
+impl_strict_encoding!(CooperativeCloseSignature<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(CooperativeCloseSignature<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    CooperativeCloseSignature<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x11),
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Lock),
+    { close_sig }
+);
+
+impl_annotate!(
+    CooperativeCloseSignature<Ctx>,
+    [Ctx: Swap],
+    { canonical close_sig }
+);
+
+impl<Ctx> From<bundle::CosignedCooperativeClose<Ctx::Ar>> for CooperativeCloseSignature<Ctx>
+where
+    Ctx: Swap,
+{
+    fn from(bundle: bundle::CosignedCooperativeClose<Ctx::Ar>) -> Self {
+        Self {
+            close_sig: bundle.close_sig,
+        }
+    }
+}
+
+/// `state_checkpoint` is sent periodically by both participants when they activate
+/// [`FEATURE_CHECKPOINTS`](crate::negotiation::FEATURE_CHECKPOINTS). It contains the digest of
+/// the sender's position in the protocol signed with its arbitrating key, see
+/// [`PositionTracker`](crate::protocol::position::PositionTracker).
+#[derive(Clone, Debug)]
+pub struct StateCheckpoint<Ctx: Swap> {
+    /// The digest of the sender's position
+    pub digest: StateDigest,
+    /// The signature of the digest with the sender's arbitrating key
+    pub signature: <Ctx::Ar as Signatures>::Signature,
+}
+
+impl<Ctx> Encodable for StateCheckpoint<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.digest.consensus_encode(s)?;
+        Ok(len + self.signature.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for StateCheckpoint<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            digest: Decodable::consensus_decode(d)?,
+            signature: <Ctx::Ar as Signatures>::Signature::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+        })
+    }
+}
+
+impl_strict_encoding!(StateCheckpoint<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(StateCheckpoint<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    StateCheckpoint<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x12),
+    None,
+    None,
+    { digest, signature }
+);
+
+impl_annotate!(
+    StateCheckpoint<Ctx>,
+    [Ctx: Swap],
+    { decode digest, canonical signature }
+);
+
+/// The action a swap state machine takes when the counterparty aborts a swap with a given
+/// [`AbortReason`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AbortAction {
+    /// The swap may be retried with the same counterparty, e.g. with adjusted parameters.
+    Retry,
+    /// The swap is terminated and must not be retried with the same counterparty.
+    Terminate,
+}
+
+/// Structured reason of an [`Abort`], allowing automation to respond differently per abort class
+/// independently of the free text of the message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AbortReason {
+    /// No reason given.
+    #[default]
+    Unspecified,
+    /// The user requested to abort the swap.
+    UserRequested,
+    /// The participants disagree on the fees of the arbitrating transactions.
+    FeeDisagreement,
+    /// The participants disagree on the amounts or the price of the swap.
+    AmountDisagreement,
+    /// The counterparty did not answer in time.
+    Timeout,
+    /// The validation of a message, a signature, or a transaction sent by the counterparty failed.
+    ValidationFailure,
+    /// The counterparty sent an unexpected message for the current state of the swap.
+    ProtocolViolation,
+    /// An internal error occured on the aborting side.
+    InternalError,
+    /// A reason code unknown to this implementation, preserved when re-encoded.
+    Other(UnknownCode),
+}
+
+impl AbortReason {
+    /// Return the action to take when receiving an abort with this reason. Disagreements,
+    /// timeouts, and internal errors are retryable, every other reason is terminal.
+    pub fn action(&self) -> AbortAction {
+        match self {
+            AbortReason::FeeDisagreement
+            | AbortReason::AmountDisagreement
+            | AbortReason::Timeout
+            | AbortReason::InternalError => AbortAction::Retry,
+            AbortReason::Unspecified
+            | AbortReason::UserRequested
+            | AbortReason::ValidationFailure
+            | AbortReason::ProtocolViolation
+            | AbortReason::Other(_) => AbortAction::Terminate,
+        }
+    }
+
+    /// Return `true` if the swap may be retried after an abort with this reason.
+    pub fn is_retryable(&self) -> bool {
+        self.action() == AbortAction::Retry
+    }
+
+    /// Return the code of the reason as sent on the wire.
+    pub fn to_u16(&self) -> u16 {
+        match self {
+            AbortReason::Unspecified => 0x00,
+            AbortReason::UserRequested => 0x01,
+            AbortReason::FeeDisagreement => 0x02,
+            AbortReason::AmountDisagreement => 0x03,
+            AbortReason::Timeout => 0x04,
+            AbortReason::ValidationFailure => 0x05,
+            AbortReason::ProtocolViolation => 0x06,
+            AbortReason::InternalError => 0x07,
+            AbortReason::Other(code) => code.code(),
+        }
+    }
+
+    /// Return the reason corresponding to the code, unknown codes map to [`AbortReason::Other`].
+    pub fn from_u16(code: u16) -> Self {
+        match code {
+            0x00 => AbortReason::Unspecified,
+            0x01 => AbortReason::UserRequested,
+            0x02 => AbortReason::FeeDisagreement,
+            0x03 => AbortReason::AmountDisagreement,
+            0x04 => AbortReason::Timeout,
+            0x05 => AbortReason::ValidationFailure,
+            0x06 => AbortReason::ProtocolViolation,
+            0x07 => AbortReason::InternalError,
+            code => AbortReason::Other(UnknownCode::new(code)),
+        }
+    }
+}
+
+impl std::fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbortReason::Unspecified => write!(f, "Unspecified"),
+            AbortReason::UserRequested => write!(f, "User requested"),
+            AbortReason::FeeDisagreement => write!(f, "Fee disagreement"),
+            AbortReason::AmountDisagreement => write!(f, "Amount disagreement"),
+            AbortReason::Timeout => write!(f, "Timeout"),
+            AbortReason::ValidationFailure => write!(f, "Validation failure"),
+            AbortReason::ProtocolViolation => write!(f, "Protocol violation"),
+            AbortReason::InternalError => write!(f, "Internal error"),
+            AbortReason::Other(code) => write!(f, "Other({})", code),
+        }
+    }
+}
+
+impl Encodable for AbortReason {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.to_u16().consensus_encode(s)
+    }
+}
+
+impl Decodable for AbortReason {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self::from_u16(Decodable::consensus_decode(d)?))
+    }
+}
+
+impl_strict_encoding!(AbortReason);
+
+/// `abort` is an `OPTIONAL` courtesy message from either swap partner to inform the counterparty
+/// that they have aborted the swap with a structured reason and an `OPTIONAL` message body to
+/// provide more details.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Abort {
-    /// OPTIONAL `body`: error code | string
+    /// The reason of the abort, see [`AbortReason::action`] for how to handle it
+    pub reason: AbortReason,
+    /// OPTIONAL `body`: free text describing the error
     pub error_body: Option<String>,
 }
 
+impl Abort {
+    /// Create an abort message with the given reason and no body.
+    pub fn new(reason: AbortReason) -> Self {
+        Self {
+            reason,
+            error_body: None,
+        }
+    }
+
+    /// Add a free text body to the abort message.
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.error_body = Some(body.into());
+        self
+    }
+
+    /// Return the action to take when receiving this abort message.
+    pub fn action(&self) -> AbortAction {
+        self.reason.action()
+    }
+}
+
 impl Encodable for Abort {
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        self.error_body.consensus_encode(s)
+        let len = self.reason.consensus_encode(s)?;
+        Ok(len + self.error_body.consensus_encode(s)?)
     }
 }
 
 impl Decodable for Abort {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
+            reason: Decodable::consensus_decode(d)?,
             error_body: Option::<String>::consensus_decode(d)?,
         })
     }
 }
 
 impl_strict_encoding!(Abort);
+
+impl_describe!(
+    Abort,
+    [],
+    Kind::Message(0x08),
+    None,
+    None,
+    { reason, error_body }
+);
+
+impl_annotate!(
+    Abort,
+    [],
+    { decode reason, decode error_body }
+);
+
+/// `accordant_locked` is sent by Alice in the reversed construction, where the accordant assets are
+/// locked first, to inform Bob that the accordant lock transaction is broadcasted. Bob watches the
+/// accordant blockchain and locks the arbitrating assets once the transaction is confirmed.
+#[cfg(feature = "reverse")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AccordantLocked {
+    /// The accordant lock transaction identifier, serialized with the accordant blockchain
+    /// consensus.
+    pub transaction_id: Vec<u8>,
+    /// The height of the accordant blockchain when the transaction was broadcasted, used as a
+    /// starting point when scanning for the transaction.
+    pub height: u64,
+}
+
+#[cfg(feature = "reverse")]
+impl Encodable for AccordantLocked {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.transaction_id.consensus_encode(s)?;
+        Ok(len + self.height.consensus_encode(s)?)
+    }
+}
+
+#[cfg(feature = "reverse")]
+impl Decodable for AccordantLocked {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            transaction_id: Decodable::consensus_decode(d)?,
+            height: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+#[cfg(feature = "reverse")]
+impl_strict_encoding!(AccordantLocked);
+
+#[cfg(feature = "reverse")]
+impl_describe!(
+    AccordantLocked,
+    [],
+    Kind::Message(0x09),
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Lock),
+    { transaction_id, height }
+);
+
+#[cfg(feature = "reverse")]
+impl_annotate!(
+    AccordantLocked,
+    [],
+    { decode transaction_id, decode height }
+);
+
+/// `accordant_lock_proof` is optionally sent by Alice once the accordant lock transaction is
+/// broadcasted. It proves that the transaction pays the accordant lock address, so Bob can lock or
+/// continue the swap without waiting for his own wallet to scan the accordant blockchain.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AccordantLockProof {
+    /// The accordant lock transaction identifier, serialized with the accordant blockchain
+    /// consensus.
+    pub transaction_id: Vec<u8>,
+    /// The payment proof, serialized with its canonical bytes.
+    pub proof: Vec<u8>,
+}
+
+impl AccordantLockProof {
+    /// Generate the proof that the accordant lock transaction pays the lock address with the
+    /// sender's secret, e.g. the transaction private key.
+    pub fn new<Ac: PaymentProof>(
+        tx: &Ac::ProvedTransaction,
+        address: &Ac::Address,
+        secret: &Ac::PaymentSecret,
+    ) -> Result<Self, Error> {
+        let proof = Ac::prove_payment(tx, address, secret)?;
+        Ok(Self {
+            transaction_id: tx.txid(),
+            proof: proof.as_canonical_bytes(),
+        })
+    }
+
+    /// Verify that the transaction is the one referenced by the proof and that it pays at least
+    /// `amount` to the lock address.
+    pub fn verify<Ac: PaymentProof>(
+        &self,
+        tx: &Ac::ProvedTransaction,
+        address: &Ac::Address,
+        amount: Ac::AssetUnit,
+    ) -> Result<(), Error> {
+        if tx.txid() != self.transaction_id {
+            return Err(Error::Crypto(crypto::Error::InvalidProof));
+        }
+        let proof = Ac::PaymentProof::from_canonical_bytes(&self.proof)?;
+        match Ac::verify_payment(tx, address, &proof)? >= amount {
+            true => Ok(()),
+            false => Err(Error::Crypto(crypto::Error::InvalidProof)),
+        }
+    }
+}
+
+impl Encodable for AccordantLockProof {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.transaction_id.consensus_encode(s)?;
+        Ok(len + self.proof.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for AccordantLockProof {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            transaction_id: Decodable::consensus_decode(d)?,
+            proof: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(AccordantLockProof);
+
+impl_describe!(
+    AccordantLockProof,
+    [],
+    Kind::Message(0x0a),
+    Some(SwapRole::Alice),
+    Some(SwapPhase::Lock),
+    { transaction_id, proof }
+);
+
+impl_annotate!(
+    AccordantLockProof,
+    [],
+    { decode transaction_id, decode proof }
+);
+
+/// `update_buy_address` is optionally sent by Alice before Bob produces the
+/// `buy_procedure_signature` to rotate the destination address of the buy transaction. The new
+/// address must be part of the set pre-committed in her reveal, see
+/// [`RevealAliceParameters::with_destination_addresses`]. Bob MUST reject the message once the
+/// buy procedure signature is sent.
+#[derive(Clone, Debug)]
+pub struct UpdateBuyAddress<Ctx: Swap> {
+    /// The new destination address
+    pub address: <Ctx::Ar as Address>::Address,
+    /// Proof of inclusion of the new address in the pre-committed set
+    pub proof: MerkleProof,
+}
+
+impl<Ctx> UpdateBuyAddress<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create the message updating the destination address to `address`, `None` if the address
+    /// is not in the pre-committed set.
+    pub fn new(
+        address: <Ctx::Ar as Address>::Address,
+        addresses: &[<Ctx::Ar as Address>::Address],
+    ) -> Option<Self> {
+        let leaves: Vec<Vec<u8>> = addresses.iter().map(|a| a.as_canonical_bytes()).collect();
+        let index = leaves
+            .iter()
+            .position(|leaf| *leaf == address.as_canonical_bytes())?;
+        Some(Self {
+            address,
+            proof: MerkleProof::new(&leaves, index)?,
+        })
+    }
+
+    /// Verify that the new destination address is part of the set pre-committed in Alice's
+    /// reveal.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn verify_with_reveal(&self, reveal: &RevealAliceParameters<Ctx>) -> Result<(), Error> {
+        match &reveal.destination_addresses_root {
+            Some(root) if self.proof.verify(root, self.address.as_canonical_bytes()) => Ok(()),
+            _ => Err(Error::Crypto(crypto::Error::InvalidCommitment)),
+        }
+    }
+
+    /// Update the destination address of Alice's parameters used to build the buy transaction.
+    /// The message must be verified first.
+    pub fn apply(&self, alice_parameters: &mut bundle::AliceParameters<Ctx>) {
+        alice_parameters.destination_address = self.address.clone();
+    }
+}
+
+impl<Ctx> RevealedAddress<Ctx> for UpdateBuyAddress<Ctx>
+where
+    Ctx: Swap,
+{
+    fn revealed_address(&self) -> &<Ctx::Ar as Address>::Address {
+        &self.address
+    }
+}
+
+impl<Ctx> Encodable for UpdateBuyAddress<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.address.as_canonical_bytes().consensus_encode(s)?;
+        Ok(len + self.proof.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for UpdateBuyAddress<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            address: <Ctx::Ar as Address>::Address::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            proof: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(UpdateBuyAddress<Ctx>, Ctx: Swap);
+impl_eq_by_encoding!(UpdateBuyAddress<Ctx>, Ctx: Swap);
+
+impl_describe!(
+    UpdateBuyAddress<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x0e),
+    Some(SwapRole::Alice),
+    None,
+    { address, proof }
+);
+
+impl_annotate!(
+    UpdateBuyAddress<Ctx>,
+    [Ctx: Swap],
+    { canonical address, decode proof }
+);
+
+/// `add_lock_input` is sent by both participants in the dual-funded variant to contribute an input
+/// to the arbitrating `lock (b)` transaction. Bob, initiating the construction, uses even serial
+/// identifiers and Alice uses odd ones.
+#[cfg(feature = "dual-funding")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AddLockInput {
+    /// The identifier of the contribution, inputs are ordered by serial identifier.
+    pub serial_id: u64,
+    /// The transaction containing the spent output, serialized with the arbitrating blockchain
+    /// consensus.
+    pub prev_tx: Vec<u8>,
+    /// The index of the spent output in the previous transaction.
+    pub prev_vout: u32,
+}
+
+#[cfg(feature = "dual-funding")]
+impl Encodable for AddLockInput {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.serial_id.consensus_encode(s)?;
+        len += self.prev_tx.consensus_encode(s)?;
+        Ok(len + self.prev_vout.consensus_encode(s)?)
+    }
+}
+
+#[cfg(feature = "dual-funding")]
+impl Decodable for AddLockInput {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            serial_id: Decodable::consensus_decode(d)?,
+            prev_tx: Decodable::consensus_decode(d)?,
+            prev_vout: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+#[cfg(feature = "dual-funding")]
+impl_strict_encoding!(AddLockInput);
+
+#[cfg(feature = "dual-funding")]
+impl_describe!(
+    AddLockInput,
+    [],
+    Kind::Message(0x0b),
+    None,
+    Some(SwapPhase::Setup),
+    { serial_id, prev_tx, prev_vout }
+);
+
+#[cfg(feature = "dual-funding")]
+impl_annotate!(
+    AddLockInput,
+    [],
+    { decode serial_id, decode prev_tx, decode prev_vout }
+);
+
+/// `add_lock_output` is sent by both participants in the dual-funded variant to add a change
+/// output to the arbitrating `lock (b)` transaction, the lock output is always the first output.
+#[cfg(feature = "dual-funding")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AddLockOutput {
+    /// The identifier of the contribution, change outputs are ordered by serial identifier.
+    pub serial_id: u64,
+    /// The amount of the output in the smallest unit of the arbitrating asset.
+    pub amount: u64,
+    /// The locking script of the output.
+    pub script_pubkey: Vec<u8>,
+}
+
+#[cfg(feature = "dual-funding")]
+impl Encodable for AddLockOutput {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.serial_id.consensus_encode(s)?;
+        len += self.amount.consensus_encode(s)?;
+        Ok(len + self.script_pubkey.consensus_encode(s)?)
+    }
+}
+
+#[cfg(feature = "dual-funding")]
+impl Decodable for AddLockOutput {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            serial_id: Decodable::consensus_decode(d)?,
+            amount: Decodable::consensus_decode(d)?,
+            script_pubkey: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+#[cfg(feature = "dual-funding")]
+impl_strict_encoding!(AddLockOutput);
+
+#[cfg(feature = "dual-funding")]
+impl_describe!(
+    AddLockOutput,
+    [],
+    Kind::Message(0x0c),
+    None,
+    Some(SwapPhase::Setup),
+    { serial_id, amount, script_pubkey }
+);
+
+#[cfg(feature = "dual-funding")]
+impl_annotate!(
+    AddLockOutput,
+    [],
+    { decode serial_id, decode amount, decode script_pubkey }
+);
+
+/// `lock_contribution_complete` is sent by both participants in the dual-funded variant when they
+/// have nothing more to contribute. The construction is over when both participants sent it
+/// without any contribution in between.
+#[cfg(feature = "dual-funding")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LockContributionComplete;
+
+#[cfg(feature = "dual-funding")]
+impl Encodable for LockContributionComplete {
+    fn consensus_encode<W: io::Write>(&self, _s: &mut W) -> Result<usize, io::Error> {
+        Ok(0)
+    }
+}
+
+#[cfg(feature = "dual-funding")]
+impl Decodable for LockContributionComplete {
+    fn consensus_decode<D: io::Read>(_d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self)
+    }
+}
+
+#[cfg(feature = "dual-funding")]
+impl_strict_encoding!(LockContributionComplete);
+
+#[cfg(feature = "dual-funding")]
+impl_describe!(
+    LockContributionComplete,
+    [],
+    Kind::Message(0x0d),
+    None,
+    Some(SwapPhase::Setup),
+    {}
+);
+
+#[cfg(feature = "dual-funding")]
+impl_annotate!(LockContributionComplete, [], {});
+
+/// `commit_hash_lock` is sent by both participants in the hash time-locked fallback mode, see
+/// [`FEATURE_HTLC`](crate::negotiation::FEATURE_HTLC), in place of the adaptor signatures. It
+/// contains the arbitrating key of the sender and the hash of its share of the accordant spend
+/// private key, revealed when the sender spends its path of the contract. Alice's values are
+/// used for the success path, Bob's for the failure path.
+///
+/// The hash is not proven to commit to the share of the sender's accordant public spend key,
+/// this mode is only safe between trusted participants, see the
+/// [`htlc`](crate::chain::bitcoin::htlc) module.
+#[cfg(feature = "htlc")]
+#[derive(Clone, Debug)]
+pub struct CommitHashLock<Ctx: Swap> {
+    /// The arbitrating key spending the sender path of the contract.
+    pub key: <Ctx::Ar as Keys>::PublicKey,
+    /// The SHA-256 hash of the sender's share of the accordant spend private key.
+    pub hash: [u8; 32],
+}
+
+#[cfg(feature = "htlc")]
+impl<Ctx> CommitHashLock<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Commit to the sender's share of the accordant spend private key.
+    pub fn new(key: <Ctx::Ar as Keys>::PublicKey, secret: &<Ctx::Ac as Keys>::PrivateKey) -> Self
+    where
+        <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+    {
+        Self {
+            key,
+            hash: crate::script::hash_secret(&secret.as_canonical_bytes()),
+        }
+    }
+
+    /// Return the hashed key of the sender for its path of the contract.
+    pub fn hashed_key(&self) -> crate::script::HashedKey<Ctx::Ar> {
+        crate::script::HashedKey::new(self.key.clone(), self.hash)
+    }
+
+    /// Verify that a secret extracted from the counter-party spending transaction is the one
+    /// committed, returning the committed secret as an accordant private key. Only the hash is
+    /// checked: the key is not guaranteed to be the counter-party share of the accordant spend
+    /// private key.
+    pub fn open(&self, secret: &[u8]) -> Result<<Ctx::Ac as Keys>::PrivateKey, consensus::Error>
+    where
+        <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+    {
+        match crate::script::hash_secret(secret) == self.hash {
+            true => <Ctx::Ac as Keys>::PrivateKey::from_canonical_bytes(secret),
+            false => Err(consensus::Error::ParseFailed(
+                "secret does not match the hash",
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "htlc")]
+impl<Ctx> Encodable for CommitHashLock<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.key.as_canonical_bytes().consensus_encode(s)?;
+        Ok(len + self.hash.consensus_encode(s)?)
+    }
+}
+
+#[cfg(feature = "htlc")]
+impl<Ctx> Decodable for CommitHashLock<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            key: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            hash: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+#[cfg(feature = "htlc")]
+impl_strict_encoding!(CommitHashLock<Ctx>, Ctx: Swap);
+#[cfg(feature = "htlc")]
+impl_eq_by_encoding!(CommitHashLock<Ctx>, Ctx: Swap);
+
+#[cfg(feature = "htlc")]
+impl_describe!(
+    CommitHashLock<Ctx>,
+    [Ctx: Swap],
+    Kind::Message(0x0f),
+    None,
+    Some(SwapPhase::Setup),
+    { key, hash }
+);
+
+#[cfg(feature = "htlc")]
+impl_annotate!(
+    CommitHashLock<Ctx>,
+    [Ctx: Swap],
+    { canonical key, decode hash }
+);
+
+/// All the protocol messages exchanged between swap daemons prefixed with their message type when
+/// encoded. The type prefix allows a receiver to decode a message without knowing in advance
+/// which message is expected.
+#[derive(Clone, Debug)]
+pub enum ProtocolMessage<Ctx: Swap> {
+    CommitAliceParameters(CommitAliceParameters<Ctx>),
+    CommitBobParameters(CommitBobParameters<Ctx>),
+    RevealAliceParameters(RevealAliceParameters<Ctx>),
+    RevealBobParameters(RevealBobParameters<Ctx>),
+    CoreArbitratingSetup(CoreArbitratingSetup<Ctx>),
+    RefundProcedureSignatures(RefundProcedureSignatures<Ctx>),
+    BuyProcedureSignature(BuyProcedureSignature<Ctx>),
+    Abort(Abort),
+    #[cfg(feature = "reverse")]
+    AccordantLocked(AccordantLocked),
+    AccordantLockProof(AccordantLockProof),
+    #[cfg(feature = "dual-funding")]
+    AddLockInput(AddLockInput),
+    #[cfg(feature = "dual-funding")]
+    AddLockOutput(AddLockOutput),
+    #[cfg(feature = "dual-funding")]
+    LockContributionComplete(LockContributionComplete),
+    UpdateBuyAddress(UpdateBuyAddress<Ctx>),
+    #[cfg(feature = "htlc")]
+    CommitHashLock(CommitHashLock<Ctx>),
+    CooperativeCloseRequest(CooperativeCloseRequest<Ctx>),
+    CooperativeCloseSignature(CooperativeCloseSignature<Ctx>),
+    StateCheckpoint(StateCheckpoint<Ctx>),
+}
+
+impl<Ctx> ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Return the type of the message, prefixed to the message when encoded.
+    pub fn message_type(&self) -> u16 {
+        match self {
+            ProtocolMessage::CommitAliceParameters(_) => 0x01,
+            ProtocolMessage::CommitBobParameters(_) => 0x02,
+            ProtocolMessage::RevealAliceParameters(_) => 0x03,
+            ProtocolMessage::RevealBobParameters(_) => 0x04,
+            ProtocolMessage::CoreArbitratingSetup(_) => 0x05,
+            ProtocolMessage::RefundProcedureSignatures(_) => 0x06,
+            ProtocolMessage::BuyProcedureSignature(_) => 0x07,
+            ProtocolMessage::Abort(_) => 0x08,
+            #[cfg(feature = "reverse")]
+            ProtocolMessage::AccordantLocked(_) => 0x09,
+            ProtocolMessage::AccordantLockProof(_) => 0x0a,
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::AddLockInput(_) => 0x0b,
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::AddLockOutput(_) => 0x0c,
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::LockContributionComplete(_) => 0x0d,
+            ProtocolMessage::UpdateBuyAddress(_) => 0x0e,
+            #[cfg(feature = "htlc")]
+            ProtocolMessage::CommitHashLock(_) => 0x0f,
+            ProtocolMessage::CooperativeCloseRequest(_) => 0x10,
+            ProtocolMessage::CooperativeCloseSignature(_) => 0x11,
+            ProtocolMessage::StateCheckpoint(_) => 0x12,
+        }
+    }
+
+    /// Return the description of the message, see [`Describe`].
+    pub fn describe(&self) -> Description {
+        match self {
+            ProtocolMessage::CommitAliceParameters(_) => CommitAliceParameters::<Ctx>::describe(),
+            ProtocolMessage::CommitBobParameters(_) => CommitBobParameters::<Ctx>::describe(),
+            ProtocolMessage::RevealAliceParameters(_) => RevealAliceParameters::<Ctx>::describe(),
+            ProtocolMessage::RevealBobParameters(_) => RevealBobParameters::<Ctx>::describe(),
+            ProtocolMessage::CoreArbitratingSetup(_) => CoreArbitratingSetup::<Ctx>::describe(),
+            ProtocolMessage::RefundProcedureSignatures(_) => {
+                RefundProcedureSignatures::<Ctx>::describe()
+            }
+            ProtocolMessage::BuyProcedureSignature(_) => BuyProcedureSignature::<Ctx>::describe(),
+            ProtocolMessage::Abort(_) => Abort::describe(),
+            #[cfg(feature = "reverse")]
+            ProtocolMessage::AccordantLocked(_) => AccordantLocked::describe(),
+            ProtocolMessage::AccordantLockProof(_) => AccordantLockProof::describe(),
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::AddLockInput(_) => AddLockInput::describe(),
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::AddLockOutput(_) => AddLockOutput::describe(),
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::LockContributionComplete(_) => LockContributionComplete::describe(),
+            ProtocolMessage::UpdateBuyAddress(_) => UpdateBuyAddress::<Ctx>::describe(),
+            #[cfg(feature = "htlc")]
+            ProtocolMessage::CommitHashLock(_) => CommitHashLock::<Ctx>::describe(),
+            ProtocolMessage::CooperativeCloseRequest(_) => {
+                CooperativeCloseRequest::<Ctx>::describe()
+            }
+            ProtocolMessage::CooperativeCloseSignature(_) => {
+                CooperativeCloseSignature::<Ctx>::describe()
+            }
+            ProtocolMessage::StateCheckpoint(_) => StateCheckpoint::<Ctx>::describe(),
+        }
+    }
+
+    /// Return the descriptions of all the messages supported with the activated features,
+    /// ordered by message type.
+    pub fn descriptions() -> Vec<Description> {
+        vec![
+            CommitAliceParameters::<Ctx>::describe(),
+            CommitBobParameters::<Ctx>::describe(),
+            RevealAliceParameters::<Ctx>::describe(),
+            RevealBobParameters::<Ctx>::describe(),
+            CoreArbitratingSetup::<Ctx>::describe(),
+            RefundProcedureSignatures::<Ctx>::describe(),
+            BuyProcedureSignature::<Ctx>::describe(),
+            Abort::describe(),
+            #[cfg(feature = "reverse")]
+            AccordantLocked::describe(),
+            AccordantLockProof::describe(),
+            #[cfg(feature = "dual-funding")]
+            AddLockInput::describe(),
+            #[cfg(feature = "dual-funding")]
+            AddLockOutput::describe(),
+            #[cfg(feature = "dual-funding")]
+            LockContributionComplete::describe(),
+            UpdateBuyAddress::<Ctx>::describe(),
+            #[cfg(feature = "htlc")]
+            CommitHashLock::<Ctx>::describe(),
+            CooperativeCloseRequest::<Ctx>::describe(),
+            CooperativeCloseSignature::<Ctx>::describe(),
+            StateCheckpoint::<Ctx>::describe(),
+        ]
+    }
+
+    fn decode_message<D: io::Read>(message_type: u16, d: &mut D) -> Result<Self, consensus::Error> {
+        match message_type {
+            0x01u16 => Ok(ProtocolMessage::CommitAliceParameters(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x02u16 => Ok(ProtocolMessage::CommitBobParameters(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x03u16 => Ok(ProtocolMessage::RevealAliceParameters(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x04u16 => Ok(ProtocolMessage::RevealBobParameters(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x05u16 => Ok(ProtocolMessage::CoreArbitratingSetup(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x06u16 => Ok(ProtocolMessage::RefundProcedureSignatures(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x07u16 => Ok(ProtocolMessage::BuyProcedureSignature(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x08u16 => Ok(ProtocolMessage::Abort(Decodable::consensus_decode(d)?)),
+            #[cfg(feature = "reverse")]
+            0x09u16 => Ok(ProtocolMessage::AccordantLocked(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x0au16 => Ok(ProtocolMessage::AccordantLockProof(
+                Decodable::consensus_decode(d)?,
+            )),
+            #[cfg(feature = "dual-funding")]
+            0x0bu16 => Ok(ProtocolMessage::AddLockInput(Decodable::consensus_decode(
+                d,
+            )?)),
+            #[cfg(feature = "dual-funding")]
+            0x0cu16 => Ok(ProtocolMessage::AddLockOutput(Decodable::consensus_decode(
+                d,
+            )?)),
+            #[cfg(feature = "dual-funding")]
+            0x0du16 => Ok(ProtocolMessage::LockContributionComplete(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x0eu16 => Ok(ProtocolMessage::UpdateBuyAddress(
+                Decodable::consensus_decode(d)?,
+            )),
+            #[cfg(feature = "htlc")]
+            0x0fu16 => Ok(ProtocolMessage::CommitHashLock(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x10u16 => Ok(ProtocolMessage::CooperativeCloseRequest(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x11u16 => Ok(ProtocolMessage::CooperativeCloseSignature(
+                Decodable::consensus_decode(d)?,
+            )),
+            0x12u16 => Ok(ProtocolMessage::StateCheckpoint(
+                Decodable::consensus_decode(d)?,
+            )),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+// Annotate a message nested under its name
+fn annotate_message<T: Annotate + Describe>(a: &mut Annotator) -> Result<(), consensus::Error> {
+    a.nested::<T>(T::describe().name)
+}
+
+impl<Ctx> Annotate for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn annotate(a: &mut Annotator) -> Result<(), consensus::Error> {
+        type AnnotateFn = fn(&mut Annotator) -> Result<(), consensus::Error>;
+        let annotate: AnnotateFn = a.decode_with("message_type", |d| {
+            Ok(match u16::consensus_decode(d)? {
+                0x01u16 => annotate_message::<CommitAliceParameters<Ctx>>,
+                0x02u16 => annotate_message::<CommitBobParameters<Ctx>>,
+                0x03u16 => annotate_message::<RevealAliceParameters<Ctx>>,
+                0x04u16 => annotate_message::<RevealBobParameters<Ctx>>,
+                0x05u16 => annotate_message::<CoreArbitratingSetup<Ctx>>,
+                0x06u16 => annotate_message::<RefundProcedureSignatures<Ctx>>,
+                0x07u16 => annotate_message::<BuyProcedureSignature<Ctx>>,
+                0x08u16 => annotate_message::<Abort>,
+                #[cfg(feature = "reverse")]
+                0x09u16 => annotate_message::<AccordantLocked>,
+                0x0au16 => annotate_message::<AccordantLockProof>,
+                #[cfg(feature = "dual-funding")]
+                0x0bu16 => annotate_message::<AddLockInput>,
+                #[cfg(feature = "dual-funding")]
+                0x0cu16 => annotate_message::<AddLockOutput>,
+                #[cfg(feature = "dual-funding")]
+                0x0du16 => annotate_message::<LockContributionComplete>,
+                0x0eu16 => annotate_message::<UpdateBuyAddress<Ctx>>,
+                #[cfg(feature = "htlc")]
+                0x0fu16 => annotate_message::<CommitHashLock<Ctx>>,
+                0x10u16 => annotate_message::<CooperativeCloseRequest<Ctx>>,
+                0x11u16 => annotate_message::<CooperativeCloseSignature<Ctx>>,
+                0x12u16 => annotate_message::<StateCheckpoint<Ctx>>,
+                _ => return Err(consensus::Error::UnknownType),
+            })
+        })?;
+        annotate(a)
+    }
+}
+
+impl<Ctx> Encodable for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.message_type().consensus_encode(s)?;
+        len += self.encode_body(s)?;
+        observer().on_message_encoded(self.message_type(), len);
+        Ok(len)
+    }
+}
+
+impl<Ctx> ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    // Encode the message without its type
+    fn encode_body<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        Ok(match self {
+            ProtocolMessage::CommitAliceParameters(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::CommitBobParameters(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::RevealAliceParameters(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::RevealBobParameters(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::CoreArbitratingSetup(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::RefundProcedureSignatures(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::BuyProcedureSignature(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::Abort(msg) => msg.consensus_encode(s)?,
+            #[cfg(feature = "reverse")]
+            ProtocolMessage::AccordantLocked(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::AccordantLockProof(msg) => msg.consensus_encode(s)?,
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::AddLockInput(msg) => msg.consensus_encode(s)?,
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::AddLockOutput(msg) => msg.consensus_encode(s)?,
+            #[cfg(feature = "dual-funding")]
+            ProtocolMessage::LockContributionComplete(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::UpdateBuyAddress(msg) => msg.consensus_encode(s)?,
+            #[cfg(feature = "htlc")]
+            ProtocolMessage::CommitHashLock(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::CooperativeCloseRequest(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::CooperativeCloseSignature(msg) => msg.consensus_encode(s)?,
+            ProtocolMessage::StateCheckpoint(msg) => msg.consensus_encode(s)?,
+        })
+    }
+
+    // Encode the message for comparison and hashing, without reporting it to the observer
+    fn body_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.message_type().to_le_bytes().to_vec();
+        self.encode_body(&mut bytes)
+            .expect("in-memory writers don't error");
+        bytes
+    }
+}
+
+impl<Ctx> PartialEq for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.body_bytes() == other.body_bytes()
+    }
+}
+
+impl<Ctx> Eq for ProtocolMessage<Ctx> where Ctx: Swap {}
+
+impl<Ctx> std::hash::Hash for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write(&self.body_bytes());
+    }
+}
+
+impl<Ctx> Decodable for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let message_type = Decodable::consensus_decode(d)?;
+        let mut reader = CountingReader::new(d);
+        let res = Self::decode_message(message_type, &mut reader).map_err(|e| {
+            consensus::Error::Message {
+                msg_type: message_type,
+                // Offset from the start of the message, including the type
+                offset: reader.count() + 2,
+                source: Box::new(e),
+            }
+        });
+        observer().on_message_decoded(message_type, res.is_ok());
+        res
+    }
+}
+
+impl_strict_encoding!(ProtocolMessage<Ctx>, Ctx: Swap);
+
+#[cfg(feature = "lightning")]
+impl<Ctx> LightningEncode for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn lightning_encode<E: io::Write>(&self, mut e: E) -> Result<usize, io::Error> {
+        self.consensus_encode(&mut e)
+    }
+}
+
+#[cfg(feature = "lightning")]
+impl<Ctx> LightningDecode for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn lightning_decode<D: io::Read>(mut d: D) -> Result<Self, lightning_encoding::Error> {
+        Ok(Decodable::consensus_decode(&mut d)?)
+    }
+}
@@ -0,0 +1,288 @@
+//! Recovery kits contain the minimal secrets needed to recover the funds of a half-completed swap
+//! on another machine if the daemon running the swap dies: the private keys of the participant,
+//! the counter-party secret recovered from an adaptor signature if any, the destination addresses,
+//! and the heights to start scanning the blockchains from.
+//!
+//! Kits are exported encrypted with a password. The encryption key is derived with
+//! PBKDF2-HMAC-SHA256 and the kit is encrypted with ChaCha20-Poly1305, the header containing the
+//! format version and the key derivation parameters is authenticated as associated data.
+//!
+//! # Format
+//!
+//! ```text
+//! version (u16) | iterations (u32) | salt (16 bytes) | nonce (12 bytes) | ciphertext
+//! ```
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use std::convert::TryFrom;
+use std::io;
+
+use crate::blockchain::{Address, Network};
+use crate::consensus::{self, decode_option, encode_option, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::{hash, ArbitratingKeyId, Keys, SharedKeyId, SharedPrivateKeys};
+use crate::role::SwapRole;
+use crate::swap::{Swap, SwapId};
+
+/// The current version of the recovery kit format.
+pub const RECOVERY_KIT_VERSION: u16 = 1;
+
+/// The default number of PBKDF2 iterations used to derive the encryption key.
+pub const RECOVERY_KIT_ITERATIONS: u32 = 100_000;
+
+/// The maximum number of PBKDF2 iterations accepted, bounds the work done when decrypting a kit.
+pub const MAX_RECOVERY_KIT_ITERATIONS: u32 = 10 * RECOVERY_KIT_ITERATIONS;
+
+const HEADER_LEN: usize = 2 + 4 + 16 + 12;
+
+/// The secrets and data needed to recover the funds of a swap from the point of view of one
+/// participant.
+pub struct RecoveryKit<Ctx: Swap> {
+    pub swap_id: SwapId,
+    pub swap_role: SwapRole,
+    pub network: Network,
+    /// The participant arbitrating private keys with their identifiers
+    pub arbitrating_keys: Vec<(ArbitratingKeyId, <Ctx::Ar as Keys>::PrivateKey)>,
+    /// The participant share of the accordant spend private key
+    pub accordant_spend: Option<<Ctx::Ac as Keys>::PrivateKey>,
+    /// The participant shares of the accordant shared private keys, e.g. the view key
+    pub accordant_shared_keys: Vec<(
+        SharedKeyId,
+        <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey,
+    )>,
+    /// The counter-party share of the accordant spend private key, recovered from an adaptor
+    /// signature, if already known
+    pub recovered_secret: Option<<Ctx::Ac as Keys>::PrivateKey>,
+    /// The arbitrating address receiving the funds
+    pub arbitrating_address: Option<<Ctx::Ar as Address>::Address>,
+    /// The accordant address receiving the funds
+    pub accordant_address: Option<<Ctx::Ac as Address>::Address>,
+    /// The arbitrating blockchain height before the lock transaction was broadcasted
+    pub arbitrating_height: Option<u64>,
+    /// The accordant blockchain height to restore the accordant wallet from
+    pub accordant_height: Option<u64>,
+}
+
+impl<Ctx> RecoveryKit<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create a new empty recovery kit for a swap.
+    pub fn new(swap_id: SwapId, swap_role: SwapRole, network: Network) -> Self {
+        Self {
+            swap_id,
+            swap_role,
+            network,
+            arbitrating_keys: vec![],
+            accordant_spend: None,
+            accordant_shared_keys: vec![],
+            recovered_secret: None,
+            arbitrating_address: None,
+            accordant_address: None,
+            arbitrating_height: None,
+            accordant_height: None,
+        }
+    }
+
+    /// Add an arbitrating private key to the kit.
+    pub fn with_arbitrating_key(
+        mut self,
+        id: ArbitratingKeyId,
+        key: <Ctx::Ar as Keys>::PrivateKey,
+    ) -> Self {
+        self.arbitrating_keys.push((id, key));
+        self
+    }
+
+    /// Add the participant share of the accordant spend private key to the kit.
+    pub fn with_accordant_spend(mut self, key: <Ctx::Ac as Keys>::PrivateKey) -> Self {
+        self.accordant_spend = Some(key);
+        self
+    }
+
+    /// Add an accordant shared private key to the kit.
+    pub fn with_accordant_shared_key(
+        mut self,
+        id: SharedKeyId,
+        key: <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey,
+    ) -> Self {
+        self.accordant_shared_keys.push((id, key));
+        self
+    }
+
+    /// Add the counter-party secret recovered from an adaptor signature to the kit.
+    pub fn with_recovered_secret(mut self, key: <Ctx::Ac as Keys>::PrivateKey) -> Self {
+        self.recovered_secret = Some(key);
+        self
+    }
+
+    /// Add the arbitrating destination address to the kit.
+    pub fn with_arbitrating_address(mut self, address: <Ctx::Ar as Address>::Address) -> Self {
+        self.arbitrating_address = Some(address);
+        self
+    }
+
+    /// Add the accordant destination address to the kit.
+    pub fn with_accordant_address(mut self, address: <Ctx::Ac as Address>::Address) -> Self {
+        self.accordant_address = Some(address);
+        self
+    }
+
+    /// Add the arbitrating height to start scanning from to the kit.
+    pub fn with_arbitrating_height(mut self, height: u64) -> Self {
+        self.arbitrating_height = Some(height);
+        self
+    }
+
+    /// Add the accordant height to restore the wallet from to the kit.
+    pub fn with_accordant_height(mut self, height: u64) -> Self {
+        self.accordant_height = Some(height);
+        self
+    }
+}
+
+impl<Ctx> RecoveryKit<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ar as Keys>::PrivateKey: CanonicalBytes,
+    <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+{
+    /// Encrypt the kit with the password. The `salt` and the `nonce` MUST be generated from a
+    /// secure source of randomness and never reused. The number of iterations cannot be above
+    /// [`MAX_RECOVERY_KIT_ITERATIONS`].
+    pub fn encrypt(
+        &self,
+        password: &[u8],
+        salt: [u8; 16],
+        nonce: [u8; 12],
+        iterations: u32,
+    ) -> Result<Vec<u8>, consensus::Error> {
+        check_iterations(iterations)?;
+        let mut bytes = consensus::serialize(&RECOVERY_KIT_VERSION);
+        bytes.extend(consensus::serialize(&iterations));
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(&nonce);
+        let key = hash::pbkdf2_sha256(password, &salt, iterations);
+        let mut plaintext = vec![];
+        self.consensus_encode(&mut plaintext)?;
+        let ciphertext = ChaCha20Poly1305::new(&Key::from(key))
+            .encrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &bytes,
+                },
+            )
+            .map_err(|_| consensus::Error::ParseFailed("recovery kit encryption failed"))?;
+        bytes.extend(ciphertext);
+        Ok(bytes)
+    }
+
+    /// Decrypt a kit with the password. Fails with [`consensus::Error::InvalidMac`] if the
+    /// password is wrong or the kit has been tampered with. Kits encrypted with more than
+    /// [`MAX_RECOVERY_KIT_ITERATIONS`] iterations are rejected before deriving the key.
+    pub fn decrypt(bytes: &[u8], password: &[u8]) -> Result<Self, consensus::Error> {
+        if bytes.len() < HEADER_LEN {
+            return Err(consensus::Error::ParseFailed("recovery kit too short"));
+        }
+        let (header, ciphertext) = bytes.split_at(HEADER_LEN);
+        if consensus::deserialize::<u16>(&header[..2])? != RECOVERY_KIT_VERSION {
+            return Err(consensus::Error::UnsupportedVersion);
+        }
+        let iterations: u32 = consensus::deserialize(&header[2..6])?;
+        check_iterations(iterations)?;
+        let key = hash::pbkdf2_sha256(password, &header[6..22], iterations);
+        let mut nonce = [0u8; 12];
+        nonce.copy_from_slice(&header[22..]);
+        let plaintext = ChaCha20Poly1305::new(&Key::from(key))
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| consensus::Error::InvalidMac)?;
+        consensus::deserialize(&plaintext)
+    }
+}
+
+fn check_iterations(iterations: u32) -> Result<(), consensus::Error> {
+    match iterations {
+        1..=MAX_RECOVERY_KIT_ITERATIONS => Ok(()),
+        _ => Err(consensus::Error::ParseFailed(
+            "invalid number of iterations",
+        )),
+    }
+}
+
+fn encode_len<W: io::Write>(len: usize, s: &mut W) -> Result<usize, io::Error> {
+    u16::try_from(len)
+        .map_err(|_| io::Error::other("Too many keys"))?
+        .consensus_encode(s)
+}
+
+impl<Ctx> Encodable for RecoveryKit<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ar as Keys>::PrivateKey: CanonicalBytes,
+    <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.swap_role.consensus_encode(s)?;
+        len += self.network.consensus_encode(s)?;
+        len += encode_len(self.arbitrating_keys.len(), s)?;
+        for (id, key) in self.arbitrating_keys.iter() {
+            len += id.consensus_encode(s)?;
+            len += key.as_canonical_bytes().consensus_encode(s)?;
+        }
+        len += self.accordant_spend.consensus_encode(s)?;
+        len += encode_len(self.accordant_shared_keys.len(), s)?;
+        for (id, key) in self.accordant_shared_keys.iter() {
+            len += id.consensus_encode(s)?;
+            len += key.as_canonical_bytes().consensus_encode(s)?;
+        }
+        len += self.recovered_secret.consensus_encode(s)?;
+        len += self.arbitrating_address.consensus_encode(s)?;
+        len += self.accordant_address.consensus_encode(s)?;
+        len += encode_option(&self.arbitrating_height, s)?;
+        Ok(len + encode_option(&self.accordant_height, s)?)
+    }
+}
+
+impl<Ctx> Decodable for RecoveryKit<Ctx>
+where
+    Ctx: Swap,
+    <Ctx::Ar as Keys>::PrivateKey: CanonicalBytes,
+    <Ctx::Ac as Keys>::PrivateKey: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let mut kit = Self::new(
+            Decodable::consensus_decode(d)?,
+            Decodable::consensus_decode(d)?,
+            Decodable::consensus_decode(d)?,
+        );
+        for _ in 0..u16::consensus_decode(d)? {
+            let id = Decodable::consensus_decode(d)?;
+            let key =
+                <Ctx::Ar as Keys>::PrivateKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?;
+            kit.arbitrating_keys.push((id, key));
+        }
+        kit.accordant_spend = Decodable::consensus_decode(d)?;
+        for _ in 0..u16::consensus_decode(d)? {
+            let id = Decodable::consensus_decode(d)?;
+            let key = <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?;
+            kit.accordant_shared_keys.push((id, key));
+        }
+        kit.recovered_secret = Decodable::consensus_decode(d)?;
+        kit.arbitrating_address = Decodable::consensus_decode(d)?;
+        kit.accordant_address = Decodable::consensus_decode(d)?;
+        kit.arbitrating_height = decode_option(d)?;
+        kit.accordant_height = decode_option(d)?;
+        Ok(kit)
+    }
+}
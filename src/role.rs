@@ -1,26 +1,34 @@
 //! Roles during negotiation and swap phases, blockchain roles, and network definitions.
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::io;
 use std::str::FromStr;
 
-use crate::blockchain::{Address, Asset, Fee, FeePolitic, Onchain, Timelock, Transactions};
+use crate::blockchain::{
+    Address, AddressAllowlist, AddressScript, Asset, Fee, FeePolitic, FeeStrategyError, Onchain,
+    Timelock, Transactions,
+};
 use crate::bundle::{
     AliceParameters, BobParameters, CoreArbitratingTransactions, CosignedArbitratingCancel,
-    FullySignedBuy, FullySignedPunish, FullySignedRefund, SignedAdaptorBuy, SignedAdaptorRefund,
-    SignedArbitratingLock,
+    CosignedCooperativeClose, FullySignedBuy, FullySignedPunish, FullySignedRefund,
+    PunishDelegation, SignedAdaptorBuy, SignedAdaptorRefund, SignedArbitratingLock,
+    SignedCooperativeClose,
 };
 use crate::consensus::{self, Decodable, Encodable};
 use crate::crypto::{
-    AccordantKeyId, ArbitratingKeyId, Keys, SharedKeyId, SharedPrivateKeys, Sign, Signatures,
-    TaggedElement, Wallet,
+    AccordantKeyId, ArbitratingKeyId, Keys, SharedPrivateKeys, Sign, Signatures, TaggedElement,
+    Wallet,
+};
+use crate::negotiation::{BlindedPublicOffer, PublicOffer};
+use crate::protocol_message::{
+    Abort, AbortReason, RevealAliceParameters, RevealBobParameters, RevealedAddress, RevealedOffer,
+    UpdateBuyAddress,
 };
-use crate::negotiation::PublicOffer;
 use crate::script::{DataLock, DataPunishableLock, DoubleKeys, ScriptPath};
 use crate::swap::Swap;
 use crate::transaction::{
-    Buyable, Cancelable, Chainable, Fundable, Lockable, Punishable, Refundable, Transaction,
-    Witnessable,
+    self, Broadcastable, Buyable, Cancelable, Chainable, Fundable, Lockable, Punishable,
+    Refundable, Sweepable, Transaction, TxLabel, Witnessable,
 };
 use crate::Error;
 
@@ -79,11 +87,11 @@ impl FromStr for TradeRole {
     }
 }
 
-impl ToString for TradeRole {
-    fn to_string(&self) -> String {
+impl fmt::Display for TradeRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TradeRole::Maker => "Maker".to_string(),
-            TradeRole::Taker => "Taker".to_string(),
+            TradeRole::Maker => write!(f, "Maker"),
+            TradeRole::Taker => write!(f, "Taker"),
         }
     }
 }
@@ -143,15 +151,68 @@ impl FromStr for SwapRole {
     }
 }
 
-impl ToString for SwapRole {
-    fn to_string(&self) -> String {
+impl fmt::Display for SwapRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Alice => write!(f, "Alice"),
+            Self::Bob => write!(f, "Bob"),
+        }
+    }
+}
+
+/// Defines which assets are locked first during the swap. The default construction locks the
+/// arbitrating assets first, the reversed construction lets the participant holding the accordant
+/// assets move first.
+#[cfg(feature = "reverse")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockOrder {
+    /// Bob locks the arbitrating assets first, then Alice locks the accordant assets.
+    #[default]
+    ArbitratingFirst,
+    /// Alice locks the accordant assets first, then Bob locks the arbitrating assets.
+    AccordantFirst,
+}
+
+#[cfg(feature = "reverse")]
+impl LockOrder {
+    /// Return the swap role locking its assets first.
+    pub fn first_locker(&self) -> SwapRole {
         match self {
-            Self::Alice => "Alice".to_string(),
-            Self::Bob => "Bob".to_string(),
+            Self::ArbitratingFirst => SwapRole::Bob,
+            Self::AccordantFirst => SwapRole::Alice,
+        }
+    }
+
+    /// Return the swap role locking its assets second.
+    pub fn second_locker(&self) -> SwapRole {
+        self.first_locker().other()
+    }
+}
+
+#[cfg(feature = "reverse")]
+impl Encodable for LockOrder {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            LockOrder::ArbitratingFirst => 0x01u8.consensus_encode(writer),
+            LockOrder::AccordantFirst => 0x02u8.consensus_encode(writer),
+        }
+    }
+}
+
+#[cfg(feature = "reverse")]
+impl Decodable for LockOrder {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u8 => Ok(LockOrder::ArbitratingFirst),
+            0x02u8 => Ok(LockOrder::AccordantFirst),
+            _ => Err(consensus::Error::UnknownType),
         }
     }
 }
 
+#[cfg(feature = "reverse")]
+impl_strict_encoding!(LockOrder);
+
 /// Alice, the swap role, is the role starting with accordant blockchain assets and exchange them
 /// for arbitrating blockchain assets.
 pub struct Alice<Ctx: Swap> {
@@ -159,6 +220,8 @@ pub struct Alice<Ctx: Swap> {
     pub destination_address: <Ctx::Ar as Address>::Address,
     /// The fee politic to apply during the swap fee calculation
     pub fee_politic: FeePolitic,
+    /// The address types accepted for the refund address revealed by Bob
+    pub address_allowlist: AddressAllowlist<Ctx::Ar>,
 }
 
 struct ValidatedCoreTransactions<Ctx: Swap> {
@@ -181,9 +244,42 @@ where
         Self {
             destination_address,
             fee_politic,
+            address_allowlist: AddressAllowlist::default(),
         }
     }
 
+    /// Accept only the address types of the allowlist for the refund address revealed by Bob,
+    /// the standard address types of the arbitrating blockchain are accepted by default.
+    pub fn with_address_allowlist(mut self, allowlist: AddressAllowlist<Ctx::Ar>) -> Self {
+        self.address_allowlist = allowlist;
+        self
+    }
+
+    /// Validate Bob's reveal when received, before building any transaction with his parameters:
+    /// the refund address must be valid on the network of the offer and allowed by the role's
+    /// allowlist. Return the abort message to send to Bob otherwise.
+    pub fn validate_reveal(
+        &self,
+        reveal: &RevealBobParameters<Ctx>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<(), Abort> {
+        reveal.validate_address(public_offer.offer.network, &self.address_allowlist)
+    }
+
+    /// Validate Bob's reveal when the swap is taken from a blinded offer made by Bob: the opening
+    /// revealed by Bob must match the commitments of the blinded offer, then the reveal is
+    /// validated against the opened offer as with [`Alice::validate_reveal`]. Return the opened
+    /// public offer, or the abort message to send to Bob otherwise.
+    pub fn validate_blinded_reveal(
+        &self,
+        reveal: &RevealBobParameters<Ctx>,
+        blinded_offer: &BlindedPublicOffer<Ctx>,
+    ) -> Result<PublicOffer<Ctx>, Abort> {
+        let public_offer = reveal.validate_offer_opening(blinded_offer)?;
+        self.validate_reveal(reveal, &public_offer)?;
+        Ok(public_offer)
+    }
+
     /// Generate Alice's parameters for the protocol execution based on the arbitrating and
     /// accordant seeds and the public offer agreed upon during the negotiation phase.
     ///
@@ -199,6 +295,10 @@ where
     ///  * The timelock parameters from the public offer
     ///  * The target arbitrating address used by Alice
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
     pub fn generate_parameters(
         &self,
         wallet: &impl Wallet<
@@ -210,49 +310,37 @@ where
         >,
         public_offer: &PublicOffer<Ctx>,
     ) -> Result<AliceParameters<Ctx>, Error> {
-        let extra_arbitrating_keys: Result<
-            Vec<TaggedElement<u16, <Ctx::Ar as Keys>::PublicKey>>,
-            Error,
-        > = <Ctx::Ar as Keys>::extra_keys()
+        let extra_arbitrating_keys = <Ctx::Ar as Keys>::extra_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_pubkey(ArbitratingKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
-        let arbitrating_shared_keys: Result<
-            Vec<TaggedElement<SharedKeyId, <Ctx::Ar as SharedPrivateKeys>::SharedPrivateKey>>,
-            Error,
-        > = <Ctx::Ar as SharedPrivateKeys>::shared_keys()
+        let arbitrating_shared_keys = <Ctx::Ar as SharedPrivateKeys>::shared_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
-        let extra_accordant_keys: Result<
-            Vec<TaggedElement<u16, <Ctx::Ac as Keys>::PublicKey>>,
-            Error,
-        > = <Ctx::Ac as Keys>::extra_keys()
+        let extra_accordant_keys = <Ctx::Ac as Keys>::extra_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_pubkey(AccordantKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
-        let accordant_shared_keys: Result<
-            Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey>>,
-            Error,
-        > = <Ctx::Ac as SharedPrivateKeys>::shared_keys()
+        let accordant_shared_keys = <Ctx::Ac as SharedPrivateKeys>::shared_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
         let (spend, adaptor, proof) = wallet.generate()?;
 
@@ -302,13 +390,17 @@ where
     ///
     ///  * Parse the [`Refundable`] partial transaction in [`CoreArbitratingTransactions`]
     ///  * Validate the [`Lockable`], [`Cancelable`], [`Refundable`] partial transactions in
-    ///  [`CoreArbitratingTransactions`]
+    ///    [`CoreArbitratingTransactions`]
     ///  * Retrieve Bob's adaptor public key from [`BobParameters`] bundle
     ///  * Retrieve Alice's refund public key from [`AliceParameters`] bundle
     ///  * Generate the witness data and adaptor sign it
     ///
     /// Returns the adaptor signature inside the [`SignedAdaptorRefund`] bundle.
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
     pub fn sign_adaptor_refund(
         &self,
         wallet: &impl Sign<
@@ -332,7 +424,7 @@ where
         let key = &alice_parameters.refund;
         let adaptor = &bob_parameters.adaptor;
         let msg = refund.generate_witness_message(ScriptPath::Success)?;
-        let sig = wallet.adaptor_sign_with_key(&key, &adaptor, msg)?;
+        let sig = wallet.adaptor_sign_with_key(key, adaptor, msg)?;
 
         Ok(SignedAdaptorRefund {
             refund_adaptor_sig: sig,
@@ -360,12 +452,16 @@ where
     ///
     ///  * Parse the [`Cancelable`] partial transaction in [`CoreArbitratingTransactions`]
     ///  * Validate the [`Lockable`], [`Cancelable`], [`Refundable`] partial transactions in
-    ///  [`CoreArbitratingTransactions`]
+    ///    [`CoreArbitratingTransactions`]
     ///  * Retreive Alice's cancel public key from the parameters
     ///  * Generate the witness data and sign it
     ///
     /// Returns the witness inside the [`CosignedArbitratingCancel`] bundle.
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
     pub fn cosign_arbitrating_cancel(
         &self,
         wallet: &impl Sign<
@@ -387,7 +483,7 @@ where
         // Generate the witness message to sign and sign with the cancel key.
         let msg = cancel.generate_witness_message(ScriptPath::Failure)?;
         let key = &alice_parameters.cancel;
-        let sig = wallet.sign_with_key(&key, msg)?;
+        let sig = wallet.sign_with_key(key, msg)?;
 
         Ok(CosignedArbitratingCancel { cancel_sig: sig })
     }
@@ -418,8 +514,12 @@ where
     ///
     ///  * Parse the [`Buyable`] partial transaction in [`SignedAdaptorBuy`]
     ///  * Verify the adaptor witness in [`SignedAdaptorBuy`] with the public keys from the
-    ///  parameters bundles
+    ///    parameters bundles
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
     pub fn validate_adaptor_buy(
         &self,
         wallet: &impl Sign<
@@ -451,7 +551,7 @@ where
 
         buy.is_build_on_top_of(&lock)?;
         buy.verify_template(data_lock, self.destination_address.clone())?;
-        <Ctx::Ar as Fee>::validate_fee(buy.as_partial(), &fee_strategy)?;
+        <Ctx::Ar as Fee>::validate_fee(buy.as_partial(), TxLabel::Buy, fee_strategy)?;
 
         // Verify the adaptor buy witness
         let msg = buy.generate_witness_message(ScriptPath::Success)?;
@@ -501,6 +601,10 @@ where
     ///
     /// [`validate_adaptor_buy`]: Alice::validate_adaptor_buy
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
     pub fn fully_sign_buy(
         &self,
         wallet: &impl Sign<
@@ -532,16 +636,16 @@ where
 
         buy.is_build_on_top_of(&lock)?;
         buy.verify_template(data_lock, self.destination_address.clone())?;
-        <Ctx::Ar as Fee>::validate_fee(buy.as_partial(), &fee_strategy)?;
+        <Ctx::Ar as Fee>::validate_fee(buy.as_partial(), TxLabel::Buy, fee_strategy)?;
 
         // Generate the witness message to sign and sign with the buy key.
         let msg = buy.generate_witness_message(ScriptPath::Success)?;
         let key = &alice_parameters.buy;
-        let sig = wallet.sign_with_key(&key, msg)?;
+        let sig = wallet.sign_with_key(key, msg)?;
 
         // Retreive the adaptor public key and the counter-party adaptor witness.
         let key = &alice_parameters.adaptor;
-        let adapted_sig = wallet.adapt_signature(&key, adaptor_buy.buy_adaptor_sig.clone())?;
+        let adapted_sig = wallet.adapt_signature(key, adaptor_buy.buy_adaptor_sig.clone())?;
 
         Ok(FullySignedBuy {
             buy_sig: sig,
@@ -581,6 +685,10 @@ where
     ///
     /// [`validate_adaptor_buy`]: Alice::validate_adaptor_buy
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
     pub fn fully_sign_punish(
         &self,
         wallet: &impl Sign<
@@ -611,13 +719,26 @@ where
                 <Ctx::Ar as Transactions>::Metadata,
             >>::initialize(&cancel, punish_lock, self.destination_address.clone())?;
 
-        // Set the fees according to the strategy in the offer and the local politic.
-        <Ctx::Ar as Fee>::set_fee(punish.as_partial_mut(), &fee_strategy, self.fee_politic)?;
+        // Set the fees according to the strategy in the offer and the local politic, fees are
+        // paid from the punished output only.
+        <Ctx::Ar as Fee>::set_fee(
+            punish.as_partial_mut(),
+            TxLabel::Punish,
+            fee_strategy,
+            self.fee_politic,
+        )
+        .map_err(|e| match e {
+            FeeStrategyError::NotEnoughAssets | FeeStrategyError::AmountOfFeeTooHigh => {
+                Error::Transaction(transaction::Error::FeeExceedsPunishedOutput)
+            }
+            e => e.into(),
+        })?;
+        punish.validate_fee_source()?;
 
         // Generate the witness message to sign and sign with the punish key.
         let msg = punish.generate_witness_message(ScriptPath::Failure)?;
         let key = &alice_parameters.punish;
-        let punish_sig = wallet.sign_with_key(&key, msg)?;
+        let punish_sig = wallet.sign_with_key(key, msg)?;
 
         Ok(FullySignedPunish {
             punish: punish.to_partial(),
@@ -625,6 +746,165 @@ where
         })
     }
 
+    /// Create the [`PunishDelegation`] bundle for a watchtower enforcing the punish path on
+    /// Alice's behalf while she is offline.
+    ///
+    /// The punish transaction is created and signed with [`fully_sign_punish`] and finalized,
+    /// the cancel transaction is added unsigned, as the trigger, along the punish timelock.
+    ///
+    /// [`fully_sign_punish`]: Alice::fully_sign_punish
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
+    pub fn delegate_punish(
+        &self,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        core: &CoreArbitratingTransactions<Ctx::Ar>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<PunishDelegation<Ctx::Ar>, Error> {
+        let FullySignedPunish { punish, punish_sig } =
+            self.fully_sign_punish(wallet, alice_parameters, bob_parameters, core, public_offer)?;
+
+        let mut punish = <<Ctx::Ar as Transactions>::Punish>::from_partial(punish);
+        punish.add_witness(alice_parameters.punish.clone(), punish_sig)?;
+
+        Ok(PunishDelegation {
+            cancel: core.cancel.clone(),
+            punish: punish.finalize_and_extract()?,
+            punish_timelock: public_offer.offer.punish_timelock,
+        })
+    }
+
+    /// Validate the cooperative close transaction and Bob's signature received in a
+    /// [`SignedCooperativeClose`] bundle. The cooperative close spends the success path of the
+    /// arbitrating lock, as the buy transaction does, but pays Bob's refund address.
+    ///
+    /// # Safety
+    ///
+    /// [`CoreArbitratingTransactions`] and [`SignedCooperativeClose`] bundles are created by Bob
+    /// and requires extra validation.
+    ///
+    /// _Previously verified data_:
+    ///  * `bob_parameters`: Bob's parameters bundle
+    ///
+    /// _Trusted data_:
+    ///  * `alice_parameters`: Alice's parameters bundle
+    ///  * `public_offer`: Public offer
+    ///
+    /// _Verified data_:
+    ///  * `core`: Core arbitrating transactions bundle
+    ///  * `close`: The cooperative close transaction and signature to verify
+    ///
+    /// # Execution
+    ///
+    ///  * Parse the [`Buyable`] partial transaction in [`SignedCooperativeClose`]
+    ///  * Verify the transaction spends the lock to Bob's refund address
+    ///  * Verify Bob's witness with his buy public key
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
+    pub fn validate_cooperative_close(
+        &self,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        core: &CoreArbitratingTransactions<Ctx::Ar>,
+        public_offer: &PublicOffer<Ctx>,
+        close: &SignedCooperativeClose<Ctx::Ar>,
+    ) -> Result<(), Error> {
+        let ValidatedCoreTransactions {
+            lock, data_lock, ..
+        } = self.validate_core(alice_parameters, bob_parameters, core, public_offer)?;
+
+        let fee_strategy = &public_offer.offer.fee_strategy;
+
+        // Initialize the close transaction based on the buy transaction format.
+        let tx = <<Ctx::Ar as Transactions>::Buy>::from_partial(close.close.clone());
+
+        tx.is_build_on_top_of(&lock)?;
+        tx.verify_template(data_lock, bob_parameters.refund_address.clone())?;
+        <Ctx::Ar as Fee>::validate_fee(tx.as_partial(), TxLabel::Buy, fee_strategy)?;
+
+        // Verify Bob's witness on the success path
+        let msg = tx.generate_witness_message(ScriptPath::Success)?;
+        wallet.verify_signature(&bob_parameters.buy, msg, &close.close_sig)?;
+
+        Ok(())
+    }
+
+    /// Co-sign the cooperative close transaction created by Bob, refunding him immediately
+    /// without waiting for the cancel timelock.
+    ///
+    /// # Safety
+    ///
+    /// This function **MUST NOT** be run once the accordant assets are locked: the cooperative
+    /// close does not reveal Bob's adaptor secret, Alice would not be able to recover her
+    /// accordant assets.
+    ///
+    /// The transaction and Bob's signature are validated with [`validate_cooperative_close`]
+    /// before signing.
+    ///
+    /// Returns the signature inside a [`CosignedCooperativeClose`] bundle.
+    ///
+    /// [`validate_cooperative_close`]: Alice::validate_cooperative_close
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice", offer = %public_offer.short_id()), err)
+    )]
+    pub fn cosign_cooperative_close(
+        &self,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        core: &CoreArbitratingTransactions<Ctx::Ar>,
+        public_offer: &PublicOffer<Ctx>,
+        close: &SignedCooperativeClose<Ctx::Ar>,
+    ) -> Result<CosignedCooperativeClose<Ctx::Ar>, Error> {
+        self.validate_cooperative_close(
+            wallet,
+            alice_parameters,
+            bob_parameters,
+            core,
+            public_offer,
+            close,
+        )?;
+
+        let tx = <<Ctx::Ar as Transactions>::Buy>::from_partial(close.close.clone());
+        let msg = tx.generate_witness_message(ScriptPath::Success)?;
+        let close_sig = wallet.sign_with_key(&alice_parameters.buy, msg)?;
+
+        Ok(CosignedCooperativeClose { close_sig })
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "alice"), err)
+    )]
     pub fn recover_accordant_assets(&self) -> Result<(), Error> {
         todo!()
     }
@@ -679,7 +959,7 @@ where
         lock.verify_target_amount(target_amount)?;
         // Validate that the transaction follows the strategy.
         let fee_strategy = &public_offer.offer.fee_strategy;
-        <Ctx::Ar as Fee>::validate_fee(lock.as_partial(), &fee_strategy)?;
+        <Ctx::Ar as Fee>::validate_fee(lock.as_partial(), TxLabel::Lock, fee_strategy)?;
 
         // Get the three keys, Alice and Bob for refund and Alice's punish key. The keys are
         // needed, along with the timelock for the punish, to create the punishable on-chain
@@ -707,7 +987,7 @@ where
         cancel.is_build_on_top_of(&lock)?;
         cancel.verify_template(data_lock.clone(), punish_lock.clone())?;
         // Validate the fee strategy
-        <Ctx::Ar as Fee>::validate_fee(cancel.as_partial(), &fee_strategy)?;
+        <Ctx::Ar as Fee>::validate_fee(cancel.as_partial(), TxLabel::Cancel, fee_strategy)?;
 
         // Extract the partial transaction from the core arbitrating bundle, this operation should
         // not error if the bundle is well formed.
@@ -720,7 +1000,7 @@ where
         let refund_address = bob_parameters.refund_address.clone();
         refund.verify_template(punish_lock.clone(), refund_address)?;
         // Validate the fee strategy
-        <Ctx::Ar as Fee>::validate_fee(refund.as_partial(), &fee_strategy)?;
+        <Ctx::Ar as Fee>::validate_fee(refund.as_partial(), TxLabel::Refund, fee_strategy)?;
 
         Ok(ValidatedCoreTransactions {
             lock,
@@ -740,6 +1020,8 @@ pub struct Bob<Ctx: Swap> {
     pub refund_address: <Ctx::Ar as Address>::Address,
     /// The fee politic to apply during the swap fee calculation
     pub fee_politic: FeePolitic,
+    /// The address types accepted for the destination addresses revealed by Alice
+    pub address_allowlist: AddressAllowlist<Ctx::Ar>,
 }
 
 impl<Ctx: Swap> Bob<Ctx> {
@@ -748,9 +1030,57 @@ impl<Ctx: Swap> Bob<Ctx> {
         Self {
             refund_address,
             fee_politic,
+            address_allowlist: AddressAllowlist::default(),
         }
     }
 
+    /// Accept only the address types of the allowlist for the destination addresses revealed by
+    /// Alice, the standard address types of the arbitrating blockchain are accepted by default.
+    pub fn with_address_allowlist(mut self, allowlist: AddressAllowlist<Ctx::Ar>) -> Self {
+        self.address_allowlist = allowlist;
+        self
+    }
+
+    /// Validate Alice's reveal when received, before building any transaction with her
+    /// parameters: the destination address must be valid on the network of the offer and allowed
+    /// by the role's allowlist. Return the abort message to send to Alice otherwise.
+    pub fn validate_reveal(
+        &self,
+        reveal: &RevealAliceParameters<Ctx>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<(), Abort> {
+        reveal.validate_address(public_offer.offer.network, &self.address_allowlist)
+    }
+
+    /// Validate Alice's reveal when the swap is taken from a blinded offer made by Alice: the
+    /// opening revealed by Alice must match the commitments of the blinded offer, then the reveal
+    /// is validated against the opened offer as with [`Bob::validate_reveal`]. Return the opened
+    /// public offer, or the abort message to send to Alice otherwise.
+    pub fn validate_blinded_reveal(
+        &self,
+        reveal: &RevealAliceParameters<Ctx>,
+        blinded_offer: &BlindedPublicOffer<Ctx>,
+    ) -> Result<PublicOffer<Ctx>, Abort> {
+        let public_offer = reveal.validate_offer_opening(blinded_offer)?;
+        self.validate_reveal(reveal, &public_offer)?;
+        Ok(public_offer)
+    }
+
+    /// Validate Alice's update of her destination address when received: the new address must be
+    /// part of the set pre-committed in her reveal, valid on the network of the offer, and
+    /// allowed by the role's allowlist. Return the abort message to send to Alice otherwise.
+    pub fn validate_buy_address_update(
+        &self,
+        update: &UpdateBuyAddress<Ctx>,
+        reveal: &RevealAliceParameters<Ctx>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<(), Abort> {
+        update
+            .verify_with_reveal(reveal)
+            .map_err(|e| Abort::new(AbortReason::ValidationFailure).with_body(e.to_string()))?;
+        update.validate_address(public_offer.offer.network, &self.address_allowlist)
+    }
+
     /// Generate Bob's parameters for the protocol execution based on the arbitrating and accordant
     /// seeds and the public offer agreed upon during the negotiation phase.
     ///
@@ -766,6 +1096,10 @@ impl<Ctx: Swap> Bob<Ctx> {
     ///  * The timelock parameters from the public offer
     ///  * The target arbitrating address used by Bob
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob", offer = %public_offer.short_id()), err)
+    )]
     pub fn generate_parameters(
         &self,
         wallet: &impl Wallet<
@@ -777,49 +1111,37 @@ impl<Ctx: Swap> Bob<Ctx> {
         >,
         public_offer: &PublicOffer<Ctx>,
     ) -> Result<BobParameters<Ctx>, Error> {
-        let extra_arbitrating_keys: Result<
-            Vec<TaggedElement<u16, <Ctx::Ar as Keys>::PublicKey>>,
-            Error,
-        > = <Ctx::Ar as Keys>::extra_keys()
+        let extra_arbitrating_keys = <Ctx::Ar as Keys>::extra_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_pubkey(ArbitratingKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
-        let arbitrating_shared_keys: Result<
-            Vec<TaggedElement<SharedKeyId, <Ctx::Ar as SharedPrivateKeys>::SharedPrivateKey>>,
-            Error,
-        > = <Ctx::Ar as SharedPrivateKeys>::shared_keys()
+        let arbitrating_shared_keys = <Ctx::Ar as SharedPrivateKeys>::shared_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
-        let extra_accordant_keys: Result<
-            Vec<TaggedElement<u16, <Ctx::Ac as Keys>::PublicKey>>,
-            Error,
-        > = <Ctx::Ac as Keys>::extra_keys()
+        let extra_accordant_keys = <Ctx::Ac as Keys>::extra_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_pubkey(AccordantKeyId::Extra(tag))?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
-        let accordant_shared_keys: Result<
-            Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey>>,
-            Error,
-        > = <Ctx::Ac as SharedPrivateKeys>::shared_keys()
+        let accordant_shared_keys = <Ctx::Ac as SharedPrivateKeys>::shared_keys()
             .into_iter()
             .map(|tag| {
                 let key = wallet.get_shared_key(tag)?;
                 Ok(TaggedElement::new(tag, key))
             })
-            .collect();
+            .collect::<Result<Vec<_>, Error>>();
 
         let (spend, adaptor, proof) = wallet.generate()?;
 
@@ -841,6 +1163,262 @@ impl<Ctx: Swap> Bob<Ctx> {
         })
     }
 
+    /// Initialize the [`Fundable`] structure with Bob's funding public key and the network
+    /// specified in the public offer. The funding address returned by [`Fundable::get_address`]
+    /// must be funded by an external wallet, the funding transaction is then updated with
+    /// [`Fundable::update`] when seen on-chain by a syncer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob", offer = %public_offer.short_id()), err)
+    )]
+    pub fn initialize_funding(
+        &self,
+        wallet: &impl Wallet<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ac as Keys>::PublicKey,
+            <Ctx::Ar as SharedPrivateKeys>::SharedPrivateKey,
+            <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey,
+            Ctx::Proof,
+        >,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<<Ctx::Ar as Transactions>::Funding, Error> {
+        let funding_key = wallet.get_pubkey(ArbitratingKeyId::Fund)?;
+        Ok(<<Ctx::Ar as Transactions>::Funding as Fundable<
+            Ctx::Ar,
+            <Ctx::Ar as Transactions>::Metadata,
+        >>::initialize(
+            funding_key, public_offer.offer.network
+        )?)
+    }
+
+    /// Verify the funding transaction seen on-chain against the public offer before creating the
+    /// [`Lockable`] transaction on top of it with [`core_arbitrating_transactions`].
+    ///
+    /// # Safety
+    ///
+    /// The funding transaction is created by an external wallet and must be verified, the other
+    /// parameters follow the same requirements as in [`core_arbitrating_transactions`].
+    ///
+    /// # Execution
+    ///
+    ///  * Retreive the amount of assets available in the funding output
+    ///  * Initialize the lock transaction with the targeted amount from the public offer
+    ///  * Set the lowest and highest fee allowed by the [`FeeStrategy`] on the lock transaction
+    ///
+    /// Returns [`UnderFunded`] if the funding output cannot cover the targeted amount and the
+    /// lowest fee, and [`OverFunded`] if the funding output exceeds the targeted amount and the
+    /// highest fee.
+    ///
+    /// [`core_arbitrating_transactions`]: Bob::core_arbitrating_transactions
+    /// [`FeeStrategy`]: crate::blockchain::FeeStrategy
+    /// [`UnderFunded`]: crate::transaction::Error::UnderFunded
+    /// [`OverFunded`]: crate::transaction::Error::OverFunded
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob", offer = %public_offer.short_id()), err)
+    )]
+    pub fn verify_funding(
+        &self,
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        funding: &impl Fundable<Ctx::Ar, <Ctx::Ar as Transactions>::Metadata>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<(), Error> {
+        // The target amount is dictated from the public offer.
+        let target_amount = public_offer.offer.arbitrating_amount;
+        if funding.get_amount()? < target_amount {
+            return Err(transaction::Error::UnderFunded.into());
+        }
+
+        // Create the data structure that represents an on-chain cancelable contract for the
+        // arbitrating blockchain.
+        let cancel_lock = DataLock {
+            timelock: public_offer.offer.cancel_timelock,
+            success: DoubleKeys::new(alice_parameters.buy.clone(), bob_parameters.buy.clone()),
+            failure: DoubleKeys::new(
+                alice_parameters.cancel.clone(),
+                bob_parameters.cancel.clone(),
+            ),
+        };
+
+        let lock = <<Ctx::Ar as Transactions>::Lock as Lockable<
+            Ctx::Ar,
+            <Ctx::Ar as Transactions>::Metadata,
+        >>::initialize(funding, cancel_lock, target_amount)?;
+
+        let fee_strategy = &public_offer.offer.fee_strategy;
+
+        // With the lowest fee the lock output must at least contain the target amount.
+        let mut lowest = <<Ctx::Ar as Transactions>::Lock>::from_partial(lock.as_partial().clone());
+        match <Ctx::Ar as Fee>::set_fee(
+            lowest.as_partial_mut(),
+            TxLabel::Lock,
+            fee_strategy,
+            FeePolitic::Aggressive,
+        ) {
+            Err(FeeStrategyError::NotEnoughAssets) => {
+                return Err(transaction::Error::UnderFunded.into())
+            }
+            res => res?,
+        };
+        if lowest.output_amount() < target_amount {
+            return Err(transaction::Error::UnderFunded.into());
+        }
+
+        // With the highest fee the lock output must not contain more than the target amount.
+        let mut highest = <<Ctx::Ar as Transactions>::Lock>::from_partial(lock.to_partial());
+        match <Ctx::Ar as Fee>::set_fee(
+            highest.as_partial_mut(),
+            TxLabel::Lock,
+            fee_strategy,
+            FeePolitic::Conservative,
+        ) {
+            // The highest fee cannot be paid but the lowest can, the funding is within range.
+            Err(FeeStrategyError::NotEnoughAssets) => return Ok(()),
+            res => res?,
+        };
+        if highest.output_amount() > target_amount {
+            return Err(transaction::Error::OverFunded.into());
+        }
+
+        Ok(())
+    }
+
+    /// Recover a funding transaction that does not match the public offer. The outcome of the
+    /// funding verification is returned as a [`FundingRecovery`] branch:
+    ///
+    ///  * [`FundingRecovery::Proceed`] if the funding is valid, the swap continues with
+    ///    [`core_arbitrating_transactions`]
+    ///  * [`FundingRecovery::RefundExcess`] if the funding is over-funded, the excess is sent to
+    ///    Bob's refund address and the funding address is re-funded with the exact amount, the
+    ///    funding must be updated with the sweep transaction once seen on-chain
+    ///  * [`FundingRecovery::SweepBack`] if the funding is under-funded, all the funds are sent
+    ///    back to Bob's refund address and the swap must be aborted
+    ///
+    /// The fees on the sweep transaction and on the future lock transaction are set according to
+    /// the [`FeeStrategy`] specified in the public offer and the [`FeePolitic`] in `self`.
+    ///
+    /// [`core_arbitrating_transactions`]: Bob::core_arbitrating_transactions
+    /// [`FeeStrategy`]: crate::blockchain::FeeStrategy
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob", offer = %public_offer.short_id()), err)
+    )]
+    pub fn recover_funding(
+        &self,
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        funding: &impl Fundable<Ctx::Ar, <Ctx::Ar as Transactions>::Metadata>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<FundingRecovery<Ctx::Ar>, Error> {
+        let fee_strategy = &public_offer.offer.fee_strategy;
+
+        match self.verify_funding(alice_parameters, bob_parameters, funding, public_offer) {
+            Ok(()) => Ok(FundingRecovery::Proceed),
+            Err(Error::Transaction(transaction::Error::OverFunded)) => {
+                // Compute the fee needed for the lock transaction created on the re-funded output.
+                let target_amount = public_offer.offer.arbitrating_amount;
+                let cancel_lock = DataLock {
+                    timelock: public_offer.offer.cancel_timelock,
+                    success: DoubleKeys::new(
+                        alice_parameters.buy.clone(),
+                        bob_parameters.buy.clone(),
+                    ),
+                    failure: DoubleKeys::new(
+                        alice_parameters.cancel.clone(),
+                        bob_parameters.cancel.clone(),
+                    ),
+                };
+                let mut lock = <<Ctx::Ar as Transactions>::Lock as Lockable<
+                    Ctx::Ar,
+                    <Ctx::Ar as Transactions>::Metadata,
+                >>::initialize(funding, cancel_lock, target_amount)?;
+                let lock_fee = <Ctx::Ar as Fee>::set_fee(
+                    lock.as_partial_mut(),
+                    TxLabel::Lock,
+                    fee_strategy,
+                    self.fee_politic,
+                )?;
+
+                let mut sweep = <<Ctx::Ar as Transactions>::Sweep as Sweepable<
+                    Ctx::Ar,
+                    <Ctx::Ar as Transactions>::Metadata,
+                >>::initialize_refund_excess(
+                    funding,
+                    self.refund_address.clone(),
+                    target_amount,
+                    lock_fee.deducted,
+                )?;
+                <Ctx::Ar as Fee>::set_fee(
+                    sweep.as_partial_mut(),
+                    TxLabel::Sweep,
+                    fee_strategy,
+                    self.fee_politic,
+                )?;
+                Ok(FundingRecovery::RefundExcess(sweep.to_partial()))
+            }
+            Err(Error::Transaction(transaction::Error::UnderFunded)) => {
+                let mut sweep =
+                    <<Ctx::Ar as Transactions>::Sweep as Sweepable<
+                        Ctx::Ar,
+                        <Ctx::Ar as Transactions>::Metadata,
+                    >>::initialize(funding, self.refund_address.clone())?;
+                <Ctx::Ar as Fee>::set_fee(
+                    sweep.as_partial_mut(),
+                    TxLabel::Sweep,
+                    fee_strategy,
+                    self.fee_politic,
+                )?;
+                Ok(FundingRecovery::SweepBack(sweep.to_partial()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sign the [`Sweepable`] transaction returned by [`recover_funding`] with the funding key and
+    /// return the finalized transaction ready to be broadcasted.
+    ///
+    /// # Safety
+    ///
+    /// The sweep transaction is created by Bob and does not require any extra validation.
+    ///
+    /// [`recover_funding`]: Bob::recover_funding
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob"), err)
+    )]
+    pub fn fully_sign_sweep(
+        &self,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+        key_wallet: &impl Wallet<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ac as Keys>::PublicKey,
+            <Ctx::Ar as SharedPrivateKeys>::SharedPrivateKey,
+            <Ctx::Ac as SharedPrivateKeys>::SharedPrivateKey,
+            Ctx::Proof,
+        >,
+        partial_sweep: <Ctx::Ar as Onchain>::PartialTransaction,
+    ) -> Result<<Ctx::Ar as Onchain>::Transaction, Error> {
+        let mut sweep = <<Ctx::Ar as Transactions>::Sweep>::from_partial(partial_sweep);
+
+        // Generate the witness message to sign and sign with the fund key.
+        let msg = sweep.generate_witness_message(ScriptPath::Success)?;
+        let key = key_wallet.get_pubkey(ArbitratingKeyId::Fund)?;
+        let sig = wallet.sign_with_key(&key, msg)?;
+
+        sweep.add_witness(key, sig)?;
+        Ok(sweep.finalize_and_extract()?)
+    }
+
     /// Initialize the core arbitrating transactions composed of: [`Lockable`], [`Cancelable`], and
     /// [`Refundable`] transactions.
     ///
@@ -879,6 +1457,10 @@ impl<Ctx: Swap> Bob<Ctx> {
     ///
     /// [`FeeStrategy`]: crate::blockchain::FeeStrategy
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob", offer = %public_offer.short_id()), err)
+    )]
     pub fn core_arbitrating_transactions(
         &self,
         alice_parameters: &AliceParameters<Ctx>,
@@ -921,7 +1503,7 @@ impl<Ctx: Swap> Bob<Ctx> {
 
         // Ensure that the transaction contains enough assets to pass the fee validation latter.
         let fee_strategy = &public_offer.offer.fee_strategy;
-        <Ctx::Ar as Fee>::validate_fee(lock.as_partial(), &fee_strategy)?;
+        <Ctx::Ar as Fee>::validate_fee(lock.as_partial(), TxLabel::Lock, fee_strategy)?;
 
         // Get the three keys, Alice and Bob for refund and Alice's punish key. The keys are
         // needed, along with the timelock for the punish, to create the punishable on-chain
@@ -946,7 +1528,12 @@ impl<Ctx: Swap> Bob<Ctx> {
         >>::initialize(&lock, cancel_lock, punish_lock.clone())?;
 
         // Set the fees according to the strategy in the offer and the local politic.
-        <Ctx::Ar as Fee>::set_fee(cancel.as_partial_mut(), &fee_strategy, self.fee_politic)?;
+        <Ctx::Ar as Fee>::set_fee(
+            cancel.as_partial_mut(),
+            TxLabel::Cancel,
+            fee_strategy,
+            self.fee_politic,
+        )?;
 
         // Initialize the refund transaction for the cancel transaction, moving the funds out of
         // the punishable lock to Bob's refund address.
@@ -956,7 +1543,12 @@ impl<Ctx: Swap> Bob<Ctx> {
         >>::initialize(&cancel, punish_lock, self.refund_address.clone())?;
 
         // Set the fees according to the strategy in the offer and the local politic.
-        <Ctx::Ar as Fee>::set_fee(refund.as_partial_mut(), &fee_strategy, self.fee_politic)?;
+        <Ctx::Ar as Fee>::set_fee(
+            refund.as_partial_mut(),
+            TxLabel::Refund,
+            fee_strategy,
+            self.fee_politic,
+        )?;
 
         Ok(CoreArbitratingTransactions {
             lock: lock.to_partial(),
@@ -984,6 +1576,10 @@ impl<Ctx: Swap> Bob<Ctx> {
     ///
     /// [`cosign_arbitrating_cancel`]: Bob::cosign_arbitrating_cancel
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob"), err)
+    )]
     pub fn cosign_arbitrating_cancel(
         &self,
         wallet: &impl Sign<
@@ -1006,7 +1602,7 @@ impl<Ctx: Swap> Bob<Ctx> {
         // Generate the witness message to sign and sign with the cancel key.
         let msg = cancel.generate_witness_message(ScriptPath::Failure)?;
         let key = &bob_parameters.cancel;
-        let sig = wallet.sign_with_key(&key, msg)?;
+        let sig = wallet.sign_with_key(key, msg)?;
 
         Ok(CosignedArbitratingCancel { cancel_sig: sig })
     }
@@ -1039,8 +1635,12 @@ impl<Ctx: Swap> Bob<Ctx> {
     ///
     ///  * Parse the [`Refundable`] partial transaction in [`CoreArbitratingTransactions`]
     ///  * Verify the adaptor witness in [`SignedAdaptorRefund`] with the public keys from the
-    ///  parameters bundles
+    ///    parameters bundles
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob"), err)
+    )]
     pub fn validate_adaptor_refund(
         &self,
         wallet: &impl Sign<
@@ -1113,6 +1713,10 @@ impl<Ctx: Swap> Bob<Ctx> {
     /// [`sign_adaptor_buy`]: Bob::sign_adaptor_buy
     /// [`validate_adaptor_refund`]: Bob::validate_adaptor_refund
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob", offer = %public_offer.short_id()), err)
+    )]
     pub fn sign_adaptor_buy(
         &self,
         wallet: &impl Sign<
@@ -1164,14 +1768,19 @@ impl<Ctx: Swap> Bob<Ctx> {
 
         // Set the fees according to the strategy in the offer and the local politic.
         let fee_strategy = &public_offer.offer.fee_strategy;
-        <Ctx::Ar as Fee>::set_fee(buy.as_partial_mut(), &fee_strategy, self.fee_politic)?;
+        <Ctx::Ar as Fee>::set_fee(
+            buy.as_partial_mut(),
+            TxLabel::Buy,
+            fee_strategy,
+            self.fee_politic,
+        )?;
 
         // Generate the witness message to sign and adaptor sign with the buy key and the
         // counter-party adaptor.
         let key = &bob_parameters.buy;
         let adaptor = &alice_parameters.adaptor;
         let msg = buy.generate_witness_message(ScriptPath::Success)?;
-        let sig = wallet.adaptor_sign_with_key(&key, &adaptor, msg)?;
+        let sig = wallet.adaptor_sign_with_key(key, adaptor, msg)?;
 
         Ok(SignedAdaptorBuy {
             buy: buy.to_partial(),
@@ -1201,6 +1810,10 @@ impl<Ctx: Swap> Bob<Ctx> {
     /// [`sign_arbitrating_lock`]: Bob::sign_arbitrating_lock
     /// [`validate_adaptor_refund`]: Bob::validate_adaptor_refund
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob"), err)
+    )]
     pub fn sign_arbitrating_lock(
         &self,
         wallet: &impl Sign<
@@ -1270,6 +1883,10 @@ impl<Ctx: Swap> Bob<Ctx> {
     ///
     /// [`validate_adaptor_refund`]: Bob::validate_adaptor_refund
     ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob"), err)
+    )]
     pub fn fully_sign_refund(
         &self,
         wallet: &impl Sign<
@@ -1293,11 +1910,11 @@ impl<Ctx: Swap> Bob<Ctx> {
         // Generate the witness message to sign and sign with the refund key.
         let msg = refund.generate_witness_message(ScriptPath::Success)?;
         let key = &bob_parameters.refund;
-        let sig = wallet.sign_with_key(&key, msg)?;
+        let sig = wallet.sign_with_key(key, msg)?;
 
         let key = &bob_parameters.adaptor;
         let adapted_sig =
-            wallet.adapt_signature(&key, signed_adaptor_refund.refund_adaptor_sig.clone())?;
+            wallet.adapt_signature(key, signed_adaptor_refund.refund_adaptor_sig.clone())?;
 
         Ok(FullySignedRefund {
             refund_sig: sig,
@@ -1305,16 +1922,156 @@ impl<Ctx: Swap> Bob<Ctx> {
         })
     }
 
+    /// Create and sign the cooperative close transaction, spending the success path of the
+    /// arbitrating lock to Bob's refund address without waiting for the cancel timelock. The
+    /// transaction is then co-signed by Alice with [`Alice::cosign_cooperative_close`].
+    ///
+    /// # Safety
+    ///
+    /// Alice co-signs only before locking the accordant assets, the cooperative close is usable
+    /// only before the buy phase.
+    ///
+    /// _Previously verified data_:
+    ///  * `alice_parameters`: Alice's parameters bundle
+    ///
+    /// _Trusted data_:
+    ///  * `bob_parameters`: Bob's parameters bundle
+    ///  * `core`: Core arbitrating transactions bundle
+    ///  * `public_offer`: Public offer
+    ///
+    /// # Execution
+    ///
+    ///  * Parse the [`Lockable`] partial transaction in [`CoreArbitratingTransactions`]
+    ///  * Generate the [`DataLock`] structure from Alice and Bob parameters and the public offer
+    ///  * Initialize the [`Buyable`] transaction paying Bob's refund address
+    ///  * Generate the witness data and sign it with the buy key
+    ///
+    /// Returns the partial transaction and the signature inside the [`SignedCooperativeClose`]
+    /// bundle.
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob", offer = %public_offer.short_id()), err)
+    )]
+    pub fn sign_cooperative_close(
+        &self,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+        alice_parameters: &AliceParameters<Ctx>,
+        bob_parameters: &BobParameters<Ctx>,
+        core: &CoreArbitratingTransactions<Ctx::Ar>,
+        public_offer: &PublicOffer<Ctx>,
+    ) -> Result<SignedCooperativeClose<Ctx::Ar>, Error> {
+        let lock = <<Ctx::Ar as Transactions>::Lock>::from_partial(core.lock.clone());
+
+        let cancel_lock = DataLock {
+            timelock: public_offer.offer.cancel_timelock,
+            success: DoubleKeys::new(alice_parameters.buy.clone(), bob_parameters.buy.clone()),
+            failure: DoubleKeys::new(
+                alice_parameters.cancel.clone(),
+                bob_parameters.cancel.clone(),
+            ),
+        };
+
+        // The close transaction consumes the success path of the lock, as the buy transaction,
+        // and sends the funds back to Bob's refund address.
+        let mut close =
+            <<Ctx::Ar as Transactions>::Buy as Buyable<
+                Ctx::Ar,
+                <Ctx::Ar as Transactions>::Metadata,
+            >>::initialize(&lock, cancel_lock, bob_parameters.refund_address.clone())?;
+
+        let fee_strategy = &public_offer.offer.fee_strategy;
+        <Ctx::Ar as Fee>::set_fee(
+            close.as_partial_mut(),
+            TxLabel::Buy,
+            fee_strategy,
+            self.fee_politic,
+        )?;
+
+        let msg = close.generate_witness_message(ScriptPath::Success)?;
+        let close_sig = wallet.sign_with_key(&bob_parameters.buy, msg)?;
+
+        Ok(SignedCooperativeClose {
+            close: close.to_partial(),
+            close_sig,
+        })
+    }
+
+    /// Verify Alice's signature on the cooperative close transaction. Once verified both
+    /// signatures can be added to the transaction with the buy public keys and the
+    /// transaction broadcast.
+    ///
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob"), err)
+    )]
+    pub fn validate_cooperative_close(
+        &self,
+        wallet: &impl Sign<
+            <Ctx::Ar as Keys>::PublicKey,
+            <Ctx::Ar as Keys>::PrivateKey,
+            <Ctx::Ar as Signatures>::Message,
+            <Ctx::Ar as Signatures>::Signature,
+            <Ctx::Ar as Signatures>::AdaptorSignature,
+        >,
+        alice_parameters: &AliceParameters<Ctx>,
+        close: &SignedCooperativeClose<Ctx::Ar>,
+        cosigned: &CosignedCooperativeClose<Ctx::Ar>,
+    ) -> Result<(), Error> {
+        let tx = <<Ctx::Ar as Transactions>::Buy>::from_partial(close.close.clone());
+        let msg = tx.generate_witness_message(ScriptPath::Success)?;
+        wallet.verify_signature(&alice_parameters.buy, msg, &cosigned.close_sig)?;
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(role = "bob"), err)
+    )]
     pub fn recover_accordant_assets(&self) -> Result<(), Error> {
         todo!()
     }
 }
 
+/// The state-machine branches available to Bob after verifying the funding transaction against
+/// the public offer, see [`Bob::recover_funding`]. The sweep transactions are returned in their
+/// partial format and must be signed with [`Bob::fully_sign_sweep`].
+#[derive(Debug, Clone)]
+pub enum FundingRecovery<Ar: Onchain> {
+    /// The funding matches the public offer, the swap can proceed.
+    Proceed,
+    /// The funding is over-funded, the sweep transaction refunds the excess and re-funds the
+    /// funding address with the exact amount.
+    RefundExcess(Ar::PartialTransaction),
+    /// The funding is under-funded, the sweep transaction sends all the funds back and the swap
+    /// must be aborted.
+    SweepBack(Ar::PartialTransaction),
+}
+
 /// An arbitrating is the blockchain which will act as the decision engine, the arbitrating
 /// blockchain will use transaction to transfer the funds on both blockchains.
+///
+/// The trait is automatically implemented for any blockchain implementing all the required
+/// capabilities:
+///
+///  * [`Asset`] and [`Address`], [`AddressScript`] to identify the assets and where to send them
+///  * [`Timelock`] to fix the timelocks carried in the offer
+///  * [`Fee`] to apply and validate fee strategies on transactions
+///  * [`Onchain`] and [`Transactions`] to define the arbitrating transactions
+///  * [`Keys`], [`SharedPrivateKeys`], and [`Signatures`] for the cryptographic primitives
+///
+/// Compile errors on a blockchain not implementing [`Arbitrating`] point at the missing
+/// capability.
 pub trait Arbitrating:
     Asset
     + Address
+    + AddressScript
     + Fee
     + Keys
     + Onchain
@@ -1324,9 +2081,37 @@ pub trait Arbitrating:
     + SharedPrivateKeys
     + Clone
     + Eq
+    + Send
+    + Sync
+{
+}
+
+impl<T> Arbitrating for T where
+    T: Asset
+        + Address
+        + AddressScript
+        + Fee
+        + Keys
+        + Onchain
+        + Signatures
+        + Timelock
+        + Transactions
+        + SharedPrivateKeys
+        + Clone
+        + Eq
+        + Send
+        + Sync
 {
 }
 
 /// An accordant is the blockchain which does not need transaction inside the protocol nor
 /// timelocks, it is the blockchain with the less requirements for an atomic swap.
-pub trait Accordant: Asset + Address + Keys + SharedPrivateKeys + Clone + Eq {}
+///
+/// The trait is automatically implemented for any blockchain implementing [`Asset`], [`Address`],
+/// [`Keys`], and [`SharedPrivateKeys`].
+pub trait Accordant: Asset + Address + Keys + SharedPrivateKeys + Clone + Eq + Send + Sync {}
+
+impl<T> Accordant for T where
+    T: Asset + Address + Keys + SharedPrivateKeys + Clone + Eq + Send + Sync
+{
+}
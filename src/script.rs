@@ -1,5 +1,8 @@
 //! Script mechanism used to create the arbitration on one blockchain
 
+#[cfg(feature = "htlc")]
+use bitcoin_hashes::{sha256, Hash};
+
 use crate::blockchain::Timelock;
 use crate::crypto::Keys;
 
@@ -55,3 +58,49 @@ where
     pub success: DoubleKeys<T>,
     pub failure: T::PublicKey,
 }
+
+/// A public key with the hash of the secret its owner reveals when spending, used in the
+/// hash-locked fallback mode. The secret is the owner's share of the accordant spend private key,
+/// so the counter-party learns it on-chain as with an adaptor signature.
+#[cfg(feature = "htlc")]
+#[derive(Debug, Clone)]
+pub struct HashedKey<T>
+where
+    T: Keys,
+{
+    pub key: T::PublicKey,
+    /// The SHA-256 hash of the secret, see [`hash_secret`]
+    pub hash: [u8; 32],
+}
+
+#[cfg(feature = "htlc")]
+impl<T> HashedKey<T>
+where
+    T: Keys,
+{
+    /// Create a new hashed key
+    pub fn new(key: T::PublicKey, hash: [u8; 32]) -> Self {
+        Self { key, hash }
+    }
+}
+
+/// The data used to create a hash time-locked contract in place of a [`DataLock`] on arbitrating
+/// blockchains where adaptor signatures cannot be constructed. The success path reveals the
+/// secret of the success key owner, the failure path is available after the timelock and reveals
+/// the secret of the failure key owner.
+#[cfg(feature = "htlc")]
+#[derive(Debug, Clone)]
+pub struct DataHashLock<T>
+where
+    T: Timelock + Keys,
+{
+    pub timelock: T::Timelock,
+    pub success: HashedKey<T>,
+    pub failure: HashedKey<T>,
+}
+
+/// Return the SHA-256 hash of a secret committed in a [`HashedKey`].
+#[cfg(feature = "htlc")]
+pub fn hash_secret(secret: &[u8]) -> [u8; 32] {
+    sha256::Hash::hash(secret).into_inner()
+}
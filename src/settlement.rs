@@ -0,0 +1,505 @@
+//! Settlement reports summarize a swap once it reached a terminal state: the outcome, the final
+//! transactions on both blockchains with the fees paid, the swapped amounts, the time spent in
+//! each phase of the swap, and the [`FeeLedger`] of the fees set on all the transactions built
+//! during the swap, mined or not. Reports are encodable so they can be exported to accounting
+//! systems.
+//!
+//! Reports also record the addresses used by both participants, so wallets can detect address
+//! reuse across their swap history with [`address_reuse`] and warn users about the privacy
+//! degradation, and makers can refuse counter-parties reusing addresses.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::blockchain::{Address, Asset, Fee};
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::ledger::FeeLedger;
+use crate::role::SwapRole;
+use crate::swap::{Swap, SwapId};
+use crate::transaction::TxLabel;
+
+/// The terminal state of a swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwapOutcome {
+    /// The buy transaction has been mined, both participants received the counter-party assets.
+    Success,
+    /// The refund transaction has been mined, both participants recovered their assets.
+    Refunded,
+    /// The punish transaction has been mined, Alice took Bob's arbitrating assets.
+    Punished,
+}
+
+impl SwapOutcome {
+    /// Return the outcome of a swap given the last arbitrating transaction mined, if the
+    /// transaction is terminal.
+    pub fn from_final_transaction(label: TxLabel) -> Option<Self> {
+        match label {
+            TxLabel::Buy => Some(SwapOutcome::Success),
+            TxLabel::Refund => Some(SwapOutcome::Refunded),
+            TxLabel::Punish => Some(SwapOutcome::Punished),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SwapOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SwapOutcome::Success => write!(f, "Success"),
+            SwapOutcome::Refunded => write!(f, "Refunded"),
+            SwapOutcome::Punished => write!(f, "Punished"),
+        }
+    }
+}
+
+impl Encodable for SwapOutcome {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            SwapOutcome::Success => 0x01u8.consensus_encode(writer),
+            SwapOutcome::Refunded => 0x02u8.consensus_encode(writer),
+            SwapOutcome::Punished => 0x03u8.consensus_encode(writer),
+        }
+    }
+}
+
+impl Decodable for SwapOutcome {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u8 => Ok(SwapOutcome::Success),
+            0x02u8 => Ok(SwapOutcome::Refunded),
+            0x03u8 => Ok(SwapOutcome::Punished),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl_strict_encoding!(SwapOutcome);
+
+/// The phases of a swap, used to report the time spent in each of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwapPhase {
+    /// From the offer acceptance to the exchange of all the parameters and signatures.
+    Setup,
+    /// From the arbitrating lock broadcast to the accordant lock confirmation.
+    Lock,
+    /// From the accordant lock confirmation to the final transaction confirmation.
+    Settlement,
+}
+
+impl Encodable for SwapPhase {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            SwapPhase::Setup => 0x01u8.consensus_encode(writer),
+            SwapPhase::Lock => 0x02u8.consensus_encode(writer),
+            SwapPhase::Settlement => 0x03u8.consensus_encode(writer),
+        }
+    }
+}
+
+impl Decodable for SwapPhase {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u8 => Ok(SwapPhase::Setup),
+            0x02u8 => Ok(SwapPhase::Lock),
+            0x03u8 => Ok(SwapPhase::Settlement),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl_strict_encoding!(SwapPhase);
+
+/// How an address has been used by a participant in a swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AddressUsage {
+    /// The address receiving the counter-party assets.
+    Destination,
+    /// The address receiving the refunded assets.
+    Refund,
+    /// The address funding the arbitrating lock.
+    Funding,
+}
+
+impl fmt::Display for AddressUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressUsage::Destination => write!(f, "Destination"),
+            AddressUsage::Refund => write!(f, "Refund"),
+            AddressUsage::Funding => write!(f, "Funding"),
+        }
+    }
+}
+
+impl Encodable for AddressUsage {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            AddressUsage::Destination => 0x01u8.consensus_encode(writer),
+            AddressUsage::Refund => 0x02u8.consensus_encode(writer),
+            AddressUsage::Funding => 0x03u8.consensus_encode(writer),
+        }
+    }
+}
+
+impl Decodable for AddressUsage {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u8 => Ok(AddressUsage::Destination),
+            0x02u8 => Ok(AddressUsage::Refund),
+            0x03u8 => Ok(AddressUsage::Funding),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl_strict_encoding!(AddressUsage);
+
+/// A transaction of the swap mined on-chain with its final identifier and, if known, the fee paid
+/// by the participant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettledTransaction<F> {
+    pub label: TxLabel,
+    pub txid: Vec<u8>,
+    pub fee: Option<F>,
+}
+
+impl<F> Encodable for SettledTransaction<F>
+where
+    F: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.label.consensus_encode(s)?;
+        len += self.txid.consensus_encode(s)?;
+        Ok(len + self.fee.consensus_encode(s)?)
+    }
+}
+
+impl<F> Decodable for SettledTransaction<F>
+where
+    F: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            label: Decodable::consensus_decode(d)?,
+            txid: Decodable::consensus_decode(d)?,
+            fee: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+/// An address used by a participant of the swap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettledAddress<A> {
+    pub swap_role: SwapRole,
+    pub usage: AddressUsage,
+    pub address: A,
+}
+
+impl<A> Encodable for SettledAddress<A>
+where
+    A: CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_role.consensus_encode(s)?;
+        len += self.usage.consensus_encode(s)?;
+        Ok(len + self.address.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<A> Decodable for SettledAddress<A>
+where
+    A: CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_role: Decodable::consensus_decode(d)?,
+            usage: Decodable::consensus_decode(d)?,
+            address: A::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+/// The time spent in a phase of the swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseDuration {
+    pub phase: SwapPhase,
+    pub duration: Duration,
+}
+
+impl Encodable for PhaseDuration {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.phase.consensus_encode(s)?;
+        Ok(len + self.duration.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for PhaseDuration {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            phase: Decodable::consensus_decode(d)?,
+            duration: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(PhaseDuration);
+
+/// The summary of a swap produced when it reaches a terminal state.
+#[derive(Debug, Clone)]
+pub struct SettlementReport<Ctx: Swap> {
+    pub swap_id: SwapId,
+    pub swap_role: SwapRole,
+    pub outcome: SwapOutcome,
+    pub arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit,
+    pub accordant_amount: <Ctx::Ac as Asset>::AssetUnit,
+    /// The arbitrating transactions mined on-chain, with the fees paid in the arbitrating fee
+    /// asset.
+    pub arbitrating_transactions: Vec<SettledTransaction<<Ctx::Ar as Fee>::FeeAssetUnit>>,
+    /// The accordant transactions mined on-chain, with the fees paid in the accordant asset.
+    pub accordant_transactions: Vec<SettledTransaction<<Ctx::Ac as Asset>::AssetUnit>>,
+    pub phases: Vec<PhaseDuration>,
+    /// The arbitrating addresses used by both participants.
+    pub arbitrating_addresses: Vec<SettledAddress<<Ctx::Ar as Address>::Address>>,
+    /// The accordant addresses used by both participants.
+    pub accordant_addresses: Vec<SettledAddress<<Ctx::Ac as Address>::Address>>,
+    /// The fees set on the arbitrating transactions built during the swap.
+    pub fees: FeeLedger<<Ctx::Ar as Fee>::FeeAssetUnit>,
+}
+
+impl<Ctx> SettlementReport<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create a new report without transactions, phase durations, addresses, nor fees.
+    pub fn new(
+        swap_id: SwapId,
+        swap_role: SwapRole,
+        outcome: SwapOutcome,
+        arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit,
+        accordant_amount: <Ctx::Ac as Asset>::AssetUnit,
+    ) -> Self {
+        Self {
+            swap_id,
+            swap_role,
+            outcome,
+            arbitrating_amount,
+            accordant_amount,
+            arbitrating_transactions: vec![],
+            accordant_transactions: vec![],
+            phases: vec![],
+            arbitrating_addresses: vec![],
+            accordant_addresses: vec![],
+            fees: FeeLedger::new(),
+        }
+    }
+
+    /// Add a mined arbitrating transaction to the report.
+    pub fn with_arbitrating_transaction(
+        mut self,
+        label: TxLabel,
+        txid: Vec<u8>,
+        fee: Option<<Ctx::Ar as Fee>::FeeAssetUnit>,
+    ) -> Self {
+        self.arbitrating_transactions
+            .push(SettledTransaction { label, txid, fee });
+        self
+    }
+
+    /// Add a mined accordant transaction to the report.
+    pub fn with_accordant_transaction(
+        mut self,
+        label: TxLabel,
+        txid: Vec<u8>,
+        fee: Option<<Ctx::Ac as Asset>::AssetUnit>,
+    ) -> Self {
+        self.accordant_transactions
+            .push(SettledTransaction { label, txid, fee });
+        self
+    }
+
+    /// Add the time spent in a phase of the swap to the report.
+    pub fn with_phase_duration(mut self, phase: SwapPhase, duration: Duration) -> Self {
+        self.phases.push(PhaseDuration { phase, duration });
+        self
+    }
+
+    /// Add an arbitrating address used by a participant to the report.
+    pub fn with_arbitrating_address(
+        mut self,
+        swap_role: SwapRole,
+        usage: AddressUsage,
+        address: <Ctx::Ar as Address>::Address,
+    ) -> Self {
+        self.arbitrating_addresses.push(SettledAddress {
+            swap_role,
+            usage,
+            address,
+        });
+        self
+    }
+
+    /// Add an accordant address used by a participant to the report.
+    pub fn with_accordant_address(
+        mut self,
+        swap_role: SwapRole,
+        usage: AddressUsage,
+        address: <Ctx::Ac as Address>::Address,
+    ) -> Self {
+        self.accordant_addresses.push(SettledAddress {
+            swap_role,
+            usage,
+            address,
+        });
+        self
+    }
+
+    /// Set the fees of the transactions built during the swap, e.g. the ledger of the session.
+    pub fn with_fees(mut self, fees: FeeLedger<<Ctx::Ar as Fee>::FeeAssetUnit>) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    /// Return the total duration of the swap, the sum of all the phases.
+    pub fn total_duration(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.duration).sum()
+    }
+
+    /// Return the final identifier of a transaction of the swap, if mined.
+    pub fn txid(&self, label: TxLabel) -> Option<&[u8]> {
+        match label.is_arbitrating() {
+            true => self
+                .arbitrating_transactions
+                .iter()
+                .find(|tx| tx.label == label)
+                .map(|tx| tx.txid.as_ref()),
+            false => self
+                .accordant_transactions
+                .iter()
+                .find(|tx| tx.label == label)
+                .map(|tx| tx.txid.as_ref()),
+        }
+    }
+}
+
+impl<Ctx> Encodable for SettlementReport<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.swap_role.consensus_encode(s)?;
+        len += self.outcome.consensus_encode(s)?;
+        len += self
+            .arbitrating_amount
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self
+            .accordant_amount
+            .as_canonical_bytes()
+            .consensus_encode(s)?;
+        len += self.arbitrating_transactions.consensus_encode(s)?;
+        len += self.accordant_transactions.consensus_encode(s)?;
+        len += self.phases.consensus_encode(s)?;
+        len += self.arbitrating_addresses.consensus_encode(s)?;
+        len += self.accordant_addresses.consensus_encode(s)?;
+        Ok(len + self.fees.consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for SettlementReport<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            swap_id: Decodable::consensus_decode(d)?,
+            swap_role: Decodable::consensus_decode(d)?,
+            outcome: Decodable::consensus_decode(d)?,
+            arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            accordant_amount: <Ctx::Ac as Asset>::AssetUnit::from_canonical_bytes(
+                unwrap_vec_ref!(d).as_ref(),
+            )?,
+            arbitrating_transactions: Decodable::consensus_decode(d)?,
+            accordant_transactions: Decodable::consensus_decode(d)?,
+            phases: Decodable::consensus_decode(d)?,
+            arbitrating_addresses: Decodable::consensus_decode(d)?,
+            accordant_addresses: Decodable::consensus_decode(d)?,
+            fees: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(SettlementReport<Ctx>, Ctx: Swap);
+
+/// A use of an address in a swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressUse {
+    pub swap_id: SwapId,
+    pub swap_role: SwapRole,
+    pub usage: AddressUsage,
+}
+
+/// An address used more than once in the swap history, in the same swap or across swaps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressReuse {
+    /// True if the address is on the arbitrating blockchain
+    pub arbitrating: bool,
+    /// The canonical bytes of the address
+    pub address: Vec<u8>,
+    /// The uses of the address, in the order of the reports
+    pub uses: Vec<AddressUse>,
+}
+
+impl AddressReuse {
+    /// Return true if the address has been used in more than one swap.
+    pub fn is_across_swaps(&self) -> bool {
+        self.uses.iter().any(|u| u.swap_id != self.uses[0].swap_id)
+    }
+
+    /// Return true if the address has been used more than once by the participant.
+    pub fn is_reused_by(&self, swap_role: SwapRole) -> bool {
+        self.uses
+            .iter()
+            .filter(|u| u.swap_role == swap_role)
+            .count()
+            > 1
+    }
+}
+
+/// Flag the addresses used more than once by either participant in the given settlement reports,
+/// e.g. a destination address reused across swaps or a refund address also used to fund the lock.
+/// Reuses are returned ordered by blockchain and address.
+pub fn address_reuse<Ctx>(reports: &[SettlementReport<Ctx>]) -> Vec<AddressReuse>
+where
+    Ctx: Swap,
+{
+    let mut uses: BTreeMap<(bool, Vec<u8>), Vec<AddressUse>> = BTreeMap::new();
+    for report in reports {
+        let mut record = |arbitrating: bool, address: Vec<u8>, swap_role, usage| {
+            uses.entry((arbitrating, address))
+                .or_default()
+                .push(AddressUse {
+                    swap_id: report.swap_id,
+                    swap_role,
+                    usage,
+                })
+        };
+        for a in report.arbitrating_addresses.iter() {
+            record(true, a.address.as_canonical_bytes(), a.swap_role, a.usage);
+        }
+        for a in report.accordant_addresses.iter() {
+            record(false, a.address.as_canonical_bytes(), a.swap_role, a.usage);
+        }
+    }
+    uses.into_iter()
+        .filter(|(_, uses)| uses.len() > 1)
+        .map(|((arbitrating, address), uses)| AddressReuse {
+            arbitrating,
+            address,
+            uses,
+        })
+        .collect()
+}
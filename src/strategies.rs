@@ -0,0 +1,218 @@
+//! Proptest strategies generating the core types, enabled with the `test-utils` feature.
+//!
+//! Downstream crates and the core tests use these strategies to write property tests, e.g. that
+//! every offer round-trips through its encoding or that a fee strategy always bounds the
+//! estimated fee, without duplicating generator code. Non-generic types implement [`Arbitrary`]
+//! with their default strategy, generic types are generated for the [`BtcXmr`] swap pair.
+//!
+//! All generated values are valid: amounts are non-zero, timelocks are block-based, ranges are
+//! ordered, and stall timeouts are within the allowed bounds.
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::Range;
+use std::time::Duration;
+
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+use internet2::{FramingProtocol, RemoteNodeAddr, RemoteSocketAddr};
+use proptest::arbitrary::Arbitrary;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::strategy::BoxedStrategy;
+
+use crate::blockchain::{ConfirmationTarget, FeeStrategy, Network};
+use crate::chain::bitcoin::fee::SatPerVByte;
+use crate::chain::bitcoin::timelock::CSVTimelock;
+use crate::chain::bitcoin::Bitcoin;
+use crate::chain::monero::Monero;
+use crate::chain::pairs::btcxmr::BtcXmr;
+use crate::checkpoint::Checkpoint;
+use crate::negotiation::{Offer, PublicOffer};
+use crate::protocol_message::{Abort, AbortReason};
+use crate::role::SwapRole;
+use crate::swap::SwapId;
+use crate::timeouts::{StallTimeouts, MAX_STALL_TIMEOUT, MIN_STALL_TIMEOUT};
+
+/// The maximum amount of bitcoin generated, in satoshi.
+pub const MAX_BITCOIN_AMOUNT: u64 = 21_000_000 * 100_000_000;
+
+/// The maximum fee rate generated, in satoshi per virtual byte.
+pub const MAX_FEE_RATE: u64 = 10_000;
+
+/// Generate one of the networks.
+pub fn network() -> impl Strategy<Value = Network> {
+    select(Network::ALL.to_vec())
+}
+
+/// Generate one of the swap roles.
+pub fn swap_role() -> impl Strategy<Value = SwapRole> {
+    select(vec![SwapRole::Alice, SwapRole::Bob])
+}
+
+/// Generate a random swap identifier.
+pub fn swap_id() -> impl Strategy<Value = SwapId> {
+    any::<[u8; 32]>().prop_map(SwapId)
+}
+
+/// Generate a non-zero amount of bitcoin up to [`MAX_BITCOIN_AMOUNT`].
+pub fn bitcoin_amount() -> impl Strategy<Value = bitcoin::Amount> {
+    (1..=MAX_BITCOIN_AMOUNT).prop_map(bitcoin::Amount::from_sat)
+}
+
+/// Generate a non-zero amount of monero.
+pub fn monero_amount() -> impl Strategy<Value = monero::Amount> {
+    (1..=u64::MAX).prop_map(monero::Amount::from_pico)
+}
+
+/// Generate a block-based timelock.
+pub fn csv_timelock() -> impl Strategy<Value = CSVTimelock> {
+    (1u32..=0xffff).prop_map(CSVTimelock::new)
+}
+
+/// Generate a non-zero fee rate up to [`MAX_FEE_RATE`].
+pub fn sat_per_vbyte() -> impl Strategy<Value = SatPerVByte> {
+    (1..=MAX_FEE_RATE).prop_map(SatPerVByte::from_sat)
+}
+
+/// Generate a fixed, range, or targeted fee strategy.
+pub fn fee_strategy() -> impl Strategy<Value = FeeStrategy<SatPerVByte>> {
+    let fixed = sat_per_vbyte().prop_map(FeeStrategy::Fixed);
+    let range = (1..=MAX_FEE_RATE, 1..=MAX_FEE_RATE).prop_map(|(a, b)| {
+        FeeStrategy::Range(Range {
+            start: SatPerVByte::from_sat(a.min(b)),
+            end: SatPerVByte::from_sat(a.max(b)),
+        })
+    });
+    let base = prop_oneof![fixed, range];
+    prop_oneof![
+        base.clone(),
+        (base, 1u16..=1008).prop_map(|(strategy, blocks)| {
+            strategy.with_confirmation_target(
+                ConfirmationTarget::new(blocks).expect("target is not null"),
+            )
+        }),
+    ]
+}
+
+/// Generate stall timeouts within [`MIN_STALL_TIMEOUT`] and [`MAX_STALL_TIMEOUT`], with a
+/// precision of one second.
+pub fn stall_timeouts() -> impl Strategy<Value = StallTimeouts> {
+    let timeout =
+        (MIN_STALL_TIMEOUT.as_secs()..=MAX_STALL_TIMEOUT.as_secs()).prop_map(Duration::from_secs);
+    (timeout.clone(), timeout.clone(), timeout.clone(), timeout).prop_map(
+        |(reveal, core_arbitrating_setup, refund_procedure_signatures, lock)| StallTimeouts {
+            reveal,
+            core_arbitrating_setup,
+            refund_procedure_signatures,
+            lock,
+        },
+    )
+}
+
+/// Generate an offer for the Bitcoin-Monero swap pair.
+pub fn offer() -> impl Strategy<Value = Offer<BtcXmr>> {
+    (
+        network(),
+        bitcoin_amount(),
+        monero_amount(),
+        csv_timelock(),
+        csv_timelock(),
+        fee_strategy(),
+        swap_role(),
+        option::of(stall_timeouts()),
+    )
+        .prop_map(
+            |(
+                network,
+                arbitrating_amount,
+                accordant_amount,
+                cancel_timelock,
+                punish_timelock,
+                fee_strategy,
+                maker_role,
+                stall_timeouts,
+            )| Offer {
+                network,
+                arbitrating_blockchain: Bitcoin,
+                accordant_blockchain: Monero,
+                arbitrating_amount,
+                accordant_amount,
+                cancel_timelock,
+                punish_timelock,
+                fee_strategy,
+                maker_role,
+                stall_timeouts,
+            },
+        )
+}
+
+/// Generate the address of a daemon listening on an IPv4 address.
+pub fn node_addr() -> impl Strategy<Value = RemoteNodeAddr> {
+    (
+        any::<[u8; 32]>().prop_filter_map("invalid secret key", |bytes| {
+            SecretKey::from_slice(&bytes).ok()
+        }),
+        any::<[u8; 4]>(),
+        any::<u16>(),
+    )
+        .prop_map(|(secret, ip, port)| RemoteNodeAddr {
+            node_id: PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret),
+            remote_addr: RemoteSocketAddr::with_ip_addr(
+                FramingProtocol::FramedRaw,
+                IpAddr::V4(Ipv4Addr::from(ip)),
+                port,
+            ),
+        })
+}
+
+/// Generate a public offer for the Bitcoin-Monero swap pair, of version 1 unless the offer sets
+/// stall timeouts.
+pub fn public_offer() -> impl Strategy<Value = PublicOffer<BtcXmr>> {
+    (offer(), node_addr()).prop_map(|(offer, addr)| offer.to_public(addr))
+}
+
+/// Generate an abort reason, known or not.
+pub fn abort_reason() -> impl Strategy<Value = AbortReason> {
+    any::<u16>().prop_map(AbortReason::from_u16)
+}
+
+/// Generate an abort message with an optional body.
+pub fn abort() -> impl Strategy<Value = Abort> {
+    (abort_reason(), option::of("[ -~]{0,64}")).prop_map(|(reason, body)| Abort {
+        reason,
+        error_body: body,
+    })
+}
+
+/// Generate the checkpoint of a swap just started, without parameters nor transactions.
+pub fn checkpoint() -> impl Strategy<Value = Checkpoint<BtcXmr>> {
+    (swap_id(), swap_role(), public_offer()).prop_map(|(swap_id, swap_role, public_offer)| {
+        Checkpoint::new(swap_id, swap_role, public_offer)
+    })
+}
+
+macro_rules! impl_arbitrary {
+    ($type:ty, $strategy:ident) => {
+        impl Arbitrary for $type {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                $strategy().boxed()
+            }
+        }
+    };
+}
+
+impl_arbitrary!(Network, network);
+impl_arbitrary!(SwapRole, swap_role);
+impl_arbitrary!(SwapId, swap_id);
+impl_arbitrary!(CSVTimelock, csv_timelock);
+impl_arbitrary!(SatPerVByte, sat_per_vbyte);
+impl_arbitrary!(FeeStrategy<SatPerVByte>, fee_strategy);
+impl_arbitrary!(StallTimeouts, stall_timeouts);
+impl_arbitrary!(Offer<BtcXmr>, offer);
+impl_arbitrary!(PublicOffer<BtcXmr>, public_offer);
+impl_arbitrary!(AbortReason, abort_reason);
+impl_arbitrary!(Abort, abort);
+impl_arbitrary!(Checkpoint<BtcXmr>, checkpoint);
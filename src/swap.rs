@@ -1,14 +1,18 @@
 //! Defines the high level of a swap between a Arbitrating blockchain and an Accordant blockchain.
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::io;
 
-use crate::consensus::CanonicalBytes;
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
 use crate::crypto::Commitment;
 use crate::role::{Accordant, Arbitrating};
 
 /// Specifie the context of a swap, fixing the arbitrating blockchain, the accordant blockchain and
 /// the link between them.
-pub trait Swap: Debug + Clone + Commitment {
+///
+/// The context and all the blockchain types it fixes are [`Send`] and [`Sync`], so the swaps can be
+/// driven from any thread of a daemon.
+pub trait Swap: Debug + Clone + Commitment + Send + Sync {
     /// The arbitrating blockchain concrete implementation used for the swap.
     type Ar: Arbitrating;
 
@@ -16,5 +20,30 @@ pub trait Swap: Debug + Clone + Commitment {
     type Ac: Accordant;
 
     ///// The concrete type to link both blockchain cryptographic groups used in by the signatures.
-    type Proof: Clone + Debug + CanonicalBytes;
+    type Proof: Clone + Debug + CanonicalBytes + Send + Sync;
 }
+
+/// Identifies a swap among all the swaps running in parallel, used in messages exchanged between
+/// the client and the daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SwapId(pub [u8; 32]);
+
+impl fmt::Display for SwapId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl Encodable for SwapId {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        self.0.consensus_encode(writer)
+    }
+}
+
+impl Decodable for SwapId {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self(Decodable::consensus_decode(d)?))
+    }
+}
+
+impl_strict_encoding!(SwapId);
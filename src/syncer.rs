@@ -1,29 +1,55 @@
 //! Tasks used for the daemon to instruct the syncer on what info to track
-
+//!
+//! Tasks are identified by a [`TaskId`] allocated by the daemon with a [`TaskIdAllocator`]. The
+//! allocator is persisted with the swap state so identifiers are never re-used after a restart.
+//! The syncer acknowledges every task with [`TaskAcknowledged`] and reports the end of its
+//! lifecycle with [`TaskCompleted`] or [`TaskFailed`].
+//!
+//! Registering a task with an identifier already active in the syncer is idempotent: the task is
+//! not duplicated and the acknowledgment is flagged as resumed. A resumed swap can thus re-submit
+//! its tasks after a restart and reconcile which watches are still active with
+//! [`Syncer::active_tasks`].
+//!
+//! Transaction tasks carry the [`TxLabel`] of the swap transaction they refer to, and the events
+//! reporting on them echo it, so the daemon does not need to map task identifiers back to the
+//! transactions of the swap.
+//!
+//! Tasks and events are encoded with their type code followed by their content. Types added by a
+//! later version decode as [`Unknown`] and are preserved when re-encoded.
+
+use std::convert::TryFrom;
 use std::error;
 use std::fmt;
 use std::io;
 
 use thiserror::Error;
 
-use crate::consensus::{self, Decodable, Encodable};
+use crate::consensus::{self, deserialize, deserialize_partial, Decodable, Encodable, UnknownCode};
+use crate::transaction::TxLabel;
+
+#[cfg(feature = "test-utils")]
+pub mod mock;
 
 /// Errors when manipulating tasks
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// The task lifetime is expired.
     #[error("Lifetime expired")]
     LifetimeExpired,
+    /// All the task identifiers have been allocated.
+    #[error("Task identifiers exhausted")]
+    TaskIdExhausted,
     /// Any syncer error not part of this list.
     #[error("Syncer error: {0}")]
-    Other(Box<dyn error::Error>),
+    Other(Box<dyn error::Error + Send + Sync>),
 }
 
 impl Error {
     /// Creates a new cryptographic error of type other with an arbitrary payload.
     pub fn new<E>(error: E) -> Self
     where
-        E: Into<Box<dyn error::Error>>,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
     {
         Self::Other(error.into())
     }
@@ -35,7 +61,7 @@ impl Error {
     ///
     /// [`new`]: Error::new
     ///
-    pub fn into_inner(self) -> Option<Box<dyn error::Error>> {
+    pub fn into_inner(self) -> Option<Box<dyn error::Error + Send + Sync>> {
         match self {
             Self::Other(error) => Some(error),
             _ => None,
@@ -49,6 +75,83 @@ impl From<std::io::Error> for Error {
     }
 }
 
+/// Identifier of a task, unique in the daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(pub i32);
+
+impl Encodable for TaskId {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.0.consensus_encode(s)
+    }
+}
+
+impl Decodable for TaskId {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self(i32::consensus_decode(d)?))
+    }
+}
+
+impl_strict_encoding!(TaskId);
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Allocates monotonically increasing task identifiers. The allocator is serializable so it can
+/// be persisted and resumed after a restart without re-using identifiers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskIdAllocator {
+    next: i32,
+}
+
+impl TaskIdAllocator {
+    /// Create a new allocator starting at identifier `0`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resume an allocator after the last allocated identifier `last`, fails if `last` is the
+    /// maximum identifier.
+    pub fn resume(last: TaskId) -> Result<Self, Error> {
+        let next = last.0.checked_add(1).ok_or(Error::TaskIdExhausted)?;
+        Ok(Self { next })
+    }
+
+    /// Allocate a new task identifier, fails once all the identifiers have been allocated.
+    pub fn allocate(&mut self) -> Result<TaskId, Error> {
+        let next = self.next.checked_add(1).ok_or(Error::TaskIdExhausted)?;
+        let id = TaskId(self.next);
+        self.next = next;
+        Ok(id)
+    }
+
+    /// Return the last allocated identifier, if any.
+    pub fn last(&self) -> Option<TaskId> {
+        match self.next {
+            0 => None,
+            next => Some(TaskId(next - 1)),
+        }
+    }
+}
+
+impl Encodable for TaskIdAllocator {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.next.consensus_encode(s)
+    }
+}
+
+impl Decodable for TaskIdAllocator {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            next: i32::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(TaskIdAllocator);
+
 pub trait Syncer {
     fn abort(&mut self, task: Abort) -> Result<(), Error>;
     fn watch_height(&mut self, task: WatchHeight) -> Result<(), Error>;
@@ -56,11 +159,13 @@ pub trait Syncer {
     fn watch_transaction(&mut self, task: WatchTransaction) -> Result<(), Error>;
     fn broadcast_transaction(&mut self, task: BroadcastTransaction) -> Result<(), Error>;
     fn poll(&mut self) -> Result<Vec<Event>, Error>;
+    /// Return the identifiers of the tasks still active in the syncer.
+    fn active_tasks(&self) -> Result<Vec<TaskId>, Error>;
 }
 
 #[derive(Debug, Clone)]
 pub struct Abort {
-    pub id: i32,
+    pub id: TaskId,
 }
 
 impl Encodable for Abort {
@@ -72,7 +177,7 @@ impl Encodable for Abort {
 impl Decodable for Abort {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
         })
     }
 }
@@ -87,7 +192,7 @@ impl fmt::Display for Abort {
 
 #[derive(Debug, Clone)]
 pub struct WatchHeight {
-    pub id: i32,
+    pub id: TaskId,
     pub lifetime: u64,
 
     // Additional data, such as which blockchain to watch the height of
@@ -107,7 +212,7 @@ impl Encodable for WatchHeight {
 impl Decodable for WatchHeight {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
             lifetime: u64::consensus_decode(d)?,
             addendum: Vec::<u8>::consensus_decode(d)?,
         })
@@ -124,7 +229,7 @@ impl fmt::Display for WatchHeight {
 
 #[derive(Debug, Clone)]
 pub struct WatchAddress {
-    pub id: i32,
+    pub id: TaskId,
     pub lifetime: u64,
     pub addendum: Vec<u8>,
 }
@@ -140,7 +245,7 @@ impl Encodable for WatchAddress {
 impl Decodable for WatchAddress {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
             lifetime: u64::consensus_decode(d)?,
             addendum: Vec::<u8>::consensus_decode(d)?,
         })
@@ -157,7 +262,8 @@ impl fmt::Display for WatchAddress {
 
 #[derive(Debug, Clone)]
 pub struct WatchTransaction {
-    pub id: i32,
+    pub id: TaskId,
+    pub label: TxLabel,
     pub lifetime: u64,
     pub hash: Vec<u8>,
     pub confirmation_bound: u16,
@@ -166,6 +272,7 @@ pub struct WatchTransaction {
 impl Encodable for WatchTransaction {
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
         let mut len = self.id.consensus_encode(s)?;
+        len += self.label.consensus_encode(s)?;
         len += self.lifetime.consensus_encode(s)?;
         len += self.hash.consensus_encode(s)?;
         Ok(len + self.confirmation_bound.consensus_encode(s)?)
@@ -175,7 +282,8 @@ impl Encodable for WatchTransaction {
 impl Decodable for WatchTransaction {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
+            label: TxLabel::consensus_decode(d)?,
             lifetime: u64::consensus_decode(d)?,
             hash: Vec::<u8>::consensus_decode(d)?,
             confirmation_bound: u16::consensus_decode(d)?,
@@ -193,13 +301,15 @@ impl fmt::Display for WatchTransaction {
 
 #[derive(Debug, Clone)]
 pub struct BroadcastTransaction {
-    pub id: i32,
+    pub id: TaskId,
+    pub label: TxLabel,
     pub tx: Vec<u8>,
 }
 
 impl Encodable for BroadcastTransaction {
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.id.consensus_encode(s)?;
+        let mut len = self.id.consensus_encode(s)?;
+        len += self.label.consensus_encode(s)?;
         Ok(len + self.tx.consensus_encode(s)?)
     }
 }
@@ -207,7 +317,8 @@ impl Encodable for BroadcastTransaction {
 impl Decodable for BroadcastTransaction {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
+            label: TxLabel::consensus_decode(d)?,
             tx: Vec::<u8>::consensus_decode(d)?,
         })
     }
@@ -221,18 +332,221 @@ impl fmt::Display for BroadcastTransaction {
     }
 }
 
+/// Maximum length of the encoded content of a task or an event.
+pub const MAX_CONTENT_LEN: usize = 1 << 20;
+
+/// A task or an event unknown to this implementation, preserved when re-encoded. Tasks and
+/// events all start with their identifier, the content following it is kept as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unknown {
+    /// The type code of the task or the event.
+    pub code: UnknownCode,
+    /// The identifier of the task.
+    pub id: TaskId,
+    /// The encoded content following the identifier.
+    pub data: Vec<u8>,
+}
+
+impl Unknown {
+    fn from_content(code: u16, content: &[u8]) -> Result<Self, consensus::Error> {
+        let (id, consumed) = deserialize_partial::<TaskId>(content)?;
+        Ok(Self {
+            code: UnknownCode::new(code),
+            id,
+            data: content[consumed..].to_vec(),
+        })
+    }
+}
+
+impl Encodable for Unknown {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.id.consensus_encode(s)?;
+        s.write_all(&self.data)?;
+        Ok(len + self.data.len())
+    }
+}
+
+// Encode the type code and the content of a task or an event prefixed by its length
+fn encode_content<W: io::Write>(
+    code: u16,
+    content: &impl Encodable,
+    s: &mut W,
+) -> Result<usize, io::Error> {
+    let mut bytes = vec![];
+    content.consensus_encode(&mut bytes)?;
+    if bytes.len() > MAX_CONTENT_LEN {
+        return Err(io::Error::other("Content is too long"));
+    }
+    let len = code.consensus_encode(s)? + (bytes.len() as u32).consensus_encode(s)?;
+    s.write_all(&bytes)?;
+    Ok(len + bytes.len())
+}
+
+// Decode the type code and the raw content of a task or an event
+fn decode_content<D: io::Read>(d: &mut D) -> Result<(u16, Vec<u8>), consensus::Error> {
+    let code = u16::consensus_decode(d)?;
+    let len = usize::try_from(u32::consensus_decode(d)?)
+        .ok()
+        .filter(|len| *len <= MAX_CONTENT_LEN)
+        .ok_or(consensus::Error::ParseFailed("Content is too long"))?;
+    let mut content = vec![0u8; len];
+    d.read_exact(&mut content)?;
+    Ok((code, content))
+}
+
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Task {
     Abort(Abort),
     WatchHeight(WatchHeight),
     WatchAddress(WatchAddress),
     WatchTransaction(WatchTransaction),
     BroadcastTransaction(BroadcastTransaction),
+    /// A task unknown to this implementation.
+    Unknown(Unknown),
+}
+
+impl Task {
+    /// Return the identifier of the task.
+    pub fn id(&self) -> TaskId {
+        match self {
+            Task::Abort(task) => task.id,
+            Task::WatchHeight(task) => task.id,
+            Task::WatchAddress(task) => task.id,
+            Task::WatchTransaction(task) => task.id,
+            Task::BroadcastTransaction(task) => task.id,
+            Task::Unknown(task) => task.id,
+        }
+    }
+}
+
+impl Encodable for Task {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            Task::Abort(task) => encode_content(0x01, task, s),
+            Task::WatchHeight(task) => encode_content(0x02, task, s),
+            Task::WatchAddress(task) => encode_content(0x03, task, s),
+            Task::WatchTransaction(task) => encode_content(0x04, task, s),
+            Task::BroadcastTransaction(task) => encode_content(0x05, task, s),
+            Task::Unknown(task) => encode_content(task.code.code(), task, s),
+        }
+    }
+}
+
+impl Decodable for Task {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let (code, content) = decode_content(d)?;
+        match code {
+            0x01 => Ok(Task::Abort(deserialize(&content)?)),
+            0x02 => Ok(Task::WatchHeight(deserialize(&content)?)),
+            0x03 => Ok(Task::WatchAddress(deserialize(&content)?)),
+            0x04 => Ok(Task::WatchTransaction(deserialize(&content)?)),
+            0x05 => Ok(Task::BroadcastTransaction(deserialize(&content)?)),
+            code => Ok(Task::Unknown(Unknown::from_content(code, &content)?)),
+        }
+    }
+}
+
+impl_strict_encoding!(Task);
+
+/// Emitted when the syncer registers a task. `resumed` is true if a task with the same
+/// identifier was already active, in which case the task is not registered twice.
+#[derive(Debug, Clone)]
+pub struct TaskAcknowledged {
+    pub id: TaskId,
+    pub resumed: bool,
+}
+
+impl Encodable for TaskAcknowledged {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.id.consensus_encode(s)?;
+        Ok(len + (self.resumed as u8).consensus_encode(s)?)
+    }
+}
+
+impl Decodable for TaskAcknowledged {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let id = TaskId::consensus_decode(d)?;
+        let resumed = match u8::consensus_decode(d)? {
+            0u8 => false,
+            1u8 => true,
+            _ => return Err(consensus::Error::UnknownType),
+        };
+        Ok(Self { id, resumed })
+    }
+}
+
+impl_strict_encoding!(TaskAcknowledged);
+
+impl fmt::Display for TaskAcknowledged {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "taskacknowledged id {}", self.id)
+    }
+}
+
+/// Emitted when a task reached the end of its lifecycle, e.g. its lifetime expired or the
+/// transaction has been broadcasted, and is no longer active in the syncer.
+#[derive(Debug, Clone)]
+pub struct TaskCompleted {
+    pub id: TaskId,
+}
+
+impl Encodable for TaskCompleted {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.id.consensus_encode(s)
+    }
+}
+
+impl Decodable for TaskCompleted {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            id: TaskId::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(TaskCompleted);
+
+impl fmt::Display for TaskCompleted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "taskcompleted id {}", self.id)
+    }
+}
+
+/// Emitted when the syncer cannot process a task, the task is no longer active.
+#[derive(Debug, Clone)]
+pub struct TaskFailed {
+    pub id: TaskId,
+    pub error: String,
+}
+
+impl Encodable for TaskFailed {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.id.consensus_encode(s)?;
+        Ok(len + self.error.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for TaskFailed {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            id: TaskId::consensus_decode(d)?,
+            error: String::consensus_decode(d)?,
+        })
+    }
+}
+
+impl_strict_encoding!(TaskFailed);
+
+impl fmt::Display for TaskFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "taskfailed id {}: {}", self.id, self.error)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct TaskAborted {
-    pub id: i32,
+    pub id: TaskId,
     pub success_abort: i32,
 }
 
@@ -246,7 +560,7 @@ impl Encodable for TaskAborted {
 impl Decodable for TaskAborted {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
             success_abort: i32::consensus_decode(d)?,
         })
     }
@@ -262,7 +576,7 @@ impl fmt::Display for TaskAborted {
 
 #[derive(Debug, Clone)]
 pub struct HeightChanged {
-    pub id: i32,
+    pub id: TaskId,
     pub block: Vec<u8>,
     pub height: u64,
 }
@@ -278,7 +592,7 @@ impl Encodable for HeightChanged {
 impl Decodable for HeightChanged {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
             block: Vec::<u8>::consensus_decode(d)?,
             height: u64::consensus_decode(d)?,
         })
@@ -295,7 +609,7 @@ impl fmt::Display for HeightChanged {
 
 #[derive(Debug, Clone)]
 pub struct AddressTransaction {
-    pub id: i32,
+    pub id: TaskId,
     pub hash: Vec<u8>,
     pub amount: u64,
     pub block: Vec<u8>,
@@ -313,7 +627,7 @@ impl Encodable for AddressTransaction {
 impl Decodable for AddressTransaction {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
             hash: Vec::<u8>::consensus_decode(d)?,
             amount: u64::consensus_decode(d)?,
             block: Vec::<u8>::consensus_decode(d)?,
@@ -331,7 +645,8 @@ impl fmt::Display for AddressTransaction {
 
 #[derive(Debug, Clone)]
 pub struct TransactionConfirmations {
-    pub id: i32,
+    pub id: TaskId,
+    pub label: TxLabel,
     pub block: Vec<u8>,
     pub confirmations: i32,
 }
@@ -339,6 +654,7 @@ pub struct TransactionConfirmations {
 impl Encodable for TransactionConfirmations {
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
         let mut len = self.id.consensus_encode(s)?;
+        len += self.label.consensus_encode(s)?;
         len += self.block.consensus_encode(s)?;
         Ok(len + self.confirmations.consensus_encode(s)?)
     }
@@ -347,7 +663,8 @@ impl Encodable for TransactionConfirmations {
 impl Decodable for TransactionConfirmations {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
+            label: TxLabel::consensus_decode(d)?,
             block: Vec::<u8>::consensus_decode(d)?,
             confirmations: i32::consensus_decode(d)?,
         })
@@ -364,7 +681,8 @@ impl fmt::Display for TransactionConfirmations {
 
 #[derive(Debug, Clone)]
 pub struct TransactionBroadcasted {
-    pub id: i32,
+    pub id: TaskId,
+    pub label: TxLabel,
     pub tx_len: i16,
     pub tx: Vec<u8>,
     pub success_broadcast: i32,
@@ -373,6 +691,7 @@ pub struct TransactionBroadcasted {
 impl Encodable for TransactionBroadcasted {
     fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
         let mut len = self.id.consensus_encode(s)?;
+        len += self.label.consensus_encode(s)?;
         len += self.tx_len.consensus_encode(s)?;
         len += self.tx.consensus_encode(s)?;
         Ok(len + self.success_broadcast.consensus_encode(s)?)
@@ -382,7 +701,8 @@ impl Encodable for TransactionBroadcasted {
 impl Decodable for TransactionBroadcasted {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         Ok(Self {
-            id: i32::consensus_decode(d)?,
+            id: TaskId::consensus_decode(d)?,
+            label: TxLabel::consensus_decode(d)?,
             tx_len: i16::consensus_decode(d)?,
             tx: Vec::<u8>::consensus_decode(d)?,
             success_broadcast: i32::consensus_decode(d)?,
@@ -399,10 +719,51 @@ impl fmt::Display for TransactionBroadcasted {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Event {
     HeightChanged(HeightChanged),
     AddressTransaction(AddressTransaction),
     TransactionConfirmations(TransactionConfirmations),
     TransactionBroadcasted(TransactionBroadcasted),
     TaskAborted(TaskAborted),
+    TaskAcknowledged(TaskAcknowledged),
+    TaskCompleted(TaskCompleted),
+    TaskFailed(TaskFailed),
+    /// An event unknown to this implementation.
+    Unknown(Unknown),
 }
+
+impl Encodable for Event {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            Event::HeightChanged(event) => encode_content(0x01, event, s),
+            Event::AddressTransaction(event) => encode_content(0x02, event, s),
+            Event::TransactionConfirmations(event) => encode_content(0x03, event, s),
+            Event::TransactionBroadcasted(event) => encode_content(0x04, event, s),
+            Event::TaskAborted(event) => encode_content(0x05, event, s),
+            Event::TaskAcknowledged(event) => encode_content(0x06, event, s),
+            Event::TaskCompleted(event) => encode_content(0x07, event, s),
+            Event::TaskFailed(event) => encode_content(0x08, event, s),
+            Event::Unknown(event) => encode_content(event.code.code(), event, s),
+        }
+    }
+}
+
+impl Decodable for Event {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let (code, content) = decode_content(d)?;
+        match code {
+            0x01 => Ok(Event::HeightChanged(deserialize(&content)?)),
+            0x02 => Ok(Event::AddressTransaction(deserialize(&content)?)),
+            0x03 => Ok(Event::TransactionConfirmations(deserialize(&content)?)),
+            0x04 => Ok(Event::TransactionBroadcasted(deserialize(&content)?)),
+            0x05 => Ok(Event::TaskAborted(deserialize(&content)?)),
+            0x06 => Ok(Event::TaskAcknowledged(deserialize(&content)?)),
+            0x07 => Ok(Event::TaskCompleted(deserialize(&content)?)),
+            0x08 => Ok(Event::TaskFailed(deserialize(&content)?)),
+            code => Ok(Event::Unknown(Unknown::from_content(code, &content)?)),
+        }
+    }
+}
+
+impl_strict_encoding!(Event);
@@ -0,0 +1,356 @@
+//! A mock syncer over an in-memory chain for testing the daemon state machine deterministically.
+//!
+//! The chain is driven by the test with scripted operations: transactions are broadcasted or
+//! injected in the mempool, blocks are mined, and reorgs unconfirm transactions, optionally
+//! replacing them with conflicting ones. Events are queued and returned by [`Syncer::poll`].
+//!
+//! Watched transactions are reported with [`TransactionConfirmations`] events where the number of
+//! confirmations is `0` when the transaction is back in the mempool and `-1` when the transaction
+//! has been replaced by a conflicting one and will never confirm.
+//!
+//! Watch tasks are completed when their lifetime expires, broadcast tasks are completed once
+//! processed.
+
+use bitcoin_hashes::{sha256d, Hash};
+
+use crate::syncer::{
+    Abort, AddressTransaction, BroadcastTransaction, Error, Event, HeightChanged, Syncer,
+    TaskAborted, TaskAcknowledged, TaskCompleted, TaskId, TransactionBroadcasted,
+    TransactionConfirmations, WatchAddress, WatchHeight, WatchTransaction,
+};
+
+/// Number of confirmations reported for a transaction replaced by a conflicting one.
+pub const CONFLICTED: i32 = -1;
+
+/// Computes the identifier of a transaction given its serialization.
+pub type TxId = fn(&[u8]) -> Vec<u8>;
+
+#[derive(Debug, Clone)]
+struct Block {
+    hash: Vec<u8>,
+    transactions: Vec<Vec<u8>>,
+}
+
+/// A syncer over an in-memory chain with scripted blocks and reorgs.
+#[derive(Debug, Clone)]
+pub struct MockSyncer {
+    txid: TxId,
+    blocks: Vec<Block>,
+    mempool: Vec<Vec<u8>>,
+    conflicted: Vec<Vec<u8>>,
+    heights: Vec<WatchHeight>,
+    addresses: Vec<WatchAddress>,
+    transactions: Vec<WatchTransaction>,
+    events: Vec<Event>,
+}
+
+impl Default for MockSyncer {
+    /// Create a mock syncer identifying transactions with their double SHA256.
+    fn default() -> Self {
+        Self::new(|tx| sha256d::Hash::hash(tx).into_inner().to_vec())
+    }
+}
+
+impl MockSyncer {
+    /// Create a mock syncer with an empty chain at height zero, transactions are identified with
+    /// `txid`.
+    pub fn new(txid: TxId) -> Self {
+        Self {
+            txid,
+            blocks: vec![],
+            mempool: vec![],
+            conflicted: vec![],
+            heights: vec![],
+            addresses: vec![],
+            transactions: vec![],
+            events: vec![],
+        }
+    }
+
+    /// Return the current height of the chain.
+    pub fn height(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    /// Return true if the transaction is in the mempool.
+    pub fn in_mempool(&self, hash: &[u8]) -> bool {
+        self.mempool.iter().any(|tx| tx == hash)
+    }
+
+    /// Return the number of confirmations of a transaction, `0` if unconfirmed.
+    pub fn confirmations(&self, hash: &[u8]) -> i32 {
+        self.blocks
+            .iter()
+            .position(|block| block.transactions.iter().any(|tx| tx == hash))
+            .map(|index| (self.blocks.len() - index) as i32)
+            .unwrap_or(0)
+    }
+
+    /// Add a transaction not broadcasted through the syncer in the mempool, e.g. a transaction
+    /// broadcasted by the counter-party.
+    pub fn add_to_mempool(&mut self, hash: Vec<u8>) {
+        if !self.in_mempool(&hash) && self.confirmations(&hash) == 0 {
+            self.conflicted.retain(|tx| *tx != hash);
+            self.mempool.push(hash);
+        }
+    }
+
+    /// Report a transaction paying `amount` to the watched `address`, the transaction is added to
+    /// the mempool.
+    pub fn pay_address(&mut self, address: &[u8], hash: Vec<u8>, amount: u64) {
+        let events: Vec<Event> = self
+            .addresses
+            .iter()
+            .filter(|task| task.addendum == address)
+            .map(|task| {
+                Event::AddressTransaction(AddressTransaction {
+                    id: task.id,
+                    hash: hash.clone(),
+                    amount,
+                    block: vec![],
+                })
+            })
+            .collect();
+        self.events.extend(events);
+        self.add_to_mempool(hash);
+    }
+
+    /// Mine a new block including all the transactions in the mempool.
+    pub fn mine_block(&mut self) {
+        let transactions: Vec<Vec<u8>> = self.mempool.drain(..).collect();
+        self.push_block(transactions);
+    }
+
+    /// Mine `count` new blocks, the first one includes the transactions in the mempool.
+    pub fn mine_blocks(&mut self, count: u64) {
+        for _ in 0..count {
+            self.mine_block();
+        }
+    }
+
+    /// Remove the last `depth` blocks from the chain, their transactions are put back in the
+    /// mempool and the chain height decreases accordingly.
+    pub fn reorg(&mut self, depth: u64) {
+        let depth = (depth as usize).min(self.blocks.len());
+        let removed = self.blocks.split_off(self.blocks.len() - depth);
+        let mut unconfirmed: Vec<Vec<u8>> = removed
+            .into_iter()
+            .flat_map(|block| block.transactions)
+            .collect();
+        for hash in unconfirmed.iter() {
+            self.notify_transaction(hash, 0);
+        }
+        unconfirmed.append(&mut self.mempool);
+        self.mempool = unconfirmed;
+        self.notify_height();
+    }
+
+    /// Replace an unconfirmed transaction by a conflicting one, e.g. after a reorg. The replaced
+    /// transaction is reported as conflicted and the new transaction is added to the mempool.
+    pub fn replace(&mut self, hash: &[u8], conflicting: Vec<u8>) {
+        self.mempool.retain(|tx| tx != hash);
+        self.conflicted.push(hash.to_vec());
+        self.notify_transaction(hash, CONFLICTED);
+        self.add_to_mempool(conflicting);
+    }
+
+    fn push_block(&mut self, transactions: Vec<Vec<u8>>) {
+        let mut data = self
+            .blocks
+            .last()
+            .map(|block| block.hash.clone())
+            .unwrap_or_default();
+        for tx in transactions.iter() {
+            data.extend_from_slice(tx);
+        }
+        data.extend_from_slice(&self.height().to_le_bytes());
+        let hash = sha256d::Hash::hash(&data).into_inner().to_vec();
+        self.blocks.push(Block { hash, transactions });
+        self.notify_height();
+        let hashes: Vec<Vec<u8>> = self
+            .transactions
+            .iter()
+            .map(|task| task.hash.clone())
+            .collect();
+        for hash in hashes.iter() {
+            let confirmations = self.confirmations(hash);
+            if confirmations > 0 {
+                self.notify_transaction(hash, confirmations);
+            }
+        }
+    }
+
+    fn tip(&self) -> Vec<u8> {
+        self.blocks
+            .last()
+            .map(|block| block.hash.clone())
+            .unwrap_or_default()
+    }
+
+    fn expire_tasks(&mut self) {
+        let height = self.height();
+        let mut expired: Vec<TaskId> = vec![];
+        expired.extend(
+            self.heights
+                .iter()
+                .filter(|t| t.lifetime < height)
+                .map(|t| t.id),
+        );
+        expired.extend(
+            self.addresses
+                .iter()
+                .filter(|t| t.lifetime < height)
+                .map(|t| t.id),
+        );
+        expired.extend(
+            self.transactions
+                .iter()
+                .filter(|t| t.lifetime < height)
+                .map(|t| t.id),
+        );
+        self.heights.retain(|task| task.lifetime >= height);
+        self.addresses.retain(|task| task.lifetime >= height);
+        self.transactions.retain(|task| task.lifetime >= height);
+        self.events.extend(
+            expired
+                .into_iter()
+                .map(|id| Event::TaskCompleted(TaskCompleted { id })),
+        );
+    }
+
+    fn is_active(&self, id: TaskId) -> bool {
+        self.heights.iter().any(|t| t.id == id)
+            || self.addresses.iter().any(|t| t.id == id)
+            || self.transactions.iter().any(|t| t.id == id)
+    }
+
+    // Acknowledge the task and return true if it must be registered
+    fn acknowledge(&mut self, id: TaskId) -> bool {
+        let resumed = self.is_active(id);
+        self.events
+            .push(Event::TaskAcknowledged(TaskAcknowledged { id, resumed }));
+        !resumed
+    }
+
+    fn notify_height(&mut self) {
+        self.expire_tasks();
+        let (block, height) = (self.tip(), self.height());
+        let events: Vec<Event> = self
+            .heights
+            .iter()
+            .map(|task| {
+                Event::HeightChanged(HeightChanged {
+                    id: task.id,
+                    block: block.clone(),
+                    height,
+                })
+            })
+            .collect();
+        self.events.extend(events);
+    }
+
+    fn notify_transaction(&mut self, hash: &[u8], confirmations: i32) {
+        let block = match confirmations > 0 {
+            true => self.blocks[self.blocks.len() - confirmations as usize]
+                .hash
+                .clone(),
+            false => vec![],
+        };
+        let events: Vec<Event> = self
+            .transactions
+            .iter()
+            .filter(|task| task.hash == hash)
+            .filter(|task| confirmations <= 0 || confirmations <= task.confirmation_bound as i32)
+            .map(|task| {
+                Event::TransactionConfirmations(TransactionConfirmations {
+                    id: task.id,
+                    label: task.label,
+                    block: block.clone(),
+                    confirmations,
+                })
+            })
+            .collect();
+        self.events.extend(events);
+    }
+}
+
+impl Syncer for MockSyncer {
+    fn abort(&mut self, task: Abort) -> Result<(), Error> {
+        let removed = self.is_active(task.id);
+        self.heights.retain(|t| t.id != task.id);
+        self.addresses.retain(|t| t.id != task.id);
+        self.transactions.retain(|t| t.id != task.id);
+        self.events.push(Event::TaskAborted(TaskAborted {
+            id: task.id,
+            success_abort: removed as i32,
+        }));
+        Ok(())
+    }
+
+    fn watch_height(&mut self, task: WatchHeight) -> Result<(), Error> {
+        if !self.acknowledge(task.id) {
+            return Ok(());
+        }
+        self.events.push(Event::HeightChanged(HeightChanged {
+            id: task.id,
+            block: self.tip(),
+            height: self.height(),
+        }));
+        self.heights.push(task);
+        Ok(())
+    }
+
+    fn watch_address(&mut self, task: WatchAddress) -> Result<(), Error> {
+        if self.acknowledge(task.id) {
+            self.addresses.push(task);
+        }
+        Ok(())
+    }
+
+    fn watch_transaction(&mut self, task: WatchTransaction) -> Result<(), Error> {
+        if !self.acknowledge(task.id) {
+            return Ok(());
+        }
+        let hash = task.hash.clone();
+        self.transactions.push(task);
+        if self.conflicted.contains(&hash) {
+            self.notify_transaction(&hash, CONFLICTED);
+        } else if self.in_mempool(&hash) || self.confirmations(&hash) > 0 {
+            let confirmations = self.confirmations(&hash);
+            self.notify_transaction(&hash, confirmations);
+        }
+        Ok(())
+    }
+
+    fn broadcast_transaction(&mut self, task: BroadcastTransaction) -> Result<(), Error> {
+        self.acknowledge(task.id);
+        let hash = (self.txid)(&task.tx);
+        // A transaction conflicting with an already mined or replaced one cannot be broadcasted
+        let success = !self.conflicted.contains(&hash);
+        if success {
+            self.add_to_mempool(hash);
+        }
+        self.events
+            .push(Event::TransactionBroadcasted(TransactionBroadcasted {
+                id: task.id,
+                label: task.label,
+                tx_len: task.tx.len() as i16,
+                tx: task.tx,
+                success_broadcast: success as i32,
+            }));
+        self.events
+            .push(Event::TaskCompleted(TaskCompleted { id: task.id }));
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Result<Vec<Event>, Error> {
+        Ok(self.events.drain(..).collect())
+    }
+
+    fn active_tasks(&self) -> Result<Vec<TaskId>, Error> {
+        let mut ids: Vec<TaskId> = self.heights.iter().map(|t| t.id).collect();
+        ids.extend(self.addresses.iter().map(|t| t.id));
+        ids.extend(self.transactions.iter().map(|t| t.id));
+        Ok(ids)
+    }
+}
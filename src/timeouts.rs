@@ -0,0 +1,253 @@
+//! Stall detection for the setup phases of a swap, before any asset is locked on-chain.
+//!
+//! A counter-party can grief a swap by never answering a protocol message, keeping the other
+//! participant's daemon busy and the offer unavailable. Offers can carry [`StallTimeouts`], the
+//! maximum time each participant has to send the next expected message, agreed by both sides when
+//! the offer is accepted. A [`StallDetector`] tracks the current phase and tells when the
+//! counter-party exceeded its time so the swap can be dropped early.
+//!
+//! Timeouts are bounded by [`MIN_STALL_TIMEOUT`] and [`MAX_STALL_TIMEOUT`], an offer with values
+//! outside these bounds is rejected. Times are read from a [`Clock`] given by the caller, e.g. the
+//! [`SystemClock`](crate::clock::SystemClock) or a timestamp as a [`Duration`], so the detector
+//! does not depend on the system time.
+
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::clock::Clock;
+use crate::consensus::{self, deserialize, serialize, CanonicalBytes, Decodable, Encodable};
+use crate::negotiation::Offer;
+use crate::swap::Swap;
+
+/// The minimum timeout allowed for a phase, shorter values would drop honest participants on a
+/// slow network.
+pub const MIN_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum timeout allowed for a phase.
+pub const MAX_STALL_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Errors when validating stall timeouts.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The timeout of the phase is shorter than [`MIN_STALL_TIMEOUT`].
+    #[error("Stall timeout of phase {0} is too short")]
+    TooShort(StallPhase),
+    /// The timeout of the phase is longer than [`MAX_STALL_TIMEOUT`].
+    #[error("Stall timeout of phase {0} is too long")]
+    TooLong(StallPhase),
+}
+
+/// The phases of a swap where a participant waits for a message from the counter-party before
+/// funding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StallPhase {
+    /// From the reception of the commitment to the reception of the reveal.
+    Reveal,
+    /// From the reveal to the reception of Bob's core arbitrating setup.
+    CoreArbitratingSetup,
+    /// From the core arbitrating setup to the reception of Alice's refund procedure signatures.
+    RefundProcedureSignatures,
+    /// From the refund procedure signatures to the broadcast of Bob's arbitrating lock.
+    Lock,
+}
+
+impl StallPhase {
+    /// All the phases in the order they happen during a swap.
+    pub const ALL: [StallPhase; 4] = [
+        StallPhase::Reveal,
+        StallPhase::CoreArbitratingSetup,
+        StallPhase::RefundProcedureSignatures,
+        StallPhase::Lock,
+    ];
+
+    /// Return true if the phase happens before Bob commits to the funding of the arbitrating lock
+    /// in his core arbitrating setup, the amounts of the swap can still be renegotiated.
+    pub fn is_pre_funding(&self) -> bool {
+        matches!(self, StallPhase::Reveal)
+    }
+
+    /// Return the phase following this one, if any.
+    pub fn next(&self) -> Option<StallPhase> {
+        match self {
+            StallPhase::Reveal => Some(StallPhase::CoreArbitratingSetup),
+            StallPhase::CoreArbitratingSetup => Some(StallPhase::RefundProcedureSignatures),
+            StallPhase::RefundProcedureSignatures => Some(StallPhase::Lock),
+            StallPhase::Lock => None,
+        }
+    }
+}
+
+impl fmt::Display for StallPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StallPhase::Reveal => write!(f, "Reveal"),
+            StallPhase::CoreArbitratingSetup => write!(f, "CoreArbitratingSetup"),
+            StallPhase::RefundProcedureSignatures => write!(f, "RefundProcedureSignatures"),
+            StallPhase::Lock => write!(f, "Lock"),
+        }
+    }
+}
+
+/// The maximum response time of each phase, carried in the offer. Timeouts are serialized with
+/// a precision of one second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StallTimeouts {
+    pub reveal: Duration,
+    pub core_arbitrating_setup: Duration,
+    pub refund_procedure_signatures: Duration,
+    pub lock: Duration,
+}
+
+impl StallTimeouts {
+    /// Create timeouts with the same value for all the phases.
+    pub fn uniform(timeout: Duration) -> Self {
+        Self {
+            reveal: timeout,
+            core_arbitrating_setup: timeout,
+            refund_procedure_signatures: timeout,
+            lock: timeout,
+        }
+    }
+
+    /// Return the timeout of a phase.
+    pub fn timeout(&self, phase: StallPhase) -> Duration {
+        match phase {
+            StallPhase::Reveal => self.reveal,
+            StallPhase::CoreArbitratingSetup => self.core_arbitrating_setup,
+            StallPhase::RefundProcedureSignatures => self.refund_procedure_signatures,
+            StallPhase::Lock => self.lock,
+        }
+    }
+
+    /// Validate that all the timeouts are within [`MIN_STALL_TIMEOUT`] and
+    /// [`MAX_STALL_TIMEOUT`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn validate(&self) -> Result<(), Error> {
+        for phase in StallPhase::ALL.iter() {
+            let timeout = self.timeout(*phase);
+            if timeout < MIN_STALL_TIMEOUT {
+                return Err(Error::TooShort(*phase));
+            }
+            if timeout > MAX_STALL_TIMEOUT {
+                return Err(Error::TooLong(*phase));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Encodable for StallTimeouts {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = 0;
+        for phase in StallPhase::ALL.iter() {
+            len += self.timeout(*phase).consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl Decodable for StallTimeouts {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let timeouts = Self {
+            reveal: Decodable::consensus_decode(d)?,
+            core_arbitrating_setup: Decodable::consensus_decode(d)?,
+            refund_procedure_signatures: Decodable::consensus_decode(d)?,
+            lock: Decodable::consensus_decode(d)?,
+        };
+        timeouts
+            .validate()
+            .map_err(|_| consensus::Error::ParseFailed("invalid stall timeouts"))?;
+        Ok(timeouts)
+    }
+}
+
+impl CanonicalBytes for StallTimeouts {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        serialize(self)
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        deserialize(bytes)
+    }
+}
+
+impl_strict_encoding!(StallTimeouts);
+
+/// Track the current phase of a swap and detect when the counter-party stalls.
+#[derive(Debug, Clone)]
+pub struct StallDetector {
+    timeouts: StallTimeouts,
+    phase: Option<(StallPhase, Duration)>,
+}
+
+impl StallDetector {
+    /// Create a detector with the given timeouts, no phase is started.
+    pub fn new(timeouts: StallTimeouts) -> Self {
+        Self {
+            timeouts,
+            phase: None,
+        }
+    }
+
+    /// Create a detector from the timeouts agreed in the offer, if any.
+    pub fn from_offer<Ctx: Swap>(offer: &Offer<Ctx>) -> Option<Self> {
+        offer.stall_timeouts.map(Self::new)
+    }
+
+    /// Return the timeouts used by the detector.
+    pub fn timeouts(&self) -> &StallTimeouts {
+        &self.timeouts
+    }
+
+    /// Return the phase currently tracked, if any.
+    pub fn phase(&self) -> Option<StallPhase> {
+        self.phase.map(|(phase, _)| phase)
+    }
+
+    /// Start waiting for the message ending the phase, `now` gives the time at which the previous
+    /// message was received or sent.
+    pub fn start(&mut self, phase: StallPhase, now: impl Clock) {
+        self.phase = Some((phase, now.now()));
+    }
+
+    /// Stop tracking the current phase, e.g. once the arbitrating lock is broadcasted.
+    pub fn stop(&mut self) {
+        self.phase = None;
+    }
+
+    /// Return the time left before the current phase stalls, zero if already stalled, or `None`
+    /// if no phase is started.
+    pub fn remaining(&self, now: impl Clock) -> Option<Duration> {
+        self.phase.map(|(phase, started)| {
+            let elapsed = now.now().checked_sub(started).unwrap_or_default();
+            self.timeouts
+                .timeout(phase)
+                .checked_sub(elapsed)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Return the phase exceeding its timeout, if any. The swap can be dropped by policy when the
+    /// counter-party stalls.
+    pub fn stalled(&self, now: impl Clock) -> Option<StallPhase> {
+        match self.remaining(now) {
+            Some(remaining) if remaining == Duration::from_secs(0) => self.phase(),
+            _ => None,
+        }
+    }
+
+    /// Return true if the current phase exceeded its timeout.
+    pub fn is_stalled(&self, now: impl Clock) -> bool {
+        self.stalled(now).is_some()
+    }
+}
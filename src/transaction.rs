@@ -1,18 +1,20 @@
 //! Arbitrating transaction module
 
 use std::error;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::io;
+use std::str::FromStr;
 
 use thiserror::Error;
 
-use crate::blockchain::{Address, Asset, Fee, Network, Onchain, Timelock};
-use crate::consensus::{self, Decodable, Encodable};
+use crate::blockchain::{Address, Asset, Fee, Network, Onchain, RawTransaction, Timelock};
+use crate::consensus::{self, Decodable, Encodable, UnknownCode};
 use crate::crypto::{Keys, Signatures};
 use crate::script::{DataLock, DataPunishableLock, ScriptPath};
 
 /// A list specifying general categories of transaction error.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Missing signature data.
     #[error("Missing signature")]
@@ -35,12 +37,23 @@ pub enum Error {
     /// Not enough assets to create the transaction.
     #[error("Not enough assets to create the transaction")]
     NotEnoughAssets,
+    /// The funding transaction does not contain enough assets to create the lock transaction with
+    /// the targeted amount and the fee strategy.
+    #[error("The funding transaction is under-funded")]
+    UnderFunded,
+    /// The funding transaction contains more assets than the targeted amount and the maximum fee
+    /// allowed by the fee strategy.
+    #[error("The funding transaction is over-funded")]
+    OverFunded,
     /// Wrong transaction template.
     #[error("Wrong transaction template")]
     WrongTemplate,
     /// The transaction chain validation failed
     #[error("The transaction chain validation failed")]
     InvalidTransactionChain,
+    /// The fee strategy demands more than the output claimed by the punish transaction.
+    #[error("The fee exceeds the punished output")]
+    FeeExceedsPunishedOutput,
     /// Any transaction error not part of this list.
     #[error("Transaction error: {0}")]
     Other(Box<dyn error::Error + Send + Sync>),
@@ -143,6 +156,153 @@ impl Decodable for TxId {
     }
 }
 
+/// Labels all the transactions involved in a swap, on both the arbitrating and the accordant
+/// blockchains. Labels are shared across modules, e.g. in syncer tasks, logs, and progress
+/// reports, instead of relying on strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum TxLabel {
+    /// The arbitrating funding transaction, see [`TxId::Funding`].
+    Funding,
+    /// The arbitrating lock transaction, see [`TxId::Lock`].
+    Lock,
+    /// The arbitrating buy transaction, see [`TxId::Buy`].
+    Buy,
+    /// The arbitrating cancel transaction, see [`TxId::Cancel`].
+    Cancel,
+    /// The arbitrating refund transaction, see [`TxId::Refund`].
+    Refund,
+    /// The arbitrating punish transaction, see [`TxId::Punish`].
+    Punish,
+    /// The arbitrating sweep transaction spending a funding that does not match the public
+    /// offer, see [`Sweepable`].
+    Sweep,
+    /// The transaction locking the assets on the accordant blockchain.
+    AccordantLock,
+    /// The transaction sweeping the locked assets on the accordant blockchain.
+    AccordantSweep,
+    /// A label code unknown to this implementation, preserved when re-encoded.
+    Other(UnknownCode),
+}
+
+impl TxLabel {
+    /// Return `true` if the transaction is on the arbitrating blockchain.
+    pub fn is_arbitrating(&self) -> bool {
+        matches!(
+            self,
+            TxLabel::Funding
+                | TxLabel::Lock
+                | TxLabel::Buy
+                | TxLabel::Cancel
+                | TxLabel::Refund
+                | TxLabel::Punish
+                | TxLabel::Sweep
+        )
+    }
+
+    /// Return the arbitrating transaction identifier if the transaction is on the arbitrating
+    /// blockchain.
+    pub fn tx_id(&self) -> Option<TxId> {
+        match self {
+            TxLabel::Funding => Some(TxId::Funding),
+            TxLabel::Lock => Some(TxId::Lock),
+            TxLabel::Buy => Some(TxId::Buy),
+            TxLabel::Cancel => Some(TxId::Cancel),
+            TxLabel::Refund => Some(TxId::Refund),
+            TxLabel::Punish => Some(TxId::Punish),
+            TxLabel::Sweep
+            | TxLabel::AccordantLock
+            | TxLabel::AccordantSweep
+            | TxLabel::Other(_) => None,
+        }
+    }
+}
+
+impl From<TxId> for TxLabel {
+    fn from(id: TxId) -> Self {
+        match id {
+            TxId::Funding => TxLabel::Funding,
+            TxId::Lock => TxLabel::Lock,
+            TxId::Buy => TxLabel::Buy,
+            TxId::Cancel => TxLabel::Cancel,
+            TxId::Refund => TxLabel::Refund,
+            TxId::Punish => TxLabel::Punish,
+        }
+    }
+}
+
+impl Encodable for TxLabel {
+    fn consensus_encode<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        match self {
+            TxLabel::Funding => 0x01u16.consensus_encode(writer),
+            TxLabel::Lock => 0x02u16.consensus_encode(writer),
+            TxLabel::Buy => 0x03u16.consensus_encode(writer),
+            TxLabel::Cancel => 0x04u16.consensus_encode(writer),
+            TxLabel::Refund => 0x05u16.consensus_encode(writer),
+            TxLabel::Punish => 0x06u16.consensus_encode(writer),
+            TxLabel::AccordantLock => 0x07u16.consensus_encode(writer),
+            TxLabel::AccordantSweep => 0x08u16.consensus_encode(writer),
+            TxLabel::Sweep => 0x09u16.consensus_encode(writer),
+            TxLabel::Other(code) => code.consensus_encode(writer),
+        }
+    }
+}
+
+impl Decodable for TxLabel {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Decodable::consensus_decode(d)? {
+            0x01u16 => Ok(TxLabel::Funding),
+            0x02u16 => Ok(TxLabel::Lock),
+            0x03u16 => Ok(TxLabel::Buy),
+            0x04u16 => Ok(TxLabel::Cancel),
+            0x05u16 => Ok(TxLabel::Refund),
+            0x06u16 => Ok(TxLabel::Punish),
+            0x07u16 => Ok(TxLabel::AccordantLock),
+            0x08u16 => Ok(TxLabel::AccordantSweep),
+            0x09u16 => Ok(TxLabel::Sweep),
+            code => Ok(TxLabel::Other(UnknownCode::new(code))),
+        }
+    }
+}
+
+impl_strict_encoding!(TxLabel);
+
+impl fmt::Display for TxLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxLabel::Funding => write!(f, "Funding"),
+            TxLabel::Lock => write!(f, "Lock"),
+            TxLabel::Buy => write!(f, "Buy"),
+            TxLabel::Cancel => write!(f, "Cancel"),
+            TxLabel::Refund => write!(f, "Refund"),
+            TxLabel::Punish => write!(f, "Punish"),
+            TxLabel::Sweep => write!(f, "Sweep"),
+            TxLabel::AccordantLock => write!(f, "AccordantLock"),
+            TxLabel::AccordantSweep => write!(f, "AccordantSweep"),
+            TxLabel::Other(code) => write!(f, "Other({})", code),
+        }
+    }
+}
+
+impl FromStr for TxLabel {
+    type Err = consensus::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Funding" | "funding" => Ok(TxLabel::Funding),
+            "Lock" | "lock" => Ok(TxLabel::Lock),
+            "Buy" | "buy" => Ok(TxLabel::Buy),
+            "Cancel" | "cancel" => Ok(TxLabel::Cancel),
+            "Refund" | "refund" => Ok(TxLabel::Refund),
+            "Punish" | "punish" => Ok(TxLabel::Punish),
+            "Sweep" | "sweep" => Ok(TxLabel::Sweep),
+            "AccordantLock" | "accordant_lock" => Ok(TxLabel::AccordantLock),
+            "AccordantSweep" | "accordant_sweep" => Ok(TxLabel::AccordantSweep),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
 /// Transaction that requries one or more participants to sign and add witness before finalizing
 /// the transaction.
 pub trait Witnessable<T>
@@ -154,6 +314,10 @@ where
     /// valid transaction.
     fn generate_witness_message(&self, path: ScriptPath) -> Result<T::Message, Error>;
 
+    /// Generate the preimage the witness message is computed from, see
+    /// [`SigHashPreimage`](crate::crypto::SigHashPreimage).
+    fn generate_witness_preimage(&self, path: ScriptPath) -> Result<Vec<u8>, Error>;
+
     /// Add a cooperation to the transaction and store it internally for later usage.
     fn add_witness(&mut self, pubkey: T::PublicKey, sig: T::Signature) -> Result<(), Error>;
 }
@@ -183,6 +347,23 @@ where
         self.finalize()?;
         Ok(self.extract())
     }
+
+    /// Finalize the internal transaction and extract it in the raw format expected by the
+    /// network, ready to be passed to a broadcast adapter.
+    fn finalize_and_extract_raw(&mut self) -> Result<Vec<u8>, Error>
+    where
+        T::Transaction: RawTransaction,
+    {
+        Ok(self.finalize_and_extract()?.to_raw_bytes())
+    }
+
+    /// Return the identifier of the extracted transaction as used on the network.
+    fn extracted_txid(&self) -> Vec<u8>
+    where
+        T::Transaction: RawTransaction,
+    {
+        self.extract().txid()
+    }
 }
 
 /// Implemented by transactions that can be link to form chains of logic. A linkable transaction
@@ -235,7 +416,7 @@ where
 /// system.
 pub trait Fundable<T, O>: Linkable<O>
 where
-    T: Address + Keys + Onchain,
+    T: Address + Asset + Keys + Onchain,
     Self: Sized,
 {
     /// Create a new funding 'output', or equivalent depending on the blockchain and the
@@ -255,6 +436,10 @@ where
     /// Create a raw funding structure based only on the transaction seen on-chain.
     fn raw(tx: T::Transaction) -> Result<Self, Error>;
 
+    /// Return the amount of assets available in the funding output, the transaction must have
+    /// been seen on-chain first.
+    fn get_amount(&self) -> Result<T::AssetUnit, Error>;
+
     /// Return the Farcaster transaction identifier.
     fn get_id(&self) -> TxId {
         TxId::Funding
@@ -446,8 +631,98 @@ where
         destination_target: T::Address,
     ) -> Result<Self, Error>;
 
+    /// Validate that the fee is paid from the claimed `cancel (d)` output only, i.e. the
+    /// transaction does not consume any other input, and that the output left after the fee is
+    /// not empty. The punish path must never depend on the wallet liquidity of the punisher.
+    ///
+    /// The default implementation accepts all transactions so existing implementations keep
+    /// compiling, blockchains where the fee can be paid from other inputs must override it.
+    fn validate_fee_source(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Return the Farcaster transaction identifier.
     fn get_id(&self) -> TxId {
         TxId::Punish
     }
 }
+
+/// Represent a sweepable transaction that consumes the `funding (a)` transaction when the funding
+/// amount does not match the public offer. A sweep either sends all the funds back to their
+/// original owner, aborting the swap, or refunds only the excess of assets and re-funds the
+/// funding address with the exact amount needed to create the `lock (b)` transaction.
+///
+/// # Verify template
+///
+/// This transaction does not have a `verify_template` function as it is created unilaterally and
+/// thus is fully trusted by the creator.
+pub trait Sweepable<T, O>: Transaction<T, O> + Broadcastable<T> + Witnessable<T>
+where
+    T: Keys + Address + Fee + Signatures,
+    Self: Sized,
+{
+    /// Creates a new sweep transaction based on the `funding (a)` transaction sending all the
+    /// assets to the sweep address.
+    ///
+    /// This correspond to the "creator" and initial "updater" roles in BIP 174. Creates a new
+    /// transaction and fill the inputs and outputs data. The fee must be set afterwards on the
+    /// first output.
+    fn initialize(prev: &impl Fundable<T, O>, sweep_target: T::Address) -> Result<Self, Error>;
+
+    /// Creates a new sweep transaction based on the `funding (a)` transaction sending the excess
+    /// of assets to the refund address and re-funding the funding address with the target amount
+    /// plus the fee needed for the `lock (b)` transaction.
+    ///
+    /// The refund of the excess is the first output, the fee must be set afterwards on it. The
+    /// initialization must return an error if the amount is insufficient.
+    fn initialize_refund_excess(
+        prev: &impl Fundable<T, O>,
+        refund_target: T::Address,
+        target_amount: T::AssetUnit,
+        lock_fee: T::AssetUnit,
+    ) -> Result<Self, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{deserialize, serialize};
+
+    #[test]
+    fn tx_labels() {
+        let labels = [
+            TxLabel::Funding,
+            TxLabel::Lock,
+            TxLabel::Buy,
+            TxLabel::Cancel,
+            TxLabel::Refund,
+            TxLabel::Punish,
+            TxLabel::Sweep,
+            TxLabel::AccordantLock,
+            TxLabel::AccordantSweep,
+        ];
+        for label in labels.iter() {
+            assert_eq!(deserialize::<TxLabel>(&serialize(label)).unwrap(), *label);
+            assert_eq!(TxLabel::from_str(&label.to_string()).unwrap(), *label);
+            if let Some(id) = label.tx_id() {
+                assert_eq!(serialize(&id), serialize(label));
+                assert_eq!(TxLabel::from(id), *label);
+            }
+        }
+        assert!(!TxLabel::AccordantSweep.is_arbitrating());
+        assert!(TxLabel::Punish.is_arbitrating());
+        assert!(TxLabel::Sweep.is_arbitrating());
+        // Unknown labels are preserved
+        let unknown = deserialize::<TxLabel>(&[0x42, 0x00]).unwrap();
+        assert!(matches!(unknown, TxLabel::Other(code) if code.code() == 0x42));
+        assert_eq!(unknown.to_string(), "Other(0x0042)");
+        // Known codes never decode as unknown
+        assert_eq!(
+            deserialize::<TxLabel>(&[0x02, 0x00]).unwrap(),
+            TxLabel::Lock
+        );
+        assert_eq!(serialize(&unknown), vec![0x42, 0x00]);
+        assert!(!unknown.is_arbitrating());
+        assert_eq!(unknown.tx_id(), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Known-good serializations used as test vectors. Downstream implementations can assert their
+//! encoding against these values instead of copying them from the repository tests.
+//!
+//! Vectors are hex encoded. Any change in these values is a breaking change of the protocol.
+
+/// An [`Offer`](crate::negotiation::Offer) on testnet for [`BtcXmr`] with an arbitrating amount
+/// of 5 satoshis, an accordant amount of 6 piconeros, a cancel timelock of 7 blocks, a punish
+/// timelock of 8 blocks, a fixed fee strategy of 9 satoshis per virtual byte, and Bob as maker.
+///
+/// [`BtcXmr`]: crate::chain::pairs::btcxmr::BtcXmr
+pub const OFFER: &str = "02000000808000008008000500000000000000080006000000000000000400070000000\
+                         40008000000010800090000000000000002";
+
+/// A [`PublicOffer`](crate::negotiation::PublicOffer) on testnet selling 100000 satoshis for 200
+/// piconeros with cancel and punish timelocks of 10 blocks and a fixed fee strategy of 20
+/// satoshis per virtual byte, published by the node derived from the WIF private key
+/// `L1HKVVLHXiUhecWnwFYF6L3shkf1E12HUmuZTESvBXUdx3yqVP1D` at `tcp://0.0.0.0:9735`.
+pub const PUBLIC_OFFER: &str = "46435357505401000200000080800000800800a086010000000000080\
+                                0c80000000000000004000a00000004000a00000001080014000000000\
+                                000000203b31a0a70343bb46f3db3768296ac5027f9873921b37f8528\
+                                60c690063ff9e4c9000000000000000000000000000000000000000000\
+                                0000000000000000000000000000260700";
+
+/// The [`PUBLIC_OFFER`] in version 2 with stall timeouts of 5 minutes for the reveal phase and
+/// 30 minutes for the other phases.
+pub const PUBLIC_OFFER_V2: &str = "46435357505402000200000080800000800800a0860100000000000800\
+                                   c80000000000000004000a00000004000a000000010800140000000000\
+                                   0000020130002c01000000000000000000000807000000000000000000\
+                                   0008070000000000000000000008070000000000000000000003b31a0a\
+                                   70343bb46f3db3768296ac5027f9873921b37f852860c690063ff9e4c9\
+                                   0000000000000000000000000000000000000000000000000000000000\
+                                   000000000000260700";
+
+/// A serialized public offer with invalid magic bytes, MUST fail to decode.
+pub const INVALID_MAGIC_PUBLIC_OFFER: &str = "474353574150010002000000808000008008a0860100000\
+                                              0000008c800000000000000040a000000040a00000001\
+                                              08140000000000000002";
+
+/// The address used as destination and refund address with [`PUBLIC_OFFER`].
+pub const ADDRESS: &str = "bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk";
+
+/// A Bitcoin coinbase transaction used to fund the swaps of [`PUBLIC_OFFER`].
+pub const FUNDING_TRANSACTION: &str = "02000000000101000000000000000000000000000000000000000\
+                                       0000000000000000000000000ffffffff03510101ffffffff0200\
+                                       f2052a0100000016001490d2e860d4e51f68857d65bfa7d0da32d\
+                                       d6c9b350000000000000000266a24aa21a9ede2f61c3f71d1defd\
+                                       3fa999dfa36953755c690689799962b48bebd836974e8cf901200\
+                                       000000000000000000000000000000000000000000000000000000\
+                                       000000000";
+
+/// A DER encoded ECDSA signature.
+pub const ECDSA_SIGNATURE: &str = "3045022100b75f569de3e57f4f445bcf9e42be9e5b5128f317ab86e451fd\
+                                   fe7be5ffd6a7da0220776b30307b5d761512635dc0394573be7fe17b5300\
+                                   b160340dae370b641bc4ca";
+
+/// A checkpoint in version 1 of the format of the Bob swap `[0x07; 32]` for [`PUBLIC_OFFER`],
+/// without parameters.
+pub const CHECKPOINT_V1: &str =
+    "010007070707070707070707070707070707070707070707070707070707070707\
+                                 070246435357505401000200000080800000800800a0860100000000000800c800\
+                                 00000000000004000a00000004000a00000001080014000000000000000203b31a\
+                                 0a70343bb46f3db3768296ac5027f9873921b37f852860c690063ff9e4c9000000\
+                                 000000000000000000000000000000000000000000000000000000000000000026\
+                                 07000000";
+
+/// The checkpoint [`CHECKPOINT_V1`] in version 2 of the format, without core arbitrating
+/// transactions.
+pub const CHECKPOINT_V2: &str =
+    "020007070707070707070707070707070707070707070707070707070707070707\
+                                 070246435357505401000200000080800000800800a0860100000000000800c800\
+                                 00000000000004000a00000004000a00000001080014000000000000000203b31a\
+                                 0a70343bb46f3db3768296ac5027f9873921b37f852860c690063ff9e4c9000000\
+                                 000000000000000000000000000000000000000000000000000000000000000026\
+                                 0700000000";
+
+/// The checkpoint [`CHECKPOINT_V1`] in version 3 of the format, without secret escrow and
+/// magic bytes.
+pub const CHECKPOINT_V3: &str =
+    "030007070707070707070707070707070707070707070707070707070707070707\
+                                 070246435357505401000200000080800000800800a0860100000000000800c800\
+                                 00000000000004000a00000004000a00000001080014000000000000000203b31a\
+                                 0a70343bb46f3db3768296ac5027f9873921b37f852860c690063ff9e4c9000000\
+                                 000000000000000000000000000000000000000000000000000000000000000026\
+                                 070000000000";
+
+/// The SHA256 digest of the canonical bytes of the offer contained in [`PUBLIC_OFFER`].
+pub const OFFER_DIGEST: &str = "756fd72655d2dffe840bf8da0811ed09455bf16e46f72a2df0abad8c12c11345";
+
+/// The SHA256 digest of the canonical bytes of Alice's parameters generated for [`PUBLIC_OFFER`]
+/// with a wallet seeded with `[2; 32]` and [`ADDRESS`] as destination address.
+pub const ALICE_PARAMETERS_DIGEST: &str =
+    "0b06bb6c157dfc7bf8b6de695c99f21bd5128bc7e1741a02db1d32ed06c0e46d";
+
+/// The SHA256 digest of the canonical bytes of Bob's parameters generated for [`PUBLIC_OFFER`]
+/// with a wallet seeded with `[1; 32]` and [`ADDRESS`] as refund address.
+pub const BOB_PARAMETERS_DIGEST: &str =
+    "07097c1a1a2799856bf3b30e2133117550bc55be89b5a84ee0b1fe6b2ff36ad7";
@@ -0,0 +1,91 @@
+#![cfg(all(feature = "parse-amounts", feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::blockchain::FeeStrategy;
+use farcaster_core::blockchain::{AmountParseError, Network, UnitAmount};
+use farcaster_core::chain::bitcoin::fee::SatPerVByte;
+use farcaster_core::chain::bitcoin::timelock::CSVTimelock;
+use farcaster_core::chain::bitcoin::Bitcoin;
+use farcaster_core::chain::monero::Monero;
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::negotiation::{Buy, Offer, Sell};
+
+use bitcoin::Amount;
+
+#[test]
+fn parse_amounts_with_unit() {
+    assert_eq!(
+        Amount::parse_with_unit("0.5 BTC"),
+        Ok(Amount::from_sat(50_000_000))
+    );
+    assert_eq!(
+        Amount::parse_with_unit("100000 sat"),
+        Ok(Amount::from_sat(100_000))
+    );
+    assert_eq!(
+        monero::Amount::parse_with_unit("2.5 XMR"),
+        Ok(monero::Amount::from_pico(2_500_000_000_000))
+    );
+    assert_eq!(
+        monero::Amount::parse_with_unit("2500000000000 pico"),
+        Ok(monero::Amount::from_pico(2_500_000_000_000))
+    );
+
+    // Units are not interchangeable between assets
+    assert_eq!(
+        Amount::parse_with_unit("2.5 XMR"),
+        Err(AmountParseError::UnknownUnit("xmr".to_string()))
+    );
+    assert_eq!(
+        monero::Amount::parse_with_unit("100000 sat"),
+        Err(AmountParseError::UnknownUnit("sat".to_string()))
+    );
+    assert_eq!(
+        Amount::parse_with_unit("100000"),
+        Err(AmountParseError::MissingUnit)
+    );
+    assert!(matches!(
+        Amount::parse_with_unit("0.000000001 BTC"),
+        Err(AmountParseError::InvalidAmount(_))
+    ));
+
+    // Formatted amounts are parsed back
+    let amount = monero::Amount::from_pico(2_500_000_000_000);
+    assert_eq!(
+        monero::Amount::parse_with_unit(&amount.to_string_with_unit()),
+        Ok(amount)
+    );
+    let amount = Amount::from_sat(123_456);
+    assert_eq!(
+        Amount::parse_with_unit(&amount.to_string_with_unit()),
+        Ok(amount)
+    );
+}
+
+#[test]
+fn build_offer_with_parsed_amounts() {
+    let offer: Offer<BtcXmr> = Sell::some_parsed(Bitcoin, "0.001 BTC")
+        .unwrap()
+        .for_some_parsed(Monero, "200 pico")
+        .unwrap()
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_offer()
+        .expect("an offer");
+    assert_eq!(offer.arbitrating_amount, Amount::from_sat(100000));
+    assert_eq!(offer.accordant_amount, monero::Amount::from_pico(200));
+
+    let offer: Offer<BtcXmr> = Buy::some_parsed(Bitcoin, "100000 sat")
+        .unwrap()
+        .with_parsed(Monero, "0.0000000002 XMR")
+        .unwrap()
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_offer()
+        .expect("an offer");
+    assert_eq!(offer.arbitrating_amount, Amount::from_sat(100000));
+    assert_eq!(offer.accordant_amount, monero::Amount::from_pico(200));
+
+    assert!(Buy::<BtcXmr>::some_parsed(Bitcoin, "100000 pico").is_err());
+}
@@ -0,0 +1,147 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::blockchain::{AmountError, Asset, AtomicAmount, Fee};
+use farcaster_core::chain::bitcoin::Bitcoin;
+use farcaster_core::consensus::CanonicalBytes;
+use farcaster_core::ledger::FeeLedger;
+use farcaster_core::transaction::TxLabel;
+
+use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::Amount;
+
+// One thousand units of an asset with 18 decimals, above 64 bits
+const TOKENS: u128 = 1_000 * 1_000_000_000_000_000_000;
+
+// An asset with 18 decimals counted on 128 bits
+#[derive(Clone, Copy, Debug)]
+struct Token;
+
+impl Asset for Token {
+    type AssetUnit = u128;
+
+    fn from_u32(bytes: u32) -> Option<Self> {
+        match bytes {
+            60 => Some(Self),
+            _ => None,
+        }
+    }
+
+    fn to_u32(&self) -> u32 {
+        60
+    }
+}
+
+fn offered_amount<A: Asset>(value: u128) -> Result<A::AssetUnit, AmountError> {
+    A::AssetUnit::from_atomic(value)
+}
+
+#[test]
+fn encode_variable_width_amounts() {
+    assert!(TOKENS > u64::MAX as u128);
+    let bytes = TOKENS.as_canonical_bytes();
+    assert_eq!(bytes.len(), 9);
+    assert_eq!(u128::from_canonical_bytes(&bytes).unwrap(), TOKENS);
+
+    assert!(0u128.as_canonical_bytes().is_empty());
+    assert_eq!(u64::from_canonical_bytes(&[]).unwrap(), 0);
+    assert_eq!(255u64.as_canonical_bytes(), vec![0xff]);
+    assert_eq!(
+        u128::MAX,
+        u128::from_canonical_bytes(&u128::MAX.as_canonical_bytes()).unwrap()
+    );
+
+    // MUST fail on non-minimal encodings and values wider than the type
+    assert!(u64::from_canonical_bytes(&[0xff, 0x00]).is_err());
+    assert!(u64::from_canonical_bytes(&bytes).is_err());
+    assert!(u128::from_canonical_bytes(&[0x01; 17]).is_err());
+}
+
+#[test]
+fn checked_amount_conversions() {
+    assert_eq!(u128::from_atomic(TOKENS).unwrap().to_atomic(), TOKENS);
+    assert_eq!(u64::from_atomic(TOKENS), Err(AmountError::Overflow));
+    assert_eq!(Amount::from_atomic(TOKENS), Err(AmountError::Overflow));
+    assert_eq!(
+        monero::Amount::from_atomic(2_500_000_000_000).unwrap(),
+        monero::Amount::from_pico(2_500_000_000_000)
+    );
+    assert_eq!(Amount::from_sat(1_000).to_atomic(), 1_000);
+
+    let max = Amount::from_sat(u64::MAX);
+    let one = Amount::from_sat(1);
+    assert_eq!(max.try_add(&one), Err(AmountError::Overflow));
+    assert_eq!(one.try_sub(&max), Err(AmountError::Underflow));
+    assert_eq!(max.try_sub(&max).unwrap(), Amount::from_sat(0));
+    assert_eq!(u128::try_sum(vec![TOKENS, TOKENS]).unwrap(), 2 * TOKENS);
+    assert_eq!(Amount::try_sum(vec![]).unwrap(), Amount::from_sat(0));
+    assert_eq!(Amount::try_sum(vec![max, one]), Err(AmountError::Overflow));
+}
+
+#[test]
+fn offer_amounts_above_64_bits() {
+    let amount = offered_amount::<Token>(TOKENS).unwrap();
+    assert_eq!(amount, TOKENS);
+    assert_eq!(
+        u128::from_canonical_bytes(&amount.as_canonical_bytes()).unwrap(),
+        TOKENS
+    );
+    assert_eq!(
+        offered_amount::<Bitcoin>(TOKENS),
+        Err(AmountError::Overflow)
+    );
+    assert_eq!(
+        offered_amount::<Bitcoin>(1_000).unwrap(),
+        Amount::from_sat(1_000)
+    );
+}
+
+#[test]
+fn checked_fee_totals() {
+    let mut ledger = FeeLedger::new();
+    ledger.record(TxLabel::Lock, TOKENS);
+    ledger.record(TxLabel::Cancel, TOKENS);
+    assert_eq!(ledger.checked_total().unwrap(), 2 * TOKENS);
+    assert_eq!(
+        ledger.checked_total_with(TxLabel::Cancel, 1).unwrap(),
+        TOKENS + 1
+    );
+    assert_eq!(
+        ledger.checked_total_with(TxLabel::Refund, u128::MAX),
+        Err(AmountError::Overflow)
+    );
+
+    let mut ledger = FeeLedger::new();
+    ledger.record(TxLabel::Lock, Amount::from_sat(u64::MAX));
+    ledger.record(TxLabel::Cancel, Amount::from_sat(1));
+    assert_eq!(ledger.checked_total(), Err(AmountError::Overflow));
+}
+
+#[test]
+fn reject_overflowing_fee_inputs() {
+    let txin = TxIn {
+        previous_output: OutPoint::default(),
+        script_sig: Script::default(),
+        sequence: 0xffffffff,
+        witness: vec![],
+    };
+    let tx = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![txin.clone(), txin],
+        output: vec![TxOut {
+            value: 1_000,
+            script_pubkey: Script::default(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    // The counter-party provides previous outputs summing above the maximum amount
+    for input in psbt.inputs.iter_mut() {
+        input.witness_utxo = Some(TxOut {
+            value: u64::MAX,
+            script_pubkey: Script::default(),
+        });
+    }
+    assert!(Bitcoin::fee_paid(&psbt).is_err());
+}
@@ -1,31 +1,44 @@
-use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
 
-use farcaster_core::blockchain::FeePolitic;
-use farcaster_core::consensus::deserialize;
+use farcaster_core::chain::pairs::btcxmr::{
+    accordant_spend, adaptor_point, adaptor_secret, aggregate_spend_shares, verify_revealed_spend,
+    BtcXmr, Wallet,
+};
+use farcaster_core::vectors;
+
+use farcaster_core::blockchain::{AddressAllowlist, FeePolitic, Network as FcNetwork};
+use farcaster_core::bundle::{AliceParameters, BobParameters};
+use farcaster_core::chain::bitcoin::address::AddressType;
+use farcaster_core::chain::bitcoin::Bitcoin;
+use farcaster_core::chain::monero::Monero;
+use farcaster_core::consensus::{deserialize, serialize, Deterministic};
+use farcaster_core::crypto::{
+    self, AccordantKeyId, GenerateKey, GenerateKeyShare, KeyShares, KeyTracker, ProveCrossGroupDleq,
+};
 use farcaster_core::negotiation::PublicOffer;
 use farcaster_core::protocol_message::{
-    CommitAliceParameters, CommitBobParameters, RevealAliceParameters, RevealBobParameters,
+    AbortReason, CommitAliceParameters, CommitBobParameters, ProtocolMessage,
+    RevealAliceParameters, RevealBobParameters, RevealedAddress, RevealedOffer, UpdateBuyAddress,
 };
 use farcaster_core::role::{Alice, Bob};
+use farcaster_core::swap::SwapId;
 
-use bitcoin::Address;
+use bitcoin::bech32::u5;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::util::address::Payload;
+use bitcoin::{Address, Network};
 
 use std::str::FromStr;
 
 fn init_alice() -> (Alice<BtcXmr>, Bob<BtcXmr>, PublicOffer<BtcXmr>) {
-    let hex = "46435357415001000200000080800000800800a0860100000000000800c80000000000000004000\
-               a00000004000a00000001080014000000000000000203b31a0a70343bb46f3db3768296ac5027f9\
-               873921b37f852860c690063ff9e4c90000000000000000000000000000000000000000000000000\
-               000000000000000000000260700";
-
-    let destination_address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk")
-        .expect("Parsable address")
-        .into();
+    let hex = vectors::PUBLIC_OFFER;
+
+    let destination_address =
+        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
     let fee_politic = FeePolitic::Aggressive;
     let alice: Alice<BtcXmr> = Alice::new(destination_address, fee_politic);
-    let refund_address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk")
-        .expect("Parsable address")
-        .into();
+    let refund_address =
+        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
     let bob: Bob<BtcXmr> = Bob::new(refund_address, fee_politic);
 
     let pub_offer: PublicOffer<BtcXmr> =
@@ -75,6 +88,20 @@ fn create_bob_parameters() {
     //assert!(false);
 }
 
+// Bob's parameters once omitted the accordant spend key and could not be decoded back
+#[test]
+fn encode_bob_accordant_spend_key() {
+    let (_, bob, pub_offer) = init_alice();
+    let wallet = Wallet::new([1; 32]);
+    let bob_params = bob.generate_parameters(&wallet, &pub_offer).unwrap();
+
+    let bytes = serialize(&bob_params);
+    let de: BobParameters<BtcXmr> = deserialize(&bytes[..]).unwrap();
+    assert_eq!(de.spend, bob_params.spend);
+    assert_eq!(de.refund_address, bob_params.refund_address);
+    assert_eq!(serialize(&de), bytes);
+}
+
 #[test]
 fn tampered_reveal_must_fail() {
     let (_, bob, pub_offer) = init_alice();
@@ -132,3 +159,461 @@ fn missing_commitment_in_vec() {
 }
 
 // What if you commit in vec but you don't reveal?
+
+#[test]
+fn validate_revealed_address() {
+    let (alice, bob, pub_offer) = init_alice();
+
+    let wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+
+    let mut reveal_bob_params: RevealBobParameters<_> =
+        bob.generate_parameters(&wallet, &pub_offer).unwrap().into();
+    assert!(reveal_bob_params
+        .verify_address(&AddressAllowlist::default())
+        .is_ok());
+    // Address is P2WPKH, MUST error if only P2PKH is allowed
+    let allowlist = AddressAllowlist::<Bitcoin>::new(vec![AddressType::P2pkh]);
+    assert!(reveal_bob_params.verify_address(&allowlist).is_err());
+
+    // Alice validates the reveal with the network of the offer and her allowlist
+    let mut mainnet_offer = pub_offer.clone();
+    mainnet_offer.offer.network = FcNetwork::Mainnet;
+    assert!(alice
+        .validate_reveal(&reveal_bob_params, &mainnet_offer)
+        .is_ok());
+    assert!(alice
+        .validate_reveal(&reveal_bob_params, &pub_offer)
+        .is_err());
+    let alice = alice.with_address_allowlist(allowlist);
+    assert_eq!(
+        alice
+            .validate_reveal(&reveal_bob_params, &mainnet_offer)
+            .unwrap_err()
+            .reason,
+        AbortReason::ValidationFailure
+    );
+
+    // Future witness versions are not allowed unless negotiated
+    reveal_bob_params.address = Address {
+        payload: Payload::WitnessProgram {
+            version: u5::try_from_u8(1).unwrap(),
+            program: vec![0x42; 32],
+        },
+        network: Network::Bitcoin,
+    };
+    assert!(reveal_bob_params
+        .verify_address(&AddressAllowlist::default())
+        .is_err());
+    let allowlist = AddressAllowlist::default().allow(AddressType::WitnessProgram(1));
+    assert!(reveal_bob_params.verify_address(&allowlist).is_ok());
+
+    // Invalid version 0 witness program length are always rejected
+    reveal_bob_params.address = Address {
+        payload: Payload::WitnessProgram {
+            version: u5::try_from_u8(0).unwrap(),
+            program: vec![0x42; 25],
+        },
+        network: Network::Bitcoin,
+    };
+    assert!(reveal_bob_params.verify_address(&allowlist).is_err());
+}
+
+#[test]
+fn reject_address_from_other_network() {
+    let (_, bob, pub_offer) = init_alice();
+
+    let wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+
+    let mut reveal_bob_params: RevealBobParameters<BtcXmr> =
+        bob.generate_parameters(&wallet, &pub_offer).unwrap().into();
+    let allowlist = AddressAllowlist::default();
+    // A mainnet address is only valid in a mainnet swap
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Mainnet, &allowlist)
+        .is_ok());
+    let abort = reveal_bob_params
+        .validate_address(FcNetwork::Testnet, &allowlist)
+        .unwrap_err();
+    assert_eq!(abort.reason, AbortReason::ValidationFailure);
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Local, &allowlist)
+        .is_err());
+
+    reveal_bob_params.address =
+        Address::from_str("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").unwrap();
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Testnet, &allowlist)
+        .is_ok());
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Local, &allowlist)
+        .is_err());
+
+    // Regtest segwit addresses have their own prefix
+    reveal_bob_params.address =
+        Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080").unwrap();
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Local, &allowlist)
+        .is_ok());
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Testnet, &allowlist)
+        .is_err());
+
+    // Base58 prefixes are shared by testnet and regtest
+    reveal_bob_params.address = Address::from_str("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn").unwrap();
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Local, &allowlist)
+        .is_ok());
+    assert!(reveal_bob_params
+        .validate_address(FcNetwork::Testnet, &allowlist)
+        .is_ok());
+}
+
+#[test]
+fn reveal_pre_committed_refund_address() {
+    let (_, bob, pub_offer) = init_alice();
+
+    let wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+
+    let bob_params = bob.generate_parameters(&wallet, &pub_offer).unwrap();
+    let addresses = vec![
+        Address::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").unwrap(),
+        bob_params.refund_address.clone(),
+        Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap(),
+    ];
+
+    // Bob commits to the set of refund addresses and reveals only the one used
+    let commit_bob_params = CommitBobParameters::commit_to_bundle(&wallet, bob_params.clone())
+        .with_refund_addresses(&addresses);
+    let reveal_bob_params: RevealBobParameters<BtcXmr> = bob_params.clone().into();
+    let reveal_bob_params = reveal_bob_params
+        .with_refund_address_proof(&addresses)
+        .expect("Address is part of the set");
+    let de: CommitBobParameters<BtcXmr> = deserialize(&serialize(&commit_bob_params)).unwrap();
+    assert_eq!(
+        de.refund_addresses_root,
+        commit_bob_params.refund_addresses_root
+    );
+    let de: RevealBobParameters<BtcXmr> = deserialize(&serialize(&reveal_bob_params)).unwrap();
+    assert_eq!(
+        de.refund_address_proof,
+        reveal_bob_params.refund_address_proof
+    );
+    assert!(commit_bob_params
+        .verify_with_reveal(&wallet, reveal_bob_params.clone())
+        .is_ok());
+
+    // MUST error if the proof is missing
+    let mut missing_proof = reveal_bob_params.clone();
+    missing_proof.refund_address_proof = None;
+    assert!(commit_bob_params
+        .verify_with_reveal(&wallet, missing_proof)
+        .is_err());
+
+    // MUST error if the proof is for another address of the set
+    let mut other_proof = reveal_bob_params.clone();
+    other_proof.refund_address_proof = RevealBobParameters::<BtcXmr>::from(bob_params.clone())
+        .with_refund_address_proof(&addresses[1..])
+        .unwrap()
+        .refund_address_proof;
+    assert!(commit_bob_params
+        .verify_with_reveal(&wallet, other_proof)
+        .is_err());
+
+    // MUST error if a proof is revealed without pre-commitment
+    let commit_without_root = CommitBobParameters::commit_to_bundle(&wallet, bob_params.clone());
+    assert!(commit_without_root
+        .verify_with_reveal(&wallet, reveal_bob_params)
+        .is_err());
+
+    // An address outside the set cannot be proved
+    let reveal_bob_params: RevealBobParameters<BtcXmr> = bob_params.into();
+    assert!(reveal_bob_params
+        .with_refund_address_proof(&[addresses[0].clone()])
+        .is_none());
+}
+
+#[test]
+fn update_pre_committed_buy_address() {
+    let (alice, bob, pub_offer) = init_alice();
+
+    let wallet = Wallet::new([2; 32]);
+    let mut alice_params = alice.generate_parameters(&wallet, &pub_offer).unwrap();
+    let addresses = vec![
+        alice_params.destination_address.clone(),
+        Address::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").unwrap(),
+        Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap(),
+    ];
+
+    // Alice commits to the set of destination addresses in her reveal
+    let reveal_alice_params = RevealAliceParameters::<BtcXmr>::from(alice_params.clone())
+        .with_destination_addresses(&addresses);
+    let de: RevealAliceParameters<BtcXmr> = deserialize(&serialize(&reveal_alice_params)).unwrap();
+    assert_eq!(
+        de.destination_addresses_root,
+        reveal_alice_params.destination_addresses_root
+    );
+
+    // Alice rotates her destination address before the buy procedure signature
+    let update = UpdateBuyAddress::<BtcXmr>::new(addresses[2].clone(), &addresses).unwrap();
+    let msg = ProtocolMessage::UpdateBuyAddress(update);
+    let update = match deserialize::<ProtocolMessage<BtcXmr>>(&serialize(&msg)).unwrap() {
+        ProtocolMessage::UpdateBuyAddress(update) => update,
+        _ => panic!("an update buy address message"),
+    };
+    assert!(update.verify_with_reveal(&reveal_alice_params).is_ok());
+    assert!(update
+        .validate_address(FcNetwork::Mainnet, &AddressAllowlist::default())
+        .is_ok());
+    let mut mainnet_offer = pub_offer.clone();
+    mainnet_offer.offer.network = FcNetwork::Mainnet;
+    assert!(bob
+        .validate_reveal(&reveal_alice_params, &mainnet_offer)
+        .is_ok());
+    assert!(bob
+        .validate_buy_address_update(&update, &reveal_alice_params, &mainnet_offer)
+        .is_ok());
+    update.apply(&mut alice_params);
+    assert_eq!(alice_params.destination_address, addresses[2]);
+
+    // MUST error if the address is not pre-committed
+    let other_set = UpdateBuyAddress::<BtcXmr>::new(addresses[2].clone(), &addresses[1..]).unwrap();
+    assert!(other_set.verify_with_reveal(&reveal_alice_params).is_err());
+    assert!(bob
+        .validate_buy_address_update(&other_set, &reveal_alice_params, &mainnet_offer)
+        .is_err());
+    let without_root = RevealAliceParameters::<BtcXmr>::from(alice_params);
+    assert!(update.verify_with_reveal(&without_root).is_err());
+    assert!(UpdateBuyAddress::<BtcXmr>::new(addresses[2].clone(), &addresses[..2]).is_none());
+
+    // MUST error if the address is not valid on the network of the swap
+    assert!(update
+        .validate_address(FcNetwork::Testnet, &AddressAllowlist::default())
+        .is_err());
+}
+
+#[test]
+fn reveal_blinded_offer_opening() {
+    let (alice, bob, pub_offer) = init_alice();
+
+    // The addresses of the roles are on mainnet
+    let mut offer = pub_offer.offer.clone();
+    offer.network = FcNetwork::Mainnet;
+    let (blinded, opening) = offer.blind([0x11; 32], [0x22; 32]).unwrap();
+    let blinded_offer = blinded.to_public(pub_offer.daemon_service.clone());
+
+    // Alice is the maker and reveals the opening of her blinded offer to Bob
+    let wallet = Wallet::new([2; 32]);
+    let alice_params = alice.generate_parameters(&wallet, &pub_offer).unwrap();
+    let reveal_alice_params =
+        RevealAliceParameters::<BtcXmr>::from(alice_params).with_offer_opening(opening.clone());
+    let reveal_alice_params: RevealAliceParameters<BtcXmr> =
+        deserialize(&serialize(&reveal_alice_params)).unwrap();
+    assert_eq!(reveal_alice_params.offer_opening(), Some(&opening));
+    let opened = bob
+        .validate_blinded_reveal(&reveal_alice_params, &blinded_offer)
+        .unwrap();
+    assert_eq!(opened.offer, offer);
+
+    // Bob is the maker and reveals the opening of his blinded offer to Alice
+    let bob_params = bob.generate_parameters(&wallet, &pub_offer).unwrap();
+    let reveal_bob_params =
+        RevealBobParameters::<BtcXmr>::from(bob_params).with_offer_opening(opening.clone());
+    let reveal_bob_params: RevealBobParameters<BtcXmr> =
+        deserialize(&serialize(&reveal_bob_params)).unwrap();
+    let opened = alice
+        .validate_blinded_reveal(&reveal_bob_params, &blinded_offer)
+        .unwrap();
+    assert_eq!(opened.offer, offer);
+
+    // MUST abort if the opening does not match the blinded offer commitments
+    let mut mismatched = opening.clone();
+    mismatched.arbitrating_blinding = [0x12; 32];
+    let mut tampered = reveal_alice_params.clone();
+    tampered.offer_opening = Some(mismatched.clone());
+    let abort = bob
+        .validate_blinded_reveal(&tampered, &blinded_offer)
+        .unwrap_err();
+    assert_eq!(abort.reason, AbortReason::ValidationFailure);
+    let mut tampered = reveal_bob_params.clone();
+    tampered.offer_opening = Some(mismatched);
+    assert!(alice
+        .validate_blinded_reveal(&tampered, &blinded_offer)
+        .is_err());
+
+    // MUST abort if the opening is for other amounts
+    let mut tampered = reveal_alice_params.clone();
+    let mut other_amount = opening;
+    other_amount.accordant_amount += monero::Amount::from_pico(1);
+    tampered.offer_opening = Some(other_amount);
+    assert!(tampered.open_offer(&blinded_offer).is_err());
+
+    // MUST abort if the opening is not revealed
+    let mut missing = reveal_alice_params;
+    missing.offer_opening = None;
+    assert!(bob
+        .validate_blinded_reveal(&missing, &blinded_offer)
+        .is_err());
+}
+
+// Serialization is part of the protocol, digests are pinned to detect any change across versions
+#[test]
+fn deterministic_serialization() {
+    let (alice, bob, pub_offer) = init_alice();
+
+    let alice_wallet = Wallet::new([2; 32]);
+    let bob_wallet = Wallet::new([1; 32]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    // Same inputs produce the same bytes
+    assert_eq!(
+        alice_params.canonical_bytes(),
+        alice
+            .generate_parameters(&alice_wallet, &pub_offer)
+            .unwrap()
+            .canonical_bytes()
+    );
+    assert_eq!(
+        bob_params.canonical_bytes(),
+        bob.generate_parameters(&bob_wallet, &pub_offer)
+            .unwrap()
+            .canonical_bytes()
+    );
+
+    // Decoding and re-encoding is the identity
+    let bytes = pub_offer.canonical_bytes();
+    let de: PublicOffer<BtcXmr> = deserialize(&bytes[..]).unwrap();
+    assert_eq!(bytes, de.canonical_bytes());
+    assert_eq!(bytes, serialize(&pub_offer));
+    let bytes = alice_params.canonical_bytes();
+    let de: AliceParameters<BtcXmr> = deserialize(&bytes[..]).unwrap();
+    assert_eq!(bytes, de.canonical_bytes());
+    let bytes = bob_params.canonical_bytes();
+    let de: BobParameters<BtcXmr> = deserialize(&bytes[..]).unwrap();
+    assert_eq!(bytes, de.canonical_bytes());
+
+    // Pinned digests of the serialization
+    assert_eq!(
+        sha256::Hash::hash(&pub_offer.offer.canonical_bytes()).to_string(),
+        vectors::OFFER_DIGEST
+    );
+    assert_eq!(
+        sha256::Hash::hash(&alice_params.canonical_bytes()).to_string(),
+        vectors::ALICE_PARAMETERS_DIGEST
+    );
+    assert_eq!(
+        sha256::Hash::hash(&bob_params.canonical_bytes()).to_string(),
+        vectors::BOB_PARAMETERS_DIGEST
+    );
+}
+
+#[test]
+fn detect_key_reuse_across_swaps() {
+    let (alice, bob, pub_offer) = init_alice();
+
+    let alice_params = alice
+        .generate_parameters(&Wallet::new([2; 32]), &pub_offer)
+        .unwrap();
+    let bob_params = bob
+        .generate_parameters(&Wallet::new([1; 32]), &pub_offer)
+        .unwrap();
+    let other_params = bob
+        .generate_parameters(&Wallet::new([3; 32]), &pub_offer)
+        .unwrap();
+
+    let swap_a = SwapId([0x0a; 32]);
+    let swap_b = SwapId([0x0b; 32]);
+    let mut tracker = KeyTracker::new();
+
+    tracker.track(swap_a, alice_params.public_keys()).unwrap();
+    tracker.track(swap_a, bob_params.public_keys()).unwrap();
+    // Tracking again in the same swap is fine
+    tracker.track(swap_a, bob_params.public_keys()).unwrap();
+    assert_eq!(tracker.swap_of(&bob_params.buy), Some(swap_a));
+
+    // The same counter-party keys in another swap are detected
+    assert!(matches!(
+        tracker.track(swap_b, bob_params.public_keys()),
+        Err(crypto::Error::KeyReused(id)) if id == swap_a
+    ));
+    assert!(tracker.track_key(swap_b, &alice_params.spend).is_err());
+    tracker.track(swap_b, other_params.public_keys()).unwrap();
+
+    // Once released the keys can be used again
+    tracker.release(&swap_a);
+    assert_eq!(tracker.swap_of(&bob_params.buy), None);
+    tracker.track(swap_b, bob_params.public_keys()).unwrap();
+}
+
+#[test]
+fn split_spend_key_across_devices() {
+    let wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+    let (device, second_factor) = wallet.split_spend_key([0xff; 32]).unwrap();
+    assert_ne!(device.private_share(), second_factor.private_share());
+
+    // The counter-party sees the same keys as with an unsplit wallet
+    let (spend, adaptor) = aggregate_spend_shares(&device, &second_factor).unwrap();
+    assert_eq!(spend, wallet.get_pubkey(AccordantKeyId::Spend).unwrap());
+    assert_eq!(adaptor, wallet.project_over().unwrap());
+    assert_eq!(
+        Monero::aggregate_private_shares(&device.private_share(), &second_factor.private_share()),
+        wallet.private_spend_from_seed().unwrap()
+    );
+
+    // A single share does not reveal the spend key
+    assert_ne!(
+        device.get_pubkey_share(AccordantKeyId::Spend).unwrap(),
+        spend
+    );
+    assert!(device.get_pubkey_share(AccordantKeyId::Extra(1)).is_err());
+}
+
+#[test]
+fn derive_adaptor_point_from_spend_key() {
+    let spend = monero::PrivateKey::from_str(
+        "77916d0cd56ed1920aef6ca56d8a41bac915b68e4c46a589e0956e27a7b77404",
+    )
+    .unwrap();
+    let secret = adaptor_secret(&spend).unwrap();
+    assert_eq!(
+        secret.to_string(),
+        "77916d0cd56ed1920aef6ca56d8a41bac915b68e4c46a589e0956e27a7b77404"
+    );
+    assert_eq!(
+        adaptor_point(&spend).unwrap().to_string(),
+        "03a60098e516fc8992b3ae8894405d4de41f348accbe0dccc383cfc88870e12c8f"
+    );
+    assert_eq!(accordant_spend(&secret).unwrap(), spend);
+    // Secrets above the order of ed25519 are not the image of a spend key
+    let large = bitcoin::secp256k1::SecretKey::from_slice(&[0x7f; 32]).unwrap();
+    assert!(accordant_spend(&large).is_err());
+    assert!(adaptor_secret(&monero::PrivateKey::from_slice(&[0; 32]).unwrap()).is_err());
+
+    // The wallet proves the same mapping it reveals
+    let wallet = Wallet::new([0x42; 32]);
+    let (public_spend, adaptor, _) = wallet.generate().unwrap();
+    assert_eq!(adaptor, wallet.project_over().unwrap());
+    let private_spend = wallet.private_spend_from_seed().unwrap();
+    assert_eq!(adaptor_point(&private_spend).unwrap(), adaptor);
+    assert!(verify_revealed_spend(&private_spend, &public_spend, &adaptor).is_ok());
+    assert!(matches!(
+        verify_revealed_spend(&spend, &public_spend, &adaptor),
+        Err(crypto::Error::InvalidProof)
+    ));
+    assert!(verify_revealed_spend(&spend, &public_spend, &adaptor_point(&spend).unwrap()).is_err());
+}
@@ -0,0 +1,264 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::blockchain::Network;
+use farcaster_core::chain::bitcoin::local::LocalParams;
+use farcaster_core::chain::bitcoin::params::ChainParams as BtcChainParams;
+use farcaster_core::chain::bitcoin::Bitcoin;
+use farcaster_core::chain::monero::params::{AddressPrefixes, ChainParams as XmrChainParams};
+use farcaster_core::syncer::TaskId;
+use farcaster_core::transaction::TxLabel;
+
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::hashes::Hash;
+use bitcoin::Address;
+
+use std::str::FromStr;
+
+#[test]
+fn bitcoin_chain_params() {
+    let mainnet = BtcChainParams::mainnet();
+    assert_eq!(mainnet, Bitcoin::chain_params(Network::Mainnet));
+    assert_eq!(mainnet.magic, 0xd9b4bef9);
+    let signet = BtcChainParams::signet();
+    assert_eq!(signet.magic, bitcoin::Network::Signet.magic());
+    assert_eq!(
+        signet.genesis_hash,
+        genesis_block(bitcoin::Network::Signet).block_hash()
+    );
+    assert_eq!(
+        BtcChainParams::regtest(),
+        BtcChainParams::from(LocalParams::regtest())
+    );
+
+    // Addresses are encoded as on the public networks
+    for addr in [
+        "bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk",
+        "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+        "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+    ]
+    .iter()
+    {
+        let address = Address::from_str(addr).unwrap();
+        assert_eq!(mainnet.encode_address(&address).unwrap(), *addr);
+        assert_eq!(mainnet.parse_address(addr).unwrap(), address);
+    }
+    let address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+    let regtest = BtcChainParams::regtest();
+    let encoded = regtest.encode_address(&address).unwrap();
+    assert!(encoded.starts_with("bcrt1"));
+    assert_eq!(
+        regtest.parse_address(&encoded).unwrap().network,
+        bitcoin::Network::Regtest
+    );
+
+    // A fork with its own address format
+    let fork = BtcChainParams::mainnet()
+        .with_magic(0xdbb6c0fb)
+        .with_genesis_hash(bitcoin::BlockHash::hash(b"fork genesis"))
+        .with_bech32_hrp("frk")
+        .with_address_prefixes(0x30, 0x32)
+        .with_finality(12);
+    let encoded = fork.encode_address(&address).unwrap();
+    assert!(encoded.starts_with("frk1"));
+    assert_eq!(fork.parse_address(&encoded).unwrap(), address);
+    assert!(mainnet.parse_address(&encoded).is_err());
+    let p2pkh = Address::from_str("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").unwrap();
+    let encoded = fork.encode_address(&p2pkh).unwrap();
+    assert_eq!(fork.parse_address(&encoded).unwrap(), p2pkh);
+    assert!(mainnet.parse_address(&encoded).is_err());
+    assert!(fork
+        .parse_address("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2")
+        .is_err());
+    assert_eq!(
+        fork.watch_transaction(TaskId(1), TxLabel::Lock, 100, vec![])
+            .confirmation_bound,
+        12
+    );
+
+    // MUST error if the human readable part is not valid
+    assert!(fork.with_bech32_hrp("").encode_address(&address).is_err());
+}
+
+#[test]
+fn monero_chain_params() {
+    let addr = "44AFFq5kSiGBoZ4NMDwYtN18obc8AemS33DBLWs3H7otXft3XjrpDtQGv7SqSsaBYBb98uNbr2VBBEt7f2wfn3RVGQBEP3A";
+    let address = monero::Address::from_str(addr).unwrap();
+    let mainnet = XmrChainParams::mainnet();
+    assert_eq!(mainnet.encode_address(&address).unwrap(), addr);
+    assert_eq!(mainnet.parse_address(addr).unwrap(), address);
+    assert_eq!(XmrChainParams::regtest().network, monero::Network::Mainnet);
+    assert_ne!(
+        XmrChainParams::stagenet().genesis_hash,
+        mainnet.genesis_hash
+    );
+
+    // The same keys on stagenet
+    let stagenet = XmrChainParams::stagenet();
+    let encoded = stagenet.encode_address(&address).unwrap();
+    let parsed = monero::Address::from_str(&encoded).unwrap();
+    assert_eq!(parsed.network, monero::Network::Stagenet);
+    assert_eq!(parsed.public_spend, address.public_spend);
+    assert!(mainnet.parse_address(&encoded).is_err());
+
+    // A fork with its own address prefixes
+    let fork = mainnet.with_prefixes(AddressPrefixes {
+        standard: 0x20,
+        integrated: 0x21,
+        subaddress: 0x22,
+    });
+    let encoded = fork.encode_address(&address).unwrap();
+    assert!(monero::Address::from_str(&encoded).is_err());
+    assert_eq!(fork.parse_address(&encoded).unwrap(), address);
+    assert!(fork.parse_address(addr).is_err());
+
+    // MUST error if the checksum is not valid
+    let mut tampered = encoded.into_bytes();
+    let last = tampered.len() - 1;
+    tampered[last] = if tampered[last] == b'1' { b'2' } else { b'1' };
+    assert!(fork
+        .parse_address(std::str::from_utf8(&tampered).unwrap())
+        .is_err());
+}
+
+#[test]
+fn bitcoin_signet() {
+    use farcaster_core::blockchain::AddressScript;
+    use farcaster_core::chain::bitcoin::policy::{MempoolPolicy, SIGNET_POLICY};
+
+    let signet = BtcChainParams::signet();
+    assert_eq!(signet.network(), Network::Testnet);
+    assert_eq!(Network::from(bitcoin::Network::Signet), Network::Testnet);
+    assert_eq!(Network::from(bitcoin::Network::Bitcoin), Network::Mainnet);
+    assert_eq!(Network::from(bitcoin::Network::Regtest), Network::Local);
+    for network in Network::ALL.iter() {
+        assert_eq!(Network::from(Bitcoin::network(*network)), *network);
+    }
+
+    // Signet addresses are valid on test networks only
+    let address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+    let encoded = signet.encode_address(&address).unwrap();
+    assert!(encoded.starts_with("tb1"));
+    let address = signet.parse_address(&encoded).unwrap();
+    assert_eq!(address.network, bitcoin::Network::Signet);
+    assert!(Bitcoin::validate_network(&address, Network::Testnet).is_ok());
+    assert!(Bitcoin::validate_network(&address, Network::Mainnet).is_err());
+    assert!(Bitcoin::validate_network(&address, Network::Local).is_err());
+
+    let policy = MempoolPolicy::for_chain(&signet);
+    assert_eq!(policy, SIGNET_POLICY);
+    assert_eq!(policy.network(), Network::Testnet);
+    assert!(policy.require_standard());
+    assert!(policy.with_min_relay_fee(0).is_err());
+    assert_eq!(
+        MempoolPolicy::for_chain(&BtcChainParams::regtest()),
+        MempoolPolicy::for_network(Network::Local)
+    );
+}
+
+#[test]
+fn monero_network_mapping() {
+    use farcaster_core::blockchain::AddressScript;
+    use farcaster_core::chain::monero::local::LocalParams as XmrLocalParams;
+    use farcaster_core::chain::monero::Monero;
+
+    assert_eq!(Monero::network(Network::Mainnet), monero::Network::Mainnet);
+    assert_eq!(Monero::network(Network::Testnet), monero::Network::Stagenet);
+    assert_eq!(Monero::network(Network::Local), monero::Network::Testnet);
+    for network in Network::ALL.iter() {
+        assert_eq!(Network::from(Monero::network(*network)), *network);
+    }
+
+    let view = monero::PrivateKey::from_slice(&[1u8; 32]).unwrap();
+    let spend =
+        monero::PublicKey::from_private_key(&monero::PrivateKey::from_slice(&[2u8; 32]).unwrap());
+    let stagenet = Monero::lock_address(Network::Testnet, spend, &view);
+    assert_eq!(stagenet.network, monero::Network::Stagenet);
+    assert_eq!(stagenet.public_spend, spend);
+    assert_eq!(
+        stagenet.public_view,
+        monero::PublicKey::from_private_key(&view)
+    );
+    assert!(Monero::validate_network(&stagenet, Network::Testnet).is_ok());
+    assert!(Monero::validate_network(&stagenet, Network::Mainnet).is_err());
+    assert!(Monero::validate_network(&stagenet, Network::Local).is_err());
+
+    let mainnet = Monero::lock_address(Network::Mainnet, spend, &view);
+    assert!(Monero::validate_network(&mainnet, Network::Mainnet).is_ok());
+    assert!(Monero::validate_network(&mainnet, Network::Testnet).is_err());
+
+    // Regtest nodes use the mainnet address format
+    let regtest = XmrLocalParams::regtest().lock_address(spend, &view);
+    assert_eq!(regtest, mainnet);
+    assert!(Monero::validate_network(&regtest, Network::Local).is_ok());
+    let local = XmrLocalParams::regtest()
+        .with_network(monero::Network::Testnet)
+        .lock_address(spend, &view);
+    assert_eq!(local, Monero::lock_address(Network::Local, spend, &view));
+    assert!(Monero::validate_network(&local, Network::Local).is_ok());
+}
+
+#[test]
+fn cryptonote_chain_from_parameters() {
+    use farcaster_core::blockchain::{Asset, AssetId, BlockTime};
+    use farcaster_core::chain::cryptonote::{self, CryptoNote};
+    use farcaster_core::chain::monero::Monero;
+    use farcaster_core::crypto::{KeyShares, SharedPrivateKeys};
+    use farcaster_core::role::Accordant;
+
+    use std::time::Duration;
+
+    // A fork of Monero with its own asset identifier, block time, and address prefixes
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    struct Fork;
+
+    impl CryptoNote for Fork {
+        const ASSET_ID: u32 = 0x8000dead;
+        const BLOCK_TIME: Duration = Duration::from_secs(60);
+        const HASH_DOMAIN: &'static str = "fork/";
+
+        fn chain_params(network: Network) -> XmrChainParams {
+            Monero::chain_params(network).with_prefixes(AddressPrefixes {
+                standard: 0x20,
+                integrated: 0x21,
+                subaddress: 0x22,
+            })
+        }
+    }
+
+    fn is_accordant<T: Accordant>() {}
+    is_accordant::<Fork>();
+
+    assert_eq!(Fork.asset_id(), AssetId::native(0x8000dead));
+    assert_eq!(Fork::from_asset_id(&Fork.asset_id()), Some(Fork));
+    assert_eq!(Fork::from_asset_id(&Monero.asset_id()), None);
+    assert_eq!(<Fork as BlockTime>::BLOCK_TIME, Duration::from_secs(60));
+    assert_eq!(Fork::shared_keys(), Monero::shared_keys());
+
+    // Keys and addresses are the ones of Monero, encoded with the prefixes of the fork
+    let view = monero::PrivateKey::from_slice(&[1u8; 32]).unwrap();
+    let spend =
+        monero::PublicKey::from_private_key(&monero::PrivateKey::from_slice(&[2u8; 32]).unwrap());
+    assert_eq!(
+        Fork::aggregate_public_shares(&spend, &spend),
+        Monero::aggregate_public_shares(&spend, &spend)
+    );
+    let address = Fork::lock_address(Network::Mainnet, spend, &view);
+    assert_eq!(
+        address,
+        Monero::lock_address(Network::Mainnet, spend, &view)
+    );
+    let params = Fork::chain_params(Network::Mainnet);
+    let encoded = params.encode_address(&address).unwrap();
+    assert_ne!(encoded, address.to_string());
+    assert_eq!(params.parse_address(&encoded).unwrap(), address);
+
+    // Hashes to scalar are domain separated per chain
+    assert_ne!(
+        cryptonote::hash_to_scalar::<Fork>("wallet:view", b"seed"),
+        cryptonote::hash_to_scalar::<Monero>("wallet:view", b"seed")
+    );
+    assert_eq!(
+        cryptonote::hash_to_scalar::<Monero>("wallet:view", b"seed"),
+        farcaster_core::crypto::hash::hash_to_ed25519_scalar("wallet:view", b"seed")
+    );
+}
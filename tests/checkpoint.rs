@@ -0,0 +1,228 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::bitcoin::transaction::Funding;
+use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+use farcaster_core::vectors;
+
+use farcaster_core::blockchain::{FeePolitic, Network};
+use farcaster_core::checkpoint::{
+    Checkpoint, CHECKPOINT_MAC_LEN, CHECKPOINT_MAGIC_BYTES, CHECKPOINT_VERSION,
+};
+use farcaster_core::consensus::{self, deserialize, serialize};
+use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey, Sign};
+use farcaster_core::escrow::SecretEscrow;
+use farcaster_core::negotiation::PublicOffer;
+use farcaster_core::role::{Alice, Bob, SwapRole};
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::Fundable;
+
+use bitcoin::hashes::sha256d::Hash as Sha256dHash;
+use bitcoin::hashes::Hash;
+use bitcoin::Address;
+
+use std::str::FromStr;
+
+fn checkpoint() -> Checkpoint<BtcXmr> {
+    let hex = vectors::PUBLIC_OFFER;
+    let pub_offer: PublicOffer<BtcXmr> = deserialize(&hex::decode(hex).unwrap()[..]).unwrap();
+
+    let funding_tx = "020000000001010000000000000000000000000000000000000000000000000000000000\
+               000000ffffffff03510101ffffffff0200f2052a0100000016001490d2e860d4e51f68857d65bfa\
+               7d0da32dd6c9b350000000000000000266a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c\
+               690689799962b48bebd836974e8cf90120000000000000000000000000000000000000000000000\
+               000000000000000000000000000";
+    let funding_tx: bitcoin::Transaction =
+        bitcoin::consensus::encode::deserialize(&hex::decode(funding_tx).unwrap()).unwrap();
+
+    let address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+    let alice: Alice<BtcXmr> = Alice::new(address.clone(), FeePolitic::Aggressive);
+    let bob: Bob<BtcXmr> = Bob::new(address, FeePolitic::Aggressive);
+
+    let alice_wallet = Wallet::new([2; 32]);
+    let bob_wallet = Wallet::new([1; 32]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    let mut funding_tx = funding_tx;
+    funding_tx.output[0].script_pubkey = funding.get_address().unwrap().script_pubkey();
+    funding.update(funding_tx).unwrap();
+    let core = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
+        .unwrap();
+
+    Checkpoint::new(SwapId([0x07; 32]), SwapRole::Bob, pub_offer)
+        .with_alice_parameters(alice_params)
+        .with_bob_parameters(bob_params)
+        .with_core_arbitrating_transactions(core)
+}
+
+#[test]
+fn serialize_checkpoint() {
+    let checkpoint = checkpoint();
+    let ser = serialize(&checkpoint);
+    assert_eq!(
+        Checkpoint::<BtcXmr>::version(&ser).unwrap(),
+        CHECKPOINT_VERSION
+    );
+
+    assert!(ser.starts_with(&CHECKPOINT_MAGIC_BYTES));
+
+    let de: Checkpoint<BtcXmr> = deserialize(&ser[..]).unwrap();
+    assert_eq!(ser, serialize(&de));
+    // Checkpoints are recognized by their magic bytes, not misread as protocol messages
+    match consensus::try_decode_any::<BtcXmr>(&ser) {
+        Ok(consensus::DecodedEntity::Checkpoint(de)) => assert_eq!(ser, serialize(&*de)),
+        _ => panic!("blob should decode as a checkpoint"),
+    }
+    match consensus::try_decode_any_str::<BtcXmr>(&hex::encode(&ser)) {
+        Ok(consensus::DecodedEntity::Checkpoint(de)) => assert_eq!(ser, serialize(&*de)),
+        _ => panic!("hex should decode as a checkpoint"),
+    }
+
+    // Unknown versions are rejected
+    let mut unknown = ser.clone();
+    unknown[CHECKPOINT_MAGIC_BYTES.len()] = 0xff;
+    assert!(matches!(
+        deserialize::<Checkpoint<BtcXmr>>(&unknown[..]),
+        Err(consensus::Error::UnsupportedVersion)
+    ));
+}
+
+#[test]
+fn migrate_checkpoint() {
+    let pub_offer: PublicOffer<BtcXmr> =
+        deserialize(&hex::decode(vectors::PUBLIC_OFFER).unwrap()[..]).unwrap();
+    let expected = Checkpoint::new(SwapId([0x07; 32]), SwapRole::Bob, pub_offer);
+
+    // Checkpoints persisted with the previous versions are decoded in the current version
+    let fixtures = [
+        (vectors::CHECKPOINT_V1, 1),
+        (vectors::CHECKPOINT_V2, 2),
+        (vectors::CHECKPOINT_V3, 3),
+    ];
+    for (fixture, version) in fixtures.iter() {
+        let bytes = hex::decode(fixture).unwrap();
+        assert_eq!(Checkpoint::<BtcXmr>::version(&bytes).unwrap(), *version);
+        let migrated: Checkpoint<BtcXmr> = deserialize(&bytes[..]).unwrap();
+        assert_eq!(serialize(&migrated), serialize(&expected));
+        assert_eq!(
+            Checkpoint::<BtcXmr>::version(&serialize(&migrated)).unwrap(),
+            CHECKPOINT_VERSION
+        );
+        // Checkpoints without magic bytes are not recognized among other blobs
+        assert!(!matches!(
+            consensus::try_decode_any::<BtcXmr>(&bytes),
+            Ok(consensus::DecodedEntity::Checkpoint(_))
+        ));
+    }
+
+    // Parameters are migrated, core arbitrating transactions only exist from version 2
+    let checkpoint = checkpoint();
+    let ser = serialize(&checkpoint);
+    let mut v2 = serialize(&2u16);
+    v2.extend_from_slice(&ser[CHECKPOINT_MAGIC_BYTES.len() + 2..ser.len() - 1]);
+    let migrated: Checkpoint<BtcXmr> = deserialize(&v2[..]).unwrap();
+    assert!(migrated.secret_escrow.is_none());
+    assert_eq!(serialize(&migrated), ser);
+
+    let mut v1 = v2.clone();
+    let core = serialize(checkpoint.core_arbitrating_transactions.as_ref().unwrap());
+    v1.truncate(v1.len() - core.len() - 1);
+    v1[0] = 0x01;
+    let migrated: Checkpoint<BtcXmr> = deserialize(&v1[..]).unwrap();
+    assert!(migrated.core_arbitrating_transactions.is_none());
+    assert_eq!(
+        serialize(&migrated.with_core_arbitrating_transactions(
+            checkpoint.core_arbitrating_transactions.clone().unwrap()
+        )),
+        ser
+    );
+
+    // MUST fail if the fields of the version are missing or trailing
+    assert!(deserialize::<Checkpoint<BtcXmr>>(&v1[..v1.len() - 1]).is_err());
+    let mut trailing = v1;
+    trailing.push(0x00);
+    assert!(deserialize::<Checkpoint<BtcXmr>>(&trailing[..]).is_err());
+}
+
+#[test]
+fn authenticate_checkpoint() {
+    let checkpoint = checkpoint();
+    let secret = [0x42; 32];
+
+    let bytes = checkpoint.to_authenticated_bytes(&secret);
+    assert_eq!(
+        bytes.len(),
+        serialize(&checkpoint).len() + CHECKPOINT_MAC_LEN
+    );
+    let de = Checkpoint::<BtcXmr>::from_authenticated_bytes(&bytes, &secret).unwrap();
+    assert_eq!(serialize(&de), serialize(&checkpoint));
+
+    // Wrong secret
+    assert!(matches!(
+        Checkpoint::<BtcXmr>::from_authenticated_bytes(&bytes, &[0x43; 32]),
+        Err(consensus::Error::InvalidMac)
+    ));
+
+    // Tampered data
+    let mut tampered = bytes.clone();
+    tampered[10] ^= 0x01;
+    assert!(matches!(
+        Checkpoint::<BtcXmr>::from_authenticated_bytes(&tampered, &secret),
+        Err(consensus::Error::InvalidMac)
+    ));
+
+    // Truncated data
+    assert!(matches!(
+        Checkpoint::<BtcXmr>::from_authenticated_bytes(&bytes[..16], &secret),
+        Err(consensus::Error::InvalidMac)
+    ));
+}
+
+#[test]
+fn escrow_secrets_in_checkpoint() {
+    let mut checkpoint = checkpoint();
+    let secret = [0x42; 32];
+    assert!(checkpoint.open_secret_escrow(&secret).unwrap().is_none());
+
+    // Alice escrows Bob's adaptor signature then the signature seen on-chain
+    let wallet = Wallet::new([0x02; 32]);
+    let key = wallet.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+    let msg = Sha256dHash::hash(b"buy");
+    let adaptor_sig = wallet.adaptor_sign_with_key(&key, &key, msg).unwrap();
+    let sig = wallet.sign_with_key(&key, msg).unwrap();
+    let escrow =
+        SecretEscrow::<BtcXmr>::new(checkpoint.swap_id).with_adaptor_signature(adaptor_sig);
+    checkpoint.escrow_secrets(&escrow, &secret, 0).unwrap();
+    assert!(!escrow.is_pending_recovery());
+    let escrow = escrow.with_signature(sig);
+    // MUST error if the sequence number does not increase
+    assert!(checkpoint.escrow_secrets(&escrow, &secret, 0).is_err());
+    checkpoint.escrow_secrets(&escrow, &secret, 1).unwrap();
+    // MUST error if the escrow is for another swap
+    assert!(checkpoint
+        .escrow_secrets(&SecretEscrow::new(SwapId([0x08; 32])), &secret, 2)
+        .is_err());
+
+    // The escrow survives a restart
+    let bytes = checkpoint.to_authenticated_bytes(&secret);
+    let restored = Checkpoint::<BtcXmr>::from_authenticated_bytes(&bytes, &secret).unwrap();
+    let opened = restored.open_secret_escrow(&secret).unwrap().unwrap();
+    assert!(opened.is_pending_recovery());
+    assert_eq!(opened.adaptor_signature, Some(adaptor_sig));
+    assert_eq!(opened.signature, Some(sig));
+    assert!(matches!(
+        restored.open_secret_escrow(&[0x43; 32]),
+        Err(consensus::Error::InvalidMac)
+    ));
+
+    // A sealed escrow cannot be moved to another swap
+    let mut other = restored.clone();
+    other.swap_id = SwapId([0x08; 32]);
+    assert!(other.open_secret_escrow(&secret).is_err());
+}
@@ -0,0 +1,61 @@
+#![cfg(all(feature = "envelope", feature = "bitcoin", feature = "monero"))]
+
+use std::time::Duration;
+
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::clock::{Clock, ManualClock, SystemClock};
+use farcaster_core::envelope::{Envelope, Mailbox, SessionKey};
+use farcaster_core::protocol_message::{Abort, AbortReason, ProtocolMessage};
+use farcaster_core::timeouts::{StallDetector, StallPhase, StallTimeouts};
+
+#[test]
+fn fast_forward_manual_clock() {
+    let start = Duration::from_secs(1_600_000_000);
+    let clock = ManualClock::new(start);
+    let handle = clock.clone();
+    handle.advance(Duration::from_secs(10));
+    assert_eq!(clock.now(), start + Duration::from_secs(10));
+    handle.set(start);
+    assert_eq!(clock.now(), start);
+
+    // Timestamps are clocks frozen in time
+    assert_eq!(start.now(), start);
+    assert!(SystemClock.now() > start);
+}
+
+#[test]
+fn detect_stalls_with_clock() {
+    let clock = ManualClock::new(Duration::from_secs(1_600_000_000));
+    let mut detector = StallDetector::new(StallTimeouts::uniform(Duration::from_secs(60)));
+    detector.start(StallPhase::Reveal, &clock);
+
+    clock.advance(Duration::from_secs(59));
+    assert_eq!(detector.remaining(&clock), Some(Duration::from_secs(1)));
+    assert!(!detector.is_stalled(&clock));
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(detector.stalled(&clock), Some(StallPhase::Reveal));
+}
+
+#[test]
+fn expire_mailbox_with_clock() {
+    let alice = SessionKey::from_bytes([0x41; 32]);
+    let bob = SessionKey::from_bytes([0x42; 32]);
+    let message: ProtocolMessage<BtcXmr> =
+        ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified));
+    let seal = |sequence: u64| {
+        let ephemeral = SessionKey::from_bytes([sequence as u8 + 1; 32]);
+        Envelope::seal(&message, &alice, &bob.public_key(), &ephemeral, sequence).unwrap()
+    };
+    let hour = Duration::from_secs(3600);
+    let clock = ManualClock::default();
+    let mut mailbox = Mailbox::new()
+        .with_message(seal(0), hour)
+        .with_message(seal(1), hour * 2);
+
+    clock.advance(hour);
+    assert_eq!(mailbox.deliverable(&clock).len(), 1);
+    assert_eq!(mailbox.expire(&clock), 1);
+    clock.advance(hour);
+    assert_eq!(mailbox.expire(&clock), 1);
+    assert!(mailbox.is_empty());
+}
@@ -0,0 +1,69 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::bundle::{AliceParameters, CoreArbitratingTransactions};
+use farcaster_core::chain::bitcoin::Bitcoin;
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::consensus::{self, deserialize};
+use farcaster_core::describe::{Describe, Kind};
+use farcaster_core::protocol_message::{
+    Abort, AbortReason, CommitAliceParameters, ProtocolMessage,
+};
+use farcaster_core::role::SwapRole;
+use farcaster_core::settlement::SwapPhase;
+
+#[test]
+fn describe_protocol_messages() {
+    let description = CommitAliceParameters::<BtcXmr>::describe();
+    assert_eq!(description.name, "CommitAliceParameters");
+    assert_eq!(description.kind, Kind::Message(0x01));
+    assert_eq!(description.role, Some(SwapRole::Alice));
+    assert_eq!(description.phase, Some(SwapPhase::Setup));
+    assert_eq!(description.fields[0].name, "buy");
+    assert!(description.fields[0].type_name.contains("Hash"));
+    assert_eq!(description.fields.len(), 10);
+
+    let msg = ProtocolMessage::<BtcXmr>::Abort(Abort::new(AbortReason::Unspecified));
+    assert_eq!(msg.describe(), Abort::describe());
+    assert_eq!(msg.describe().kind, Kind::Message(msg.message_type()));
+    assert_eq!(msg.describe().role, None);
+
+    // Every described message type is known by the decoder, in increasing order
+    let descriptions = ProtocolMessage::<BtcXmr>::descriptions();
+    let mut previous = 0;
+    for description in descriptions.iter() {
+        let msg_type = match description.kind {
+            Kind::Message(msg_type) => msg_type,
+            Kind::Bundle => panic!("{} is not a message", description.name),
+        };
+        assert!(msg_type > previous);
+        previous = msg_type;
+        match deserialize::<ProtocolMessage<BtcXmr>>(&msg_type.to_le_bytes()) {
+            Err(consensus::Error::Message { msg_type: t, .. }) => assert_eq!(t, msg_type),
+            #[cfg(feature = "dual-funding")]
+            Ok(ProtocolMessage::LockContributionComplete(_)) => (),
+            res => panic!("Unexpected result for {}: {:?}", description.name, res),
+        }
+    }
+    assert!(descriptions
+        .iter()
+        .any(|d| d.name == "CooperativeCloseSignature"));
+}
+
+#[test]
+fn describe_bundles() {
+    let description = AliceParameters::<BtcXmr>::describe();
+    assert_eq!(description.kind, Kind::Bundle);
+    assert_eq!(description.role, Some(SwapRole::Alice));
+    assert!(description
+        .fields
+        .iter()
+        .any(|field| field.name == "destination_address"));
+
+    let description = CoreArbitratingTransactions::<Bitcoin>::describe();
+    assert_eq!(description.role, Some(SwapRole::Bob));
+    let names: Vec<&str> = description.fields.iter().map(|field| field.name).collect();
+    assert_eq!(names, vec!["lock", "cancel", "refund"]);
+    let text = description.to_string();
+    assert!(text.starts_with("bundle CoreArbitratingTransactions, from Bob, during Setup"));
+    assert!(text.contains("\n  lock: "));
+}
@@ -0,0 +1,78 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey};
+use farcaster_core::dispute::{DisputePackage, Error, Transcript};
+use farcaster_core::protocol_message::{Abort, AbortReason, ProtocolMessage};
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::TxLabel;
+
+fn abort(body: &str) -> ProtocolMessage<BtcXmr> {
+    ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified).with_body(body))
+}
+
+#[test]
+fn verify_dispute_package() {
+    let mut transcript = Transcript::new(SwapId([0x07; 32]));
+    for body in ["first", "second", "third"].iter() {
+        transcript.push(&abort(body));
+    }
+    let commitment = transcript.commitment().unwrap();
+    assert_eq!(commitment.len, 3);
+
+    let alice = Wallet::new([0x01; 32]);
+    let bob = Wallet::new([0x02; 32]);
+    let alice_key = alice.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+    let bob_key = bob.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+    let tx = bitcoin::Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![],
+        output: vec![],
+    };
+
+    // Only the second message is disclosed
+    let package = DisputePackage::<BtcXmr>::new(commitment)
+        .sign(SwapRole::Alice, alice_key, &alice)
+        .unwrap()
+        .sign(SwapRole::Bob, bob_key, &bob)
+        .unwrap()
+        .with_message(transcript.disclose(1).unwrap())
+        .with_transaction(TxLabel::Lock, tx.clone());
+    assert!(transcript.disclose(3).is_none());
+
+    // The arbiter receives the encoded package
+    let received: DisputePackage<BtcXmr> = deserialize(&serialize(&package)).unwrap();
+    received.verify(&alice).unwrap();
+    let messages = received.protocol_messages().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].0, 1);
+    assert_eq!(serialize(&messages[0].1), serialize(&abort("second")));
+    assert_eq!(received.transactions, vec![(TxLabel::Lock, tx)]);
+
+    // MUST error if a disclosed message is tampered with
+    let mut tampered = received.clone();
+    tampered.messages[0] = transcript.disclose(0).unwrap();
+    tampered.messages[0].proof.index = 1;
+    assert!(matches!(
+        tampered.verify(&alice),
+        Err(Error::InvalidProof(1))
+    ));
+
+    // MUST error if the commitment does not match its signatures
+    let mut tampered = received.clone();
+    tampered.commitment.len = 4;
+    assert!(matches!(
+        tampered.verify(&alice),
+        Err(Error::InvalidSignature(SwapRole::Alice))
+    ));
+
+    // MUST error if the commitment is not signed
+    let unsigned = DisputePackage::<BtcXmr>::new(commitment);
+    assert!(matches!(
+        unsigned.verify(&alice),
+        Err(Error::MissingSignature)
+    ));
+}
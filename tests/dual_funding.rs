@@ -0,0 +1,184 @@
+#![cfg(all(feature = "dual-funding", feature = "test-utils"))]
+
+use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use bitcoin::consensus::encode::serialize as btc_serialize;
+use bitcoin::{Address, Amount, Network, Script};
+
+use farcaster_core::chain::bitcoin::dual_funding::{Error, LockConstruction};
+use farcaster_core::chain::bitcoin::local::key;
+use farcaster_core::chain::bitcoin::timelock::CSVTimelock;
+use farcaster_core::chain::bitcoin::transaction::{ordering, Tx};
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::protocol_message::{
+    AddLockInput, AddLockOutput, LockContributionComplete, ProtocolMessage,
+};
+use farcaster_core::role::SwapRole;
+use farcaster_core::script::{DataLock, DoubleKeys};
+use farcaster_core::transaction::{Linkable, Transaction as _};
+
+// Create a transaction paying `value` to a segwit address of the key
+fn prev_tx(byte: u8, value: u64) -> Vec<u8> {
+    btc_serialize(&Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::default(),
+            script_sig: Script::default(),
+            sequence: 0xffffffff,
+            witness: vec![],
+        }],
+        output: vec![TxOut {
+            value,
+            script_pubkey: Address::p2wpkh(&key(byte), Network::Bitcoin)
+                .unwrap()
+                .script_pubkey(),
+        }],
+    })
+}
+
+#[test]
+fn construct_dual_funded_lock() {
+    let data_lock = DataLock {
+        timelock: CSVTimelock::new(10),
+        success: DoubleKeys::new(key(1), key(2)),
+        failure: DoubleKeys::new(key(3), key(4)),
+    };
+    let mut construction =
+        LockConstruction::new(Amount::from_sat(70_000), Amount::from_sat(30_000));
+
+    let bob_input = AddLockInput {
+        serial_id: 0,
+        prev_tx: prev_tx(5, 60_000),
+        prev_vout: 0,
+    };
+    let msg = ProtocolMessage::<BtcXmr>::AddLockInput(bob_input.clone());
+    match deserialize(&serialize(&msg)).unwrap() {
+        ProtocolMessage::<BtcXmr>::AddLockInput(decoded) => assert_eq!(decoded, bob_input),
+        _ => panic!("should decode as an add lock input message"),
+    }
+    construction.add_input(SwapRole::Bob, &bob_input).unwrap();
+
+    // MUST error if the serial identifier does not match the role or is reused
+    assert_eq!(
+        construction.add_input(SwapRole::Alice, &bob_input),
+        Err(Error::WrongSerialIdParity(0))
+    );
+    assert_eq!(
+        construction.add_input(SwapRole::Bob, &bob_input),
+        Err(Error::DuplicateSerialId(0))
+    );
+    // MUST error if the output is not segwit
+    let mut legacy: Transaction = bitcoin::consensus::deserialize(&prev_tx(6, 1_000)).unwrap();
+    legacy.output[0].script_pubkey = Address::p2pkh(&key(6), Network::Bitcoin).script_pubkey();
+    let legacy_input = AddLockInput {
+        serial_id: 3,
+        prev_tx: btc_serialize(&legacy),
+        prev_vout: 0,
+    };
+    assert_eq!(
+        construction.add_input(SwapRole::Alice, &legacy_input),
+        Err(Error::NonSegwitInput)
+    );
+
+    construction
+        .add_output(
+            SwapRole::Bob,
+            &AddLockOutput {
+                serial_id: 2,
+                amount: 10_000,
+                script_pubkey: Address::p2wpkh(&key(5), Network::Bitcoin)
+                    .unwrap()
+                    .script_pubkey()
+                    .into_bytes(),
+            },
+        )
+        .unwrap();
+
+    // Bob completes but Alice contributes afterward, the completion restarts
+    construction.complete(SwapRole::Bob, &LockContributionComplete);
+    construction
+        .add_input(
+            SwapRole::Alice,
+            &AddLockInput {
+                serial_id: 1,
+                prev_tx: prev_tx(7, 30_000),
+                prev_vout: 0,
+            },
+        )
+        .unwrap();
+    construction.complete(SwapRole::Alice, &LockContributionComplete);
+    assert!(!construction.is_complete());
+    assert_eq!(construction.validate(), Err(Error::NotComplete));
+    construction.complete(SwapRole::Bob, &LockContributionComplete);
+    assert!(construction.is_complete());
+
+    construction.validate().unwrap();
+    assert_eq!(
+        construction.contribution(SwapRole::Alice),
+        Ok(Amount::from_sat(30_000))
+    );
+    assert_eq!(construction.fee(), Ok(Amount::from_sat(10_000)));
+
+    let lock = construction.to_lock(&data_lock).unwrap();
+    let tx = &lock.as_partial().global.unsigned_tx;
+    assert_eq!(tx.input.len(), 2);
+    assert_eq!(tx.output[0].value, 70_000);
+    assert_eq!(tx.output[1].value, 10_000);
+    // The lock output can be spent by the cancel and buy transactions
+    assert_eq!(lock.get_consumable_output().unwrap().tx_out.value, 70_000);
+    assert!(construction.verify_lock(&lock, &data_lock).is_ok());
+
+    // Inputs are ordered whoever contributed them, the lock output stays first
+    assert!(ordering::is_sorted(tx, 0, 1));
+    let mut unordered = lock.as_partial().clone();
+    unordered.global.unsigned_tx.input.swap(0, 1);
+    unordered.inputs.swap(0, 1);
+    assert!(construction
+        .verify_lock(&Tx::from_partial(unordered), &data_lock)
+        .is_err());
+
+    // MUST error if Alice does not contribute her share
+    let mut construction =
+        LockConstruction::new(Amount::from_sat(70_000), Amount::from_sat(40_000));
+    construction.add_input(SwapRole::Bob, &bob_input).unwrap();
+    construction
+        .add_input(
+            SwapRole::Alice,
+            &AddLockInput {
+                serial_id: 1,
+                prev_tx: prev_tx(7, 30_000),
+                prev_vout: 0,
+            },
+        )
+        .unwrap();
+    construction.complete(SwapRole::Bob, &LockContributionComplete);
+    construction.complete(SwapRole::Alice, &LockContributionComplete);
+    assert_eq!(
+        construction.validate(),
+        Err(Error::InsufficientContribution(SwapRole::Alice))
+    );
+}
+
+#[test]
+fn reject_overflowing_contributions() {
+    let mut construction = LockConstruction::new(Amount::from_sat(70_000), Amount::from_sat(0));
+    // The counter-party contributes inputs summing above the maximum amount
+    for serial_id in [0, 2] {
+        construction
+            .add_input(
+                SwapRole::Bob,
+                &AddLockInput {
+                    serial_id,
+                    prev_tx: prev_tx(serial_id as u8 + 5, u64::MAX),
+                    prev_vout: 0,
+                },
+            )
+            .unwrap();
+    }
+    assert_eq!(
+        construction.contribution(SwapRole::Bob),
+        Err(Error::NotEnoughAssets)
+    );
+    assert_eq!(construction.fee(), Err(Error::NotEnoughAssets));
+}
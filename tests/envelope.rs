@@ -0,0 +1,102 @@
+#![cfg(all(feature = "envelope", feature = "bitcoin", feature = "monero"))]
+
+use std::time::Duration;
+
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::consensus::{self, deserialize, serialize};
+use farcaster_core::envelope::{Envelope, Mailbox, SessionKey, SessionPublicKey};
+use farcaster_core::protocol_message::{Abort, AbortReason, ProtocolMessage};
+
+fn abort() -> ProtocolMessage<BtcXmr> {
+    ProtocolMessage::Abort(Abort {
+        reason: AbortReason::Unspecified,
+        error_body: Some(String::from("relayed")),
+    })
+}
+
+#[test]
+fn seal_and_open_envelope() {
+    let alice = SessionKey::from_bytes([0x41; 32]);
+    let bob = SessionKey::from_bytes([0x42; 32]);
+    let ephemeral = SessionKey::from_bytes([0x07; 32]);
+    let envelope = Envelope::seal(&abort(), &alice, &bob.public_key(), &ephemeral, 3).unwrap();
+
+    // Envelopes are relayed encoded
+    let relayed: Envelope = deserialize(&serialize(&envelope)).unwrap();
+    assert_eq!(relayed, envelope);
+    assert_eq!(relayed.sequence(), 3);
+    match relayed.open_in_sequence::<ProtocolMessage<BtcXmr>>(&bob, &alice.public_key(), 3) {
+        Ok(ProtocolMessage::Abort(abort)) => {
+            assert_eq!(abort.error_body, Some(String::from("relayed")))
+        }
+        _ => panic!("envelope should contain an abort message"),
+    }
+    // MUST error if the sequence number is not the expected one
+    assert!(relayed
+        .open_in_sequence::<ProtocolMessage<BtcXmr>>(&bob, &alice.public_key(), 4)
+        .is_err());
+
+    // MUST error with an invalid mac if the key is wrong
+    let eve = SessionKey::from_bytes([0x66; 32]);
+    assert!(matches!(
+        relayed.open::<ProtocolMessage<BtcXmr>>(&eve, &alice.public_key()),
+        Err(consensus::Error::InvalidMac)
+    ));
+
+    // MUST error if the envelope is forged by a relay knowing the public session keys
+    let forged = Envelope::seal(&abort(), &eve, &bob.public_key(), &ephemeral, 3).unwrap();
+    assert!(matches!(
+        forged.open::<ProtocolMessage<BtcXmr>>(&bob, &alice.public_key()),
+        Err(consensus::Error::InvalidMac)
+    ));
+
+    // MUST error if the relay tampers with the sequence number
+    let mut bytes = serialize(&envelope);
+    bytes[2 + 32] = 0x04;
+    let tampered: Envelope = deserialize(&bytes).unwrap();
+    assert!(matches!(
+        tampered.open::<ProtocolMessage<BtcXmr>>(&bob, &alice.public_key()),
+        Err(consensus::Error::InvalidMac)
+    ));
+
+    // MUST error if the recipient key has a small order
+    let small_order = SessionPublicKey([0u8; 32]);
+    assert!(Envelope::seal(&abort(), &alice, &small_order, &ephemeral, 0).is_err());
+    assert!(envelope
+        .open::<ProtocolMessage<BtcXmr>>(&bob, &small_order)
+        .is_err());
+}
+
+#[test]
+fn deliver_mailbox() {
+    let alice = SessionKey::from_bytes([0x41; 32]);
+    let bob = SessionKey::from_bytes([0x42; 32]);
+    let seal = |sequence: u64| {
+        let ephemeral = SessionKey::from_bytes([sequence as u8 + 1; 32]);
+        Envelope::seal(&abort(), &alice, &bob.public_key(), &ephemeral, sequence).unwrap()
+    };
+    let hour = Duration::from_secs(3600);
+    let mut mailbox = Mailbox::new()
+        .with_message(seal(1), hour * 2)
+        .with_message(seal(0), hour * 2)
+        .with_message(seal(2), hour);
+
+    let relayed: Mailbox = deserialize(&serialize(&mailbox)).unwrap();
+    assert_eq!(relayed, mailbox);
+
+    // Envelopes are opened in sequence order
+    let messages = relayed
+        .open_all::<ProtocolMessage<BtcXmr>>(&bob, &alice.public_key(), 0, Duration::from_secs(0))
+        .unwrap();
+    assert_eq!(messages.len(), 3);
+    // MUST error if the first message is not the expected one
+    assert!(relayed
+        .open_all::<ProtocolMessage<BtcXmr>>(&bob, &alice.public_key(), 1, Duration::from_secs(0))
+        .is_err());
+
+    assert_eq!(mailbox.deliverable(hour).len(), 2);
+    assert_eq!(mailbox.expire(hour), 1);
+    assert_eq!(mailbox.len(), 2);
+    assert_eq!(mailbox.expire(hour * 2), 2);
+    assert!(mailbox.is_empty());
+}
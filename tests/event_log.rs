@@ -0,0 +1,385 @@
+use farcaster_core::consensus::{deserialize, serialize, unpad, Padding};
+use farcaster_core::protocol::beacon::EntropyBeacon;
+use farcaster_core::protocol::loopback::LoopbackMachine;
+use farcaster_core::protocol::queue::{OutputQueue, Prioritized, Priority};
+use farcaster_core::protocol::replay::{replay, Divergence};
+use farcaster_core::protocol::session::{Error, Progress, Session, SessionManager};
+use farcaster_core::protocol::{input_digest, EventLog, StateMachine};
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::TxLabel;
+
+/// A counter incremented by the inputs, emitting the new value every time it crosses a ten. The
+/// counter is over once it reaches a hundred.
+#[derive(Debug, Clone, PartialEq)]
+struct Counter;
+
+impl StateMachine for Counter {
+    type State = u64;
+    type Input = u8;
+    type Output = u64;
+    type Error = &'static str;
+
+    fn transition(state: &u64, input: &u8) -> Result<(u64, Vec<u64>), Self::Error> {
+        if *input == 0 {
+            return Err("null increment");
+        }
+        let next = state + *input as u64;
+        let outputs = match next / 10 > state / 10 {
+            true => vec![next],
+            false => vec![],
+        };
+        Ok((next, outputs))
+    }
+
+    fn is_terminal(state: &u64) -> bool {
+        *state >= 100
+    }
+}
+
+/// The counter after a refactor emitting the values on every multiple of five.
+#[derive(Debug, Clone, PartialEq)]
+struct RefactoredCounter;
+
+impl StateMachine for RefactoredCounter {
+    type State = u64;
+    type Input = u8;
+    type Output = u64;
+    type Error = &'static str;
+
+    fn transition(state: &u64, input: &u8) -> Result<(u64, Vec<u64>), Self::Error> {
+        if *input > 5 {
+            return Err("increment too large");
+        }
+        let next = state + *input as u64;
+        let outputs = match next / 5 > state / 5 {
+            true => vec![next],
+            false => vec![],
+        };
+        Ok((next, outputs))
+    }
+}
+
+fn record_log(inputs: &[u8]) -> EventLog<Counter> {
+    let mut log = EventLog::new();
+    let mut state = 0u64;
+    for input in inputs.iter() {
+        state = log.execute(&state, *input).unwrap().0;
+    }
+    log
+}
+
+#[test]
+fn record_transitions_in_log() {
+    let mut log = EventLog::<Counter>::new();
+    assert!(log.is_empty());
+
+    let mut state = 0u64;
+    for input in [3u8, 8, 4].iter() {
+        state = log.execute(&state, *input).unwrap().0;
+    }
+    assert_eq!(state, 15);
+    // Failed transitions are not recorded
+    assert!(log.execute(&state, 0).is_err());
+    assert_eq!(log.len(), 3);
+    assert_eq!(log.last_state(), Some(&15));
+
+    let records: Vec<_> = log.records().collect();
+    assert_eq!(records[0].from, 0);
+    assert_eq!(records[0].to, 3);
+    assert_eq!(records[0].input_digest, input_digest(&3u8));
+    assert!(records[0].outputs.is_empty());
+    assert_eq!(records[1].outputs, vec![11]);
+    // Transitions chain
+    assert!(log
+        .records()
+        .zip(log.records().skip(1))
+        .all(|(a, b)| a.to == b.from));
+
+    let de: EventLog<Counter> = deserialize(&serialize(&log)).unwrap();
+    assert_eq!(de, log);
+
+    // MUST error if a recorded input does not match its digest
+    let mut tampered = serialize(&log);
+    // Skip the length prefix to alter the first input
+    tampered[2] = 0x05;
+    assert!(deserialize::<EventLog<Counter>>(&tampered).is_err());
+}
+
+#[test]
+fn replay_recorded_log() {
+    let log = record_log(&[3, 8, 4]);
+    assert_eq!(replay(&log, 0), Ok(15));
+    assert_eq!(replay(&EventLog::<Counter>::new(), 7), Ok(7));
+
+    // MUST diverge if the log does not start from the initial state
+    assert_eq!(
+        replay(&log, 1),
+        Err(Divergence::From {
+            index: 0,
+            recorded: 0,
+            replayed: 1
+        })
+    );
+
+    // Replay the persisted log against the refactored state machine
+    let bytes = serialize(&log);
+    let refactored: EventLog<RefactoredCounter> = deserialize(&bytes).unwrap();
+    let divergence = replay(&refactored, 0).unwrap_err();
+    assert_eq!(divergence.index(), 1);
+    assert_eq!(
+        divergence,
+        Divergence::Rejected {
+            index: 1,
+            error: "increment too large"
+        }
+    );
+
+    // Same states but different outputs
+    let log: EventLog<RefactoredCounter> = deserialize(&serialize(&record_log(&[3, 4]))).unwrap();
+    assert_eq!(
+        replay(&log, 0),
+        Err(Divergence::Outputs {
+            index: 1,
+            recorded: vec![],
+            replayed: vec![7]
+        })
+    );
+}
+
+#[test]
+fn multiplex_sessions() {
+    let mut manager = SessionManager::<Counter, &str>::new(2);
+    let (a, b, c) = (SwapId([0x01; 32]), SwapId([0x02; 32]), SwapId([0x03; 32]));
+
+    manager.open(a, "alice", 0).unwrap();
+    manager.open(b, "alice", 95).unwrap();
+    assert!(matches!(manager.open(a, "bob", 0), Err(Error::DuplicateSession(id)) if id == a));
+    // MUST error if the peer already runs the maximum number of sessions
+    assert!(matches!(
+        manager.open(c, "alice", 0),
+        Err(Error::PeerLimitReached)
+    ));
+    manager.open(c, "bob", 0).unwrap();
+
+    // Inputs are routed to the session of the swap only
+    assert_eq!(manager.route(a, 12).unwrap(), vec![12]);
+    assert_eq!(manager.session(&a).unwrap().state(), &12);
+    assert_eq!(manager.session(&c).unwrap().state(), &0);
+    assert!(matches!(
+        manager.route(SwapId([0xff; 32]), 1),
+        Err(Error::UnknownSession(_))
+    ));
+    // A failed transition leaves the session unchanged
+    assert!(matches!(manager.route(a, 0), Err(Error::Transition(_))));
+    assert_eq!(manager.session(&a).unwrap().log().len(), 1);
+
+    // Terminated sessions accept no input and do not count toward the peer limit
+    assert_eq!(manager.route(b, 5).unwrap(), vec![100]);
+    assert!(manager.session(&b).unwrap().is_terminal());
+    assert!(matches!(
+        manager.route(b, 1),
+        Err(Error::SessionTerminated(id)) if id == b
+    ));
+    assert_eq!(manager.running_with(&"alice"), 1);
+    assert_eq!(
+        manager.progress(),
+        Progress {
+            running: 2,
+            terminated: 1,
+            transitions: 2,
+            fees: 0,
+        }
+    );
+
+    // Fees recorded in the sessions are summed up in the progress
+    assert_eq!(manager.record_fee(a, TxLabel::Lock, 150).unwrap(), None);
+    assert_eq!(manager.record_fee(c, TxLabel::Lock, 200).unwrap(), None);
+    assert_eq!(
+        manager.record_fee(a, TxLabel::Lock, 180).unwrap(),
+        Some(150)
+    );
+    assert_eq!(manager.session(&a).unwrap().fees().total(), 180);
+    assert_eq!(manager.progress().fees, 380);
+    assert!(matches!(
+        manager.record_fee(SwapId([0xff; 32]), TxLabel::Lock, 1),
+        Err(Error::UnknownSession(_))
+    ));
+
+    let session = manager.close(&b).unwrap();
+    assert_eq!(replay(session.log(), 95), Ok(100));
+    assert!(manager.session(&b).is_none());
+    manager.open(b, "alice", 0).unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Output {
+    Punish,
+    Message(u8),
+    Report(u8),
+}
+
+impl Prioritized for Output {
+    fn priority(&self) -> Priority {
+        match self {
+            Output::Punish => Priority::Safety,
+            Output::Message(_) => Priority::Protocol,
+            Output::Report(_) => Priority::Progress,
+        }
+    }
+}
+
+#[test]
+fn release_outputs_by_priority() {
+    let mut queue = OutputQueue::new();
+    queue.extend(vec![
+        Output::Report(0),
+        Output::Message(0),
+        Output::Message(1),
+        Output::Report(1),
+    ]);
+    let (first, output) = queue.pop().unwrap();
+    assert_eq!(output, Output::Message(0));
+
+    // A safety-critical broadcast overtakes the queued messages
+    queue.push(Output::Punish);
+    assert_eq!(queue.peek(), Some(&Output::Punish));
+    let (punish, _) = queue.pop().unwrap();
+    assert_eq!(queue.in_flight(), 2);
+    assert_eq!(queue.ack(punish), Some(Output::Punish));
+    assert_eq!(queue.ack(punish), None);
+
+    // An output not delivered is released again before the following ones
+    assert!(queue.nack(first));
+    assert!(!queue.nack(first));
+    assert_eq!(queue.pending(Priority::Protocol), 2);
+    let released: Vec<_> = std::iter::from_fn(|| queue.pop().map(|(_, o)| o)).collect();
+    assert_eq!(
+        released,
+        vec![
+            Output::Message(0),
+            Output::Message(1),
+            Output::Report(0),
+            Output::Report(1)
+        ]
+    );
+    assert!(queue.is_empty());
+    assert_eq!(queue.in_flight(), 4);
+}
+
+/// Both roles pass a ball back and forth, the fifth pass is broadcasted instead.
+#[derive(Debug, Clone, PartialEq)]
+struct PingPong;
+
+impl StateMachine for PingPong {
+    type State = u64;
+    type Input = u8;
+    type Output = u64;
+    type Error = &'static str;
+
+    fn transition(state: &u64, input: &u8) -> Result<(u64, Vec<u64>), Self::Error> {
+        let output = match input {
+            0..=3 => *input as u64 + 1,
+            _ => 100,
+        };
+        Ok((state + 1, vec![output]))
+    }
+}
+
+impl LoopbackMachine for PingPong {
+    fn deliver(output: &u64) -> Option<u8> {
+        match output {
+            100 => None,
+            _ => Some(*output as u8),
+        }
+    }
+}
+
+#[test]
+fn run_loopback_swap() {
+    let mut manager = SessionManager::<PingPong, &str>::new(2);
+    let swap_id = SwapId([0x01; 32]);
+    manager.open_loopback(swap_id, "self", 0, 0).unwrap();
+    assert!(matches!(
+        manager.open(swap_id, "bob", 0),
+        Err(Error::DuplicateSession(_))
+    ));
+    // Both sessions count toward the peer limit
+    assert!(matches!(
+        manager.open(SwapId([0x02; 32]), "self", 0),
+        Err(Error::PeerLimitReached)
+    ));
+
+    // Messages are delivered in memory until a role produces an output for the daemon
+    let outputs = manager.route_loopback(swap_id, SwapRole::Alice, 0).unwrap();
+    assert_eq!(outputs, vec![(SwapRole::Alice, 100)]);
+    let loopback = manager.loopback(&swap_id).unwrap();
+    assert_eq!(loopback.session(SwapRole::Alice).state(), &3);
+    assert_eq!(loopback.session(SwapRole::Bob).state(), &2);
+    assert_eq!(manager.progress().transitions, 5);
+    assert!(matches!(
+        manager.route(swap_id, 0),
+        Err(Error::UnknownSession(_))
+    ));
+}
+
+#[test]
+fn derive_session_entropy_from_beacon() {
+    let swap_id = SwapId([0x01; 32]);
+    let mut session = Session::<Counter, &str>::new(swap_id, "bob", 0);
+    assert_eq!(session.entropy("padding"), None);
+
+    let beacon = EntropyBeacon::from_contributions(swap_id, b"alice reveal", b"bob reveal");
+    // MUST error if the beacon is computed for another swap
+    let other =
+        EntropyBeacon::from_contributions(SwapId([0x02; 32]), b"alice reveal", b"bob reveal");
+    assert!(matches!(
+        session.set_beacon(other),
+        Err(Error::InvalidBeacon(_))
+    ));
+    session.set_beacon(beacon).unwrap();
+    session.set_beacon(beacon).unwrap();
+    // MUST error if the beacon changes once set
+    let shifted = EntropyBeacon::from_contributions(swap_id, b"alice reveal bob", b" reveal");
+    assert_ne!(shifted, beacon);
+    assert!(matches!(
+        session.set_beacon(shifted),
+        Err(Error::InvalidBeacon(_))
+    ));
+
+    // Values are distinct per label and reproducible from the persisted beacon
+    let padding = session.entropy("padding").unwrap();
+    assert_ne!(padding, session.entropy("blinding").unwrap());
+    let restored: EntropyBeacon = deserialize(&serialize(&beacon)).unwrap();
+    assert_eq!(restored.entropy("padding"), padding);
+
+    // Private values depend on the participant secret
+    let blinding = session.keyed_entropy(b"bob secret", "blinding").unwrap();
+    assert_eq!(blinding, restored.keyed_entropy(b"bob secret", "blinding"));
+    assert_ne!(
+        blinding,
+        restored.keyed_entropy(b"alice secret", "blinding")
+    );
+    assert_ne!(blinding, padding);
+}
+
+#[test]
+fn pad_session_messages() {
+    let swap_id = SwapId([0x01; 32]);
+    let label = TxLabel::Cancel;
+    let session = Session::<Counter, &str>::new(swap_id, "bob", 0);
+    assert_eq!(session.padding(), None);
+    assert_eq!(session.encode_message(&label).unwrap(), serialize(&label));
+
+    let mut manager = SessionManager::<Counter, &str>::new(2).with_padding(Padding::new(vec![64]));
+    let session = manager.open(swap_id, "bob", 0).unwrap();
+    assert_eq!(session.padding(), Some(&Padding::new(vec![64])));
+    let padded = session.encode_message(&label).unwrap();
+    assert_eq!(padded.len(), 64);
+    assert_eq!(unpad(&padded).unwrap(), serialize(&label));
+    assert_eq!(session.decode_message::<TxLabel>(&padded).unwrap(), label);
+    // MUST error if the counter-party does not pad its messages
+    assert!(session
+        .decode_message::<TxLabel>(&serialize(&label))
+        .is_err());
+}
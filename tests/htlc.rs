@@ -0,0 +1,116 @@
+#![cfg(all(feature = "htlc", feature = "test-utils"))]
+
+use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use bitcoin::{Address, Amount, Script};
+
+use farcaster_core::blockchain::Network;
+use farcaster_core::chain::bitcoin::descriptor::{self, Descriptor};
+use farcaster_core::chain::bitcoin::htlc::{self, HtlcClaim, HtlcRefund};
+use farcaster_core::chain::bitcoin::local::key_pair;
+use farcaster_core::chain::bitcoin::timelock::CSVTimelock;
+use farcaster_core::chain::bitcoin::transaction::{sign_hash, Funding, Tx};
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::consensus::{deserialize, serialize, CanonicalBytes};
+use farcaster_core::negotiation::{Version, FEATURE_HTLC};
+use farcaster_core::protocol_message::{CommitHashLock, ProtocolMessage};
+use farcaster_core::script::{DataHashLock, ScriptPath};
+use farcaster_core::transaction::{Broadcastable, Fundable, Linkable, Witnessable};
+
+#[test]
+fn claim_hash_time_locked_contract() {
+    let version = Version::new_v1().with_feature(FEATURE_HTLC);
+    assert!(version.has_feature(FEATURE_HTLC));
+
+    let (funding_key, funding_secret) = key_pair(1);
+    let (alice_key, alice_secret) = key_pair(2);
+    let (bob_key, _) = key_pair(3);
+    let alice_share = monero::PrivateKey::from_slice(&[0x04; 32]).unwrap();
+    let bob_share = monero::PrivateKey::from_slice(&[0x05; 32]).unwrap();
+
+    // Both participants commit to their share of the accordant spend key
+    let alice_commit = CommitHashLock::<BtcXmr>::new(alice_key, &alice_share);
+    let bob_commit = CommitHashLock::<BtcXmr>::new(bob_key, &bob_share);
+    let msg = ProtocolMessage::CommitHashLock(alice_commit.clone());
+    let alice_commit = match deserialize::<ProtocolMessage<BtcXmr>>(&serialize(&msg)).unwrap() {
+        ProtocolMessage::CommitHashLock(commit) => commit,
+        _ => panic!("expected a hash lock commitment"),
+    };
+    let data_lock = DataHashLock {
+        timelock: CSVTimelock::new(10),
+        success: alice_commit.hashed_key(),
+        failure: bob_commit.hashed_key(),
+    };
+
+    // Bob funds and locks the contract
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    funding
+        .update(Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::default(),
+                sequence: 0xffffffff,
+                witness: vec![],
+            }],
+            output: vec![TxOut {
+                value: 100_000,
+                script_pubkey: funding.get_address().unwrap().script_pubkey(),
+            }],
+        })
+        .unwrap();
+    let target_amount = Amount::from_sat(99_000);
+    let mut lock = htlc::lock(&funding, &data_lock, target_amount).unwrap();
+    htlc::verify_lock(&lock, &data_lock, target_amount).unwrap();
+    assert!(htlc::verify_lock(&lock, &data_lock, Amount::from_sat(1)).is_err());
+    let sig = sign_hash(
+        lock.generate_witness_message(ScriptPath::Success).unwrap(),
+        &funding_secret,
+    )
+    .unwrap();
+    lock.add_witness(funding_key, sig).unwrap();
+    lock.finalize_and_extract().unwrap();
+
+    // Alice claims the contract, revealing her share
+    let destination = Address::p2wpkh(&alice_key, bitcoin::Network::Bitcoin).unwrap();
+    let mut claim = Tx::<HtlcClaim>::initialize(&lock, destination.clone()).unwrap();
+    assert!(claim
+        .add_secret(&data_lock, bob_share.as_canonical_bytes())
+        .is_err());
+    claim
+        .add_secret(&data_lock, alice_share.as_canonical_bytes())
+        .unwrap();
+    let sig = sign_hash(
+        claim.generate_witness_message(ScriptPath::Success).unwrap(),
+        &alice_secret,
+    )
+    .unwrap();
+    claim.add_witness(alice_key, sig).unwrap();
+    let claimed = claim.finalize_and_extract().unwrap();
+    assert_eq!(claimed.input[0].witness.len(), 4);
+    assert_eq!(claimed.input[0].witness[2], vec![1]);
+    assert_eq!(
+        claimed.input[0].witness[3],
+        htlc::htlc_script(&data_lock).into_bytes()
+    );
+
+    // The contract is exported as a miniscript descriptor and parsed back
+    let desc = descriptor::htlc_descriptor(&data_lock);
+    assert_eq!(
+        desc.script_pubkey().unwrap(),
+        lock.get_consumable_output().unwrap().tx_out.script_pubkey
+    );
+    assert!(desc.to_string().starts_with("wsh(c:or_i(and_v(v:sha256("));
+    assert_eq!(desc.to_string().parse::<Descriptor>().unwrap(), desc);
+
+    // Bob extracts Alice's share from the witness
+    let secret = htlc::extract_secret(&claimed, &alice_commit.hash).unwrap();
+    assert_eq!(alice_commit.open(&secret).unwrap(), alice_share);
+    assert!(bob_commit.open(&secret).is_err());
+    assert!(htlc::extract_secret(&claimed, &bob_commit.hash).is_none());
+
+    // Bob refunds after the timelock with his own share
+    let refund = Tx::<HtlcRefund>::initialize(&lock, &data_lock, destination).unwrap();
+    let refund_tx = refund.extract();
+    assert_eq!(refund_tx.input[0].sequence, 10);
+}
@@ -0,0 +1,572 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::bitcoin::transaction::{
+    signature_hash_preimage, Funding, Refund, Tx, TxInRef,
+};
+use farcaster_core::chain::bitcoin::Bitcoin;
+use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+use farcaster_core::vectors;
+
+use farcaster_core::blockchain::{FeePolitic, Network};
+use farcaster_core::bundle::{AliceParameters, BobParameters, CoreArbitratingTransactions};
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey, Sign};
+use farcaster_core::instruction::{
+    AdaptRequest, Error, PendingRequest, Policy, PolicyClient, RemoteSigner, SignClient,
+    SignRequest, SignResponse, SignedDigest, SwapContext, WatchOnlySigner,
+};
+use farcaster_core::negotiation::PublicOffer;
+use farcaster_core::role::{Alice, Bob};
+use farcaster_core::script::ScriptPath;
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::{Fundable, Transaction, TxLabel, Witnessable};
+
+use bitcoin::hashes::sha256d::Hash as Sha256dHash;
+use bitcoin::hashes::Hash;
+use bitcoin::Address;
+
+use std::cell::{Cell, RefCell};
+use std::str::FromStr;
+
+const OFFER: &str = vectors::PUBLIC_OFFER;
+
+// A client holding the private keys and recording the requests received
+struct LocalClient {
+    wallet: Wallet,
+    requests: RefCell<Vec<(TxLabel, ArbitratingKeyId)>>,
+}
+
+impl SignClient<Bitcoin> for LocalClient {
+    fn sign(&self, request: SignRequest<Bitcoin>) -> Result<SignResponse<Bitcoin>, Error> {
+        self.requests
+            .borrow_mut()
+            .push((request.tx_label, request.key_id));
+        let key = self.wallet.get_pubkey(request.key_id).map_err(Error::new)?;
+        let signature = match request.adaptor {
+            Some(adaptor) => SignedDigest::AdaptorSignature(
+                self.wallet
+                    .adaptor_sign_with_key(&key, &adaptor, request.digest)
+                    .map_err(Error::new)?,
+            ),
+            None => SignedDigest::Signature(
+                self.wallet
+                    .sign_with_key(&key, request.digest)
+                    .map_err(Error::new)?,
+            ),
+        };
+        Ok(SignResponse {
+            swap_id: request.swap_id,
+            tx_label: request.tx_label,
+            signature,
+        })
+    }
+
+    fn adapt(&self, request: AdaptRequest<Bitcoin>) -> Result<SignResponse<Bitcoin>, Error> {
+        let key = self.wallet.get_pubkey(request.key_id).map_err(Error::new)?;
+        let signature = self
+            .wallet
+            .adapt_signature(&key, request.adaptor_signature)
+            .map_err(Error::new)?;
+        Ok(SignResponse {
+            swap_id: request.swap_id,
+            tx_label: request.tx_label,
+            signature: SignedDigest::Signature(signature),
+        })
+    }
+}
+
+struct Setup {
+    pub_offer: PublicOffer<BtcXmr>,
+    alice: Alice<BtcXmr>,
+    bob: Bob<BtcXmr>,
+    alice_wallet: Wallet,
+    bob_wallet: Wallet,
+    alice_params: AliceParameters<BtcXmr>,
+    bob_params: BobParameters<BtcXmr>,
+    core: CoreArbitratingTransactions<Bitcoin>,
+}
+
+fn setup() -> Setup {
+    let pub_offer: PublicOffer<BtcXmr> = deserialize(&hex::decode(OFFER).unwrap()[..]).unwrap();
+
+    let funding_tx = "020000000001010000000000000000000000000000000000000000000000000000000000\
+               000000ffffffff03510101ffffffff0200f2052a0100000016001490d2e860d4e51f68857d65bfa\
+               7d0da32dd6c9b350000000000000000266a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c\
+               690689799962b48bebd836974e8cf90120000000000000000000000000000000000000000000000\
+               000000000000000000000000000";
+    let funding_tx: bitcoin::Transaction =
+        bitcoin::consensus::encode::deserialize(&hex::decode(funding_tx).unwrap()).unwrap();
+
+    let address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+    let alice: Alice<BtcXmr> = Alice::new(address.clone(), FeePolitic::Aggressive);
+    let bob: Bob<BtcXmr> = Bob::new(address, FeePolitic::Aggressive);
+
+    let alice_wallet = Wallet::new([
+        32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10,
+        9, 8, 7, 6, 5, 4, 3, 2, 1,
+    ]);
+    let bob_wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    let mut funding_tx = funding_tx;
+    funding_tx.output[0].script_pubkey = funding.get_address().unwrap().script_pubkey();
+    funding.update(funding_tx).unwrap();
+    let core = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
+        .unwrap();
+
+    Setup {
+        pub_offer,
+        alice,
+        bob,
+        alice_wallet,
+        bob_wallet,
+        alice_params,
+        bob_params,
+        core,
+    }
+}
+
+#[test]
+fn sign_with_remote_signer() {
+    let Setup {
+        pub_offer,
+        alice,
+        bob,
+        alice_wallet,
+        bob_wallet,
+        alice_params,
+        bob_params,
+        core,
+    } = setup();
+
+    let swap_id = SwapId([0x42; 32]);
+    let verifier = Wallet::new_keyless();
+
+    // Bob signs the lock through the client, the funding key also signs sweeps
+    let bob_client = LocalClient {
+        wallet: bob_wallet.clone(),
+        requests: RefCell::new(vec![]),
+    };
+    let remote = RemoteSigner::new(swap_id, &bob_wallet, &bob_client, &verifier);
+    assert!(bob
+        .sign_arbitrating_lock(&remote, &bob_wallet, &core)
+        .is_err());
+    let remote = remote.with_tx_label(TxLabel::Lock);
+    let signed_lock = bob
+        .sign_arbitrating_lock(&remote, &bob_wallet, &core)
+        .unwrap();
+    let expected = bob
+        .sign_arbitrating_lock(&bob_wallet, &bob_wallet, &core)
+        .unwrap();
+    assert_eq!(signed_lock.lock_sig, expected.lock_sig);
+    assert_eq!(
+        bob_client.requests.borrow()[..],
+        [(TxLabel::Lock, ArbitratingKeyId::Fund)]
+    );
+
+    // Alice adaptor signs the refund through the client
+    let alice_client = LocalClient {
+        wallet: alice_wallet.clone(),
+        requests: RefCell::new(vec![]),
+    };
+    let remote = RemoteSigner::new(swap_id, &alice_wallet, &alice_client, &verifier);
+    let adaptor_refund = alice
+        .sign_adaptor_refund(&remote, &alice_params, &bob_params, &core, &pub_offer)
+        .unwrap();
+    assert!(bob
+        .validate_adaptor_refund(
+            &verifier,
+            &alice_params,
+            &bob_params,
+            &core,
+            &adaptor_refund
+        )
+        .is_ok());
+    assert_eq!(
+        alice_client.requests.borrow()[..],
+        [(TxLabel::Refund, ArbitratingKeyId::Refund)]
+    );
+
+    // Unknown keys are not forwarded to the client
+    let remote = RemoteSigner::new(swap_id, &verifier, &alice_client, &verifier);
+    assert!(remote
+        .sign_with_key(&alice_params.buy, Sha256dHash::hash(b"msg"))
+        .is_err());
+}
+
+// A client signing another digest than the one requested
+struct DishonestClient(LocalClient);
+
+impl SignClient<Bitcoin> for DishonestClient {
+    fn sign(&self, mut request: SignRequest<Bitcoin>) -> Result<SignResponse<Bitcoin>, Error> {
+        request.digest = Sha256dHash::hash(b"other");
+        self.0.sign(request)
+    }
+
+    fn adapt(&self, request: AdaptRequest<Bitcoin>) -> Result<SignResponse<Bitcoin>, Error> {
+        self.0.adapt(request)
+    }
+}
+
+#[test]
+fn reject_invalid_remote_signatures() {
+    let Setup {
+        alice_wallet,
+        alice_params,
+        bob_params,
+        ..
+    } = setup();
+
+    let swap_id = SwapId([0x42; 32]);
+    let verifier = Wallet::new_keyless();
+    let client = DishonestClient(LocalClient {
+        wallet: alice_wallet.clone(),
+        requests: RefCell::new(vec![]),
+    });
+    let remote = RemoteSigner::new(swap_id, &alice_wallet, &client, &verifier);
+    let msg = Sha256dHash::hash(b"msg");
+
+    // MUST fail if the signature is not valid for the requested key and digest
+    assert!(remote.sign_with_key(&alice_params.buy, msg).is_err());
+    assert!(remote
+        .adaptor_sign_with_key(&alice_params.refund, &bob_params.adaptor, msg)
+        .is_err());
+    assert_eq!(client.0.requests.borrow().len(), 2);
+
+    // Honest signatures are accepted
+    let client = LocalClient {
+        wallet: alice_wallet.clone(),
+        requests: RefCell::new(vec![]),
+    };
+    let remote = RemoteSigner::new(swap_id, &alice_wallet, &client, &verifier);
+    let sig = remote.sign_with_key(&alice_params.buy, msg).unwrap();
+    assert!(verifier
+        .verify_signature(&alice_params.buy, msg, &sig)
+        .is_ok());
+}
+
+#[test]
+fn sign_with_watch_only_signer() {
+    let Setup {
+        pub_offer,
+        alice,
+        bob,
+        alice_wallet,
+        bob_wallet,
+        alice_params,
+        bob_params,
+        core,
+    } = setup();
+
+    let swap_id = SwapId([0x42; 32]);
+    let verifier = Wallet::new_keyless();
+    let offline_client = LocalClient {
+        wallet: bob_wallet.clone(),
+        requests: RefCell::new(vec![]),
+    };
+
+    // The signature is deferred to the air-gapped client
+    let mut watch_only =
+        WatchOnlySigner::new(swap_id, &bob_wallet, &verifier).with_tx_label(TxLabel::Lock);
+    assert!(bob
+        .sign_arbitrating_lock(&watch_only, &bob_wallet, &core)
+        .is_err());
+    // Calling again does not issue the request twice
+    assert!(bob
+        .sign_arbitrating_lock(&watch_only, &bob_wallet, &core)
+        .is_err());
+    let pending = watch_only.pending();
+    assert_eq!(pending.len(), 1);
+
+    // The requests are exported and answered offline
+    let exported = serialize(&pending[0]);
+    let request = match deserialize::<PendingRequest<Bitcoin>>(&exported[..]).unwrap() {
+        PendingRequest::Sign(request) => request,
+        PendingRequest::Adapt(_) => panic!("unexpected adapt request"),
+    };
+    assert_eq!(request.tx_label, TxLabel::Lock);
+
+    // MUST error if the response is not valid for the request
+    let alice_fund = alice_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let forged = SignResponse {
+        swap_id,
+        tx_label: TxLabel::Lock,
+        signature: SignedDigest::Signature(
+            alice_wallet
+                .sign_with_key(&alice_fund, request.digest)
+                .unwrap(),
+        ),
+    };
+    assert!(matches!(
+        watch_only.answer(forged),
+        Err(Error::InvalidResponse)
+    ));
+    assert!(watch_only.has_pending());
+
+    let response = offline_client.sign(request).unwrap();
+    watch_only.answer(response.clone()).unwrap();
+    assert!(!watch_only.has_pending());
+    // MUST error if no request is pending for the response
+    assert!(matches!(
+        watch_only.answer(response),
+        Err(Error::InvalidResponse)
+    ));
+
+    // The swap resumes with the answered signature
+    let signed_lock = bob
+        .sign_arbitrating_lock(&watch_only, &bob_wallet, &core)
+        .unwrap();
+    let expected = bob
+        .sign_arbitrating_lock(&bob_wallet, &bob_wallet, &core)
+        .unwrap();
+    assert_eq!(signed_lock.lock_sig, expected.lock_sig);
+
+    // Adaptor signatures are verified when answered and checked by the counter-party
+    let alice_client = LocalClient {
+        wallet: alice_wallet.clone(),
+        requests: RefCell::new(vec![]),
+    };
+    let mut watch_only = WatchOnlySigner::new(swap_id, &alice_wallet, &verifier);
+    assert!(alice
+        .sign_adaptor_refund(&watch_only, &alice_params, &bob_params, &core, &pub_offer)
+        .is_err());
+    for pending in watch_only.pending() {
+        if let PendingRequest::Sign(request) = pending {
+            watch_only
+                .answer(alice_client.sign(request).unwrap())
+                .unwrap();
+        }
+    }
+    let adaptor_refund = alice
+        .sign_adaptor_refund(&watch_only, &alice_params, &bob_params, &core, &pub_offer)
+        .unwrap();
+    assert!(bob
+        .validate_adaptor_refund(
+            &verifier,
+            &alice_params,
+            &bob_params,
+            &core,
+            &adaptor_refund
+        )
+        .is_ok());
+}
+
+// A client refusing to sign digests it cannot recompute
+struct VerifyingClient(LocalClient);
+
+impl SignClient<Bitcoin> for VerifyingClient {
+    fn sign(&self, request: SignRequest<Bitcoin>) -> Result<SignResponse<Bitcoin>, Error> {
+        request.verify_preimage()?;
+        self.0.sign(request)
+    }
+
+    fn adapt(&self, request: AdaptRequest<Bitcoin>) -> Result<SignResponse<Bitcoin>, Error> {
+        self.0.adapt(request)
+    }
+}
+
+#[test]
+fn sign_with_sighash_preimage() {
+    let Setup {
+        pub_offer,
+        alice,
+        alice_wallet,
+        alice_params,
+        bob_params,
+        core,
+        ..
+    } = setup();
+
+    let swap_id = SwapId([0x42; 32]);
+    let verifier = Wallet::new_keyless();
+    let client = VerifyingClient(LocalClient {
+        wallet: alice_wallet.clone(),
+        requests: RefCell::new(vec![]),
+    });
+
+    // MUST error if the preimage is not attached to the request
+    let mut remote = RemoteSigner::new(swap_id, &alice_wallet, &client, &verifier);
+    assert!(alice
+        .sign_adaptor_refund(&remote, &alice_params, &bob_params, &core, &pub_offer)
+        .is_err());
+
+    let refund = Tx::<Refund>::from_partial(core.refund.clone());
+    let preimage = refund
+        .generate_witness_preimage(ScriptPath::Success)
+        .unwrap();
+    let unsigned_tx = &core.refund.global.unsigned_tx;
+    let input = &core.refund.inputs[0];
+    assert_eq!(
+        preimage,
+        signature_hash_preimage(
+            TxInRef::new(unsigned_tx, 0),
+            input.witness_script.as_ref().unwrap(),
+            input.witness_utxo.as_ref().unwrap().value,
+            input.sighash_type.unwrap(),
+        )
+    );
+    assert_eq!(
+        Sha256dHash::hash(&preimage),
+        refund
+            .generate_witness_message(ScriptPath::Success)
+            .unwrap()
+    );
+    remote.add_preimage(preimage.clone());
+    assert!(alice
+        .sign_adaptor_refund(&remote, &alice_params, &bob_params, &core, &pub_offer)
+        .is_ok());
+
+    // MUST error if the digest is not computed from the preimage
+    let request = SignRequest::<Bitcoin> {
+        swap_id,
+        tx_label: TxLabel::Refund,
+        digest: Sha256dHash::hash(b"farcaster"),
+        key_id: ArbitratingKeyId::Refund,
+        adaptor: None,
+        preimage: Some(preimage),
+    };
+    assert!(matches!(
+        request.verify_preimage(),
+        Err(Error::InvalidPreimage)
+    ));
+    let request = SignRequest {
+        preimage: None,
+        ..request
+    };
+    assert!(matches!(
+        request.verify_preimage(),
+        Err(Error::MissingPreimage)
+    ));
+}
+
+#[test]
+fn encode_sign_request_and_response() {
+    let wallet = Wallet::new([1; 32]);
+    let key = wallet.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+    let digest = Sha256dHash::hash(b"farcaster");
+
+    let request: SignRequest<Bitcoin> = SignRequest {
+        swap_id: SwapId([0x01; 32]),
+        tx_label: TxLabel::Buy,
+        digest,
+        key_id: ArbitratingKeyId::Extra(7),
+        adaptor: Some(key),
+        preimage: Some(b"farcaster".to_vec()),
+    };
+    let ser = serialize(&request);
+    let de: SignRequest<Bitcoin> = deserialize(&ser[..]).unwrap();
+    assert_eq!(de.swap_id, request.swap_id);
+    assert_eq!(de.tx_label, TxLabel::Buy);
+    assert_eq!(de.digest, digest);
+    assert_eq!(de.key_id, ArbitratingKeyId::Extra(7));
+    assert_eq!(de.adaptor, Some(key));
+    assert_eq!(de.preimage, Some(b"farcaster".to_vec()));
+    assert!(de.verify_preimage().is_ok());
+
+    let response: SignResponse<Bitcoin> = SignResponse {
+        swap_id: SwapId([0x01; 32]),
+        tx_label: TxLabel::Buy,
+        signature: SignedDigest::AdaptorSignature(wallet.sign_with_key(&key, digest).unwrap()),
+    };
+    let ser = serialize(&response);
+    let de: SignResponse<Bitcoin> = deserialize(&ser[..]).unwrap();
+    assert_eq!(ser, serialize(&de));
+    assert!(matches!(de.signature, SignedDigest::AdaptorSignature(_)));
+}
+
+// Allows swaps up to 0.01 BTC on testnet and at most two requests
+struct TestPolicy {
+    requests: Cell<u32>,
+}
+
+impl Policy<Bitcoin> for TestPolicy {
+    fn max_amount(&self) -> Option<bitcoin::Amount> {
+        Some(bitcoin::Amount::from_sat(1_000_000))
+    }
+
+    fn allowed_networks(&self) -> Option<Vec<Network>> {
+        Some(vec![Network::Testnet])
+    }
+
+    fn allow_request(&self, _swap_id: &SwapId, _tx_label: TxLabel) -> bool {
+        self.requests.set(self.requests.get() + 1);
+        self.requests.get() <= 2
+    }
+}
+
+#[test]
+fn enforce_sign_policy() {
+    let pub_offer: PublicOffer<BtcXmr> = deserialize(&hex::decode(OFFER).unwrap()[..]).unwrap();
+    let wallet = Wallet::new([1; 32]);
+    let client = LocalClient {
+        wallet: wallet.clone(),
+        requests: RefCell::new(vec![]),
+    };
+    let policy = TestPolicy {
+        requests: Cell::new(0),
+    };
+    let mut policy_client = PolicyClient::new(&client, &policy);
+
+    let request = |swap_id: SwapId| SignRequest::<Bitcoin> {
+        swap_id,
+        tx_label: TxLabel::Lock,
+        digest: Sha256dHash::hash(b"farcaster"),
+        key_id: ArbitratingKeyId::Fund,
+        adaptor: None,
+        preimage: None,
+    };
+
+    // Unknown swaps are refused
+    let swap_id = SwapId([0x01; 32]);
+    assert!(matches!(
+        policy_client.sign(request(swap_id)),
+        Err(Error::Refused)
+    ));
+
+    policy_client.register_swap(swap_id, SwapContext::new(&pub_offer.offer, None));
+    assert!(policy_client.sign(request(swap_id)).is_ok());
+
+    // Swaps above the maximum amount are refused
+    let mut offer = pub_offer.offer.clone();
+    offer.arbitrating_amount = bitcoin::Amount::from_sat(1_000_001);
+    let big_swap = SwapId([0x02; 32]);
+    policy_client.register_swap(big_swap, SwapContext::new(&offer, None));
+    assert!(matches!(
+        policy_client.sign(request(big_swap)),
+        Err(Error::AmountNotAllowed)
+    ));
+
+    // Swaps on other networks are refused
+    let mut offer = pub_offer.offer.clone();
+    offer.network = Network::Mainnet;
+    let mainnet_swap = SwapId([0x03; 32]);
+    policy_client.register_swap(mainnet_swap, SwapContext::new(&offer, None));
+    assert!(matches!(
+        policy_client.sign(request(mainnet_swap)),
+        Err(Error::NetworkNotAllowed)
+    ));
+
+    // Rate limited after two requests
+    assert!(policy_client.sign(request(swap_id)).is_ok());
+    assert!(matches!(
+        policy_client.sign(request(swap_id)),
+        Err(Error::RateLimited)
+    ));
+    assert_eq!(client.requests.borrow().len(), 2);
+
+    policy_client.remove_swap(&swap_id);
+    assert!(matches!(
+        policy_client.sign(request(swap_id)),
+        Err(Error::Refused)
+    ));
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "monero")]
+
+use curve25519_dalek::scalar::Scalar;
+use farcaster_core::chain::monero::key_image::{
+    hash_to_point, key_image, lock_key_images, spent_key_images,
+};
+use farcaster_core::chain::monero::Monero;
+use farcaster_core::crypto::KeyShares;
+
+use monero::blockdata::transaction::{ExtraField, KeyImage, SubField, TxIn, TxOutTarget};
+use monero::cryptonote::hash::Hash;
+use monero::cryptonote::onetime_key::KeyGenerator;
+use monero::util::ringct::RctSig;
+use monero::{Address, Network, PrivateKey, PublicKey, TransactionPrefix, TxOut, VarInt};
+
+use std::str::FromStr;
+
+fn transaction(
+    inputs: Vec<TxIn>,
+    outputs: Vec<PublicKey>,
+    tx_key: &PrivateKey,
+) -> monero::Transaction {
+    monero::Transaction {
+        prefix: TransactionPrefix {
+            version: VarInt(2),
+            unlock_time: VarInt(0),
+            inputs,
+            outputs: outputs
+                .into_iter()
+                .map(|key| TxOut {
+                    amount: VarInt(0),
+                    target: TxOutTarget::ToKey { key },
+                })
+                .collect(),
+            extra: ExtraField(vec![SubField::TxPublicKey(PublicKey::from_private_key(
+                tx_key,
+            ))]),
+        },
+        signatures: vec![],
+        rct_signatures: RctSig { sig: None, p: None },
+    }
+}
+
+#[test]
+fn compute_key_image() {
+    let secret =
+        PrivateKey::from_str("77916d0cd56ed1920aef6ca56d8a41bac915b68e4c46a589e0956e27a7b77404")
+            .unwrap();
+    let public = PublicKey::from_private_key(&secret);
+    assert_eq!(
+        public.to_string(),
+        "eac2cc96e0ae684388e3185d5277e51313bff98b9ad4a12dcd9205f20d37f1a3"
+    );
+    assert_eq!(
+        hex::encode(hash_to_point(&public).compress().to_bytes()),
+        "d6a85f88530dfa1facd142d025ddbb610e01d549c83a297eb028b487356e5991"
+    );
+    assert_eq!(
+        hex::encode(key_image(&secret).image.to_bytes()),
+        "8f0dc1daa023dd499483652550967a220cfc9f35f820c85a74babee025bdfc2d"
+    );
+}
+
+#[test]
+fn watch_lock_output_spends() {
+    let spend_alice = PrivateKey::from_scalar(Scalar::from(11u64));
+    let spend_bob = PrivateKey::from_scalar(Scalar::from(31u64));
+    let view = PrivateKey::from_scalar(Scalar::from(13u64));
+    let spend = Monero::aggregate_private_shares(&spend_alice, &spend_bob);
+    let address = Address::standard(
+        Network::Stagenet,
+        PublicKey::from_private_key(&spend),
+        PublicKey::from_private_key(&view),
+    );
+    let tx_key = PrivateKey::from_scalar(Scalar::from(17u64));
+    let generator = KeyGenerator::from_random(address.public_view, address.public_spend, tx_key);
+    let other = PublicKey::from_private_key(&PrivateKey::from_scalar(Scalar::from(19u64)));
+    let lock = transaction(vec![], vec![other, generator.one_time_key(1)], &tx_key);
+    // Manually recomputed from the recovered one-time private key of the output
+    let expected = key_image(&(generator.get_rvn_scalar(1) + spend));
+
+    let images = lock_key_images(&lock, &spend_alice, &spend_bob, &view);
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].0, 1);
+    assert_eq!(images[0].1.image, expected.image);
+    // Without the right shares no output is recognized as locked
+    assert!(lock_key_images(&lock, &spend_alice, &spend_alice, &view).is_empty());
+
+    let watched: Vec<KeyImage> = images.into_iter().map(|(_, image)| image).collect();
+    let unrelated = KeyImage {
+        image: Hash([0x42; 32]),
+    };
+    let input = |k_image: &KeyImage| TxIn::ToKey {
+        amount: VarInt(0),
+        key_offsets: vec![VarInt(3), VarInt(7)],
+        k_image: k_image.clone(),
+    };
+    let sweep = transaction(
+        vec![input(&unrelated), input(&watched[0])],
+        vec![other],
+        &tx_key,
+    );
+    let spent = spent_key_images(&sweep, &watched);
+    assert_eq!(spent.len(), 1);
+    assert_eq!(spent[0].image, expected.image);
+    let unrelated_spend = transaction(vec![input(&unrelated)], vec![other], &tx_key);
+    assert!(spent_key_images(&unrelated_spend, &watched).is_empty());
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "test-utils")]
+
+use farcaster_core::blockchain::Network;
+use farcaster_core::chain::bitcoin::local::{self as btc_local, faucet_transaction};
+use farcaster_core::chain::bitcoin::transaction::Funding;
+use farcaster_core::chain::monero::local as xmr_local;
+use farcaster_core::chain::pairs::btcxmr::{funded_wallet, Wallet};
+use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey};
+use farcaster_core::syncer::TaskId;
+use farcaster_core::transaction::{Fundable, TxLabel};
+
+use bitcoin::hashes::Hash;
+use bitcoin::Amount;
+
+#[test]
+fn local_network_params() {
+    let params = btc_local::LocalParams::regtest();
+    assert_eq!(params, btc_local::LocalParams::default());
+    assert_eq!(params.magic, 0xdab5bffa);
+    assert_eq!(params.finality, btc_local::REGTEST_FINALITY);
+    assert!(params.finality < btc_local::PUBLIC_NETWORK_FINALITY);
+
+    let custom = params
+        .with_magic(0x0b110907)
+        .with_genesis_hash(bitcoin::BlockHash::hash(b"custom genesis"))
+        .with_finality(3);
+    assert_ne!(custom.genesis_hash, params.genesis_hash);
+    let task = custom.watch_transaction(TaskId(1), TxLabel::Lock, 100, vec![0x42; 32]);
+    assert_eq!(task.confirmation_bound, 3);
+    assert_eq!(task.label, TxLabel::Lock);
+
+    let params = xmr_local::LocalParams::regtest();
+    assert_eq!(params.network, monero::Network::Mainnet);
+    assert_eq!(
+        hex::encode(params.genesis_hash.as_bytes()),
+        "418015bb9ae982a1975da7d79277c2705727a56894ba0fb246adaabb1f4632e3"
+    );
+    assert!(params.finality < xmr_local::PUBLIC_NETWORK_FINALITY);
+    let stagenet = params
+        .with_network(monero::Network::Stagenet)
+        .with_finality(2);
+    assert_eq!(
+        stagenet
+            .watch_transaction(TaskId(2), TxLabel::AccordantLock, 100, vec![])
+            .confirmation_bound,
+        2
+    );
+}
+
+#[test]
+fn fund_deterministic_wallets() {
+    let (wallet, tx) = funded_wallet("bob", Amount::from_sat(100_000)).unwrap();
+    let (_, same_tx) = funded_wallet("bob", Amount::from_sat(100_000)).unwrap();
+    assert_eq!(tx, same_tx);
+    let (other, _) = funded_wallet("alice", Amount::from_sat(100_000)).unwrap();
+    assert_ne!(
+        wallet.local_funding_address().unwrap(),
+        other.local_funding_address().unwrap()
+    );
+
+    // The faucet transaction funds the swap funding on a local network
+    let pubkey = wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(pubkey, Network::Local).unwrap();
+    assert_eq!(
+        funding.get_address().unwrap(),
+        wallet.local_funding_address().unwrap()
+    );
+    funding.update(tx).unwrap();
+    assert_eq!(funding.get_amount().unwrap(), Amount::from_sat(100_000));
+
+    let address = Wallet::from_label("carol").local_funding_address().unwrap();
+    let tx = faucet_transaction(&address, Amount::from_sat(42));
+    assert_eq!(tx.output[0].script_pubkey, address.script_pubkey());
+}
@@ -1,24 +1,46 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::bitcoin::deterrence::{
+    self, DeterrencePolicy, DeterrenceWarning, PunishPath, Severity,
+};
 use farcaster_core::chain::bitcoin::fee::SatPerVByte;
 use farcaster_core::chain::bitcoin::timelock::CSVTimelock;
 use farcaster_core::chain::bitcoin::Bitcoin;
 use farcaster_core::chain::monero::Monero;
 use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::vectors;
 
-use farcaster_core::blockchain::{FeeStrategy, Network};
-use farcaster_core::consensus::{self, deserialize, serialize_hex};
-use farcaster_core::negotiation::{Buy, Offer, PublicOffer, Sell};
+use farcaster_core::blockchain::{Asset, AssetId, FeeStrategy, Network};
+use farcaster_core::consensus::{
+    self, deserialize, serialize, serialize_hex, CanonicalBytes, Encodable,
+};
+use farcaster_core::negotiation::book::{
+    BookRecord, EvictionPolicy, OfferBook, OfferStore, ResolveError,
+};
+use farcaster_core::negotiation::gossip::{self, OfferFilter, ShortCode, ShortOfferId};
+use farcaster_core::negotiation::signing::{self, SignedOffer};
+use farcaster_core::negotiation::{
+    self, BlindedPublicOffer, Buy, FieldDiff, IntentAmount, Offer, OfferBuilderError, OfferField,
+    OfferOpening, PublicOffer, Quote, QuoteExpired, ReQuote, Sell, TakerIntent, Version,
+    FEATURE_COMPRESSION, PUBLIC_OFFER_HRP,
+};
 use farcaster_core::role::SwapRole;
+use farcaster_core::timeouts::{StallDetector, StallPhase, StallTimeouts};
 
+use bech32::ToBase32;
+use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
 use bitcoin::Amount;
 
 use internet2::{RemoteNodeAddr, RemoteSocketAddr};
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::str::FromStr;
+use std::time::Duration;
 
 #[test]
 fn create_offer() {
-    let hex = "02000000808000008008000500000000000000080006000000000000000400070000000400080000000\
-               10800090000000000000002";
+    let hex = vectors::OFFER;
     let offer: Offer<BtcXmr> = Offer {
         network: Network::Testnet,
         arbitrating_blockchain: Bitcoin,
@@ -29,6 +51,7 @@ fn create_offer() {
         punish_timelock: CSVTimelock::new(8),
         fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(9)),
         maker_role: SwapRole::Bob,
+        stall_timeouts: None,
     };
 
     assert_eq!(hex, serialize_hex(&offer));
@@ -36,41 +59,84 @@ fn create_offer() {
 
 #[test]
 fn maker_buy_arbitrating_assets_offer() {
-    let offer: Option<Offer<BtcXmr>> = Buy::some(Bitcoin, Amount::from_sat(100000))
-        .with(Monero, monero::Amount::from_pico(200))
-        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
-        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
-        .on(Network::Testnet)
-        .to_offer();
-    assert!(offer.is_some());
+    let offer: Result<Offer<BtcXmr>, OfferBuilderError> =
+        Buy::some(Bitcoin, Amount::from_sat(100000))
+            .with(Monero, monero::Amount::from_pico(200))
+            .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+            .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+            .on(Network::Testnet)
+            .to_offer();
+    assert!(offer.is_ok());
     assert_eq!(offer.expect("an offer").maker_role, SwapRole::Alice);
 }
 
 #[test]
 fn maker_sell_arbitrating_assets_offer() {
-    let offer: Option<Offer<BtcXmr>> = Sell::some(Bitcoin, Amount::from_sat(100000))
+    let offer: Result<Offer<BtcXmr>, OfferBuilderError> =
+        Sell::some(Bitcoin, Amount::from_sat(100000))
+            .for_some(Monero, monero::Amount::from_pico(200))
+            .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+            .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+            .on(Network::Testnet)
+            .to_offer();
+    assert!(offer.is_ok());
+    assert_eq!(offer.expect("an offer").maker_role, SwapRole::Bob);
+}
+
+#[test]
+fn report_offer_builder_errors() {
+    let missing = Buy::<BtcXmr>::some(Bitcoin, Amount::from_sat(100000))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+        .to_offer()
+        .unwrap_err();
+    assert_eq!(
+        missing,
+        OfferBuilderError::MissingFields(vec![
+            OfferField::Network,
+            OfferField::AccordantBlockchain,
+            OfferField::AccordantAmount,
+            OfferField::FeeStrategy,
+        ])
+    );
+    assert_eq!(
+        missing.to_string(),
+        "Missing offer fields: network, accordant blockchain, accordant amount, fee strategy"
+    );
+
+    let empty_range = Sell::<BtcXmr>::some(Bitcoin, Amount::from_sat(100000))
+        .for_some(Monero, monero::Amount::from_pico(200))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+        .with_fee(FeeStrategy::Range(
+            SatPerVByte::from_sat(20)..SatPerVByte::from_sat(10),
+        ))
+        .on(Network::Testnet)
+        .to_offer();
+    assert_eq!(empty_range, Err(OfferBuilderError::EmptyFeeRange));
+
+    let inconsistent = Sell::<BtcXmr>::some(Bitcoin, Amount::from_sat(100000))
         .for_some(Monero, monero::Amount::from_pico(200))
         .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
         .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
         .on(Network::Testnet)
         .to_offer();
-    assert!(offer.is_some());
-    assert_eq!(offer.expect("an offer").maker_role, SwapRole::Bob);
+    assert_eq!(inconsistent, Err(OfferBuilderError::InconsistentTimelocks));
 }
 
 #[test]
 fn serialize_public_offer() {
-    let hex = "46435357415001000200000080800000800800a0860100000000000800c80000000000000004000\
-               a00000004000a00000001080014000000000000000203b31a0a70343bb46f3db3768296ac5027f9\
-               873921b37f852860c690063ff9e4c90000000000000000000000000000000000000000000000000\
-               000000000000000000000260700";
-    let offer: Offer<BtcXmr> = Sell::some(Bitcoin, Amount::from_sat(100000))
-        .for_some(Monero, monero::Amount::from_pico(200))
-        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
-        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
-        .on(Network::Testnet)
-        .to_offer()
-        .unwrap();
+    let hex = vectors::PUBLIC_OFFER;
+    let offer: Offer<BtcXmr> = Offer {
+        network: Network::Testnet,
+        arbitrating_blockchain: Bitcoin,
+        accordant_blockchain: Monero,
+        arbitrating_amount: Amount::from_sat(100000),
+        accordant_amount: monero::Amount::from_pico(200),
+        cancel_timelock: CSVTimelock::new(10),
+        punish_timelock: CSVTimelock::new(10),
+        fee_strategy: FeeStrategy::Fixed(SatPerVByte::from_sat(20)),
+        maker_role: SwapRole::Bob,
+        stall_timeouts: None,
+    };
     let overlay = FromStr::from_str("tcp").unwrap();
     let ip = FromStr::from_str("0.0.0.0").unwrap();
     let port = FromStr::from_str("9735").unwrap();
@@ -90,19 +156,921 @@ fn serialize_public_offer() {
     assert_eq!(hex, serialize_hex(&public_offer));
 }
 
+#[test]
+fn convert_public_offer_from_and_into_bytes() {
+    let hex = vectors::PUBLIC_OFFER;
+    let bytes = hex::decode(hex).unwrap();
+
+    let public_offer = PublicOffer::<BtcXmr>::try_from(&bytes[..]).expect("Parsable public offer");
+    assert_eq!(Vec::<u8>::from(&public_offer), bytes);
+    assert_eq!(public_offer, deserialize(&bytes[..]).unwrap());
+    let offer_bytes: Vec<u8> = (&public_offer.offer).into();
+    assert_eq!(
+        Offer::<BtcXmr>::try_from(&offer_bytes[..]).unwrap(),
+        public_offer.offer
+    );
+
+    // Bytes MUST be consumed entirely
+    let mut trailing = bytes.clone();
+    trailing.push(0x00);
+    assert!(PublicOffer::<BtcXmr>::try_from(&trailing[..]).is_err());
+    assert!(PublicOffer::<BtcXmr>::try_from(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn annotate_public_offer() {
+    let bytes = hex::decode(vectors::PUBLIC_OFFER).unwrap();
+
+    let dump = consensus::annotate::<PublicOffer<BtcXmr>>(&bytes);
+    assert!(dump.is_complete());
+    // The top-level fields cover all the bytes in order
+    let mut position = 0;
+    for span in dump.spans().iter().filter(|span| span.depth == 0) {
+        assert_eq!(span.range.start, position);
+        position = span.range.end;
+    }
+    assert_eq!(position, bytes.len());
+    let fields: Vec<&str> = dump.spans().iter().map(|s| s.field.as_str()).collect();
+    assert_eq!(
+        &fields[..4],
+        &["magic_bytes", "version", "offer", "offer.network"]
+    );
+    assert_eq!(fields.last(), Some(&"daemon_service"));
+    let timelock = dump
+        .spans()
+        .iter()
+        .find(|span| span.field == "offer.cancel_timelock")
+        .unwrap();
+    assert_eq!(
+        dump.field_at(timelock.range.start).unwrap().field,
+        "offer.cancel_timelock"
+    );
+
+    // A truncated offer reports the field being decoded
+    let dump = consensus::annotate::<PublicOffer<BtcXmr>>(&bytes[..timelock.range.end - 1]);
+    let failure = dump.failure().unwrap();
+    assert_eq!(failure.field, "offer.cancel_timelock");
+    assert_eq!(failure.range, timelock.range.start..timelock.range.end - 1);
+    assert!(dump.to_string().contains(&format!(
+        "decode failed at bytes {}..{} (offer.cancel_timelock)",
+        timelock.range.start,
+        timelock.range.end - 1
+    )));
+
+    // As well as the trailing bytes
+    let mut trailing = bytes.clone();
+    trailing.push(0x00);
+    let dump = consensus::annotate::<PublicOffer<BtcXmr>>(&trailing);
+    assert_eq!(dump.failure().unwrap().range, bytes.len()..trailing.len());
+}
+
+#[test]
+fn diff_public_offers() {
+    let hex = vectors::PUBLIC_OFFER;
+    let public_offer: PublicOffer<BtcXmr> = deserialize(&hex::decode(hex).unwrap()[..]).unwrap();
+    assert!(public_offer.diff(&public_offer.clone()).is_empty());
+
+    let mut edited = public_offer.clone();
+    edited.offer.accordant_amount = monero::Amount::from_pico(300);
+    edited.offer.punish_timelock = CSVTimelock::new(11);
+    let diffs = public_offer.diff(&edited);
+    assert_eq!(
+        diffs.iter().map(|d| d.field).collect::<Vec<_>>(),
+        vec![OfferField::AccordantAmount, OfferField::PunishTimelock]
+    );
+    assert_eq!(diffs, public_offer.offer.diff(&edited.offer));
+    assert_eq!(
+        diffs[1],
+        FieldDiff {
+            field: OfferField::PunishTimelock,
+            expected: format!("{:?}", CSVTimelock::new(10)),
+            found: format!("{:?}", CSVTimelock::new(11)),
+        }
+    );
+    assert!(diffs[1]
+        .to_string()
+        .starts_with("punish timelock: expected"));
+
+    edited.version = Version::new(2);
+    assert_eq!(public_offer.diff(&edited)[0].field, OfferField::Version);
+}
+
+#[test]
+fn encode_public_offer_in_bech32() {
+    let hex = vectors::PUBLIC_OFFER;
+    let public_offer: PublicOffer<BtcXmr> = deserialize(&hex::decode(hex).unwrap()[..]).unwrap();
+    let encoded = public_offer.to_bech32();
+    assert!(encoded.starts_with(&format!("{}1", PUBLIC_OFFER_HRP)));
+    assert_eq!(PublicOffer::from_bech32(&encoded).unwrap(), public_offer);
+    assert_eq!(
+        PublicOffer::from_bech32(&encoded.to_uppercase()).unwrap(),
+        public_offer
+    );
+    // Both the hex and the bech32 encodings are parsed
+    assert_eq!(
+        encoded.parse::<PublicOffer<BtcXmr>>().unwrap(),
+        public_offer
+    );
+    assert_eq!(hex.parse::<PublicOffer<BtcXmr>>().unwrap(), public_offer);
+
+    // MUST fail if a character is corrupted
+    let mut corrupted = encoded.clone().into_bytes();
+    let i = corrupted.len() / 2;
+    corrupted[i] = if corrupted[i] == b'q' { b'p' } else { b'q' };
+    let corrupted = String::from_utf8(corrupted).unwrap();
+    assert!(PublicOffer::<BtcXmr>::from_bech32(&corrupted).is_err());
+    assert!(corrupted.parse::<PublicOffer<BtcXmr>>().is_err());
+
+    // MUST fail with another human readable part
+    let other = bech32::encode("bc", serialize(&public_offer).to_base32()).unwrap();
+    assert!(PublicOffer::<BtcXmr>::from_bech32(&other).is_err());
+}
+
 #[test]
 fn check_public_offer_magic_bytes() {
-    let valid = "46435357415001000200000080800000800800a0860100000000000800c80000000000000004000\
-                 a00000004000a00000001080014000000000000000203b31a0a70343bb46f3db3768296ac5027f9\
-                 873921b37f852860c690063ff9e4c90000000000000000000000000000000000000000000000000\
-                 000000000000000000000260700";
+    let valid = vectors::PUBLIC_OFFER;
     let pub_offer: Result<PublicOffer<BtcXmr>, consensus::Error> =
         deserialize(&hex::decode(valid).unwrap()[..]);
     assert!(pub_offer.is_ok());
 
-    let invalid = "474353574150010002000000808000008008a08601000000000008c800000000000000040a00000\
-                   0040a0000000108140000000000000002";
+    let invalid = vectors::INVALID_MAGIC_PUBLIC_OFFER;
     let pub_offer: Result<PublicOffer<BtcXmr>, consensus::Error> =
         deserialize(&hex::decode(invalid).unwrap()[..]);
     assert!(pub_offer.is_err());
 }
+
+#[test]
+fn check_public_offer_network_magic_bytes() {
+    let hex = vectors::PUBLIC_OFFER;
+    let bytes = hex::decode(hex).unwrap();
+    assert_eq!(
+        Network::from_offer_magic_bytes(&bytes[..6]),
+        Some(Network::Testnet)
+    );
+
+    // A testnet offer with mainnet magic bytes is rejected
+    let mut mainnet = bytes.clone();
+    mainnet[..6].copy_from_slice(Network::Mainnet.offer_magic_bytes());
+    assert!(matches!(
+        deserialize::<PublicOffer<BtcXmr>>(&mainnet[..]),
+        Err(consensus::Error::IncorrectMagicBytes)
+    ));
+
+    // Re-encoding the offer on mainnet uses mainnet magic bytes
+    let mut pub_offer: PublicOffer<BtcXmr> = deserialize(&bytes[..]).unwrap();
+    pub_offer.offer.network = Network::Mainnet;
+    let ser = serialize(&pub_offer);
+    assert_eq!(&ser[..6], Network::Mainnet.offer_magic_bytes());
+    assert!(deserialize::<PublicOffer<BtcXmr>>(&ser[..]).is_ok());
+}
+
+#[test]
+fn network_constants_are_distinct() {
+    for (i, a) in Network::ALL.iter().enumerate() {
+        for b in Network::ALL.iter().skip(i + 1) {
+            assert_ne!(a.offer_magic_bytes(), b.offer_magic_bytes());
+            assert_ne!(a.blinded_offer_magic_bytes(), b.blinded_offer_magic_bytes());
+            assert_ne!(a.offer_magic_bytes(), b.blinded_offer_magic_bytes());
+            assert_ne!(a.bech32_hrp(), b.bech32_hrp());
+            assert_ne!(a.uri_scheme(), b.uri_scheme());
+        }
+        assert_eq!(
+            Network::from_offer_magic_bytes(a.offer_magic_bytes()),
+            Some(*a)
+        );
+        assert_eq!(
+            Network::from_blinded_offer_magic_bytes(a.blinded_offer_magic_bytes()),
+            Some(*a)
+        );
+        assert_eq!(Network::from_bech32_hrp(a.bech32_hrp()), Some(*a));
+        assert_eq!(Network::from_uri_scheme(a.uri_scheme()), Some(*a));
+    }
+    assert_eq!(Network::from_offer_magic_bytes(b"FCBLND"), None);
+}
+
+#[test]
+fn decode_any_public_offer() {
+    let hex = vectors::PUBLIC_OFFER;
+    let bytes = hex::decode(hex).unwrap();
+
+    let decoded = [
+        consensus::try_decode_any::<BtcXmr>(&bytes),
+        consensus::try_decode_any_str::<BtcXmr>(hex),
+    ];
+    for entity in decoded.iter() {
+        match entity {
+            Ok(consensus::DecodedEntity::PublicOffer(pub_offer)) => {
+                assert_eq!(hex, serialize_hex(pub_offer))
+            }
+            _ => panic!("blob should decode as a public offer"),
+        }
+    }
+}
+
+#[test]
+fn blind_and_open_offer() {
+    let offer: Offer<BtcXmr> = Sell::some(Bitcoin, Amount::from_sat(100000))
+        .for_some(Monero, monero::Amount::from_pico(200))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_offer()
+        .unwrap();
+    let overlay = FromStr::from_str("tcp").unwrap();
+    let ip = FromStr::from_str("0.0.0.0").unwrap();
+    let port = FromStr::from_str("9735").unwrap();
+    let remote_addr = RemoteSocketAddr::with_ip_addr(overlay, ip, port);
+
+    let secp = secp256k1::Secp256k1::new();
+    let sk = bitcoin::PrivateKey::from_wif("L1HKVVLHXiUhecWnwFYF6L3shkf1E12HUmuZTESvBXUdx3yqVP1D")
+        .unwrap()
+        .key;
+    let node_id = secp256k1::PublicKey::from_secret_key(&secp, &sk);
+    let peer = RemoteNodeAddr {
+        node_id,
+        remote_addr,
+    };
+
+    let (blinded, opening) = offer.blind([0x11; 32], [0x22; 32]).unwrap();
+    let pub_blinded = blinded.to_public_v1(peer.clone());
+
+    // The blinded public offer does not reveal the amounts
+    let ser = serialize(&pub_blinded);
+    let amount = consensus::serialize(&offer.arbitrating_amount.as_canonical_bytes());
+    assert!(!ser.windows(amount.len()).any(|w| w == &amount[..]));
+
+    let pub_blinded: BlindedPublicOffer<BtcXmr> = deserialize(&ser[..]).unwrap();
+    match consensus::try_decode_any::<BtcXmr>(&ser) {
+        Ok(consensus::DecodedEntity::BlindedPublicOffer(_)) => (),
+        _ => panic!("blob should decode as a blinded public offer"),
+    }
+
+    // The taker receives the opening and verifies it
+    let opening: OfferOpening<BtcXmr> = deserialize(&serialize(&opening)[..]).unwrap();
+    let pub_offer = pub_blinded.open(&opening).unwrap();
+    assert_eq!(pub_offer, offer.clone().to_public_v1(peer));
+
+    // A tampered opening MUST fail
+    let mut tampered = opening.clone();
+    tampered.arbitrating_amount = Amount::from_sat(100001);
+    assert!(pub_blinded.open(&tampered).is_err());
+    let mut tampered = opening;
+    tampered.accordant_blinding = [0x23; 32];
+    assert!(pub_blinded.offer.open(&tampered).is_err());
+}
+
+#[test]
+fn encode_asset_identifiers() {
+    // Native assets are encoded as their SLIP 44 identifier
+    assert_eq!(Bitcoin.asset_id(), AssetId::native(0x80000000));
+    assert_eq!(serialize_hex(&Bitcoin.asset_id()), "00000080");
+    assert_eq!(
+        Bitcoin::from_asset_id(&AssetId::native(0x80000000)),
+        Some(Bitcoin)
+    );
+
+    // Tokens carry the blockchain identifier and the contract hash
+    let token = AssetId::token(0x8000003c, [0x42; 32]);
+    assert!(token.is_token());
+    let ser = serialize(&token);
+    assert_eq!(ser.len(), 4 + 4 + 32);
+    assert_eq!(&ser[..4], &[0xff; 4]);
+    let de: AssetId = deserialize(&ser[..]).unwrap();
+    assert_eq!(de, token);
+
+    // Native assets implementations do not parse tokens
+    assert_eq!(
+        Bitcoin::from_asset_id(&AssetId::token(0x80000000, [0x42; 32])),
+        None
+    );
+    let hex = "0200000080ffffffff80000080424242424242424242424242424242424242424242424242424242\
+               4242424242";
+    let mut bytes = hex::decode(hex).unwrap();
+    bytes.extend_from_slice(&[0u8; 48]);
+    assert!(matches!(
+        deserialize::<Offer<BtcXmr>>(&bytes[..]),
+        Err(consensus::Error::UnknownType)
+    ));
+}
+
+#[test]
+fn quote_taker_intent() {
+    let intent: TakerIntent<BtcXmr> = TakerIntent {
+        network: Network::Testnet,
+        arbitrating_blockchain: Bitcoin,
+        accordant_blockchain: Monero,
+        amount: IntentAmount::Arbitrating(Amount::from_sat(100000)),
+        taker_role: SwapRole::Alice,
+    };
+    let ser = serialize(&intent);
+    let de: TakerIntent<BtcXmr> = deserialize(&ser[..]).unwrap();
+    assert_eq!(de.id(), intent.id());
+
+    // Quote 2000 piconero per satoshi, only sell bitcoin
+    let policy = |intent: &TakerIntent<BtcXmr>| match (intent.amount, intent.taker_role) {
+        (IntentAmount::Arbitrating(amount), SwapRole::Alice) => Sell::some(Bitcoin, amount)
+            .for_some(Monero, monero::Amount::from_pico(amount.as_sat() * 2000))
+            .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+            .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+            .on(intent.network)
+            .to_offer()
+            .ok(),
+        _ => None,
+    };
+
+    let overlay = FromStr::from_str("tcp").unwrap();
+    let ip = FromStr::from_str("0.0.0.0").unwrap();
+    let port = FromStr::from_str("9735").unwrap();
+    let secp = secp256k1::Secp256k1::new();
+    let sk = bitcoin::PrivateKey::from_wif("L1HKVVLHXiUhecWnwFYF6L3shkf1E12HUmuZTESvBXUdx3yqVP1D")
+        .unwrap()
+        .key;
+    let peer = RemoteNodeAddr {
+        node_id: secp256k1::PublicKey::from_secret_key(&secp, &sk),
+        remote_addr: RemoteSocketAddr::with_ip_addr(overlay, ip, port),
+    };
+
+    let quote = negotiation::quote(&policy, &intent, peer.clone())
+        .unwrap()
+        .unwrap();
+    assert!(quote.verify(&intent).is_ok());
+    assert_eq!(
+        quote.public_offer.offer.accordant_amount,
+        monero::Amount::from_pico(200_000_000)
+    );
+    let ser = serialize(&quote);
+    let de: Quote<BtcXmr> = deserialize(&ser[..]).unwrap();
+    assert!(de.verify(&intent).is_ok());
+
+    // The quote does not answer another intent
+    let mut other = intent.clone();
+    other.amount = IntentAmount::Arbitrating(Amount::from_sat(1));
+    assert!(quote.verify(&other).is_err());
+
+    // The maker does not buy bitcoin
+    let mut buy = intent.clone();
+    buy.taker_role = SwapRole::Bob;
+    assert!(negotiation::quote(&policy, &buy, peer.clone())
+        .unwrap()
+        .is_none());
+
+    // A policy generating an offer not matching the intent is rejected
+    let bad_policy = |intent: &TakerIntent<BtcXmr>| policy(&other).or_else(|| policy(intent));
+    assert!(matches!(
+        negotiation::quote(&bad_policy, &intent, peer),
+        Err(negotiation::Error::InvalidQuote)
+    ));
+
+    // The taker did not accept in time, the maker refreshes the pricing
+    let expired = QuoteExpired::new(&quote);
+    assert_eq!(expired.intent_id, intent.id());
+    assert_eq!(expired.quote_id, quote.id());
+    let de: QuoteExpired = deserialize(&serialize(&expired)).unwrap();
+    assert_eq!(de, expired);
+
+    let accordant_amount = monero::Amount::from_pico(210_000_000);
+    let arbitrating_amount = Amount::from_sat(100000);
+    let requote = ReQuote::new(&quote, arbitrating_amount, accordant_amount, None).unwrap();
+    assert_eq!(requote.expired_quote_id, expired.quote_id);
+    assert!(requote.verify(&intent, &quote, None).is_ok());
+    assert!(requote
+        .verify(&intent, &quote, Some(StallPhase::Reveal))
+        .is_ok());
+    let de: ReQuote<BtcXmr> = deserialize(&serialize(&requote)).unwrap();
+    assert!(de.verify(&intent, &quote, None).is_ok());
+
+    // MUST error once the swap reached a funding phase
+    assert!(matches!(
+        ReQuote::new(
+            &quote,
+            arbitrating_amount,
+            accordant_amount,
+            Some(StallPhase::CoreArbitratingSetup)
+        ),
+        Err(negotiation::Error::ReQuoteNotAllowed(
+            StallPhase::CoreArbitratingSetup
+        ))
+    ));
+    assert!(matches!(
+        requote.verify(&intent, &quote, Some(StallPhase::RefundProcedureSignatures)),
+        Err(negotiation::Error::ReQuoteNotAllowed(_))
+    ));
+
+    // MUST error if the re-quote changes more than the amounts
+    let mut tampered = requote.clone();
+    tampered.quote.public_offer.offer.cancel_timelock = CSVTimelock::new(1);
+    assert!(matches!(
+        tampered.verify(&intent, &quote, None),
+        Err(negotiation::Error::InvalidReQuote)
+    ));
+    // MUST error if the re-quote does not refresh the expired quote
+    let mut other_quote = quote.clone();
+    other_quote.public_offer.offer.accordant_amount = accordant_amount;
+    assert!(matches!(
+        requote.verify(&intent, &other_quote, None),
+        Err(negotiation::Error::InvalidReQuote)
+    ));
+    // The refreshed pricing must still satisfy the intent
+    let smaller = ReQuote::new(&quote, Amount::from_sat(1), accordant_amount, None).unwrap();
+    assert!(matches!(
+        smaller.verify(&intent, &quote, None),
+        Err(negotiation::Error::InvalidQuote)
+    ));
+}
+
+#[test]
+fn scan_public_offers_in_stream() {
+    let hex = vectors::PUBLIC_OFFER;
+    let offer = hex::decode(hex).unwrap();
+    let mut mainnet: PublicOffer<BtcXmr> = deserialize(&offer[..]).unwrap();
+    mainnet.offer.network = Network::Mainnet;
+    let mainnet = serialize(&mainnet);
+
+    // Two offers in a log with garbage and truncated magic bytes in between
+    let mut stream = b"[INFO] offer: ".to_vec();
+    stream.extend_from_slice(&offer);
+    stream.extend_from_slice(b"\nFCSW garbage FCSWPT truncated\n");
+    let second = stream.len();
+    stream.extend_from_slice(&mainnet);
+
+    let results: Vec<_> = negotiation::scan::<BtcXmr, _>(&stream[..]).collect();
+    assert_eq!(results.len(), 3);
+    let first = results[0].as_ref().unwrap();
+    assert_eq!(first.position, 14);
+    assert_eq!(first.public_offer.offer.network, Network::Testnet);
+    // The truncated magic bytes fail to decode
+    assert!(results[1].is_err());
+    let last = results[2].as_ref().unwrap();
+    assert_eq!(last.position, second);
+    assert_eq!(last.public_offer.offer.network, Network::Mainnet);
+
+    assert_eq!(negotiation::scan::<BtcXmr, _>(&b"no offer"[..]).count(), 0);
+}
+
+#[test]
+fn negotiate_compression_feature() {
+    let v1 = Version::new_v1();
+    assert!(!v1.has_feature(FEATURE_COMPRESSION));
+    let compressed = Version::new_v1().with_feature(FEATURE_COMPRESSION);
+    assert_eq!(compressed.version(), 1);
+    assert_eq!(compressed.to_u16(), 0x0101);
+    assert!(compressed.has_feature(FEATURE_COMPRESSION));
+    assert!(compressed.supports_compression_with(&compressed));
+    assert!(!compressed.supports_compression_with(&v1));
+    assert!(!v1.supports_compression_with(&compressed));
+}
+
+#[test]
+fn offer_with_stall_timeouts() {
+    let timeouts = StallTimeouts {
+        reveal: Duration::from_secs(5 * 60),
+        ..StallTimeouts::uniform(Duration::from_secs(30 * 60))
+    };
+    let offer: Offer<BtcXmr> = Sell::some(Bitcoin, Amount::from_sat(100000))
+        .for_some(Monero, monero::Amount::from_pico(200))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .with_stall_timeouts(timeouts)
+        .to_offer()
+        .expect("an offer");
+    assert_eq!(offer.min_version(), 2);
+
+    // Stall timeouts are only encoded by public offers of version 2
+    let public_offer: PublicOffer<BtcXmr> =
+        deserialize(&hex::decode(vectors::PUBLIC_OFFER).unwrap()[..]).unwrap();
+    let mut public_offer = public_offer.upgrade(2).unwrap();
+    public_offer.offer.stall_timeouts = Some(timeouts);
+    assert_eq!(vectors::PUBLIC_OFFER_V2, serialize_hex(&public_offer));
+    let de: PublicOffer<BtcXmr> = deserialize(&serialize(&public_offer)[..]).unwrap();
+    assert_eq!(de.offer.stall_timeouts, Some(timeouts));
+    let v1 = PublicOffer {
+        version: Version::new_v1(),
+        ..public_offer.clone()
+    };
+    assert!(v1.consensus_encode(&mut vec![]).is_err());
+    // An offer alone is encoded with the layout of its minimal version
+    let mut v2 = vec![];
+    offer.consensus_encode_versioned(2, &mut v2).unwrap();
+    assert_eq!(serialize(&offer), v2);
+
+    // Out of bounds timeouts are rejected by the builder and when decoding
+    let mut invalid = public_offer.clone();
+    invalid.offer.stall_timeouts = Some(StallTimeouts::uniform(Duration::from_secs(1)));
+    assert!(deserialize::<PublicOffer<BtcXmr>>(&serialize(&invalid)[..]).is_err());
+    assert!(matches!(
+        Sell::<BtcXmr>::some(Bitcoin, Amount::from_sat(100000))
+            .for_some(Monero, monero::Amount::from_pico(200))
+            .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+            .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+            .on(Network::Testnet)
+            .with_stall_timeouts(StallTimeouts::uniform(Duration::from_secs(
+                7 * 24 * 60 * 60
+            )))
+            .to_offer(),
+        Err(OfferBuilderError::InvalidStallTimeouts(_))
+    ));
+
+    // The counter-party is dropped once it exceeds the agreed response time
+    let mut detector = StallDetector::from_offer(&offer).expect("stall timeouts");
+    let commit = Duration::from_secs(1_600_000_000);
+    assert!(!detector.is_stalled(commit));
+    detector.start(StallPhase::Reveal, commit);
+    assert_eq!(
+        detector.remaining(commit + Duration::from_secs(60)),
+        Some(Duration::from_secs(4 * 60))
+    );
+    assert!(!detector.is_stalled(commit + Duration::from_secs(4 * 60)));
+    assert_eq!(
+        detector.stalled(commit + Duration::from_secs(5 * 60)),
+        Some(StallPhase::Reveal)
+    );
+    let reveal = commit + Duration::from_secs(4 * 60);
+    detector.start(StallPhase::CoreArbitratingSetup, reveal);
+    assert!(!detector.is_stalled(commit + Duration::from_secs(5 * 60)));
+    detector.stop();
+    assert_eq!(detector.remaining(reveal), None);
+}
+
+#[test]
+fn encode_public_offer_with_stall_timeouts() {
+    let offer: Offer<BtcXmr> = Sell::some(Bitcoin, Amount::from_sat(100000))
+        .for_some(Monero, monero::Amount::from_pico(200))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .with_stall_timeouts(StallTimeouts::uniform(Duration::from_secs(30 * 60)))
+        .to_offer()
+        .expect("an offer");
+    let peer = book_offer(200).daemon_service;
+
+    // The public offer is stamped with the version able to carry the stall timeouts
+    let public_offer = offer.clone().to_public(peer.clone());
+    assert_eq!(public_offer.version.version(), 2);
+    assert_eq!(offer.clone().to_public_v1(peer.clone()), public_offer);
+    let displayed = public_offer.to_string();
+    assert_eq!(
+        displayed.parse::<PublicOffer<BtcXmr>>().unwrap(),
+        public_offer
+    );
+    let encoded = public_offer.to_bech32();
+    assert_eq!(PublicOffer::from_bech32(&encoded).unwrap(), public_offer);
+    assert_eq!(
+        encoded.parse::<PublicOffer<BtcXmr>>().unwrap(),
+        public_offer
+    );
+
+    // Offers without stall timeouts are still stamped with version 1
+    let mut no_timeouts = offer.clone();
+    no_timeouts.stall_timeouts = None;
+    assert_eq!(no_timeouts.to_public(peer.clone()).version.version(), 1);
+
+    // Blinded public offers are compared without failing to encode
+    let (blinded, opening) = offer.blind([0x11; 32], [0x22; 32]).unwrap();
+    let pub_blinded = blinded.to_public(peer.clone());
+    assert_eq!(pub_blinded.version.version(), 2);
+    assert_eq!(pub_blinded, pub_blinded.clone());
+    let de: BlindedPublicOffer<BtcXmr> = deserialize(&serialize(&pub_blinded)).unwrap();
+    assert_eq!(de.open(&opening).unwrap(), public_offer);
+}
+
+#[test]
+fn reconcile_offer_inventories() {
+    let hex = vectors::PUBLIC_OFFER;
+    let public_offer: PublicOffer<BtcXmr> = deserialize(&hex::decode(hex).unwrap()[..]).unwrap();
+    let offers: Vec<PublicOffer<BtcXmr>> = (1..=100u64)
+        .map(|i| {
+            let mut offer = public_offer.clone();
+            offer.offer.arbitrating_amount = Amount::from_sat(i * 1000);
+            offer
+        })
+        .collect();
+
+    let id = public_offer.short_id();
+    assert_eq!(id, public_offer.clone().short_id());
+    assert_ne!(id, offers[0].short_id());
+    let de: ShortOfferId = deserialize(&serialize(&id)).unwrap();
+    assert_eq!(de, id);
+    assert_eq!(id.to_string().len(), 32);
+
+    let mut duplicated = offers[..10].to_vec();
+    duplicated.extend_from_slice(&offers[..5]);
+    let inventory = gossip::inventory(&duplicated);
+    assert_eq!(inventory.len(), 10);
+    assert!(inventory.windows(2).all(|w| w[0] < w[1]));
+
+    // The peer knows the first half of the offers and receives only the missing ones
+    let filter = OfferFilter::from_offers(&offers[..50], 0.001, 42);
+    assert!(offers[..50].iter().all(|o| filter.contains(&o.short_id())));
+    let missing = filter.missing(&offers);
+    assert!(missing.len() >= 48 && missing.len() <= 50);
+    assert!(missing.iter().all(|o| !filter.contains(&o.short_id())));
+
+    let de: OfferFilter = deserialize(&serialize(&filter)).unwrap();
+    assert_eq!(de, filter);
+    // An empty filter matches nothing
+    assert_eq!(OfferFilter::new(0, 0, 0).missing(&offers).len(), 100);
+    // MUST error if the filter has no hash function
+    let mut invalid = serialize(&OfferFilter::new(1, 1, 0));
+    invalid[3] = 0x00;
+    assert!(deserialize::<OfferFilter>(&invalid[..]).is_err());
+}
+
+#[test]
+fn bulk_sign_and_verify_offers() {
+    let secp = Secp256k1::signing_only();
+    let maker = SecretKey::from_slice(&[0x42; 32]).unwrap();
+    let hex = vectors::PUBLIC_OFFER;
+    let mut public_offer: PublicOffer<BtcXmr> =
+        deserialize(&hex::decode(hex).unwrap()[..]).unwrap();
+    public_offer.daemon_service.node_id = PublicKey::from_secret_key(&secp, &maker);
+    let offers: Vec<PublicOffer<BtcXmr>> = (1..=20u64)
+        .map(|i| {
+            let mut offer = public_offer.clone();
+            offer.offer.arbitrating_amount = Amount::from_sat(i * 1000);
+            offer
+        })
+        .collect();
+
+    let mut book = signing::sign_offers(offers[..15].to_vec(), &maker, &[0x01; 32]).unwrap();
+    book.extend(signing::sign_offers(offers[15..].to_vec(), &maker, &[0x02; 32]).unwrap());
+    assert_eq!(book.len(), 20);
+    assert!(book.iter().all(|o| o.verify().is_ok()));
+    signing::verify_offers(&book).unwrap();
+    let de: SignedOffer<BtcXmr> = deserialize(&serialize(&book[3])).unwrap();
+    assert_eq!(de, book[3]);
+    assert!(signing::sign_offers::<BtcXmr>(vec![], &maker, &[0x01; 32])
+        .unwrap()
+        .is_empty());
+
+    // Tampered offers are filtered out of the book
+    book[2].public_offer.offer.arbitrating_amount = Amount::from_sat(1);
+    book[17].signature = book[0].signature;
+    let valid = signing::valid_offers(&book);
+    assert_eq!(valid.len(), 18);
+    assert!(matches!(
+        signing::verify_offers(&book),
+        Err(negotiation::Error::InvalidSignature)
+    ));
+    assert!(book[17].verify().is_err());
+
+    // MUST error if an offer is announced by another node
+    let other = SecretKey::from_slice(&[0x43; 32]).unwrap();
+    assert!(matches!(
+        signing::sign_offers(offers, &other, &[0x01; 32]),
+        Err(negotiation::Error::NodeKeyMismatch)
+    ));
+}
+
+#[test]
+fn translate_public_offer_versions() {
+    let hex = vectors::PUBLIC_OFFER;
+    let public_offer: PublicOffer<BtcXmr> = deserialize(&hex::decode(hex).unwrap()[..]).unwrap();
+    assert_eq!(public_offer.offer.min_version(), 1);
+
+    let mut compressed = public_offer.clone();
+    compressed.version = compressed.version.with_feature(FEATURE_COMPRESSION);
+    let upgraded = compressed.upgrade(negotiation::LATEST_VERSION).unwrap();
+    assert_eq!(upgraded.version.version(), 2);
+    assert!(upgraded.version.has_feature(FEATURE_COMPRESSION));
+    assert_eq!(upgraded.offer, public_offer.offer);
+    let downgraded = upgraded.downgrade(1).unwrap();
+    assert_eq!(downgraded, compressed);
+
+    // The layout of the encoding follows the version, a downgraded offer is readable by peers
+    // running version 1
+    let upgraded = public_offer.upgrade(2).unwrap();
+    assert_eq!(
+        serialize(&upgraded).len(),
+        serialize(&public_offer).len() + 1
+    );
+    assert_eq!(serialize_hex(&upgraded.downgrade(1).unwrap()), hex);
+    let bytes = hex::decode(hex).unwrap();
+    assert!(deserialize::<PublicOffer<BtcXmr>>(&serialize(&upgraded)[..]).is_ok());
+    let mut relabeled = bytes.clone();
+    relabeled[6] = 0x02;
+    assert!(deserialize::<PublicOffer<BtcXmr>>(&relabeled[..]).is_err());
+
+    // MUST fail to decode unknown versions
+    for version in [0x00, negotiation::LATEST_VERSION as u8 + 1].iter() {
+        let mut unknown = bytes.clone();
+        unknown[6] = *version;
+        assert!(matches!(
+            deserialize::<PublicOffer<BtcXmr>>(&unknown[..]),
+            Err(consensus::Error::UnsupportedVersion)
+        ));
+    }
+
+    // MUST error if the version is unknown
+    assert!(matches!(
+        public_offer.upgrade(negotiation::LATEST_VERSION + 1),
+        Err(negotiation::Error::UnsupportedVersion)
+    ));
+    assert!(matches!(
+        upgraded.upgrade(1),
+        Err(negotiation::Error::UnsupportedVersion)
+    ));
+    assert!(matches!(
+        public_offer.downgrade(0),
+        Err(negotiation::Error::UnsupportedVersion)
+    ));
+
+    // MUST error if the stall timeouts would be dropped
+    let mut with_timeouts = upgraded;
+    with_timeouts.offer.stall_timeouts = Some(StallTimeouts::uniform(Duration::from_secs(600)));
+    assert_eq!(with_timeouts.offer.min_version(), 2);
+    assert!(matches!(
+        with_timeouts.downgrade(1),
+        Err(negotiation::Error::LossyConversion(1))
+    ));
+}
+
+fn book_offer(amount: u64) -> PublicOffer<BtcXmr> {
+    let mut public_offer: PublicOffer<BtcXmr> =
+        deserialize(&hex::decode(vectors::PUBLIC_OFFER).unwrap()[..]).unwrap();
+    public_offer.offer.accordant_amount = monero::Amount::from_pico(amount);
+    public_offer
+}
+
+#[derive(Default)]
+struct MemoryStore(HashMap<ShortOfferId, Vec<u8>>);
+
+impl OfferStore for MemoryStore {
+    type Error = ();
+
+    fn save(&mut self, id: &ShortOfferId, record: &[u8]) -> Result<(), ()> {
+        self.0.insert(*id, record.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, id: &ShortOfferId) -> Result<(), ()> {
+        self.0.remove(id);
+        Ok(())
+    }
+}
+
+#[test]
+fn evict_offers_from_bounded_book() {
+    let now = Duration::from_secs(1000);
+    let later = Duration::from_secs(2000);
+    let (a, b, c) = (book_offer(1), book_offer(2), book_offer(3));
+
+    let mut book = OfferBook::new(2);
+    assert!(book.insert(a.clone(), later, now).is_empty());
+    assert!(book.insert(b.clone(), later, now).is_empty());
+    // Accessing the first offer makes the second one the least recently used
+    assert_eq!(book.get(&a.short_id(), now), Some(&a));
+    assert_eq!(book.insert(c.clone(), later, now), vec![b.clone()]);
+    assert_eq!(book.offers(now).len(), 2);
+    assert!(book.peek(&b.short_id(), now).is_none());
+
+    let mut book = OfferBook::new(2).with_policy(EvictionPolicy::EarliestExpiry);
+    book.insert(a.clone(), later, now);
+    book.insert(b.clone(), Duration::from_secs(1500), now);
+    assert_eq!(book.get(&b.short_id(), now), Some(&b));
+    assert_eq!(book.insert(c.clone(), later, now), vec![b.clone()]);
+
+    // Expired offers are dropped before evicting any other offer
+    let mut book = OfferBook::new(2);
+    book.insert(a.clone(), Duration::from_secs(1100), now);
+    book.insert(b.clone(), later, now);
+    let now = Duration::from_secs(1200);
+    assert!(book.get(&a.short_id(), now).is_none());
+    assert_eq!(book.insert(c, later, now), vec![a]);
+    assert_eq!(book.expire(later), 2);
+    assert!(book.is_empty());
+}
+
+#[test]
+fn resolve_short_codes_in_book() {
+    let now = Duration::from_secs(1000);
+    let (a, b) = (book_offer(1), book_offer(2));
+
+    let code = a.short_code();
+    assert_eq!(code, a.short_id().short_code());
+    assert!(code.matches(&a.short_id()));
+    assert!(!code.matches(&b.short_id()));
+    let displayed = code.to_string();
+    assert_eq!(displayed.len(), 14);
+    assert_eq!(displayed.parse::<ShortCode>().unwrap(), code);
+    let typed: String = displayed.to_uppercase().replace('-', " ");
+    assert_eq!(typed.parse::<ShortCode>().unwrap(), code);
+
+    assert_eq!(
+        ShortOfferId([0; 16]).short_code().to_string(),
+        "qqqq-qqqq-qqqq"
+    );
+    assert_eq!(
+        ShortOfferId([0xff; 16]).short_code().to_string(),
+        "llll-llll-llll"
+    );
+    // MUST fail on characters outside the bech32 alphabet and on wrong lengths
+    assert!("qqqq-qqqq-qqqb".parse::<ShortCode>().is_err());
+    assert!("qqqq-qqqq-qqq".parse::<ShortCode>().is_err());
+    assert!("qqqq-qqqq-qqqqq".parse::<ShortCode>().is_err());
+
+    let mut book = OfferBook::new(10);
+    book.insert(a.clone(), Duration::from_secs(1100), now);
+    book.insert(b.clone(), Duration::from_secs(2000), now);
+    assert_eq!(book.resolve(&code, now), Ok(&a));
+    assert_eq!(book.resolve(&b.short_code(), now), Ok(&b));
+    assert_eq!(
+        book.resolve(&book_offer(3).short_code(), now),
+        Err(ResolveError::NotFound)
+    );
+    // Expired offers are not resolved
+    assert_eq!(
+        book.resolve(&code, Duration::from_secs(1200)),
+        Err(ResolveError::NotFound)
+    );
+}
+
+#[test]
+fn update_and_persist_offer_book() {
+    let now = Duration::from_secs(1000);
+    let expiry = Duration::from_secs(2000);
+    let (a, b) = (book_offer(1), book_offer(2));
+    let mut store = MemoryStore::default();
+
+    let mut book = OfferBook::new(10);
+    book.insert(a.clone(), expiry, now);
+    book.insert(b.clone(), expiry, now);
+    assert!(book.is_dirty());
+    assert_eq!(book.flush(&mut store), Ok(2));
+    assert!(!book.is_dirty());
+    assert_eq!(book.flush(&mut store), Ok(0));
+    let record: BookRecord<BtcXmr> = deserialize(&store.0[&a.short_id()]).unwrap();
+    assert_eq!(record.public_offer, a);
+    assert_eq!(record.expiry, expiry);
+
+    // An edited offer replaces the previous one and reports the edited fields
+    let edited = book_offer(5);
+    let diffs = book.update(&a.short_id(), edited.clone(), expiry).unwrap();
+    assert_eq!(diffs, a.diff(&edited));
+    assert!(book.update(&a.short_id(), edited.clone(), expiry).is_none());
+    assert_eq!(book.flush(&mut store), Ok(2));
+    assert!(!store.0.contains_key(&a.short_id()));
+    assert!(store.0.contains_key(&edited.short_id()));
+
+    let mut restored = OfferBook::<BtcXmr>::new(10);
+    let records: Vec<&[u8]> = store.0.values().map(Vec::as_slice).collect();
+    assert_eq!(restored.restore(records.clone(), now).unwrap(), 2);
+    assert!(!restored.is_dirty());
+    assert_eq!(restored.offers(now), book.offers(now));
+
+    // Records of offers expired while offline are deleted on the next flush
+    let mut restored = OfferBook::<BtcXmr>::new(10);
+    assert_eq!(restored.restore(records, expiry).unwrap(), 0);
+    assert_eq!(restored.flush(&mut store), Ok(2));
+    assert!(store.0.is_empty());
+}
+
+#[test]
+fn check_punishment_deterrence() {
+    let offer = |sat: u64| -> Offer<BtcXmr> {
+        Sell::some(Bitcoin, Amount::from_sat(sat))
+            .for_some(Monero, monero::Amount::from_pico(200))
+            .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(20))
+            .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+            .on(Network::Testnet)
+            .to_offer()
+            .unwrap()
+    };
+
+    let path = PunishPath::new(Amount::from_sat(100_000), &offer(100_000).fee_strategy).unwrap();
+    assert_eq!(path.cancel_fee, path.punish_fee);
+    assert_eq!(
+        path.punishable,
+        Amount::from_sat(100_000) - path.cancel_fee - path.punish_fee
+    );
+    assert!(path.deters(10));
+    assert!(!path.deters(12));
+    assert!(!path.deters(u64::MAX));
+
+    let policy = DeterrencePolicy::default();
+    assert_eq!(
+        policy.min_multiple(),
+        deterrence::DEFAULT_MIN_DETERRENCE_MULTIPLE
+    );
+    assert!(policy.check(&offer(100_000)).unwrap().is_none());
+
+    let warning = DeterrenceWarning {
+        punishable: path.punishable,
+        punish_fee: path.punish_fee,
+        min_multiple: 12,
+    };
+    let strict = DeterrencePolicy::new(12);
+    assert_eq!(strict.severity(), Severity::Reject);
+    assert!(matches!(
+        strict.check(&offer(100_000)),
+        Err(deterrence::Error::InsufficientDeterrence(w)) if w == warning
+    ));
+    let lenient = strict.with_severity(Severity::Warn);
+    assert_eq!(lenient.check(&offer(100_000)).unwrap(), Some(warning));
+
+    // MUST fail if the locked amount cannot pay the fees of the punish path, even when warning
+    assert!(matches!(
+        lenient.check(&offer(10_000)),
+        Err(deterrence::Error::Unpunishable)
+    ));
+}
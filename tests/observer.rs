@@ -0,0 +1,219 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey, Sign};
+use farcaster_core::observer::{self, Observed, Observer, SignatureOp, TaskKind, TransitionEvent};
+use farcaster_core::protocol::session::SessionManager;
+use farcaster_core::protocol::StateMachine;
+use farcaster_core::protocol_message::{Abort, AbortReason, ProtocolMessage};
+use farcaster_core::swap::SwapId;
+use farcaster_core::syncer::{
+    self, BroadcastTransaction, Event, Syncer, TaskId, WatchAddress, WatchHeight, WatchTransaction,
+};
+use farcaster_core::transaction::TxLabel;
+
+use bitcoin::hashes::sha256d::Hash as Sha256dHash;
+use bitcoin::hashes::Hash;
+
+#[derive(Debug, PartialEq)]
+enum Record {
+    Transition(TransitionEvent),
+    Encoded(u16, usize),
+    Decoded(u16, bool),
+    Signature(SignatureOp, bool),
+    Task(TaskKind, TaskId, bool),
+}
+
+static RECORDS: Mutex<Vec<Record>> = Mutex::new(vec![]);
+
+struct Recorder;
+
+impl Observer for Recorder {
+    fn on_transition(&self, event: &TransitionEvent) {
+        // Timings are not deterministic
+        let event = TransitionEvent {
+            elapsed: Duration::default(),
+            ..*event
+        };
+        RECORDS.lock().unwrap().push(Record::Transition(event));
+    }
+
+    fn on_message_encoded(&self, message_type: u16, len: usize) {
+        RECORDS
+            .lock()
+            .unwrap()
+            .push(Record::Encoded(message_type, len));
+    }
+
+    fn on_message_decoded(&self, message_type: u16, success: bool) {
+        RECORDS
+            .lock()
+            .unwrap()
+            .push(Record::Decoded(message_type, success));
+    }
+
+    fn on_signature(&self, op: SignatureOp, success: bool, _elapsed: Duration) {
+        RECORDS.lock().unwrap().push(Record::Signature(op, success));
+    }
+
+    fn on_task_registered(&self, kind: TaskKind, id: TaskId, success: bool) {
+        RECORDS
+            .lock()
+            .unwrap()
+            .push(Record::Task(kind, id, success));
+    }
+}
+
+fn drain() -> Vec<Record> {
+    RECORDS.lock().unwrap().drain(..).collect()
+}
+
+/// Accepts non-null inputs, terminated once the sum reaches ten.
+#[derive(Debug, Clone, PartialEq)]
+struct Sum;
+
+impl StateMachine for Sum {
+    type State = u64;
+    type Input = u8;
+    type Output = u64;
+    type Error = &'static str;
+
+    fn transition(state: &u64, input: &u8) -> Result<(u64, Vec<u64>), Self::Error> {
+        match input {
+            0 => Err("null input"),
+            i => Ok((state + *i as u64, vec![state + *i as u64])),
+        }
+    }
+
+    fn is_terminal(state: &u64) -> bool {
+        *state >= 10
+    }
+}
+
+/// A syncer failing to broadcast transactions.
+struct Offline;
+
+impl Syncer for Offline {
+    fn abort(&mut self, _task: syncer::Abort) -> Result<(), syncer::Error> {
+        Ok(())
+    }
+    fn watch_height(&mut self, _task: WatchHeight) -> Result<(), syncer::Error> {
+        Ok(())
+    }
+    fn watch_address(&mut self, _task: WatchAddress) -> Result<(), syncer::Error> {
+        Ok(())
+    }
+    fn watch_transaction(&mut self, _task: WatchTransaction) -> Result<(), syncer::Error> {
+        Ok(())
+    }
+    fn broadcast_transaction(&mut self, _task: BroadcastTransaction) -> Result<(), syncer::Error> {
+        Err(syncer::Error::new("offline"))
+    }
+    fn poll(&mut self) -> Result<Vec<Event>, syncer::Error> {
+        Ok(vec![])
+    }
+    fn active_tasks(&self) -> Result<Vec<TaskId>, syncer::Error> {
+        Ok(vec![])
+    }
+}
+
+// The observer is global to the process, all the callbacks are checked in the same test
+#[test]
+fn observe_core_operations() {
+    observer::set_observer(Recorder).unwrap();
+    // MUST error if an observer is already set
+    assert!(matches!(
+        observer::set_observer(observer::NoopObserver),
+        Err(observer::Error::AlreadySet)
+    ));
+
+    // Transitions
+    let swap_id = SwapId([0x01; 32]);
+    let mut manager = SessionManager::<Sum, ()>::new(1);
+    manager.open(swap_id, (), 0).unwrap();
+    manager.route(swap_id, 4).unwrap();
+    assert!(manager.route(swap_id, 0).is_err());
+    manager.route(swap_id, 6).unwrap();
+    let event = |accepted, outputs, terminal| {
+        Record::Transition(TransitionEvent {
+            swap_id,
+            accepted,
+            outputs,
+            terminal,
+            elapsed: Duration::default(),
+        })
+    };
+    assert_eq!(
+        drain(),
+        vec![
+            event(true, 1, false),
+            event(false, 0, false),
+            event(true, 1, true)
+        ]
+    );
+    // Inputs sent to terminated sessions are not transitions
+    assert!(manager.route(swap_id, 1).is_err());
+    assert!(drain().is_empty());
+
+    // Messages
+    let message: ProtocolMessage<BtcXmr> =
+        ProtocolMessage::Abort(Abort::new(AbortReason::UserRequested).with_body("bye"));
+    let bytes = serialize(&message);
+    assert_eq!(message.message_type(), 0x08);
+    let _: ProtocolMessage<BtcXmr> = deserialize(&bytes).unwrap();
+    assert!(deserialize::<ProtocolMessage<BtcXmr>>(&bytes[..bytes.len() - 1]).is_err());
+    assert_eq!(
+        drain(),
+        vec![
+            Record::Encoded(0x08, bytes.len()),
+            Record::Decoded(0x08, true),
+            Record::Decoded(0x08, false)
+        ]
+    );
+
+    // Signatures
+    let wallet = Observed(Wallet::new([0x02; 32]));
+    let key = wallet.0.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let msg = Sha256dHash::hash(b"message");
+    let sig = wallet.sign_with_key(&key, msg).unwrap();
+    assert!(wallet.verify_signature(&key, msg, &sig).is_ok());
+    let other = Sha256dHash::hash(b"other message");
+    assert!(wallet.verify_signature(&key, other, &sig).is_err());
+    assert_eq!(
+        drain(),
+        vec![
+            Record::Signature(SignatureOp::Sign, true),
+            Record::Signature(SignatureOp::Verify, true),
+            Record::Signature(SignatureOp::Verify, false)
+        ]
+    );
+
+    // Tasks
+    let mut syncer = Observed(Offline);
+    syncer
+        .watch_height(WatchHeight {
+            id: TaskId(1),
+            lifetime: 10,
+            addendum: vec![],
+        })
+        .unwrap();
+    assert!(syncer
+        .broadcast_transaction(BroadcastTransaction {
+            id: TaskId(2),
+            label: TxLabel::Lock,
+            tx: vec![0x00],
+        })
+        .is_err());
+    assert!(syncer.poll().unwrap().is_empty());
+    assert_eq!(
+        drain(),
+        vec![
+            Record::Task(TaskKind::WatchHeight, TaskId(1), true),
+            Record::Task(TaskKind::BroadcastTransaction, TaskId(2), false)
+        ]
+    );
+}
@@ -0,0 +1,124 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey};
+use farcaster_core::protocol::position::{CheckpointStatus, Error, PositionTracker};
+use farcaster_core::protocol_message::{Abort, AbortReason, ProtocolMessage};
+use farcaster_core::role::SwapRole;
+use farcaster_core::settlement::SwapPhase;
+use farcaster_core::swap::SwapId;
+
+fn abort(body: &str) -> ProtocolMessage<BtcXmr> {
+    ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified).with_body(body))
+}
+
+#[test]
+fn exchange_signed_checkpoints() {
+    let swap_id = SwapId([0x07; 32]);
+    let mut alice = PositionTracker::new(swap_id, SwapRole::Alice);
+    let mut bob = PositionTracker::new(swap_id, SwapRole::Bob);
+    let alice_wallet = Wallet::new([0x01; 32]);
+    let bob_wallet = Wallet::new([0x02; 32]);
+    let alice_key = alice_wallet.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+    let bob_key = bob_wallet.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+
+    // Both participants send a message at the same time, they cross in flight
+    alice.record_sent(&abort("alice"));
+    bob.record_sent(&abort("bob"));
+
+    let checkpoint = bob.checkpoint::<BtcXmr>(&bob_key, &bob_wallet).unwrap();
+    assert_eq!(
+        alice.verify(&checkpoint, &bob_key, &alice_wallet).unwrap(),
+        CheckpointStatus::InFlight {
+            to_peer: 1,
+            from_peer: 1
+        }
+    );
+
+    // The checkpoint is sent as a protocol message and is not recorded
+    let msg = ProtocolMessage::StateCheckpoint(checkpoint);
+    assert_eq!(msg.message_type(), 0x12);
+    let received: ProtocolMessage<BtcXmr> = deserialize(&serialize(&msg)).unwrap();
+    assert_eq!(received, msg);
+    alice.record_received(&received);
+    assert_eq!(alice.digest().bob.len, 0);
+
+    // Once both messages are received, both participants agree
+    alice.record_received(&abort("bob"));
+    bob.record_received(&abort("alice"));
+    assert_eq!(alice.digest(), bob.digest());
+    let checkpoint = bob.checkpoint::<BtcXmr>(&bob_key, &bob_wallet).unwrap();
+    assert_eq!(
+        alice.verify(&checkpoint, &bob_key, &alice_wallet).unwrap(),
+        CheckpointStatus::Agreed
+    );
+    let checkpoint = alice
+        .checkpoint::<BtcXmr>(&alice_key, &alice_wallet)
+        .unwrap();
+    assert_eq!(
+        bob.verify(&checkpoint, &alice_key, &bob_wallet).unwrap(),
+        CheckpointStatus::Agreed
+    );
+
+    // MUST error if the participants are in different phases
+    bob.set_phase(SwapPhase::Lock);
+    let checkpoint = bob.checkpoint::<BtcXmr>(&bob_key, &bob_wallet).unwrap();
+    match alice.verify(&checkpoint, &bob_key, &alice_wallet) {
+        Err(Error::Diverged { local, remote }) => {
+            assert_eq!(local.phase, SwapPhase::Setup);
+            assert_eq!(remote.phase, SwapPhase::Lock);
+        }
+        res => panic!("Expected a divergence, got {:?}", res),
+    }
+    alice.set_phase(SwapPhase::Lock);
+    assert!(alice.verify(&checkpoint, &bob_key, &alice_wallet).is_ok());
+
+    // MUST error if the checkpoint is not signed by the counter-party
+    assert!(matches!(
+        alice.verify(&checkpoint, &alice_key, &alice_wallet),
+        Err(Error::InvalidSignature)
+    ));
+    let other = PositionTracker::new(SwapId([0x08; 32]), SwapRole::Alice);
+    assert!(matches!(
+        other.verify(&checkpoint, &bob_key, &alice_wallet),
+        Err(Error::SwapMismatch)
+    ));
+}
+
+#[test]
+fn detect_diverging_transcripts() {
+    let swap_id = SwapId([0x07; 32]);
+    let mut alice = PositionTracker::new(swap_id, SwapRole::Alice);
+    let mut bob = PositionTracker::new(swap_id, SwapRole::Bob);
+    let bob_wallet = Wallet::new([0x02; 32]);
+    let bob_key = bob_wallet.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+
+    // Bob received another message than the one Alice sent
+    alice.record_sent(&abort("sent"));
+    bob.record_received(&abort("altered"));
+    let checkpoint = bob.checkpoint::<BtcXmr>(&bob_key, &bob_wallet).unwrap();
+    let err = alice
+        .verify(&checkpoint, &bob_key, &bob_wallet)
+        .unwrap_err();
+    assert!(matches!(err, Error::Diverged { .. }));
+    assert!(err.to_string().contains("1 Alice and 0 Bob messages"));
+
+    // Bob claims more messages from Alice than she sent
+    bob.record_received(&abort("unknown"));
+    let checkpoint = bob.checkpoint::<BtcXmr>(&bob_key, &bob_wallet).unwrap();
+    assert!(matches!(
+        alice.verify(&checkpoint, &bob_key, &bob_wallet),
+        Err(Error::Diverged { .. })
+    ));
+
+    // Alice received a message Bob never sent
+    let mut alice = PositionTracker::new(swap_id, SwapRole::Alice);
+    alice.record_received(&abort("forged"));
+    let bob = PositionTracker::new(swap_id, SwapRole::Bob);
+    let checkpoint = bob.checkpoint::<BtcXmr>(&bob_key, &bob_wallet).unwrap();
+    assert!(matches!(
+        alice.verify(&checkpoint, &bob_key, &bob_wallet),
+        Err(Error::Diverged { .. })
+    ));
+}
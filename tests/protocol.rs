@@ -1,16 +1,28 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::bitcoin::descriptor::{
+    self, Descriptor, Error as DescriptorError, SwapDescriptors, SwapScript,
+};
 use farcaster_core::chain::bitcoin::transaction::Funding;
+use farcaster_core::chain::bitcoin::Bitcoin;
 use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+use farcaster_core::vectors;
 
-use farcaster_core::blockchain::{FeePolitic, Network};
-use farcaster_core::consensus::deserialize;
-use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey};
+use farcaster_core::blockchain::{FeePolitic, FeeStrategy, Network, RawTransaction};
+use farcaster_core::bundle::{AuditBundle, PunishDelegation};
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::crypto::{self, ArbitratingKeyId, GenerateKey, Sign, Signatures};
 use farcaster_core::negotiation::PublicOffer;
 use farcaster_core::protocol_message::{
-    CommitAliceParameters, CommitBobParameters, RevealAliceParameters, RevealBobParameters,
+    CommitAliceParameters, CommitBobParameters, CooperativeCloseRequest, CooperativeCloseSignature,
+    ProtocolMessage, RevealAliceParameters, RevealBobParameters,
 };
-use farcaster_core::role::{Alice, Bob};
-use farcaster_core::transaction::Fundable;
+use farcaster_core::role::{Alice, Bob, FundingRecovery};
+use farcaster_core::transaction::{Error as TxError, Fundable, Linkable};
+use farcaster_core::Error;
 
+use bitcoin::hashes::sha256d::Hash as Sha256dHash;
+use bitcoin::hashes::Hash;
 use bitcoin::Address;
 
 use std::str::FromStr;
@@ -21,10 +33,7 @@ fn init() -> (
     PublicOffer<BtcXmr>,
     bitcoin::Transaction,
 ) {
-    let hex = "46435357415001000200000080800000800800a0860100000000000800c80000000000000004000\
-               a00000004000a00000001080014000000000000000203b31a0a70343bb46f3db3768296ac5027f9\
-               873921b37f852860c690063ff9e4c90000000000000000000000000000000000000000000000000\
-               000000000000000000000260700";
+    let hex = vectors::PUBLIC_OFFER;
 
     let funding_tx = "020000000001010000000000000000000000000000000000000000000000000000000000\
                000000ffffffff03510101ffffffff0200f2052a0100000016001490d2e860d4e51f68857d65bfa\
@@ -34,14 +43,12 @@ fn init() -> (
 
     let funding_tx: bitcoin::Transaction =
         bitcoin::consensus::encode::deserialize(&hex::decode(funding_tx).unwrap()).unwrap();
-    let destination_address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk")
-        .expect("Parsable address")
-        .into();
+    let destination_address =
+        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
     let fee_politic = FeePolitic::Aggressive;
     let alice: Alice<BtcXmr> = Alice::new(destination_address, fee_politic);
-    let refund_address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk")
-        .expect("Parsable address")
-        .into();
+    let refund_address =
+        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
     let bob: Bob<BtcXmr> = Bob::new(refund_address, fee_politic);
 
     let pub_offer: PublicOffer<BtcXmr> =
@@ -50,6 +57,38 @@ fn init() -> (
     (alice, bob, pub_offer, funding_tx)
 }
 
+// Funding transaction created by an external wallet with a change output
+fn funding_tx(address: &Address, amount: u64) -> bitcoin::Transaction {
+    bitcoin::Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![bitcoin::TxIn {
+            previous_output: bitcoin::OutPoint::default(),
+            script_sig: bitcoin::Script::default(),
+            sequence: 0xffffffff,
+            witness: vec![],
+        }],
+        output: vec![
+            bitcoin::TxOut {
+                value: 42_000,
+                script_pubkey: Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk")
+                    .unwrap()
+                    .script_pubkey(),
+            },
+            bitcoin::TxOut {
+                value: amount,
+                script_pubkey: address.script_pubkey(),
+            },
+        ],
+    }
+}
+
+// Pay the first output of the transaction to the funding address
+fn pay_to_funding(mut tx: bitcoin::Transaction, funding: &Funding) -> bitcoin::Transaction {
+    tx.output[0].script_pubkey = funding.get_address().unwrap().script_pubkey();
+    tx
+}
+
 #[test]
 fn execute_offline_protocol() {
     let (alice, bob, pub_offer, funding_tx) = init();
@@ -92,7 +131,9 @@ fn execute_offline_protocol() {
     //
     let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
     let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
-    funding.update(funding_tx).unwrap();
+    funding
+        .update(pay_to_funding(funding_tx, &funding))
+        .unwrap();
 
     let core = bob
         .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
@@ -144,3 +185,535 @@ fn execute_offline_protocol() {
         )
         .unwrap();
 }
+
+#[test]
+fn cooperative_close_before_buy() {
+    let (alice, bob, pub_offer, funding_tx) = init();
+
+    let alice_wallet = Wallet::new([0x01; 32]);
+    let bob_wallet = Wallet::new([0x02; 32]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    funding
+        .update(pay_to_funding(funding_tx, &funding))
+        .unwrap();
+    let core = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
+        .unwrap();
+
+    // Bob requests to close the swap before Alice locks
+    let close = bob
+        .sign_cooperative_close(&bob_wallet, &alice_params, &bob_params, &core, &pub_offer)
+        .unwrap();
+    let msg: ProtocolMessage<BtcXmr> =
+        ProtocolMessage::CooperativeCloseRequest(CooperativeCloseRequest::from(close));
+    let close = match deserialize::<ProtocolMessage<BtcXmr>>(&serialize(&msg)).unwrap() {
+        ProtocolMessage::CooperativeCloseRequest(req) => req.into(),
+        _ => panic!("Expected a cooperative close request"),
+    };
+
+    let cosigned = alice
+        .cosign_cooperative_close(
+            &alice_wallet,
+            &alice_params,
+            &bob_params,
+            &core,
+            &pub_offer,
+            &close,
+        )
+        .unwrap();
+    let msg: ProtocolMessage<BtcXmr> =
+        ProtocolMessage::CooperativeCloseSignature(CooperativeCloseSignature::from(cosigned));
+    let cosigned = match deserialize::<ProtocolMessage<BtcXmr>>(&serialize(&msg)).unwrap() {
+        ProtocolMessage::CooperativeCloseSignature(sig) => sig.into(),
+        _ => panic!("Expected a cooperative close signature"),
+    };
+    bob.validate_cooperative_close(&bob_wallet, &alice_params, &close, &cosigned)
+        .unwrap();
+
+    // MUST fail if the signatures are not from the expected buy keys
+    let mut forged = close.clone();
+    forged.close_sig = cosigned.close_sig;
+    assert!(alice
+        .validate_cooperative_close(
+            &alice_wallet,
+            &alice_params,
+            &bob_params,
+            &core,
+            &pub_offer,
+            &forged,
+        )
+        .is_err());
+    let mut forged = cosigned.clone();
+    forged.close_sig = close.close_sig;
+    assert!(bob
+        .validate_cooperative_close(&bob_wallet, &alice_params, &close, &forged)
+        .is_err());
+}
+
+#[test]
+fn export_swap_descriptors() {
+    let (alice, bob, pub_offer, funding_tx) = init();
+
+    let alice_wallet = Wallet::new([0x01; 32]);
+    let bob_wallet = Wallet::new([0x02; 32]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    let funding_address = funding.get_address().unwrap();
+    funding
+        .update(pay_to_funding(funding_tx, &funding))
+        .unwrap();
+    let core = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
+        .unwrap();
+
+    let descriptors =
+        SwapDescriptors::new(funding_key, &alice_params, &bob_params, &pub_offer).unwrap();
+    assert_eq!(descriptors.descriptors().len(), 5);
+
+    // Descriptors are exported with their checksum and parsed back
+    for desc in descriptors.descriptors() {
+        let exported = desc.to_string();
+        assert_eq!(exported.split('#').nth(1).unwrap().len(), 8);
+        assert_eq!(&exported.parse::<Descriptor>().unwrap(), desc);
+        let body = exported.split('#').next().unwrap();
+        assert_eq!(&body.parse::<Descriptor>().unwrap(), desc);
+    }
+
+    // The descriptors match the outputs of the core transactions
+    let lock_witness = core.lock.outputs[0].witness_script.clone().unwrap();
+    let cancel_witness = core.cancel.outputs[0].witness_script.clone().unwrap();
+    assert_eq!(
+        descriptors.funding.script_pubkey().unwrap(),
+        funding_address.script_pubkey()
+    );
+    assert_eq!(
+        descriptors.lock.script_pubkey().unwrap(),
+        core.lock.global.unsigned_tx.output[0].script_pubkey
+    );
+    assert_eq!(
+        descriptors.cancel.script_pubkey().unwrap(),
+        core.cancel.global.unsigned_tx.output[0].script_pubkey
+    );
+
+    // The swap scripts are exported in miniscript and parsed back
+    let exported = descriptors.lock.to_string();
+    assert!(exported.starts_with(&format!(
+        "wsh(or_i(multi(2,{},{}),and_v(v:older({}),multi(2,",
+        alice_params.buy,
+        bob_params.buy,
+        pub_offer.offer.cancel_timelock.as_u32()
+    )));
+    match exported.parse::<Descriptor>().unwrap() {
+        Descriptor::Wsh(SwapScript::Lock(lock)) => {
+            assert_eq!(lock.failure.alice, alice_params.cancel)
+        }
+        _ => panic!("should parse as a lock script"),
+    }
+    assert!(descriptors
+        .cancel
+        .to_string()
+        .contains(&format!("pk({}))", alice_params.punish)));
+
+    // The witness scripts are parsed back into the swap scripts
+    descriptors
+        .lock
+        .verify_witness_script(&lock_witness)
+        .unwrap();
+    descriptors
+        .cancel
+        .verify_witness_script(&cancel_witness)
+        .unwrap();
+    let lock = descriptor::lock_from_script(&lock_witness).unwrap();
+    assert_eq!(lock.timelock, pub_offer.offer.cancel_timelock);
+    assert_eq!(lock.success.alice, alice_params.buy);
+    assert_eq!(lock.failure.bob, bob_params.cancel);
+    let punish_lock = descriptor::punish_lock_from_script(&cancel_witness).unwrap();
+    assert_eq!(punish_lock.timelock, pub_offer.offer.punish_timelock);
+    assert_eq!(punish_lock.failure, alice_params.punish);
+
+    // MUST fail on mismatching scripts and invalid descriptors
+    assert_eq!(
+        descriptors.lock.verify_witness_script(&cancel_witness),
+        Err(DescriptorError::ScriptMismatch)
+    );
+    assert_eq!(
+        descriptor::lock_from_script(&cancel_witness).unwrap_err(),
+        DescriptorError::WrongTemplate
+    );
+    let mut exported = descriptors.refund.to_string();
+    let last = exported.pop().unwrap();
+    exported.push(if last == 'q' { 'p' } else { 'q' });
+    assert_eq!(
+        exported.parse::<Descriptor>(),
+        Err(DescriptorError::InvalidChecksum)
+    );
+    assert_eq!(
+        "pkh(00)".parse::<Descriptor>(),
+        Err(DescriptorError::Malformed)
+    );
+    let lock = descriptors.lock.to_string();
+    let lock = lock.split('#').next().unwrap();
+    assert_eq!(
+        lock.replacen("multi(2,", "multi(1,", 1)
+            .parse::<Descriptor>(),
+        Err(DescriptorError::WrongTemplate)
+    );
+    assert_eq!(
+        lock.replacen(")))", "))", 1).parse::<Descriptor>(),
+        Err(DescriptorError::Malformed)
+    );
+}
+
+#[test]
+fn delegate_punish_to_watchtower() {
+    let (alice, bob, pub_offer, funding_tx) = init();
+
+    let alice_wallet = Wallet::new([0x01; 32]);
+    let bob_wallet = Wallet::new([0x02; 32]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    funding
+        .update(pay_to_funding(funding_tx, &funding))
+        .unwrap();
+    let core = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
+        .unwrap();
+
+    let delegation = alice
+        .delegate_punish(&alice_wallet, &alice_params, &bob_params, &core, &pub_offer)
+        .unwrap();
+    let delegation: PunishDelegation<Bitcoin> = deserialize(&serialize(&delegation)).unwrap();
+
+    // The punish transaction is triggered by the cancel transaction and pays Alice
+    let cancel = &delegation.cancel.global.unsigned_tx;
+    assert_eq!(
+        delegation.punish.input[0].previous_output.txid,
+        cancel.txid()
+    );
+    assert_eq!(
+        delegation.punish.output[0].script_pubkey,
+        alice_params.destination_address.script_pubkey()
+    );
+    assert_eq!(delegation.punish_timelock, pub_offer.offer.punish_timelock);
+    assert_eq!(
+        delegation.punish.input[0].sequence,
+        pub_offer.offer.punish_timelock.as_u32()
+    );
+
+    // The punish transaction is fully signed, the cancel transaction is not
+    assert!(!delegation.punish.input[0].witness.is_empty());
+    assert!(delegation.cancel.inputs[0].partial_sigs.is_empty());
+    assert!(delegation.cancel.inputs[0].final_script_witness.is_none());
+}
+
+#[test]
+fn export_audit_bundle() {
+    let (_, bob, pub_offer, funding_tx) = init();
+
+    let alice_wallet = Wallet::new([
+        32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10,
+        9, 8, 7, 6, 5, 4, 3, 2, 1,
+    ]);
+
+    let bob_wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+
+    let alice: Alice<BtcXmr> = Alice::new(
+        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap(),
+        FeePolitic::Aggressive,
+    );
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    funding
+        .update(pay_to_funding(funding_tx, &funding))
+        .unwrap();
+
+    let core = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
+        .unwrap();
+    let adaptor_buy = bob
+        .sign_adaptor_buy(&bob_wallet, &alice_params, &bob_params, &core, &pub_offer)
+        .unwrap();
+
+    let audit = AuditBundle::new(&alice_params, &bob_params, &core).with_buy(adaptor_buy.buy);
+    assert_eq!(audit.alice_spend, alice_params.spend);
+    assert_eq!(audit.bob_accordant_shared_keys.len(), 1);
+    assert!(audit.punish.is_none());
+
+    let ser = serialize(&audit);
+    let audit: AuditBundle<BtcXmr> = deserialize(&ser[..]).unwrap();
+    assert_eq!(ser, serialize(&audit));
+}
+
+#[test]
+fn verify_funding_amount() {
+    let (alice, bob, pub_offer, _) = init();
+
+    let alice_wallet = Wallet::new([
+        32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10,
+        9, 8, 7, 6, 5, 4, 3, 2, 1,
+    ]);
+
+    let bob_wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding = bob.initialize_funding(&bob_wallet, &pub_offer).unwrap();
+    let address = funding.get_address().unwrap();
+
+    let target_amount = pub_offer.offer.arbitrating_amount.as_sat();
+    let fee_rate = match &pub_offer.offer.fee_strategy {
+        FeeStrategy::Fixed(rate) => rate.as_native_unit().as_sat(),
+        _ => panic!("Fixed fee strategy expected"),
+    };
+
+    // A largely over-funded funding transaction
+    let mut over_funding = funding.clone();
+    over_funding
+        .update(funding_tx(&address, 5_000_000_000))
+        .unwrap();
+    assert_eq!(over_funding.get_amount().unwrap().as_sat(), 5_000_000_000);
+    assert!(matches!(
+        bob.verify_funding(&alice_params, &bob_params, &over_funding, &pub_offer),
+        Err(Error::Transaction(TxError::OverFunded))
+    ));
+
+    // The lock transaction is built on top of the detected funding output
+    let core = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, over_funding, &pub_offer)
+        .unwrap();
+    assert_eq!(
+        core.lock.global.unsigned_tx.input[0].previous_output.vout,
+        1
+    );
+    let fee = core.lock.global.unsigned_tx.get_weight() as u64 * fee_rate;
+
+    let mut under_funding = funding.clone();
+    under_funding
+        .update(funding_tx(&address, target_amount + fee - 1))
+        .unwrap();
+    assert!(matches!(
+        bob.verify_funding(&alice_params, &bob_params, &under_funding, &pub_offer),
+        Err(Error::Transaction(TxError::UnderFunded))
+    ));
+
+    let mut over_funding = funding.clone();
+    over_funding
+        .update(funding_tx(&address, target_amount + fee + 1))
+        .unwrap();
+    assert!(matches!(
+        bob.verify_funding(&alice_params, &bob_params, &over_funding, &pub_offer),
+        Err(Error::Transaction(TxError::OverFunded))
+    ));
+
+    let mut funding = funding;
+    funding
+        .update(funding_tx(&address, target_amount + fee))
+        .unwrap();
+    assert!(bob
+        .verify_funding(&alice_params, &bob_params, &funding, &pub_offer)
+        .is_ok());
+}
+
+#[test]
+fn reject_legacy_funding_output() {
+    let (alice, bob, pub_offer, _) = init();
+
+    let alice_wallet = Wallet::new([0x01; 32]);
+    let bob_wallet = Wallet::new([0x02; 32]);
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    // The only output of the funding transaction pays to a legacy address
+    let funding_key = bob_wallet.get_pubkey(ArbitratingKeyId::Fund).unwrap();
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    let mut tx = funding_tx(&funding.get_address().unwrap(), 5_000_000_000);
+    tx.output.remove(0);
+    tx.output[0].script_pubkey =
+        Address::p2pkh(&funding_key, bitcoin::Network::Bitcoin).script_pubkey();
+    funding.update(tx).unwrap();
+
+    // MUST error, the legacy output is not the funding output and the lock transaction
+    // identifier would be malleable
+    let err = bob
+        .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
+        .unwrap_err();
+    assert!(matches!(err, Error::Transaction(TxError::Other(_))));
+    assert!(err.to_string().contains("funding address"));
+}
+
+#[test]
+fn reject_funding_to_wrong_address() {
+    let funding_key = Wallet::new([0x02; 32])
+        .get_pubkey(ArbitratingKeyId::Fund)
+        .unwrap();
+    let other = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+    let mut tx = funding_tx(&other, 5_000_000_000);
+    tx.output.remove(0);
+
+    // MUST error if the only output does not pay to the known funding address
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    funding.update(tx.clone()).unwrap();
+    assert!(funding.get_amount().is_err());
+    assert!(funding.get_consumable_output().is_err());
+
+    // The first output is used only if the funding address is unknown
+    let raw = Funding::raw(tx.clone()).unwrap();
+    assert_eq!(raw.get_amount().unwrap().as_sat(), 5_000_000_000);
+
+    let mut funding = Funding::initialize(funding_key, Network::Local).unwrap();
+    tx.output[0].script_pubkey = funding.get_address().unwrap().script_pubkey();
+    funding.update(tx).unwrap();
+    assert_eq!(funding.get_amount().unwrap().as_sat(), 5_000_000_000);
+}
+
+#[test]
+fn recover_funding() {
+    let (alice, bob, pub_offer, _) = init();
+
+    let alice_wallet = Wallet::new([
+        32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10,
+        9, 8, 7, 6, 5, 4, 3, 2, 1,
+    ]);
+
+    let bob_wallet = Wallet::new([
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ]);
+
+    let alice_params = alice
+        .generate_parameters(&alice_wallet, &pub_offer)
+        .unwrap();
+    let bob_params = bob.generate_parameters(&bob_wallet, &pub_offer).unwrap();
+
+    let funding = bob.initialize_funding(&bob_wallet, &pub_offer).unwrap();
+    let address = funding.get_address().unwrap();
+    let target_amount = pub_offer.offer.arbitrating_amount.as_sat();
+
+    // Over-funded: refund the excess and re-fund the funding address
+    let mut over_funding = funding.clone();
+    over_funding
+        .update(funding_tx(&address, 5_000_000_000))
+        .unwrap();
+    let sweep = match bob
+        .recover_funding(&alice_params, &bob_params, &over_funding, &pub_offer)
+        .unwrap()
+    {
+        FundingRecovery::RefundExcess(sweep) => sweep,
+        _ => panic!("Refund of the excess expected"),
+    };
+    let sweep_tx = bob
+        .fully_sign_sweep(&bob_wallet, &bob_wallet, sweep)
+        .unwrap();
+    assert_eq!(sweep_tx.output.len(), 2);
+    assert_eq!(
+        sweep_tx.output[0].script_pubkey,
+        bob.refund_address.script_pubkey()
+    );
+    assert_eq!(sweep_tx.output[1].script_pubkey, address.script_pubkey());
+    assert!(sweep_tx.output[1].value > target_amount);
+    assert!(!sweep_tx.input[0].witness.is_empty());
+
+    // The re-funded output matches the offer
+    let mut refunding = funding.clone();
+    refunding.update(sweep_tx).unwrap();
+    assert!(matches!(
+        bob.recover_funding(&alice_params, &bob_params, &refunding, &pub_offer),
+        Ok(FundingRecovery::Proceed)
+    ));
+
+    // Under-funded: sweep back all the funds and abort
+    let mut under_funding = funding;
+    under_funding
+        .update(funding_tx(&address, target_amount))
+        .unwrap();
+    let sweep = match bob
+        .recover_funding(&alice_params, &bob_params, &under_funding, &pub_offer)
+        .unwrap()
+    {
+        FundingRecovery::SweepBack(sweep) => sweep,
+        _ => panic!("Sweep back expected"),
+    };
+    assert_eq!(sweep.global.unsigned_tx.output.len(), 1);
+    assert_eq!(
+        sweep.global.unsigned_tx.output[0].script_pubkey,
+        bob.refund_address.script_pubkey()
+    );
+    assert!(sweep.global.unsigned_tx.output[0].value < target_amount);
+}
+
+#[test]
+fn verify_adaptor_without_secret() {
+    let wallet = Wallet::new([1; 32]);
+    let signing_key = wallet.get_pubkey(ArbitratingKeyId::Refund).unwrap();
+    let adaptor = Wallet::new([2; 32])
+        .get_pubkey(ArbitratingKeyId::Cancel)
+        .unwrap();
+    let msg = Sha256dHash::hash(b"refund");
+
+    let sig = wallet
+        .adaptor_sign_with_key(&signing_key, &adaptor, msg)
+        .unwrap();
+
+    // Only public data is needed to verify the adaptor signature
+    assert!(Bitcoin::verify_adaptor(&sig, msg, &signing_key, &adaptor).is_ok());
+    assert!(
+        Bitcoin::verify_adaptor(&sig, Sha256dHash::hash(b"buy"), &signing_key, &adaptor).is_err()
+    );
+    let other_key = wallet.get_pubkey(ArbitratingKeyId::Buy).unwrap();
+    assert!(Bitcoin::verify_adaptor(&sig, msg, &other_key, &adaptor).is_err());
+    // The binding to the adaptor point is not verified, but the signing key is never an adaptor
+    assert!(matches!(
+        Bitcoin::verify_adaptor(&sig, msg, &signing_key, &signing_key),
+        Err(crypto::Error::InvalidAdaptorSignature)
+    ));
+}
+
+#[test]
+fn extract_raw_transaction() {
+    let address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+    let tx = funding_tx(&address, 100_000);
+
+    let raw = tx.to_raw_bytes();
+    let de: bitcoin::Transaction = bitcoin::consensus::encode::deserialize(&raw).unwrap();
+    assert_eq!(de, tx);
+    assert_eq!(RawTransaction::txid(&tx), tx.txid().into_inner().to_vec());
+    assert_eq!(
+        RawTransaction::txid(&tx),
+        Sha256dHash::hash(&raw).into_inner().to_vec()
+    );
+}
@@ -1,21 +1,41 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
 use bitcoin::blockdata::transaction::Transaction;
 use bitcoin::secp256k1::Signature;
 use bitcoin::util::psbt::PartiallySignedTransaction;
 
-use farcaster_core::protocol_message::{Abort, BuyProcedureSignature};
+use farcaster_core::consensus::{
+    self, deserialize, serialize, serialize_hex, try_decode_any, try_decode_any_str, Decodable,
+    DecodedEntity, Fault,
+};
+use farcaster_core::protocol_message::{
+    Abort, AbortAction, AbortReason, BuyProcedureSignature, ProtocolMessage,
+};
+use farcaster_core::vectors;
+
+use farcaster_core::blockchain::FeeStrategy;
+use farcaster_core::chain::bitcoin::fee::SatPerVByte;
+use farcaster_core::negotiation::PublicOffer;
+
+#[cfg(feature = "lightning")]
+use lightning_encoding::{LightningDecode, LightningEncode};
+
+use std::collections::HashSet;
+use std::io;
 
 use farcaster_core::chain::pairs::btcxmr::BtcXmr;
 
 #[test]
 fn create_abort_message() {
     let _ = Abort {
+        reason: AbortReason::Unspecified,
         error_body: Some(String::from("An error occured ;)")),
     };
 }
 
 #[test]
 fn create_buy_procedure_signature_message() {
-    let ecdsa_sig = "3045022100b75f569de3e57f4f445bcf9e42be9e5b5128f317ab86e451fdfe7be5ffd6a7da0220776b30307b5d761512635dc0394573be7fe17b5300b160340dae370b641bc4ca";
+    let ecdsa_sig = vectors::ECDSA_SIGNATURE;
 
     let tx = Transaction {
         version: 2,
@@ -33,3 +53,338 @@ fn create_buy_procedure_signature_message() {
         buy_adaptor_sig,
     };
 }
+
+#[test]
+fn decode_any_protocol_message() {
+    let msg = ProtocolMessage::<BtcXmr>::Abort(Abort {
+        reason: AbortReason::Unspecified,
+        error_body: Some(String::from("An error occured ;)")),
+    });
+    let bytes = serialize(&msg);
+    assert_eq!(&bytes[..2], &[0x08, 0x00]);
+
+    let hex = serialize_hex(&msg);
+    let decoded = [
+        try_decode_any::<BtcXmr>(&bytes),
+        try_decode_any_str::<BtcXmr>(&format!(" {}\n", hex)),
+    ];
+    for entity in decoded.iter() {
+        match entity {
+            Ok(DecodedEntity::ProtocolMessage(ProtocolMessage::Abort(abort))) => {
+                assert_eq!(abort.error_body, Some(String::from("An error occured ;)")))
+            }
+            _ => panic!("blob should decode as an abort protocol message"),
+        }
+    }
+
+    // Hex is only decoded from strings, and only once
+    assert!(try_decode_any::<BtcXmr>(hex.as_bytes()).is_err());
+    assert!(try_decode_any_str::<BtcXmr>(&hex::encode(&hex)).is_err());
+    assert!(try_decode_any::<BtcXmr>(&[0xff, 0xff]).is_err());
+    assert!(try_decode_any::<BtcXmr>(b"not a farcaster blob").is_err());
+    assert!(try_decode_any_str::<BtcXmr>("not a farcaster blob").is_err());
+}
+
+#[test]
+fn abort_reason_handling() {
+    let msg = ProtocolMessage::<BtcXmr>::Abort(
+        Abort::new(AbortReason::FeeDisagreement).with_body("fee rate too low"),
+    );
+    match try_decode_any::<BtcXmr>(&serialize(&msg)[..]) {
+        Ok(DecodedEntity::ProtocolMessage(ProtocolMessage::Abort(abort))) => {
+            assert_eq!(abort.reason, AbortReason::FeeDisagreement);
+            assert_eq!(abort.error_body, Some(String::from("fee rate too low")));
+            assert_eq!(abort.action(), AbortAction::Retry);
+        }
+        _ => panic!("blob should decode as an abort protocol message"),
+    }
+
+    assert!(!AbortReason::ValidationFailure.is_retryable());
+    assert_eq!(AbortReason::default().action(), AbortAction::Terminate);
+    // Unknown codes are preserved and terminal
+    let unknown = AbortReason::from_u16(0x0142);
+    assert!(matches!(unknown, AbortReason::Other(code) if code.code() == 0x0142));
+    assert_eq!(AbortReason::from_u16(0x02), AbortReason::FeeDisagreement);
+    assert_eq!(unknown.to_u16(), 0x0142);
+    assert_eq!(unknown.action(), AbortAction::Terminate);
+}
+
+#[cfg(feature = "reverse")]
+#[test]
+fn decode_accordant_locked_message() {
+    use farcaster_core::protocol_message::AccordantLocked;
+    use farcaster_core::role::{LockOrder, SwapRole};
+
+    let msg = ProtocolMessage::<BtcXmr>::AccordantLocked(AccordantLocked {
+        transaction_id: vec![0x42; 32],
+        height: 2_400_000,
+    });
+    match try_decode_any::<BtcXmr>(&serialize(&msg)[..]) {
+        Ok(DecodedEntity::ProtocolMessage(ProtocolMessage::AccordantLocked(decoded))) => {
+            assert_eq!(decoded.transaction_id, vec![0x42; 32]);
+            assert_eq!(decoded.height, 2_400_000);
+        }
+        _ => panic!("blob should decode as an accordant locked message"),
+    }
+
+    assert_eq!(LockOrder::default().first_locker(), SwapRole::Bob);
+    assert_eq!(LockOrder::AccordantFirst.first_locker(), SwapRole::Alice);
+    assert_eq!(LockOrder::AccordantFirst.second_locker(), SwapRole::Bob);
+}
+
+// Build a Monero transaction paying `amount` to the address with the transaction key `tx_key`
+fn monero_lock_transaction(
+    address: &monero::Address,
+    tx_key: &monero::PrivateKey,
+    amount: u64,
+) -> monero::Transaction {
+    use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+    use monero::blockdata::transaction::{ExtraField, SubField, TxOutTarget};
+    use monero::cryptonote::hash::Hash;
+    use monero::cryptonote::onetime_key::KeyGenerator;
+    use monero::util::key::H;
+    use monero::util::ringct::{CtKey, EcdhInfo, Key, RctSig, RctSigBase, RctType};
+    use monero::{PublicKey, TransactionPrefix, TxOut, VarInt};
+
+    let generator = KeyGenerator::from_random(address.public_view, address.public_spend, *tx_key);
+    let shared = generator.get_rvn_scalar(0);
+    let blinding = Scalar::from(1234u64);
+    let sec1 = Hash::hash(shared.as_bytes()).to_bytes();
+    let sec2 = Hash::hash(&sec1).to_bytes();
+    let commitment =
+        ED25519_BASEPOINT_POINT * blinding + H.point.decompress().unwrap() * Scalar::from(amount);
+    monero::Transaction {
+        prefix: TransactionPrefix {
+            version: VarInt(2),
+            unlock_time: VarInt(0),
+            inputs: vec![],
+            outputs: vec![TxOut {
+                amount: VarInt(0),
+                target: TxOutTarget::ToKey {
+                    key: generator.one_time_key(0),
+                },
+            }],
+            extra: ExtraField(vec![SubField::TxPublicKey(PublicKey::from_private_key(
+                tx_key,
+            ))]),
+        },
+        signatures: vec![],
+        rct_signatures: RctSig {
+            sig: Some(RctSigBase {
+                rct_type: RctType::Full,
+                txn_fee: VarInt(0),
+                pseudo_outs: vec![],
+                ecdh_info: vec![EcdhInfo::Standard {
+                    mask: Key {
+                        key: (blinding + Scalar::from_bytes_mod_order(sec1)).to_bytes(),
+                    },
+                    amount: Key {
+                        key: (Scalar::from(amount) + Scalar::from_bytes_mod_order(sec2)).to_bytes(),
+                    },
+                }],
+                out_pk: vec![CtKey {
+                    mask: Key {
+                        key: commitment.compress().to_bytes(),
+                    },
+                }],
+            }),
+            p: None,
+        },
+    }
+}
+
+#[test]
+fn verify_accordant_lock_proof() {
+    use curve25519_dalek::scalar::Scalar;
+    use farcaster_core::chain::monero::Monero;
+    use farcaster_core::protocol_message::AccordantLockProof;
+    use monero::{Address, Network, PrivateKey, PublicKey};
+
+    let spend = PrivateKey::from_scalar(Scalar::from(11u64));
+    let view = PrivateKey::from_scalar(Scalar::from(13u64));
+    let address = Address::standard(
+        Network::Stagenet,
+        PublicKey::from_private_key(&spend),
+        PublicKey::from_private_key(&view),
+    );
+    let tx_key = PrivateKey::from_scalar(Scalar::from(17u64));
+    let tx = monero_lock_transaction(&address, &tx_key, 2_500_000_000_000);
+
+    let proof = AccordantLockProof::new::<Monero>(&tx, &address, &tx_key).unwrap();
+    let msg = ProtocolMessage::<BtcXmr>::AccordantLockProof(proof);
+    let proof = match try_decode_any::<BtcXmr>(&serialize(&msg)[..]) {
+        Ok(DecodedEntity::ProtocolMessage(ProtocolMessage::AccordantLockProof(decoded))) => decoded,
+        _ => panic!("blob should decode as an accordant lock proof message"),
+    };
+    assert!(proof
+        .verify::<Monero>(&tx, &address, monero::Amount::from_pico(2_500_000_000_000))
+        .is_ok());
+    // Not enough assets locked
+    assert!(proof
+        .verify::<Monero>(&tx, &address, monero::Amount::from_pico(2_500_000_000_001))
+        .is_err());
+    // Transaction paying another address
+    let other = Address::standard(
+        Network::Stagenet,
+        PublicKey::from_private_key(&view),
+        PublicKey::from_private_key(&spend),
+    );
+    assert!(proof
+        .verify::<Monero>(&tx, &other, monero::Amount::from_pico(1))
+        .is_err());
+    // Wrong transaction key
+    let wrong_key = PrivateKey::from_scalar(Scalar::from(19u64));
+    assert!(AccordantLockProof::new::<Monero>(&tx, &address, &wrong_key).is_err());
+    // Another transaction
+    let other_tx = monero_lock_transaction(&address, &wrong_key, 2_500_000_000_000);
+    assert!(proof
+        .verify::<Monero>(&other_tx, &address, monero::Amount::from_pico(1))
+        .is_err());
+}
+
+#[test]
+fn decode_errors_with_context() {
+    let msg: ProtocolMessage<BtcXmr> =
+        ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified).with_body("peer left"));
+    let bytes = serialize(&msg);
+
+    // A truncated message reports its type and where it stopped
+    let truncated = &bytes[..bytes.len() - 4];
+    match deserialize::<ProtocolMessage<BtcXmr>>(truncated) {
+        Err(err @ consensus::Error::Message { .. }) => {
+            assert_eq!(err.fault(), Fault::Peer);
+            match err {
+                consensus::Error::Message {
+                    msg_type,
+                    offset,
+                    source,
+                } => {
+                    assert_eq!(msg_type, msg.message_type());
+                    assert_eq!(offset, truncated.len() as u64);
+                    assert!(matches!(*source, consensus::Error::Io(_)));
+                }
+                _ => unreachable!(),
+            }
+        }
+        res => panic!("Expected a message error, got {:?}", res),
+    }
+
+    // Unknown types fail right after the type
+    let mut unknown = bytes.clone();
+    unknown[..2].copy_from_slice(&0xfffeu16.to_le_bytes());
+    match deserialize::<ProtocolMessage<BtcXmr>>(&unknown) {
+        Err(consensus::Error::Message {
+            msg_type: 0xfffe,
+            offset: 2,
+            source,
+        }) => assert!(matches!(*source, consensus::Error::UnknownType)),
+        res => panic!("Expected a message error, got {:?}", res),
+    }
+
+    // Failures of the local stream are not blamed on the peer
+    struct Broken;
+    impl io::Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+    let err = ProtocolMessage::<BtcXmr>::consensus_decode(&mut Broken).unwrap_err();
+    assert_eq!(err.fault(), Fault::Local);
+}
+
+#[test]
+#[cfg(feature = "lightning")]
+fn lightning_encoding_carries_context() {
+    let msg: ProtocolMessage<BtcXmr> =
+        ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified).with_body("peer left"));
+    let bytes = serialize(&msg);
+    let mut unknown = bytes.clone();
+    unknown[..2].copy_from_slice(&0xfffeu16.to_le_bytes());
+
+    // Lightning encoding carries the context and maps back into the consensus errors
+    assert_eq!(msg.lightning_serialize(), bytes);
+    let decoded = ProtocolMessage::<BtcXmr>::lightning_deserialize(&bytes).unwrap();
+    assert_eq!(serialize(&decoded), bytes);
+    let err = ProtocolMessage::<BtcXmr>::lightning_deserialize(&unknown).unwrap_err();
+    assert!(err.to_string().contains("0xfffe"));
+    assert_eq!(consensus::Error::from(err).fault(), Fault::Peer);
+    let err = consensus::Error::from(lightning_encoding::Error::DataNotEntirelyConsumed);
+    assert_eq!(err.fault(), Fault::Peer);
+}
+
+#[test]
+fn annotate_protocol_message() {
+    let msg: ProtocolMessage<BtcXmr> =
+        ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified).with_body("peer left"));
+    let bytes = serialize(&msg);
+
+    let dump = consensus::annotate::<ProtocolMessage<BtcXmr>>(&bytes);
+    assert!(dump.is_complete());
+    let fields: Vec<&str> = dump.spans().iter().map(|s| s.field.as_str()).collect();
+    assert_eq!(
+        fields,
+        vec!["message_type", "Abort", "Abort.reason", "Abort.error_body"]
+    );
+    assert_eq!(dump.field_at(0).unwrap().field, "message_type");
+    assert_eq!(
+        dump.field_at(bytes.len() - 1).unwrap().field,
+        "Abort.error_body"
+    );
+    assert_eq!(dump.spans()[1].range, 2..bytes.len());
+    assert!(dump.to_string().contains("Abort.reason"));
+
+    // The failure points to the field being decoded
+    let dump = consensus::annotate::<ProtocolMessage<BtcXmr>>(&bytes[..bytes.len() - 4]);
+    let failure = dump.failure().unwrap();
+    assert_eq!(failure.field, "Abort.error_body");
+    assert_eq!(failure.range, 4..bytes.len() - 4);
+
+    let mut unknown = bytes.clone();
+    unknown[..2].copy_from_slice(&0xfffeu16.to_le_bytes());
+    let dump = consensus::annotate::<ProtocolMessage<BtcXmr>>(&unknown);
+    assert_eq!(dump.failure().unwrap().field, "message_type");
+    assert!(dump.spans().is_empty());
+}
+
+#[test]
+fn compare_and_hash_messages() {
+    let buy = |version| BuyProcedureSignature::<BtcXmr> {
+        buy: PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version,
+            lock_time: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+        })
+        .unwrap(),
+        buy_adaptor_sig: Signature::from_der(&hex::decode(vectors::ECDSA_SIGNATURE).unwrap())
+            .unwrap(),
+    };
+    assert_eq!(buy(2), buy(2));
+    assert_ne!(buy(2), buy(1));
+
+    let messages: HashSet<ProtocolMessage<BtcXmr>> = vec![
+        ProtocolMessage::BuyProcedureSignature(buy(2)),
+        ProtocolMessage::BuyProcedureSignature(buy(2)),
+        ProtocolMessage::BuyProcedureSignature(buy(1)),
+        ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified)),
+        ProtocolMessage::Abort(Abort::new(AbortReason::Unspecified)),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(messages.len(), 3);
+
+    let offer: PublicOffer<BtcXmr> =
+        deserialize(&hex::decode(vectors::PUBLIC_OFFER).unwrap()).unwrap();
+    let offers: HashSet<_> = vec![offer.clone(), offer].into_iter().collect();
+    assert_eq!(offers.len(), 1);
+
+    let strategies: HashSet<_> = vec![
+        FeeStrategy::Fixed(SatPerVByte::from_sat(1)),
+        FeeStrategy::Fixed(SatPerVByte::from_sat(1)),
+        FeeStrategy::Range(SatPerVByte::from_sat(1)..SatPerVByte::from_sat(2)),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(strategies.len(), 2);
+}
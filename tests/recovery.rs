@@ -0,0 +1,100 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::monero::SHARED_VIEW_KEY_ID;
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+
+use farcaster_core::blockchain::Network;
+use farcaster_core::consensus::{self, CanonicalBytes};
+use farcaster_core::crypto::{ArbitratingKeyId, SharedKeyId};
+use farcaster_core::recovery::{RecoveryKit, MAX_RECOVERY_KIT_ITERATIONS, RECOVERY_KIT_VERSION};
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::SwapId;
+
+use bitcoin::Address;
+
+use std::str::FromStr;
+
+#[test]
+fn encrypt_recovery_kit() {
+    let refund =
+        bitcoin::PrivateKey::from_wif("L1HKVVLHXiUhecWnwFYF6L3shkf1E12HUmuZTESvBXUdx3yqVP1D")
+            .unwrap();
+    let spend = monero::PrivateKey::from_str(
+        "77916d0cd56ed1920aef6ca56d8a41bac915b68e4c46a589e0956e27a7b77404",
+    )
+    .unwrap();
+    let view = monero::PrivateKey::from_str(
+        "8163466f1883598e6dd14027b8da727057165da91485834314f5500a65846f09",
+    )
+    .unwrap();
+    let address = Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+
+    let kit: RecoveryKit<BtcXmr> =
+        RecoveryKit::new(SwapId([0x07; 32]), SwapRole::Bob, Network::Testnet)
+            .with_arbitrating_key(ArbitratingKeyId::Refund, refund)
+            .with_accordant_spend(spend)
+            .with_accordant_shared_key(SharedKeyId::new(SHARED_VIEW_KEY_ID), view)
+            .with_arbitrating_address(address.clone())
+            .with_arbitrating_height(2_000_000)
+            .with_accordant_height(1_000_000);
+
+    let encrypted = kit
+        .encrypt(b"correct horse", [0x01; 16], [0x02; 12], 1_000)
+        .unwrap();
+    assert_eq!(&encrypted[..2], &RECOVERY_KIT_VERSION.to_le_bytes());
+
+    let de = RecoveryKit::<BtcXmr>::decrypt(&encrypted, b"correct horse").unwrap();
+    assert_eq!(de.swap_id, kit.swap_id);
+    assert_eq!(de.swap_role, SwapRole::Bob);
+    assert_eq!(de.network, Network::Testnet);
+    assert_eq!(de.arbitrating_keys.len(), 1);
+    assert_eq!(de.arbitrating_keys[0].0, ArbitratingKeyId::Refund);
+    assert_eq!(de.arbitrating_keys[0].1, refund);
+    assert_eq!(
+        de.accordant_spend
+            .as_ref()
+            .map(|key| key.as_canonical_bytes()),
+        Some(spend.as_canonical_bytes())
+    );
+    assert_eq!(de.accordant_shared_keys[0].1, view);
+    assert!(de.recovered_secret.is_none());
+    assert_eq!(de.arbitrating_address, Some(address));
+    assert!(de.accordant_address.is_none());
+    assert_eq!(de.arbitrating_height, Some(2_000_000));
+    assert_eq!(de.accordant_height, Some(1_000_000));
+
+    // Wrong password
+    assert!(matches!(
+        RecoveryKit::<BtcXmr>::decrypt(&encrypted, b"wrong horse"),
+        Err(consensus::Error::InvalidMac)
+    ));
+
+    // The header is authenticated
+    let mut tampered = encrypted.clone();
+    tampered[2] ^= 0x01;
+    assert!(RecoveryKit::<BtcXmr>::decrypt(&tampered, b"correct horse").is_err());
+
+    // MUST reject kits requiring too many iterations
+    let mut costly = encrypted.clone();
+    costly[2..6].copy_from_slice(&(MAX_RECOVERY_KIT_ITERATIONS + 1).to_le_bytes());
+    assert!(matches!(
+        RecoveryKit::<BtcXmr>::decrypt(&costly, b"correct horse"),
+        Err(consensus::Error::ParseFailed(_))
+    ));
+    assert!(kit
+        .encrypt(
+            b"correct horse",
+            [0x01; 16],
+            [0x02; 12],
+            MAX_RECOVERY_KIT_ITERATIONS + 1
+        )
+        .is_err());
+
+    // Unknown version
+    let mut unknown = encrypted;
+    unknown[0] = 0xff;
+    assert!(matches!(
+        RecoveryKit::<BtcXmr>::decrypt(&unknown, b"correct horse"),
+        Err(consensus::Error::UnsupportedVersion)
+    ));
+}
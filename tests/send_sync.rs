@@ -0,0 +1,87 @@
+// Daemons share the swap types across threads, e.g. a session per swap driven by a thread pool.
+// The assertions are checked at compile time: the generic functions fail to compile if a type
+// stops being thread-safe for any swap context.
+
+use farcaster_core::bundle::{AliceParameters, AuditBundle, BobParameters};
+use farcaster_core::checkpoint::Checkpoint;
+use farcaster_core::crypto::{Ecdsa, TrSchnorr};
+use farcaster_core::dispute::{DisputePackage, Transcript};
+use farcaster_core::escrow::{SealedEscrow, SecretEscrow};
+use farcaster_core::instruction::{PendingRequest, RemoteSigner, WatchOnlySigner};
+use farcaster_core::negotiation::book::OfferBook;
+use farcaster_core::negotiation::{Offer, PublicOffer, TakerIntent};
+use farcaster_core::protocol::loopback::Loopback;
+use farcaster_core::protocol::position::PositionTracker;
+use farcaster_core::protocol::queue::OutputQueue;
+use farcaster_core::protocol::replay::Divergence;
+use farcaster_core::protocol::session::{self, Session, SessionManager};
+use farcaster_core::protocol::StateMachine;
+use farcaster_core::protocol_message::ProtocolMessage;
+use farcaster_core::recovery::RecoveryKit;
+use farcaster_core::role::{Alice, Arbitrating, Bob};
+use farcaster_core::settlement::SettlementReport;
+use farcaster_core::swap::Swap;
+use farcaster_core::timeouts::StallDetector;
+use farcaster_core::{consensus, syncer, Error};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[allow(dead_code)]
+fn swap_types<Ctx: Swap>() {
+    assert_send_sync::<Offer<Ctx>>();
+    assert_send_sync::<PublicOffer<Ctx>>();
+    assert_send_sync::<TakerIntent<Ctx>>();
+    assert_send_sync::<OfferBook<Ctx>>();
+    assert_send_sync::<Alice<Ctx>>();
+    assert_send_sync::<Bob<Ctx>>();
+    assert_send_sync::<AliceParameters<Ctx>>();
+    assert_send_sync::<BobParameters<Ctx>>();
+    assert_send_sync::<AuditBundle<Ctx>>();
+    assert_send_sync::<ProtocolMessage<Ctx>>();
+    assert_send_sync::<Checkpoint<Ctx>>();
+    assert_send_sync::<SecretEscrow<Ctx>>();
+    assert_send_sync::<RecoveryKit<Ctx>>();
+    assert_send_sync::<DisputePackage<Ctx>>();
+    assert_send_sync::<SettlementReport<Ctx>>();
+}
+
+#[allow(dead_code)]
+fn machine_types<M: StateMachine, P: Send + Sync>() {
+    assert_send_sync::<Session<M, P>>();
+    assert_send_sync::<SessionManager<M, P>>();
+    assert_send_sync::<Loopback<M, P>>();
+    assert_send_sync::<Divergence<M>>();
+    assert_send_sync::<session::Error<M::Error>>();
+    assert_send_sync::<OutputQueue<M::Output>>();
+}
+
+#[allow(dead_code)]
+fn signer_types<'a, Ar: Arbitrating, C: Sync + 'a, V: Sync + 'a>() {
+    assert_send_sync::<PendingRequest<Ar>>();
+    assert_send_sync::<RemoteSigner<'a, Ar, C, V>>();
+    assert_send_sync::<WatchOnlySigner<'a, Ar, V>>();
+}
+
+#[test]
+#[cfg(all(feature = "bitcoin", feature = "monero"))]
+fn btcxmr_types_are_send_sync() {
+    use farcaster_core::chain::bitcoin::Bitcoin;
+    use farcaster_core::chain::pairs::btcxmr::{BtcXmr, Wallet};
+
+    assert_send_sync::<Wallet>();
+    swap_types::<BtcXmr>();
+    signer_types::<Bitcoin, (), Wallet>();
+}
+
+#[test]
+fn types_are_send_sync() {
+    assert_send_sync::<Error>();
+    assert_send_sync::<consensus::Error>();
+    assert_send_sync::<syncer::Error>();
+    assert_send_sync::<Transcript>();
+    assert_send_sync::<PositionTracker>();
+    assert_send_sync::<SealedEscrow>();
+    assert_send_sync::<StallDetector>();
+    assert_send_sync::<Ecdsa>();
+    assert_send_sync::<TrSchnorr>();
+}
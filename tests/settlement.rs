@@ -0,0 +1,204 @@
+#![cfg(all(feature = "bitcoin", feature = "monero"))]
+
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+
+use farcaster_core::blockchain::{FeePolitic, FeeStrategy};
+use farcaster_core::chain::bitcoin::fee::SatPerVByte;
+use farcaster_core::chain::bitcoin::Bitcoin;
+use farcaster_core::consensus::{self, deserialize, serialize};
+use farcaster_core::ledger::FeeLedger;
+use farcaster_core::role::SwapRole;
+use farcaster_core::settlement::{
+    address_reuse, AddressUsage, SettlementReport, SwapOutcome, SwapPhase,
+};
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::TxLabel;
+
+use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Amount, Script};
+
+use std::str::FromStr;
+use std::time::Duration;
+
+#[test]
+fn serialize_settlement_report() {
+    let outcome = SwapOutcome::from_final_transaction(TxLabel::Buy).unwrap();
+    assert_eq!(outcome, SwapOutcome::Success);
+    assert!(SwapOutcome::from_final_transaction(TxLabel::Lock).is_none());
+
+    let report: SettlementReport<BtcXmr> = SettlementReport::new(
+        SwapId([0x07; 32]),
+        SwapRole::Alice,
+        outcome,
+        bitcoin::Amount::from_sat(100_000),
+        monero::Amount::from_pico(200_000),
+    )
+    .with_arbitrating_transaction(
+        TxLabel::Lock,
+        vec![0x01; 32],
+        Some(bitcoin::Amount::from_sat(225)),
+    )
+    .with_arbitrating_transaction(
+        TxLabel::Buy,
+        vec![0x02; 32],
+        Some(bitcoin::Amount::from_sat(150)),
+    )
+    .with_accordant_transaction(TxLabel::AccordantLock, vec![0x03; 32], None)
+    .with_phase_duration(SwapPhase::Setup, Duration::from_secs(30))
+    .with_phase_duration(SwapPhase::Lock, Duration::from_millis(1_200_500))
+    .with_phase_duration(SwapPhase::Settlement, Duration::from_secs(600));
+
+    assert_eq!(report.txid(TxLabel::Buy), Some(&[0x02; 32][..]));
+    assert_eq!(report.txid(TxLabel::AccordantLock), Some(&[0x03; 32][..]));
+    assert_eq!(report.txid(TxLabel::Refund), None);
+    assert_eq!(report.total_duration(), Duration::from_millis(1_830_500));
+
+    let ser = serialize(&report);
+    let de: SettlementReport<BtcXmr> = deserialize(&ser[..]).unwrap();
+    assert_eq!(ser, serialize(&de));
+    assert_eq!(de.outcome, SwapOutcome::Success);
+    assert_eq!(de.arbitrating_transactions, report.arbitrating_transactions);
+    assert_eq!(de.phases, report.phases);
+
+    // Unknown outcome
+    let mut unknown = ser.clone();
+    unknown[33] = 0x04;
+    assert!(matches!(
+        deserialize::<SettlementReport<BtcXmr>>(&unknown[..]),
+        Err(consensus::Error::UnknownType)
+    ));
+}
+
+#[test]
+fn detect_address_reuse() {
+    let report = |id: u8, role: SwapRole| -> SettlementReport<BtcXmr> {
+        SettlementReport::new(
+            SwapId([id; 32]),
+            role,
+            SwapOutcome::Success,
+            bitcoin::Amount::from_sat(100_000),
+            monero::Amount::from_pico(200_000),
+        )
+    };
+    let reused = bitcoin::Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").unwrap();
+    let fresh = bitcoin::Address::from_str("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").unwrap();
+    let xmr = monero::Address::from_str("4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra").unwrap();
+
+    let reports = vec![
+        report(0x01, SwapRole::Alice)
+            .with_arbitrating_address(SwapRole::Alice, AddressUsage::Destination, reused.clone())
+            .with_arbitrating_address(SwapRole::Bob, AddressUsage::Refund, fresh)
+            .with_accordant_address(SwapRole::Bob, AddressUsage::Destination, xmr),
+        report(0x02, SwapRole::Alice)
+            .with_arbitrating_address(SwapRole::Alice, AddressUsage::Destination, reused.clone())
+            .with_accordant_address(SwapRole::Bob, AddressUsage::Destination, xmr),
+    ];
+    let de: SettlementReport<BtcXmr> = deserialize(&serialize(&reports[0])[..]).unwrap();
+    assert_eq!(de.arbitrating_addresses, reports[0].arbitrating_addresses);
+    assert_eq!(de.accordant_addresses, reports[0].accordant_addresses);
+
+    let reuses = address_reuse(&reports);
+    assert_eq!(reuses.len(), 2);
+    assert!(reuses.iter().all(|r| r.is_across_swaps()));
+    let btc = reuses.iter().find(|r| r.arbitrating).unwrap();
+    assert!(btc.is_reused_by(SwapRole::Alice));
+    assert!(!btc.is_reused_by(SwapRole::Bob));
+    assert_eq!(btc.uses[1].swap_id, SwapId([0x02; 32]));
+    assert_eq!(btc.uses[1].usage, AddressUsage::Destination);
+    assert!(reuses
+        .iter()
+        .find(|r| !r.arbitrating)
+        .unwrap()
+        .is_reused_by(SwapRole::Bob));
+
+    // An address used twice in the same swap is also flagged
+    let single = vec![report(0x03, SwapRole::Bob)
+        .with_arbitrating_address(SwapRole::Bob, AddressUsage::Funding, reused.clone())
+        .with_arbitrating_address(SwapRole::Bob, AddressUsage::Refund, reused)];
+    let reuses = address_reuse(&single);
+    assert_eq!(reuses.len(), 1);
+    assert!(!reuses[0].is_across_swaps());
+    assert!(address_reuse(&reports[..1]).is_empty());
+}
+
+fn spending_psbt(input: u64) -> PartiallySignedTransaction {
+    let tx = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::default(),
+            script_sig: Script::default(),
+            sequence: 0xffffffff,
+            witness: vec![],
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Script::default(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx).unwrap();
+    psbt.inputs[0].witness_utxo = Some(TxOut {
+        value: input,
+        script_pubkey: Script::default(),
+    });
+    psbt
+}
+
+#[test]
+fn account_swap_fees() {
+    let strategy = FeeStrategy::Fixed(SatPerVByte::from_sat(2));
+    let mut ledger = FeeLedger::new();
+
+    let mut lock = spending_psbt(100_000);
+    let applied = ledger
+        .apply::<Bitcoin>(TxLabel::Lock, &mut lock, &strategy, FeePolitic::Aggressive)
+        .unwrap();
+    assert_eq!(ledger.fee(TxLabel::Lock), Some(applied.fee));
+
+    // A transaction built by the counter-party is read back
+    let mut cancel = spending_psbt(90_000);
+    cancel.global.unsigned_tx.output[0].value = 89_800;
+    assert_eq!(
+        ledger
+            .record_transaction::<Bitcoin>(TxLabel::Cancel, &cancel)
+            .unwrap(),
+        Amount::from_sat(200)
+    );
+    assert_eq!(ledger.total(), applied.fee + Amount::from_sat(200));
+
+    // The cost of the swap is known before consenting to sign the refund
+    let refund = Amount::from_sat(300);
+    assert_eq!(
+        ledger.total_with(TxLabel::Refund, refund),
+        ledger.total() + refund
+    );
+    // Recording a transaction again replaces its fee
+    assert_eq!(
+        ledger.total_with(TxLabel::Cancel, refund),
+        applied.fee + refund
+    );
+    assert_eq!(
+        ledger.record(TxLabel::Cancel, refund),
+        Some(Amount::from_sat(200))
+    );
+    assert_eq!(ledger.len(), 2);
+    assert_eq!(ledger.entries()[1].label, TxLabel::Cancel);
+
+    let report: SettlementReport<BtcXmr> = SettlementReport::new(
+        SwapId([0x07; 32]),
+        SwapRole::Bob,
+        SwapOutcome::Refunded,
+        bitcoin::Amount::from_sat(100_000),
+        monero::Amount::from_pico(200_000),
+    )
+    .with_fees(ledger.clone());
+    let de: SettlementReport<BtcXmr> = deserialize(&serialize(&report)[..]).unwrap();
+    assert_eq!(de.fees, ledger);
+
+    // MUST fail if a transaction is accounted twice
+    let mut twice = serialize(&ledger);
+    twice.extend(serialize(&ledger.entries()[0]));
+    twice[0] = 0x03;
+    assert!(deserialize::<FeeLedger<Amount>>(&twice[..]).is_err());
+}
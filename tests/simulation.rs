@@ -0,0 +1,318 @@
+#![cfg(feature = "test-utils")]
+
+use std::io;
+
+use farcaster_core::consensus::{self, Decodable, Encodable};
+use farcaster_core::protocol::loopback::LoopbackMachine;
+use farcaster_core::protocol::session::Error;
+use farcaster_core::protocol::simulation::{ChainEvent, Fault, SimulatedMachine, Simulation};
+use farcaster_core::protocol::StateMachine;
+use farcaster_core::role::SwapRole;
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::TxLabel;
+
+const SIGNATURE: u8 = 0x42;
+const DEADLINE: u64 = 3;
+const LOCK: &[u8] = b"lock";
+
+/// A minimal swap: Bob commits with a signature, Alice acknowledges, Bob broadcasts the lock
+/// transaction. The swap succeeds once the lock has two confirmations and is aborted if it is not
+/// confirmed before the deadline.
+#[derive(Debug, Clone, PartialEq)]
+struct Swap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    AliceStart,
+    AliceAcked,
+    AliceLocked,
+    AliceSwapped,
+    AliceAborted,
+    BobStart,
+    BobCommitted,
+    BobLocking,
+    BobLocked,
+    BobSwapped,
+    BobAborted,
+}
+
+const STATES: [State; 11] = [
+    State::AliceStart,
+    State::AliceAcked,
+    State::AliceLocked,
+    State::AliceSwapped,
+    State::AliceAborted,
+    State::BobStart,
+    State::BobCommitted,
+    State::BobLocking,
+    State::BobLocked,
+    State::BobSwapped,
+    State::BobAborted,
+];
+
+impl Encodable for State {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        (*self as u8).consensus_encode(s)
+    }
+}
+
+impl Decodable for State {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        STATES
+            .get(u8::consensus_decode(d)? as usize)
+            .copied()
+            .ok_or(consensus::Error::UnknownType)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Input {
+    Start,
+    Commit(u8),
+    Ack,
+    Height(u64),
+    Confirmations(i32),
+}
+
+impl Encodable for Input {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            Input::Start => 0u8.consensus_encode(s),
+            Input::Commit(signature) => {
+                Ok(1u8.consensus_encode(s)? + signature.consensus_encode(s)?)
+            }
+            Input::Ack => 2u8.consensus_encode(s),
+            Input::Height(height) => Ok(3u8.consensus_encode(s)? + height.consensus_encode(s)?),
+            Input::Confirmations(confirmations) => {
+                Ok(4u8.consensus_encode(s)? + confirmations.consensus_encode(s)?)
+            }
+        }
+    }
+}
+
+impl Decodable for Input {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match u8::consensus_decode(d)? {
+            0 => Ok(Input::Start),
+            1 => Ok(Input::Commit(Decodable::consensus_decode(d)?)),
+            2 => Ok(Input::Ack),
+            3 => Ok(Input::Height(Decodable::consensus_decode(d)?)),
+            4 => Ok(Input::Confirmations(Decodable::consensus_decode(d)?)),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Output {
+    Send(Input),
+    Broadcast(Vec<u8>),
+}
+
+impl Encodable for Output {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            Output::Send(message) => Ok(0u8.consensus_encode(s)? + message.consensus_encode(s)?),
+            Output::Broadcast(tx) => Ok(1u8.consensus_encode(s)? + tx.consensus_encode(s)?),
+        }
+    }
+}
+
+impl Decodable for Output {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match u8::consensus_decode(d)? {
+            0 => Ok(Output::Send(Decodable::consensus_decode(d)?)),
+            1 => Ok(Output::Broadcast(Decodable::consensus_decode(d)?)),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+impl StateMachine for Swap {
+    type State = State;
+    type Input = Input;
+    type Output = Output;
+    type Error = &'static str;
+
+    fn transition(state: &State, input: &Input) -> Result<(State, Vec<Output>), Self::Error> {
+        use State::*;
+        Ok(match (state, input) {
+            (BobStart, Input::Start) => {
+                (BobCommitted, vec![Output::Send(Input::Commit(SIGNATURE))])
+            }
+            (AliceStart, Input::Commit(SIGNATURE)) => (AliceAcked, vec![Output::Send(Input::Ack)]),
+            (AliceStart, Input::Commit(_)) => return Err("invalid signature"),
+            (BobCommitted, Input::Ack) => (BobLocking, vec![Output::Broadcast(LOCK.to_vec())]),
+            // The lock confirmations, going back on reorgs
+            (AliceAcked, Input::Confirmations(1)) => (AliceLocked, vec![]),
+            (AliceAcked, Input::Confirmations(c)) | (AliceLocked, Input::Confirmations(c))
+                if *c >= 2 =>
+            {
+                (AliceSwapped, vec![])
+            }
+            (AliceLocked, Input::Confirmations(0)) => (AliceAcked, vec![]),
+            (BobLocking, Input::Confirmations(1)) => (BobLocked, vec![]),
+            (BobLocking, Input::Confirmations(c)) | (BobLocked, Input::Confirmations(c))
+                if *c >= 2 =>
+            {
+                (BobSwapped, vec![])
+            }
+            (BobLocked, Input::Confirmations(0)) => (BobLocking, vec![]),
+            // The deadline is reached before the lock is confirmed
+            (AliceStart, Input::Height(h)) | (AliceAcked, Input::Height(h)) if *h >= DEADLINE => {
+                (AliceAborted, vec![])
+            }
+            (BobCommitted, Input::Height(h)) | (BobLocking, Input::Height(h)) if *h >= DEADLINE => {
+                (BobAborted, vec![])
+            }
+            (state, Input::Height(_)) | (state, Input::Confirmations(_)) => (*state, vec![]),
+            _ => return Err("unexpected input"),
+        })
+    }
+
+    fn is_terminal(state: &State) -> bool {
+        matches!(
+            state,
+            State::AliceSwapped | State::AliceAborted | State::BobSwapped | State::BobAborted
+        )
+    }
+}
+
+impl LoopbackMachine for Swap {
+    fn deliver(output: &Output) -> Option<Input> {
+        match output {
+            Output::Send(message) => Some(message.clone()),
+            Output::Broadcast(_) => None,
+        }
+    }
+}
+
+impl SimulatedMachine for Swap {
+    fn broadcast(output: &Output) -> Option<(TxLabel, Vec<u8>)> {
+        match output {
+            // The lock is the only transaction broadcasted
+            Output::Broadcast(tx) => Some((TxLabel::Lock, tx.clone())),
+            Output::Send(_) => None,
+        }
+    }
+
+    fn on_chain(event: &ChainEvent) -> Option<Input> {
+        match event {
+            ChainEvent::Height(height) => Some(Input::Height(*height)),
+            ChainEvent::Confirmations {
+                label: TxLabel::Lock,
+                confirmations,
+                ..
+            } => Some(Input::Confirmations(*confirmations)),
+            ChainEvent::Confirmations { .. } => None,
+        }
+    }
+
+    fn corrupt(message: &Input) -> Option<Input> {
+        match message {
+            Input::Commit(signature) => Some(Input::Commit(!signature)),
+            _ => None,
+        }
+    }
+}
+
+fn simulation() -> Simulation<Swap> {
+    Simulation::new(SwapId([0x01; 32]), State::AliceStart, State::BobStart)
+}
+
+#[test]
+fn simulate_successful_swap() {
+    let mut simulation = simulation();
+    let outputs = simulation.handle(SwapRole::Bob, Input::Start);
+    assert_eq!(
+        outputs,
+        vec![(SwapRole::Bob, Output::Broadcast(LOCK.to_vec()))]
+    );
+    assert!(simulation.syncer().in_mempool(LOCK));
+
+    simulation.mine_blocks(2);
+    simulation.assert_terminal(SwapRole::Alice, &State::AliceSwapped);
+    simulation.assert_terminal(SwapRole::Bob, &State::BobSwapped);
+    assert!(
+        simulation.rejected().is_empty(),
+        "{:?}",
+        simulation.rejected()
+    );
+}
+
+#[test]
+fn simulate_dropped_message() {
+    // The acknowledgment of Alice never reaches Bob
+    let mut simulation = simulation().with_fault(Fault::DropMessage(1));
+    assert!(simulation.handle(SwapRole::Bob, Input::Start).is_empty());
+    assert!(simulation.pending_faults().is_empty());
+    assert_eq!(
+        simulation.session(SwapRole::Bob).state(),
+        &State::BobCommitted
+    );
+
+    simulation.mine_blocks(DEADLINE);
+    simulation.assert_terminal(SwapRole::Alice, &State::AliceAborted);
+    simulation.assert_terminal(SwapRole::Bob, &State::BobAborted);
+    assert_eq!(simulation.syncer().confirmations(LOCK), 0);
+}
+
+#[test]
+fn simulate_corrupted_signature() {
+    let mut simulation = simulation().with_fault(Fault::CorruptSignature(0));
+    simulation.handle(SwapRole::Bob, Input::Start);
+    let rejected = simulation.rejected();
+    assert_eq!(rejected.len(), 1);
+    assert!(matches!(
+        rejected[0],
+        (SwapRole::Alice, Error::Transition("invalid signature"))
+    ));
+
+    simulation.mine_blocks(DEADLINE);
+    simulation.assert_terminal(SwapRole::Alice, &State::AliceAborted);
+    simulation.assert_terminal(SwapRole::Bob, &State::BobAborted);
+}
+
+#[test]
+fn simulate_confirmation_past_deadline() {
+    let mut simulation = simulation().with_fault(Fault::DelayConfirmation {
+        broadcast: 0,
+        blocks: DEADLINE,
+    });
+    simulation.handle(SwapRole::Bob, Input::Start);
+    assert!(!simulation.syncer().in_mempool(LOCK));
+
+    simulation.mine_blocks(DEADLINE);
+    simulation.assert_terminal(SwapRole::Alice, &State::AliceAborted);
+    simulation.assert_terminal(SwapRole::Bob, &State::BobAborted);
+    // The lock is released in the mempool and confirmed after the deadline
+    assert!(simulation.syncer().in_mempool(LOCK));
+    simulation.mine_blocks(1);
+    assert_eq!(simulation.syncer().confirmations(LOCK), 1);
+}
+
+#[test]
+fn simulate_reorg() {
+    let mut simulation = simulation().with_fault(Fault::Reorg {
+        height: 1,
+        depth: 1,
+    });
+    simulation.handle(SwapRole::Bob, Input::Start);
+    simulation.mine_blocks(1);
+    // The lock is confirmed then reorged out on the first block
+    assert_eq!(simulation.syncer().height(), 0);
+    assert!(simulation.syncer().in_mempool(LOCK));
+    assert_eq!(
+        simulation.session(SwapRole::Bob).state(),
+        &State::BobLocking
+    );
+    assert!(simulation
+        .session(SwapRole::Alice)
+        .log()
+        .records()
+        .any(|record| record.from == State::AliceLocked && record.to == State::AliceAcked));
+
+    simulation.mine_blocks(2);
+    simulation.assert_terminal(SwapRole::Alice, &State::AliceSwapped);
+    simulation.assert_terminal(SwapRole::Bob, &State::BobSwapped);
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "test-utils")]
+
+use farcaster_core::blockchain::{ConfirmationTarget, FeeEstimator, FeeStrategy, FeeStrategyError};
+use farcaster_core::chain::bitcoin::fee::SatPerVByte;
+use farcaster_core::chain::pairs::btcxmr::BtcXmr;
+use farcaster_core::checkpoint::Checkpoint;
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::negotiation::{Offer, PublicOffer};
+use farcaster_core::protocol_message::Abort;
+use farcaster_core::strategies;
+use farcaster_core::timeouts::StallTimeouts;
+
+use proptest::prelude::*;
+
+struct Estimator(SatPerVByte);
+
+impl FeeEstimator<SatPerVByte> for Estimator {
+    fn estimate_fee(
+        &self,
+        _target: Option<ConfirmationTarget>,
+    ) -> Result<SatPerVByte, FeeStrategyError> {
+        Ok(self.0.clone())
+    }
+}
+
+proptest! {
+    #[test]
+    fn offer_roundtrip(offer in any::<Offer<BtcXmr>>()) {
+        let mut encoded = vec![];
+        offer.consensus_encode_versioned(offer.min_version(), &mut encoded).unwrap();
+        let decoded =
+            Offer::<BtcXmr>::consensus_decode_versioned(offer.min_version(), &mut &encoded[..])
+                .unwrap();
+        prop_assert_eq!(decoded, offer);
+    }
+
+    #[test]
+    fn public_offer_roundtrip(public_offer in any::<PublicOffer<BtcXmr>>()) {
+        let decoded: PublicOffer<BtcXmr> = deserialize(&serialize(&public_offer)).unwrap();
+        prop_assert_eq!(decoded, public_offer);
+    }
+
+    #[test]
+    fn fee_strategy_roundtrip(strategy in any::<FeeStrategy<SatPerVByte>>()) {
+        let decoded: FeeStrategy<SatPerVByte> = deserialize(&serialize(&strategy)).unwrap();
+        prop_assert_eq!(decoded, strategy);
+    }
+
+    #[test]
+    fn fee_estimate_within_strategy(
+        strategy in any::<FeeStrategy<SatPerVByte>>(),
+        estimation in strategies::sat_per_vbyte(),
+    ) {
+        let fee = strategy.estimate_with(&Estimator(estimation)).unwrap();
+        match strategy.base() {
+            FeeStrategy::Fixed(fixed) => prop_assert_eq!(&fee, fixed),
+            FeeStrategy::Range(range) => prop_assert!(range.start <= fee && fee <= range.end),
+            FeeStrategy::Targeted(..) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn stall_timeouts_are_valid(timeouts in any::<StallTimeouts>()) {
+        prop_assert!(timeouts.validate().is_ok());
+        let decoded: StallTimeouts = deserialize(&serialize(&timeouts)).unwrap();
+        prop_assert_eq!(decoded, timeouts);
+    }
+
+    #[test]
+    fn abort_roundtrip(abort in any::<Abort>()) {
+        let encoded = serialize(&abort);
+        let decoded: Abort = deserialize(&encoded).unwrap();
+        prop_assert_eq!(serialize(&decoded), encoded);
+    }
+
+    #[test]
+    fn checkpoint_roundtrip(checkpoint in any::<Checkpoint<BtcXmr>>()) {
+        let encoded = serialize(&checkpoint);
+        let decoded: Checkpoint<BtcXmr> = deserialize(&encoded).unwrap();
+        prop_assert_eq!(serialize(&decoded), encoded);
+    }
+}
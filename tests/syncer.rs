@@ -0,0 +1,282 @@
+#![cfg(feature = "test-utils")]
+
+use farcaster_core::consensus::{deserialize, serialize};
+use farcaster_core::syncer::mock::{MockSyncer, CONFLICTED};
+use farcaster_core::syncer::{
+    Abort, BroadcastTransaction, Error, Event, Syncer, Task, TaskAcknowledged, TaskId,
+    TaskIdAllocator, TransactionConfirmations, WatchHeight, WatchTransaction,
+};
+use farcaster_core::transaction::TxLabel;
+
+fn confirmations(events: &[Event], id: TaskId) -> Vec<i32> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::TransactionConfirmations(TransactionConfirmations {
+                id: event_id,
+                confirmations,
+                ..
+            }) if *event_id == id => Some(*confirmations),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn reorg_unconfirms_transaction() {
+    let mut syncer = MockSyncer::new(|tx| tx.to_vec());
+    syncer
+        .watch_height(WatchHeight {
+            id: TaskId(1),
+            lifetime: 100,
+            addendum: vec![],
+        })
+        .unwrap();
+    syncer
+        .watch_transaction(WatchTransaction {
+            id: TaskId(2),
+            label: TxLabel::Lock,
+            lifetime: 100,
+            hash: b"lock".to_vec(),
+            confirmation_bound: 3,
+        })
+        .unwrap();
+    syncer
+        .broadcast_transaction(BroadcastTransaction {
+            id: TaskId(3),
+            label: TxLabel::Lock,
+            tx: b"lock".to_vec(),
+        })
+        .unwrap();
+    syncer.mine_blocks(2);
+    assert_eq!(syncer.confirmations(b"lock"), 2);
+    let events = syncer.poll().unwrap();
+    assert_eq!(confirmations(&events, TaskId(2)), vec![1, 2]);
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::TransactionBroadcasted(broadcasted) if broadcasted.success_broadcast == 1
+    )));
+
+    // The two blocks are reorged out, the transaction is back in the mempool
+    syncer.reorg(2);
+    assert_eq!(syncer.height(), 0);
+    assert!(syncer.in_mempool(b"lock"));
+    let events = syncer.poll().unwrap();
+    assert_eq!(confirmations(&events, TaskId(2)), vec![0]);
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, Event::HeightChanged(changed) if changed.height == 0)));
+
+    // Mined again on the new chain
+    syncer.mine_block();
+    assert_eq!(confirmations(&syncer.poll().unwrap(), TaskId(2)), vec![1]);
+}
+
+#[test]
+fn reorg_replaces_with_conflicting_transaction() {
+    let mut syncer = MockSyncer::new(|tx| tx.to_vec());
+    syncer
+        .watch_transaction(WatchTransaction {
+            id: TaskId(1),
+            label: TxLabel::Buy,
+            lifetime: 100,
+            hash: b"buy".to_vec(),
+            confirmation_bound: 6,
+        })
+        .unwrap();
+    syncer.add_to_mempool(b"buy".to_vec());
+    syncer.mine_block();
+    syncer.poll().unwrap();
+
+    // The buy is reorged out and double-spent by a cancel
+    syncer.reorg(1);
+    syncer.replace(b"buy", b"cancel".to_vec());
+    syncer.mine_block();
+    assert_eq!(
+        confirmations(&syncer.poll().unwrap(), TaskId(1)),
+        vec![0, CONFLICTED]
+    );
+    assert_eq!(syncer.confirmations(b"cancel"), 1);
+
+    // Re-broadcasting the double-spent transaction fails
+    syncer
+        .broadcast_transaction(BroadcastTransaction {
+            id: TaskId(2),
+            label: TxLabel::Buy,
+            tx: b"buy".to_vec(),
+        })
+        .unwrap();
+    assert!(syncer.poll().unwrap().iter().any(|e| matches!(
+        e,
+        Event::TransactionBroadcasted(broadcasted) if broadcasted.success_broadcast == 0
+    )));
+    assert!(!syncer.in_mempool(b"buy"));
+}
+
+#[test]
+fn resume_tasks_after_restart() {
+    let mut allocator = TaskIdAllocator::new();
+    assert_eq!(allocator.last(), None);
+    let height_id = allocator.allocate().unwrap();
+    let lock_id = allocator.allocate().unwrap();
+    assert_eq!(allocator.last(), Some(lock_id));
+
+    let watch_lock = WatchTransaction {
+        id: lock_id,
+        label: TxLabel::Lock,
+        lifetime: 2,
+        hash: b"lock".to_vec(),
+        confirmation_bound: 1,
+    };
+    let mut syncer = MockSyncer::new(|tx| tx.to_vec());
+    syncer
+        .watch_height(WatchHeight {
+            id: height_id,
+            lifetime: 100,
+            addendum: vec![],
+        })
+        .unwrap();
+    syncer.watch_transaction(watch_lock.clone()).unwrap();
+    let events = syncer.poll().unwrap();
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::TaskAcknowledged(TaskAcknowledged { id, resumed: false }) if *id == lock_id
+    )));
+
+    // The daemon restarts, the allocator is restored and the tasks re-submitted
+    let mut allocator: TaskIdAllocator = deserialize(&serialize(&allocator)).unwrap();
+    assert_eq!(allocator.allocate().unwrap(), TaskId(2));
+    assert_eq!(
+        TaskIdAllocator::resume(lock_id)
+            .unwrap()
+            .allocate()
+            .unwrap(),
+        TaskId(2)
+    );
+
+    // MUST fail instead of wrapping around once the identifiers are exhausted
+    assert!(TaskIdAllocator::resume(TaskId(i32::MAX)).is_err());
+    let mut exhausted = TaskIdAllocator::resume(TaskId(i32::MAX - 2)).unwrap();
+    assert_eq!(exhausted.allocate().unwrap(), TaskId(i32::MAX - 1));
+    assert!(matches!(exhausted.allocate(), Err(Error::TaskIdExhausted)));
+    syncer.watch_transaction(watch_lock).unwrap();
+    let events = syncer.poll().unwrap();
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::TaskAcknowledged(TaskAcknowledged { id, resumed: true }) if *id == lock_id
+    )));
+    assert_eq!(syncer.active_tasks().unwrap(), vec![height_id, lock_id]);
+
+    // The transaction watch expires
+    syncer.mine_blocks(3);
+    let events = syncer.poll().unwrap();
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, Event::TaskCompleted(completed) if completed.id == lock_id)));
+    assert_eq!(syncer.active_tasks().unwrap(), vec![height_id]);
+
+    // Aborting an unknown task fails
+    syncer.abort(Abort { id: lock_id }).unwrap();
+    assert!(syncer.poll().unwrap().iter().any(|e| matches!(
+        e,
+        Event::TaskAborted(aborted) if aborted.success_abort == 0
+    )));
+}
+
+#[test]
+fn label_transaction_tasks_and_events() {
+    let watch = Task::WatchTransaction(WatchTransaction {
+        id: TaskId(1),
+        label: TxLabel::Cancel,
+        lifetime: 100,
+        hash: b"cancel".to_vec(),
+        confirmation_bound: 2,
+    });
+    match deserialize::<Task>(&serialize(&watch)).unwrap() {
+        Task::WatchTransaction(task) => assert_eq!(task.label, TxLabel::Cancel),
+        _ => panic!("a watch transaction task"),
+    }
+    let broadcast = BroadcastTransaction {
+        id: TaskId(2),
+        label: TxLabel::Cancel,
+        tx: b"cancel".to_vec(),
+    };
+    let decoded: BroadcastTransaction = deserialize(&serialize(&broadcast)).unwrap();
+    assert_eq!(decoded.label, TxLabel::Cancel);
+
+    // The events reporting on a transaction echo the label of the task
+    let mut syncer = MockSyncer::new(|tx| tx.to_vec());
+    if let Task::WatchTransaction(task) = watch {
+        syncer.watch_transaction(task).unwrap();
+    }
+    syncer.broadcast_transaction(broadcast).unwrap();
+    syncer.mine_block();
+    let events = syncer.poll().unwrap();
+    assert!(events.iter().any(|e| matches!(
+        e,
+        Event::TransactionBroadcasted(broadcasted) if broadcasted.label == TxLabel::Cancel
+    )));
+    let labels: Vec<TxLabel> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::TransactionConfirmations(confirmations) => Some(confirmations.label),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(labels, vec![TxLabel::Cancel]);
+    for event in events {
+        let decoded: Event = deserialize(&serialize(&event)).unwrap();
+        assert_eq!(serialize(&decoded), serialize(&event));
+    }
+}
+
+#[test]
+fn preserve_unknown_tasks_and_events() {
+    let task = Task::WatchHeight(WatchHeight {
+        id: TaskId(3),
+        lifetime: 100,
+        addendum: vec![0x01],
+    });
+    let bytes = serialize(&task);
+    assert_eq!(bytes[..2], [0x02, 0x00]);
+    let decoded: Task = deserialize(&bytes).unwrap();
+    assert_eq!(decoded.id(), TaskId(3));
+    assert_eq!(serialize(&decoded), bytes);
+
+    // A task type added by a later version keeps its identifier and content
+    let mut unknown = bytes.clone();
+    unknown[0] = 0x42;
+    match deserialize::<Task>(&unknown).unwrap() {
+        Task::Unknown(task) => {
+            assert_eq!(task.code.code(), 0x42);
+            assert_eq!(task.id, TaskId(3));
+            assert_eq!(serialize(&Task::Unknown(task)), unknown);
+        }
+        _ => panic!("the task type should be unknown"),
+    }
+
+    let event = Event::TaskAcknowledged(TaskAcknowledged {
+        id: TaskId(3),
+        resumed: true,
+    });
+    let bytes = serialize(&event);
+    assert!(matches!(
+        deserialize::<Event>(&bytes).unwrap(),
+        Event::TaskAcknowledged(TaskAcknowledged {
+            id: TaskId(3),
+            resumed: true
+        })
+    ));
+    let mut unknown = bytes.clone();
+    unknown[0] = 0x42;
+    let decoded: Event = deserialize(&unknown).unwrap();
+    assert!(matches!(decoded, Event::Unknown(ref event) if event.id == TaskId(3)));
+    assert_eq!(serialize(&decoded), unknown);
+
+    // MUST fail on truncated or trailing content
+    assert!(deserialize::<Event>(&bytes[..bytes.len() - 1]).is_err());
+    let mut invalid = bytes;
+    invalid[2] += 1;
+    invalid.push(0x00);
+    assert!(deserialize::<Event>(&invalid).is_err());
+}
@@ -0,0 +1,70 @@
+use farcaster_core::consensus::{
+    deserialize, serialize, Encodable, Timestamp, MAX_DURATION, MAX_TIMESTAMP,
+};
+use farcaster_core::timeouts::StallTimeouts;
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+#[test]
+fn encode_durations() {
+    let duration = Duration::from_millis(1_200_500);
+    let bytes = serialize(&duration);
+    assert_eq!(bytes.len(), 12);
+    assert_eq!(deserialize::<Duration>(&bytes).unwrap(), duration);
+    assert_eq!(
+        deserialize::<Duration>(&serialize(&MAX_DURATION)).unwrap(),
+        MAX_DURATION
+    );
+
+    // MUST fail on invalid nanoseconds and absurd durations
+    let mut invalid = serialize(&Duration::from_secs(1));
+    invalid[8..].copy_from_slice(&1_000_000_000u32.to_le_bytes());
+    assert!(deserialize::<Duration>(&invalid).is_err());
+    let mut too_long = serialize(&MAX_DURATION);
+    too_long[7] = 0x01;
+    assert!(deserialize::<Duration>(&too_long).is_err());
+    let mut buffer = vec![];
+    assert!((MAX_DURATION + Duration::from_secs(1))
+        .consensus_encode(&mut buffer)
+        .is_err());
+}
+
+#[test]
+fn encode_timestamps() {
+    let timestamp = Timestamp::try_from(Duration::from_millis(1_600_000_000_900)).unwrap();
+    assert_eq!(timestamp.as_secs(), 1_600_000_000);
+    assert_eq!(
+        Duration::from(timestamp),
+        Duration::from_secs(1_600_000_000)
+    );
+    let bytes = serialize(&timestamp);
+    assert_eq!(bytes, 1_600_000_000u64.to_le_bytes().to_vec());
+    assert_eq!(deserialize::<Timestamp>(&bytes).unwrap(), timestamp);
+
+    // MUST fail on timestamps after the year 9999
+    assert!(Timestamp::from_secs(MAX_TIMESTAMP).is_ok());
+    assert!(Timestamp::from_secs(MAX_TIMESTAMP + 1).is_err());
+    assert!(Timestamp::try_from(Duration::from_secs(u64::MAX)).is_err());
+    assert!(deserialize::<Timestamp>(&u64::MAX.to_le_bytes()).is_err());
+}
+
+#[test]
+fn encode_stall_timeouts_as_durations() {
+    let timeout = Duration::from_secs(30 * 60);
+    let timeouts = StallTimeouts {
+        reveal: Duration::from_secs(5 * 60),
+        ..StallTimeouts::uniform(timeout)
+    };
+    let bytes = serialize(&timeouts);
+    assert_eq!(bytes.len(), 4 * 12);
+    assert_eq!(bytes[..12], serialize(&Duration::from_secs(5 * 60))[..]);
+    assert_eq!(bytes[36..], serialize(&timeout)[..]);
+    assert_eq!(deserialize::<StallTimeouts>(&bytes).unwrap(), timeouts);
+
+    // MUST fail on absurd durations instead of saturating
+    let mut too_long = bytes.clone();
+    too_long[36..].copy_from_slice(&serialize(&MAX_DURATION));
+    too_long[43] = 0x01;
+    assert!(deserialize::<StallTimeouts>(&too_long).is_err());
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "tracing")]
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use farcaster_core::protocol::session::Session;
+use farcaster_core::protocol::StateMachine;
+use farcaster_core::swap::SwapId;
+use farcaster_core::timeouts::StallTimeouts;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Records the spans created with their fields and the messages of the events.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<String>>>,
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push_str(&format!(" {}={:?}", field.name(), value));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields(span.metadata().name().to_string());
+        span.record(&mut fields);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(fields.0);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(event.metadata().level().to_string());
+        event.record(&mut fields);
+        self.events.lock().unwrap().push(fields.0);
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Countdown;
+
+impl StateMachine for Countdown {
+    type State = u8;
+    type Input = u8;
+    type Output = u8;
+    type Error = &'static str;
+
+    fn transition(state: &u8, input: &u8) -> Result<(u8, Vec<u8>), Self::Error> {
+        state
+            .checked_sub(*input)
+            .map(|s| (s, vec![]))
+            .ok_or("overflow")
+    }
+
+    fn is_terminal(state: &u8) -> bool {
+        *state == 0
+    }
+}
+
+#[test]
+fn trace_transitions_and_validations() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut session = Session::<Countdown, ()>::new(SwapId([0xab; 32]), (), 2);
+        session.handle(1).unwrap();
+        assert!(session.handle(2).is_err());
+        session.handle(1).unwrap();
+        assert!(session.handle(1).is_err());
+
+        let timeouts = StallTimeouts::uniform(Duration::from_secs(1));
+        assert!(timeouts.validate().is_err());
+    });
+
+    let swap_id = format!("swap_id={}", SwapId([0xab; 32]));
+    let spans = recorder.spans.lock().unwrap();
+    assert_eq!(spans.len(), 5);
+    assert!(spans[..4]
+        .iter()
+        .all(|span| span.starts_with("handle") && span.contains(&swap_id)));
+    assert_eq!(spans[4], "validate");
+
+    let events = recorder.events.lock().unwrap();
+    assert_eq!(
+        events[..4].to_vec(),
+        vec![
+            "DEBUG message=transition executed outputs=0 terminal=false",
+            "WARN message=transition rejected state=1",
+            "DEBUG message=transition executed outputs=0 terminal=true",
+            "WARN message=input received by a terminated session",
+        ]
+    );
+    // Validation errors are reported
+    assert!(events[4].starts_with("ERROR"));
+}
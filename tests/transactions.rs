@@ -55,7 +55,7 @@ macro_rules! setup_txs {
             Tx::<Cancel>::initialize(&lock, datalock.clone(), datapunishablelock.clone()).unwrap();
 
         // Set the fees according to the given strategy
-        Bitcoin::set_fee(cancel.as_partial_mut(), &fee, politic).unwrap();
+        Bitcoin::set_fee(cancel.as_partial_mut(), TxLabel::Cancel, &fee, politic).unwrap();
 
         //
         // Create refund tx
@@ -66,7 +66,7 @@ macro_rules! setup_txs {
                 .unwrap();
 
         // Set the fees according to the given strategy
-        Bitcoin::set_fee(refund.as_partial_mut(), &fee, politic).unwrap();
+        Bitcoin::set_fee(refund.as_partial_mut(), TxLabel::Refund, &fee, politic).unwrap();
 
         //
         // Co-Sign refund
@@ -113,7 +113,7 @@ macro_rules! setup_txs {
         let mut buy = Tx::<Buy>::initialize(&lock, datalock, new_address.into()).unwrap();
 
         // Set the fees according to the given strategy
-        Bitcoin::set_fee(buy.as_partial_mut(), &fee, politic).unwrap();
+        Bitcoin::set_fee(buy.as_partial_mut(), TxLabel::Buy, &fee, politic).unwrap();
 
         //
         // Co-Sign buy
@@ -146,7 +146,7 @@ macro_rules! setup_txs {
             Tx::<Punish>::initialize(&cancel, datapunishablelock, new_address.into()).unwrap();
 
         // Set the fees according to the given strategy
-        Bitcoin::set_fee(punish.as_partial_mut(), &fee, politic).unwrap();
+        Bitcoin::set_fee(punish.as_partial_mut(), TxLabel::Punish, &fee, politic).unwrap();
 
         //
         // Sign punish